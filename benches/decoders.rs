@@ -0,0 +1,35 @@
+//! Decoder microbenchmarks on a canned syndrome corpus
+//!
+//! `cargo bench --bench decoders`
+//!
+//! A small d=5 corpus is sampled once and then decoded by each decoder, so that only the decoding time is measured. Use
+//! `qecp-cli tool generate_dataset` and `qecp-cli tool bench_decoder` for larger corpora and latency percentiles.
+//!
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use qecp::prelude::*;
+use qecp::syndrome_corpus::*;
+
+fn decoders(c: &mut Criterion) {
+    // the physical error rate is low enough that the exact matcher solves every syndrome without blossom V
+    let corpus = SyndromeCorpus::generate(SyndromeCorpusHeader {
+        code_type: CodeType::RotatedPlanarCode, d: 5, dj: 5, noisy_measurements: 5, p: 0.001, bias_eta: 0.5
+        , noise_model_builder: Some(NoiseModelBuilder::Phenomenological), noise_model_configuration: json!({}), seed: 0, shots: 1000, detectors: 0,
+    }).unwrap();
+    let (simulator, noise_model) = corpus.header.build().unwrap();
+    let sparse_detected_erasures = SparseErasures::new();
+    let mut group = c.benchmark_group("decode_d5_corpus");
+    group.throughput(Throughput::Elements(corpus.syndromes.len() as u64));
+    for (name, decoder) in [("mwpm", BenchmarkDecoder::MWPM), ("union-find", BenchmarkDecoder::UnionFind)] {
+        let mut general_decoder = GeneralDecoder::from_decoder_config(decoder, &simulator, &noise_model, &json!({}), 1).unwrap();
+        group.bench_function(name, |b| b.iter(|| {
+            for sparse_measurement in corpus.syndromes.iter() {
+                general_decoder.decode_with_erasure(sparse_measurement, &sparse_detected_erasures);
+            }
+        }));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decoders);
+criterion_main!(benches);
@@ -1,10 +1,30 @@
 extern crate cc;
 use std::env;
+use std::fs;
 use std::path::Path;
+use std::process::Command;
 
 fn main() {
 
-    if Path::new("backend/blossomV/PerfectMatching.h").exists() {
+    // record the git commit at build time, so that an installed binary reports the commit it was built from wherever it runs
+    if let Ok(output) = Command::new("git").args(["rev-parse", "HEAD"]).output() {
+        if output.status.success() {
+            println!("cargo:rustc-env=QECP_GIT_HASH={}", String::from_utf8_lossy(&output.stdout).trim());
+        }
+    }
+    if let Ok(head) = fs::read_to_string(".git/HEAD") {
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        if let Some(reference) = head.trim().strip_prefix("ref: ") {
+            println!("cargo:rerun-if-changed=.git/{}", reference);
+        }
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=backend/blossomV");
+
+    // the minimal CLI avoids the dynamic link to libstdc++ so that it can be built as a static binary
+    let cli_minimal = env::var("CARGO_FEATURE_CLI_MINIMAL").is_ok();
+
+    if !cli_minimal && Path::new("backend/blossomV/PerfectMatching.h").exists() {
 
         println!("cargo:rustc-cfg=feature=\"blossom_v\"");
 
@@ -0,0 +1,35 @@
+//! Memory experiment using only the library facade `qecp::prelude`
+//!
+//! `cargo run --release --example memory_experiment`
+//!
+//! A d=5 rotated planar code with 5 noisy measurement rounds under circuit-level depolarizing noise is decoded by the union-find decoder.
+//!
+
+use qecp::prelude::*;
+use std::sync::Arc;
+
+fn main() {
+    let (d, noisy_measurements, p, shots) = (5, 5, 0.001, 1000);
+    // build the circuit and the noise model
+    let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(noisy_measurements, d, d));
+    let mut noise_model = NoiseModel::new(&simulator);
+    NoiseModelBuilder::DepolarizingNoise.apply(&mut simulator, &mut noise_model, &json!({}), p, 0.5, 0.);
+    simulator.compress_error_rates(&mut noise_model);
+    let noise_model = Arc::new(noise_model);
+    // build the decoder, which takes some time to precompute the decoding graph
+    let mut decoder = UnionFindDecoder::new(&simulator, noise_model.clone(), &json!({}), 1, false);
+    // run the simulation with a fixed seed to be reproducible
+    simulator.rng = Xoroshiro128StarStar::seed_from_u64(0);
+    let mut qec_failed = 0;
+    for _ in 0..shots {
+        simulator.generate_random_errors(&noise_model);
+        let measurement = simulator.generate_sparse_measurement();
+        let (correction, _runtime_statistics) = decoder.decode(&measurement);
+        let (logical_i, logical_j) = simulator.validate_correction(&correction);
+        if logical_i || logical_j {
+            qec_failed += 1;
+        }
+    }
+    println!("d = {}, noisy_measurements = {}, p = {}", d, noisy_measurements, p);
+    println!("logical error rate: {} / {} = {}", qec_failed, shots, qec_failed as f64 / shots as f64);
+}
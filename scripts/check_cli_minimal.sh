@@ -0,0 +1,48 @@
+#!/bin/bash
+# check that the minimal CLI for cluster deployment builds without the heavy dependencies, and that a small benchmark sweep
+# gives identical results to the full build under a fixed seed; extra arguments are passed to cargo, e.g. `--offline`
+set -e
+cd "$(dirname "$0")/.."
+
+MINIMAL_FEATURES="--no-default-features --features cli_minimal"
+MINIMAL_TARGET_DIR=target/cli_minimal
+
+# the heavy dependencies must not appear in the dependency graph of the minimal build
+DEPENDENCIES=$(cargo tree "$@" $MINIMAL_FEATURES -e normal --prefix none)
+for crate in actix-web actix-cors actix-rt pyo3; do
+    if echo "$DEPENDENCIES" | grep -q "^$crate v"; then
+        echo "[error] the minimal build depends on $crate"
+        exit 1
+    fi
+done
+
+cargo build "$@" --release
+cargo build "$@" --release $MINIMAL_FEATURES --target-dir $MINIMAL_TARGET_DIR
+
+# the minimal binary refuses to start a server instead of silently missing the command
+if $MINIMAL_TARGET_DIR/release/qecp-cli server 2>/dev/null; then
+    echo "[error] the minimal build should not run the server"
+    exit 1
+fi
+
+for decoder in mwpm union-find fusion; do
+    DECODER_CONFIG="{}"
+    if [ "$decoder" == "mwpm" ]; then
+        DECODER_CONFIG='{"emmd":24}'  # the exact matching without blossom V supports at most 24 defects
+    fi
+    BENCHMARK="tool benchmark [3] [3] [0.002,0.005,0.01] -p1 -m2000 --seed 1 --noise-model depolarizing-noise --decoder $decoder"
+    FULL_RESULT=$(target/release/qecp-cli $BENCHMARK --decoder-config "$DECODER_CONFIG" 2>/dev/null)
+    MINIMAL_RESULT=$($MINIMAL_TARGET_DIR/release/qecp-cli $BENCHMARK --decoder-config "$DECODER_CONFIG" 2>/dev/null)
+    if [ -z "$FULL_RESULT" ]; then
+        echo "[error] the benchmark with decoder $decoder failed"
+        exit 1
+    fi
+    echo "$MINIMAL_RESULT"
+    if [ "$FULL_RESULT" != "$MINIMAL_RESULT" ]; then
+        echo "[error] the minimal build gives different results with decoder $decoder:"
+        echo "$FULL_RESULT"
+        exit 1
+    fi
+done
+
+echo "[success] the minimal build is consistent with the full build"
@@ -0,0 +1,448 @@
+//! Artifact
+//!
+//! The JSON files of the kinds in [`ArtifactKind`] start with a top-level `{"artifact": "...", "schema_version": n}` envelope, so that a file
+//! can be identified without knowing how it was generated. The schema version is a major version: it is only bumped when the change breaks
+//! existing loaders, which then reject files of a newer version with a clear message instead of misreading them. Files written before the
+//! envelope was introduced are treated as schema version 1. The failure movies of `--visualize-failures-movie` are stored in the visualizer
+//! file and therefore carry its envelope.
+//!
+//! The other outputs don't have the envelope: the syndrome corpus of `tool generate_dataset` is a binary file identified by its own magic
+//! string and header, the model graph cache of `tool build_graph` is checked against the hash of its configuration, and the `.npy` files of
+//! `tool export_npy` and the check matrix of `tool export_pymatching` keep the formats expected by NumPy and PyMatching.
+//!
+
+use crate::serde_json;
+use crate::serde::{Serialize, Deserialize};
+use crate::serde::de::DeserializeOwned;
+use std::fs;
+use std::path::Path;
+use std::collections::BTreeMap;
+use crate::simulator::*;
+use crate::noise_model::*;
+use crate::code_builder::CodeType;
+use crate::types::*;
+use crate::tool::{SimulationConfigs, SingleSimulationConfig, ConditionedBin};
+use crate::cli::BenchmarkParameters;
+
+
+/// the standardized file names in an experiment directory, see `tool benchmark --experiment-dir`
+pub const EXPERIMENT_MANIFEST: &str = "manifest.json";
+pub const EXPERIMENT_RESULTS: &str = "results.txt";
+pub const EXPERIMENT_RUNTIME_STATISTICS: &str = "runtime_statistics.txt";
+pub const EXPERIMENT_VISUALIZER: &str = "visualizer.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    /// visualizer file, see [`crate::visualize::Visualizer`]
+    Visualizer,
+    /// runtime statistics log written by `tool benchmark --log-runtime-statistics`
+    RuntimeStatistics,
+    /// noise model written by `--debug-print noise-model`, which can be loaded by `--load-noise-model-from-file`
+    NoiseModel,
+    /// manifest of a directory written by `tool benchmark --experiment-dir`, listing the other files of the run
+    ExperimentManifest,
+}
+
+impl ArtifactKind {
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Visualizer => "visualizer",
+            Self::RuntimeStatistics => "runtime_statistics",
+            Self::NoiseModel => "noise_model",
+            Self::ExperimentManifest => "experiment_manifest",
+        }
+    }
+
+    /// the schema version written by this version of the crate, also the newest one its loaders support
+    pub fn schema_version(&self) -> usize {
+        match self {
+            Self::Visualizer => 1,
+            Self::RuntimeStatistics => 1,
+            Self::NoiseModel => 1,
+            Self::ExperimentManifest => 1,
+        }
+    }
+
+    /// insert the envelope into a top-level json object
+    pub fn add_envelope(&self, value: &mut serde_json::Value) {
+        value["artifact"] = json!(self.name());
+        value["schema_version"] = json!(self.schema_version());
+    }
+
+    /// check the envelope of a loaded file and return its schema version
+    pub fn check_envelope(&self, value: &serde_json::Value) -> Result<usize, String> {
+        self.check_envelope_supporting(value, self.schema_version())
+    }
+
+    /// check the envelope given the newest schema version that the loader supports
+    pub fn check_envelope_supporting(&self, value: &serde_json::Value, supported_version: usize) -> Result<usize, String> {
+        if let Some(artifact) = value.get("artifact") {
+            if artifact != &json!(self.name()) {
+                return Err(format!("artifact: expected {} but found {}", self.name(), artifact))
+            }
+        }
+        let schema_version = match value.get("schema_version") {
+            Some(schema_version) => schema_version.as_u64().filter(|version| *version >= 1)
+                .ok_or(format!("schema_version: expected a positive integer but found {}", schema_version))? as usize,
+            None => 1,
+        };
+        if schema_version > supported_version {
+            return Err(format!("schema_version: {} artifact of schema version {} is newer than the supported version {}, please upgrade qecp to load it"
+                , self.name(), schema_version, supported_version))
+        }
+        Ok(schema_version)
+    }
+
+    /// identify the kind of a json artifact, falling back to the layout of files written before the envelope was introduced
+    pub fn identify(value: &serde_json::Value) -> Result<Self, String> {
+        if let Some(artifact) = value.get("artifact") {
+            return serde_json::from_value(artifact.clone()).map_err(|_| format!("artifact: unknown artifact {}", artifact))
+        }
+        if value.get("format") == Some(&json!("qecp")) {
+            return Ok(Self::Visualizer)
+        }
+        if value.get("code_type").is_some() && value.get("nodes").is_some() {
+            return Ok(Self::NoiseModel)
+        }
+        Err("missing field: artifact, cannot identify the file".to_string())
+    }
+
+}
+
+/// the time spent in each stage of a shot, recorded in both the visualizer cases and the runtime statistics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShotElapsed {
+    pub simulate: f64,
+    pub decode: f64,
+    pub validate: f64,
+}
+
+/// deserialize `object[key]` and report the error at `{prefix}{key}`; a missing field is an error only if `required`
+fn check_field<T: DeserializeOwned>(object: &serde_json::Value, key: &str, prefix: &str, required: bool) -> Result<Option<T>, String> {
+    match object.get(key) {
+        Some(value) => serde_json::from_value(value.clone()).map(Some).map_err(|e| format!("{}{}: {}", prefix, key, e)),
+        None if required => Err(format!("{}missing field: {}", prefix, key)),
+        None => Ok(None),
+    }
+}
+
+/// identify the kind of an artifact file and validate it against its schema, returning a brief summary of the content
+pub fn validate_artifact_file(filepath: &str) -> Result<serde_json::Value, String> {
+    let content = fs::read_to_string(filepath).map_err(|e| format!("cannot read {}: {}", filepath, e))?;
+    if content.starts_with("#f ") {
+        return validate_runtime_statistics(&content)
+    }
+    let value: serde_json::Value = serde_json::from_str(&content).map_err(|e| format!("cannot parse json: {}", e))?;
+    match ArtifactKind::identify(&value)? {
+        ArtifactKind::Visualizer => validate_visualizer(&value),
+        ArtifactKind::NoiseModel => validate_noise_model(&value),
+        ArtifactKind::ExperimentManifest => validate_experiment_manifest(&value, Path::new(filepath).parent().unwrap_or(Path::new(""))),
+        ArtifactKind::RuntimeStatistics => Err("line 1: runtime statistics should start with `#f `".to_string()),
+    }
+}
+
+pub fn validate_visualizer(value: &serde_json::Value) -> Result<serde_json::Value, String> {
+    let schema_version = ArtifactKind::Visualizer.check_envelope(value)?;
+    let format: String = check_field(value, "format", "", true)?.unwrap();
+    if format != "qecp" {
+        return Err(format!("format: expected qecp but found {}", format))
+    }
+    let version: String = check_field(value, "version", "", true)?.unwrap();
+    for component in ["simulator", "noise_model", "model_graph", "model_hypergraph"] {
+        if let Some(component_info) = value.get(component) {
+            if !component_info.is_object() {
+                return Err(format!("{}: expected an object", component))
+            }
+        }
+    }
+    let cases = value.get("cases").ok_or("missing field: cases".to_string())?.as_array().ok_or("cases: expected an array".to_string())?;
+    for (index, case) in cases.iter().enumerate() {
+        let prefix = format!("cases[{}].", index);
+        if !case.is_object() {
+            return Err(format!("cases[{}]: expected an object", index))
+        }
+        check_field::<SparseErrorPattern>(case, "error_pattern", &prefix, false)?;
+        check_field::<SparseMeasurement>(case, "measurement", &prefix, false)?;
+        check_field::<SparseErasures>(case, "detected_erasures", &prefix, false)?;
+        check_field::<Option<SparseCorrection>>(case, "correction", &prefix, false)?;
+        check_field::<bool>(case, "qec_failed", &prefix, false)?;
+        check_field::<ShotElapsed>(case, "elapsed", &prefix, false)?;
+    }
+    Ok(json!({
+        "artifact": ArtifactKind::Visualizer,
+        "schema_version": schema_version,
+        "version": version,
+        "cases": cases.len(),
+    }))
+}
+
+pub fn validate_runtime_statistics(content: &str) -> Result<serde_json::Value, String> {
+    let mut lines = content.lines().enumerate();
+    let header = lines.next().and_then(|(_, line)| line.strip_prefix("#f ")).ok_or("line 1: runtime statistics should start with `#f `".to_string())?;
+    let header: serde_json::Value = serde_json::from_str(header).map_err(|e| format!("line 1: cannot parse json: {}", e))?;
+    let schema_version = ArtifactKind::RuntimeStatistics.check_envelope(&header).map_err(|e| format!("line 1: {}", e))?;
+    check_field::<SimulationConfigs>(&header, "configs", "line 1: ", true)?;
+    check_field::<BenchmarkParameters>(&header, "parameters", "line 1: ", true)?;
+    let mut configs = 0;
+    let mut shots = 0;
+    for (index, line) in lines {
+        let prefix = format!("line {}: ", index + 1);
+        if line.is_empty() {
+            continue
+        }
+        if let Some(config) = line.strip_prefix("# ") {
+            serde_json::from_str::<SingleSimulationConfig>(config).map_err(|e| format!("{}config: {}", prefix, e))?;
+            configs += 1;
+            continue
+        }
+        if configs == 0 {
+            return Err(format!("{}shot before the first `# ` configuration line", prefix))
+        }
+        if let Some(buckets) = line.strip_prefix("#e ") {
+            serde_json::from_str::<Vec<ConditionedBin>>(buckets).map_err(|e| format!("{}erasure buckets: {}", prefix, e))?;
+            continue
+        }
+        if let Some(bins) = line.strip_prefix("#w ") {
+            serde_json::from_str::<Vec<ConditionedBin>>(bins).map_err(|e| format!("{}weight bins: {}", prefix, e))?;
+            continue
+        }
+        if let Some(mini_batch) = line.strip_prefix("#b ") {
+            let mini_batch: serde_json::Value = serde_json::from_str(mini_batch).map_err(|e| format!("{}mini-batch: {}", prefix, e))?;
+            check_field::<usize>(&mini_batch, "mini_batch_size", &format!("{}mini-batch: ", prefix), true)?;
+            continue
+        }
+        if let Some(summary) = line.strip_prefix("#s ") {
+            let summary: serde_json::Value = serde_json::from_str(summary).map_err(|e| format!("{}streaming statistics: {}", prefix, e))?;
+            check_field::<u64>(&summary, "shots", &format!("{}streaming statistics: ", prefix), true)?;
+            continue
+        }
+        let shot: serde_json::Value = serde_json::from_str(line).map_err(|e| format!("{}cannot parse json: {}", prefix, e))?;
+        if !shot.is_object() {
+            return Err(format!("{}expected an object", prefix))
+        }
+        check_field::<bool>(&shot, "qec_failed", &prefix, true)?;
+        check_field::<ShotElapsed>(&shot, "elapsed", &prefix, true)?;
+        check_field::<SparseErrorPattern>(&shot, "error_pattern", &prefix, false)?;
+        check_field::<(bool, bool)>(&shot, "true_logical_class", &prefix, false)?;
+        check_field::<(bool, bool)>(&shot, "claimed_logical_class", &prefix, false)?;
+        check_field::<usize>(&shot, "failure_round", &prefix, false)?;
+        check_field::<bool>(&shot, "optimal_qec_failed", &prefix, false)?;
+        check_field::<usize>(&shot, "optimized_correction_operators", &prefix, false)?;
+        check_field::<usize>(&shot, "shot_index", &prefix, false)?;
+        shots += 1;
+    }
+    Ok(json!({
+        "artifact": ArtifactKind::RuntimeStatistics,
+        "schema_version": schema_version,
+        "configs": configs,
+        "shots": shots,
+    }))
+}
+
+pub fn validate_noise_model(value: &serde_json::Value) -> Result<serde_json::Value, String> {
+    let schema_version = ArtifactKind::NoiseModel.check_envelope(value)?;
+    let code_type: CodeType = check_field(value, "code_type", "", true)?.unwrap();
+    let height: usize = check_field(value, "height", "", true)?.unwrap();
+    let vertical: usize = check_field(value, "vertical", "", true)?.unwrap();
+    let horizontal: usize = check_field(value, "horizontal", "", true)?.unwrap();
+    check_field::<Vec<(usize, usize)>>(value, "tls_defects", "", false)?;
+    let nodes = value.get("nodes").ok_or("missing field: nodes".to_string())?.as_array().ok_or("nodes: expected an array".to_string())?;
+    if nodes.len() != height {
+        return Err(format!("nodes: expected {} rows (height) but found {}", height, nodes.len()))
+    }
+    let mut real_nodes = 0;
+    for (t, nodes_row_0) in nodes.iter().enumerate() {
+        let nodes_row_0 = nodes_row_0.as_array().filter(|row| row.len() == vertical)
+            .ok_or(format!("nodes[{}]: expected an array of {} rows (vertical)", t, vertical))?;
+        for (i, nodes_row_1) in nodes_row_0.iter().enumerate() {
+            let nodes_row_1 = nodes_row_1.as_array().filter(|row| row.len() == horizontal)
+                .ok_or(format!("nodes[{}][{}]: expected an array of {} nodes (horizontal)", t, i, horizontal))?;
+            for (j, node) in nodes_row_1.iter().enumerate() {
+                if node.is_null() {
+                    continue
+                }
+                let prefix = format!("nodes[{}][{}][{}].", t, i, j);
+                if !node.is_object() {
+                    return Err(format!("nodes[{}][{}][{}]: expected an object or null", t, i, j))
+                }
+                let position: Position = check_field(node, "position", &prefix, true)?.unwrap();
+                if position != pos!(t, i, j) {
+                    return Err(format!("{}position: expected {} but found {}", prefix, pos!(t, i, j), position))
+                }
+                check_field::<QubitType>(node, "qubit_type", &prefix, true)?;
+                check_field::<GateType>(node, "gate_type", &prefix, true)?;
+                check_field::<Option<Position>>(node, "gate_peer", &prefix, true)?;
+                check_field::<bool>(node, "is_virtual", &prefix, true)?;
+                check_field::<bool>(node, "is_peer_virtual", &prefix, true)?;
+                check_field::<NoiseModelNode>(node, "noise_model", &prefix, true)?;
+                real_nodes += 1;
+            }
+        }
+    }
+    Ok(json!({
+        "artifact": ArtifactKind::NoiseModel,
+        "schema_version": schema_version,
+        "code_type": code_type,
+        "height": height,
+        "vertical": vertical,
+        "horizontal": horizontal,
+        "nodes": real_nodes,
+    }))
+}
+
+/// validate the manifest of an experiment directory, where the listed files are relative to `directory` and must exist
+pub fn validate_experiment_manifest(value: &serde_json::Value, directory: &Path) -> Result<serde_json::Value, String> {
+    let schema_version = ArtifactKind::ExperimentManifest.check_envelope(value)?;
+    check_field::<BenchmarkParameters>(value, "parameters", "", true)?;
+    check_field::<SimulationConfigs>(value, "configs", "", true)?;
+    let configurations: Vec<SingleSimulationConfig> = check_field(value, "configurations", "", true)?.unwrap();
+    let seed: u64 = check_field(value, "seed", "", true)?.unwrap();
+    let environment: serde_json::Value = check_field(value, "environment", "", true)?.unwrap();
+    let version: String = check_field(&environment, "version", "environment.", true)?.unwrap();
+    check_field::<Option<String>>(&environment, "git_hash", "environment.", true)?;
+    check_field::<Vec<String>>(&environment, "arguments", "environment.", true)?;
+    check_field::<serde_json::Value>(&environment, "host", "environment.", true)?;
+    let start_time: String = check_field(value, "start_time", "", true)?.unwrap();
+    let end_time: String = check_field(value, "end_time", "", true)?.unwrap();
+    let elapsed: f64 = check_field(value, "elapsed", "", true)?.unwrap();
+    let files: BTreeMap<String, String> = check_field(value, "files", "", true)?.unwrap();
+    for (name, filename) in files.iter() {
+        if !directory.join(filename).is_file() {
+            return Err(format!("files.{}: {} does not exist", name, directory.join(filename).display()))
+        }
+    }
+    Ok(json!({
+        "artifact": ArtifactKind::ExperimentManifest,
+        "schema_version": schema_version,
+        "version": version,
+        "seed": seed,
+        "configurations": configurations.len(),
+        "start_time": start_time,
+        "end_time": end_time,
+        "elapsed": elapsed,
+        "files": files,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clap::Parser;
+    use crate::cli::*;
+    use crate::visualize::*;
+
+    fn run_benchmark(arguments: &[&str]) -> String {
+        let mut full_arguments = vec!["qecp", "tool", "benchmark"];
+        full_arguments.extend_from_slice(arguments);
+        match Cli::try_parse_from(full_arguments).unwrap().command {
+            Commands::Tool { command } => command.run().unwrap(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// generate every kind of artifact, returning the file paths
+    fn write_artifacts(name: &str) -> (String, String, String) {
+        let visualizer_filepath = std::env::temp_dir().join(format!("qecp_artifact_{}_visualizer.json", name)).to_str().unwrap().to_string();
+        let statistics_filepath = std::env::temp_dir().join(format!("qecp_artifact_{}_statistics.txt", name)).to_str().unwrap().to_string();
+        let noise_model_filepath = std::env::temp_dir().join(format!("qecp_artifact_{}_noise_model.json", name)).to_str().unwrap().to_string();
+        run_benchmark(&["[3]", "[2]", "[0.02]", "-p", "1", "--decoder", "union-find", "--noise-model", "phenomenological", "-m", "20"
+            , "--enable-visualizer", "--visualizer-filename", &visualizer_filepath, "--log-runtime-statistics", &statistics_filepath
+            , "--log-error-pattern-when-logical-error"]);
+        let noise_model = run_benchmark(&["[3]", "[2]", "[0.02]", "--noise-model", "phenomenological", "--debug-print", "noise-model"]);
+        fs::write(&noise_model_filepath, noise_model).unwrap();
+        (visualizer_filepath, statistics_filepath, noise_model_filepath)
+    }
+
+    #[test]
+    fn artifact_validate_written_files() {  // cargo test artifact_validate_written_files -- --nocapture
+        let (visualizer_filepath, statistics_filepath, noise_model_filepath) = write_artifacts("validate");
+        for (filepath, kind) in [(&visualizer_filepath, ArtifactKind::Visualizer), (&statistics_filepath, ArtifactKind::RuntimeStatistics)
+                , (&noise_model_filepath, ArtifactKind::NoiseModel)] {
+            let output = match Cli::try_parse_from(["qecp", "tool", "validate-artifact", filepath.as_str()]).unwrap().command {
+                Commands::Tool { command } => command.run().unwrap(),
+                _ => unreachable!(),
+            };
+            println!("{}", output);
+            let summary: serde_json::Value = serde_json::from_str(&output).unwrap();
+            assert_eq!(summary["artifact"], json!(kind));
+            assert_eq!(summary["schema_version"], json!(kind.schema_version()));
+        }
+        let summary = validate_artifact_file(&visualizer_filepath).unwrap();
+        assert_eq!(summary["cases"], json!(21), "20 shots and the empty placeholder case");
+        let summary = validate_artifact_file(&statistics_filepath).unwrap();
+        assert_eq!(summary["shots"], json!(20));
+    }
+
+    #[test]
+    fn artifact_corrupted_file() {  // cargo test artifact_corrupted_file -- --nocapture
+        let (visualizer_filepath, statistics_filepath, noise_model_filepath) = write_artifacts("corrupted");
+        let corrupted_filepath = std::env::temp_dir().join("qecp_artifact_corrupted.json").to_str().unwrap().to_string();
+        let corrupt_json = |filepath: &String, corrupt: &dyn Fn(&mut serde_json::Value)| -> String {
+            let mut value: serde_json::Value = serde_json::from_str(&fs::read_to_string(filepath).unwrap()).unwrap();
+            corrupt(&mut value);
+            fs::write(&corrupted_filepath, value.to_string()).unwrap();
+            validate_artifact_file(&corrupted_filepath).unwrap_err()
+        };
+        let error = corrupt_json(&visualizer_filepath, &|value| value["cases"][3]["measurement"] = json!(["[0][1]"]));
+        assert!(error.starts_with("cases[3].measurement: "), "{}", error);
+        let error = corrupt_json(&noise_model_filepath, &|value| value["nodes"][1][2][2]["noise_model"]["pp"] = json!("high"));
+        assert!(error.starts_with("nodes[1][2][2].noise_model: "), "{}", error);
+        let error = corrupt_json(&noise_model_filepath, &|value| { value["nodes"][0].as_array_mut().unwrap().pop(); });
+        assert!(error.starts_with("nodes[0]: expected an array of 7 rows"), "{}", error);
+        // the runtime statistics is line based
+        let content = fs::read_to_string(&statistics_filepath).unwrap();
+        let mut lines: Vec<String> = content.lines().map(|line| line.to_string()).collect();
+        lines[4] = lines[4].replace("\"qec_failed\":false", "\"qec_failed\":0").replace("\"qec_failed\":true", "\"qec_failed\":1");
+        fs::write(&corrupted_filepath, lines.join("\n")).unwrap();
+        let error = validate_artifact_file(&corrupted_filepath).unwrap_err();
+        assert!(error.starts_with("line 5: qec_failed: "), "{}", error);
+        lines.truncate(3);
+        let half = lines[2].len() / 2;
+        lines[2].truncate(half);
+        fs::write(&corrupted_filepath, lines.join("\n")).unwrap();
+        let error = validate_artifact_file(&corrupted_filepath).unwrap_err();
+        assert!(error.starts_with("line 3: cannot parse json: "), "{}", error);
+        fs::write(&corrupted_filepath, "{\"artifact\":\"dataset\"}").unwrap();
+        assert_eq!(validate_artifact_file(&corrupted_filepath).unwrap_err(), "artifact: unknown artifact \"dataset\"");
+    }
+
+    #[test]
+    fn artifact_schema_version_compatibility() {  // cargo test artifact_schema_version_compatibility -- --nocapture
+        let (visualizer_filepath, _, noise_model_filepath) = write_artifacts("compatibility");
+        let kind = ArtifactKind::NoiseModel;
+        let current = kind.schema_version();
+        let mut value: serde_json::Value = serde_json::from_str(&fs::read_to_string(&noise_model_filepath).unwrap()).unwrap();
+        assert_eq!(kind.check_envelope(&value), Ok(current));
+        // files without the envelope are accepted as the first version
+        let mut legacy = value.clone();
+        legacy.as_object_mut().unwrap().remove("artifact");
+        legacy.as_object_mut().unwrap().remove("schema_version");
+        assert_eq!(ArtifactKind::identify(&legacy), Ok(kind));
+        assert_eq!(kind.check_envelope(&legacy), Ok(1));
+        assert!(NoiseModel::from_json(&legacy).is_ok());
+        // a file from a future major version is rejected by every loader with a clear message
+        value["schema_version"] = json!(current + 1);
+        let message = format!("schema_version: noise_model artifact of schema version {} is newer than the supported version {}, please upgrade qecp to load it"
+            , current + 1, current);
+        assert_eq!(kind.check_envelope(&value), Err(message.clone()));
+        assert_eq!(NoiseModel::from_json(&value).err(), Some(message.clone()));
+        assert_eq!(validate_noise_model(&value).err(), Some(message.clone()));
+        // after bumping the supported version, both the new and the old files are accepted
+        assert_eq!(kind.check_envelope_supporting(&value, current + 1), Ok(current + 1));
+        assert_eq!(kind.check_envelope_supporting(&legacy, current + 1), Ok(1));
+        // artifacts of a different kind are rejected
+        assert!(ArtifactKind::Visualizer.check_envelope(&value).unwrap_err().starts_with("artifact: expected visualizer"));
+        // the visualizer loader checks the envelope as well
+        let mut visualizer: serde_json::Value = serde_json::from_str(&fs::read_to_string(&visualizer_filepath).unwrap()).unwrap();
+        assert!(Visualizer::load_case(&visualizer_filepath, 1).is_ok());
+        visualizer["schema_version"] = json!(ArtifactKind::Visualizer.schema_version() + 1);
+        let future_filepath = std::env::temp_dir().join("qecp_artifact_future_visualizer.json").to_str().unwrap().to_string();
+        fs::write(&future_filepath, visualizer.to_string()).unwrap();
+        let error = Visualizer::load_case(&future_filepath, 1).unwrap_err();
+        assert!(error.contains("is newer than the supported version"), "{}", error);
+    }
+
+}
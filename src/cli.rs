@@ -33,8 +33,10 @@ pub enum Commands {
         #[clap(subcommand)]
         command: ToolCommands,
     },
-    /// HTTP server for decoding information
+    /// HTTP server for decoding information, only available with the `web` feature
     Server(ServerParameters),
+    /// interactive shell to build a code, inject errors, decode and validate; reads a script from the standard input if it's not a terminal
+    Repl,
 }
 
 #[derive(Subcommand, Clone)]
@@ -53,6 +55,57 @@ pub enum TestCommands {
 pub enum ToolCommands {
     /// built-in tests
     Benchmark(BenchmarkParameters),
+    /// compare two noise model json files (e.g. generated by `--debug-print noise-model`) and print the positions that differ
+    #[clap(alias = "diff_noise_models")]
+    DiffNoiseModels(DiffNoiseModelsParameters),
+    /// print a TikZ figure of the code layout, i.e. the data qubits, the ancilla qubits and the stabilizer plaquettes
+    #[clap(alias = "export_tikz")]
+    ExportTikz(ExportTikzParameters),
+    /// print the total binary entropy of the model graph edge probabilities, a summary of how informative the noise model is to the decoder
+    #[clap(alias = "graph_entropy")]
+    GraphEntropy(GraphEntropyParameters),
+    /// compare the logical error rates of a decoder quantized with different `max_half_weight`, decoding the same sampled syndromes at every level
+    #[clap(alias = "weight_sensitivity")]
+    WeightSensitivity(WeightSensitivityParameters),
+    /// estimate the derivative of the logical error rate with respect to the error rate of each class of locations (e.g. data idle, each two-qubit gate
+    /// step and measurement), by perturbing the error rates of one class at a time with the same random numbers in every shot
+    #[clap(alias = "sensitivity_analysis")]
+    SensitivityAnalysis(SensitivityAnalysisParameters),
+    /// check over random shots that the real and virtual defect measurements together account for all the stabilizer flips, and that the fast
+    /// measurement given few errors agrees with the full propagation
+    #[clap(alias = "check_virtual_consistency")]
+    CheckVirtualConsistency(CheckVirtualConsistencyParameters),
+    /// decode the sampled syndromes of each configuration in a single thread and report the mean and maximum decoding time,
+    /// together with the error pattern of the slowest shot; takes the same arguments as `benchmark` but only uses those defining the configurations and the decoder
+    #[clap(alias = "worst_case_latency")]
+    WorstCaseLatency(BenchmarkParameters),
+    /// identify an artifact file (visualizer file, runtime statistics log, noise model or experiment manifest) and validate it against its schema
+    #[clap(alias = "validate_artifact")]
+    ValidateArtifact(ValidateArtifactParameters),
+    /// validate the manifest of a directory written by `benchmark --experiment-dir` and print a summary of the run followed by its results
+    #[clap(alias = "show_experiment")]
+    ShowExperiment(ShowExperimentParameters),
+    /// sample shots and write the dense syndrome tensors and the logical labels to NumPy `.npy` files, e.g. to train machine-learning decoders
+    #[clap(alias = "export_npy")]
+    ExportNpy(ExportNpyParameters),
+    /// sample syndromes at a fixed configuration once and write them to a binary corpus file for `bench_decoder`
+    #[clap(alias = "generate_dataset")]
+    GenerateDataset(GenerateDatasetParameters),
+    /// decode the syndromes of a corpus with each decoder and print a table of the pure decoding throughput and latency percentiles,
+    /// without any simulation cost
+    #[clap(alias = "bench_decoder")]
+    BenchDecoder(BenchDecoderParameters),
+    /// build the model graph of a single configuration once and save it to disk, to be loaded by `benchmark --load_graph`; takes the same arguments
+    /// as `benchmark` which define the configuration, the noise model and the decoder
+    #[clap(alias = "build_graph")]
+    BuildGraph(BuildGraphParameters),
+    /// build the model graph of a single configuration and write it as a check matrix with edge weights, error probabilities and the logical
+    /// faults matrix in the CSC format of PyMatching's `Matching.from_check_matrix`; takes the same arguments as `benchmark`
+    #[clap(alias = "export_pymatching")]
+    ExportPymatching(ExportPymatchingParameters),
+    /// print each configuration that `benchmark` would run with its shot and time budgets, followed by the total compute, without running anything;
+    /// takes the same arguments as `benchmark`, e.g. `--config-file` to plan a recorded run
+    Plan(BenchmarkParameters),
 }
 
 #[derive(Clone)]
@@ -127,16 +180,16 @@ impl TypedValueParser for SerdeJsonParser {
 #[derive(Parser, Clone, Serialize, Deserialize)]
 pub struct BenchmarkParameters {
     /// [di1,di2,di3,...,din] code distance of vertical axis
-    #[clap(value_parser = ValueParser::new(VecUsizeParser))]
+    #[clap(required_unless_present = "config_file", default_value = "[]", hide_default_value = true, value_parser = ValueParser::new(VecUsizeParser))]
     pub dis: std::vec::Vec<usize>,
     /// [dj1,dj2,dj3,...,djn] code distance of horizontal axis, will use `dis` if not provided, otherwise must have exactly the same length as `dis`
     #[clap(long, value_parser = ValueParser::new(VecUsizeParser))]
     pub djs: Option<std::vec::Vec<usize>>,
     /// [nm1,nm2,nm3,...,nmn] number of noisy measurement rounds, must have exactly the same length as `dis`; note that a perfect measurement is always capped at the end, so to simulate a single round of perfect measurement you should set this to 0
-    #[clap(value_parser = ValueParser::new(VecUsizeParser))]
+    #[clap(required_unless_present = "config_file", default_value = "[]", hide_default_value = true, value_parser = ValueParser::new(VecUsizeParser))]
     pub nms: std::vec::Vec<usize>,
     /// [p1,p2,p3,...,pm] p = px + py + pz unless noise model has special interpretation of this value
-    #[clap(value_parser = ValueParser::new(VecF64Parser))]
+    #[clap(required_unless_present = "config_file", default_value = "[]", hide_default_value = true, value_parser = ValueParser::new(VecF64Parser))]
     pub ps: std::vec::Vec<f64>,
     /// [p1,p2,p3,...,pm] defaults to ps, used to build the decoding graph
     #[clap(long, value_parser = ValueParser::new(VecF64Parser))]
@@ -147,6 +200,10 @@ pub struct BenchmarkParameters {
     /// [pe1,pe2,pe3,...,pem] defaults to pes, used to build the decoding graph
     #[clap(long, value_parser = ValueParser::new(VecF64Parser))]
     pub pes_graph: Option<std::vec::Vec<f64>>,
+    /// simulate every combination of `ps` and `pes` instead of pairing them, e.g. for a threshold map over p and pe; `ps_graph` is still paired with
+    /// `ps` and `pes_graph` with `pes`, and the results are printed with p as the outer loop
+    #[clap(long, alias = "grid_sweep", action, requires = "pes")]
+    pub grid_sweep: bool,
     /// bias_eta = pz / (px + py) and px = py, px + py + pz = p. default to 1/2, which means px = pz = py
     #[clap(long, default_value_t = 0.5)]
     pub bias_eta: f64,
@@ -162,6 +219,10 @@ pub struct BenchmarkParameters {
     /// how many parallel threads to use when initializing decoders, default to be the same with `parallel`
     #[clap(long)]
     pub parallel_init: Option<usize>,
+    /// how many threads to use when building the decoding graphs, e.g. the all-pairs Dijkstra of the complete model graph
+    /// when `precompute_complete_model_graph` is set; 0 means using number of CPUs - 1, overrides `parallel_init`
+    #[clap(long, alias = "graph_build_threads")]
+    pub graph_build_threads: Option<usize>,
     /// code type, see code_builder.rs for more information
     #[clap(short = 'c', long, value_enum, default_value_t = code_builder::CodeType::StandardPlanarCode)]
     pub code_type: code_builder::CodeType,
@@ -171,33 +232,197 @@ pub struct BenchmarkParameters {
     /// decoder configuration json, panic if any field is not recognized
     #[clap(long, default_value_t = json!({}), value_parser = ValueParser::new(SerdeJsonParser))]
     pub decoder_config: serde_json::Value,
+    /// the number of shots decoded by every candidate of `--decoder auto` to pick the decoder of each configuration
+    #[clap(long, alias = "auto_calibration_shots", default_value_t = 1000)]
+    pub auto_calibration_shots: usize,
+    /// `--decoder auto` picks the cheapest candidate (union-find before MWPM) among those whose calibrated logical error rate
+    /// is at most this factor times the best one; the calibrated decoding time is only reported
+    #[clap(long, alias = "auto_decoder_factor", default_value_t = 1.1)]
+    pub auto_decoder_factor: f64,
     /// ignore the logical error of i axis, e.g. logical Z error in standard CSS surface code
     #[clap(long, action)]
     pub ignore_logical_i: bool,
     /// ignore the logical error of j axis, e.g. logical X error in standard CSS surface code
     #[clap(long, action)]
     pub ignore_logical_j: bool,
+    /// also decode every shot with the exact minimum-weight decoder and append `<optimal_failed> <optimal_pL>` to the output: such a shot fails when the
+    /// minimum-weight error consistent with the syndrome differs from the actual error by a logical operator, which no minimum-weight decoder can correct,
+    /// so the gap to `<pL>` is the headroom of the decoder. The weights come from the decoding graph, which decodes X and Z errors separately,
+    /// so the minimum weight is exact only without correlated (e.g. Y) errors; syndromes with more than 24 defects fall back to blossom V,
+    /// so this is intended for small code distances
+    #[clap(long, action)]
+    pub optimal_failure_rate: bool,
+    /// [e1,e2,...,en] bucket the shots by their number of detected erasures, where each value is the lower boundary of a bucket starting from 0,
+    /// e.g. `[0,2,4]` gives the buckets [0, 2), [2, 4) and [4, inf); the conditional logical error rate P(logical | erasures) and the fraction of shots
+    /// of every bucket are written to the statistics log file as a `#e ` line after the shots of each configuration
+    #[clap(long, alias = "erasure_buckets", requires = "log_runtime_statistics", value_parser = ValueParser::new(VecUsizeParser))]
+    pub erasure_buckets: Option<std::vec::Vec<usize>>,
+    /// bin the shots by their error weight, i.e. the number of qubits with a Pauli error (`SparseErrorPattern::len()`), for a curve of the logical
+    /// error rate vs the physical error weight; the conditional logical error rate P(logical | weight) and the fraction of shots of every weight
+    /// are written to the statistics log file as a `#w ` line after the shots of each configuration
+    #[clap(long, alias = "weight_conditioned", requires = "log_runtime_statistics", action)]
+    pub weight_conditioned: bool,
+    /// keep bounded-memory summaries of the shots instead of logging every one of them, for runs with billions of shots: only a uniform random
+    /// sample of this many shots is written to the statistics log file, followed by a `#s ` line with the number of shots, the percentiles of the
+    /// decoding latency and the histogram of the number of defects of each configuration; see the `streaming_statistics` module
+    #[clap(long, alias = "statistics_reservoir", requires = "log_runtime_statistics")]
+    pub statistics_reservoir: Option<usize>,
+    /// re-validate a random fraction of the shots through an independent path (the stabilizers of the final round and the anticommutation with the
+    /// logical operators, see [`code_builder::code_builder_cross_check_correction`]) and abort with diagnostics if the outcomes disagree,
+    /// guarding against a broken validation that reports optimistic results; the overhead is proportional to the fraction
+    #[clap(long, alias = "cross_check_fraction")]
+    pub cross_check_fraction: Option<f64>,
+    /// corrupt the regular validation so that every shot is reported as successful, only to test `cross_check_fraction`
+    #[cfg(test)]
+    #[clap(skip)]
+    #[serde(skip)]
+    pub inject_validation_error: bool,
     /// only print requested information without running the benchmark
     #[clap(long)]
     pub debug_print: Option<tool::BenchmarkDebugPrint>,
     /// for each configuration, give a maximum time to run (in second)
     #[clap(long)]
     pub time_budget: Option<f64>,
-    /// log the runtime statistical information, given the path of the statistics log file
+    /// instead of running the benchmark, simulate this many shots (default 1000) of each configuration in a single thread and print the estimated
+    /// number of shots and running time to reach `min_failed_cases` or `max_repeats`, extrapolated from the measured logical error rate and throughput
+    /// with `parallel` threads; a configuration without any failure in the calibration only gives a lower bound, marked with `>=`
+    #[clap(long, alias = "dry_run", num_args = 0..=1, default_missing_value = "1000")]
+    pub dry_run: Option<usize>,
+    /// instead of direct Monte Carlo, estimate the logical error rate of each configuration by sampling errors conditioned on the number of faults
+    /// (weight shells) from a single fault on, and reweighting the failure rate of each shell by its exact probability; the shells are added until
+    /// the probability of the remaining ones is negligible compared to the estimated logical error rate; `<shots>` and `<failed>`
+    /// sum over the shells and `<pL_dev>` is derived from the variance of the estimate; runs in a single thread and only supports Pauli errors,
+    /// see the `importance_sampling` module
+    #[clap(long, alias = "importance_sampling", value_enum, conflicts_with_all = ["dry_run", "experiment_dir"])]
+    pub importance_sampling: Option<tool::ImportanceSampling>,
+    /// the number of shots of each weight shell of `--importance-sampling`
+    #[clap(long, alias = "shell_shots", default_value_t = 1000, requires = "importance_sampling")]
+    pub shell_shots: usize,
+    /// for each configuration, periodically print the partial results to stderr every this many seconds, useful for monitoring long simulations
+    #[clap(long)]
+    pub report_interval: Option<f64>,
+    /// push the result of each completed configuration as a JSON line `{"config": ..., "result": ...}` to the Unix domain socket at this path,
+    /// e.g. for a live dashboard; the benchmark never waits for the listener, messages are dropped if it's too slow to receive them
+    #[clap(long, alias = "stream_socket")]
+    pub stream_socket: Option<String>,
+    /// wall-clock budget of decoding each shot in microseconds; currently only supported by the union-find decoder, which stops growing clusters
+    /// and returns the correction based on the current clusters when the budget elapses; the shot is marked as timed-out and the timeout rate is reported
+    #[clap(long)]
+    pub decode_timeout_us: Option<u64>,
+    /// latency budget of decoding each shot in microseconds: the MWPM and union-find decoders check the deadline cooperatively and abort,
+    /// while other decoders are checked after decoding; timed-out shots are counted separately and the timeout rate is reported;
+    /// shots without any defect never time out. The abort is best-effort: blossom V is not interrupted once started, so an MWPM shot may
+    /// run past the deadline, although it's still counted as timed out
+    #[clap(long)]
+    pub shot_time_limit_us: Option<u64>,
+    /// count the shots exceeding `shot_time_limit_us` as logical errors, so that the reported logical error rate is the effective one
+    /// under the latency budget; otherwise they are counted as shots without logical error
+    #[clap(long, action)]
+    pub shot_time_limit_as_failure: bool,
+    /// each detector in the noisy measurement rounds loses its measurement record with this probability, e.g. because of FIFO overflow in the hardware;
+    /// the lost detectors are excluded from the syndrome and their time-like edges are merged, supported by the MWPM and union-find decoders
+    #[clap(long)]
+    pub detector_dropout_rate: Option<f64>,
+    /// prior probability that logical_i is flipped before decoding, e.g. soft information of the logical observable from a previous stage;
+    /// the MWPM decoder then picks the logical class with the better posterior instead of the minimum-weight matching; only syndromes solved by
+    /// the exact matcher use the prior, see `exact_matching_max_defects` in the decoder configuration
+    #[clap(long, alias = "logical_prior")]
+    pub logical_prior: Option<f64>,
+    /// scale the weight of all boundary edges in the decoding graph of the MWPM decoder, to study over- or under-matching to the boundaries;
+    /// a value larger than 1 discourages matching defects to the boundary, see `boundary_weight_multiplier` in the decoder configuration
+    #[clap(long, alias = "boundary_weight_multiplier")]
+    pub boundary_weight_multiplier: Option<f64>,
+    /// load the model graph from a file saved by `tool build_graph` instead of building it, supported by the MWPM and union-find decoders;
+    /// the file is rejected if its configuration hash doesn't match the one of the simulated configuration
+    #[clap(long, alias = "load_graph")]
+    pub load_graph: Option<String>,
+    /// decode only one type of errors of a CSS code on its own sub-graph, e.g. `z-only` decodes the Z errors from the X stabilizers and only counts
+    /// the logical Z error (of i axis), which is about twice as fast when the two types are independent; supported by the MWPM and union-find decoders
+    #[clap(long, alias = "decoding_basis", value_enum, default_value_t = tool::DecodingBasis::Both)]
+    pub decoding_basis: tool::DecodingBasis,
+    /// load an error pattern from a json file, e.g. `{"[0][1][1]": "X"}` as printed by `--debug-print failed-error-pattern`, and apply it in every shot
+    /// instead of sampling random errors, so that the decoder can be profiled deterministically; not supported by the compact simulator
+    #[clap(long, alias = "fixed_error_file", conflicts_with = "use_compact_simulator")]
+    pub fixed_error_file: Option<String>,
+    /// decode only the defects inside a region of the decoding graph, given as inclusive ranges of each axis, e.g. `{"t": [6, 18], "i": [0, 6]}`
+    /// where an absent axis is unbounded; edges crossing the border become boundary edges with their original weights; together with
+    /// `--fixed-error-file` it replays a failed shot to find which part of the volume is responsible; supported by the MWPM and union-find decoders
+    #[clap(long, alias = "decode_region", value_parser = ValueParser::new(SerdeJsonParser))]
+    pub decode_region: Option<serde_json::Value>,
+    /// log the runtime statistical information, given the path of the statistics log file; the first line records the parameters (including the seed),
+    /// the crate version and the command line arguments, so that the benchmark can be reproduced by `--config-file`; after the shots of each
+    /// configuration, a `#b ` line records the final mini-batch size adapted to the decoding latency, see `--mini-sync-time`
     #[clap(long)]
     pub log_runtime_statistics: Option<String>,
+    /// write all the files of this run to a new directory with standardized names, i.e. `results.txt`, `runtime_statistics.txt` and `visualizer.json`
+    /// (replacing the paths given by `--log-runtime-statistics` and `--visualizer-filename`), together with a `manifest.json` recording the resolved
+    /// configuration, the seed, the version and the host; refuses to write into a non-empty directory unless `--force`, see `tool show_experiment`
+    #[clap(long, alias = "experiment_dir", conflicts_with = "dry_run")]
+    pub experiment_dir: Option<String>,
+    /// allow `experiment_dir` to be a non-empty directory, overwriting the files of a previous run
+    #[clap(long, action, requires = "experiment_dir")]
+    pub force: bool,
+    /// seed of the random number generators; each worker derives its own seed from it and every configuration uses the same seeds,
+    /// so that a single-threaded benchmark is reproducible; if not provided, a random seed is picked and recorded in the runtime statistics
+    #[clap(long, alias = "rng_seed", alias = "rng-seed")]
+    pub seed: Option<u64>,
+    /// rerun the benchmark recorded in the first line of a runtime statistics file (or a json file with the same content),
+    /// reproducing the same samples in a single-threaded benchmark; all the other arguments are ignored except `--log-runtime-statistics` and `--stream-socket`
+    #[clap(long, alias = "config_file")]
+    pub config_file: Option<String>,
     /// log the error pattern in the statistics log file, which is useful when debugging rare cases but it can make the log file much larger
     #[clap(long, action)]
     pub log_error_pattern_when_logical_error: bool,
+    /// log the measurement round at which each logical error happens in the statistics log file, found by decoding the truncated volumes
+    /// of the first rounds of the failing shots; not supported by the compact simulator
+    #[clap(long, action, requires = "log_runtime_statistics", conflicts_with = "use_compact_simulator")]
+    pub log_failure_round: bool,
+    /// log the true logical class of the errors and the logical class claimed by the decoder's correction of every shot in the statistics log file,
+    /// as `[logical_i, logical_j]`, whose XOR is the logical error; useful when the decoded logical Pauli frame is consumed by a chained experiment
+    #[clap(long, action, requires = "log_runtime_statistics")]
+    pub log_logical_class: bool,
+    /// keep the decoder's correction as is instead of removing its redundant operators, i.e. the identities left where overlapping corrections
+    /// cancel each other; when the optimization is enabled, the number of eliminated operators of every shot is logged as
+    /// `optimized_correction_operators` in the statistics log file
+    #[clap(long, action, alias = "disable_optimize_correction_pattern")]
+    pub disable_optimize_correction_pattern: bool,
+    /// insert idle layers right after the initialization of every measurement cycle, modeling a deliberate delay between QEC cycles;
+    /// the noise model builder applies its idle noise to them, see [`code_builder::code_builder_insert_idle_rounds`]
+    #[clap(long, alias = "idle_rounds_between_cycles", default_value_t = 0)]
+    pub idle_rounds_between_cycles: usize,
+    /// the number of perfect measurement rounds at the end of the simulation, where the noise model builders add no error
+    #[clap(long, alias = "final_perfect_rounds", default_value_t = 1)]
+    pub final_perfect_rounds: usize,
     /// possible noise models see noise_model_builder.rs
     #[clap(long, alias = "noise-model")]
     pub noise_model_builder: Option<noise_model_builder::NoiseModelBuilder>,
-    /// a json object describing the noise model details
+    /// a json object describing the noise model details; a top-level value `{"sweep": [v1, v2, ...]}` runs one configuration per value,
+    /// expanding the Cartesian product of all the swept keys and appending their resolved values to each result;
+    /// the key `erasure_rate_multiplier` (e.g. `{"linear": 0.5}`) scales the erasure rates of each measurement round with any noise model;
+    /// the key `feedforwards` (e.g. `[{"trigger": "[6][1][2]", "pe": {"[7][2][2]": "X"}}]`) applies Pauli operators conditioned on measurement outcomes;
+    /// the key `tls` (e.g. `{"count": 2, "severity": 10.0, "seed": 1}`) multiplies the Pauli error rates of a few data qubits in every round,
+    /// and `"informed": false` in it keeps the decoder ignorant of them;
+    /// the key `noisy_final_round` (e.g. `true`) makes the final measurement round as noisy as the one below it and reconstructs its outcomes from the data readout
     #[clap(long, default_value_t = json!({}), value_parser = ValueParser::new(SerdeJsonParser))]
     pub noise_model_configuration: serde_json::Value,
+    /// replace the noise model of the measurement rounds `[start, end)` by another one, e.g. a calibration glitch lasting a few rounds:
+    /// `{"rounds": [5, 8], "error_model": "phenomenological", "p": 0.02}` with optional `pe` and `noise_model_configuration`, where `error_model`
+    /// defaults to `--noise-model`; the decoding graph is built from the composed noise model, so the decoder knows about the noisier window
+    #[clap(long, alias = "noise_override", value_parser = ValueParser::new(SerdeJsonParser))]
+    pub noise_override: Option<serde_json::Value>,
     /// wait for some time for threads to end, otherwise print out the unstopped threads and detach them; useful when debugging rare deadlock cases; if set to negative value, no timeout and no thread debug information recording for maximum performance
     #[clap(long, default_value_t = 60.)]
     pub thread_timeout: f64,
+    /// target time (in second) of each mini-batch of shots, after which a thread synchronizes its results;
+    /// the mini-batch size is adapted based on the decoding latency of the configuration
+    #[clap(long, default_value_t = 0.5)]
+    pub mini_sync_time: f64,
+    /// minimum number of shots in a mini-batch
+    #[clap(long, default_value_t = 1)]
+    pub mini_batch_min: usize,
+    /// maximum number of shots in a mini-batch
+    #[clap(long, default_value_t = 100000)]
+    pub mini_batch_max: usize,
     /// use brief edges in model graph to save memories; it will drop the error pattern and correction as long as another one is more probable
     #[clap(long, action)]
     pub use_brief_edge: bool,
@@ -210,15 +435,24 @@ pub struct BenchmarkParameters {
     /// if provided, will fetch a Json from file to update noise model
     #[clap(long)]
     pub load_noise_model_from_file: Option<String>,
+    /// if provided, will load the logical operators of a `customized` code from a Json file, e.g. `{"logical_x": ["[0][1][1]"], "logical_z": ["[0][1][1]"]}`,
+    /// to validate the corrections; see [`code_builder::CustomizedLogicalOperators`]
+    #[clap(long)]
+    pub load_logical_operators_from_file: Option<String>,
     /// logging to the default visualizer file at visualize/data/visualizer.json
     #[clap(long, action)]
     pub enable_visualizer: bool,
-    /// visualizer file at visualize/data/<visualizer_filename.json>
+    /// visualizer file at visualize/data/<visualizer_filename.json>, or at the given path if it's absolute
     #[clap(long, default_value_t = crate::visualize::static_visualize_data_filename())]
     pub visualizer_filename: String,
     /// when visualizer is enabled, only record failed cases; useful when trying to debug rare failed cases, e.g. finding the lowest number of physical errors that causes a logical error
     #[clap(long, action)]
     pub visualizer_skip_success_cases: bool,
+    /// when visualizer is enabled, record only the first given number of failed cases, each with a `frames` array stepping through the
+    /// measurement rounds: the new errors and erasures, the raw syndrome, the defects appearing and disappearing, and finally the correction
+    /// together with the residual logical error
+    #[clap(long, alias = "visualize_failures_movie", requires = "enable_visualizer", conflicts_with = "use_compact_simulator")]
+    pub visualize_failures_movie: Option<usize>,
     /// include model graph in the visualizer file
     #[clap(long, action)]
     pub visualizer_model_graph: bool,
@@ -242,6 +476,316 @@ pub struct BenchmarkParameters {
     pub use_compact_simulator_compressed: bool,
 }
 
+#[derive(Parser, Clone, Serialize, Deserialize)]
+pub struct DiffNoiseModelsParameters {
+    /// path of the first noise model json file
+    #[clap(long)]
+    pub a: String,
+    /// path of the second noise model json file
+    #[clap(long)]
+    pub b: String,
+}
+
+#[derive(Parser, Clone, Serialize, Deserialize)]
+pub struct ValidateArtifactParameters {
+    /// path of the artifact file
+    pub filepath: String,
+}
+
+#[derive(Parser, Clone, Serialize, Deserialize)]
+pub struct ShowExperimentParameters {
+    /// path of the experiment directory
+    pub directory: String,
+}
+
+#[derive(Parser, Clone, Serialize, Deserialize)]
+pub struct ExportTikzParameters {
+    /// code distance of vertical axis
+    #[clap(long)]
+    pub d: usize,
+    /// code distance of horizontal axis, will use `d` if not provided
+    #[clap(long)]
+    pub dj: Option<usize>,
+    /// code type, see code_builder.rs for more information
+    #[clap(short = 'c', long, value_enum, default_value_t = code_builder::CodeType::StandardPlanarCode)]
+    pub code_type: code_builder::CodeType,
+}
+
+#[derive(Parser, Clone)]
+pub struct GraphEntropyParameters {
+    /// code distance of vertical axis
+    #[clap(long)]
+    pub d: usize,
+    /// code distance of horizontal axis, will use `d` if not provided
+    #[clap(long)]
+    pub dj: Option<usize>,
+    /// number of noisy measurement rounds
+    #[clap(long, default_value_t = 0)]
+    pub noisy_measurements: usize,
+    /// p = px + py + pz unless noise model has special interpretation of this value
+    #[clap(long)]
+    pub p: f64,
+    /// erasure error rate
+    #[clap(long, default_value_t = 0.)]
+    pub pe: f64,
+    /// bias_eta = pz / (px + py) and px = py, px + py + pz = p. default to 1/2, which means px = pz = py
+    #[clap(long, default_value_t = 0.5)]
+    pub bias_eta: f64,
+    /// code type, see code_builder.rs for more information
+    #[clap(short = 'c', long, value_enum, default_value_t = code_builder::CodeType::StandardPlanarCode)]
+    pub code_type: code_builder::CodeType,
+    /// possible noise models see noise_model_builder.rs
+    #[clap(long, alias = "noise-model")]
+    pub noise_model_builder: Option<noise_model_builder::NoiseModelBuilder>,
+    /// a json object describing the noise model details
+    #[clap(long, default_value_t = json!({}), value_parser = ValueParser::new(SerdeJsonParser))]
+    pub noise_model_configuration: serde_json::Value,
+}
+
+#[derive(Parser, Clone)]
+pub struct WeightSensitivityParameters {
+    /// code distance of vertical axis
+    #[clap(long)]
+    pub d: usize,
+    /// code distance of horizontal axis, will use `d` if not provided
+    #[clap(long)]
+    pub dj: Option<usize>,
+    /// number of noisy measurement rounds
+    #[clap(long, default_value_t = 0)]
+    pub noisy_measurements: usize,
+    /// p = px + py + pz unless noise model has special interpretation of this value
+    #[clap(long)]
+    pub p: f64,
+    /// erasure error rate
+    #[clap(long, default_value_t = 0.)]
+    pub pe: f64,
+    /// bias_eta = pz / (px + py) and px = py, px + py + pz = p. default to 1/2, which means px = pz = py
+    #[clap(long, default_value_t = 0.5)]
+    pub bias_eta: f64,
+    /// code type, see code_builder.rs for more information
+    #[clap(short = 'c', long, value_enum, default_value_t = code_builder::CodeType::StandardPlanarCode)]
+    pub code_type: code_builder::CodeType,
+    /// possible noise models see noise_model_builder.rs
+    #[clap(long, alias = "noise-model")]
+    pub noise_model_builder: Option<noise_model_builder::NoiseModelBuilder>,
+    /// a json object describing the noise model details
+    #[clap(long, default_value_t = json!({}), value_parser = ValueParser::new(SerdeJsonParser))]
+    pub noise_model_configuration: serde_json::Value,
+    /// the quantized decoder, which must support `max_half_weight`
+    #[clap(long, value_enum, default_value_t = tool::BenchmarkDecoder::UnionFind)]
+    pub decoder: tool::BenchmarkDecoder,
+    /// decoder configuration json shared by all the levels, whose `max_half_weight` is overwritten by each level
+    #[clap(long, default_value_t = json!({}), value_parser = ValueParser::new(SerdeJsonParser))]
+    pub decoder_config: serde_json::Value,
+    /// [w1,w2,w3,...,wn] the `max_half_weight` of each quantization level; the last one is the reference to compare with
+    #[clap(long, alias = "max_half_weights", default_value = "[1,2,4,8,16,32]", value_parser = ValueParser::new(VecUsizeParser))]
+    pub max_half_weights: std::vec::Vec<usize>,
+    /// number of shots, each decoded by all the levels
+    #[clap(short = 'm', long, default_value_t = 10000)]
+    pub shots: usize,
+    /// seed of the random number generator, so that the same samples can be reproduced
+    #[clap(long, default_value_t = 0)]
+    pub seed: u64,
+}
+
+#[derive(Parser, Clone)]
+pub struct SensitivityAnalysisParameters {
+    /// code distance of vertical axis
+    #[clap(long)]
+    pub d: usize,
+    /// code distance of horizontal axis, will use `d` if not provided
+    #[clap(long)]
+    pub dj: Option<usize>,
+    /// number of noisy measurement rounds
+    #[clap(long, default_value_t = 0)]
+    pub noisy_measurements: usize,
+    /// p = px + py + pz unless noise model has special interpretation of this value
+    #[clap(long)]
+    pub p: f64,
+    /// erasure error rate
+    #[clap(long, default_value_t = 0.)]
+    pub pe: f64,
+    /// bias_eta = pz / (px + py) and px = py, px + py + pz = p. default to 1/2, which means px = pz = py
+    #[clap(long, default_value_t = 0.5)]
+    pub bias_eta: f64,
+    /// code type, see code_builder.rs for more information
+    #[clap(short = 'c', long, value_enum, default_value_t = code_builder::CodeType::StandardPlanarCode)]
+    pub code_type: code_builder::CodeType,
+    /// possible noise models see noise_model_builder.rs
+    #[clap(long, alias = "noise-model")]
+    pub noise_model_builder: Option<noise_model_builder::NoiseModelBuilder>,
+    /// a json object describing the noise model details
+    #[clap(long, default_value_t = json!({}), value_parser = ValueParser::new(SerdeJsonParser))]
+    pub noise_model_configuration: serde_json::Value,
+    /// the decoder, which is built from the unperturbed noise model
+    #[clap(long, value_enum, default_value_t = tool::BenchmarkDecoder::UnionFind)]
+    pub decoder: tool::BenchmarkDecoder,
+    /// decoder configuration json
+    #[clap(long, default_value_t = json!({}), value_parser = ValueParser::new(SerdeJsonParser))]
+    pub decoder_config: serde_json::Value,
+    /// the relative perturbation `delta` of the error rates, i.e. the error rates of a class are multiplied by `1 + delta`
+    #[clap(long, default_value_t = 0.2)]
+    pub perturbation: f64,
+    /// number of shots, each sampled with and without the perturbation of every class
+    #[clap(short = 'm', long, default_value_t = 10000)]
+    pub shots: usize,
+    /// seed of the random number generator, so that the same samples can be reproduced
+    #[clap(long, default_value_t = 0)]
+    pub seed: u64,
+}
+
+#[derive(Parser, Clone)]
+pub struct CheckVirtualConsistencyParameters {
+    /// code distance of vertical axis
+    #[clap(long)]
+    pub d: usize,
+    /// code distance of horizontal axis, will use `d` if not provided
+    #[clap(long)]
+    pub dj: Option<usize>,
+    /// number of noisy measurement rounds
+    #[clap(long, default_value_t = 0)]
+    pub noisy_measurements: usize,
+    /// p = px + py + pz unless noise model has special interpretation of this value
+    #[clap(long)]
+    pub p: f64,
+    /// erasure error rate
+    #[clap(long, default_value_t = 0.)]
+    pub pe: f64,
+    /// bias_eta = pz / (px + py) and px = py, px + py + pz = p. default to 1/2, which means px = pz = py
+    #[clap(long, default_value_t = 0.5)]
+    pub bias_eta: f64,
+    /// code type, see code_builder.rs for more information
+    #[clap(short = 'c', long, value_enum, default_value_t = code_builder::CodeType::StandardPlanarCode)]
+    pub code_type: code_builder::CodeType,
+    /// possible noise models see noise_model_builder.rs
+    #[clap(long, alias = "noise-model")]
+    pub noise_model_builder: Option<noise_model_builder::NoiseModelBuilder>,
+    /// a json object describing the noise model details
+    #[clap(long, default_value_t = json!({}), value_parser = ValueParser::new(SerdeJsonParser))]
+    pub noise_model_configuration: serde_json::Value,
+    /// number of random shots to check
+    #[clap(short = 'm', long, default_value_t = 10000)]
+    pub shots: usize,
+    /// seed of the random number generator, so that a failed shot can be reproduced
+    #[clap(long, default_value_t = 0)]
+    pub seed: u64,
+}
+
+#[derive(Parser, Clone)]
+pub struct ExportNpyParameters {
+    /// code distance of vertical axis
+    #[clap(long)]
+    pub d: usize,
+    /// code distance of horizontal axis, will use `d` if not provided
+    #[clap(long)]
+    pub dj: Option<usize>,
+    /// number of noisy measurement rounds
+    #[clap(long, default_value_t = 0)]
+    pub noisy_measurements: usize,
+    /// p = px + py + pz unless noise model has special interpretation of this value
+    #[clap(long)]
+    pub p: f64,
+    /// erasure error rate
+    #[clap(long, default_value_t = 0.)]
+    pub pe: f64,
+    /// bias_eta = pz / (px + py) and px = py, px + py + pz = p. default to 1/2, which means px = pz = py
+    #[clap(long, default_value_t = 0.5)]
+    pub bias_eta: f64,
+    /// code type, see code_builder.rs for more information
+    #[clap(short = 'c', long, value_enum, default_value_t = code_builder::CodeType::StandardPlanarCode)]
+    pub code_type: code_builder::CodeType,
+    /// possible noise models see noise_model_builder.rs
+    #[clap(long, alias = "noise-model")]
+    pub noise_model_builder: Option<noise_model_builder::NoiseModelBuilder>,
+    /// a json object describing the noise model details
+    #[clap(long, default_value_t = json!({}), value_parser = ValueParser::new(SerdeJsonParser))]
+    pub noise_model_configuration: serde_json::Value,
+    /// number of shots
+    #[clap(short = 'm', long, default_value_t = 10000)]
+    pub shots: usize,
+    /// seed of the random number generator, so that the same samples can be reproduced
+    #[clap(long, default_value_t = 0)]
+    pub seed: u64,
+    /// prefix of the output files: `<output>_syndromes.npy` holds the `uint8` syndromes of shape `(shots, rounds, vertical, horizontal)`,
+    /// see [`simulator::SparseMeasurement::to_ndarray`], and `<output>_logicals.npy` holds the `uint8` logical errors `(logical_i, logical_j)`
+    /// of shape `(shots, 2)`, i.e. whether the errors flip each logical operator without any correction
+    #[clap(short = 'o', long)]
+    pub output: String,
+}
+
+#[derive(Parser, Clone)]
+pub struct GenerateDatasetParameters {
+    /// code distance of vertical axis
+    #[clap(long)]
+    pub d: usize,
+    /// code distance of horizontal axis, will use `d` if not provided
+    #[clap(long)]
+    pub dj: Option<usize>,
+    /// number of noisy measurement rounds
+    #[clap(long, default_value_t = 0)]
+    pub noisy_measurements: usize,
+    /// p = px + py + pz unless noise model has special interpretation of this value
+    #[clap(long)]
+    pub p: f64,
+    /// bias_eta = pz / (px + py) and px = py, px + py + pz = p. default to 1/2, which means px = pz = py
+    #[clap(long, default_value_t = 0.5)]
+    pub bias_eta: f64,
+    /// code type, see code_builder.rs for more information
+    #[clap(short = 'c', long, value_enum, default_value_t = code_builder::CodeType::StandardPlanarCode)]
+    pub code_type: code_builder::CodeType,
+    /// possible noise models see noise_model_builder.rs; erasures are not supported because the corpus only stores the syndromes
+    #[clap(long, alias = "noise-model")]
+    pub noise_model_builder: Option<noise_model_builder::NoiseModelBuilder>,
+    /// a json object describing the noise model details
+    #[clap(long, default_value_t = json!({}), value_parser = ValueParser::new(SerdeJsonParser))]
+    pub noise_model_configuration: serde_json::Value,
+    /// number of shots
+    #[clap(short = 'm', long, default_value_t = 10000)]
+    pub shots: usize,
+    /// seed of the random number generator, so that the same samples can be reproduced
+    #[clap(long, default_value_t = 0)]
+    pub seed: u64,
+    /// the corpus file, see syndrome_corpus.rs for the format
+    #[clap(short = 'o', long)]
+    pub output: String,
+}
+
+#[derive(Parser, Clone)]
+pub struct BenchDecoderParameters {
+    /// the corpus file generated by `generate_dataset`
+    #[clap(long)]
+    pub corpus: String,
+    /// the decoders to compare, e.g. `--decoder mwpm,union-find`
+    #[clap(long = "decoder", alias = "decoders", value_enum, value_delimiter = ',', required = true)]
+    pub decoders: Vec<tool::BenchmarkDecoder>,
+    /// decoder configuration json shared by all the decoders
+    #[clap(long, default_value_t = json!({}), value_parser = ValueParser::new(SerdeJsonParser))]
+    pub decoder_config: serde_json::Value,
+    /// decode the whole corpus this number of times after a warm-up pass; the relative standard deviation of the throughput across the passes
+    /// tells whether the numbers are stable
+    #[clap(long, default_value_t = 3)]
+    pub passes: usize,
+}
+
+#[derive(Parser, Clone)]
+pub struct BuildGraphParameters {
+    #[clap(flatten)]
+    pub benchmark: BenchmarkParameters,
+    /// the model graph file, a json object of the model graph and its configuration hash
+    #[clap(long)]
+    pub out: String,
+}
+
+#[derive(Parser, Clone)]
+pub struct ExportPymatchingParameters {
+    #[clap(flatten)]
+    pub benchmark: BenchmarkParameters,
+    /// the check matrix file, a json object of `detectors`, `indptr`, `indices`, `weights`, `error_probabilities`, `boundary_edges`,
+    /// `observables`, `faults_indptr` and `faults_indices`
+    #[clap(long)]
+    pub out: String,
+}
+
 #[derive(Parser, Clone)]
 pub struct ServerParameters {
     /// listening on <addr>:<port>, default to 8066
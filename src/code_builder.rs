@@ -40,6 +40,9 @@ pub enum CodeType {
     RotatedTailoredCodeBellInit,
     /// periodic boundary condition of rotated tailored surface code, code distances must be even number
     PeriodicRotatedTailoredCode,
+    /// periodic boundary condition of standard planar code in both directions, i.e. the toric code encoding 2 logical qubits;
+    /// noisy measurement rounds (excluding the final perfect measurement cap), vertical code distance, horizontal code distance
+    StandardToricCode,
     /// unknown code type, user must provide necessary information and build circuit-level implementation
     Customized,
 }
@@ -55,6 +58,9 @@ pub struct CodeSize {
     pub di: usize,
     #[cfg_attr(feature = "python_binding", pyo3(get, set))]
     pub dj: usize,
+    /// the number of perfect measurement rounds at the end, default to 1
+    #[cfg_attr(feature = "python_binding", pyo3(get, set))]
+    pub final_perfect_rounds: usize,
 }
 
 #[cfg_attr(feature = "python_binding", cfg_eval)]
@@ -66,13 +72,35 @@ impl CodeSize {
             noisy_measurements: noisy_measurements,
             di: di,
             dj: dj,
+            final_perfect_rounds: 1,
         }
     }
 }
 
+impl CodeSize {
+    /// end with `final_perfect_rounds` perfect measurement rounds instead of a single one, e.g. for protocols that continue after the memory experiment
+    pub fn with_final_perfect_rounds(mut self, final_perfect_rounds: usize) -> Self {
+        assert!(final_perfect_rounds >= 1, "at least one perfect measurement round is required to validate the correction");
+        self.final_perfect_rounds = final_perfect_rounds;
+        self
+    }
+}
+
 #[cfg_attr(feature = "python_binding", pymethods)]
 impl CodeType {
 
+    /// the number of layers of a measurement cycle built by [`build_code`], or None if the circuit is provided by the user
+    pub fn default_measurement_cycles(&self) -> Option<usize> {
+        match self {
+            CodeType::StandardPlanarCode | CodeType::RotatedPlanarCode => Some(6),
+            CodeType::StandardXZZXCode | CodeType::RotatedXZZXCode => Some(6),
+            CodeType::StandardTailoredCode | CodeType::RotatedTailoredCode | CodeType::RotatedTailoredCodeBellInit => Some(6),
+            CodeType::PeriodicRotatedTailoredCode => Some(6),
+            CodeType::StandardToricCode => Some(6),
+            CodeType::Customized => None,
+        }
+    }
+
     /// get position on the left of (i, j), note that this position may be invalid for open-boundary code if it doesn't exist
     pub fn get_left(&self, i: usize, j: usize, code_size: &CodeSize) -> (usize, usize) {
         match self {
@@ -83,6 +111,10 @@ impl CodeType {
                     (i, usize::MAX)
                 }
             },
+            &CodeType::StandardToricCode => {
+                let horizontal = 2 * code_size.dj;
+                (i, (j + horizontal - 1) % horizontal)
+            },
             &CodeType::PeriodicRotatedTailoredCode => {
                 let dp = code_size.di;
                 let dn = code_size.dj;
@@ -109,6 +141,10 @@ impl CodeType {
                     (usize::MAX, j)
                 }
             },
+            &CodeType::StandardToricCode => {
+                let vertical = 2 * code_size.di;
+                ((i + vertical - 1) % vertical, j)
+            },
             &CodeType::PeriodicRotatedTailoredCode => {
                 let dp = code_size.di;
                 let dn = code_size.dj;
@@ -133,6 +169,10 @@ impl CodeType {
             &CodeType::RotatedTailoredCode | &CodeType::RotatedTailoredCodeBellInit => {
                 (i, j + 1)
             },
+            &CodeType::StandardToricCode => {
+                let horizontal = 2 * code_size.dj;
+                (i, (j + 1) % horizontal)
+            },
             &CodeType::PeriodicRotatedTailoredCode => {
                 let dp = code_size.di;
                 let dn = code_size.dj;
@@ -155,6 +195,10 @@ impl CodeType {
             &CodeType::RotatedTailoredCode | &CodeType::RotatedTailoredCodeBellInit => {
                 (i + 1, j)
             },
+            &CodeType::StandardToricCode => {
+                let vertical = 2 * code_size.di;
+                ((i + 1) % vertical, j)
+            },
             &CodeType::PeriodicRotatedTailoredCode => {
                 let dp = code_size.di;
                 let dn = code_size.dj;
@@ -201,12 +245,14 @@ impl CodeType {
 pub fn build_code(simulator: &mut Simulator) {
     let code_type = &simulator.code_type;
     let code_size = &simulator.code_size;
+    if let Some(measurement_cycles) = code_type.default_measurement_cycles() {
+        simulator.measurement_cycles = measurement_cycles;
+    }
     match code_type {
         &CodeType::StandardPlanarCode| &CodeType::RotatedPlanarCode => {
             let di = code_size.di;
             let dj = code_size.dj;
             let noisy_measurements = code_size.noisy_measurements;
-            simulator.measurement_cycles = 6;
             assert!(di > 0, "code distance must be positive integer");
             assert!(dj > 0, "code distance must be positive integer");
             let is_rotated = matches!(code_type, CodeType::RotatedPlanarCode { .. });
@@ -220,7 +266,7 @@ pub fn build_code(simulator: &mut Simulator) {
             } else {
                 (2 * di + 1, 2 * dj + 1)
             };
-            let height = simulator.measurement_cycles * (noisy_measurements + 1) + 1;
+            let height = simulator.measurement_cycles * (noisy_measurements + code_size.final_perfect_rounds) + 1;
             // each measurement takes 6 time steps
             let mut nodes = Vec::with_capacity(height);
             let is_real = |i: usize, j: usize| -> bool {
@@ -375,7 +421,6 @@ pub fn build_code(simulator: &mut Simulator) {
             let di = code_size.di;
             let dj = code_size.dj;
             let noisy_measurements = code_size.noisy_measurements;
-            simulator.measurement_cycles = 6;
             assert!(di > 0, "code distance must be positive integer");
             assert!(dj > 0, "code distance must be positive integer");
             let is_rotated = matches!(code_type, CodeType::RotatedTailoredCode { .. }) || matches!(code_type, CodeType::RotatedTailoredCodeBellInit { .. });
@@ -390,7 +435,7 @@ pub fn build_code(simulator: &mut Simulator) {
             } else {
                 (2 * di + 1, 2 * dj + 1)
             };
-            let height = simulator.measurement_cycles * (noisy_measurements + 1) + 1;
+            let height = simulator.measurement_cycles * (noisy_measurements + code_size.final_perfect_rounds) + 1;
             // each measurement takes 6 time steps
             let mut nodes = Vec::with_capacity(height);
             let is_real = |i: usize, j: usize| -> bool {
@@ -664,7 +709,6 @@ pub fn build_code(simulator: &mut Simulator) {
             let dp = code_size.di;
             let dn = code_size.dj;
             let noisy_measurements = code_size.noisy_measurements;
-            simulator.measurement_cycles = 6;
             assert!(dp > 0, "code distance must be positive integer");
             assert!(dn > 0, "code distance must be positive integer");
             assert!(dp % 2 == 0, "code distance must be even integer, current: dp = {}", dp);
@@ -673,7 +717,7 @@ pub fn build_code(simulator: &mut Simulator) {
             let di = dp - 1;  // to use previously developed functions
             let dj = dn - 1;
             let (vertical, horizontal) = (di + dj + 2, di + dj + 1);
-            let height = simulator.measurement_cycles * (noisy_measurements + 1) + 1;
+            let height = simulator.measurement_cycles * (noisy_measurements + code_size.final_perfect_rounds) + 1;
             // each measurement takes 6 time steps
             let mut nodes = Vec::with_capacity(height);
             let is_present = |i: usize, j: usize| -> bool {
@@ -778,6 +822,88 @@ pub fn build_code(simulator: &mut Simulator) {
             simulator.height = height;
             simulator.nodes = nodes;
         },
+        &CodeType::StandardToricCode => {
+            let di = code_size.di;
+            let dj = code_size.dj;
+            let noisy_measurements = code_size.noisy_measurements;
+            assert!(di >= 2, "code distance must be at least 2 for periodic boundary, current: di = {}", di);
+            assert!(dj >= 2, "code distance must be at least 2 for periodic boundary, current: dj = {}", dj);
+            // the same layout as the standard planar code without the boundaries: row `vertical` wraps to row 0 and column `horizontal` to column 0,
+            // so that every position is present and there is no virtual node
+            let (vertical, horizontal) = (2 * di, 2 * dj);
+            let height = simulator.measurement_cycles * (noisy_measurements + code_size.final_perfect_rounds) + 1;
+            // each measurement takes 6 time steps
+            let mut nodes = Vec::with_capacity(height);
+            for t in 0..height {
+                let mut row_i = Vec::with_capacity(vertical);
+                for i in 0..vertical {
+                    let mut row_j = Vec::with_capacity(horizontal);
+                    for j in 0..horizontal {
+                        let qubit_type = if (i + j) % 2 == 0 { QubitType::Data } else { if i % 2 == 1 { QubitType::StabZ } else { QubitType::StabX } };
+                        let mut gate_type = GateType::None;
+                        let mut gate_peer = None;
+                        match t % simulator.measurement_cycles {
+                            1 => {  // initialization
+                                match qubit_type {
+                                    QubitType::StabZ => { gate_type = GateType::InitializeZ; }
+                                    QubitType::StabX => { gate_type = GateType::InitializeX; }
+                                    QubitType::Data => { }
+                                    _ => { unreachable!() }
+                                }
+                            },
+                            2 => {  // gate 1
+                                let (pi, pj) = if qubit_type == QubitType::Data { code_type.get_down(i, j, code_size) } else { code_type.get_up(i, j, code_size) };
+                                gate_type = if (j % 2 == 1) == (qubit_type == QubitType::Data) { GateType::CXGateTarget } else { GateType::CXGateControl };
+                                gate_peer = Some(pos!(t, pi, pj));
+                            },
+                            3 => {  // gate 2
+                                if j % 2 == 1 {  // operate with right
+                                    let (pi, pj) = code_type.get_right(i, j, code_size);
+                                    gate_type = GateType::CXGateControl;
+                                    gate_peer = Some(pos!(t, pi, pj));
+                                } else {  // operate with left
+                                    let (pi, pj) = code_type.get_left(i, j, code_size);
+                                    gate_type = GateType::CXGateTarget;
+                                    gate_peer = Some(pos!(t, pi, pj));
+                                }
+                            },
+                            4 => {  // gate 3
+                                if j % 2 == 1 {  // operate with left
+                                    let (pi, pj) = code_type.get_left(i, j, code_size);
+                                    gate_type = GateType::CXGateControl;
+                                    gate_peer = Some(pos!(t, pi, pj));
+                                } else {  // operate with right
+                                    let (pi, pj) = code_type.get_right(i, j, code_size);
+                                    gate_type = GateType::CXGateTarget;
+                                    gate_peer = Some(pos!(t, pi, pj));
+                                }
+                            },
+                            5 => {  // gate 4
+                                let (pi, pj) = if qubit_type == QubitType::Data { code_type.get_up(i, j, code_size) } else { code_type.get_down(i, j, code_size) };
+                                gate_type = if (j % 2 == 1) == (qubit_type == QubitType::Data) { GateType::CXGateTarget } else { GateType::CXGateControl };
+                                gate_peer = Some(pos!(t, pi, pj));
+                            },
+                            0 => {  // measurement
+                                match qubit_type {
+                                    QubitType::StabZ => { gate_type = GateType::MeasureZ; }
+                                    QubitType::StabX => { gate_type = GateType::MeasureX; }
+                                    QubitType::Data => { }
+                                    _ => { unreachable!() }
+                                }
+                            },
+                            _ => unreachable!()
+                        }
+                        row_j.push(Some(Box::new(SimulatorNode::new(qubit_type, gate_type, gate_peer))));
+                    }
+                    row_i.push(row_j);
+                }
+                nodes.push(row_i)
+            }
+            simulator.vertical = vertical;
+            simulator.horizontal = horizontal;
+            simulator.height = height;
+            simulator.nodes = nodes;
+        },
         CodeType::Customized => {
             // skip user customized code
         },
@@ -785,7 +911,6 @@ pub fn build_code(simulator: &mut Simulator) {
             let di = code_size.di;
             let dj = code_size.dj;
             let noisy_measurements = code_size.noisy_measurements;
-            simulator.measurement_cycles = 6;
             assert!(di > 0, "code distance must be positive integer");
             assert!(dj > 0, "code distance must be positive integer");
             let is_rotated = matches!(code_type, CodeType::RotatedXZZXCode { .. });
@@ -799,7 +924,7 @@ pub fn build_code(simulator: &mut Simulator) {
             } else {
                 (2 * di + 1, 2 * dj + 1)
             };
-            let height = simulator.measurement_cycles * (noisy_measurements + 1) + 1;
+            let height = simulator.measurement_cycles * (noisy_measurements + code_size.final_perfect_rounds) + 1;
             // each measurement takes 6 time steps
             let mut nodes = Vec::with_capacity(height);
             let is_real = |i: usize, j: usize| -> bool {
@@ -969,6 +1094,56 @@ pub fn visualize_positions(simulator: &Simulator) -> Vec<Vec<VisualizePosition>>
     positions
 }
 
+/// insert `idle_rounds` layers right after the initialization layer (`t % measurement_cycles == 1`) of every measurement cycle, where all the qubits
+/// idle with `GateType::None`, e.g. to study the decoherence scaling with a deliberate delay between QEC cycles; the initialization and measurement
+/// layers keep their offsets in the longer cycle, so the noise model builders apply as before and the idle noise they assign to gate-free qubits
+/// (e.g. `depolarizing-noise`) also applies to the inserted layers
+pub fn code_builder_insert_idle_rounds(simulator: &mut Simulator, idle_rounds: usize) -> Result<(), String> {
+    if idle_rounds == 0 {
+        return Ok(())
+    }
+    let measurement_cycles = simulator.measurement_cycles;
+    if measurement_cycles < 2 || !(simulator.height - 1).is_multiple_of(measurement_cycles) {
+        return Err(format!("idle rounds require repeated measurement cycles with an initialization layer, but measurement_cycles = {} and height = {}"
+            , measurement_cycles, simulator.height))
+    }
+    let idle_measurement_cycles = measurement_cycles + idle_rounds;
+    let height = idle_measurement_cycles * ((simulator.height - 1) / measurement_cycles) + 1;
+    // the original layer of each new layer, None for the idle layers
+    let original_t = |t: usize| -> Option<usize> {
+        let (round, offset) = (t / idle_measurement_cycles, t % idle_measurement_cycles);
+        if offset <= 1 {
+            Some(round * measurement_cycles + offset)
+        } else if offset < 2 + idle_rounds {
+            None
+        } else {
+            Some(round * measurement_cycles + offset - idle_rounds)
+        }
+    };
+    let mut nodes = Vec::with_capacity(height);
+    for t in 0..height {
+        let is_idle = original_t(t).is_none();
+        // an idle layer copies the qubits of the initialization layer below it
+        let source_t = original_t(t).unwrap_or(t / idle_measurement_cycles * measurement_cycles + 1);
+        nodes.push(simulator.nodes[source_t].iter().map(|row| row.iter().map(|node| node.as_ref().map(|node| {
+            let mut node = node.clone();
+            if is_idle {
+                node.gate_type = GateType::None;
+                node.gate_peer = None;
+                node.is_peer_virtual = false;
+            } else if let Some(gate_peer) = node.gate_peer.as_ref() {
+                node.gate_peer = Some(std::sync::Arc::new(pos!(t, gate_peer.i, gate_peer.j)));
+            }
+            node
+        })).collect()).collect());
+    }
+    simulator.nodes = nodes;
+    simulator.height = height;
+    simulator.measurement_cycles = idle_measurement_cycles;
+    simulator.clear_all_errors();
+    code_builder_sanity_check(simulator)
+}
+
 /// detect common bugs of code building, e.g. peer gate invalid type, is_virtual not correct, etc...
 pub fn code_builder_sanity_check(simulator: &Simulator) -> Result<(), String> {
     simulator_iter!(simulator, position, node, {
@@ -1015,32 +1190,79 @@ pub fn code_builder_sanity_check(simulator: &Simulator) -> Result<(), String> {
             }
         }
     });
-    simulator_iter!(simulator, base_position, _base_node, t => 0, {
-        // check that initialization and measurement are always in the same basis
-        let mut previous_initialization = GateType::None;
-        for t in 1..simulator.height {
-            let position = &mut base_position.clone();
-            position.t = t;
-            let node = simulator.get_node_unwrap(position);
-            if node.gate_type.is_initialization() {
-                previous_initialization = node.gate_type;
-            }
-            if node.gate_type.is_measurement() {
-                if !node.gate_type.is_corresponding_initialization(&previous_initialization) {
-                    return Err(format!("measurement and initialization not in the same basis: node {} has gate type {:?} but previous initialization is {:?}"
-                        , position, node.gate_type, previous_initialization))
-                }
-            }
-        }
-    });
+    // check that initialization and measurement are always in the same basis
+    simulator.check_basis_consistency()?;
     Ok(())
 }
 
 pub fn code_builder_validate_correction(simulator: &mut Simulator, correction: &SparseCorrection) -> Option<(bool, bool)> {
-    // apply the correction directly to the top layer
+    let top_t = simulator.height - 1;
+    code_builder_validate_correction_at(simulator, correction, top_t)
+}
+
+/// the logical errors `(logical_i, logical_j)` of the two logical qubits of [`CodeType::StandardToricCode`] given the propagated errors at layer `layer_t`;
+/// a logical error is a non-contractible loop around either direction of the torus, detected by the odd cardinality of the anticommuting errors
+/// along the conjugate loop, where the first (second) logical qubit is checked by the X operator on row 1 (column 0) and the Z operator on column 1 (row 0)
+fn standard_toric_code_logical_errors(simulator: &Simulator, layer_t: usize) -> ((bool, bool), (bool, bool)) {
+    let is_odd_cardinality = |positions: Vec<(usize, usize)>, operator: ErrorType| -> bool {
+        positions.iter().filter(|&&(i, j)| simulator.get_node_unwrap(&pos!(layer_t, i, j)).propagated.anticommute(&operator)).count() % 2 != 0
+    };
+    let (vertical, horizontal) = (simulator.vertical, simulator.horizontal);
+    // Z error loops along the vertical (horizontal) direction cross the X operator on row 1 (column 0) exactly once
+    // X error loops along the horizontal (vertical) direction cross the Z operator on column 1 (row 0) exactly once
+    let first = (is_odd_cardinality((1..horizontal).step_by(2).map(|j| (1, j)).collect(), X)
+        , is_odd_cardinality((1..vertical).step_by(2).map(|i| (i, 1)).collect(), Z));
+    let second = (is_odd_cardinality((0..vertical).step_by(2).map(|i| (i, 0)).collect(), X)
+        , is_odd_cardinality((0..horizontal).step_by(2).map(|j| (0, j)).collect(), Z));
+    (first, second)
+}
+
+/// the logical errors `(logical_p, logical_n)` of the two logical qubits of [`CodeType::PeriodicRotatedTailoredCode`] given the propagated
+/// errors at layer `layer_t`, checked by the X and Y operators along the top and the left boundaries (see [`code_builder_logical_operators`]);
+/// since the code distances are even, the first logical qubit is checked by the X operator on top and the Y operator on left, which anticommute
+/// at their single shared qubit, and the second one by the X operator on left and the Y operator on top
+fn periodic_rotated_tailored_code_logical_errors(simulator: &Simulator, layer_t: usize) -> ((bool, bool), (bool, bool)) {
+    let (dp, dn) = (simulator.code_size.di, simulator.code_size.dj);
+    let is_odd_cardinality = |positions: Vec<(usize, usize)>, operator: ErrorType| -> bool {
+        positions.iter().filter(|&&(i, j)| simulator.get_node_unwrap(&pos!(layer_t, i, j)).propagated.anticommute(&operator)).count() % 2 != 0
+    };
+    let top = || -> Vec<(usize, usize)> { (0..dn).map(|delta| (dn-delta, delta)).collect() };
+    let left = || -> Vec<(usize, usize)> { (0..dp).map(|delta| (dn+delta, delta)).collect() };
+    let first = (is_odd_cardinality(top(), X), is_odd_cardinality(left(), Y));
+    let second = (is_odd_cardinality(left(), X), is_odd_cardinality(top(), Y));
+    (first, second)
+}
+
+/// validate the correction of each logical qubit separately, returning `((logical_i, logical_j), (logical_i, logical_j))` of the first and the second
+/// logical qubit; codes encoding a single logical qubit never fail the second one, and [`code_builder_validate_correction`] is their OR
+pub fn code_builder_validate_correction_multi(simulator: &mut Simulator, correction: &SparseCorrection) -> Option<((bool, bool), (bool, bool))> {
+    let logical_errors = match simulator.code_type {
+        CodeType::StandardToricCode => standard_toric_code_logical_errors,
+        CodeType::PeriodicRotatedTailoredCode => periodic_rotated_tailored_code_logical_errors,
+        _ => return code_builder_validate_correction(simulator, correction).map(|logical| (logical, (false, false))),
+    };
     let top_t = simulator.height - 1;
     for (position, error) in correction.iter() {
-        assert_eq!(position.t, top_t, "correction pattern must only be at top layer");
+        assert_eq!(position.t, top_t, "correction pattern must only be at the top layer");
+        let node = simulator.get_node_mut_unwrap(position);
+        node.propagated = node.propagated.multiply(error);
+    }
+    let result = logical_errors(simulator, top_t);
+    for (position, error) in correction.iter() {
+        let node = simulator.get_node_mut_unwrap(position);
+        node.propagated = node.propagated.multiply(error);
+    }
+    Some(result)
+}
+
+/// validate the correction against the propagated errors at layer `layer_t` instead of the top layer, where the correction must be
+/// expressed as propagated errors at that layer (see [`Simulator::generate_sparse_correction_at`]); errors happening after `layer_t`
+/// are invisible to this validation
+pub fn code_builder_validate_correction_at(simulator: &mut Simulator, correction: &SparseCorrection, layer_t: usize) -> Option<(bool, bool)> {
+    assert!(layer_t < simulator.height, "layer {} out of range", layer_t);
+    // apply the correction directly to the layer
+    for (position, error) in correction.iter() {
+        assert_eq!(position.t, layer_t, "correction pattern must only be at the validated layer");
         let node = simulator.get_node_mut_unwrap(position);
         node.propagated = node.propagated.multiply(error);
     }
@@ -1052,7 +1274,7 @@ pub fn code_builder_validate_correction(simulator: &mut Simulator, correction: &
             // check cardinality of top boundary for logical_i
             let mut top_cardinality = 0;
             for j in (1..simulator.horizontal).step_by(2) {
-                let node = simulator.get_node_unwrap(&pos!(top_t, 1, j));
+                let node = simulator.get_node_unwrap(&pos!(layer_t, 1, j));
                 if node.propagated == Z || node.propagated == Y {
                     top_cardinality += 1;
                 }
@@ -1061,7 +1283,7 @@ pub fn code_builder_validate_correction(simulator: &mut Simulator, correction: &
             // check cardinality of left boundary for logical_j
             let mut left_cardinality = 0;
             for i in (1..simulator.vertical).step_by(2) {
-                let node = simulator.get_node_unwrap(&pos!(top_t, i, 1));
+                let node = simulator.get_node_unwrap(&pos!(layer_t, i, 1));
                 if node.propagated == X || node.propagated == Y {
                     left_cardinality += 1;
                 }
@@ -1075,7 +1297,7 @@ pub fn code_builder_validate_correction(simulator: &mut Simulator, correction: &
             let dn = code_size.dj;
             let mut top_cardinality = 0;
             for delta in 0..dn {
-                let node = simulator.get_node_unwrap(&pos!(top_t, dn-delta, 1+delta));
+                let node = simulator.get_node_unwrap(&pos!(layer_t, dn-delta, 1+delta));
                 if node.propagated == Z || node.propagated == Y {
                     top_cardinality += 1;
                 }
@@ -1084,7 +1306,7 @@ pub fn code_builder_validate_correction(simulator: &mut Simulator, correction: &
             // check cardinality of left boundary for logical_j
             let mut left_cardinality = 0;
             for delta in 0..dp {
-                let node = simulator.get_node_unwrap(&pos!(top_t, dn+delta, 1+delta));
+                let node = simulator.get_node_unwrap(&pos!(layer_t, dn+delta, 1+delta));
                 if node.propagated == X || node.propagated == Y {
                     left_cardinality += 1;
                 }
@@ -1096,7 +1318,7 @@ pub fn code_builder_validate_correction(simulator: &mut Simulator, correction: &
             // check cardinality of top boundary for logical_i
             let mut top_cardinality = 0;
             for j in (1..simulator.horizontal).step_by(2) {
-                let node = simulator.get_node_unwrap(&pos!(top_t, 1, j));
+                let node = simulator.get_node_unwrap(&pos!(layer_t, 1, j));
                 if node.propagated == Y || node.propagated == Z {
                     top_cardinality += 1;
                 }
@@ -1105,7 +1327,7 @@ pub fn code_builder_validate_correction(simulator: &mut Simulator, correction: &
             // check cardinality of left boundary for logical_j
             let mut left_cardinality = 0;
             for i in (1..simulator.vertical).step_by(2) {
-                let node = simulator.get_node_unwrap(&pos!(top_t, i, 1));
+                let node = simulator.get_node_unwrap(&pos!(layer_t, i, 1));
                 if node.propagated == X || node.propagated == Z {
                     left_cardinality += 1;
                 }
@@ -1119,7 +1341,7 @@ pub fn code_builder_validate_correction(simulator: &mut Simulator, correction: &
             let dn = code_size.dj;
             let mut top_cardinality = 0;
             for delta in 0..dn {
-                let node = simulator.get_node_unwrap(&pos!(top_t, dn-delta, 1+delta));
+                let node = simulator.get_node_unwrap(&pos!(layer_t, dn-delta, 1+delta));
                 if node.propagated == Y || node.propagated == Z {
                     top_cardinality += 1;
                 }
@@ -1128,7 +1350,7 @@ pub fn code_builder_validate_correction(simulator: &mut Simulator, correction: &
             // check cardinality of left boundary for logical_j
             let mut left_cardinality = 0;
             for delta in 0..dp {
-                let node = simulator.get_node_unwrap(&pos!(top_t, dn+delta, 1+delta));
+                let node = simulator.get_node_unwrap(&pos!(layer_t, dn+delta, 1+delta));
                 if node.propagated == X || node.propagated == Z {
                     left_cardinality += 1;
                 }
@@ -1137,42 +1359,20 @@ pub fn code_builder_validate_correction(simulator: &mut Simulator, correction: &
             Some((logical_p, logical_n))
         },
         &CodeType::PeriodicRotatedTailoredCode => {
-            let dp = code_size.di;
-            let dn = code_size.dj;
-            // check cardinality of top boundary for logical_i
-            let mut top_cardinality_y = 0;
-            let mut top_cardinality_x = 0;
-            for delta in 0..dn {
-                let node = simulator.get_node_unwrap(&pos!(top_t, dn-delta, delta));
-                if node.propagated == Y || node.propagated == Z {
-                    top_cardinality_y += 1;
-                }
-                if node.propagated == X || node.propagated == Z {
-                    top_cardinality_x += 1;
-                }
-            }
-            // check cardinality of left boundary for logical_j
-            let mut left_cardinality_y = 0;
-            let mut left_cardinality_x = 0;
-            for delta in 0..dp {
-                let node = simulator.get_node_unwrap(&pos!(top_t, dn+delta, delta));
-                if node.propagated == Y || node.propagated == Z {
-                    left_cardinality_y += 1;
-                }
-                if node.propagated == X || node.propagated == Z {
-                    left_cardinality_x += 1;
-                }
-            }
-            // odd cardinality means there is a logical error; there are two logical qubits so either error
-            let logical_p = top_cardinality_y % 2 != 0 || left_cardinality_y % 2 != 0;
-            let logical_n = top_cardinality_x % 2 != 0 || left_cardinality_x % 2 != 0;
-            Some((logical_p, logical_n))
+            // there are two logical qubits so either logical error counts
+            let (first, second) = periodic_rotated_tailored_code_logical_errors(simulator, layer_t);
+            Some((first.0 || second.0, first.1 || second.1))
+        },
+        &CodeType::StandardToricCode => {
+            // there are two logical qubits so either logical error counts
+            let (first, second) = standard_toric_code_logical_errors(simulator, layer_t);
+            Some((first.0 || second.0, first.1 || second.1))
         },
         &CodeType::StandardXZZXCode => {
             // check cardinality of top boundary for logical_i
             let mut top_cardinality = 0;
             for j in (1..simulator.horizontal).step_by(2) {
-                let node = simulator.get_node_unwrap(&pos!(top_t, 1, j));
+                let node = simulator.get_node_unwrap(&pos!(layer_t, 1, j));
                 if node.propagated == X || node.propagated == Y {
                     top_cardinality += 1;
                 }
@@ -1181,7 +1381,7 @@ pub fn code_builder_validate_correction(simulator: &mut Simulator, correction: &
             // check cardinality of left boundary for logical_j
             let mut left_cardinality = 0;
             for i in (1..simulator.vertical).step_by(2) {
-                let node = simulator.get_node_unwrap(&pos!(top_t, i, 1));
+                let node = simulator.get_node_unwrap(&pos!(layer_t, i, 1));
                 if node.propagated == Z || node.propagated == Y {
                     left_cardinality += 1;
                 }
@@ -1192,11 +1392,16 @@ pub fn code_builder_validate_correction(simulator: &mut Simulator, correction: &
         &CodeType::RotatedXZZXCode => {
             let dp = code_size.di;
             let dn = code_size.dj;
+            // the logical operators are Clifford-deformed: every other qubit along the boundary is Hadamard-rotated,
+            // so the checked Pauli alternates between Z and X (see [`code_builder_logical_operators`])
+            let deformed = |delta: usize, operator: ErrorType| -> ErrorType {
+                if delta % 2 == 1 { if operator == X { Z } else { X } } else { operator }
+            };
             // check cardinality of top boundary for logical_i
             let mut top_cardinality = 0;
             for delta in 0..dn {
-                let node = simulator.get_node_unwrap(&pos!(top_t, dn-delta, 1+delta));
-                if node.propagated == X || node.propagated == Y {
+                let node = simulator.get_node_unwrap(&pos!(layer_t, dn-delta, 1+delta));
+                if node.propagated.anticommute(&deformed(delta, Z)) {
                     top_cardinality += 1;
                 }
             }
@@ -1204,15 +1409,22 @@ pub fn code_builder_validate_correction(simulator: &mut Simulator, correction: &
             // check cardinality of left boundary for logical_j
             let mut left_cardinality = 0;
             for delta in 0..dp {
-                let node = simulator.get_node_unwrap(&pos!(top_t, dn+delta, 1+delta));
-                if node.propagated == Z || node.propagated == Y {
+                let node = simulator.get_node_unwrap(&pos!(layer_t, dn+delta, 1+delta));
+                if node.propagated.anticommute(&deformed(delta, X)) {
                     left_cardinality += 1;
                 }
             }
             let logical_n = left_cardinality % 2 != 0;  // odd cardinality means there is a logical X error
             Some((logical_p, logical_n))
         },
-        _ => None
+        &CodeType::Customized => simulator.customized_logical_operators.as_ref().map(|operators| {
+            let is_odd_cardinality = |positions: &Vec<Position>, operator: ErrorType| -> bool {
+                positions.iter().filter(|position| {
+                    simulator.get_node_unwrap(&pos!(layer_t, position.i, position.j)).propagated.anticommute(&operator)
+                }).count() % 2 != 0
+            };
+            (is_odd_cardinality(&operators.logical_x, X), is_odd_cardinality(&operators.logical_z, Z))
+        }),
     };
     // recover the errors
     for (position, error) in correction.iter() {
@@ -1222,6 +1434,112 @@ pub fn code_builder_validate_correction(simulator: &mut Simulator, correction: &
     result
 }
 
+/// logical operators of a [`CodeType::Customized`] code given by the qubits they act on, e.g. `{"logical_x": ["[0][1][1]", "[0][1][3]"],
+/// "logical_z": ["[0][1][1]", "[0][3][1]"]}`; the `t` of each position is ignored because the operators are checked at the validated layer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomizedLogicalOperators {
+    /// the logical X operator, which detects logical Z errors, reported as `logical_i`
+    pub logical_x: Vec<Position>,
+    /// the logical Z operator, which detects logical X errors, reported as `logical_j`
+    pub logical_z: Vec<Position>,
+}
+
+impl CustomizedLogicalOperators {
+    pub fn from_json_file(filepath: &str) -> Result<Self, String> {
+        let value = std::fs::read_to_string(filepath).map_err(|_| format!("logical operators file cannot open: {}", filepath))?;
+        serde_json::from_str(&value).map_err(|e| format!("logical operators cannot recognize: {}", e))
+    }
+
+    /// check that every position is a real data qubit of the simulator
+    pub fn sanity_check(&self, simulator: &Simulator) -> Result<(), String> {
+        for position in self.logical_x.iter().chain(self.logical_z.iter()) {
+            let position = pos!(0, position.i, position.j);
+            if !simulator.is_valid_position(&position) || !simulator.is_node_real(&position)
+                    || simulator.get_node_unwrap(&position).qubit_type != QubitType::Data {
+                return Err(format!("logical operator on [{}][{}] is not a data qubit", position.i, position.j))
+            }
+        }
+        Ok(())
+    }
+
+    /// the logical X operator and the logical Z operator at layer `t`
+    pub fn at_layer(&self, t: usize) -> Vec<SparseErrorPattern> {
+        let operator_on = |positions: &Vec<Position>, operator: ErrorType| -> SparseErrorPattern {
+            let mut pattern = SparseErrorPattern::new();
+            for position in positions.iter() {
+                pattern.add(pos!(t, position.i, position.j), operator);
+            }
+            pattern
+        };
+        vec![operator_on(&self.logical_x, X), operator_on(&self.logical_z, Z)]
+    }
+}
+
+/// the logical operators on the top layer that are checked by [`code_builder_validate_correction`]: a logical error is
+/// reported whenever the propagated errors anticommute with one of them; return None if the code type is unknown
+pub fn code_builder_logical_operators(simulator: &Simulator) -> Option<Vec<SparseErrorPattern>> {
+    let top_t = simulator.height - 1;
+    let code_size = &simulator.code_size;
+    let operator_on = |positions: &Vec<Position>, operator: ErrorType| -> SparseErrorPattern {
+        let mut pattern = SparseErrorPattern::new();
+        for position in positions.iter() {
+            pattern.add(position.clone(), operator);
+        }
+        pattern
+    };
+    let standard_top: Vec<Position> = (1..simulator.horizontal).step_by(2).map(|j| pos!(top_t, 1, j)).collect();
+    let standard_left: Vec<Position> = (1..simulator.vertical).step_by(2).map(|i| pos!(top_t, i, 1)).collect();
+    let (dp, dn) = (code_size.di, code_size.dj);
+    let rotated_top: Vec<Position> = (0..dn).map(|delta| pos!(top_t, dn-delta, 1+delta)).collect();
+    let rotated_left: Vec<Position> = (0..dp).map(|delta| pos!(top_t, dn+delta, 1+delta)).collect();
+    match simulator.code_type {
+        CodeType::StandardPlanarCode => Some(vec![operator_on(&standard_top, X), operator_on(&standard_left, Z)]),
+        CodeType::RotatedPlanarCode => Some(vec![operator_on(&rotated_top, X), operator_on(&rotated_left, Z)]),
+        CodeType::StandardTailoredCode => Some(vec![operator_on(&standard_top, X), operator_on(&standard_left, Y)]),
+        CodeType::RotatedTailoredCode | CodeType::RotatedTailoredCodeBellInit => {
+            Some(vec![operator_on(&rotated_top, X), operator_on(&rotated_left, Y)])
+        },
+        CodeType::PeriodicRotatedTailoredCode => {
+            let periodic_top: Vec<Position> = (0..dn).map(|delta| pos!(top_t, dn-delta, delta)).collect();
+            let periodic_left: Vec<Position> = (0..dp).map(|delta| pos!(top_t, dn+delta, delta)).collect();
+            Some(vec![operator_on(&periodic_top, X), operator_on(&periodic_left, X), operator_on(&periodic_top, Y), operator_on(&periodic_left, Y)])
+        },
+        CodeType::StandardToricCode => {
+            let toric_top: Vec<Position> = (0..simulator.horizontal).step_by(2).map(|j| pos!(top_t, 0, j)).collect();
+            let toric_left: Vec<Position> = (0..simulator.vertical).step_by(2).map(|i| pos!(top_t, i, 0)).collect();
+            Some(vec![operator_on(&standard_top, X), operator_on(&toric_left, X), operator_on(&standard_left, Z), operator_on(&toric_top, Z)])
+        },
+        CodeType::StandardXZZXCode => Some(vec![operator_on(&standard_top, Z), operator_on(&standard_left, X)]),
+        CodeType::RotatedXZZXCode => {
+            // every other qubit along the boundary is Hadamard-rotated, so the operators alternate between Z and X
+            let deformed_on = |positions: &Vec<Position>, operator: ErrorType, rotated_operator: ErrorType| -> SparseErrorPattern {
+                let mut pattern = SparseErrorPattern::new();
+                for (delta, position) in positions.iter().enumerate() {
+                    pattern.add(position.clone(), if delta % 2 == 1 { rotated_operator } else { operator });
+                }
+                pattern
+            };
+            Some(vec![deformed_on(&rotated_top, Z, X), deformed_on(&rotated_left, X, Z)])
+        },
+        CodeType::Customized => simulator.customized_logical_operators.as_ref().map(|operators| operators.at_layer(top_t)),
+    }
+}
+
+/// a representative of the logical operator on the data qubits at the top layer, which commutes with all the stabilizers;
+/// only codes encoding a single logical qubit are supported, return None otherwise
+pub fn code_builder_transversal_logical(simulator: &Simulator, logical: LogicalOp) -> Option<SparseErrorPattern> {
+    let mut logical_operators = code_builder_logical_operators(simulator)?;
+    if logical_operators.len() != 2 {
+        return None
+    }
+    // logical X error is detected by the first operator and logical Z error by the second one
+    let operator = match logical {
+        LogicalOp::X => logical_operators.swap_remove(0),
+        LogicalOp::Z => logical_operators.swap_remove(1),
+    };
+    Some(operator)
+}
+
 /// check if correction indeed recover all stabilizer measurements (this is expensive for runtime)
 #[allow(dead_code)]
 pub fn code_builder_sanity_check_correction(simulator: &mut Simulator, correction: &SparseCorrection) -> Result<(), Vec<Position>> {
@@ -1240,7 +1558,7 @@ pub fn code_builder_sanity_check_correction(simulator: &mut Simulator, correctio
     let mut violating_positions = Vec::new();
     simulator_iter_real!(simulator, position, node, t => top_t, {
         if node.gate_type.is_measurement() {
-            let minus_one = node.gate_type.stabilizer_measurement(&node.propagated);
+            let minus_one = simulator.stabilizer_outcome(position);
             if minus_one {
                 violating_positions.push(position.clone());
             }
@@ -1263,6 +1581,34 @@ pub fn code_builder_sanity_check_correction(simulator: &mut Simulator, correctio
 
 }
 
+/// validate the correction through a path independent of [`code_builder_validate_correction`], to catch bugs in the validation itself:
+/// the corrected errors must not trigger any stabilizer of the final round (see [`code_builder_sanity_check_correction`]), and the logical
+/// class is computed as the anticommutation of the residual errors with the operators of [`code_builder_logical_operators`];
+/// only codes encoding a single logical qubit are supported
+pub fn code_builder_cross_check_correction(simulator: &mut Simulator, correction: &SparseCorrection) -> Result<(bool, bool), String> {
+    let logical_operators = code_builder_logical_operators(simulator).ok_or(format!("logical operators unknown for code type {:?}", simulator.code_type))?;
+    if logical_operators.len() != 2 {
+        return Err(format!("cross check only supports codes encoding a single logical qubit, but there are {} logical operators", logical_operators.len()))
+    }
+    let top_t = simulator.height - 1;
+    let correction = if correction.is_at_layer(top_t) { correction.clone() } else { correction.normalize_to_layer(simulator, top_t) };
+    code_builder_sanity_check_correction(simulator, &correction).map_err(|violating_positions| {
+        format!("the corrected errors still trigger the stabilizers {:?}", violating_positions.iter().map(|position| position.to_string()).collect::<Vec<_>>())
+    })?;
+    let logical_classes: Vec<bool> = logical_operators.iter().map(|logical_operator| {
+        let mut parity = false;
+        for (position, operator) in logical_operator.iter() {
+            let mut residual = simulator.get_node_unwrap(position).propagated;
+            if let Some(error) = correction.get(position) {
+                residual = residual.multiply(error);
+            }
+            parity ^= residual.anticommute(operator);
+        }
+        parity
+    }).collect();
+    Ok((logical_classes[0], logical_classes[1]))
+}
+
 #[cfg(feature="python_binding")]
 #[pyfunction]
 pub(crate) fn register(py: Python<'_>, m: &PyModule) -> PyResult<()> {
@@ -1428,6 +1774,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn code_builder_default_measurement_cycles() {  // cargo test code_builder_default_measurement_cycles -- --nocapture
+        let noisy_measurements = 2;
+        for code_type in CodeType::value_variants().iter() {
+            let measurement_cycles = match code_type.default_measurement_cycles() {
+                Some(measurement_cycles) => measurement_cycles,
+                None => continue,  // the circuit is provided by the user
+            };
+            let d = if *code_type == CodeType::PeriodicRotatedTailoredCode { 4 } else { 5 };
+            let simulator = Simulator::new(*code_type, CodeSize::new(noisy_measurements, d, d));
+            code_builder_sanity_check(&simulator).unwrap();
+            assert_eq!(simulator.measurement_cycles, measurement_cycles, "{:?}", code_type);
+            assert_eq!(simulator.height, measurement_cycles * (noisy_measurements + 1) + 1, "{:?}", code_type);
+            // the stabilizers are only measured at the end of the measurement cycles
+            simulator_iter!(simulator, position, node, {
+                assert!(!node.gate_type.is_measurement() || position.t.is_multiple_of(measurement_cycles), "{:?} {}", code_type, position);
+            });
+        }
+    }
+
     #[test]
     fn code_builder_periodic_rotated_tailored_code() {  // cargo test code_builder_periodic_rotated_tailored_code -- --nocapture
         let di = 7;
@@ -1457,6 +1823,379 @@ mod tests {
             assert_measurement!(simulator, [(pos!(0, 8, 12), Z)], [pos!(6, 0, 5), pos!(6, 1, 4), pos!(6, 7, 12), pos!(6, 8, 11)]);
             assert_measurement!(simulator, [(pos!(0, 8, 12), Y)], [pos!(6, 0, 5), pos!(6, 8, 11)]);
         }
+        // each logical qubit is validated by a pair of anticommuting logical operators: X on top with Y on left, and X on left with Y on top
+        let logical_operators = code_builder_logical_operators(&simulator).unwrap();
+        let top_t = simulator.height - 1;
+        let mut data_positions = Vec::new();
+        simulator_iter_real!(simulator, position, node, t => top_t, if node.qubit_type == QubitType::Data {
+            data_positions.push(position.clone());
+        });
+        simulator.clear_all_errors();
+        simulator.propagate_errors();
+        for position in data_positions.iter() {
+            for error in [X, Z, Y] {
+                let mut correction = SparseCorrection::new();
+                correction.add(position.clone(), error);
+                let mut error_pattern = SparseErrorPattern::new();
+                error_pattern.add(position.clone(), error);
+                let anticommute = |index: usize| logical_operators[index].anticommute(&error_pattern);
+                let (first, second) = ((anticommute(0), anticommute(3)), (anticommute(1), anticommute(2)));
+                assert_eq!(code_builder_validate_correction_multi(&mut simulator, &correction), Some((first, second)));
+                assert_eq!(code_builder_validate_correction(&mut simulator, &correction), Some((first.0 || second.0, first.1 || second.1)));
+                assert_eq!(simulator.validate_correction_multi(&correction), (first, second));
+            }
+        }
+    }
+
+    #[test]
+    fn code_builder_rotated_xzzx_code() {  // cargo test code_builder_rotated_xzzx_code -- --nocapture
+        let di = 5;
+        let dj = 3;
+        let noisy_measurements = 3;
+        let mut simulator = Simulator::new(CodeType::RotatedXZZXCode, CodeSize::new(noisy_measurements, di, dj));
+        code_builder_sanity_check(&simulator).unwrap();
+        {  // count how many nodes
+            let mut nodes_count = 0;
+            let mut virtual_nodes_count = 0;
+            let mut data_count = 0;
+            simulator_iter!(simulator, position, node, t => 0, {
+                nodes_count += 1;
+                if node.is_virtual {
+                    virtual_nodes_count += 1;
+                }
+                if node.qubit_type == QubitType::Data {
+                    data_count += 1;
+                }
+            });
+            assert_eq!(data_count, di * dj);
+            // the stabilizers sit on the (di+1) x (dj+1) grid between the data qubits, of which di * dj - 1 are real
+            assert_eq!(nodes_count, di * dj + (di + 1) * (dj + 1));
+            assert_eq!(virtual_nodes_count, (di + 1) * (dj + 1) - (di * dj - 1));
+        }
+        {  // check individual nodes
+            {
+                let node = simulator.get_node_unwrap(&pos!(0, 0, 3));
+                assert_eq!(node.qubit_type, QubitType::StabXZZXLogicalX);
+                assert_eq!(node.gate_type, GateType::MeasureX);
+                assert!(node.is_virtual);
+            }
+            {
+                let node = simulator.get_node_unwrap(&pos!(0, 3, 0));
+                assert_eq!(node.qubit_type, QubitType::StabXZZXLogicalZ);
+                assert_eq!(node.gate_type, GateType::MeasureX);
+                assert!(node.is_virtual);
+            }
+            {
+                let node = simulator.get_node_unwrap(&pos!(0, 4, 4));
+                assert_eq!(node.qubit_type, QubitType::Data);
+                assert_eq!(node.gate_type, GateType::None);
+                assert!(!node.is_virtual);
+            }
+            {
+                let node = simulator.get_node_unwrap(&pos!(0, 3, 4));
+                assert_eq!(node.qubit_type, QubitType::StabXZZXLogicalZ);
+                assert_eq!(node.gate_type, GateType::MeasureX);
+                assert!(!node.is_virtual);
+            }
+            {
+                let node = simulator.get_node_unwrap(&pos!(1, 4, 3));
+                assert_eq!(node.qubit_type, QubitType::StabXZZXLogicalX);
+                assert_eq!(node.gate_type, GateType::InitializeX);
+                assert!(!node.is_virtual);
+            }
+        }
+        {  // check gate sequence
+            {  // data qubit at center
+                let node = simulator.get_node_unwrap(&pos!(1, 4, 4));
+                assert_eq!(node.gate_type, GateType::None);
+                let node = simulator.get_node_unwrap(&pos!(2, 4, 4));
+                assert_eq!(node.gate_type, GateType::CZGate);
+                assert_eq!(node.gate_peer.as_ref().map(|x| (**x).clone()), Some(pos!(2, 5, 4)));
+                let node = simulator.get_node_unwrap(&pos!(3, 4, 4));
+                assert_eq!(node.gate_type, GateType::CXGateTarget);
+                assert_eq!(node.gate_peer.as_ref().map(|x| (**x).clone()), Some(pos!(3, 4, 5)));
+                let node = simulator.get_node_unwrap(&pos!(4, 4, 4));
+                assert_eq!(node.gate_type, GateType::CXGateTarget);
+                assert_eq!(node.gate_peer.as_ref().map(|x| (**x).clone()), Some(pos!(4, 4, 3)));
+                let node = simulator.get_node_unwrap(&pos!(5, 4, 4));
+                assert_eq!(node.gate_type, GateType::CZGate);
+                assert_eq!(node.gate_peer.as_ref().map(|x| (**x).clone()), Some(pos!(5, 3, 4)));
+            }
+            {  // data qubit at the top corner, next to virtual stabilizers
+                let node = simulator.get_node_unwrap(&pos!(2, 1, 3));
+                assert!(!node.is_peer_virtual);
+                assert_eq!(node.gate_type, GateType::CZGate);
+                assert_eq!(node.gate_peer.as_ref().map(|x| (**x).clone()), Some(pos!(2, 2, 3)));
+                let node = simulator.get_node_unwrap(&pos!(3, 1, 3));
+                assert!(node.is_peer_virtual);
+                assert_eq!(node.gate_type, GateType::CXGateTarget);
+                assert_eq!(node.gate_peer.as_ref().map(|x| (**x).clone()), Some(pos!(3, 1, 4)));
+                let node = simulator.get_node_unwrap(&pos!(5, 1, 3));
+                assert!(node.is_peer_virtual);
+                assert_eq!(node.gate_type, GateType::CZGate);
+                assert_eq!(node.gate_peer.as_ref().map(|x| (**x).clone()), Some(pos!(5, 0, 3)));
+            }
+        }
+        {  // check stabilizer measurements: each stabilizer measures Z on the data qubits above and below and X on the left and right
+            // data qubit at center
+            assert_measurement!(simulator, [(pos!(0, 4, 4), X)], [pos!(6, 3, 4), pos!(6, 5, 4)]);
+            assert_measurement!(simulator, [(pos!(0, 4, 4), Z)], [pos!(6, 4, 3), pos!(6, 4, 5)]);
+            assert_measurement!(simulator, [(pos!(0, 4, 4), Y)], [pos!(6, 3, 4), pos!(6, 4, 3), pos!(6, 4, 5), pos!(6, 5, 4)]);
+            // data qubit at corners
+            assert_measurement!(simulator, [(pos!(0, 1, 3), X)], [pos!(6, 2, 3)]);
+            assert_measurement!(simulator, [(pos!(0, 1, 3), Z)], [pos!(6, 1, 2)]);
+            assert_measurement!(simulator, [(pos!(0, 1, 3), Y)], [pos!(6, 1, 2), pos!(6, 2, 3)]);
+            assert_measurement!(simulator, [(pos!(0, 3, 1), X)], [pos!(6, 4, 1)]);
+            assert_measurement!(simulator, [(pos!(0, 3, 1), Z)], [pos!(6, 3, 2)]);
+            assert_measurement!(simulator, [(pos!(0, 3, 1), Y)], [pos!(6, 3, 2), pos!(6, 4, 1)]);
+            assert_measurement!(simulator, [(pos!(0, 7, 5), X)], [pos!(6, 6, 5)]);
+            assert_measurement!(simulator, [(pos!(0, 7, 5), Z)], [pos!(6, 7, 6)]);
+            // data qubit at boundary
+            assert_measurement!(simulator, [(pos!(0, 6, 6), X)], [pos!(6, 5, 6), pos!(6, 7, 6)]);
+            assert_measurement!(simulator, [(pos!(0, 6, 6), Z)], [pos!(6, 6, 5)]);
+            assert_measurement!(simulator, [(pos!(0, 6, 6), Y)], [pos!(6, 5, 6), pos!(6, 6, 5), pos!(6, 7, 6)]);
+            assert_measurement!(simulator, [(pos!(0, 2, 2), X)], [pos!(6, 1, 2), pos!(6, 3, 2)]);
+            assert_measurement!(simulator, [(pos!(0, 2, 2), Z)], [pos!(6, 2, 3)]);
+            // stabilizer measurement error: both kinds of stabilizers are measured in the X basis
+            assert_measurement!(simulator, [(pos!(5, 3, 4), X)], []);
+            assert_measurement!(simulator, [(pos!(5, 3, 4), Z)], [pos!(6, 3, 4), pos!(12, 3, 4)]);
+            assert_measurement!(simulator, [(pos!(5, 4, 3), Y)], [pos!(6, 4, 3), pos!(12, 4, 3)]);
+        }
+        {  // validate the correction against the logical operators
+            let logical_operators = code_builder_logical_operators(&simulator).unwrap();
+            let top_t = simulator.height - 1;
+            let mut data_positions = Vec::new();
+            simulator_iter_real!(simulator, position, node, t => top_t, if node.qubit_type == QubitType::Data {
+                data_positions.push(position.clone());
+            });
+            simulator.clear_all_errors();
+            simulator.propagate_errors();
+            for position in data_positions.iter() {
+                for error in [X, Z, Y] {
+                    let mut correction = SparseCorrection::new();
+                    correction.add(position.clone(), error);
+                    let mut error_pattern = SparseErrorPattern::new();
+                    error_pattern.add(position.clone(), error);
+                    let expected = (logical_operators[0].anticommute(&error_pattern), logical_operators[1].anticommute(&error_pattern));
+                    assert_eq!(code_builder_validate_correction(&mut simulator, &correction), Some(expected));
+                }
+            }
+            // the logical operators themselves are undetectable errors that flip exactly one logical qubit
+            for (index, logical_operator) in logical_operators.iter().enumerate() {
+                let errors: Vec<(Position, ErrorType)> = logical_operator.iter().map(|(position, error)| (pos!(0, position.i, position.j), *error)).collect();
+                assert_measurement!(simulator, errors, []);
+                let result = code_builder_validate_correction(&mut simulator, &SparseCorrection::new()).unwrap();
+                assert!(result.0 ^ result.1, "logical operator {} should flip exactly one logical qubit", index);
+            }
+        }
+    }
+
+    #[test]
+    fn code_builder_rotated_tailored_code() {  // cargo test code_builder_rotated_tailored_code -- --nocapture
+        let di = 5;
+        let dj = 3;
+        let noisy_measurements = 3;
+        let mut simulator = Simulator::new(CodeType::RotatedTailoredCode, CodeSize::new(noisy_measurements, di, dj));
+        code_builder_sanity_check(&simulator).unwrap();
+        {  // count how many nodes
+            let mut nodes_count = 0;
+            let mut virtual_nodes_count = 0;
+            let mut data_count = 0;
+            simulator_iter!(simulator, position, node, t => 0, {
+                nodes_count += 1;
+                if node.is_virtual {
+                    virtual_nodes_count += 1;
+                }
+                if node.qubit_type == QubitType::Data {
+                    data_count += 1;
+                }
+            });
+            assert_eq!(data_count, di * dj);
+            // the stabilizers sit on the (di+1) x (dj+1) grid between the data qubits, of which di * dj - 1 are real
+            assert_eq!(nodes_count, di * dj + (di + 1) * (dj + 1));
+            assert_eq!(virtual_nodes_count, (di + 1) * (dj + 1) - (di * dj - 1));
+        }
+        {  // check individual nodes
+            {
+                let node = simulator.get_node_unwrap(&pos!(0, 0, 3));
+                assert_eq!(node.qubit_type, QubitType::StabX);
+                assert_eq!(node.gate_type, GateType::MeasureX);
+                assert!(node.is_virtual);
+            }
+            {
+                let node = simulator.get_node_unwrap(&pos!(0, 1, 4));
+                assert_eq!(node.qubit_type, QubitType::StabY);
+                assert!(node.is_virtual);
+            }
+            {
+                let node = simulator.get_node_unwrap(&pos!(0, 3, 4));
+                assert_eq!(node.qubit_type, QubitType::StabY);
+                assert_eq!(node.gate_type, GateType::MeasureX);
+                assert!(!node.is_virtual);
+            }
+            {
+                let node = simulator.get_node_unwrap(&pos!(1, 4, 3));
+                assert_eq!(node.qubit_type, QubitType::StabX);
+                assert_eq!(node.gate_type, GateType::InitializeX);
+                assert!(!node.is_virtual);
+            }
+        }
+        {  // check gate sequence: the data qubit is the target of CY gates with the Y stabilizers and CX gates with the X stabilizers
+            let node = simulator.get_node_unwrap(&pos!(2, 4, 4));
+            assert_eq!(node.gate_type, GateType::CYGateTarget);
+            assert_eq!(node.gate_peer.as_ref().map(|x| (**x).clone()), Some(pos!(2, 5, 4)));
+            let node = simulator.get_node_unwrap(&pos!(3, 4, 4));
+            assert_eq!(node.gate_type, GateType::CXGateTarget);
+            assert_eq!(node.gate_peer.as_ref().map(|x| (**x).clone()), Some(pos!(3, 4, 3)));
+            let node = simulator.get_node_unwrap(&pos!(4, 4, 4));
+            assert_eq!(node.gate_type, GateType::CXGateTarget);
+            assert_eq!(node.gate_peer.as_ref().map(|x| (**x).clone()), Some(pos!(4, 4, 5)));
+            let node = simulator.get_node_unwrap(&pos!(5, 4, 4));
+            assert_eq!(node.gate_type, GateType::CYGateTarget);
+            assert_eq!(node.gate_peer.as_ref().map(|x| (**x).clone()), Some(pos!(5, 3, 4)));
+        }
+        {  // check stabilizer measurements: a single Y error is only detected by the X stabilizers
+            // data qubit at center
+            assert_measurement!(simulator, [(pos!(0, 4, 4), X)], [pos!(6, 3, 4), pos!(6, 5, 4)]);
+            assert_measurement!(simulator, [(pos!(0, 4, 4), Y)], [pos!(6, 4, 3), pos!(6, 4, 5)]);
+            assert_measurement!(simulator, [(pos!(0, 4, 4), Z)], [pos!(6, 3, 4), pos!(6, 4, 3), pos!(6, 4, 5), pos!(6, 5, 4)]);
+            // data qubit at corners
+            assert_measurement!(simulator, [(pos!(0, 1, 3), X)], [pos!(6, 1, 2)]);
+            assert_measurement!(simulator, [(pos!(0, 1, 3), Y)], [pos!(6, 2, 3)]);
+            assert_measurement!(simulator, [(pos!(0, 1, 3), Z)], [pos!(6, 1, 2), pos!(6, 2, 3)]);
+            assert_measurement!(simulator, [(pos!(0, 3, 1), Y)], [pos!(6, 4, 1)]);
+            assert_measurement!(simulator, [(pos!(0, 7, 5), Y)], [pos!(6, 6, 5)]);
+            // data qubit at boundary
+            assert_measurement!(simulator, [(pos!(0, 6, 6), X)], [pos!(6, 5, 6), pos!(6, 7, 6)]);
+            assert_measurement!(simulator, [(pos!(0, 6, 6), Y)], [pos!(6, 6, 5)]);
+            assert_measurement!(simulator, [(pos!(0, 5, 3), Y)], [pos!(6, 4, 3), pos!(6, 6, 3)]);
+            assert_measurement!(simulator, [(pos!(0, 3, 5), Y)], [pos!(6, 2, 5), pos!(6, 4, 5)]);
+            // stabilizer measurement error: both kinds of stabilizers are measured in the X basis
+            assert_measurement!(simulator, [(pos!(5, 3, 4), X)], []);
+            assert_measurement!(simulator, [(pos!(5, 3, 4), Z)], [pos!(6, 3, 4), pos!(12, 3, 4)]);
+            assert_measurement!(simulator, [(pos!(5, 4, 3), Y)], [pos!(6, 4, 3), pos!(12, 4, 3)]);
+        }
+        {  // validate the correction against the logical operators
+            let logical_operators = code_builder_logical_operators(&simulator).unwrap();
+            let top_t = simulator.height - 1;
+            let mut data_positions = Vec::new();
+            simulator_iter_real!(simulator, position, node, t => top_t, if node.qubit_type == QubitType::Data {
+                data_positions.push(position.clone());
+            });
+            simulator.clear_all_errors();
+            simulator.propagate_errors();
+            for position in data_positions.iter() {
+                for error in [X, Z, Y] {
+                    let mut correction = SparseCorrection::new();
+                    correction.add(position.clone(), error);
+                    let mut error_pattern = SparseErrorPattern::new();
+                    error_pattern.add(position.clone(), error);
+                    let expected = (logical_operators[0].anticommute(&error_pattern), logical_operators[1].anticommute(&error_pattern));
+                    assert_eq!(code_builder_validate_correction(&mut simulator, &correction), Some(expected));
+                }
+            }
+            // the logical operators themselves are undetectable errors that flip exactly one logical qubit
+            for (index, logical_operator) in logical_operators.iter().enumerate() {
+                let errors: Vec<(Position, ErrorType)> = logical_operator.iter().map(|(position, error)| (pos!(0, position.i, position.j), *error)).collect();
+                assert_measurement!(simulator, errors, []);
+                let result = code_builder_validate_correction(&mut simulator, &SparseCorrection::new()).unwrap();
+                assert!(result.0 ^ result.1, "logical operator {} should flip exactly one logical qubit", index);
+            }
+        }
+    }
+
+    #[test]
+    fn code_builder_standard_toric_code() {  // cargo test code_builder_standard_toric_code -- --nocapture
+        let di = 3;
+        let dj = 4;
+        let noisy_measurements = 3;
+        let mut simulator = Simulator::new(CodeType::StandardToricCode, CodeSize::new(noisy_measurements, di, dj));
+        code_builder_sanity_check(&simulator).unwrap();
+        {  // count how many nodes: every position is present and there is no virtual node
+            let mut nodes_count = 0;
+            let mut virtual_nodes_count = 0;
+            simulator_iter!(simulator, position, node, t => 0, {
+                nodes_count += 1;
+                if node.is_virtual {
+                    virtual_nodes_count += 1;
+                }
+            });
+            assert_eq!(nodes_count, (2 * di) * (2 * dj));
+            assert_eq!(virtual_nodes_count, 0);
+        }
+        {  // check gate sequence across the periodic boundary
+            let node = simulator.get_node_unwrap(&pos!(2, 0, 0));
+            assert_eq!(node.gate_type, GateType::CXGateControl);
+            assert_eq!(node.gate_peer.as_ref().map(|x| (**x).clone()), Some(pos!(2, 1, 0)));
+            let node = simulator.get_node_unwrap(&pos!(3, 0, 0));
+            assert_eq!(node.gate_type, GateType::CXGateTarget);
+            assert_eq!(node.gate_peer.as_ref().map(|x| (**x).clone()), Some(pos!(3, 0, 2 * dj - 1)));
+            let node = simulator.get_node_unwrap(&pos!(5, 0, 0));
+            assert_eq!(node.gate_type, GateType::CXGateControl);
+            assert_eq!(node.gate_peer.as_ref().map(|x| (**x).clone()), Some(pos!(5, 2 * di - 1, 0)));
+        }
+        {  // check stabilizer measurements
+            // data qubit at the corner wraps around in both directions
+            assert_measurement!(simulator, [(pos!(0, 0, 0), X)], [pos!(6, 1, 0), pos!(6, 5, 0)]);
+            assert_measurement!(simulator, [(pos!(0, 0, 0), Z)], [pos!(6, 0, 1), pos!(6, 0, 7)]);
+            assert_measurement!(simulator, [(pos!(0, 0, 0), Y)], [pos!(6, 0, 1), pos!(6, 0, 7), pos!(6, 1, 0), pos!(6, 5, 0)]);
+            assert_measurement!(simulator, [(pos!(0, 5, 7), X)], [pos!(6, 5, 0), pos!(6, 5, 6)]);
+            assert_measurement!(simulator, [(pos!(0, 5, 7), Z)], [pos!(6, 0, 7), pos!(6, 4, 7)]);
+            // data qubit at center
+            assert_measurement!(simulator, [(pos!(0, 2, 2), X)], [pos!(6, 1, 2), pos!(6, 3, 2)]);
+            assert_measurement!(simulator, [(pos!(0, 2, 2), Z)], [pos!(6, 2, 1), pos!(6, 2, 3)]);
+            // Z stabilizer measurement error
+            assert_measurement!(simulator, [(pos!(5, 1, 0), X)], [pos!(6, 1, 0), pos!(12, 1, 0)]);
+            assert_measurement!(simulator, [(pos!(5, 1, 0), Z)], []);  // not sensitive to Z error
+            // X stabilizer measurement error
+            assert_measurement!(simulator, [(pos!(5, 0, 7), X)], []);  // not sensitive to X error
+            assert_measurement!(simulator, [(pos!(5, 0, 7), Z)], [pos!(6, 0, 7), pos!(12, 0, 7)]);
+        }
+        {  // a loop of errors wrapping around the torus triggers no defect but flips a logical qubit
+            let empty_correction = SparseCorrection::new();
+            let vertical_z_loop: Vec<(Position, ErrorType)> = (1..2*di).step_by(2).map(|i| (pos!(0, i, 1), Z)).collect();
+            assert_measurement!(simulator, vertical_z_loop, []);
+            assert_eq!(code_builder_validate_correction(&mut simulator, &empty_correction), Some((true, false)));
+            assert_eq!(code_builder_validate_correction_multi(&mut simulator, &empty_correction), Some(((true, false), (false, false))));
+            let horizontal_z_loop: Vec<(Position, ErrorType)> = (0..2*dj).step_by(2).map(|j| (pos!(0, 2, j), Z)).collect();
+            assert_measurement!(simulator, horizontal_z_loop, []);
+            assert_eq!(code_builder_validate_correction(&mut simulator, &empty_correction), Some((true, false)));
+            assert_eq!(code_builder_validate_correction_multi(&mut simulator, &empty_correction), Some(((false, false), (true, false))));
+            let horizontal_x_loop: Vec<(Position, ErrorType)> = (1..2*dj).step_by(2).map(|j| (pos!(0, 3, j), X)).collect();
+            assert_measurement!(simulator, horizontal_x_loop, []);
+            assert_eq!(code_builder_validate_correction(&mut simulator, &empty_correction), Some((false, true)));
+            assert_eq!(code_builder_validate_correction_multi(&mut simulator, &empty_correction), Some(((false, true), (false, false))));
+            let vertical_x_loop: Vec<(Position, ErrorType)> = (0..2*di).step_by(2).map(|i| (pos!(0, i, 4), X)).collect();
+            assert_measurement!(simulator, vertical_x_loop, []);
+            assert_eq!(code_builder_validate_correction(&mut simulator, &empty_correction), Some((false, true)));
+            assert_eq!(code_builder_validate_correction_multi(&mut simulator, &empty_correction), Some(((false, false), (false, true))));
+            // a contractible loop is a stabilizer
+            assert_measurement!(simulator, [(pos!(0, 1, 1), Z), (pos!(0, 1, 3), Z), (pos!(0, 0, 2), Z), (pos!(0, 2, 2), Z)], []);
+            assert_eq!(code_builder_validate_correction(&mut simulator, &empty_correction), Some((false, false)));
+            assert_measurement!(simulator, [(pos!(0, 0, 6), X), (pos!(0, 0, 0), X), (pos!(0, 1, 7), X), (pos!(0, 5, 7), X)], []);
+            assert_eq!(code_builder_validate_correction(&mut simulator, &empty_correction), Some((false, false)));
+        }
+        // the toric code encodes 2 logical qubits, and the logical operators agree with the validation on single-qubit errors
+        assert_eq!(simulator.logical_operator_count(), 2);
+        let logical_operators = code_builder_logical_operators(&simulator).unwrap();
+        let top_t = simulator.height - 1;
+        let mut data_positions = Vec::new();
+        simulator_iter_real!(simulator, position, node, t => top_t, if node.qubit_type == QubitType::Data {
+            data_positions.push(position.clone());
+        });
+        simulator.clear_all_errors();
+        simulator.propagate_errors();
+        for position in data_positions.iter() {
+            for error in [X, Z, Y] {
+                let mut correction = SparseCorrection::new();
+                correction.add(position.clone(), error);
+                let mut error_pattern = SparseErrorPattern::new();
+                error_pattern.add(position.clone(), error);
+                let anticommute = |index: usize| logical_operators[index].anticommute(&error_pattern);
+                let expected = (anticommute(0) || anticommute(1), anticommute(2) || anticommute(3));
+                assert_eq!(code_builder_validate_correction(&mut simulator, &correction), Some(expected));
+            }
+        }
     }
 
     #[test]
@@ -1563,4 +2302,201 @@ mod tests {
         visualizer.add_component(&simulator).unwrap();
     }
 
+    #[test]
+    fn code_builder_logical_operator_count() {  // cargo test code_builder_logical_operator_count -- --nocapture
+        let noisy_measurements = 0;
+        for code_type in [CodeType::StandardPlanarCode, CodeType::RotatedPlanarCode, CodeType::StandardXZZXCode, CodeType::RotatedXZZXCode
+                , CodeType::StandardTailoredCode, CodeType::RotatedTailoredCode] {
+            let simulator = Simulator::new(code_type, CodeSize::new(noisy_measurements, 5, 5));
+            assert_eq!(simulator.logical_operator_count(), 1, "planar code {:?} encodes 1 logical qubit", code_type);
+        }
+        // periodic boundary encodes 2 logical qubits like the toric code
+        let simulator = Simulator::new(CodeType::PeriodicRotatedTailoredCode, CodeSize::new(noisy_measurements, 4, 4));
+        assert_eq!(simulator.logical_operator_count(), 2);
+        // the logical operators must agree with `code_builder_validate_correction` on single-qubit errors
+        for code_type in [CodeType::StandardPlanarCode, CodeType::RotatedPlanarCode, CodeType::StandardXZZXCode, CodeType::RotatedXZZXCode] {
+            let mut simulator = Simulator::new(code_type, CodeSize::new(noisy_measurements, 5, 3));
+            let logical_operators = code_builder_logical_operators(&simulator).unwrap();
+            let top_t = simulator.height - 1;
+            let mut data_positions = Vec::new();
+            simulator_iter_real!(simulator, position, node, t => top_t, if node.qubit_type == QubitType::Data {
+                data_positions.push(position.clone());
+            });
+            for position in data_positions.iter() {
+                for error in [X, Z, Y] {
+                    let mut correction = SparseCorrection::new();
+                    correction.add(position.clone(), error);
+                    let mut error_pattern = SparseErrorPattern::new();
+                    error_pattern.add(position.clone(), error);
+                    let expected = (logical_operators[0].anticommute(&error_pattern), logical_operators[1].anticommute(&error_pattern));
+                    assert_eq!(code_builder_validate_correction(&mut simulator, &correction), Some(expected));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn code_builder_customized_logical_operators() {  // cargo test code_builder_customized_logical_operators -- --nocapture
+        // a user-built circuit that happens to be a planar code, whose logical operators are repetition-code-like lines of a single Pauli
+        let mut builtin = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(0, 3, 3));
+        let mut simulator = builtin.clone();
+        simulator.code_type = CodeType::Customized;
+        let filepath = std::env::temp_dir().join("qecp_code_builder_customized_logical_operators.json").to_str().unwrap().to_string();
+        std::fs::write(&filepath, json!({
+            "logical_x": ["[0][1][1]", "[0][1][3]", "[0][1][5]"],
+            "logical_z": ["[0][1][1]", "[0][3][1]", "[0][5][1]"],
+        }).to_string()).unwrap();
+        let customized_logical_operators = CustomizedLogicalOperators::from_json_file(&filepath).unwrap();
+        assert_eq!(customized_logical_operators.sanity_check(&simulator), Ok(()));
+        simulator.customized_logical_operators = Some(customized_logical_operators);
+        assert_eq!(simulator.logical_operator_count(), 1);
+        // agree with the built-in validation on every single-qubit error
+        let top_t = simulator.height - 1;
+        let mut data_positions = Vec::new();
+        simulator_iter_real!(simulator, position, node, t => top_t, if node.qubit_type == QubitType::Data {
+            data_positions.push(position.clone());
+        });
+        for position in data_positions.iter() {
+            for error in [X, Z, Y] {
+                let mut correction = SparseCorrection::new();
+                correction.add(position.clone(), error);
+                assert_eq!(simulator.validate_correction(&correction), builtin.validate_correction(&correction));
+            }
+        }
+        // a full line of Z errors is a logical Z error
+        let mut correction = SparseCorrection::new();
+        for i in [1, 3, 5] {
+            correction.add(pos!(top_t, i, 1), Z);
+        }
+        assert_eq!(simulator.validate_correction(&correction), (true, false));
+        // logical operators must act on data qubits
+        let invalid = CustomizedLogicalOperators { logical_x: vec![pos!(0, 1, 2)], logical_z: vec![] };
+        assert!(invalid.sanity_check(&simulator).is_err());
+    }
+
+    #[test]
+    fn code_builder_validate_stabilizers() {  // cargo test code_builder_validate_stabilizers -- --nocapture
+        // a stabilizer applied as a correction must not be reported as a logical error, which requires the validation to check
+        // genuine logical operators that commute with all the stabilizers (e.g. the Clifford-deformed ones of the rotated XZZX code)
+        for code_type in [CodeType::StandardPlanarCode, CodeType::RotatedPlanarCode, CodeType::StandardXZZXCode, CodeType::RotatedXZZXCode
+                , CodeType::StandardTailoredCode, CodeType::RotatedTailoredCode] {
+            let mut simulator = Simulator::new(code_type, CodeSize::new(0, 5, 5));
+            let top_t = simulator.height - 1;
+            let mut stabilizers = Vec::new();
+            simulator_iter_real!(simulator, position, node, t => 0, if node.qubit_type != QubitType::Data && node.gate_type.is_measurement() {
+                // the Pauli stabilizing the initialized ancilla is kept on the ancilla and copied to each data qubit it interacts with
+                let ancilla_pauli = if node.gate_type == GateType::MeasureX { X } else { Z };
+                let mut stabilizer = SparseCorrection::new();
+                for t in 1..simulator.measurement_cycles {
+                    let ancilla = simulator.get_node_unwrap(&pos!(t, position.i, position.j));
+                    if ancilla.gate_type.is_two_qubit_gate() && !ancilla.is_peer_virtual {
+                        let peer = ancilla.gate_peer.as_ref().unwrap();
+                        stabilizer.add(pos!(top_t, peer.i, peer.j), ancilla.gate_type.propagate_peer(&ancilla_pauli));
+                    }
+                }
+                stabilizers.push((position.clone(), stabilizer));
+            });
+            assert!(!stabilizers.is_empty());
+            for (position, stabilizer) in stabilizers.iter() {
+                assert_eq!(code_builder_sanity_check_correction(&mut simulator, stabilizer), Ok(()), "{:?} {} should be a stabilizer", code_type, position);
+                assert_eq!(simulator.validate_correction(stabilizer), (false, false), "stabilizer {} is reported as a logical error in {:?}", position, code_type);
+            }
+        }
+    }
+
+    #[test]
+    fn code_builder_xzzx_biased_logical_channel() {  // cargo test code_builder_xzzx_biased_logical_channel -- --nocapture
+        use crate::noise_model::*;
+        use crate::noise_model_builder::*;
+        use crate::decoder_mwpm::*;
+        use crate::reproducible_rand::Xoroshiro128StarStar;
+        use crate::rand_core::SeedableRng;
+        use std::sync::Arc;
+        // pure Z errors only anticommute with the deformed logical operator on the left boundary of the XZZX code, so under high Z bias
+        // almost all the logical errors must be reported in the single channel `(false, true)`, and exactly all of them under pure Z noise
+        let dominant_channel = (false, true);
+        let shots = 2000;
+        for code_type in [CodeType::StandardXZZXCode, CodeType::RotatedXZZXCode] {
+            for bias_eta in [100., f64::INFINITY] {
+                let mut simulator = Simulator::new(code_type, CodeSize::new(0, 5, 5));
+                let mut noise_model = NoiseModel::new(&simulator);
+                NoiseModelBuilder::CodeCapacity.apply(&mut simulator, &mut noise_model, &json!({}), 0.15, bias_eta, 0.);
+                simulator.compress_error_rates(&mut noise_model);
+                let noise_model = Arc::new(noise_model);
+                let mut decoder = MWPMDecoder::new(&simulator, Arc::clone(&noise_model), &json!({"emmd": 16}), 1, false);
+                simulator.rng = Xoroshiro128StarStar::seed_from_u64(1717);
+                let mut channels = std::collections::BTreeMap::new();
+                for _ in 0..shots {
+                    simulator.generate_random_errors(&noise_model);
+                    let sparse_measurement = simulator.generate_sparse_measurement();
+                    if sparse_measurement.len() > 16 {  // too many defects for exact matching
+                        continue
+                    }
+                    let (correction, _) = decoder.decode(&sparse_measurement);
+                    *channels.entry(simulator.validate_correction(&correction)).or_insert(0) += 1;
+                }
+                println!("{:?} bias_eta = {}: {:?}", code_type, bias_eta, channels);
+                let dominant_failed = channels.get(&dominant_channel).cloned().unwrap_or(0);
+                let other_failed: usize = channels.iter().filter(|(channel, _)| **channel != (false, false) && **channel != dominant_channel)
+                    .map(|(_, count)| *count).sum();
+                assert!(dominant_failed > 0, "the dominant logical channel must be observed");
+                if bias_eta == f64::INFINITY {
+                    assert_eq!(other_failed, 0, "pure Z errors can only cause logical errors in the dominant channel");
+                } else {
+                    assert!(other_failed * 3 < dominant_failed, "logical errors must concentrate in the dominant channel");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn code_builder_idle_rounds() {  // cargo test code_builder_idle_rounds -- --nocapture
+        use crate::noise_model::*;
+        use crate::noise_model_builder::*;
+        use crate::model_graph::*;
+        use std::sync::Arc;
+        let noisy_measurements = 2;
+        let p = 0.001;
+        let mut time_like_probabilities = vec![];
+        for idle_rounds in [0, 1, 2, 4] {
+            let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(noisy_measurements, 3, 3));
+            let reference = simulator.clone();
+            code_builder_insert_idle_rounds(&mut simulator, idle_rounds).unwrap();
+            let measurement_cycles = simulator.measurement_cycles;
+            assert_eq!(measurement_cycles, 6 + idle_rounds);
+            assert_eq!(simulator.height, measurement_cycles * (noisy_measurements + 1) + 1);
+            simulator_iter!(simulator, position, node, {
+                let offset = position.t % measurement_cycles;
+                if offset >= 2 && offset < 2 + idle_rounds {
+                    assert_eq!(node.gate_type, GateType::None);
+                } else {  // the same gate as the original schedule
+                    let original_t = position.t / measurement_cycles * 6 + if offset <= 1 { offset } else { offset - idle_rounds };
+                    assert_eq!(node.gate_type, reference.get_node_unwrap(&pos!(original_t, position.i, position.j)).gate_type);
+                }
+            });
+            // a single data qubit error is still detected in the next measurement round
+            simulator.get_node_mut_unwrap(&pos!(measurement_cycles + 1, 2, 2)).error = X;
+            simulator.propagate_errors();
+            assert_eq!(simulator.generate_sparse_measurement().to_vec(), vec![pos!(2 * measurement_cycles, 1, 2), pos!(2 * measurement_cycles, 3, 2)]);
+            // the idle ancilla qubits suffer depolarizing noise after their initialization, accumulating on the time-like edges
+            let mut noise_model = NoiseModel::new(&simulator);
+            NoiseModelBuilder::DepolarizingNoise.apply(&mut simulator, &mut noise_model, &json!({}), p, 0.5, 0.);
+            simulator.compress_error_rates(&mut noise_model);
+            let mut model_graph = ModelGraph::new(&simulator);
+            model_graph.build(&mut simulator, Arc::new(noise_model), &WeightFunction::AutotuneImproved, 1, true, false);
+            let edge = &model_graph.get_node_unwrap(&pos!(measurement_cycles, 1, 2)).edges[&pos!(2 * measurement_cycles, 1, 2)];
+            println!("idle_rounds = {}: time-like edge probability {}", idle_rounds, edge.probability);
+            time_like_probabilities.push(edge.probability);
+        }
+        let idle_contribution = |index: usize| time_like_probabilities[index] - time_like_probabilities[0];
+        assert!(idle_contribution(1) > 0.);
+        for (index, idle_rounds) in [(2, 2.), (3, 4.)] {
+            let ratio = idle_contribution(index) / idle_contribution(1);
+            assert!((ratio - idle_rounds).abs() < 0.05 * idle_rounds, "idle noise should scale linearly with the idle rounds, ratio = {}", ratio);
+        }
+        // the idle rounds need repeated measurement cycles
+        let mut simulator = Simulator::new(CodeType::Customized, CodeSize::new(0, 3, 3));
+        assert!(code_builder_insert_idle_rounds(&mut simulator, 1).is_err());
+    }
+
 }
@@ -7,7 +7,7 @@ use super::simulator::*;
 use super::model_graph::*;
 use super::priority_queue::PriorityQueue;
 use super::float_ord::FloatOrd;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc};
 
 #[derive(Debug, Clone, Serialize)]
 pub struct CompleteModelGraph {
@@ -424,39 +424,33 @@ impl CompleteModelGraph {
                     self.precompute_dijkstra(position);
                 });
             } else {
-                // spawn `parallel` threads to compute in parallel
+                // spawn `parallel` threads to compute in parallel; each source is computed independently so the result doesn't depend on scheduling
+                let mut positions = Vec::new();
+                simulator_iter!(simulator, position, if self.is_node_exist(position) {
+                    positions.push(position.clone());
+                });
+                let positions = Arc::new(positions);
                 let mut handlers = Vec::new();
-                let mut instances = Vec::new();
-                let shared_simulator = Arc::new(simulator.clone());
-                for parallel_idx in 0..parallel {
-                    let instance = Arc::new(Mutex::new(self.clone()));
-                    let simulator = Arc::clone(&shared_simulator);
-                    let thread_idx = parallel_idx;
-                    instances.push(Arc::clone(&instance));
+                for thread_idx in 0..parallel {
+                    let mut instance = self.clone();
+                    let positions = Arc::clone(&positions);
                     handlers.push(std::thread::spawn(move || {
-                        let mut counter = 0;
-                        let mut instance = instance.lock().unwrap();
-                        simulator_iter!(simulator, position, if instance.is_node_exist(position) {
-                            if counter % parallel == thread_idx {  // only compute my part of share
-                                instance.precompute_dijkstra(position);
-                            }
-                            counter += 1;
-                        });
+                        let mut results = Vec::new();
+                        for position in positions.iter().skip(thread_idx).step_by(parallel) {  // only compute my part of share
+                            instance.precompute_dijkstra(position);
+                            let instance_node = instance.get_node_mut_unwrap(position);
+                            results.push((position.clone(), instance_node.precomputed.clone()));  // later sources still read its boundary
+                        }
+                        results
                     }));
                 }
-                for handler in handlers.drain(..) {
-                    handler.join().unwrap();
-                }
-                // move the data from instances (without additional large memory allocation)
-                let mut counter = 0;
-                simulator_iter!(simulator, position, if self.is_node_exist(position) {
-                    let instance = &instances[counter % parallel];
-                    let mut instance = instance.lock().unwrap();
+                // merge the per-source results in sorted order of the sources
+                let mut results: Vec<_> = handlers.drain(..).flat_map(|handler| handler.join().unwrap()).collect();
+                results.sort_by(|(position1, _), (position2, _)| position1.cmp(position2));
+                for (position, precomputed) in results.drain(..) {
                     let node = self.get_node_mut_unwrap(&position);
-                    let instance_node = instance.get_node_mut_unwrap(&position);
-                    node.precomputed = instance_node.precomputed.clone();
-                    counter += 1;
-                });
+                    node.precomputed = precomputed;
+                }
             }
             // it's safe to disable copying all complete graph edges
             for array in self.nodes.iter_mut() {
@@ -538,3 +532,60 @@ impl PriorityElement {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::code_builder::*;
+    use super::super::noise_model::*;
+    use std::hash::{Hash, Hasher};
+    use std::collections::hash_map::DefaultHasher;
+
+    fn build_complete_model_graph(d: usize, noisy_measurements: usize, parallel: usize) -> (Simulator, CompleteModelGraph, std::time::Duration) {
+        let p = 0.001;
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p / 3., p / 3., p / 3., 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        let mut model_graph = ModelGraph::new(&simulator);
+        model_graph.build(&mut simulator, Arc::new(noise_model), &WeightFunction::AutotuneImproved, 1, true, false);
+        let mut complete_model_graph = CompleteModelGraph::new(&simulator, Arc::new(model_graph));
+        let begin = std::time::Instant::now();
+        complete_model_graph.precompute(&simulator, true, parallel);
+        (simulator, complete_model_graph, begin.elapsed())
+    }
+
+    /// hash of the precomputed data only, because timestamps are internal states that depend on the order of Dijkstra runs
+    fn precomputed_hash(simulator: &Simulator, complete_model_graph: &CompleteModelGraph) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        simulator_iter!(simulator, position, if complete_model_graph.is_node_exist(position) {
+            let node = complete_model_graph.get_node_unwrap(position);
+            serde_json::to_string(&node.precomputed).unwrap().hash(&mut hasher);
+        });
+        hasher.finish()
+    }
+
+    #[test]
+    fn complete_model_graph_parallel_precompute_deterministic() {  // cargo test complete_model_graph_parallel_precompute_deterministic -- --nocapture
+        let (simulator, sequential, _) = build_complete_model_graph(5, 1, 1);
+        let expected_hash = precomputed_hash(&simulator, &sequential);
+        for parallel in [2, 3] {
+            let (simulator, complete_model_graph, _) = build_complete_model_graph(5, 1, parallel);
+            assert_eq!(precomputed_hash(&simulator, &complete_model_graph), expected_hash, "parallel = {}", parallel);
+        }
+    }
+
+    /// on an 8-core machine, the all-pairs Dijkstra at d = 15 should be at least 4x faster with 8 threads
+    #[test]
+    #[ignore]
+    fn complete_model_graph_parallel_precompute_benchmark() {  // cargo test --release complete_model_graph_parallel_precompute_benchmark -- --ignored --nocapture
+        let d = 15;
+        let (_, _, sequential_duration) = build_complete_model_graph(d, 4, 1);
+        println!("d = {}, parallel = 1: {:?}", d, sequential_duration);
+        for parallel in [2, 4, 8] {
+            let (_, _, duration) = build_complete_model_graph(d, 4, parallel);
+            println!("d = {}, parallel = {}: {:?}, speedup {:.2}x", d, parallel, duration, sequential_duration.as_secs_f64() / duration.as_secs_f64());
+        }
+    }
+
+}
@@ -533,6 +533,42 @@ mod tests {
         assert!(!logical_i && !logical_j);
     }
 
+    /// the code capacity threshold of MWPM decoder under independent bit-flip noise is about 10.3%, which the exact matching of fusion blossom
+    /// reproduces without blossom V
+    #[test]
+    fn fusion_decoder_code_capacity_threshold() {  // cargo test fusion_decoder_code_capacity_threshold -- --nocapture
+        use crate::reproducible_rand::Xoroshiro128StarStar;
+        use crate::rand_core::SeedableRng;
+        let logical_error_rate = |d: usize, p: f64| -> f64 {
+            let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(0, d, d));
+            let mut noise_model = NoiseModel::new(&simulator);
+            NoiseModelBuilder::CodeCapacity.apply(&mut simulator, &mut noise_model, &json!({}), p, f64::INFINITY, 0.);  // only Z errors
+            simulator.compress_error_rates(&mut noise_model);
+            let noise_model = Arc::new(noise_model);
+            let mut fusion_decoder = FusionDecoder::new(&Arc::new(simulator.clone()), Arc::clone(&noise_model), &json!({}), 1, false);
+            simulator.rng = Xoroshiro128StarStar::seed_from_u64(1710);
+            let repeats = 10000;
+            let mut qec_failed = 0;
+            for _ in 0..repeats {
+                simulator.generate_random_errors(&noise_model);
+                let (correction, _) = fusion_decoder.decode(&simulator.generate_sparse_measurement());
+                let (logical_i, logical_j) = simulator.validate_correction(&correction);
+                if logical_i || logical_j {
+                    qec_failed += 1;
+                }
+            }
+            qec_failed as f64 / repeats as f64
+        };
+        // the logical error rate decreases with the code distance below the threshold and increases above it
+        for (p, below_threshold) in [(0.085, true), (0.125, false)] {
+            let logical_error_rates: Vec<f64> = [3, 5, 7].iter().map(|&d| logical_error_rate(d, p)).collect();
+            println!("p = {}: {:?}", p, logical_error_rates);
+            for window in logical_error_rates.windows(2) {
+                assert_eq!(window[1] < window[0], below_threshold);
+            }
+        }
+    }
+
     #[test]
     fn fusion_decoder_debug_2() {  // cargo test fusion_decoder_debug_2 -- --nocapture
         let d = 7;
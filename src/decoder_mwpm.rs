@@ -9,8 +9,11 @@ use super::complete_model_graph::*;
 use super::serde_json;
 use std::sync::{Arc};
 use std::time::Instant;
+use std::collections::BTreeSet;
 use super::blossom_v;
+use super::exact_matching;
 use super::erasure_graph::*;
+use super::types::DecodingError;
 
 
 /// MWPM decoder, initialized and cloned for multiple threads
@@ -26,6 +29,10 @@ pub struct MWPMDecoder {
     pub config: MWPMDecoderConfig,
     /// an immutably shared simulator that is used to change model graph on the fly for correcting erasure errors
     pub simulator: Arc<Simulator>,
+    /// a simulator owned by each thread to classify the logical class of the matched corrections when `logical_prior` is set, so that
+    /// the shared simulator is never copied when decoding
+    #[serde(skip)]
+    scratch_simulator: Option<Simulator>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +51,25 @@ pub struct MWPMDecoderConfig {
     #[serde(alias = "ucp")]  // abbreviation
     #[serde(default = "mwpm_default_configs::use_combined_probability")]
     pub use_combined_probability: bool,
+    /// syndromes with no more than this number of defects are solved by the pure-Rust exact matcher instead of blossom V,
+    /// which gives the same matching weight without requiring the blossom V library; set to 0 to always use blossom V
+    #[serde(alias = "emmd")]  // abbreviation
+    #[serde(default = "mwpm_default_configs::exact_matching_max_defects")]
+    pub exact_matching_max_defects: usize,
+    /// prior probability that logical_i is flipped, e.g. soft information from a previous stage of the experiment; when set, the decoder
+    /// compares the best matching of each logical class after adding `ln((1-q)/q)` to the flipped one. Only syndromes solved by the exact
+    /// matcher (no more than `exact_matching_max_defects` defects) use the prior, larger ones fall back to the plain minimum-weight matching
+    #[serde(default)]
+    pub logical_prior: Option<f64>,
+    /// scale the weight of all boundary edges, where a value larger than 1 discourages matching defects to the boundary
+    #[serde(alias = "bwm")]  // abbreviation
+    #[serde(default = "mwpm_default_configs::boundary_weight_multiplier")]
+    pub boundary_weight_multiplier: f64,
+    /// split the boundary into a representative per physical boundary segment, so that a defect is matched to the segment that most likely
+    /// flipped it, see [`ModelGraph::split_boundary`]
+    #[serde(alias = "sb")]  // abbreviation
+    #[serde(default = "mwpm_default_configs::split_boundary")]
+    pub split_boundary: bool,
 }
 
 pub mod mwpm_default_configs {
@@ -51,21 +77,50 @@ pub mod mwpm_default_configs {
     pub fn precompute_complete_model_graph() -> bool { false }  // save for erasure noise model and also large code distance
     pub fn weight_function() -> WeightFunction { WeightFunction::AutotuneImproved }
     pub fn use_combined_probability() -> bool { true }  // default use combined probability for better accuracy
+    pub fn exact_matching_max_defects() -> usize { 10 }  // covers the common low-p case with negligible overhead
+    pub fn boundary_weight_multiplier() -> f64 { 1. }
+    pub fn split_boundary() -> bool { false }
 }
 
 impl MWPMDecoder {
     /// create a new MWPM decoder with decoder configuration
     pub fn new(simulator: &Simulator, noise_model: Arc<NoiseModel>, decoder_configuration: &serde_json::Value, parallel: usize, use_brief_edge: bool) -> Self {
+        Self::new_with_model_graph(simulator, noise_model, decoder_configuration, parallel, use_brief_edge, None)
+    }
+
+    /// create a new MWPM decoder with a model graph built in advance, e.g. loaded by [`ModelGraph::load`], which must be built with the
+    /// `weight_function` and `use_combined_probability` of the decoder configuration; the model graph is built if not provided
+    pub fn new_with_model_graph(simulator: &Simulator, noise_model: Arc<NoiseModel>, decoder_configuration: &serde_json::Value, parallel: usize
+            , use_brief_edge: bool, model_graph: Option<ModelGraph>) -> Self {
         // read attribute of decoder configuration
         let config: MWPMDecoderConfig = serde_json::from_value(decoder_configuration.clone()).unwrap();
+        assert!(config.exact_matching_max_defects <= exact_matching::EXACT_MATCHING_MAX_DEFECTS, "exact_matching_max_defects should not exceed {}"
+            , exact_matching::EXACT_MATCHING_MAX_DEFECTS);
+        if let Some(logical_prior) = config.logical_prior {
+            assert!(logical_prior > 0. && logical_prior < 1., "logical_prior should be in (0, 1), but got {}", logical_prior);
+        }
         // build model graph
         let mut simulator = simulator.clone();
-        let mut model_graph = ModelGraph::new(&simulator);
-        model_graph.build(&mut simulator, Arc::clone(&noise_model), &config.weight_function, parallel, config.use_combined_probability, use_brief_edge);
+        let mut model_graph = model_graph.unwrap_or_else(|| {
+            let mut model_graph = ModelGraph::new(&simulator);
+            model_graph.build(&mut simulator, Arc::clone(&noise_model), &config.weight_function, parallel, config.use_combined_probability, use_brief_edge);
+            model_graph
+        });
+        if config.split_boundary {  // before scaling the boundary weights, which are recomputed from the probabilities when splitting
+            model_graph.split_boundary(&simulator, &config.weight_function, config.use_combined_probability);
+        }
+        if config.boundary_weight_multiplier != 1. {
+            model_graph.scale_boundary_weights(&simulator, config.boundary_weight_multiplier);
+        }
         let model_graph = Arc::new(model_graph);
         // build erasure graph
         let mut erasure_graph = ErasureGraph::new(&simulator);
         erasure_graph.build(&mut simulator, Arc::clone(&noise_model), parallel);
+        if erasure_graph.has_erasure(&simulator) {
+            if let Err(error) = validate_graph_consistency(&simulator, &model_graph, &erasure_graph, &noise_model) {
+                panic!("the erasure graph is inconsistent with the model graph: {}", error);
+            }
+        }
         let erasure_graph = Arc::new(erasure_graph);
         // build complete model graph
         let mut complete_model_graph = CompleteModelGraph::new(&simulator, Arc::clone(&model_graph));
@@ -74,11 +129,33 @@ impl MWPMDecoder {
             model_graph: model_graph,
             erasure_graph: erasure_graph,
             complete_model_graph: complete_model_graph,
+            scratch_simulator: config.logical_prior.map(|_| simulator.clone()),
             config: config,
             simulator: Arc::new(simulator),
         }
     }
 
+    /// decode only the defects inside `region` on the model graph clipped to it, see [`ModelGraph::clip_to_region`], e.g. to find which part
+    /// of the volume is responsible for a failure; the complete model graph is rebuilt for the clipped graph, so this is for debugging only
+    pub fn decode_region(&self, sparse_measurement: &SparseMeasurement, region: &Region) -> (SparseCorrection, serde_json::Value) {
+        let mut model_graph = (*self.model_graph).clone();
+        model_graph.clip_to_region(&self.simulator, region);
+        let model_graph = Arc::new(model_graph);
+        let mut complete_model_graph = CompleteModelGraph::new(&self.simulator, Arc::clone(&model_graph));
+        complete_model_graph.precompute(&self.simulator, false, 1);
+        let mut config = self.config.clone();
+        config.precompute_complete_model_graph = false;
+        let mut decoder = Self {
+            model_graph,
+            erasure_graph: Arc::clone(&self.erasure_graph),
+            complete_model_graph,
+            config,
+            simulator: Arc::clone(&self.simulator),
+            scratch_simulator: self.scratch_simulator.clone(),
+        };
+        decoder.decode(&region.clip_measurement(sparse_measurement))
+    }
+
     /// decode given measurement results
     #[allow(dead_code)]
     pub fn decode(&mut self, sparse_measurement: &SparseMeasurement) -> (SparseCorrection, serde_json::Value) {
@@ -87,7 +164,22 @@ impl MWPMDecoder {
 
     /// decode given measurement results and detected erasures
     pub fn decode_with_erasure(&mut self, sparse_measurement: &SparseMeasurement, sparse_detected_erasures: &SparseErasures) -> (SparseCorrection, serde_json::Value) {
-        if sparse_detected_erasures.len() > 0 {
+        self.decode_with_erasure_deadline(sparse_measurement, sparse_detected_erasures, None).expect("never times out without deadline")
+    }
+
+    /// decode given measurement results and detected erasures, checking the deadline when building the complete graph between defects
+    /// (the most time-consuming part of the decoder) and before running the matching algorithm; the deadline is best-effort, because
+    /// blossom V doesn't check it and may run past it, see `minimum_weight_matching`
+    pub fn decode_with_erasure_deadline(&mut self, sparse_measurement: &SparseMeasurement, sparse_detected_erasures: &SparseErasures
+            , deadline: Option<Instant>) -> Result<(SparseCorrection, serde_json::Value), DecodingError> {
+        let erasure_edges = sparse_detected_erasures.get_erasure_edges(&self.erasure_graph);
+        self.decode_with_erasure_edges(sparse_measurement, &erasure_edges, deadline)
+    }
+
+    /// decode given measurement results and the edges whose weights are 0 in this shot, caused by erasures or lost detectors
+    pub fn decode_with_erasure_edges(&mut self, sparse_measurement: &SparseMeasurement, erasure_edges: &[ErasureEdge]
+            , deadline: Option<Instant>) -> Result<(SparseCorrection, serde_json::Value), DecodingError> {
+        if !erasure_edges.is_empty() {
             assert!(self.config.precompute_complete_model_graph == false, "if erasure happens, the precomputed complete graph is invalid; please disable `precompute_complete_model_graph` or `pcmg` in the decoder configuration");
         }
         let mut correction = SparseCorrection::new();
@@ -95,26 +187,28 @@ impl MWPMDecoder {
         let to_be_matched = sparse_measurement.to_vec();
         let mut time_prepare_graph = 0.;
         let mut time_blossom_v = 0.;
+        let mut time_exact_matching = 0.;
+        let mut exact_matching = false;
+        let mut logical_class_weights = None;
         let mut time_build_correction = 0.;
+        let mut timed_out = false;
         if to_be_matched.len() > 0 {
             // println!{"to_be_matched: {:?}", to_be_matched};
             let begin = Instant::now();
             // add the edges to the graph
             let m_len = to_be_matched.len();  // virtual boundary of `i` is `i + m_len`
-            let node_num = m_len * 2;
-            // Z (X) stabilizers are (fully) connected, boundaries are fully connected
-            // stabilizer to boundary is one-to-one connected
-            let mut weighted_edges = Vec::<(usize, usize, f64)>::new();
             // update model graph weights to consider erasure information
             let mut erasure_graph_modifier = ErasureGraphModifier::<f64>::new();
-            if sparse_detected_erasures.len() > 0 {  // if erasure exists, the model graph will be duplicated on demand
-                let erasure_edges = sparse_detected_erasures.get_erasure_edges(&self.erasure_graph);
+            if !erasure_edges.is_empty() {  // if erasure exists, the model graph will be duplicated on demand
                 let model_graph_mut = self.complete_model_graph.get_model_graph_mut();
                 for erasure_edge in erasure_edges.iter() {
                     match erasure_edge {
                         ErasureEdge::Connection(position1, position2) => {
                             let node1 = model_graph_mut.get_node_mut_unwrap(position1);
-                            let edge12 = node1.edges.get_mut(position2).expect("neighbor must exist");
+                            let edge12 = match node1.edges.get_mut(position2) {
+                                Some(edge12) => edge12,
+                                None => continue,  // e.g. a lost detector without a time-like edge to its next measurement
+                            };
                             let original_weight12 = edge12.weight;
                             edge12.weight = 0.;  // set to 0 because of erasure
                             let node2 = model_graph_mut.get_node_mut_unwrap(position2);
@@ -134,52 +228,61 @@ impl MWPMDecoder {
                 }
                 self.complete_model_graph.model_graph_changed(&self.simulator);
             }
-            // invalidate previous cache to save memory
-            self.complete_model_graph.invalidate_previous_dijkstra();
-            for i in 0..m_len {
-                let position = &to_be_matched[i];
-                let (edges, boundary) = self.complete_model_graph.get_edges(position, &to_be_matched);
-                match boundary {
-                    Some(weight) => {
-                        // eprintln!{"boundary {} {} ", i, weight};
-                        weighted_edges.push((i, i + m_len, weight));
-                    }, None => { }
+            let prepared = self.prepare_matching(&to_be_matched, deadline);
+            time_prepare_graph += begin.elapsed().as_secs_f64();
+            if let Some((defect_edges, boundary_weights)) = prepared {
+                // run the exact matcher for small instances, otherwise run the Blossom algorithm
+                let begin = Instant::now();
+                let prior_weighted = match self.config.logical_prior {
+                    Some(logical_prior) if m_len <= self.config.exact_matching_max_defects => {
+                        self.prior_weighted_matching(&to_be_matched, &defect_edges, &boundary_weights, logical_prior)
+                    },
+                    _ => None,
+                };
+                let matched = match prior_weighted {
+                    Some((matching, class_weights)) => {
+                        logical_class_weights = Some(class_weights);
+                        Ok((matching, true))
+                    },
+                    None => self.minimum_weight_matching(&defect_edges, &boundary_weights, deadline),
+                };
+                let matching = match matched {
+                    Ok((matching, is_exact_matching)) => {
+                        exact_matching = is_exact_matching;
+                        matching
+                    },
+                    Err(DecodingError::Timeout) => {
+                        timed_out = true;
+                        vec![]  // no correction is built
+                    },
+                };
+                if exact_matching {
+                    time_exact_matching += begin.elapsed().as_secs_f64();
+                } else {
+                    time_blossom_v += begin.elapsed().as_secs_f64();
                 }
-                for &(j, weight) in edges.iter() {
-                    if i < j {  // remove duplicated edges
-                        // eprintln!{"edge {} {} {} ", i, j, weight};
-                        weighted_edges.push((i, j, weight));
+                // build correction based on the matching
+                let begin = Instant::now();
+                for i in 0..matching.len() {
+                    let j = matching[i];
+                    let a = &to_be_matched[i];
+                    if j < i {  // only add correction if j < i, so that the same correction is not applied twice
+                        // println!("match peer {:?} {:?}", to_be_matched[i], to_be_matched[j]);
+                        let b = &to_be_matched[j];
+                        let matching_correction = self.complete_model_graph.build_correction_matching(a, b);
+                        correction.extend(&matching_correction);
+                    } else if j >= m_len {  // matched with boundary
+                        // println!("match boundary {:?}", to_be_matched[i]);
+                        let boundary_correction = self.complete_model_graph.build_correction_boundary(a);
+                        correction.extend(&boundary_correction);
                     }
                 }
-                for j in (i+1)..m_len {
-                    // virtual boundaries are always fully connected
-                    weighted_edges.push((i + m_len, j + m_len, 0.));
-                }
+                time_build_correction += begin.elapsed().as_secs_f64();
+            } else {
+                timed_out = true;
             }
-            time_prepare_graph += begin.elapsed().as_secs_f64();
-            // run the Blossom algorithm
-            let begin = Instant::now();
-            let matching = blossom_v::safe_minimum_weight_perfect_matching(node_num, weighted_edges);
-            time_blossom_v += begin.elapsed().as_secs_f64();
-            // build correction based on the matching
-            let begin = Instant::now();
-            for i in 0..m_len {
-                let j = matching[i];
-                let a = &to_be_matched[i];
-                if j < i {  // only add correction if j < i, so that the same correction is not applied twice
-                    // println!("match peer {:?} {:?}", to_be_matched[i], to_be_matched[j]);
-                    let b = &to_be_matched[j];
-                    let matching_correction = self.complete_model_graph.build_correction_matching(a, b);
-                    correction.extend(&matching_correction);
-                } else if j >= m_len {  // matched with boundary
-                    // println!("match boundary {:?}", to_be_matched[i]);
-                    let boundary_correction = self.complete_model_graph.build_correction_boundary(a);
-                    correction.extend(&boundary_correction);
-                }
-            }
-            time_build_correction += begin.elapsed().as_secs_f64();
-            // recover the modified edges
-            if sparse_detected_erasures.len() > 0 {
+            // recover the modified edges even if timed out, so that the decoder is ready for the next shot
+            if !erasure_edges.is_empty() {
                 let model_graph_mut = self.complete_model_graph.get_model_graph_mut();
                 while erasure_graph_modifier.has_modified_edges() {
                     let (erasure_edge, weight) = erasure_graph_modifier.pop_modified_edge();
@@ -206,27 +309,617 @@ impl MWPMDecoder {
                 self.complete_model_graph.model_graph_changed(&self.simulator);
             }
         }
-        (correction, json!({
+        if timed_out {
+            return Err(DecodingError::Timeout)
+        }
+        let mut runtime_statistics = json!({
             "to_be_matched": to_be_matched.len(),
             "time_prepare_graph": time_prepare_graph,
             "time_blossom_v": time_blossom_v,
+            "time_exact_matching": time_exact_matching,
+            "exact_matching": exact_matching,
+            "exact_matching_max_defects": self.config.exact_matching_max_defects,
             "time_build_correction": time_build_correction,
+        });
+        if let Some(logical_class_weights) = logical_class_weights {
+            runtime_statistics["logical_class_weights"] = json!(logical_class_weights);
+        }
+        Ok((correction, runtime_statistics))
+    }
+
+    /// find the best matching of each logical class (whether it flips logical_i compared to no correction) and choose between them after
+    /// charging the flipped class with the weight `ln((1-q)/q)` of the prior; return the matching and the total weight of each class,
+    /// where an impossible class has infinite weight; return None if no perfect matching exists among the known edges
+    fn prior_weighted_matching(&mut self, to_be_matched: &[Position], defect_edges: &[(usize, usize, f64)], boundary_weights: &[Option<f64>]
+            , logical_prior: f64) -> Option<(Vec<usize>, [f64; 2])> {
+        let m_len = to_be_matched.len();
+        // the logical class of each edge, because validating a correction is linear in the correction
+        let mut classified_edges = Vec::with_capacity(defect_edges.len());
+        let mut classified_boundaries = Vec::with_capacity(m_len);
+        {
+            let complete_model_graph = &mut self.complete_model_graph;
+            let simulator = self.scratch_simulator.as_mut().expect("scratch simulator is created with logical_prior");
+            let (empty_logical_i, _) = simulator.validate_correction(&SparseCorrection::new());
+            for &(a, b, weight) in defect_edges.iter() {
+                let matching_correction = complete_model_graph.build_correction_matching(&to_be_matched[a], &to_be_matched[b]);
+                let (logical_i, _) = simulator.validate_correction(&matching_correction);
+                classified_edges.push((a, b, weight, logical_i ^ empty_logical_i));
+            }
+            for (a, boundary_weight) in boundary_weights.iter().enumerate() {
+                classified_boundaries.push(boundary_weight.map(|weight| {
+                    let boundary_correction = complete_model_graph.build_correction_boundary(&to_be_matched[a]);
+                    let (logical_i, _) = simulator.validate_correction(&boundary_correction);
+                    (weight, logical_i ^ empty_logical_i)
+                }));
+            }
+        }
+        let matchings = exact_matching::exact_minimum_weight_matching_by_class(m_len, &classified_edges, &classified_boundaries);
+        let prior_weights = [0., ((1. - logical_prior) / logical_prior).ln()];
+        let mut class_weights = [f64::INFINITY; 2];
+        for class in 0..2 {
+            if let Some(matching) = &matchings[class] {
+                class_weights[class] = exact_matching::matching_weight(matching, defect_edges, boundary_weights) + prior_weights[class];
+            }
+        }
+        let chosen = if class_weights[1] < class_weights[0] { 1 } else { 0 };
+        matchings[chosen].clone().map(|matching| (matching, class_weights))
+    }
+
+    /// compute the edges between the defects and the boundary weight of each defect in the complete model graph;
+    /// return None if the deadline passes before all defects are processed
+    #[allow(clippy::type_complexity)]
+    fn prepare_matching(&mut self, to_be_matched: &Vec<Position>, deadline: Option<Instant>) -> Option<(Vec<(usize, usize, f64)>, Vec<Option<f64>>)> {
+        let mut defect_edges = Vec::<(usize, usize, f64)>::new();
+        let mut boundary_weights = Vec::<Option<f64>>::with_capacity(to_be_matched.len());
+        // invalidate previous cache to save memory
+        self.complete_model_graph.invalidate_previous_dijkstra();
+        for (i, position) in to_be_matched.iter().enumerate() {
+            if let Some(deadline) = deadline {
+                if Instant::now() >= deadline {
+                    return None
+                }
+            }
+            let (edges, boundary) = self.complete_model_graph.get_edges(position, to_be_matched);
+            boundary_weights.push(boundary);
+            for &(j, weight) in edges.iter() {
+                if i < j {  // remove duplicated edges
+                    defect_edges.push((i, j, weight));
+                }
+            }
+        }
+        if let Some(deadline) = deadline {
+            if Instant::now() >= deadline {
+                return None
+            }
+        }
+        Some((defect_edges, boundary_weights))
+    }
+
+    /// find the minimum-weight matching where each defect `i` is matched with another defect or its virtual boundary `i + m_len`;
+    /// use the exact matcher for small instances and otherwise blossom V, returning whether the exact matcher is used;
+    /// the exact matcher checks the deadline periodically, while blossom V always runs to the end and is only preceded by a check
+    fn minimum_weight_matching(&self, defect_edges: &[(usize, usize, f64)], boundary_weights: &[Option<f64>], deadline: Option<Instant>)
+            -> Result<(Vec<usize>, bool), DecodingError> {
+        let m_len = boundary_weights.len();
+        let mut is_preempted = || deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        if m_len <= self.config.exact_matching_max_defects {
+            if let Some(matching) = exact_matching::exact_minimum_weight_matching_preemptible(m_len, defect_edges, boundary_weights, &mut is_preempted)? {
+                return Ok((matching, true))
+            }
+        }
+        if is_preempted() {
+            return Err(DecodingError::Timeout)
+        }
+        // Z (X) stabilizers are (fully) connected, boundaries are fully connected
+        // stabilizer to boundary is one-to-one connected
+        let mut weighted_edges = defect_edges.to_vec();
+        for (i, boundary_weight) in boundary_weights.iter().enumerate() {
+            if let Some(weight) = *boundary_weight {
+                weighted_edges.push((i, i + m_len, weight));
+            }
+            for j in (i+1)..m_len {
+                // virtual boundaries are always fully connected
+                weighted_edges.push((i + m_len, j + m_len, 0.));
+            }
+        }
+        Ok((blossom_v::safe_minimum_weight_perfect_matching(m_len * 2, weighted_edges), false))
+    }
+
+    /// decode a long syndrome using overlapping windows of `window_rounds` measurement rounds, each committing the matchings in `commit_rounds`
+    /// rounds and leaving `window_rounds - commit_rounds` buffer rounds on both sides, where a defect may be deferred by matching to the past
+    /// or the future at zero cost. The windows of the first layer are disjoint and decoded independently; the deferred defects are then left in
+    /// the gaps between their committed regions, which are again independent windows committing all their defects. The independent windows
+    /// of each layer are distributed over `parallel` threads, each running a clone of this decoder.
+    pub fn decode_windowed(&mut self, sparse_measurement: &SparseMeasurement, window_rounds: usize, commit_rounds: usize, parallel: usize)
+            -> (SparseCorrection, serde_json::Value) {
+        assert!(commit_rounds >= 1 && commit_rounds <= window_rounds, "require 1 <= commit_rounds <= window_rounds");
+        assert!(parallel >= 1, "require at least 1 thread");
+        let measurement_cycles = self.simulator.measurement_cycles;
+        let last_round = (self.simulator.height - 1) / measurement_cycles;
+        let buffer_rounds = window_rounds - commit_rounds;
+        let period = commit_rounds + 2 * buffer_rounds;
+        let round_of = |position: &Position| position.t / measurement_cycles;
+        // first layer: the window around each committed region, which commits everything above it if there is no round above the window
+        let mut windows = Vec::new();
+        let mut gaps = Vec::new();
+        let mut commit_begin = 0;
+        while commit_begin <= last_round {
+            let window_end = commit_begin + commit_rounds + buffer_rounds;  // exclusive
+            let commit_end = if window_end > last_round { usize::MAX } else { commit_begin + commit_rounds };
+            let to_be_matched: Vec<Position> = sparse_measurement.iter().filter(|position| {
+                round_of(position) + buffer_rounds >= commit_begin && round_of(position) < window_end
+            }).cloned().collect();
+            windows.push((to_be_matched, commit_begin..commit_end));
+            if commit_end != usize::MAX {
+                gaps.push(commit_end..commit_begin + period);
+            }
+            commit_begin += period;
+        }
+        let mut correction = SparseCorrection::new();
+        let mut remaining: BTreeSet<Position> = sparse_measurement.iter().cloned().collect();
+        let mut decoded_windows = 0;
+        for (window_correction, committed) in self.match_windows(windows, parallel) {
+            decoded_windows += 1;
+            correction.extend(&window_correction);
+            remaining.retain(|position| !committed.contains(position));
+        }
+        // second layer: the gaps commit all the deferred defects
+        let windows: Vec<_> = gaps.into_iter().map(|gap| {
+            let to_be_matched: Vec<Position> = remaining.iter().filter(|position| gap.contains(&round_of(position))).cloned().collect();
+            (to_be_matched, 0..usize::MAX)
+        }).collect();
+        for (window_correction, committed) in self.match_windows(windows, parallel) {
+            decoded_windows += 1;
+            correction.extend(&window_correction);
+            remaining.retain(|position| !committed.contains(position));
+        }
+        assert!(remaining.is_empty(), "the windows must commit all defects");
+        (correction, json!({
+            "to_be_matched": sparse_measurement.len(),
+            "windows": decoded_windows,
+            "window_rounds": window_rounds,
+            "commit_rounds": commit_rounds,
         }))
     }
 
+    /// match each non-empty window `(to_be_matched, committed_rounds)` independently, distributed over `parallel` threads; return the correction
+    /// and the committed defects of each window, where a defect outside `committed_rounds` is matched to the past or the future at zero cost and
+    /// only committed if it's matched with a defect inside
+    fn match_windows(&mut self, windows: Vec<(Vec<Position>, std::ops::Range<usize>)>, parallel: usize) -> Vec<(SparseCorrection, BTreeSet<Position>)> {
+        let windows: Vec<_> = windows.into_iter().filter(|(to_be_matched, _)| !to_be_matched.is_empty()).collect();
+        if parallel <= 1 || windows.len() <= 1 {
+            return windows.iter().map(|(to_be_matched, committed_rounds)| self.match_window(to_be_matched, committed_rounds)).collect()
+        }
+        let chunk_size = windows.len().div_ceil(parallel);
+        std::thread::scope(|scope| {
+            let handlers: Vec<_> = windows.chunks(chunk_size).map(|chunk| {
+                let mut decoder = self.clone();
+                scope.spawn(move || {
+                    chunk.iter().map(|(to_be_matched, committed_rounds)| decoder.match_window(to_be_matched, committed_rounds)).collect::<Vec<_>>()
+                })
+            }).collect();
+            handlers.into_iter().flat_map(|handler| handler.join().unwrap()).collect()
+        })
+    }
+
+    fn match_window(&mut self, to_be_matched: &Vec<Position>, committed_rounds: &std::ops::Range<usize>) -> (SparseCorrection, BTreeSet<Position>) {
+        let measurement_cycles = self.simulator.measurement_cycles;
+        let is_committed_region = |position: &Position| committed_rounds.contains(&(position.t / measurement_cycles));
+        let m_len = to_be_matched.len();
+        let (defect_edges, mut boundary_weights) = self.prepare_matching(to_be_matched, None).expect("never times out without deadline");
+        for (i, position) in to_be_matched.iter().enumerate() {
+            if !is_committed_region(position) {
+                boundary_weights[i] = Some(0.);  // the past or future boundary
+            }
+        }
+        let (matching, _) = self.minimum_weight_matching(&defect_edges, &boundary_weights, None).expect("never times out without deadline");
+        let mut correction = SparseCorrection::new();
+        let mut committed = BTreeSet::new();
+        for i in 0..m_len {
+            let j = matching[i];
+            let a = &to_be_matched[i];
+            if j >= m_len {
+                if is_committed_region(a) {
+                    correction.extend(&self.complete_model_graph.build_correction_boundary(a));
+                    committed.insert(a.clone());
+                }
+            } else if j < i {
+                let b = &to_be_matched[j];
+                if is_committed_region(a) || is_committed_region(b) {
+                    correction.extend(&self.complete_model_graph.build_correction_matching(a, b));
+                    committed.insert(a.clone());
+                    committed.insert(b.clone());
+                }
+            }
+        }
+        (correction, committed)
+    }
+
 }
 
 
-#[cfg(feature = "blossom_v")]
 #[cfg(test)]
 mod tests {
     use super::*;
     use super::super::code_builder::*;
     use super::super::noise_model_builder::*;
+    use super::super::types::*;
+    use super::super::tool::{GeneralDecoder, SharedSyndromeHarness};
+
+    fn mwpm_decoder_build_phenomenological(d: usize, p: f64, decoder_config: serde_json::Value) -> (Simulator, Arc<NoiseModel>, MWPMDecoder) {
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(d, d, d));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), p, 0.5, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        let noise_model = Arc::new(noise_model);
+        let mwpm_decoder = MWPMDecoder::new(&simulator, Arc::clone(&noise_model), &decoder_config, 1, false);
+        (simulator, noise_model, mwpm_decoder)
+    }
+
+    /// the exact matcher doesn't need blossom V library
+    #[test]
+    fn mwpm_decoder_exact_matching() {  // cargo test mwpm_decoder_exact_matching -- --nocapture
+        let (mut simulator, noise_model, mut mwpm_decoder) = mwpm_decoder_build_phenomenological(5, 0.005, json!({"emmd": 24}));
+        for _ in 0..100 {
+            simulator.generate_random_errors(&noise_model);
+            let sparse_measurement = simulator.generate_sparse_measurement();
+            if sparse_measurement.len() > 24 {
+                continue
+            }
+            let (correction, runtime_statistics) = mwpm_decoder.decode(&sparse_measurement);
+            assert_eq!(runtime_statistics["exact_matching_max_defects"], json!(24));
+            assert_eq!(runtime_statistics["exact_matching"], json!(sparse_measurement.len() > 0));
+            code_builder_sanity_check_correction(&mut simulator, &correction).unwrap();
+        }
+    }
+
+    #[test]
+    fn mwpm_decoder_windowed_single_error() {  // cargo test mwpm_decoder_windowed_single_error -- --nocapture
+        let (mut simulator, noise_model, mut mwpm_decoder) = mwpm_decoder_build_phenomenological(3, 0.01, json!({}));
+        let mut error_positions = Vec::new();
+        simulator_iter_real!(simulator, position, _node, {
+            if !noise_model.get_node_unwrap(position).is_noiseless() {
+                error_positions.push(position.clone());
+            }
+        });
+        for (window_rounds, commit_rounds) in [(1, 1), (2, 1), (3, 2)] {
+            for position in error_positions.iter() {
+                for error in [ErrorType::X, ErrorType::Z, ErrorType::Y] {
+                    if !noise_model.is_single_error_possible(&simulator, position, &error) {
+                        continue
+                    }
+                    let mut sparse_error_pattern = SparseErrorPattern::new();
+                    sparse_error_pattern.add(position.clone(), error);
+                    simulator.load_sparse_error_pattern(&sparse_error_pattern, &noise_model).unwrap();
+                    simulator.clear_propagate_errors();
+                    simulator.propagate_errors();
+                    let sparse_measurement = simulator.generate_sparse_measurement();
+                    let (correction, _) = mwpm_decoder.decode_windowed(&sparse_measurement, window_rounds, commit_rounds, 1);
+                    code_builder_sanity_check_correction(&mut simulator, &correction).unwrap();
+                    let (parallel_correction, _) = mwpm_decoder.decode_windowed(&sparse_measurement, window_rounds, commit_rounds, 2);
+                    assert_eq!(parallel_correction.to_vec(), correction.to_vec());
+                    if window_rounds > 1 {  // a single window round cannot tell measurement errors from data errors
+                        assert_eq!(simulator.validate_correction(&correction), (false, false), "{} {}", position, error);
+                    }
+                }
+            }
+        }
+    }
+
+    /// on an even-distance code, some syndromes have two equally likely corrections in different logical classes; an extreme prior must
+    /// decide between them
+    #[test]
+    fn mwpm_decoder_logical_prior() {  // cargo test mwpm_decoder_logical_prior -- --nocapture
+        let d = 4;
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(0, d, d));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::CodeCapacity.apply(&mut simulator, &mut noise_model, &json!({}), 0.01, 0.5, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        let noise_model = Arc::new(noise_model);
+        let build_decoder = |logical_prior: f64| MWPMDecoder::new(&simulator, Arc::clone(&noise_model), &json!({"emmd": 16, "logical_prior": logical_prior}), 1, false);
+        let mut neutral_decoder = build_decoder(0.5);
+        let mut unlikely_decoder = build_decoder(1e-6);
+        let mut likely_decoder = build_decoder(1. - 1e-6);
+        let mut data_qubits = Vec::new();
+        simulator_iter_real!(simulator, position, node, t => 0, if node.qubit_type == QubitType::Data { data_qubits.push(position.clone()); });
+        let mut ambiguous_count = 0;
+        for error in [ErrorType::X, ErrorType::Z] {
+            for a in 0..data_qubits.len() {
+                for b in a..data_qubits.len() {
+                    let mut sparse_error_pattern = SparseErrorPattern::new();
+                    sparse_error_pattern.add(data_qubits[a].clone(), error);
+                    sparse_error_pattern.add(data_qubits[b].clone(), error);  // a single error when a == b
+                    simulator.load_sparse_error_pattern(&sparse_error_pattern, &noise_model).unwrap();
+                    simulator.clear_propagate_errors();
+                    simulator.propagate_errors();
+                    let sparse_measurement = simulator.generate_sparse_measurement();
+                    if sparse_measurement.len() == 0 {
+                        continue
+                    }
+                    let (_, runtime_statistics) = neutral_decoder.decode(&sparse_measurement);
+                    // an impossible logical class has infinite weight, which is serialized as null
+                    let class_weights: Vec<Option<f64>> = serde_json::from_value(runtime_statistics["logical_class_weights"].clone()).unwrap();
+                    match (class_weights[0], class_weights[1]) {
+                        (Some(weight_0), Some(weight_1)) if (weight_0 - weight_1).abs() < 1e-9 => { },
+                        _ => continue,
+                    }
+                    ambiguous_count += 1;
+                    for (decoder, expected_claimed_class) in [(&mut unlikely_decoder, false), (&mut likely_decoder, true)] {
+                        let (correction, _) = decoder.decode(&sparse_measurement);
+                        code_builder_sanity_check_correction(&mut simulator, &correction).unwrap();
+                        let (_, (claimed_logical_i, _)) = simulator.validate_correction_with_logical_classes(&correction);
+                        assert_eq!(claimed_logical_i, expected_claimed_class, "{:?}", sparse_error_pattern);
+                    }
+                }
+            }
+        }
+        println!("ambiguous syndromes: {}", ambiguous_count);
+        assert!(ambiguous_count > 0);
+    }
+
+    #[test]
+    fn mwpm_decoder_boundary_weight_multiplier() {  // cargo test mwpm_decoder_boundary_weight_multiplier -- --nocapture
+        let d = 5;
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(0, d, d));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::CodeCapacity.apply(&mut simulator, &mut noise_model, &json!({}), 0.01, 0.5, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        let noise_model = Arc::new(noise_model);
+        let mut default_decoder = MWPMDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1, false);
+        let mut scaled_decoder = MWPMDecoder::new(&simulator, Arc::clone(&noise_model), &json!({"bwm": 10.}), 1, false);
+        // only the boundary edges are scaled
+        simulator_iter!(simulator, position, delta_t => simulator.measurement_cycles, if default_decoder.model_graph.is_node_exist(position) {
+            let default_node = default_decoder.model_graph.get_node_unwrap(position);
+            let scaled_node = scaled_decoder.model_graph.get_node_unwrap(position);
+            if let Some(boundary) = default_node.boundary.as_ref() {
+                assert!((scaled_node.boundary.as_ref().unwrap().weight - 10. * boundary.weight).abs() < 1e-9);
+            }
+            for (target, edge) in default_node.edges.iter() {
+                assert_eq!(scaled_node.edges[target].weight, edge.weight);
+            }
+        });
+        // two defects next to the boundaries but far from each other are matched to the boundaries by default, and to each other when
+        // the boundary is expensive, which may even cause a logical error
+        let mut changed_count = 0;
+        let mut data_qubits = Vec::new();
+        simulator_iter_real!(simulator, position, node, t => 0, if node.qubit_type == QubitType::Data { data_qubits.push(position.clone()); });
+        for a in 0..data_qubits.len() {
+            for b in a+1..data_qubits.len() {
+                let mut sparse_error_pattern = SparseErrorPattern::new();
+                sparse_error_pattern.add(data_qubits[a].clone(), ErrorType::X);
+                sparse_error_pattern.add(data_qubits[b].clone(), ErrorType::X);
+                simulator.load_sparse_error_pattern(&sparse_error_pattern, &noise_model).unwrap();
+                simulator.clear_propagate_errors();
+                simulator.propagate_errors();
+                let sparse_measurement = simulator.generate_sparse_measurement();
+                if sparse_measurement.len() != 2 {
+                    continue
+                }
+                let (default_correction, _) = default_decoder.decode(&sparse_measurement);
+                let (scaled_correction, _) = scaled_decoder.decode(&sparse_measurement);
+                code_builder_sanity_check_correction(&mut simulator, &scaled_correction).unwrap();
+                if default_correction.len() == 2 && scaled_correction.len() > 2 {
+                    changed_count += 1;
+                    assert_eq!(simulator.validate_correction(&default_correction), (false, false));
+                }
+                assert!(scaled_correction.len() >= default_correction.len(), "{:?}", sparse_error_pattern);
+            }
+        }
+        println!("corrections changed by the boundary weight multiplier: {}", changed_count);
+        assert!(changed_count > 0);
+    }
+
+    #[test]
+    fn mwpm_decoder_split_boundary() {  // cargo test mwpm_decoder_split_boundary -- --nocapture
+        // a narrow code whose Z stabilizers are next to both the left and the right boundaries; the left data qubit may flip at two time steps,
+        // each less likely than the single error of the right data qubit, but more likely together
+        let simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(0, 3, 2));
+        let mut noise_model = NoiseModel::new(&simulator);
+        for (position, p) in [(pos!(0, 1, 1), 0.01), (pos!(1, 1, 1), 0.01), (pos!(0, 1, 3), 0.015)] {
+            let mut noise_model_node = noise_model.get_node_unwrap(&position).clone();
+            noise_model_node.pauli_error_rates.error_rate_X = p;
+            noise_model.set_node(&position, Some(Arc::new(noise_model_node)));
+        }
+        let noise_model = Arc::new(noise_model);
+        let mut default_decoder = MWPMDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1, false);
+        let mut split_decoder = MWPMDecoder::new(&simulator, Arc::clone(&noise_model), &json!({"sb": true}), 1, false);
+        let segments = ModelGraph::boundary_segments(&simulator);
+        assert_ne!(segments[&(1, 0)], segments[&(1, 4)]);
+        assert_eq!(segments[&(1, 0)], segments[&(5, 0)]);
+        let stabilizer = pos!(simulator.measurement_cycles, 1, 2);
+        let elected_segment = |decoder: &MWPMDecoder| {
+            let boundary = decoder.model_graph.get_node_unwrap(&stabilizer).boundary.as_ref().unwrap();
+            let virtual_node = boundary.virtual_node.as_ref().unwrap();
+            (segments[&(virtual_node.i, virtual_node.j)], boundary.probability)
+        };
+        let (default_segment, default_probability) = elected_segment(&default_decoder);
+        let (split_segment, split_probability) = elected_segment(&split_decoder);
+        assert_eq!(default_segment, segments[&(1, 4)]);
+        assert_eq!(split_segment, segments[&(1, 0)]);
+        assert!(split_probability < default_probability, "the other segment is no longer combined");
+        // a single defect is matched to the right boundary by default, but to the more likely left boundary when the boundary is split
+        let sparse_measurement = SparseMeasurement::from_vec(&vec![stabilizer.clone()]);
+        let top_t = simulator.height - 1;
+        let (default_correction, _) = default_decoder.decode(&sparse_measurement);
+        let (split_correction, _) = split_decoder.decode(&sparse_measurement);
+        assert_eq!(default_correction.to_vec(), vec![(pos!(top_t, 1, 3), ErrorType::X)]);
+        assert_eq!(split_correction.to_vec(), vec![(pos!(top_t, 1, 1), ErrorType::X)]);
+        // for a regular code the logical accuracy is unchanged on the same syndromes
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(3, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::DepolarizingNoise.apply(&mut simulator, &mut noise_model, &json!({}), 0.003, 0.5, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        let noise_model = Arc::new(noise_model);
+        let decoders = [json!({"emmd": 24}), json!({"emmd": 24, "sb": true})].iter().map(|config| {
+            GeneralDecoder::MWPM(MWPMDecoder::new(&simulator, Arc::clone(&noise_model), config, 1, false))
+        }).collect();
+        let mut harness = SharedSyndromeHarness::new(simulator, Arc::clone(&noise_model), decoders);
+        let statistics = harness.run(3000);
+        let (difference, standard_error) = statistics.difference(1, 0);
+        println!("logical errors: {} and {}, difference {} ± {}", statistics.failed[0][0], statistics.failed[1][1], difference, standard_error);
+        assert!(statistics.failed[0][0] > 0);
+        assert!(difference.abs() <= 3. * standard_error + 1e-9);
+    }
+
+    /// the defects outside the region are ignored, and those inside are decoded as if the rest of the volume didn't exist
+    #[test]
+    fn mwpm_decoder_decode_region() {  // cargo test mwpm_decoder_decode_region -- --nocapture
+        let (mut simulator, noise_model, mut mwpm_decoder) = mwpm_decoder_build_phenomenological(7, 0.005, json!({"emmd": 24}));
+        let noisy_data_qubit = |round: usize, i: usize, j: usize| -> Position {
+            let t = (round * simulator.measurement_cycles..(round + 1) * simulator.measurement_cycles)
+                .find(|t| !noise_model.get_node_unwrap(&pos!(*t, i, j)).is_noiseless()).unwrap();
+            pos!(t, i, j)
+        };
+        let first_error = noisy_data_qubit(2, 3, 3);
+        let second_error = noisy_data_qubit(5, 9, 9);
+        assert_eq!(simulator.get_node_unwrap(&first_error).qubit_type, QubitType::Data);
+        let measure = |simulator: &mut Simulator, errors: &[&Position]| -> SparseMeasurement {
+            let mut sparse_error_pattern = SparseErrorPattern::new();
+            for position in errors.iter() {
+                sparse_error_pattern.add((*position).clone(), ErrorType::X);
+            }
+            simulator.load_sparse_error_pattern(&sparse_error_pattern, &noise_model).unwrap();
+            simulator.clear_propagate_errors();
+            simulator.propagate_errors();
+            simulator.generate_sparse_measurement()
+        };
+        let (expected_correction, _) = mwpm_decoder.decode(&measure(&mut simulator, &[&first_error]));
+        let sparse_measurement = measure(&mut simulator, &[&first_error, &second_error]);
+        assert_eq!(sparse_measurement.len(), 4);
+        let region = Region { t: Some((0, simulator.measurement_cycles * 4)), i: Some((0, 6)), j: None };
+        let clipped_measurement = region.clip_measurement(&sparse_measurement);
+        assert_eq!(clipped_measurement.len(), 2);
+        let (correction, _) = mwpm_decoder.decode_region(&sparse_measurement, &region);
+        assert_eq!(correction.to_vec(), expected_correction.to_vec());
+        // the original decoder is untouched
+        let (correction, _) = mwpm_decoder.decode(&sparse_measurement);
+        code_builder_sanity_check_correction(&mut simulator, &correction).unwrap();
+    }
+
+    /// the exact matcher must find matchings of the same total weight as blossom V
+    #[cfg(feature = "blossom_v")]
+    #[test]
+    fn mwpm_decoder_exact_matching_blossom_v() {  // cargo test mwpm_decoder_exact_matching_blossom_v -- --nocapture
+        let max_defects = 12;
+        let (mut simulator, noise_model, mut mwpm_decoder) = mwpm_decoder_build_phenomenological(5, 0.005, json!({}));
+        let mut compared = 0;
+        for _ in 0..10000 {
+            simulator.generate_random_errors(&noise_model);
+            let to_be_matched = simulator.generate_sparse_measurement().to_vec();
+            let m_len = to_be_matched.len();
+            if m_len == 0 || m_len > max_defects {
+                continue
+            }
+            let mut weighted_edges = Vec::new();
+            let mut defect_edges = Vec::new();
+            let mut boundary_weights = Vec::new();
+            mwpm_decoder.complete_model_graph.invalidate_previous_dijkstra();
+            for i in 0..m_len {
+                let (edges, boundary) = mwpm_decoder.complete_model_graph.get_edges(&to_be_matched[i], &to_be_matched);
+                boundary_weights.push(boundary);
+                if let Some(weight) = boundary {
+                    weighted_edges.push((i, i + m_len, weight));
+                }
+                for &(j, weight) in edges.iter() {
+                    if i < j {
+                        weighted_edges.push((i, j, weight));
+                        defect_edges.push((i, j, weight));
+                    }
+                }
+                for j in (i+1)..m_len {
+                    weighted_edges.push((i + m_len, j + m_len, 0.));
+                }
+            }
+            let exact = exact_matching::exact_minimum_weight_matching(m_len, &defect_edges, &boundary_weights).unwrap();
+            let blossom = blossom_v::safe_minimum_weight_perfect_matching(m_len * 2, weighted_edges);
+            let exact_weight = exact_matching::matching_weight(&exact, &defect_edges, &boundary_weights);
+            let blossom_weight = exact_matching::matching_weight(&blossom[..m_len], &defect_edges, &boundary_weights);
+            // blossom V rounds the weights to integers, allow for the rounding error
+            assert!((exact_weight - blossom_weight).abs() <= 1e-6 * exact_weight.abs().max(1.), "{} != {}", exact_weight, blossom_weight);
+            compared += 1;
+        }
+        assert!(compared > 0);
+    }
+
     
     // 2022.6.16: mwpm decoder should correct this pattern because UF decoder does
     // {"[0][1][5]":"Z","[0][2][6]":"Z","[0][4][4]":"X","[0][5][7]":"X","[0][9][7]":"Y"}, {"erasures":["[0][1][3]","[0][1][5]","[0][2][6]","[0][4][4]","[0][5][7]","[0][6][6]","[0][9][7]"]}
     // cargo run --release -- tool benchmark [5] [0] [0] --pes [0.1] --max_repeats 0 --min_failed_cases 10 --time_budget 60 --decoder mwpm --code_type StandardPlanarCode --noise_model erasure-only-phenomenological -p0 --debug_print failed-error-pattern
+    /// the code capacity threshold of MWPM decoder under independent bit-flip noise is about 10.3%
+    #[cfg(feature = "blossom_v")]
+    #[test]
+    fn mwpm_decoder_code_capacity_threshold() {  // cargo test mwpm_decoder_code_capacity_threshold -- --nocapture
+        let logical_error_rate = |d: usize, p: f64| -> f64 {
+            let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(0, d, d));
+            let mut noise_model = NoiseModel::new(&simulator);
+            NoiseModelBuilder::CodeCapacity.apply(&mut simulator, &mut noise_model, &json!({}), p, f64::INFINITY, 0.);  // only Z errors
+            simulator.compress_error_rates(&mut noise_model);
+            let noise_model = Arc::new(noise_model);
+            let mut mwpm_decoder = MWPMDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1, false);
+            let repeats = 5000;
+            let mut qec_failed = 0;
+            for _ in 0..repeats {
+                simulator.generate_random_errors(&noise_model);
+                let (correction, _) = mwpm_decoder.decode(&simulator.generate_sparse_measurement());
+                let (logical_i, logical_j) = simulator.validate_correction(&correction);
+                if logical_i || logical_j {
+                    qec_failed += 1;
+                }
+            }
+            qec_failed as f64 / repeats as f64
+        };
+        for (p, below_threshold) in [(0.05, true), (0.15, false)] {
+            let logical_error_rates: Vec<f64> = [3, 5, 7].iter().map(|&d| logical_error_rate(d, p)).collect();
+            println!("p = {}: {:?}", p, logical_error_rates);
+            for window in logical_error_rates.windows(2) {
+                assert_eq!(window[1] < window[0], below_threshold);
+            }
+        }
+    }
+
+    /// with a noisy final round, defects appear in the reconstructed final round and the decoder still corrects them back to the code space,
+    /// while the logical error rate increases because of the additional noise
+    #[test]
+    fn mwpm_decoder_noisy_final_round() {  // cargo test mwpm_decoder_noisy_final_round -- --nocapture
+        use crate::reproducible_rand::Xoroshiro128StarStar;
+        use crate::rand_core::SeedableRng;
+        let p = 0.005;
+        for d in [3, 5] {
+            let mut failed_counts = vec![];
+            for noisy_final_round in [false, true] {
+                let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(2, d, d));
+                let mut noise_model = NoiseModel::new(&simulator);
+                NoiseModelBuilder::StimNoiseModel.apply(&mut simulator, &mut noise_model, &json!({"noisy_final_round": noisy_final_round}), p, 0.5, 0.);
+                noise_model_sanity_check(&simulator, &noise_model).unwrap();
+                simulator.compress_error_rates(&mut noise_model);
+                let noise_model = Arc::new(noise_model);
+                let mut mwpm_decoder = MWPMDecoder::new(&simulator, Arc::clone(&noise_model), &json!({"emmd": 24}), 1, false);
+                simulator.rng = Xoroshiro128StarStar::seed_from_u64(1730);
+                let top_t = simulator.height - 1;
+                let (mut failed, mut top_defects) = (0, 0);
+                for _ in 0..2000 {
+                    simulator.generate_random_errors(&noise_model);
+                    let sparse_measurement = simulator.generate_sparse_measurement();
+                    if sparse_measurement.len() > 24 {
+                        continue
+                    }
+                    top_defects += sparse_measurement.round_defects(top_t).count();
+                    let (correction, _) = mwpm_decoder.decode(&sparse_measurement);
+                    code_builder_sanity_check_correction(&mut simulator, &correction).unwrap();
+                    let (logical_i, logical_j) = simulator.validate_correction(&correction);
+                    if logical_i || logical_j {
+                        failed += 1;
+                    }
+                }
+                println!("d = {}, noisy_final_round = {}: failed {}, top defects {}", d, noisy_final_round, failed, top_defects);
+                assert!(top_defects > 0);
+                failed_counts.push(failed);
+            }
+            assert!(failed_counts[1] > failed_counts[0]);
+        }
+    }
+
+    #[cfg(feature = "blossom_v")]
     #[test]
     fn mwpm_decoder_debug_1() {  // cargo test mwpm_decoder_debug_1 -- --nocapture
         let d = 5;
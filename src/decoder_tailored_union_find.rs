@@ -0,0 +1,553 @@
+//! tailored surface code union-find decoder
+//!
+//! The tailored surface code under highly Z-biased noise decouples into two matching problems on the diagonals of the code, see
+//! [`TailoredMWPMDecoder`](super::decoder_tailored_mwpm::TailoredMWPMDecoder). Instead of matching the positive and negative graphs,
+//! this decoder grows union-find clusters on the two diagonal sub-graphs, which only couple through the (zero-weight) connection between
+//! the two copies of each virtual node, just like the matching graph of the tailored MWPM decoder. The clusters of both sub-graphs are then
+//! merged into tailored clusters: neutral clusters are corrected by neutral matching inside the cluster, clusters touching the boundary are
+//! neutralized by their virtual nodes, while the other charged clusters are left to the (naive) residual decoding.
+//!
+
+use serde::{Serialize, Deserialize};
+use super::simulator::*;
+use super::noise_model::*;
+use super::model_graph::*;
+use super::decoder_mwpm::*;
+use super::decoder_union_find::*;
+use super::decoder_tailored_mwpm::tailored_mwpm_default_configs;
+use super::tailored_model_graph::*;
+use super::tailored_complete_model_graph::*;
+use super::serde_json;
+use std::sync::{Arc};
+use std::time::Instant;
+use super::union_find::DefaultUnionFind;
+use super::types::*;
+use std::collections::{BTreeMap, BTreeSet};
+use super::position_indexer::*;
+
+/// tailored union-find decoder, initialized and cloned for multiple threads
+#[derive(Debug, Clone, Serialize)]
+pub struct TailoredUnionFindDecoder {
+    /// model graph is immutably shared
+    pub tailored_model_graph: Arc<TailoredModelGraph>,
+    /// complete model graph each thread maintain its own precomputed data, used to build the correction of neutral clusters
+    pub tailored_complete_model_graph: TailoredCompleteModelGraph,
+    /// cluster growth on the positive and negative sub-graphs
+    pub diagonal_union_find: DiagonalUnionFind,
+    /// normal union-find decoder to handle residual decoding
+    pub union_find_decoder: UnionFindDecoder,
+    /// base simulator, which is immutable but can be used to check code information
+    #[serde(skip)]
+    pub simulator: Arc<Simulator>,
+    /// save configuration for later usage
+    pub config: TailoredUnionFindDecoderConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TailoredUnionFindDecoderConfig {
+    /// build complete model graph at first, but this will consume O(N^2) memory and increase initialization time,
+    /// disable this when you're simulating large code
+    #[serde(alias = "pcmg")]  // abbreviation
+    #[serde(default = "mwpm_default_configs::precompute_complete_model_graph")]
+    pub precompute_complete_model_graph: bool,
+    /// weight function, by default using [`WeightFunction::AutotuneImproved`]; note that the diagonal clusters grow proportional to the weights,
+    /// so [`WeightFunction::Unweighted`] lets the unlikely X and Y errors merge the clusters of different diagonals
+    #[serde(alias = "wf")]  // abbreviation
+    #[serde(default = "mwpm_default_configs::weight_function")]
+    pub weight_function: WeightFunction,
+    /// the `max_half_weight` of the residual union-find decoder, see [`UnionFindDecoderConfig`]
+    #[serde(alias = "mhw")]  // abbreviation
+    #[serde(default = "union_find_default_configs::max_half_weight")]
+    pub max_half_weight: usize,
+    /// disable residual decoding to test correctness under infinite bias
+    #[serde(default = "tailored_mwpm_default_configs::disable_residual_decoding")]
+    pub disable_residual_decoding: bool,
+}
+
+/// weighted union-find cluster growth on the positive and negative sub-graphs of the tailored model graph;
+/// node `index` of the positive sub-graph is `index + measurement_num` in the negative sub-graph, and the two copies of a virtual node are connected
+/// with a zero-weight edge, so that a cluster reaching the boundary in one sub-graph continues to grow in the other one
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagonalUnionFind {
+    /// the real and virtual nodes of a single sub-graph are indexed by [`PositionIndexer::measurement_index`]
+    pub position_indexer: Arc<PositionIndexer>,
+    /// the edges `(node1, node2, weight)` of both sub-graphs, together with the zero-weight edges between the two copies of virtual nodes
+    pub edges: Arc<Vec<(usize, usize, f64)>>,
+    /// the incident edges `(peer, edge index)` of each node
+    #[serde(skip)]
+    pub neighbors: Arc<Vec<Vec<(usize, usize)>>>,
+    /// the grown length of each edge
+    pub growth: Vec<f64>,
+    /// the clusters, where the cardinality counts the nontrivial measurements in both sub-graphs
+    pub union_find: DefaultUnionFind,
+    /// the boundary nodes of each cluster indexed by its root, i.e. the nodes with an edge not fully grown towards another cluster,
+    /// so that only the boundary of the odd clusters is visited when growing, just like [`UnionFindDecoder::cluster_boundaries`]
+    #[serde(skip)]
+    pub cluster_boundaries: Vec<Vec<usize>>,
+}
+
+impl DiagonalUnionFind {
+    /// build the sub-graphs out of the positive (`idx = 0`) and negative (`idx = 1`) edges of the tailored model graph
+    pub fn new(simulator: &Simulator, tailored_model_graph: &TailoredModelGraph) -> Self {
+        let position_indexer = PositionIndexer::new(simulator);
+        let node_num = position_indexer.measurement_num();
+        let mut edges = Vec::new();
+        for index in 0..node_num {
+            let position = position_indexer.measurement_position(index);
+            debug_assert!(tailored_model_graph.is_node_exist(position), "every measurement must be a node in the tailored model graph");
+            for idx in 0..2 {
+                let offset = idx * node_num;
+                for (target, edge) in tailored_model_graph.get_node_unwrap(position)[idx].edges.iter() {
+                    let target_index = position_indexer.measurement_index(target).expect("edge target must be a measurement");
+                    if edge.probability > 0. && index < target_index {  // the edges are added in both directions
+                        edges.push((index + offset, target_index + offset, edge.weight.max(0.)));
+                    }
+                }
+            }
+            if simulator.get_node_unwrap(position).is_virtual {
+                edges.push((index, index + node_num, 0.));
+            }
+        }
+        let mut neighbors = vec![Vec::new(); 2 * node_num];
+        for (edge_index, &(node1, node2, _)) in edges.iter().enumerate() {
+            neighbors[node1].push((node2, edge_index));
+            neighbors[node2].push((node1, edge_index));
+        }
+        Self {
+            union_find: DefaultUnionFind::new(2 * node_num),
+            cluster_boundaries: (0..2 * node_num).map(|index| vec![index]).collect(),
+            growth: vec![0.; edges.len()],
+            position_indexer: Arc::new(position_indexer),
+            edges: Arc::new(edges),
+            neighbors: Arc::new(neighbors),
+        }
+    }
+
+    /// grow the clusters until they are all even or cannot grow anymore
+    pub fn grow_clusters(&mut self, sparse_measurement: &SparseMeasurement) {
+        let node_num = self.position_indexer.measurement_num();
+        self.union_find.clear();
+        self.growth.fill(0.);
+        for (index, boundaries) in self.cluster_boundaries.iter_mut().enumerate() {
+            boundaries.clear();
+            boundaries.push(index);
+        }
+        for position in sparse_measurement.iter() {
+            let index = self.position_indexer.measurement_index(position).expect("nontrivial measurement must be indexed");
+            self.union_find.get_mut(index).cardinality = 1;
+            self.union_find.get_mut(index + node_num).cardinality = 1;
+        }
+        let fusion_list: Vec<(usize, usize)> = self.edges.iter().filter(|(_, _, weight)| *weight <= 0.).map(|&(node1, node2, _)| (node1, node2)).collect();
+        self.merge_clusters(&fusion_list);
+        let mut odd_clusters: Vec<usize> = (0..2 * node_num).filter(|&index| self.union_find.find(index) == index
+            && self.union_find.get(index).cardinality % 2 == 1).collect();
+        while !odd_clusters.is_empty() {
+            // every odd cluster grows along its boundary edges at the same speed, until the next edge is fully grown;
+            // an edge between two odd clusters is visited from both sides and thus grows twice as fast
+            let mut growing_edges = Vec::new();
+            let mut step = f64::MAX;
+            for &cluster in odd_clusters.iter() {
+                for &node in self.cluster_boundaries[cluster].iter() {
+                    for &(peer, edge_index) in self.neighbors[node].iter() {
+                        let weight = self.edges[edge_index].2;
+                        if self.growth[edge_index] >= weight {
+                            continue
+                        }
+                        let peer_cluster = self.union_find.find(peer);
+                        if peer_cluster == cluster {
+                            continue
+                        }
+                        let speed = 1 + self.union_find.get(peer_cluster).cardinality % 2;
+                        step = step.min((weight - self.growth[edge_index]) / speed as f64);
+                        growing_edges.push(edge_index);
+                    }
+                }
+            }
+            if growing_edges.is_empty() {
+                break
+            }
+            let mut fusion_list = Vec::new();
+            for edge_index in growing_edges.into_iter() {
+                let (node1, node2, weight) = self.edges[edge_index];
+                if self.growth[edge_index] >= weight {  // already fully grown from the other side
+                    continue
+                }
+                self.growth[edge_index] += step;
+                if self.growth[edge_index] >= weight * (1. - 1e-9) {
+                    self.growth[edge_index] = weight;
+                    fusion_list.push((node1, node2));
+                }
+            }
+            self.merge_clusters(&fusion_list);
+            // an even cluster never grows, so the odd clusters after merging are always among the previous ones
+            for cluster in odd_clusters.iter_mut() {
+                *cluster = self.union_find.find(*cluster);
+            }
+            odd_clusters.sort_unstable();
+            odd_clusters.dedup();
+            odd_clusters.retain(|&cluster| self.union_find.immutable_get(cluster).cardinality % 2 == 1);
+        }
+    }
+
+    /// union the clusters connected by the fully grown edges, and drop the nodes of the merged clusters that are no longer on the boundary
+    fn merge_clusters(&mut self, fusion_list: &[(usize, usize)]) {
+        let mut merged_clusters = Vec::new();
+        for &(node1, node2) in fusion_list.iter() {
+            let (root1, root2) = (self.union_find.find(node1), self.union_find.find(node2));
+            if root1 == root2 {
+                continue
+            }
+            self.union_find.union(root1, root2);
+            let (root, child) = if self.union_find.find(root1) == root1 { (root1, root2) } else { (root2, root1) };
+            let mut child_boundaries = std::mem::take(&mut self.cluster_boundaries[child]);
+            self.cluster_boundaries[root].append(&mut child_boundaries);
+            merged_clusters.push(root);
+        }
+        for cluster in merged_clusters.into_iter() {
+            let cluster = self.union_find.find(cluster);
+            let mut boundaries = std::mem::take(&mut self.cluster_boundaries[cluster]);
+            boundaries.retain(|&node| self.neighbors[node].iter().any(|&(peer, edge_index)| {
+                self.growth[edge_index] < self.edges[edge_index].2 && self.union_find.immutable_find(peer) != cluster
+            }));
+            self.cluster_boundaries[cluster] = boundaries;
+        }
+    }
+
+    /// grow the clusters and peel a spanning forest of the fully grown edges in each cluster, which gives the positive (`[0]`) and negative (`[1]`)
+    /// matchings of the nontrivial measurements and virtual nodes; a path going across the zero-weight edge of a virtual node matches both copies of it,
+    /// and the nontrivial measurements of an odd cluster that cannot grow anymore are partially left unmatched
+    pub fn find_matchings(&mut self, sparse_measurement: &SparseMeasurement) -> [Vec<(Position, Position)>; 2] {
+        self.grow_clusters(sparse_measurement);
+        let node_num = self.position_indexer.measurement_num();
+        // spanning forest of the fully grown edges, starting from the nontrivial measurements
+        let mut parent: Vec<Option<usize>> = vec![None; 2 * node_num];
+        let mut visited = vec![false; 2 * node_num];
+        let mut order = Vec::new();
+        for root in sparse_measurement.iter().flat_map(|position| {
+            let index = self.position_indexer.measurement_index(position).expect("nontrivial measurement must be indexed");
+            [index, index + node_num]
+        }) {
+            if visited[root] {
+                continue
+            }
+            visited[root] = true;
+            let mut head = order.len();
+            order.push(root);
+            while head < order.len() {
+                let node = order[head];
+                head += 1;
+                for &(peer, edge_index) in self.neighbors[node].iter() {
+                    if !visited[peer] && self.growth[edge_index] >= self.edges[edge_index].2 {
+                        visited[peer] = true;
+                        parent[peer] = Some(node);
+                        order.push(peer);
+                    }
+                }
+            }
+        }
+        // peel the forest from the leaves: a pending endpoint moves towards the root until it meets another one
+        let mut matchings = [Vec::new(), Vec::new()];
+        let mut pending: Vec<Option<usize>> = vec![None; 2 * node_num];
+        let arrive = |matchings: &mut [Vec<(Position, Position)>; 2], pending: &mut Vec<Option<usize>>, node: usize, endpoint: usize| {
+            match pending[node].take() {
+                Some(peer) => matchings[node / node_num].push((self.position_indexer.measurement_position(peer % node_num).clone(), self.position_indexer.measurement_position(endpoint % node_num).clone())),
+                None => { pending[node] = Some(endpoint); },
+            }
+        };
+        for position in sparse_measurement.iter() {
+            let index = self.position_indexer.measurement_index(position).expect("nontrivial measurement must be indexed");
+            arrive(&mut matchings, &mut pending, index, index);
+            arrive(&mut matchings, &mut pending, index + node_num, index + node_num);
+        }
+        for &node in order.iter().rev() {
+            if let (Some(endpoint), Some(parent)) = (pending[node], parent[node]) {
+                pending[node] = None;
+                if node % node_num == parent % node_num {  // across the zero-weight edge of a virtual node
+                    matchings[node / node_num].push((self.position_indexer.measurement_position(endpoint % node_num).clone(), self.position_indexer.measurement_position(node % node_num).clone()));
+                    arrive(&mut matchings, &mut pending, parent, parent);
+                } else {
+                    arrive(&mut matchings, &mut pending, parent, endpoint);
+                }
+            }
+        }
+        matchings
+    }
+}
+
+impl TailoredUnionFindDecoder {
+    /// create a new tailored union-find decoder with decoder configuration
+    pub fn new(simulator: &Simulator, noise_model: Arc<NoiseModel>, decoder_configuration: &serde_json::Value, parallel: usize, use_brief_edge: bool) -> Self {
+        // read attribute of decoder configuration
+        let config: TailoredUnionFindDecoderConfig = serde_json::from_value(decoder_configuration.clone()).unwrap();
+        // build model graph
+        let mut simulator = simulator.clone();
+        let mut tailored_model_graph = TailoredModelGraph::new(&simulator);
+        tailored_model_graph.build(&mut simulator, noise_model.as_ref(), &config.weight_function);
+        let tailored_model_graph = Arc::new(tailored_model_graph);
+        // build complete model graph
+        let mut tailored_complete_model_graph = TailoredCompleteModelGraph::new(&simulator, Arc::clone(&tailored_model_graph));
+        tailored_complete_model_graph.precompute(&simulator, config.precompute_complete_model_graph, parallel);
+        let diagonal_union_find = DiagonalUnionFind::new(&simulator, &tailored_model_graph);
+        // build union-find decoder for residual decoding
+        let union_find_decoder = UnionFindDecoder::new(&simulator, noise_model, &json!({
+            "precompute_complete_model_graph": config.precompute_complete_model_graph,
+            "weight_function": config.weight_function,
+            "max_half_weight": config.max_half_weight,
+        }), parallel, use_brief_edge);
+        Self {
+            tailored_model_graph,
+            tailored_complete_model_graph,
+            diagonal_union_find,
+            union_find_decoder,
+            simulator: Arc::new(simulator),
+            config,
+        }
+    }
+
+    /// the tailored clusters, each as the list of nontrivial measurements and virtual nodes alternately matched in the positive and negative sub-graphs,
+    /// together with whether the cluster is complete; an incomplete cluster has nontrivial measurements left unmatched in some sub-graph
+    pub fn tailored_clusters(&mut self, sparse_measurement: &SparseMeasurement) -> Vec<(Vec<Position>, bool)> {
+        let [positive_matching, negative_matching] = self.diagonal_union_find.find_matchings(sparse_measurement);
+        // nontrivial measurements and the matched virtual nodes, where a position unmatched in a sub-graph is matched to itself
+        let mut matchings = BTreeMap::<Position, [Option<Position>; 2]>::new();
+        for position in sparse_measurement.iter() {
+            matchings.insert(position.clone(), [None, None]);
+        }
+        for (idx, matching) in [positive_matching, negative_matching].into_iter().enumerate() {
+            for (position1, position2) in matching.into_iter() {
+                matchings.entry(position1.clone()).or_insert([None, None])[idx] = Some(position2.clone());
+                matchings.entry(position2).or_insert([None, None])[idx] = Some(position1);
+            }
+        }
+        let mut clusters = Vec::new();
+        let mut visited = BTreeSet::<Position>::new();
+        for start in matchings.keys() {
+            if visited.contains(start) {
+                continue
+            }
+            // walk along the positive and negative matchings alternately, which either returns to the start or ends at an unmatched position
+            let walk = |idx: usize| -> (Vec<Position>, bool) {
+                let mut path = vec![start.clone()];
+                let mut idx = idx;
+                while let Some(peer) = matchings[path.last().unwrap()][idx].as_ref() {
+                    if peer == start {
+                        return (path, true)
+                    }
+                    path.push(peer.clone());
+                    idx = 1 - idx;
+                }
+                (path, false)
+            };
+            let (mut cluster, is_complete) = walk(0);
+            if !is_complete {
+                let (mut backward, _) = walk(1);
+                backward.reverse();
+                backward.pop();
+                backward.append(&mut cluster);
+                cluster = backward;
+            }
+            visited.extend(cluster.iter().cloned());
+            clusters.push((cluster, is_complete));
+        }
+        clusters
+    }
+
+    pub fn decode(&mut self, sparse_measurement: &SparseMeasurement) -> (SparseCorrection, serde_json::Value) {
+        let mut correction = SparseCorrection::new();
+        let mut time_tailored_clusters = 0.;
+        let mut time_residual_decoding = 0.;
+        let mut time_build_correction = 0.;
+        let mut charged_cluster_count = 0;
+        if sparse_measurement.len() > 0 {
+            let begin = Instant::now();
+            let clusters = self.tailored_clusters(sparse_measurement);
+            time_tailored_clusters += begin.elapsed().as_secs_f64();
+            let begin = Instant::now();
+            let mut residual_to_be_matched = Vec::new();
+            self.tailored_complete_model_graph.invalidate_previous_dijkstra();
+            for (cluster, is_complete) in clusters.into_iter() {
+                let stab_y_count = cluster.iter().filter(|position| self.simulator.get_node_unwrap(position).qubit_type == QubitType::StabY).count();
+                if is_complete && stab_y_count % 2 == 0 {
+                    // neutral cluster: match the X-type and Y-type stabilizers respectively in the order of the cluster
+                    let mut last_y: Option<&Position> = None;
+                    let mut last_x: Option<&Position> = None;
+                    for position in cluster.iter() {
+                        let last = match self.simulator.get_node_unwrap(position).qubit_type {
+                            QubitType::StabX => &mut last_x,
+                            QubitType::StabY => &mut last_y,
+                            qubit_type => unreachable!("tailored surface code only has X-type and Y-type stabilizers, found {:?} at {}", qubit_type, position),
+                        };
+                        match last.take() {
+                            Some(last_position) => {
+                                let matching_correction = self.tailored_complete_model_graph.build_correction_neutral_matching(last_position, position);
+                                correction.extend(&matching_correction);
+                            },
+                            None => { *last = Some(position); },
+                        }
+                    }
+                } else {
+                    // charged cluster is left to the residual decoding
+                    charged_cluster_count += 1;
+                    residual_to_be_matched.extend(cluster.into_iter().filter(|position| !self.simulator.get_node_unwrap(position).is_virtual));
+                }
+            }
+            time_build_correction += begin.elapsed().as_secs_f64();
+            let begin = Instant::now();
+            if !self.config.disable_residual_decoding && !residual_to_be_matched.is_empty() {
+                let (residual_correction, _) = self.union_find_decoder.decode(&SparseMeasurement::from_vec(&residual_to_be_matched));
+                correction.extend(&residual_correction);
+            }
+            time_residual_decoding += begin.elapsed().as_secs_f64();
+        }
+        (correction, json!({
+            "to_be_matched": sparse_measurement.len(),
+            "charged_cluster_count": charged_cluster_count,
+            "time_tailored_clusters": time_tailored_clusters,
+            "time_residual_decoding": time_residual_decoding,
+            "time_build_correction": time_build_correction,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::code_builder::*;
+    use super::super::types::ErrorType::*;
+    use super::super::reproducible_rand::Xoroshiro128StarStar;
+    use crate::rand_core::SeedableRng;
+
+    fn tailored_union_find_decoder_inf_bias(di: usize, dj: usize, p: f64) -> (Simulator, Arc<NoiseModel>, TailoredUnionFindDecoder) {
+        let bias_eta = 1e200;
+        let mut simulator = Simulator::new(CodeType::RotatedTailoredCode, CodeSize::new(0, di, dj));
+        code_builder_sanity_check(&simulator).unwrap();
+        let mut noise_model = NoiseModel::new(&simulator);
+        let px = p / (1. + bias_eta) / 2.;
+        let py = px;
+        let pz = p - 2. * px;
+        simulator.set_error_rates(&mut noise_model, px, py, pz, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let noise_model = Arc::new(noise_model);
+        let decoder = TailoredUnionFindDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1, false);
+        (simulator, noise_model, decoder)
+    }
+
+    #[test]
+    fn tailored_union_find_decoder_diagonal_sub_graphs() {  // cargo test tailored_union_find_decoder_diagonal_sub_graphs -- --nocapture
+        let (mut simulator, noise_model, mut decoder) = tailored_union_find_decoder_inf_bias(5, 5, 0.05);
+        // Z errors only connect stabilizers on the same diagonal: `i - j` in the positive sub-graph and `i + j` in the negative one
+        let diagonal_union_find = &decoder.diagonal_union_find;
+        let node_num = diagonal_union_find.position_indexer.measurement_num();
+        let mut likely_edge_count = [0, 0];
+        for &(node1, node2, weight) in diagonal_union_find.edges.iter() {
+            let (position1, position2) = (diagonal_union_find.position_indexer.measurement_position(node1 % node_num)
+                , diagonal_union_find.position_indexer.measurement_position(node2 % node_num));
+            if node1 % node_num == node2 % node_num {
+                assert!(simulator.get_node_unwrap(position1).is_virtual && weight == 0., "only virtual nodes connect the two sub-graphs");
+                continue
+            }
+            assert_eq!(node1 / node_num, node2 / node_num, "the edges never cross the sub-graphs");
+            if weight < 100. {  // X and Y errors are extremely unlikely under infinite bias
+                let idx = node1 / node_num;
+                likely_edge_count[idx] += 1;
+                let (i1, j1, i2, j2) = (position1.i as isize, position1.j as isize, position2.i as isize, position2.j as isize);
+                if idx == 0 {
+                    assert_eq!(i1 - j1, i2 - j2, "positive edge between {} and {} is not diagonal", position1, position2);
+                } else {
+                    assert_eq!(i1 + j1, i2 + j2, "negative edge between {} and {} is not diagonal", position1, position2);
+                }
+            }
+        }
+        assert!(likely_edge_count[0] > 0 && likely_edge_count[1] > 0);
+        // a single Z error in the bulk creates 4 defects, which split into two diagonal pairs in each sub-graph
+        simulator.clear_all_errors();
+        simulator.set_error_check(&noise_model, &pos!(0, 5, 5), &Z);
+        simulator.propagate_errors();
+        let sparse_measurement = simulator.generate_sparse_measurement();
+        assert_eq!(sparse_measurement.len(), 4);
+        let [positive_matching, negative_matching] = decoder.diagonal_union_find.find_matchings(&sparse_measurement);
+        assert_eq!(positive_matching.len(), 2);
+        assert_eq!(negative_matching.len(), 2);
+        for (position1, position2) in positive_matching.iter() {
+            assert_eq!(position1.i as isize - position1.j as isize, position2.i as isize - position2.j as isize);
+        }
+        for (position1, position2) in negative_matching.iter() {
+            assert_eq!(position1.i + position1.j, position2.i + position2.j);
+        }
+        let (correction, runtime_statistics) = decoder.decode(&sparse_measurement);
+        assert_eq!(runtime_statistics["charged_cluster_count"], json!(0));
+        code_builder_sanity_check_correction(&mut simulator, &correction).unwrap();
+        let (logical_i, logical_j) = simulator.validate_correction(&correction);
+        assert!(!logical_i && !logical_j);
+    }
+
+    /// call `callback` on every combination of `k` elements out of `0..n`
+    fn for_each_combination(n: usize, k: usize, combination: &mut Vec<usize>, callback: &mut dyn FnMut(&Vec<usize>)) {
+        if combination.len() == k {
+            callback(combination);
+            return
+        }
+        let start = combination.last().map(|&last| last + 1).unwrap_or(0);
+        for element in start..n {
+            combination.push(element);
+            for_each_combination(n, k, combination, callback);
+            combination.pop();
+        }
+    }
+
+    #[test]
+    fn tailored_union_find_decoder_code_capacity_inf_bias() {  // cargo test tailored_union_find_decoder_code_capacity_inf_bias -- --nocapture
+        // under infinite bias, the effective distance of a d x d code is 2d - 1, while that of coprime di x dj code is di * dj;
+        // the decoder corrects all Z errors of weight beyond (min(di, dj) - 1) / 2 in the following cases
+        for (di, dj, max_weight) in [(3, 3, 2), (3, 5, 3), (5, 5, 4)] {
+            let (mut simulator, noise_model, mut decoder) = tailored_union_find_decoder_inf_bias(di, dj, 0.05);
+            let mut data_qubits = Vec::new();
+            simulator_iter_real!(simulator, position, node, t => 0, if node.qubit_type == QubitType::Data {
+                data_qubits.push(position.clone());
+            });
+            for weight in 1..=max_weight {
+                for_each_combination(data_qubits.len(), weight, &mut Vec::new(), &mut |combination| {
+                    simulator.clear_all_errors();
+                    for &index in combination.iter() {
+                        simulator.set_error_check(&noise_model, &data_qubits[index], &Z);
+                    }
+                    simulator.propagate_errors();
+                    let sparse_measurement = simulator.generate_sparse_measurement();
+                    let (correction, _runtime_statistics) = decoder.decode(&sparse_measurement);
+                    let (logical_i, logical_j) = simulator.validate_correction(&correction);
+                    assert!(!logical_i && !logical_j, "{} x {} code failed to correct Z errors on {:?}", di, dj
+                        , combination.iter().map(|&index| data_qubits[index].clone()).collect::<Vec<_>>());
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn tailored_union_find_decoder_coprime_scaling() {  // cargo test tailored_union_find_decoder_coprime_scaling -- --nocapture
+        // the coprime 3 x 5 code has the same min(di, dj) as the 3 x 3 code, but a much lower logical error rate under infinite bias
+        let p = 0.15;
+        let shots = 3000;
+        let mut failed_counts = Vec::new();
+        for (di, dj) in [(3, 3), (3, 5)] {
+            let (mut simulator, noise_model, mut decoder) = tailored_union_find_decoder_inf_bias(di, dj, p);
+            simulator.rng = Xoroshiro128StarStar::seed_from_u64(1);
+            let mut failed_count = 0;
+            for _ in 0..shots {
+                simulator.generate_random_errors(&noise_model);
+                let sparse_measurement = simulator.generate_sparse_measurement();
+                let (correction, _runtime_statistics) = decoder.decode(&sparse_measurement);
+                let (logical_i, logical_j) = simulator.validate_correction(&correction);
+                if logical_i || logical_j {
+                    failed_count += 1;
+                }
+            }
+            println!("{} x {} code: {} failed out of {} shots", di, dj, failed_count, shots);
+            failed_counts.push(failed_count);
+        }
+        assert!(failed_counts[0] > 0, "3 x 3 code should fail at p = {}", p);
+        assert!(failed_counts[1] * 2 < failed_counts[0], "3 x 5 code should fail much less often than 3 x 3 code");
+    }
+
+}
@@ -0,0 +1,221 @@
+//! two-stage union-find then MWPM decoder
+//!
+//! Most clusters of the union-find decoder at low physical error rates are easy: a single defect next to the boundary, or a pair of adjacent
+//! defects far from the boundary, where the union-find correction is the same as the minimum-weight one. This decoder grows the union-find
+//! clusters first and corrects the easy clusters directly, while the defects of the ambiguous clusters (more than `max_easy_defects` defects,
+//! or an even cluster that also touches the boundary) are handed to the MWPM decoder together. Since the MWPM decoder only sees a few defects,
+//! usually none at all, it runs much faster than decoding the whole syndrome, while keeping most of the accuracy of MWPM.
+//!
+
+use serde::{Serialize, Deserialize};
+use super::simulator::*;
+use super::noise_model::*;
+use super::model_graph::*;
+use super::decoder_mwpm::*;
+use super::decoder_union_find::*;
+use super::erasure_graph::*;
+use super::serde_json;
+use std::sync::{Arc};
+use std::time::Instant;
+
+/// two-stage union-find then MWPM decoder, initialized and cloned for multiple threads
+#[derive(Debug, Clone, Serialize)]
+pub struct UfThenMwpmDecoder {
+    /// union-find decoder to grow the clusters and correct the easy ones
+    pub union_find_decoder: UnionFindDecoder,
+    /// MWPM decoder to decode the defects of the ambiguous clusters
+    pub mwpm_decoder: MWPMDecoder,
+    /// save configuration for later usage
+    pub config: UfThenMwpmDecoderConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UfThenMwpmDecoderConfig {
+    /// build complete model graph at first, but this will consume O(N^2) memory and increase initialization time,
+    /// disable this when you're simulating large code
+    #[serde(alias = "pcmg")]  // abbreviation
+    #[serde(default = "mwpm_default_configs::precompute_complete_model_graph")]
+    pub precompute_complete_model_graph: bool,
+    /// weight function, by default using [`WeightFunction::AutotuneImproved`]
+    #[serde(alias = "wf")]  // abbreviation
+    #[serde(default = "mwpm_default_configs::weight_function")]
+    pub weight_function: WeightFunction,
+    /// combined probability can improve accuracy, but will cause probabilities differ a lot even in the case of i.i.d. noise model
+    #[serde(alias = "ucp")]  // abbreviation
+    #[serde(default = "mwpm_default_configs::use_combined_probability")]
+    pub use_combined_probability: bool,
+    /// the `max_half_weight` of the union-find decoder, see [`UnionFindDecoderConfig`]
+    #[serde(alias = "mhw")]  // abbreviation
+    #[serde(default = "union_find_default_configs::max_half_weight")]
+    pub max_half_weight: usize,
+    /// the `exact_matching_max_defects` of the MWPM decoder, see [`MWPMDecoderConfig`]
+    #[serde(alias = "emmd")]  // abbreviation
+    #[serde(default = "mwpm_default_configs::exact_matching_max_defects")]
+    pub exact_matching_max_defects: usize,
+    /// clusters with more defects are ambiguous and decoded by MWPM; set to 0 to decode every defect by MWPM
+    #[serde(alias = "med")]  // abbreviation
+    #[serde(default = "uf_then_mwpm_default_configs::max_easy_defects")]
+    pub max_easy_defects: usize,
+}
+
+pub mod uf_then_mwpm_default_configs {
+    pub fn max_easy_defects() -> usize { 2 }
+}
+
+impl UfThenMwpmDecoder {
+    /// create a new two-stage decoder with decoder configuration
+    pub fn new(simulator: &Simulator, noise_model: Arc<NoiseModel>, decoder_configuration: &serde_json::Value, parallel: usize, use_brief_edge: bool) -> Self {
+        // read attribute of decoder configuration
+        let config: UfThenMwpmDecoderConfig = serde_json::from_value(decoder_configuration.clone()).unwrap();
+        // both decoders share the same model graph
+        let mut simulator = simulator.clone();
+        let mut model_graph = ModelGraph::new(&simulator);
+        model_graph.build(&mut simulator, Arc::clone(&noise_model), &config.weight_function, parallel, config.use_combined_probability, use_brief_edge);
+        let union_find_decoder = UnionFindDecoder::new_with_model_graph(&simulator, Arc::clone(&noise_model), &json!({
+            "precompute_complete_model_graph": config.precompute_complete_model_graph,
+            "weight_function": config.weight_function,
+            "use_combined_probability": config.use_combined_probability,
+            "max_half_weight": config.max_half_weight,
+        }), parallel, use_brief_edge, Some(model_graph.clone()));
+        let mwpm_decoder = MWPMDecoder::new_with_model_graph(&simulator, noise_model, &json!({
+            "precompute_complete_model_graph": config.precompute_complete_model_graph,
+            "weight_function": config.weight_function,
+            "use_combined_probability": config.use_combined_probability,
+            "exact_matching_max_defects": config.exact_matching_max_defects,
+        }), parallel, use_brief_edge, Some(model_graph));
+        Self {
+            union_find_decoder,
+            mwpm_decoder,
+            config,
+        }
+    }
+
+    /// decode given measurement results
+    #[allow(dead_code)]
+    pub fn decode(&mut self, sparse_measurement: &SparseMeasurement) -> (SparseCorrection, serde_json::Value) {
+        self.decode_with_erasure(sparse_measurement, &SparseErasures::new())
+    }
+
+    /// decode given measurement results and detected erasures
+    pub fn decode_with_erasure(&mut self, sparse_measurement: &SparseMeasurement, sparse_detected_erasures: &SparseErasures) -> (SparseCorrection, serde_json::Value) {
+        let erasure_edges = sparse_detected_erasures.get_erasure_edges(&self.union_find_decoder.erasure_graph);
+        self.decode_with_erasure_edges(sparse_measurement, &erasure_edges)
+    }
+
+    /// whether the correction of a union-find cluster may differ from the minimum-weight one
+    pub fn is_ambiguous_cluster(&self, defect_count: usize, is_touching_boundary: bool) -> bool {
+        defect_count > self.config.max_easy_defects || (defect_count.is_multiple_of(2) && is_touching_boundary)
+    }
+
+    /// decode given measurement results and the edges whose weights are 0 in this shot, caused by erasures
+    pub fn decode_with_erasure_edges(&mut self, sparse_measurement: &SparseMeasurement, erasure_edges: &[ErasureEdge]) -> (SparseCorrection, serde_json::Value) {
+        let mut correction = SparseCorrection::new();
+        // grow the union-find clusters and correct the easy ones
+        let begin = Instant::now();
+        let (_, _, timed_out) = self.union_find_decoder.grow_clusters(sparse_measurement, erasure_edges, None);
+        debug_assert!(!timed_out, "never times out without deadline");
+        self.union_find_decoder.complete_model_graph.invalidate_previous_dijkstra();
+        let clusters = self.union_find_decoder.collect_clusters(sparse_measurement);
+        let cluster_count = clusters.len();
+        let mut ambiguous_clusters = 0;
+        let mut ambiguous_defects = SparseMeasurement::new();
+        for (root, error_syndromes) in clusters.into_iter() {
+            let is_touching_boundary = self.union_find_decoder.union_find.get(root).touching_boundary_index != usize::MAX;
+            if self.is_ambiguous_cluster(error_syndromes.len(), is_touching_boundary) {
+                ambiguous_clusters += 1;
+                for index in error_syndromes.into_iter() {
+                    ambiguous_defects.insert_defect_measurement(self.union_find_decoder.position_indexer.stabilizers.position(index));
+                }
+            } else {
+                self.union_find_decoder.build_cluster_correction(root, error_syndromes, false, &mut correction);
+            }
+        }
+        let time_union_find = begin.elapsed().as_secs_f64();
+        // decode the defects of the ambiguous clusters altogether, because they may match with each other
+        let begin = Instant::now();
+        if ambiguous_defects.len() > 0 {
+            let (mwpm_correction, _) = self.mwpm_decoder.decode_with_erasure_edges(&ambiguous_defects, erasure_edges, None)
+                .expect("never times out without deadline");
+            correction.extend(&mwpm_correction);
+        }
+        let time_mwpm = begin.elapsed().as_secs_f64();
+        (correction, json!({
+            "clusters": cluster_count,
+            "ambiguous_clusters": ambiguous_clusters,
+            "mwpm_defects": ambiguous_defects.len(),
+            "time_union_find": time_union_find,
+            "time_mwpm": time_mwpm,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::code_builder::*;
+    use super::super::tool::*;
+    use super::super::types::ErrorType::*;
+    use super::super::reproducible_rand::Xoroshiro128StarStar;
+    use crate::rand_core::SeedableRng;
+
+    fn uf_then_mwpm_decoder_code_capacity(code_type: CodeType, d: usize, px: f64, pz: f64) -> (Simulator, Arc<NoiseModel>) {
+        let mut simulator = Simulator::new(code_type, CodeSize::new(0, d, d));
+        code_builder_sanity_check(&simulator).unwrap();
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, px, 0., pz, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        (simulator, Arc::new(noise_model))
+    }
+
+    #[test]
+    fn uf_then_mwpm_decoder_easy_clusters() {  // cargo test uf_then_mwpm_decoder_easy_clusters -- --nocapture
+        let (mut simulator, noise_model) = uf_then_mwpm_decoder_code_capacity(CodeType::StandardPlanarCode, 7, 0.01, 0.01);
+        let mut decoder = UfThenMwpmDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1, false);
+        // isolated single errors form easy clusters that never reach the MWPM decoder
+        simulator.clear_all_errors();
+        simulator.set_error_check(&noise_model, &pos!(0, 5, 7), &X);
+        simulator.set_error_check(&noise_model, &pos!(0, 11, 3), &Z);
+        simulator.propagate_errors();
+        let sparse_measurement = simulator.generate_sparse_measurement();
+        let (correction, runtime_statistics) = decoder.decode(&sparse_measurement);
+        println!("{}", runtime_statistics);
+        assert_eq!(runtime_statistics["mwpm_defects"], json!(0));
+        assert_eq!(simulator.validate_correction(&correction), (false, false));
+        // with no easy cluster, all the defects are decoded by the MWPM decoder, giving the same correction as pure MWPM
+        let (mut simulator, noise_model) = uf_then_mwpm_decoder_code_capacity(CodeType::StandardPlanarCode, 7, 0.02, 0.02);
+        let mut decoder = UfThenMwpmDecoder::new(&simulator, Arc::clone(&noise_model), &json!({ "max_easy_defects": 0, "emmd": 24 }), 1, false);
+        let mut mwpm_decoder = MWPMDecoder::new(&simulator, Arc::clone(&noise_model), &json!({ "emmd": 24 }), 1, false);
+        simulator.rng = Xoroshiro128StarStar::seed_from_u64(0);
+        for _ in 0..100 {
+            simulator.generate_random_errors(&noise_model);
+            let sparse_measurement = simulator.generate_sparse_measurement();
+            let (correction, runtime_statistics) = decoder.decode(&sparse_measurement);
+            assert_eq!(runtime_statistics["mwpm_defects"], json!(sparse_measurement.len()));
+            assert_eq!(json!(correction), json!(mwpm_decoder.decode(&sparse_measurement).0));
+        }
+    }
+
+    #[test]
+    fn uf_then_mwpm_decoder_accuracy_and_speed() {  // cargo test uf_then_mwpm_decoder_accuracy_and_speed -- --nocapture
+        // bit-flip errors only, so that pure MWPM rarely sees more than the 24 defects of the exact matcher
+        let (mut simulator, noise_model) = uf_then_mwpm_decoder_code_capacity(CodeType::RotatedPlanarCode, 11, 0.05, 0.);
+        let decoder_config = json!({ "exact_matching_max_defects": 24 });
+        let decoders = vec![
+            GeneralDecoder::UnionFind(UnionFindDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1, false)),
+            GeneralDecoder::UfThenMwpm(UfThenMwpmDecoder::new(&simulator, Arc::clone(&noise_model), &decoder_config, 1, false)),
+            GeneralDecoder::MWPM(MWPMDecoder::new(&simulator, Arc::clone(&noise_model), &decoder_config, 1, false)),
+        ];
+        simulator.rng = Xoroshiro128StarStar::seed_from_u64(1);
+        let mut harness = SharedSyndromeHarness::new(simulator, noise_model, decoders);
+        let statistics = harness.run(3000);
+        let failed: Vec<usize> = (0..3).map(|idx| statistics.failed[idx][idx]).collect();
+        println!("failed [uf, uf-then-mwpm, mwpm]: {:?}, decode time: {:?}", failed, harness.decode_time);
+        // the accuracy is between pure union-find and pure MWPM
+        assert!(failed[2] <= failed[1] && failed[1] < failed[0]);
+        // decoding only the ambiguous clusters with MWPM is faster than pure MWPM
+        assert!(harness.decode_time[1] < harness.decode_time[2]);
+    }
+
+}
@@ -11,13 +11,13 @@ use super::decoder_mwpm::*;
 use super::union_find::*;
 use std::sync::{Arc};
 use std::time::Instant;
-use std::collections::{HashMap, BTreeMap};
+use std::collections::BTreeMap;
 use super::either::Either;
-use crate::rand::thread_rng;
-use crate::rand::seq::SliceRandom;
 use crate::parking_lot::RwLock;
 use crate::derive_more::{Deref, DerefMut};
 use super::erasure_graph::*;
+use super::types::DecodingError;
+use super::position_indexer::*;
 
 /// MWPM decoder, initialized and cloned for multiple threads
 #[derive(Debug, Clone, Serialize)]
@@ -28,10 +28,8 @@ pub struct UnionFindDecoder {
     pub erasure_graph: Arc<ErasureGraph>,
     /// complete model graph each thread maintain its own precomputed data
     pub complete_model_graph: CompleteModelGraph,
-    /// index to position mapping (immutable shared), index is the one used in the union-find algorithm
-    pub index_to_position: Arc<Vec<Position>>,
-    /// position to index mapping (immutable shared)
-    pub position_to_index: Arc<HashMap<Position, usize>>,
+    /// position indices (immutable shared), the stabilizer index is the one used in the union-find algorithm
+    pub position_indexer: Arc<PositionIndexer>,
     /// decoder nodes, each corresponds to a node in the model graph; each instance needs to modify node information and thus not shared
     pub nodes: NodeVec,
     /// union-find algorithm
@@ -58,6 +56,10 @@ pub struct UnionFindDecoder {
     pub count_memory_access: usize,  // use the same way to count as in AFS paper
     /// save configuration for later usage
     pub config: UnionFindDecoderConfig,
+    /// an immutably shared simulator, used to rebuild the graphs in [`UnionFindDecoder::decode_region`]
+    pub simulator: Arc<Simulator>,
+    /// wall-clock budget of decoding in microseconds, after which the clusters stop growing and the correction is built from the current clusters
+    pub decode_timeout_us: Option<u64>,
     /// internal cache used by iteration
     fusion_list: Vec<(usize, usize)>,
     /// internal variable that works like `odd_clusters_set: BTreeSet<usize>` but with constant performance
@@ -96,7 +98,7 @@ impl Clone for NodeVec {
 
 #[derive(Debug, Clone, Serialize)]
 pub struct UnionFindDecoderNode {
-    /// the index used in union-find algorithm, can be used to query position using [`UnionFindDecoder::index_to_position`]
+    /// the index used in union-find algorithm, can be used to query position using [`UnionFindDecoder::position_indexer`]
     pub index: usize,
     /// whether this stabilizer has detected a error
     pub is_error_syndrome: bool,
@@ -107,6 +109,8 @@ pub struct UnionFindDecoderNode {
     pub boundary_length: Option<usize>,
     /// increased region towards boundary, only valid when `node.boundary_length` is `Some(_)`
     pub boundary_increased: usize,
+    /// the boundary has zero probability and is only present because it can be erased, so it never grows unless erased in the shot
+    pub is_boundary_erasure_only: bool,
     /// whether visited ever in the algorithm, recorded as statistics
     pub node_visited: bool,
     /// internal variable that works like `odd_clusters_set: BTreeSet<usize>` but with constant performance
@@ -125,6 +129,13 @@ impl UnionFindDecoderNode {
         }
         None
     }
+    /// whether the cluster can still grow from this node towards the boundary
+    fn is_boundary_growable(&self) -> bool {
+        match self.boundary_length {
+            Some(boundary_length) => self.boundary_increased < boundary_length && !self.is_boundary_erasure_only,
+            None => false,
+        }
+    }
 }
 
 pub type NeighborEdgePtr = Arc<RwLock<NeighborEdge>>;
@@ -136,6 +147,15 @@ pub struct NeighborEdge {
     pub increased: usize,
     /// the total length of this edge. if the sum of the `increased` of two partial edges is no less than `length`, then two vertices are merged
     pub length: usize,
+    /// the edge has zero probability and is only present because it can be erased, so it never grows unless erased in the shot
+    pub is_erasure_only: bool,
+}
+
+impl NeighborEdge {
+    /// whether the clusters can still grow along this edge
+    fn is_growable(&self) -> bool {
+        self.increased < self.length && !self.is_erasure_only
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -167,17 +187,29 @@ pub struct UnionFindDecoderConfig {
     #[serde(alias = "bsbc")]  // abbreviation
     #[serde(default = "union_find_default_configs::benchmark_skip_building_correction")]
     pub benchmark_skip_building_correction: bool,
+    /// report the number of clusters and the mean and max size (the number of defects, i.e. the cardinality) of the final clusters of each shot
+    /// in the runtime statistics, see [`UnionFindDecoder::cluster_sizes`]
+    #[serde(alias = "rcs")]  // abbreviation
+    #[serde(default = "union_find_default_configs::report_cluster_size")]
+    pub report_cluster_size: bool,
 }
 
 pub mod union_find_default_configs {
     pub fn max_half_weight() -> usize { 1 }
     pub fn use_real_weighted() -> bool { false }
     pub fn benchmark_skip_building_correction() -> bool { false }
+    pub fn report_cluster_size() -> bool { false }
 }
 
 impl UnionFindDecoder {
     /// create a new MWPM decoder with decoder configuration
     pub fn new(simulator: &Simulator, noise_model: Arc<NoiseModel>, decoder_configuration: &serde_json::Value, parallel: usize, use_brief_edge: bool) -> Self {
+        Self::new_with_model_graph(simulator, noise_model, decoder_configuration, parallel, use_brief_edge, None)
+    }
+
+    /// create a new union-find decoder with a model graph built in advance, see [`MWPMDecoder::new_with_model_graph`]
+    pub fn new_with_model_graph(simulator: &Simulator, noise_model: Arc<NoiseModel>, decoder_configuration: &serde_json::Value, parallel: usize
+            , use_brief_edge: bool, model_graph: Option<ModelGraph>) -> Self {
         // read attribute of decoder configuration
         let config: UnionFindDecoderConfig = serde_json::from_value(decoder_configuration.clone()).unwrap();
         if config.use_real_weighted {
@@ -185,49 +217,53 @@ impl UnionFindDecoder {
         }
         // build model graph
         let mut simulator = simulator.clone();
-        let mut model_graph = ModelGraph::new(&simulator);
-        model_graph.build(&mut simulator, Arc::clone(&noise_model), &config.weight_function, parallel, config.use_combined_probability, use_brief_edge);
+        let model_graph = model_graph.unwrap_or_else(|| {
+            let mut model_graph = ModelGraph::new(&simulator);
+            model_graph.build(&mut simulator, Arc::clone(&noise_model), &config.weight_function, parallel, config.use_combined_probability, use_brief_edge);
+            model_graph
+        });
         let model_graph = Arc::new(model_graph);
         // build erasure graph
         let mut erasure_graph = ErasureGraph::new(&simulator);
         erasure_graph.build(&mut simulator, Arc::clone(&noise_model), parallel);
+        if erasure_graph.has_erasure(&simulator) {
+            if let Err(error) = validate_graph_consistency(&simulator, &model_graph, &erasure_graph, &noise_model) {
+                panic!("the erasure graph is inconsistent with the model graph: {}", error);
+            }
+        }
         let erasure_graph = Arc::new(erasure_graph);
+        Self::from_graphs(Arc::new(simulator), model_graph, erasure_graph, config, parallel)
+    }
+
+    /// build the union-find graph from the model graph and the erasure graph
+    fn from_graphs(simulator: Arc<Simulator>, model_graph: Arc<ModelGraph>, erasure_graph: Arc<ErasureGraph>, config: UnionFindDecoderConfig
+            , parallel: usize) -> Self {
         // build complete model graph
         let mut complete_model_graph = CompleteModelGraph::new(&simulator, Arc::clone(&model_graph));
         complete_model_graph.optimize_weight_greater_than_sum_boundary = false;  // disable this optimization for any matching pair to exist
         complete_model_graph.precompute(&simulator, config.precompute_complete_model_graph, parallel);
         // build union-find graph
-        let mut index_to_position = Vec::<Position>::new();
-        let mut position_to_index = HashMap::<Position, usize>::with_capacity(simulator.height * simulator.vertical * simulator.horizontal);
+        let position_indexer = PositionIndexer::new(&simulator);
         let mut nodes = Vec::<UnionFindDecoderNode>::new();
-        simulator_iter!(simulator, position, delta_t => simulator.measurement_cycles, if model_graph.is_node_exist(position) {
-            let index = nodes.len();
+        for (index, position) in position_indexer.stabilizers.positions().iter().enumerate() {
+            debug_assert!(model_graph.is_node_exist(position), "every stabilizer must be a node in the model graph");
             let node = UnionFindDecoderNode {
                 index: index,
                 is_error_syndrome: false,
                 neighbors: Vec::new(),  // updated later
                 boundary_length: None,  // updated later
                 boundary_increased: 0,
+                is_boundary_erasure_only: false,  // updated later
                 node_visited: false,
                 odd_clusters_set_timestamp: 0,
                 shrunk_boundaries_timestamp: 0,
             };
-            index_to_position.push(position.clone());
             nodes.push(node);
-        });
-        if false {
-            // shuffle the order of nodes to reduce the effect of cache
-            index_to_position.shuffle(&mut thread_rng());
-            // eprintln!("index_to_position: {:?}", index_to_position);
-        }
-        for index in 0..nodes.len() {
-            let position = index_to_position[index].clone();
-            position_to_index.insert(position, index);
         }
         // calculate scaling factor of edges
         let mut maximum_weight = 0.;
         for index in 0..nodes.len() {
-            let position = index_to_position.get(index).unwrap();
+            let position = position_indexer.stabilizers.position(index);
             let model_graph_node = model_graph.get_node_unwrap(position);
             for (_peer_position, edge) in model_graph_node.edges.iter() {
                 if edge.probability > 0. && edge.weight > maximum_weight {
@@ -262,35 +298,36 @@ impl UnionFindDecoder {
         let mut cluster_boundaries = Vec::with_capacity(nodes.len());
         let mut idle_cluster_boundaries = Vec::with_capacity(nodes.len());
         for index in 0..nodes.len() {
-            let position = index_to_position.get(index).unwrap();
+            let position = position_indexer.stabilizers.position(index);
             let model_graph_node = model_graph.get_node_unwrap(position);
+            // zero-probability edges are in the model graph only because they can be erased, keep them as non-growable edges so that
+            // erasures can be applied but the clusters never grow along them otherwise; dropping them instead would leave erasure-only
+            // noise models (p = 0, pe > 0) without any edge to decode along
             for (peer_position, edge) in model_graph_node.edges.iter() {
-                if edge.probability > 0. {
-                    let peer_index = position_to_index[peer_position];
-                    let node = nodes.get_mut(index).unwrap();
-                    assert!(node.index_to_neighbor(&peer_index).is_none(), "duplicate edge forbidden");
-                    let edge_ptr = {  // fetch the same edge ptr from peer, if exists
-                        let peer_node = nodes.get_mut(peer_index).unwrap();
-                        match peer_node.index_to_neighbor(&index) {
-                            Some(index) => { Arc::clone(&peer_node.neighbors[index].1) },
-                            None => {
-                                Arc::new(RwLock::new(NeighborEdge {
-                                    increased: 0,
-                                    length: scale_weight(edge.weight),
-                                }))
-                            }
+                let peer_index = position_indexer.stabilizers.index(peer_position);
+                let node = nodes.get_mut(index).unwrap();
+                assert!(node.index_to_neighbor(&peer_index).is_none(), "duplicate edge forbidden");
+                let edge_ptr = {  // fetch the same edge ptr from peer, if exists
+                    let peer_node = nodes.get_mut(peer_index).unwrap();
+                    match peer_node.index_to_neighbor(&index) {
+                        Some(index) => { Arc::clone(&peer_node.neighbors[index].1) },
+                        None => {
+                            Arc::new(RwLock::new(NeighborEdge {
+                                increased: 0,
+                                length: scale_weight(edge.weight),
+                                is_erasure_only: edge.probability <= 0.,
+                            }))
                         }
-                    };
-                    let node = nodes.get_mut(index).unwrap();
-                    node.neighbors.push((peer_index, edge_ptr));
-                }
+                    }
+                };
+                let node = nodes.get_mut(index).unwrap();
+                node.neighbors.push((peer_index, edge_ptr));
             }
             match &model_graph_node.boundary {
                 Some(boundary) => {
-                    if boundary.probability > 0. {
-                        let node = nodes.get_mut(index).unwrap();
-                        node.boundary_length = Some(scale_weight(boundary.weight));
-                    }
+                    let node = nodes.get_mut(index).unwrap();
+                    node.boundary_length = Some(scale_weight(boundary.weight));
+                    node.is_boundary_erasure_only = boundary.probability <= 0.;
                 },
                 None => { }
             }
@@ -302,8 +339,7 @@ impl UnionFindDecoder {
             model_graph: model_graph,
             erasure_graph: erasure_graph,
             complete_model_graph: complete_model_graph,
-            index_to_position: Arc::new(index_to_position),
-            position_to_index: Arc::new(position_to_index),
+            position_indexer: Arc::new(position_indexer),
             nodes: NodeVec(nodes),
             union_find: union_find,
             odd_clusters: Vec::new(),
@@ -320,6 +356,8 @@ impl UnionFindDecoder {
             count_iteration: 0,
             count_memory_access: 0,
             config: config,
+            simulator,
+            decode_timeout_us: None,
             // internal caches
             fusion_list: Vec::new(),
             odd_clusters_set_active_timestamp: 0,
@@ -408,6 +446,16 @@ impl UnionFindDecoder {
         self.count_memory_access = 0;
     }
 
+    /// decode only the defects inside `region` on the model graph clipped to it, see [`MWPMDecoder::decode_region`]
+    pub fn decode_region(&self, sparse_measurement: &SparseMeasurement, region: &Region) -> (SparseCorrection, serde_json::Value) {
+        let mut model_graph = (*self.model_graph).clone();
+        model_graph.clip_to_region(&self.simulator, region);
+        let mut config = self.config.clone();
+        config.precompute_complete_model_graph = false;
+        let mut decoder = Self::from_graphs(Arc::clone(&self.simulator), Arc::new(model_graph), Arc::clone(&self.erasure_graph), config, 1);
+        decoder.decode(&region.clip_measurement(sparse_measurement))
+    }
+
     /// decode given measurement results
     #[allow(dead_code)]
     pub fn decode(&mut self, sparse_measurement: &SparseMeasurement) -> (SparseCorrection, serde_json::Value) {
@@ -416,12 +464,79 @@ impl UnionFindDecoder {
 
     /// decode given measurement results and detected erasures
     pub fn decode_with_erasure(&mut self, sparse_measurement: &SparseMeasurement, sparse_detected_erasures: &SparseErasures) -> (SparseCorrection, serde_json::Value) {
+        let deadline = self.decode_timeout_us.map(|decode_timeout_us| Instant::now() + std::time::Duration::from_micros(decode_timeout_us));
+        let erasure_edges = sparse_detected_erasures.get_erasure_edges(&self.erasure_graph);
+        self.decode_with_erasure_internal(sparse_measurement, &erasure_edges, deadline, true).expect("partial correction is returned on timeout")
+    }
+
+    /// decode given measurement results and detected erasures, checking the deadline in every growth iteration and aborting once it passes;
+    /// unlike `decode_timeout_us`, no partial correction is returned
+    pub fn decode_with_erasure_deadline(&mut self, sparse_measurement: &SparseMeasurement, sparse_detected_erasures: &SparseErasures
+            , deadline: Option<Instant>) -> Result<(SparseCorrection, serde_json::Value), DecodingError> {
+        let erasure_edges = sparse_detected_erasures.get_erasure_edges(&self.erasure_graph);
+        self.decode_with_erasure_edges(sparse_measurement, &erasure_edges, deadline)
+    }
+
+    /// decode given measurement results and the edges fully grown in this shot, caused by erasures or lost detectors
+    pub fn decode_with_erasure_edges(&mut self, sparse_measurement: &SparseMeasurement, erasure_edges: &[ErasureEdge]
+            , deadline: Option<Instant>) -> Result<(SparseCorrection, serde_json::Value), DecodingError> {
+        self.decode_with_erasure_internal(sparse_measurement, erasure_edges, deadline, false)
+    }
+
+    fn decode_with_erasure_internal(&mut self, sparse_measurement: &SparseMeasurement, erasure_edges: &[ErasureEdge]
+            , deadline: Option<Instant>, partial_correction_on_timeout: bool) -> Result<(SparseCorrection, serde_json::Value), DecodingError> {
+        let (time_prepare_decoders, time_run_to_stable, timed_out) = self.grow_clusters(sparse_measurement, erasure_edges, deadline);
+        if timed_out && !partial_correction_on_timeout {
+            return Err(DecodingError::Timeout)
+        }
+        // build correction based on the matching
+        let (time_build_correction, correction) = {
+            let begin = Instant::now();
+            let mut correction = SparseCorrection::new();
+            if !self.config.benchmark_skip_building_correction {
+                // invalidate previous cache to save memory
+                self.complete_model_graph.invalidate_previous_dijkstra();
+                // in order to build correction, first collect the nodes for each cluster, then build correction based on each correction
+                for (root, error_syndromes) in self.collect_clusters(sparse_measurement).into_iter() {
+                    self.build_cluster_correction(root, error_syndromes, timed_out, &mut correction);
+                }
+            }
+            (begin.elapsed().as_secs_f64(), correction)
+        };
+        let mut runtime_statistics = json!({
+            "time_run_to_stable": time_run_to_stable,
+            "time_prepare_decoders": time_prepare_decoders,
+            "time_uf_grow_step": self.time_uf_grow_step,
+            "time_uf_grow": self.time_uf_grow,
+            "count_uf_grow": self.count_uf_grow,
+            "time_uf_merge": self.time_uf_merge,
+            "time_uf_update": self.time_uf_update,
+            "time_uf_remove": self.time_uf_remove,
+            "time_build_correction": time_build_correction,
+            "count_node_visited": self.count_node_visited,
+            "count_iteration": self.count_iteration,
+            "count_memory_access": self.count_memory_access,
+            "timed_out": timed_out,
+        });
+        if self.config.report_cluster_size {
+            let cluster_sizes = self.cluster_sizes(sparse_measurement);
+            runtime_statistics["cluster_count"] = json!(cluster_sizes.len());
+            runtime_statistics["cluster_size_mean"] = json!(if cluster_sizes.is_empty() { 0. } else {
+                cluster_sizes.iter().sum::<usize>() as f64 / cluster_sizes.len() as f64 });
+            runtime_statistics["cluster_size_max"] = json!(cluster_sizes.iter().max().cloned().unwrap_or(0));
+        }
+        Ok((correction, runtime_statistics))
+    }
+
+    /// load the measurement results and the erasure edges, then grow the clusters until stable or the deadline is reached;
+    /// return the time of preparing the decoder, the time of growing the clusters and whether it timed out
+    pub fn grow_clusters(&mut self, sparse_measurement: &SparseMeasurement, erasure_edges: &[ErasureEdge], deadline: Option<Instant>) -> (f64, f64, bool) {
         // clean the state and then read measurement result
         let time_prepare_decoders = {
             let begin = Instant::now();
             self.clear();
             for position in sparse_measurement.iter() {
-                let index = self.position_to_index[position];
+                let index = self.position_indexer.stabilizers.index(position);
                 self.odd_clusters.push(index);
                 self.insert_odd_clusters_set(index);
                 self.nodes[index].is_error_syndrome = true;
@@ -435,21 +550,23 @@ impl UnionFindDecoder {
             begin.elapsed().as_secs_f64()
         };
         // load the erasure information
-        if sparse_detected_erasures.len() > 0 {
-            let erasure_edges = sparse_detected_erasures.get_erasure_edges(&self.erasure_graph);
+        if !erasure_edges.is_empty() {
             for erasure_edge in erasure_edges.iter() {
                 match erasure_edge {
                     ErasureEdge::Connection(position1, position2) => {
-                        let index1 = self.position_to_index[position1];
-                        let index2 = self.position_to_index[position2];
+                        let index1 = self.position_indexer.stabilizers.index(position1);
+                        let index2 = self.position_indexer.stabilizers.index(position2);
                         let node1 = self.nodes.get_mut(index1).unwrap();
-                        let neighbor = node1.index_to_neighbor(&index2).expect("neighbor must exist");
+                        let neighbor = match node1.index_to_neighbor(&index2) {
+                            Some(neighbor) => neighbor,
+                            None => continue,  // e.g. a lost detector without a time-like edge to its next measurement
+                        };
                         let neighbor_edge_ptr = &node1.neighbors[neighbor].1;
                         let mut neighbor_edge = neighbor_edge_ptr.write();
                         neighbor_edge.increased = neighbor_edge.length;
                     },
                     ErasureEdge::Boundary(position) => {
-                        let index = self.position_to_index[position];
+                        let index = self.position_indexer.stabilizers.index(position);
                         let node = self.nodes.get_mut(index).unwrap();
                         node.boundary_increased = node.boundary_length.expect("boundary must exist");
                     },
@@ -458,78 +575,68 @@ impl UnionFindDecoder {
             self.run_single_iteration_optional_grow(true);  // need to update the state of clusters after manually set the growth of each edge
         }
         // decode
+        let mut timed_out = false;
         let time_run_to_stable = if sparse_measurement.len() > 0 {
             let begin = Instant::now();
-            if true {  // set to false when debugging
+            if let Some(deadline) = deadline {
+                timed_out = !self.run_to_stable_until(deadline);
+            } else if true {  // set to false when debugging
                 self.run_to_stable();
             } else {
                 self.detailed_print_run_to_stable();
             }
             begin.elapsed().as_secs_f64()
         } else { 0. };
-        // build correction based on the matching
-        let (time_build_correction, correction) = {
-            let begin = Instant::now();
-            let mut correction = SparseCorrection::new();
-            if !self.config.benchmark_skip_building_correction {
-                // invalidate previous cache to save memory
-                self.complete_model_graph.invalidate_previous_dijkstra();
-                // in order to build correction, first collect the nodes for each cluster
-                let mut cluster_nodes = BTreeMap::<usize, Vec<usize>>::new();
-                for position in sparse_measurement.iter() {
-                    let index = self.position_to_index[position];
-                    let root = self.union_find.find(index);
-                    if !cluster_nodes.contains_key(&root) {
-                        cluster_nodes.insert(root, vec![]);
-                    }
-                    cluster_nodes.get_mut(&root).unwrap().push(index);
-                }
-                // then build correction based on each correction
-                for (root, mut error_syndromes) in cluster_nodes.into_iter() {
-                    let root_node_cardinality = self.union_find.get(root).cardinality;
-                    let cluster_boundary_index = self.union_find.get(root).touching_boundary_index;
-                    debug_assert!(root_node_cardinality > 0, "each nontrivial measurement must be in a non-empty cluster");
-                    assert_eq!(error_syndromes.len(), root_node_cardinality);
-                    if root_node_cardinality % 2 == 1 {
-                        assert!(cluster_boundary_index != usize::MAX, "boundary of odd cluster must exists");
-                        // connect to a boundary and others internally
-                        error_syndromes.push(cluster_boundary_index);  // let it match with others
-                        let cluster_boundary_position = &self.index_to_position[cluster_boundary_index];
-                        // println!("match boundary {:?}", cluster_boundary_position);
-                        let boundary_correction = self.complete_model_graph.build_correction_boundary(cluster_boundary_position);
-                        correction.extend(&boundary_correction);
-                    }
-                    assert_eq!(error_syndromes.len() % 2, 0);
-                    let half_len = error_syndromes.len() / 2;
-                    for i in 0..half_len{
-                        let index1 = error_syndromes[i];
-                        let index2 = error_syndromes[i + half_len];
-                        if index1 != index2 {
-                            let position1 = &self.index_to_position[index1];
-                            let position2 = &self.index_to_position[index2];
-                            // println!("match peer {:?} {:?}", position1, position2);
-                            let matching_correction = self.complete_model_graph.build_correction_matching(position1, position2);
-                            correction.extend(&matching_correction);
-                        }
-                    }
-                }
+        (time_prepare_decoders, time_run_to_stable, timed_out)
+    }
+
+    /// the nontrivial measurements of each cluster after [`UnionFindDecoder::grow_clusters`], keyed by the root of the cluster
+    pub fn collect_clusters(&mut self, sparse_measurement: &SparseMeasurement) -> BTreeMap<usize, Vec<usize>> {
+        let mut cluster_nodes = BTreeMap::<usize, Vec<usize>>::new();
+        for position in sparse_measurement.iter() {
+            let index = self.position_indexer.stabilizers.index(position);
+            let root = self.union_find.find(index);
+            cluster_nodes.entry(root).or_default().push(index);
+        }
+        cluster_nodes
+    }
+
+    /// the size of each final cluster after [`UnionFindDecoder::grow_clusters`], i.e. the number of defects in it (the cardinality)
+    pub fn cluster_sizes(&mut self, sparse_measurement: &SparseMeasurement) -> Vec<usize> {
+        self.collect_clusters(sparse_measurement).keys().map(|&root| self.union_find.get(root).cardinality).collect()
+    }
+
+    /// add the correction of a single cluster given by [`UnionFindDecoder::collect_clusters`], matching an odd cluster to the boundary it touches
+    pub fn build_cluster_correction(&mut self, root: usize, mut error_syndromes: Vec<usize>, timed_out: bool, correction: &mut SparseCorrection) {
+        let root_node_cardinality = self.union_find.get(root).cardinality;
+        let cluster_boundary_index = self.union_find.get(root).touching_boundary_index;
+        debug_assert!(root_node_cardinality > 0, "each nontrivial measurement must be in a non-empty cluster");
+        assert_eq!(error_syndromes.len(), root_node_cardinality);
+        if root_node_cardinality % 2 == 1 && timed_out && cluster_boundary_index == usize::MAX {
+            // the cluster didn't grow to stable before timeout: leave one defect unmatched and match the others internally
+            error_syndromes.pop();
+        } else if root_node_cardinality % 2 == 1 {
+            assert!(cluster_boundary_index != usize::MAX, "boundary of odd cluster must exists");
+            // connect to a boundary and others internally
+            error_syndromes.push(cluster_boundary_index);  // let it match with others
+            let cluster_boundary_position = self.position_indexer.stabilizers.position(cluster_boundary_index);
+            // println!("match boundary {:?}", cluster_boundary_position);
+            let boundary_correction = self.complete_model_graph.build_correction_boundary(cluster_boundary_position);
+            correction.extend(&boundary_correction);
+        }
+        assert_eq!(error_syndromes.len() % 2, 0);
+        let half_len = error_syndromes.len() / 2;
+        for i in 0..half_len{
+            let index1 = error_syndromes[i];
+            let index2 = error_syndromes[i + half_len];
+            if index1 != index2 {
+                let position1 = self.position_indexer.stabilizers.position(index1);
+                let position2 = self.position_indexer.stabilizers.position(index2);
+                // println!("match peer {:?} {:?}", position1, position2);
+                let matching_correction = self.complete_model_graph.build_correction_matching(position1, position2);
+                correction.extend(&matching_correction);
             }
-            (begin.elapsed().as_secs_f64(), correction)
-        };
-        (correction, json!({
-            "time_run_to_stable": time_run_to_stable,
-            "time_prepare_decoders": time_prepare_decoders,
-            "time_uf_grow_step": self.time_uf_grow_step,
-            "time_uf_grow": self.time_uf_grow,
-            "count_uf_grow": self.count_uf_grow,
-            "time_uf_merge": self.time_uf_merge,
-            "time_uf_update": self.time_uf_update,
-            "time_uf_remove": self.time_uf_remove,
-            "time_build_correction": time_build_correction,
-            "count_node_visited": self.count_node_visited,
-            "count_iteration": self.count_iteration,
-            "count_memory_access": self.count_memory_access,
-        }))
+        }
     }
 
     /// run single iterations until no non-terminating (odd and not yet touching boundary) clusters exist
@@ -541,6 +648,18 @@ impl UnionFindDecoder {
         }
     }
 
+    /// run single iterations until stable or the deadline is reached, return whether the clusters are stable
+    pub fn run_to_stable_until(&mut self, deadline: Instant) -> bool {
+        while !self.odd_clusters.is_empty() {
+            if Instant::now() >= deadline {
+                return false
+            }
+            self.run_single_iteration();
+            self.count_iteration += 1;
+        }
+        true
+    }
+
     /// debug function where a limited iterations can be run
     #[allow(dead_code)]
     pub fn detailed_print_run_to_stable(&mut self) {
@@ -568,8 +687,8 @@ impl UnionFindDecoder {
     pub fn debug_print_clusters(&self) {
         let nodes_len = self.nodes.len();
         for i in 0..nodes_len {
-            let this_position = &self.index_to_position[i];
-            let root_position = &self.index_to_position[self.union_find.immutable_find(i)];
+            let this_position = self.position_indexer.stabilizers.position(i);
+            let root_position = self.position_indexer.stabilizers.position(self.union_find.immutable_find(i));
             let node = &self.nodes[i];
             let error_symbol = if node.is_error_syndrome { "x" } else { " " };
             let boundary_string = match node.boundary_length {
@@ -585,7 +704,7 @@ impl UnionFindDecoder {
                 let (neighbor_index, edge_ptr) = &self.nodes[i].neighbors[j];
                 let increased = edge_ptr.read_recursive().increased;
                 let length = edge_ptr.read_recursive().length;
-                let neighbor_position = &self.index_to_position[*neighbor_index];
+                let neighbor_position = self.position_indexer.stabilizers.position(*neighbor_index);
                 let color = if increased > 0 { "\x1b[93m" } else { "" };
                 let string = format!("{}{}({}/{})\x1b[0m ", color, neighbor_position, increased, length);
                 neighbor_string.push_str(string.as_str());
@@ -604,10 +723,10 @@ impl UnionFindDecoder {
             }
             let mut user_data = Vec::new();
             for &idx in boundaries_vec.iter() {
-                let position = &self.index_to_position[idx];
+                let position = self.position_indexer.stabilizers.position(idx);
                 user_data.push(format!("{}", position));
             }
-            let root_position = &self.index_to_position[cluster];
+            let root_position = self.position_indexer.stabilizers.position(cluster);
             println!("{}: {}", root_position, user_data.join(" "));
         }
     }
@@ -630,7 +749,7 @@ impl UnionFindDecoder {
                         self.count_memory_access += 2;
                         let edge = edge_ptr.read_recursive();
                         self.count_memory_access += 2;
-                        if edge.increased < edge.length {  // not grown
+                        if edge.is_growable() {  // not grown
                             let mut safe_length = edge.length - edge.increased;
                             // judge if peer needs to grow as well, if so, the safe length is halved
                             let neighbor_root = self.union_find.find(*neighbor_index);
@@ -646,18 +765,13 @@ impl UnionFindDecoder {
                     }
                     // grow to the code boundary if it has
                     self.count_memory_access += 1;
-                    match self.nodes[boundary].boundary_length {
-                        Some(boundary_length) => {
-                            let boundary_increased = &mut self.nodes[boundary].boundary_increased;
-                            self.count_memory_access += 1;
-                            if *boundary_increased < boundary_length {
-                                let safe_length = boundary_length - *boundary_increased;
-                                if safe_length < maximum_safe_length {
-                                    maximum_safe_length = safe_length;
-                                }
-                            }
-                        },
-                        None => { }  // do nothing
+                    if self.nodes[boundary].is_boundary_growable() {
+                        let node = &self.nodes[boundary];
+                        self.count_memory_access += 1;
+                        let safe_length = node.boundary_length.unwrap() - node.boundary_increased;
+                        if safe_length < maximum_safe_length {
+                            maximum_safe_length = safe_length;
+                        }
                     }
                 }
             }
@@ -699,7 +813,7 @@ impl UnionFindDecoder {
                                 is_fusion = true;
                             }
                         } else {
-                            if edge.increased < edge.length {  // not grown
+                            if edge.is_growable() {  // not grown
                                 self.count_memory_access += 1;  // write
                                 edge.increased += grow_step;  // may over-grown, but ok as long as weight is much smaller than usize::MAX
                                 if edge.increased >= edge.length {  // found new grown edge
@@ -717,6 +831,7 @@ impl UnionFindDecoder {
                 }
                 // grow to the code boundary if it has
                 self.count_memory_access += 1;
+                let is_boundary_erasure_only = node.is_boundary_erasure_only;
                 match node.boundary_length {
                     Some(boundary_length) => {
                         let boundary_increased = &mut self.nodes[boundary].boundary_increased;
@@ -728,7 +843,7 @@ impl UnionFindDecoder {
                                 union_find_node.touching_boundary_index = boundary;
                             }
                         } else {
-                            if *boundary_increased < boundary_length {
+                            if *boundary_increased < boundary_length && !is_boundary_erasure_only {
                                 *boundary_increased += grow_step;
                                 self.count_memory_access += 1;  // write
                                 if *boundary_increased >= boundary_length {
@@ -752,7 +867,7 @@ impl UnionFindDecoder {
         // {  // debug print `fusion_list`
         //     println!("fusion_list:");
         //     for (a, b) in fusion_list.iter() {
-        //         println!("    {} {}", self.index_to_position[*a], self.index_to_position[*b]);
+        //         println!("    {} {}", self.position_indexer.stabilizers.position(*a), self.position_indexer.stabilizers.position(*b));
         //     }
         // }
     }
@@ -831,21 +946,14 @@ impl UnionFindDecoder {
                     self.count_memory_access += 1;
                     self.count_memory_access += 1;
                     let edge = edge_ptr.read_recursive();
-                    if edge.increased < edge.length {  // not grown
+                    if edge.is_growable() {  // not grown
                         all_grown = false;
                         break
                     }
                 }
-                let boundary_node = &self.nodes[boundary];
-                self.count_memory_access += 1;
-                match boundary_node.boundary_length {
-                    Some(boundary_length) => {
-                        self.count_memory_access += 1;
-                        if boundary_node.boundary_increased < boundary_length {
-                            all_grown = false;
-                        }
-                    },
-                    None => { },  // do nothing
+                self.count_memory_access += 2;
+                if self.nodes[boundary].is_boundary_growable() {
+                    all_grown = false;
                 }
                 if !all_grown {
                     let not_present = {  // borrow checker workaround
@@ -995,6 +1103,7 @@ mod tests {
     use super::*;
     use super::super::code_builder::*;
     use super::super::types::ErrorType::*;
+    use super::super::types::PauliErrorRates;
     use super::super::noise_model_builder::*;
     use super::super::tool::*;
 
@@ -1091,6 +1200,35 @@ mod tests {
     // 2022.6.15: found an infinite-loop case
     // {"correction":null,"detected_erasures":{"erasures":["[0][1][5]","[0][3][7]","[0][4][2]","[0][4][8]","[0][5][1]","[0][6][8]","[0][7][3]","[0][9][5]"]},"error_pattern":{"[0][1][5]":"Y","[0][4][2]":"X","[0][5][1]":"X"},"measurement":null,"thread_counter":451986}
     // cargo run --release -- tool benchmark [5] [0] [0] --pes [0.1] --max_repeats 0 --min_failed_cases 0 --time_budget 60 --decoder union-find --decoder_config=\{\"pcmg\":true\} --code_type StandardPlanarCode --noise_model erasure-only-phenomenological
+    #[test]
+    fn union_find_decoder_decode_region() {  // cargo test union_find_decoder_decode_region -- --nocapture
+        let d = 7;
+        let p = 0.001;
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(0, d, d));
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p, p, p, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        let noise_model = Arc::new(noise_model);
+        let mut union_find_decoder = UnionFindDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1, false);
+        let region = Region { t: None, i: Some((0, 6)), j: Some((0, 6)) };
+        // the full decoding of the first error alone
+        simulator.clear_all_errors();
+        simulator.set_error_check(&noise_model, &pos!(0, 3, 3), &Z);
+        simulator.propagate_errors();
+        let (expected_correction, _) = union_find_decoder.decode(&simulator.generate_sparse_measurement());
+        // the second error is far outside the region and ignored
+        simulator.set_error_check(&noise_model, &pos!(0, 9, 9), &Z);
+        simulator.clear_propagate_errors();
+        simulator.propagate_errors();
+        let sparse_measurement = simulator.generate_sparse_measurement();
+        assert_eq!(sparse_measurement.len(), 4);
+        let (correction, _) = union_find_decoder.decode_region(&sparse_measurement, &region);
+        assert_eq!(correction.to_vec(), expected_correction.to_vec());
+        // the defects next to the border are matched to it
+        let (correction, _) = union_find_decoder.decode_region(&sparse_measurement, &Region { t: None, i: Some((0, 8)), j: Some((0, 8)) });
+        assert!(correction.to_vec().iter().all(|(position, _)| position.i <= 8 && position.j <= 8), "{:?}", correction);
+    }
+
     #[test]
     fn union_find_decoder_debug_1() {  // cargo test union_find_decoder_debug_1 -- --nocapture
         let d = 5;
@@ -1159,4 +1297,91 @@ mod tests {
         assert!(!logical_i && !logical_j);
     }
 
+
+    #[test]
+    fn union_find_decoder_cluster_size() {  // cargo test union_find_decoder_cluster_size -- --nocapture
+        let d = 5;
+        let p = 0.001;
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(0, d, d));
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p, p, p, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        let noise_model = Arc::new(noise_model);
+        let mut union_find_decoder = UnionFindDecoder::new(&simulator, Arc::clone(&noise_model), &json!({"rcs": true}), 1, false);
+        // a single error in the middle of the code gives two neighboring defects, which merge into a single cluster right away
+        simulator.clear_all_errors();
+        simulator.set_error_check(&noise_model, &pos!(0, 5, 5), &Z);
+        simulator.propagate_errors();
+        let sparse_measurement = simulator.generate_sparse_measurement();
+        assert_eq!(sparse_measurement.len(), 2);
+        let (_, runtime_statistics) = union_find_decoder.decode(&sparse_measurement);
+        assert_eq!(runtime_statistics["cluster_count"], json!(1));
+        assert_eq!(runtime_statistics["cluster_size_mean"], json!(2.));
+        assert_eq!(runtime_statistics["cluster_size_max"], json!(2));
+        // two errors far away from each other give two clusters of two defects
+        simulator.set_error_check(&noise_model, &pos!(0, 1, 1), &Z);
+        simulator.clear_propagate_errors();
+        simulator.propagate_errors();
+        let sparse_measurement = simulator.generate_sparse_measurement();
+        let (_, runtime_statistics) = union_find_decoder.decode(&sparse_measurement);
+        println!("{}", runtime_statistics);
+        assert_eq!(runtime_statistics["cluster_size_max"], json!(2));
+        // no defect, no cluster
+        let (_, runtime_statistics) = union_find_decoder.decode(&SparseMeasurement::new());
+        assert_eq!(runtime_statistics["cluster_count"], json!(0));
+        assert_eq!(runtime_statistics["cluster_size_max"], json!(0));
+        // not reported by default
+        let mut union_find_decoder = UnionFindDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1, false);
+        let (_, runtime_statistics) = union_find_decoder.decode(&sparse_measurement);
+        assert!(runtime_statistics.get("cluster_size_max").is_none());
+    }
+
+    #[test]
+    fn union_find_decoder_erasure_only_edge() {  // cargo test union_find_decoder_erasure_only_edge -- --nocapture
+        let d = 5;
+        let p = 0.001;
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(0, d, d));
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p, p, p, 0.);
+        // the data qubit in the middle never has Pauli errors unless erased, so its edges have zero probability
+        let erasure_only_position = pos!(0, 5, 5);
+        let mut erasure_only_node = noise_model.get_node_unwrap(&erasure_only_position).clone();
+        erasure_only_node.pauli_error_rates = PauliErrorRates::default();
+        erasure_only_node.erasure_error_rate = 0.1;
+        noise_model.set_node(&erasure_only_position, Some(Arc::new(erasure_only_node)));
+        let noise_model = Arc::new(noise_model);
+        let mut union_find_decoder = UnionFindDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1, false);
+        simulator.clear_all_errors();
+        simulator.get_node_mut_unwrap(&erasure_only_position).error = Z;
+        simulator.propagate_errors();
+        let sparse_measurement = simulator.generate_sparse_measurement();
+        assert_eq!(sparse_measurement.len(), 2);
+        let indices: Vec<usize> = sparse_measurement.iter().map(|position| union_find_decoder.position_indexer.stabilizers.index(position)).collect();
+        let edge_increased = |union_find_decoder: &UnionFindDecoder| -> usize {
+            let node = &union_find_decoder.nodes[indices[0]];
+            let neighbor = node.index_to_neighbor(&indices[1]).expect("the erasure-only edge must exist");
+            let increased = node.neighbors[neighbor].1.read_recursive().increased;
+            increased
+        };
+        let top_t = simulator.height - 1;
+        // without erasure, the clusters never grow along the zero-probability edge
+        union_find_decoder.grow_clusters(&sparse_measurement, &[], None);
+        assert_eq!(edge_increased(&union_find_decoder), 0);
+        let (correction, _) = union_find_decoder.decode(&sparse_measurement);
+        assert_eq!(correction.get(&pos!(top_t, 5, 5)), None);
+        // once erased in the shot, the edge is fully grown and explains the defects
+        let sparse_detected_erasures: SparseErasures = serde_json::from_value(json!(["[0][5][5]"])).unwrap();
+        let erasure_edges = sparse_detected_erasures.get_erasure_edges(&union_find_decoder.erasure_graph);
+        union_find_decoder.grow_clusters(&sparse_measurement, &erasure_edges, None);
+        let length = {
+            let node = &union_find_decoder.nodes[indices[0]];
+            let length = node.neighbors[node.index_to_neighbor(&indices[1]).unwrap()].1.read_recursive().length;
+            length
+        };
+        assert_eq!(edge_increased(&union_find_decoder), length);
+        assert_eq!(union_find_decoder.collect_clusters(&sparse_measurement).len(), 1);
+        let (correction, _) = union_find_decoder.decode_with_erasure(&sparse_measurement, &sparse_detected_erasures);
+        let (logical_i, logical_j) = simulator.validate_correction(&correction);
+        assert!(!logical_i && !logical_j);
+    }
 }
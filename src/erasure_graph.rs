@@ -12,6 +12,7 @@ use super::util_macros::*;
 use std::sync::{Arc, Mutex};
 use super::noise_model::*;
 use super::types::*;
+use super::model_graph::*;
 use serde::{Serialize};
 #[cfg(feature="python_binding")]
 use super::pyo3::prelude::*;
@@ -73,6 +74,29 @@ impl ErasureGraph {
         &mut self.nodes[position.t][position.i][position.j]
     }
 
+    /// whether it's possible to have erasure error at this node, either by itself or by a correlated erasure of its gate peer
+    pub fn is_erasure_possible(simulator: &Simulator, noise_model: &NoiseModel, position: &Position) -> bool {
+        let noise_model_node = noise_model.get_node_unwrap(position);
+        noise_model_node.erasure_error_rate > 0. || noise_model_node.correlated_erasure_error_rates.is_some() || {
+            let node = simulator.get_node_unwrap(position);
+            if let Some(gate_peer) = node.gate_peer.as_ref() {
+                let peer_noise_model_node = noise_model.get_node_unwrap(gate_peer);
+                if let Some(correlated_erasure_error_rates) = &peer_noise_model_node.correlated_erasure_error_rates {
+                    correlated_erasure_error_rates.error_probability() > 0.
+                } else { false }
+            } else { false }
+        }
+    }
+
+    /// check if any erasure may happen, i.e. the decoders need to apply erasure edges
+    pub fn has_erasure(&self, simulator: &Simulator) -> bool {
+        let mut has_erasure = false;
+        simulator_iter!(simulator, position, if self.is_node_exist(position) {
+            has_erasure = true;
+        });
+        has_erasure
+    }
+
     /// build erasure graph given the simulator and the noise model in a specific region, for parallel initialization
     pub fn build_with_region(&mut self, simulator: &mut Simulator, noise_model: Arc<NoiseModel>, t_start: usize, t_end: usize) {
         let all_possible_errors = ErrorType::all_possible_errors();
@@ -83,18 +107,7 @@ impl ErasureGraph {
             if position.t < t_start || position.t >= t_end {
                 continue
             }
-            let noise_model_node = noise_model.get_node_unwrap(position);
-            // whether it's possible to have erasure error at this node
-            let possible_erasure_error = noise_model_node.erasure_error_rate > 0. || noise_model_node.correlated_erasure_error_rates.is_some() || {
-                let node = simulator.get_node_unwrap(position);
-                if let Some(gate_peer) = node.gate_peer.as_ref() {
-                    let peer_noise_model_node = noise_model.get_node_unwrap(gate_peer);
-                    if let Some(correlated_erasure_error_rates) = &peer_noise_model_node.correlated_erasure_error_rates {
-                        correlated_erasure_error_rates.error_probability() > 0.
-                    } else { false }
-                } else { false }
-            };
-            if possible_erasure_error {
+            if Self::is_erasure_possible(simulator, &noise_model, position) {
                 let mut erasure_edges = Vec::new();
                 for error in all_possible_errors.iter() {
                     // simulate the error and measure it
@@ -208,6 +221,100 @@ impl ErasureGraph {
 
 }
 
+/// statistics of an erasure graph that is consistent with the model graph, see [`validate_graph_consistency`]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConsistencyReport {
+    /// the positions where erasure may happen
+    pub erasure_nodes: usize,
+    /// the erasure edges connecting two stabilizer measurements
+    pub connection_edges: usize,
+    /// the erasure edges connecting a stabilizer measurement to the boundary
+    pub boundary_edges: usize,
+    /// the erasure edges whose model graph edge has zero probability, i.e. it exists only because it can be erased
+    pub erasure_only_edges: usize,
+    /// the positions where erasure may happen but none of the Pauli errors is detectable, e.g. a Z error right after initializing to |0>
+    pub undetectable_erasure_nodes: usize,
+}
+
+/// check that the erasure graph matches the model graph built from the same noise model, otherwise the decoders silently set the weight of
+/// nonexistent edges to 0 or miss the edges that should be erased: every position with nonzero erasure rate must have an erasure node
+/// (and no other position may have one) with at least one erasure edge unless none of its Pauli errors is detectable, every erasure edge must
+/// refer to an existing model graph edge or boundary, and the probabilities of these edges must be valid and agree in both directions
+pub fn validate_graph_consistency(simulator: &Simulator, model_graph: &ModelGraph, erasure_graph: &ErasureGraph, noise_model: &NoiseModel)
+        -> Result<ConsistencyReport, String> {
+    let mut report = ConsistencyReport::default();
+    let mut probe_simulator = simulator.clone();
+    probe_simulator.clear_all_errors();
+    let model_graph_node = |position: &Position| -> Result<&ModelGraphNode, String> {
+        if position.t >= simulator.height || !simulator.is_node_exist(position) || !model_graph.is_node_exist(position) {
+            return Err(format!("erasure edge refers to {} which is not a model graph node", position))
+        }
+        Ok(model_graph.get_node_unwrap(position))
+    };
+    let check_probability = |probability: f64, description: String| -> Result<(), String> {
+        if !(0. ..=1.).contains(&probability) {
+            return Err(format!("{} has invalid probability {}", description, probability))
+        }
+        Ok(())
+    };
+    simulator_iter!(simulator, position, {
+        let is_erasure_possible = ErasureGraph::is_erasure_possible(simulator, noise_model, position);
+        if !is_erasure_possible {
+            if erasure_graph.is_node_exist(position) {
+                return Err(format!("erasure graph has a node at {} where no erasure may happen", position))
+            }
+            continue
+        }
+        let erasure_error_rate = noise_model.get_node_unwrap(position).erasure_error_rate;
+        check_probability(erasure_error_rate, format!("erasure error at {}", position))?;
+        if !erasure_graph.is_node_exist(position) {
+            return Err(format!("erasure may happen at {} but the erasure graph has no node there", position))
+        }
+        report.erasure_nodes += 1;
+        let erasure_node = erasure_graph.get_node_unwrap(position);
+        if erasure_node.erasure_edges.is_empty() {
+            for error in ErrorType::all_possible_errors().iter() {
+                let mut sparse_errors = SparseErrorPattern::new();
+                sparse_errors.add(position.clone(), *error);
+                let (_, sparse_measurement_real, _) = probe_simulator.fast_measurement_given_few_errors(&Arc::new(sparse_errors));
+                if sparse_measurement_real.len() > 0 {
+                    return Err(format!("erasure at {} has no erasure edge but its {:?} error is detectable", position, error))
+                }
+            }
+            report.undetectable_erasure_nodes += 1;
+        }
+        for erasure_edge in erasure_node.erasure_edges.iter() {
+            match erasure_edge {
+                ErasureEdge::Connection(position1, position2) => {
+                    let edge12 = model_graph_node(position1)?.edges.get(position2)
+                        .ok_or(format!("erasure at {} refers to the edge {} - {} missing in the model graph", position, position1, position2))?;
+                    let edge21 = model_graph_node(position2)?.edges.get(position1)
+                        .ok_or(format!("erasure at {} refers to the edge {} - {} missing in the model graph", position, position2, position1))?;
+                    if edge12.probability != edge21.probability || edge12.weight != edge21.weight {
+                        return Err(format!("model graph edge {} - {} is asymmetric: probability {} vs {}, weight {} vs {}", position1, position2
+                            , edge12.probability, edge21.probability, edge12.weight, edge21.weight))
+                    }
+                    check_probability(edge12.probability, format!("model graph edge {} - {}", position1, position2))?;
+                    report.connection_edges += 1;
+                    if edge12.probability == 0. {
+                        report.erasure_only_edges += 1;
+                    }
+                },
+                ErasureEdge::Boundary(position1) => {
+                    let boundary = model_graph_node(position1)?.boundary.as_ref()
+                        .ok_or(format!("erasure at {} refers to the boundary of {} missing in the model graph", position, position1))?;
+                    check_probability(boundary.probability, format!("model graph boundary of {}", position1))?;
+                    report.boundary_edges += 1;
+                    if boundary.probability == 0. {
+                        report.erasure_only_edges += 1;
+                    }
+                },
+            }
+        }
+    });
+    Ok(report)
+}
+
 /// temporarily remember the weights that has been changed, so that it can revert back
 pub struct ErasureGraphModifier<Weight> {
     /// edge with 0 weighted caused by the erasure, used by UF decoder or (indirectly) by MWPM decoder
@@ -234,3 +341,73 @@ impl<Weight> ErasureGraphModifier<Weight> {
         self.modified.pop().expect("no more modified edges, please check `has_modified_edges` before calling this method")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::code_builder::*;
+    use super::super::noise_model_builder::*;
+
+    fn build_graphs(code_type: CodeType, noise_model_builder: Option<NoiseModelBuilder>, noise_model_configuration: serde_json::Value, p: f64, pe: f64)
+            -> (Simulator, NoiseModel, ModelGraph, ErasureGraph) {
+        let mut simulator = Simulator::new(code_type, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p / 3., p / 3., p / 3., pe);
+        if let Some(noise_model_builder) = noise_model_builder {
+            noise_model_builder.apply(&mut simulator, &mut noise_model, &noise_model_configuration, p, 0.5, pe);
+        }
+        simulator.compress_error_rates(&mut noise_model);
+        let noise_model = Arc::new(noise_model);
+        let mut model_graph = ModelGraph::new(&simulator);
+        model_graph.build(&mut simulator, Arc::clone(&noise_model), &WeightFunction::AutotuneImproved, 1, true, false);
+        let mut erasure_graph = ErasureGraph::new(&simulator);
+        erasure_graph.build(&mut simulator, Arc::clone(&noise_model), 1);
+        (simulator, (*noise_model).clone(), model_graph, erasure_graph)
+    }
+
+    #[test]
+    fn erasure_graph_consistency() {  // cargo test erasure_graph_consistency -- --nocapture
+        let builtin_configurations = [
+            (CodeType::StandardPlanarCode, None, json!({}), 0.01, 0.05),
+            (CodeType::RotatedPlanarCode, None, json!({}), 0.01, 0.05),
+            (CodeType::StandardPlanarCode, Some(NoiseModelBuilder::ErasureOnlyPhenomenological), json!({}), 0., 0.05),
+            (CodeType::RotatedPlanarCode, Some(NoiseModelBuilder::ErasureOnlyPhenomenological), json!({}), 0., 0.05),
+            (CodeType::StandardPlanarCode, Some(NoiseModelBuilder::MixedPhenomenological), json!({}), 0.01, 0.05),
+            (CodeType::StandardPlanarCode, Some(NoiseModelBuilder::OnlyGateErrorCircuitLevel), json!({}), 0.001, 0.01),
+            (CodeType::StandardPlanarCode, Some(NoiseModelBuilder::OnlyGateErrorCircuitLevel), json!({"use_correlated_erasure": true}), 0.001, 0.01),
+            (CodeType::RotatedPlanarCode, Some(NoiseModelBuilder::OnlyGateErrorCircuitLevel), json!({"use_correlated_erasure": true}), 0.001, 0.01),
+        ];
+        for (code_type, noise_model_builder, noise_model_configuration, p, pe) in builtin_configurations {
+            let (simulator, noise_model, model_graph, erasure_graph) = build_graphs(code_type, noise_model_builder, noise_model_configuration, p, pe);
+            let report = validate_graph_consistency(&simulator, &model_graph, &erasure_graph, &noise_model).unwrap();
+            println!("{:?} {:?}: {:?}", code_type, noise_model_builder, report);
+            assert!(report.erasure_nodes > 0 && report.connection_edges > 0 && report.boundary_edges > 0);
+        }
+        // break the erasure graph by hand
+        let (simulator, noise_model, model_graph, erasure_graph) = build_graphs(CodeType::StandardPlanarCode, None, json!({}), 0.01, 0.05);
+        let mut erasure_position = None;
+        simulator_iter!(simulator, position, if erasure_graph.is_node_exist(position) && erasure_position.is_none()
+                && erasure_graph.get_node_unwrap(position).erasure_edges.iter().any(|edge| matches!(edge, ErasureEdge::Connection(..))) {
+            erasure_position = Some(position.clone());
+        });
+        let erasure_position = erasure_position.unwrap();
+        let mut broken = erasure_graph.clone();
+        for erasure_edge in broken.get_node_mut(&erasure_position).as_mut().unwrap().erasure_edges.iter_mut() {
+            if let ErasureEdge::Connection(position1, _) = erasure_edge {
+                *erasure_edge = ErasureEdge::Connection(position1.clone(), position1.clone());  // a self loop never exists
+                break
+            }
+        }
+        let error = validate_graph_consistency(&simulator, &model_graph, &broken, &noise_model).unwrap_err();
+        assert!(error.contains("missing in the model graph"), "{}", error);
+        let mut broken = erasure_graph.clone();
+        *broken.get_node_mut(&erasure_position) = None;
+        let error = validate_graph_consistency(&simulator, &model_graph, &broken, &noise_model).unwrap_err();
+        assert!(error.contains("has no node"), "{}", error);
+        let mut broken = erasure_graph.clone();
+        broken.get_node_mut(&erasure_position).as_mut().unwrap().erasure_edges.clear();
+        let error = validate_graph_consistency(&simulator, &model_graph, &broken, &noise_model).unwrap_err();
+        assert!(error.contains("is detectable"), "{}", error);
+    }
+
+}
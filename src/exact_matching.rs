@@ -0,0 +1,316 @@
+//! exact minimum-weight matching for small instances
+//!
+//! Blossom V is only licensed for research purposes, and it's an overkill when the syndrome only has a few defects.
+//! This module solves the same matching problem as the MWPM decoder (each defect is either matched with another defect or
+//! with its own virtual boundary) using dynamic programming over the subsets of defects, which is exact but takes
+//! `O(2^n * n)` time and `O(2^n)` memory, thus only suitable for a small number of defects `n`.
+//!
+
+use super::types::DecodingError;
+
+/// the maximum number of defects supported, to avoid allocating huge memory by mistake
+pub const EXACT_MATCHING_MAX_DEFECTS: usize = 24;
+
+/// the preemption callback is checked once every this number of subsets, which keeps its overhead negligible
+pub const PREEMPTION_CHECK_INTERVAL: usize = 1 << 10;
+
+/// find the minimum-weight matching of `defect_num` defects given the weighted edges between defects and the optional boundary weight of each defect;
+/// return the peer of each defect in the same format as the output of blossom V on the graph with virtual boundaries: `peer[i] = j` if defect `i` is
+/// matched with defect `j`, or `peer[i] = i + defect_num` if defect `i` is matched to boundary; return None if no perfect matching exists
+pub fn exact_minimum_weight_matching(defect_num: usize, weighted_edges: &[(usize, usize, f64)], boundary_weights: &[Option<f64>]) -> Option<Vec<usize>> {
+    exact_minimum_weight_matching_preemptible(defect_num, weighted_edges, boundary_weights, &mut || false).expect("never preempted")
+}
+
+/// the same as [`exact_minimum_weight_matching`], but the periodic callback `is_preempted` is checked during the dynamic programming,
+/// aborting with [`DecodingError::Timeout`] once it returns true
+pub fn exact_minimum_weight_matching_preemptible(defect_num: usize, weighted_edges: &[(usize, usize, f64)], boundary_weights: &[Option<f64>]
+        , is_preempted: &mut dyn FnMut() -> bool) -> Result<Option<Vec<usize>>, DecodingError> {
+    assert!(defect_num <= EXACT_MATCHING_MAX_DEFECTS, "too many defects for exact matching: {} > {}", defect_num, EXACT_MATCHING_MAX_DEFECTS);
+    assert_eq!(boundary_weights.len(), defect_num);
+    let mut weights = vec![f64::INFINITY; defect_num * defect_num];
+    for &(i, j, weight) in weighted_edges.iter() {
+        assert!(i < defect_num && j < defect_num && i != j, "invalid edge between {} and {}", i, j);
+        weights[i * defect_num + j] = weights[i * defect_num + j].min(weight);
+        weights[j * defect_num + i] = weights[j * defect_num + i].min(weight);
+    }
+    // `costs[mask]` is the minimum weight of matching all defects in `mask`, where defects are always matched from the lowest index
+    let full_mask = (1usize << defect_num) - 1;
+    let mut costs = vec![f64::INFINITY; full_mask + 1];
+    let mut choices = vec![(usize::MAX, usize::MAX); full_mask + 1];  // (i, peer) where peer == defect_num means boundary
+    costs[0] = 0.;
+    for mask in 0..full_mask {
+        if mask % PREEMPTION_CHECK_INTERVAL == 0 && is_preempted() {
+            return Err(DecodingError::Timeout)
+        }
+        let cost = costs[mask];
+        if cost == f64::INFINITY {
+            continue
+        }
+        let i = (!mask).trailing_zeros() as usize;  // the first unmatched defect
+        let mut relax = |next_mask: usize, next_cost: f64, peer: usize| {
+            if next_cost < costs[next_mask] {
+                costs[next_mask] = next_cost;
+                choices[next_mask] = (i, peer);
+            }
+        };
+        if let Some(boundary_weight) = boundary_weights[i] {
+            relax(mask | (1 << i), cost + boundary_weight, defect_num);
+        }
+        for j in (i+1)..defect_num {
+            let weight = weights[i * defect_num + j];
+            if mask & (1 << j) == 0 && weight != f64::INFINITY {
+                relax(mask | (1 << i) | (1 << j), cost + weight, j);
+            }
+        }
+    }
+    if costs[full_mask] == f64::INFINITY {
+        return Ok(None)
+    }
+    // trace back the choices
+    let mut peers = vec![usize::MAX; defect_num];
+    let mut mask = full_mask;
+    while mask != 0 {
+        let (i, peer) = choices[mask];
+        if peer == defect_num {
+            peers[i] = i + defect_num;
+            mask ^= 1 << i;
+        } else {
+            peers[i] = peer;
+            peers[peer] = i;
+            mask ^= (1 << i) | (1 << peer);
+        }
+    }
+    Ok(Some(peers))
+}
+
+/// similar to [`exact_minimum_weight_matching`], but each edge also carries the logical class of its correction, and the class of a matching
+/// is the XOR of the classes of its edges; return the minimum-weight matching of class `false` and `true` respectively, None if no perfect
+/// matching exists in that class
+pub fn exact_minimum_weight_matching_by_class(defect_num: usize, weighted_edges: &[(usize, usize, f64, bool)], boundary_weights: &[Option<(f64, bool)>])
+        -> [Option<Vec<usize>>; 2] {
+    assert!(defect_num <= EXACT_MATCHING_MAX_DEFECTS, "too many defects for exact matching: {} > {}", defect_num, EXACT_MATCHING_MAX_DEFECTS);
+    assert_eq!(boundary_weights.len(), defect_num);
+    let mut weights = vec![(f64::INFINITY, false); defect_num * defect_num];
+    for &(i, j, weight, class) in weighted_edges.iter() {
+        assert!(i < defect_num && j < defect_num && i != j, "invalid edge between {} and {}", i, j);
+        if weight < weights[i * defect_num + j].0 {
+            weights[i * defect_num + j] = (weight, class);
+            weights[j * defect_num + i] = (weight, class);
+        }
+    }
+    // `costs[mask][class]` is the minimum weight of matching all defects in `mask` with the given logical class
+    let full_mask = (1usize << defect_num) - 1;
+    let mut costs = vec![[f64::INFINITY; 2]; full_mask + 1];
+    let mut choices = vec![[(usize::MAX, usize::MAX); 2]; full_mask + 1];  // (i, peer) where peer == defect_num means boundary
+    costs[0][0] = 0.;
+    for mask in 0..full_mask {
+        let i = (!mask).trailing_zeros() as usize;  // the first unmatched defect
+        for class in 0..2 {
+            let cost = costs[mask][class];
+            if cost == f64::INFINITY {
+                continue
+            }
+            let mut relax = |next_mask: usize, next_cost: f64, edge_class: bool, peer: usize| {
+                let next_class = class ^ (edge_class as usize);
+                if next_cost < costs[next_mask][next_class] {
+                    costs[next_mask][next_class] = next_cost;
+                    choices[next_mask][next_class] = (i, peer);
+                }
+            };
+            if let Some((boundary_weight, boundary_class)) = boundary_weights[i] {
+                relax(mask | (1 << i), cost + boundary_weight, boundary_class, defect_num);
+            }
+            for j in (i+1)..defect_num {
+                let (weight, edge_class) = weights[i * defect_num + j];
+                if mask & (1 << j) == 0 && weight != f64::INFINITY {
+                    relax(mask | (1 << i) | (1 << j), cost + weight, edge_class, j);
+                }
+            }
+        }
+    }
+    // trace back the choices of each class
+    let trace_back = |class: usize| -> Option<Vec<usize>> {
+        if costs[full_mask][class] == f64::INFINITY {
+            return None
+        }
+        let mut peers = vec![usize::MAX; defect_num];
+        let mut mask = full_mask;
+        let mut class = class;
+        while mask != 0 {
+            let (i, peer) = choices[mask][class];
+            if peer == defect_num {
+                peers[i] = i + defect_num;
+                mask ^= 1 << i;
+                class ^= boundary_weights[i].unwrap().1 as usize;
+            } else {
+                peers[i] = peer;
+                peers[peer] = i;
+                mask ^= (1 << i) | (1 << peer);
+                class ^= weights[i * defect_num + peer].1 as usize;
+            }
+        }
+        Some(peers)
+    };
+    [trace_back(0), trace_back(1)]
+}
+
+/// the total weight of a matching in the format returned by [`exact_minimum_weight_matching`]
+pub fn matching_weight(peers: &[usize], weighted_edges: &[(usize, usize, f64)], boundary_weights: &[Option<f64>]) -> f64 {
+    let defect_num = boundary_weights.len();
+    let mut total_weight = 0.;
+    for i in 0..defect_num {
+        let j = peers[i];
+        if j >= defect_num {
+            total_weight += boundary_weights[i].expect("matched to a non-existing boundary");
+        } else if i < j {
+            total_weight += weighted_edges.iter().filter(|&&(a, b, _)| (a == i && b == j) || (a == j && b == i))
+                .map(|&(_, _, weight)| weight).fold(f64::INFINITY, f64::min);
+        }
+    }
+    total_weight
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::reproducible_rand::Xoroshiro128StarStar;
+
+    /// enumerate all possible matchings to find the minimum weight
+    fn brute_force_minimum_weight(defect_num: usize, weighted_edges: &[(usize, usize, f64)], boundary_weights: &[Option<f64>]) -> f64 {
+        fn search(peers: &mut Vec<usize>, weighted_edges: &[(usize, usize, f64)], boundary_weights: &[Option<f64>]) -> f64 {
+            let defect_num = boundary_weights.len();
+            let i = match peers.iter().position(|&peer| peer == usize::MAX) {
+                Some(i) => i,
+                None => return matching_weight(peers, weighted_edges, boundary_weights),
+            };
+            let mut minimum = f64::INFINITY;
+            let mut candidates: Vec<usize> = ((i+1)..defect_num).filter(|&j| peers[j] == usize::MAX).collect();
+            if boundary_weights[i].is_some() {
+                candidates.push(i + defect_num);
+            }
+            for j in candidates {
+                peers[i] = j;
+                if j < defect_num { peers[j] = i; }
+                minimum = minimum.min(search(peers, weighted_edges, boundary_weights));
+                peers[i] = usize::MAX;
+                if j < defect_num { peers[j] = usize::MAX; }
+            }
+            minimum
+        }
+        search(&mut vec![usize::MAX; defect_num], weighted_edges, boundary_weights)
+    }
+
+    #[test]
+    fn exact_matching_simple() {  // cargo test exact_matching_simple -- --nocapture
+        // 0 - 1 are close, 2 is close to boundary
+        let weighted_edges = vec![(0, 1, 1.), (0, 2, 5.), (1, 2, 5.)];
+        let boundary_weights = vec![Some(3.), Some(3.), Some(1.)];
+        let peers = exact_minimum_weight_matching(3, &weighted_edges, &boundary_weights).unwrap();
+        assert_eq!(peers, vec![1, 0, 5]);
+        assert_eq!(matching_weight(&peers, &weighted_edges, &boundary_weights), 2.);
+        // no perfect matching without boundary
+        assert_eq!(exact_minimum_weight_matching(3, &weighted_edges, &[None; 3]), None);
+        assert_eq!(exact_minimum_weight_matching(0, &[], &[]), Some(vec![]));
+        // preemption aborts the matching
+        assert_eq!(exact_minimum_weight_matching_preemptible(3, &weighted_edges, &boundary_weights, &mut || true), Err(DecodingError::Timeout));
+        assert_eq!(exact_minimum_weight_matching_preemptible(3, &weighted_edges, &boundary_weights, &mut || false), Ok(Some(vec![1, 0, 5])));
+    }
+
+    /// enumerate all possible matchings to find the minimum weight of each logical class
+    fn brute_force_minimum_weight_by_class(defect_num: usize, weighted_edges: &[(usize, usize, f64, bool)], boundary_weights: &[Option<(f64, bool)>]) -> [f64; 2] {
+        fn search(peers: &mut Vec<usize>, weight: f64, class: bool, weighted_edges: &[(usize, usize, f64, bool)], boundary_weights: &[Option<(f64, bool)>]
+                , minimum: &mut [f64; 2]) {
+            let defect_num = boundary_weights.len();
+            let i = match peers.iter().position(|&peer| peer == usize::MAX) {
+                Some(i) => i,
+                None => {
+                    minimum[class as usize] = minimum[class as usize].min(weight);
+                    return
+                },
+            };
+            if let Some((boundary_weight, boundary_class)) = boundary_weights[i] {
+                peers[i] = i + defect_num;
+                search(peers, weight + boundary_weight, class ^ boundary_class, weighted_edges, boundary_weights, minimum);
+                peers[i] = usize::MAX;
+            }
+            for &(a, b, edge_weight, edge_class) in weighted_edges.iter() {
+                let j = if a == i { b } else if b == i { a } else { continue };
+                if peers[j] == usize::MAX {
+                    peers[i] = j;
+                    peers[j] = i;
+                    search(peers, weight + edge_weight, class ^ edge_class, weighted_edges, boundary_weights, minimum);
+                    peers[i] = usize::MAX;
+                    peers[j] = usize::MAX;
+                }
+            }
+        }
+        let mut minimum = [f64::INFINITY; 2];
+        search(&mut vec![usize::MAX; defect_num], 0., false, weighted_edges, boundary_weights, &mut minimum);
+        minimum
+    }
+
+    #[test]
+    fn exact_matching_by_class_random() {  // cargo test exact_matching_by_class_random -- --nocapture
+        let mut rng = Xoroshiro128StarStar::new();
+        for _ in 0..1000 {
+            let defect_num = (rng.next_f64() * 7.) as usize;
+            let mut weighted_edges = Vec::new();
+            for i in 0..defect_num {
+                for j in (i+1)..defect_num {
+                    if rng.next_f64() < 0.7 {
+                        weighted_edges.push((i, j, (rng.next_f64() * 10.).round(), rng.next_f64() < 0.3));
+                    }
+                }
+            }
+            let boundary_weights: Vec<Option<(f64, bool)>> = (0..defect_num).map(|_| if rng.next_f64() < 0.5 {
+                Some(((rng.next_f64() * 10.).round(), rng.next_f64() < 0.5))
+            } else { None }).collect();
+            let expected = brute_force_minimum_weight_by_class(defect_num, &weighted_edges, &boundary_weights);
+            let matchings = exact_minimum_weight_matching_by_class(defect_num, &weighted_edges, &boundary_weights);
+            for class in 0..2 {
+                match &matchings[class] {
+                    Some(peers) => {
+                        let edges: Vec<(usize, usize, f64)> = weighted_edges.iter().map(|&(i, j, weight, _)| (i, j, weight)).collect();
+                        let boundaries: Vec<Option<f64>> = boundary_weights.iter().map(|boundary| boundary.map(|(weight, _)| weight)).collect();
+                        assert_eq!(matching_weight(peers, &edges, &boundaries), expected[class]);
+                        // the class of the returned matching
+                        let mut matching_class = false;
+                        for i in 0..defect_num {
+                            let j = peers[i];
+                            if j >= defect_num {
+                                matching_class ^= boundary_weights[i].unwrap().1;
+                            } else if i < j {
+                                matching_class ^= weighted_edges.iter().find(|&&(a, b, _, _)| a == i && b == j).unwrap().3;
+                            }
+                        }
+                        assert_eq!(matching_class, class == 1);
+                    },
+                    None => assert_eq!(expected[class], f64::INFINITY),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exact_matching_random() {  // cargo test exact_matching_random -- --nocapture
+        let mut rng = Xoroshiro128StarStar::new();
+        for _ in 0..1000 {
+            let defect_num = (rng.next_f64() * 8.) as usize;
+            let mut weighted_edges = Vec::new();
+            for i in 0..defect_num {
+                for j in (i+1)..defect_num {
+                    if rng.next_f64() < 0.7 {
+                        weighted_edges.push((i, j, (rng.next_f64() * 10.).round()));
+                    }
+                }
+            }
+            let boundary_weights: Vec<Option<f64>> = (0..defect_num).map(|_| if rng.next_f64() < 0.5 { Some((rng.next_f64() * 10.).round()) } else { None }).collect();
+            let expected = brute_force_minimum_weight(defect_num, &weighted_edges, &boundary_weights);
+            match exact_minimum_weight_matching(defect_num, &weighted_edges, &boundary_weights) {
+                Some(peers) => assert_eq!(matching_weight(&peers, &weighted_edges, &boundary_weights), expected),
+                None => assert_eq!(expected, f64::INFINITY),
+            }
+        }
+    }
+
+}
@@ -0,0 +1,363 @@
+//! Importance sampling
+//!
+//! At a low physical error rate almost every shot of a direct Monte Carlo simulation has too few errors to cause a logical error, so most of the
+//! running time is wasted. The weight-shell sampler instead splits the logical error rate by the number of faults `W`, i.e. the number of error
+//! mechanisms of the noise model that happen in a shot: `pL = sum_w P(W = w) P(fail | W = w)`. The probability of each shell `P(W = w)` is computed
+//! exactly from the independent error mechanisms, while `P(fail | W = w)` is estimated by sampling errors conditioned on exactly `w` faults, so
+//! the rare high-weight errors are sampled directly. Only the shell without any fault is skipped, because it has no defect and never fails; the
+//! low shells are sampled as well, since a few hook or correlated faults of a circuit-level noise model may already cause a logical error. The
+//! shells are added one by one until the truncated tail `P(W > max_weight)` is negligible compared to the estimated `pL`, see
+//! [`WeightShellEstimate::is_tail_negligible`].
+//!
+
+use serde::{Serialize, Deserialize};
+use super::simulator::*;
+use super::noise_model::*;
+use super::types::*;
+use std::collections::BTreeMap;
+
+
+/// the probability of the truncated shells above `max_weight` relative to the estimated logical error rate
+pub const WEIGHT_SHELL_TAIL_TOLERANCE: f64 = 1e-3;
+
+/// an independent error mechanism of the noise model
+#[derive(Debug, Clone)]
+pub enum ErrorMechanism {
+    /// a single-qubit Pauli error
+    Pauli { position: Position, rates: PauliErrorRates },
+    /// a two-qubit correlated Pauli error on a gate and its peer
+    CorrelatedPauli { position: Position, peer: Position, rates: CorrelatedPauliErrorRates },
+}
+
+/// samples errors with exactly a given number of faults, see the module documentation
+#[derive(Debug, Clone)]
+pub struct WeightShellSampler {
+    pub mechanisms: Vec<(ErrorMechanism, f64)>,
+    /// the highest weight shell that can be sampled
+    pub max_weight: usize,
+    /// `P(W = w)` for `w` in `0..=max_weight`, followed by the truncated tail `P(W > max_weight)`
+    pub weight_distribution: Vec<f64>,
+    /// `suffix_distribution[k][m]` is the probability that exactly `m` faults happen among the mechanisms `k..`
+    suffix_distribution: Vec<Vec<f64>>,
+}
+
+/// the distribution of the number of faults, i.e. `P(W = w)` for `w` in `0..=max_weight` followed by `P(W > max_weight)`; the tail is accumulated
+/// directly instead of computed as `1 - sum`, so that it's accurate even if it's tiny
+pub fn weight_distribution(probabilities: &[f64], max_weight: usize) -> Vec<f64> {
+    let mut distribution = vec![0.; max_weight + 2];
+    distribution[0] = 1.;
+    for &probability in probabilities.iter() {
+        distribution[max_weight + 1] += distribution[max_weight] * probability;
+        for w in (1..=max_weight).rev() {
+            distribution[w] = distribution[w] * (1. - probability) + distribution[w - 1] * probability;
+        }
+        distribution[0] *= 1. - probability;
+    }
+    distribution
+}
+
+impl WeightShellSampler {
+    /// collect the error mechanisms and prepare the shells up to `max_weight`; only Pauli errors are supported, because the erasure errors,
+    /// the additional noise and the feedforwards are not independent faults with a fixed probability
+    pub fn new(simulator: &Simulator, noise_model: &NoiseModel, max_weight: usize) -> Result<Self, String> {
+        if !noise_model.additional_noise.is_empty() || !noise_model.feedforwards.is_empty() {
+            return Err("weight shell sampling doesn't support additional noise or feedforwards".to_string())
+        }
+        let mut mechanisms = Vec::new();
+        let mut has_erasure = false;
+        simulator_iter!(simulator, position, node, {
+            let noise_model_node = noise_model.get_node_unwrap(position);
+            let probability = noise_model_node.pauli_error_rates.error_probability();
+            if probability > 0. {
+                mechanisms.push((ErrorMechanism::Pauli { position: position.clone(), rates: noise_model_node.pauli_error_rates.clone() }, probability));
+            }
+            if let Some(correlated_pauli_error_rates) = &noise_model_node.correlated_pauli_error_rates {
+                let probability = correlated_pauli_error_rates.error_probability();
+                if probability > 0. {
+                    let peer = node.gate_peer.as_ref().expect("correlated pauli error must corresponds to a two-qubit gate");
+                    mechanisms.push((ErrorMechanism::CorrelatedPauli { position: position.clone(), peer: (**peer).clone()
+                        , rates: correlated_pauli_error_rates.clone() }, probability));
+                }
+            }
+            has_erasure |= noise_model.erasure_error_rate(position, simulator.measurement_cycles) > 0.;
+            has_erasure |= noise_model_node.correlated_erasure_error_rates.as_ref().map(|rates| rates.error_probability() > 0.).unwrap_or(false);
+        });
+        if has_erasure {
+            return Err("weight shell sampling doesn't support erasure errors".to_string())
+        }
+        if max_weight > mechanisms.len() {
+            return Err(format!("no error has {} faults, there are only {} error mechanisms", max_weight, mechanisms.len()))
+        }
+        let probabilities: Vec<f64> = mechanisms.iter().map(|(_, probability)| *probability).collect();
+        let distribution = weight_distribution(&probabilities, max_weight);
+        let mut suffix_distribution = vec![vec![0.; max_weight + 1]; mechanisms.len() + 1];
+        suffix_distribution[mechanisms.len()][0] = 1.;
+        for k in (0..mechanisms.len()).rev() {
+            let probability = probabilities[k];
+            for m in 0..=max_weight {
+                let fired = if m > 0 { suffix_distribution[k + 1][m - 1] * probability } else { 0. };
+                suffix_distribution[k][m] = suffix_distribution[k + 1][m] * (1. - probability) + fired;
+            }
+        }
+        Ok(Self {
+            mechanisms,
+            max_weight,
+            weight_distribution: distribution,
+            suffix_distribution,
+        })
+    }
+
+    /// prepare one more shell by extending the distributions of the lower shells, instead of building a new sampler from the noise model;
+    /// `P(W = max_weight)` is the probability of `max_weight` faults among all the mechanisms, and the new tail sums the probabilities of a
+    /// mechanism firing while exactly `max_weight` faults happen among the following ones, which is accurate even if it's tiny
+    pub fn increase_max_weight(&mut self) -> Result<(), String> {
+        let max_weight = self.max_weight + 1;
+        if max_weight > self.mechanisms.len() {
+            return Err(format!("no error has {} faults, there are only {} error mechanisms", max_weight, self.mechanisms.len()))
+        }
+        for suffix in self.suffix_distribution.iter_mut() {
+            suffix.push(0.);
+        }
+        let mut tail_probability = 0.;
+        for k in (0..self.mechanisms.len()).rev() {
+            let probability = self.mechanisms[k].1;
+            let fired = self.suffix_distribution[k + 1][max_weight - 1] * probability;
+            self.suffix_distribution[k][max_weight] = self.suffix_distribution[k + 1][max_weight] * (1. - probability) + fired;
+            tail_probability += self.suffix_distribution[k + 1][max_weight] * probability;
+        }
+        self.weight_distribution.pop();
+        self.weight_distribution.push(self.suffix_distribution[0][max_weight]);
+        self.weight_distribution.push(tail_probability);
+        self.max_weight = max_weight;
+        Ok(())
+    }
+
+    /// the probability of the truncated shells above `max_weight`, which bounds the bias of the estimate from above
+    pub fn tail_probability(&self) -> f64 {
+        self.weight_distribution[self.max_weight + 1]
+    }
+
+    /// generate the errors of exactly `weight` faults in the simulator and propagate them, where each fault is picked with the probability
+    /// conditioned on the total number of faults, and its Pauli type with the probability conditioned on the mechanism firing
+    pub fn generate_errors(&self, simulator: &mut Simulator, weight: usize) -> SparseErrorPattern {
+        assert!(weight <= self.max_weight, "weight {} is above the highest shell {}", weight, self.max_weight);
+        let mut rng = simulator.rng.clone();  // avoid mutable borrow
+        let mut errors = BTreeMap::<Position, ErrorType>::new();
+        let mut add_error = |position: &Position, error: ErrorType| {
+            let existing = errors.get(position).cloned().unwrap_or(ErrorType::I);
+            errors.insert(position.clone(), existing.multiply(&error));
+        };
+        let mut remaining = weight;
+        for (k, (mechanism, probability)) in self.mechanisms.iter().enumerate() {
+            if remaining == 0 {
+                break
+            }
+            let fire_probability = probability * self.suffix_distribution[k + 1][remaining - 1] / self.suffix_distribution[k][remaining];
+            if rng.next_f64() >= fire_probability {
+                continue
+            }
+            remaining -= 1;
+            let random_pauli = rng.next_f64() * probability;
+            match mechanism {
+                ErrorMechanism::Pauli { position, rates } => {
+                    add_error(position, if random_pauli < rates.error_rate_X { ErrorType::X }
+                        else if random_pauli < rates.error_rate_X + rates.error_rate_Z { ErrorType::Z }
+                        else { ErrorType::Y });
+                },
+                ErrorMechanism::CorrelatedPauli { position, peer, rates } => {
+                    let correlated_pauli_error_type = rates.generate_random_error(random_pauli);
+                    add_error(position, correlated_pauli_error_type.my_error());
+                    add_error(peer, correlated_pauli_error_type.peer_error());
+                },
+            }
+        }
+        debug_assert_eq!(remaining, 0, "conditional sampling must pick exactly the given number of faults");
+        simulator.rng = rng;
+        simulator.clear_all_errors();
+        let mut sparse_error_pattern = SparseErrorPattern::new();
+        for (position, error) in errors.into_iter().filter(|(_, error)| *error != ErrorType::I) {
+            simulator.get_node_mut_unwrap(&position).set_error_temp(&error);
+            sparse_error_pattern.add(position, error);
+        }
+        simulator.propagate_errors();
+        sparse_error_pattern
+    }
+}
+
+/// the number of shots and failures of a weight shell
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeightShell {
+    pub weight: usize,
+    /// `P(W = weight)`
+    pub probability: f64,
+    pub shots: usize,
+    pub failed: usize,
+}
+
+/// the reweighting estimator of the logical error rate from the conditional failure rates of the weight shells
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeightShellEstimate {
+    pub shells: Vec<WeightShell>,
+    /// `P(W > max_weight)`, see [`WeightShellSampler::tail_probability`]
+    pub tail_probability: f64,
+}
+
+impl Default for WeightShellEstimate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WeightShellEstimate {
+    /// no shell is sampled yet, so the whole probability of any fault is in the tail
+    pub fn new() -> Self {
+        Self {
+            shells: Vec::new(),
+            tail_probability: 1.,
+        }
+    }
+
+    /// add the shells from one fault up to `sampler.max_weight` that are not in the estimate yet, and return their weights
+    pub fn extend(&mut self, sampler: &WeightShellSampler) -> std::ops::RangeInclusive<usize> {
+        let new_weights = self.shells.len() + 1..=sampler.max_weight;
+        for weight in new_weights.clone() {
+            self.shells.push(WeightShell {
+                weight,
+                probability: sampler.weight_distribution[weight],
+                shots: 0,
+                failed: 0,
+            });
+        }
+        self.tail_probability = sampler.tail_probability();
+        new_weights
+    }
+
+    pub fn record(&mut self, weight: usize, failed: bool) {
+        let shell = self.shells.iter_mut().find(|shell| shell.weight == weight).expect("weight shell not sampled");
+        shell.shots += 1;
+        if failed {
+            shell.failed += 1;
+        }
+    }
+
+    pub fn shots(&self) -> usize {
+        self.shells.iter().map(|shell| shell.shots).sum()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.shells.iter().map(|shell| shell.failed).sum()
+    }
+
+    /// `sum_w P(W = w) failed_w / shots_w`
+    pub fn logical_error_rate(&self) -> f64 {
+        self.shells.iter().filter(|shell| shell.shots > 0).map(|shell| shell.probability * shell.failed as f64 / shell.shots as f64).sum()
+    }
+
+    /// the variance of the estimate, summing the binomial variances of the independent shells
+    pub fn variance(&self) -> f64 {
+        self.shells.iter().filter(|shell| shell.shots > 0).map(|shell| {
+            let failure_rate = shell.failed as f64 / shell.shots as f64;
+            shell.probability.powi(2) * failure_rate * (1. - failure_rate) / shell.shots as f64
+        }).sum()
+    }
+
+    /// whether the truncated tail is at most `tolerance` of the estimated logical error rate; without any failed case the estimate is 0, so
+    /// the tail is compared to its upper bound `sum_w P(W = w) 3 / shots_w` instead (the rule of three)
+    pub fn is_tail_negligible(&self, tolerance: f64) -> bool {
+        let logical_error_rate = if self.failed() > 0 {
+            self.logical_error_rate()
+        } else {
+            self.shells.iter().filter(|shell| shell.shots > 0).map(|shell| shell.probability * 3. / shell.shots as f64).sum()
+        };
+        self.tail_probability <= tolerance * logical_error_rate
+    }
+
+    /// the relative 95% confidence interval of the estimate, in the same format as `<pL_dev>`; 0 without any failed case
+    pub fn relative_deviation(&self) -> f64 {
+        let logical_error_rate = self.logical_error_rate();
+        if logical_error_rate == 0. {
+            return 0.
+        }
+        1.96 * self.variance().sqrt() / logical_error_rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::code_builder::*;
+    use super::super::noise_model_builder::*;
+
+    #[test]
+    fn importance_sampling_weight_distribution() {  // cargo test importance_sampling_weight_distribution -- --nocapture
+        // identical mechanisms give the binomial distribution
+        let n = 20;
+        let p: f64 = 0.01;
+        let distribution = weight_distribution(&vec![p; n], 3);
+        let binomial = |w: i32| (1..=w).map(|k| (n as f64 - k as f64 + 1.) / k as f64).product::<f64>() * p.powi(w) * (1. - p).powi(n as i32 - w);
+        for (w, &probability) in distribution.iter().enumerate().take(4) {
+            assert!((probability - binomial(w as i32)).abs() < 1e-12, "{} {}", probability, binomial(w as i32));
+        }
+        let tail: f64 = (4..=n as i32).map(binomial).sum();
+        assert!((distribution[4] - tail).abs() < 1e-15 && distribution[4] > 0.);
+        assert!((distribution.iter().sum::<f64>() - 1.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn importance_sampling_exact_weight() {  // cargo test importance_sampling_exact_weight -- --nocapture
+        let d = 3;
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, d, d));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), 0.01, 0.5, 0.);
+        let sampler = WeightShellSampler::new(&simulator, &noise_model, 4).unwrap();
+        assert_eq!(sampler.max_weight, 4);
+        assert!((sampler.weight_distribution.iter().sum::<f64>() - 1.).abs() < 1e-12);
+        // each fault of the phenomenological noise model is a single-qubit error at a different position
+        for weight in 0..=sampler.max_weight {
+            for _ in 0..20 {
+                let sparse_error_pattern = sampler.generate_errors(&mut simulator, weight);
+                assert_eq!(sparse_error_pattern.len(), weight);
+                assert_eq!(simulator.generate_sparse_error_pattern().len(), weight);
+            }
+        }
+        // erasure errors are not independent Pauli faults
+        NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), 0.01, 0.5, 0.01);
+        assert!(WeightShellSampler::new(&simulator, &noise_model, 2).is_err());
+    }
+
+    #[test]
+    fn importance_sampling_tail_tolerance() {  // cargo test importance_sampling_tail_tolerance -- --nocapture
+        let d = 3;
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, d, d));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), 0.001, 0.5, 0.);
+        let mut estimate = WeightShellEstimate::new();
+        let mut sampler = WeightShellSampler::new(&simulator, &noise_model, 2).unwrap();
+        assert_eq!(estimate.extend(&sampler), 1..=2);
+        sampler.increase_max_weight().unwrap();
+        assert_eq!(estimate.extend(&sampler), 3..=3);
+        // extending the sampler gives the same distributions as building it with the higher shell
+        let rebuilt = WeightShellSampler::new(&simulator, &noise_model, 3).unwrap();
+        for (extended, rebuilt) in sampler.weight_distribution.iter().zip(rebuilt.weight_distribution.iter()) {
+            assert!((extended - rebuilt).abs() <= 1e-12 * rebuilt, "{} vs {}", extended, rebuilt);
+        }
+        assert_eq!(sampler.suffix_distribution.len(), rebuilt.suffix_distribution.len());
+        for (extended, rebuilt) in sampler.suffix_distribution.iter().flatten().zip(rebuilt.suffix_distribution.iter().flatten()) {
+            assert!((extended - rebuilt).abs() <= 1e-12 * rebuilt, "{} vs {}", extended, rebuilt);
+        }
+        assert_eq!(estimate.shells.iter().map(|shell| shell.weight).collect::<Vec<_>>(), vec![1, 2, 3]);
+        // the tail is compared to the logical error rate rather than to the probability of the sampled shells, which is much larger
+        let sampled: f64 = estimate.shells.iter().map(|shell| shell.probability).sum();
+        assert!(estimate.tail_probability <= WEIGHT_SHELL_TAIL_TOLERANCE * sampled);
+        for _ in 0..100 {
+            estimate.record(1, false);
+            estimate.record(2, false);
+            estimate.record(3, false);
+        }
+        estimate.record(2, true);
+        assert!(estimate.logical_error_rate() > 0.);
+        assert!(estimate.tail_probability > WEIGHT_SHELL_TAIL_TOLERANCE * estimate.logical_error_rate());
+        assert!(!estimate.is_tail_negligible(WEIGHT_SHELL_TAIL_TOLERANCE));
+        assert!(estimate.is_tail_negligible(estimate.tail_probability / estimate.logical_error_rate()));
+    }
+}
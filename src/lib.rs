@@ -7,7 +7,9 @@ extern crate clap;
 #[macro_use] extern crate serde_json;
 extern crate ndarray;
 extern crate rand;
+#[cfg(feature="web")]
 extern crate actix_web;
+#[cfg(feature="web")]
 extern crate actix_cors;
 extern crate serde;
 extern crate libc;
@@ -36,14 +38,18 @@ extern crate urlencoding;
 #[cfg(feature="hyperion")]
 extern crate mwps;
 #[macro_use] extern crate enum_dispatch;
+#[cfg(feature="readline")]
+extern crate rustyline;
 
 pub mod util;
 pub mod test;
 pub mod tool;
 pub mod types;
+#[cfg(feature="web")]
 pub mod web;
 pub mod cli;
 pub mod blossom_v;
+pub mod exact_matching;
 pub mod reproducible_rand;
 // pub mod distributed_uf_decoder;  TODO: migrate back
 // pub mod fpga_generator;  TODO: migrate back
@@ -56,12 +62,17 @@ pub mod complete_model_graph;
 pub mod noise_model;
 pub mod decoder_mwpm;
 pub mod decoder_tailored_mwpm;
+pub mod decoder_tailored_union_find;
+pub mod decoder_uf_then_mwpm;
 pub mod decoder_union_find;
 pub mod tailored_model_graph;
 pub mod tailored_complete_model_graph;
 pub mod noise_model_builder;
 pub mod union_find;
 pub mod erasure_graph;
+pub mod position_indexer;
+pub mod npy;
+pub mod syndrome_corpus;
 #[cfg(feature="fusion_blossom")]
 pub mod decoder_fusion;
 pub mod visualize;
@@ -71,6 +82,12 @@ pub mod decoder_hyper_union_find;
 #[cfg(feature="python_binding")]
 use pyo3::prelude::*;
 pub mod simulator_compact;
+pub mod prelude;
+pub mod repl;
+pub mod artifact;
+pub mod importance_sampling;
+pub mod streaming_statistics;
+pub mod stim_circuit;
 
 
 #[cfg(feature="python_binding")]
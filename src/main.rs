@@ -2,12 +2,15 @@ extern crate clap;
 extern crate pbr;
 
 use qecp::cli::*;
+#[cfg(feature="web")]
 use qecp::web;
+use qecp::repl::Repl;
 use crate::clap::Parser;
+#[cfg(feature="readline")]
+use std::io::IsTerminal;
 
 
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
+fn main() -> std::io::Result<()> {
 
     match Cli::parse().command {
         Commands::Test { command } => {
@@ -18,16 +21,44 @@ async fn main() -> std::io::Result<()> {
             print!("{}", output);  // outputs normally comes with \n
         }
         Commands::Server(server_parameters) => {
-            let port = server_parameters.port;
-            let addr = server_parameters.addr;
-            let root_url = server_parameters.root_url;
-            println!("QECP server booting...");
-            println!("visit http://{}:{}{}<commands>", addr, port, root_url);
-            println!("supported commands include `hello`, `naive_decoder`, etc. See `web.rs` for more commands");
-            web::run_server(port, addr, root_url).await?;
+            run_server(server_parameters)?;
+        }
+        Commands::Repl => {
+            run_repl(Repl::new())?;
         }
     }
 
     Ok(())
 
 }
+
+#[cfg(feature="readline")]
+fn run_repl(mut repl: Repl) -> std::io::Result<()> {
+    if std::io::stdin().is_terminal() {
+        repl.run_interactive().map_err(std::io::Error::other)
+    } else {
+        repl.run_script(std::io::stdin().lock(), &mut std::io::stdout())
+    }
+}
+
+/// without line editing, a terminal is read line by line just like a script
+#[cfg(not(feature="readline"))]
+fn run_repl(mut repl: Repl) -> std::io::Result<()> {
+    repl.run_script(std::io::stdin().lock(), &mut std::io::stdout())
+}
+
+#[cfg(feature="web")]
+fn run_server(server_parameters: ServerParameters) -> std::io::Result<()> {
+    let port = server_parameters.port;
+    let addr = server_parameters.addr;
+    let root_url = server_parameters.root_url;
+    println!("QECP server booting...");
+    println!("visit http://{}:{}{}<commands>", addr, port, root_url);
+    println!("supported commands include `hello`, `naive_decoder`, etc. See `web.rs` for more commands");
+    actix_web::rt::System::new().block_on(web::run_server(port, addr, root_url))
+}
+
+#[cfg(not(feature="web"))]
+fn run_server(_server_parameters: ServerParameters) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "the server is not available in this build, please enable the `web` feature"))
+}
@@ -11,12 +11,14 @@ use std::sync::{Arc, Mutex};
 use serde::{Serialize, Deserialize};
 use super::float_cmp;
 use super::visualize::*;
+use super::position_indexer::*;
+use super::code_builder::code_builder_logical_operators;
 #[cfg(feature="python_binding")]
 use pyo3::prelude::*;
 
 
 /// edges connecting two nontrivial measurements generated by a single error
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "python_binding", pyclass)]
 pub struct ModelGraph {
     pub nodes: Vec::< Vec::< Vec::< Option< Box< ModelGraphNode > > > > >,
@@ -54,7 +56,7 @@ impl QecpVisualizer for ModelGraph {
 }
 
 /// only defined for measurement nodes (including virtual measurement nodes)
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelGraphNode {
     /// used when building the graph, record all possible edges that connect the two measurement syndromes.
     /// (this might be dropped to save memory usage after election)
@@ -68,7 +70,7 @@ pub struct ModelGraphNode {
 }
 
 /// without concrete correction, can be used to save memory but not all error pattern will be recorded
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BriefModelGraphEdge {
     /// the probability of this edge to happen
     pub probability: f64,
@@ -76,7 +78,7 @@ pub struct BriefModelGraphEdge {
     pub weight: f64,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelGraphEdge {
     /// the probability of this edge to happen
     pub probability: f64,
@@ -99,7 +101,7 @@ impl ModelGraphEdge {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelGraphBoundary {
     /// the probability of this boundary edge to happen
     pub probability: f64,
@@ -113,6 +115,31 @@ pub struct ModelGraphBoundary {
     pub virtual_node: Option<Position>,
 }
 
+/// a spatial and temporal window of the decoding graph, given as inclusive ranges `[start, end]` of each axis where an absent range is unbounded,
+/// e.g. `{"t": [6, 18], "i": [0, 6]}` in `--decode-region`; see [`ModelGraph::clip_to_region`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Region {
+    #[serde(default)]
+    pub t: Option<(usize, usize)>,
+    #[serde(default)]
+    pub i: Option<(usize, usize)>,
+    #[serde(default)]
+    pub j: Option<(usize, usize)>,
+}
+
+impl Region {
+    pub fn contains(&self, position: &Position) -> bool {
+        let within = |range: &Option<(usize, usize)>, value: usize| range.map(|(start, end)| start <= value && value <= end).unwrap_or(true);
+        within(&self.t, position.t) && within(&self.i, position.i) && within(&self.j, position.j)
+    }
+
+    /// the defects inside the region, treating those outside as absent
+    pub fn clip_measurement(&self, sparse_measurement: &SparseMeasurement) -> SparseMeasurement {
+        SparseMeasurement::new_set(sparse_measurement.iter().filter(|position| self.contains(position)).cloned().collect())
+    }
+}
+
 impl ModelGraphBoundary {
     fn component_edge_info(&self, abbrev: bool) -> serde_json::Value {
         json!({
@@ -125,6 +152,52 @@ impl ModelGraphBoundary {
     }
 }
 
+/// identifies an elected edge of the model graph: either a normal edge between two nodes (the smaller position first) or the boundary edge of a node
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum EdgeId {
+    Edge(Position, Position),
+    Boundary(Position),
+}
+
+/// the elected edges of the model graph as a check matrix in the compressed sparse column (CSC) format, with one row per detector of
+/// [`DetectorMap`] and one column per edge where a boundary edge is a column of a single nonzero, as accepted by PyMatching, e.g.
+/// `Matching.from_check_matrix(csc_matrix((ones, indices, indptr), shape=(detectors, len(weights))), weights, error_probabilities, faults_matrix)`;
+/// see [`ModelGraph::check_matrix`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckMatrix {
+    /// the number of rows
+    pub detectors: usize,
+    /// the rows of column `k` are `indices[indptr[k]..indptr[k+1]]`
+    pub indptr: Vec<usize>,
+    pub indices: Vec<usize>,
+    /// the weight of each column as computed by the weight function
+    pub weights: Vec<f64>,
+    /// the (combined) probability of each column
+    pub error_probabilities: Vec<f64>,
+    /// the columns of the boundary edges
+    pub boundary_edges: Vec<usize>,
+    /// the number of logical operators, i.e. the rows of the faults matrix
+    pub observables: usize,
+    /// the faults matrix in the same format: column `k` flips the logical operators `faults_indices[faults_indptr[k]..faults_indptr[k+1]]`
+    pub faults_indptr: Vec<usize>,
+    pub faults_indices: Vec<usize>,
+}
+
+impl CheckMatrix {
+    pub fn columns(&self) -> usize {
+        self.weights.len()
+    }
+
+    fn push_column(&mut self, rows: &[usize], weight: f64, probability: f64, observables: Vec<usize>) {
+        self.indices.extend_from_slice(rows);
+        self.indptr.push(self.indices.len());
+        self.weights.push(weight);
+        self.error_probabilities.push(probability);
+        self.faults_indices.extend(observables);
+        self.faults_indptr.push(self.faults_indices.len());
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WeightFunction {
     /// Autotune: compute weight based on noise model
@@ -135,6 +208,31 @@ pub enum WeightFunction {
     Unweighted,
 }
 
+/// 64-bit FNV-1a hash of the bytes written to it; unlike [`std::collections::hash_map::DefaultHasher`], the value is stable across Rust versions
+/// and can be saved to disk
+struct StableHasher {
+    hash: u64,
+}
+
+impl StableHasher {
+    fn new() -> Self {
+        Self { hash: 0xcbf29ce484222325 }
+    }
+}
+
+impl std::io::Write for StableHasher {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        for &byte in bytes.iter() {
+            self.hash ^= byte as u64;
+            self.hash = self.hash.wrapping_mul(0x100000001b3);
+        }
+        Ok(bytes.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 pub mod weight_function {
 
     pub fn autotune(p: f64) -> f64 {
@@ -161,16 +259,13 @@ impl ModelGraph {
                     (0..simulator.horizontal).map(|j| {
                         let position = &pos!(t, i, j);
                         // model graph only contains real node at measurement round
-                        if t != 0 && t % simulator.measurement_cycles == 0 && simulator.is_node_real(position) {
-                            let node = simulator.get_node_unwrap(position);
-                            if node.gate_type.is_measurement() {  // only define model graph node for measurements
-                                return Some(Box::new(ModelGraphNode {
-                                    all_edges: BTreeMap::new(),
-                                    edges: BTreeMap::new(),
-                                    all_boundaries: Vec::with_capacity(0),  // only a few nodes have boundary, so no need to have initial capacity
-                                    boundary: None,
-                                }))
-                            }
+                        if PositionIndexer::is_stabilizer(simulator, position) {
+                            return Some(Box::new(ModelGraphNode {
+                                all_edges: BTreeMap::new(),
+                                edges: BTreeMap::new(),
+                                all_boundaries: Vec::with_capacity(0),  // only a few nodes have boundary, so no need to have initial capacity
+                                boundary: None,
+                            }))
                         }
                         None
                     }).collect()
@@ -201,15 +296,25 @@ impl ModelGraph {
 
     /// build model graph given the simulator
     pub fn build(&mut self, simulator: &mut Simulator, noise_model: Arc<NoiseModel>, weight_function: &WeightFunction, parallel: usize, use_combined_probability: bool, use_brief_edge: bool) {
+        self.build_stabilizer_type(simulator, noise_model, weight_function, parallel, use_combined_probability, use_brief_edge, None)
+    }
+
+    /// build model graph given the simulator, only with the edges and boundaries of the stabilizers of `stabilizer_type` if given;
+    /// the result is the same as [`ModelGraph::build`] followed by [`ModelGraph::retain_stabilizer_type`], without storing the other sub-graph
+    #[allow(clippy::too_many_arguments)]
+    pub fn build_stabilizer_type(&mut self, simulator: &mut Simulator, noise_model: Arc<NoiseModel>, weight_function: &WeightFunction, parallel: usize
+            , use_combined_probability: bool, use_brief_edge: bool, stabilizer_type: Option<QubitType>) {
         match weight_function {
-            WeightFunction::Autotune => self.build_with_weight_function(simulator, noise_model, weight_function::autotune, parallel, use_combined_probability, use_brief_edge),
-            WeightFunction::AutotuneImproved => self.build_with_weight_function(simulator, noise_model, weight_function::autotune_improved, parallel, use_combined_probability, use_brief_edge),
-            WeightFunction::Unweighted => self.build_with_weight_function(simulator, noise_model, weight_function::unweighted, parallel, use_combined_probability, use_brief_edge),
+            WeightFunction::Autotune => self.build_with_weight_function_stabilizer_type(simulator, noise_model, weight_function::autotune, parallel, use_combined_probability, use_brief_edge, stabilizer_type),
+            WeightFunction::AutotuneImproved => self.build_with_weight_function_stabilizer_type(simulator, noise_model, weight_function::autotune_improved, parallel, use_combined_probability, use_brief_edge, stabilizer_type),
+            WeightFunction::Unweighted => self.build_with_weight_function_stabilizer_type(simulator, noise_model, weight_function::unweighted, parallel, use_combined_probability, use_brief_edge, stabilizer_type),
         }
     }
 
     /// single-thread computation with region
-    fn build_with_weight_function_region<F>(&mut self, simulator: &mut Simulator, noise_model: Arc<NoiseModel>, weight_of: F, t_start: usize, t_end: usize, use_brief_edge: bool) where F: Fn(f64) -> f64 + Copy {
+    #[allow(clippy::too_many_arguments)]
+    fn build_with_weight_function_region<F>(&mut self, simulator: &mut Simulator, noise_model: Arc<NoiseModel>, weight_of: F, t_start: usize, t_end: usize, use_brief_edge: bool
+            , stabilizer_type: Option<QubitType>) where F: Fn(f64) -> f64 + Copy {
         // calculate all possible errors to be iterated
         let mut all_possible_errors: Vec<Either<ErrorType, CorrelatedPauliErrorType>> = Vec::new();
         for error_type in ErrorType::all_possible_errors().drain(..) {
@@ -273,6 +378,11 @@ impl ModelGraph {
                     if sparse_measurement_real.len() == 0 {  // no way to detect it, ignore
                         continue
                     }
+                    if let Some(stabilizer_type) = stabilizer_type {  // the edges of other stabilizers are never used
+                        if sparse_measurement_real.iter().any(|position| simulator.get_node_unwrap(position).qubit_type != stabilizer_type) {
+                            continue
+                        }
+                    }
                     // println!("{:?} at {} will cause measurement errors: real {:?} and virtual {:?}", error, position, sparse_measurement_real, sparse_measurement_virtual);
                     if sparse_measurement_real.len() == 1 {  // boundary edge
                         let position = &sparse_measurement_real[0];
@@ -310,6 +420,13 @@ impl ModelGraph {
     /// build model graph given the simulator with customized weight function;
     /// if `optimize_memory_usage` is set to True, then not all edges are recorded but only the optimal one
     pub fn build_with_weight_function<F>(&mut self, simulator: &mut Simulator, noise_model: Arc<NoiseModel>, weight_of: F, parallel: usize, use_combined_probability: bool, use_brief_edge: bool) where F: Fn(f64) -> f64 + Copy + Send + Sync + 'static {
+        self.build_with_weight_function_stabilizer_type(simulator, noise_model, weight_of, parallel, use_combined_probability, use_brief_edge, None)
+    }
+
+    /// build model graph with customized weight function, only with the edges and boundaries of the stabilizers of `stabilizer_type` if given
+    #[allow(clippy::too_many_arguments)]
+    pub fn build_with_weight_function_stabilizer_type<F>(&mut self, simulator: &mut Simulator, noise_model: Arc<NoiseModel>, weight_of: F, parallel: usize
+            , use_combined_probability: bool, use_brief_edge: bool, stabilizer_type: Option<QubitType>) where F: Fn(f64) -> f64 + Copy + Send + Sync + 'static {
         debug_assert!({
             let mut state_clean = true;
             simulator_iter!(simulator, position, node, {
@@ -327,7 +444,7 @@ impl ModelGraph {
             state_clean
         });
         if parallel <= 1 {
-            self.build_with_weight_function_region(simulator, noise_model, weight_of, 0, simulator.height, use_brief_edge);
+            self.build_with_weight_function_region(simulator, noise_model, weight_of, 0, simulator.height, use_brief_edge, stabilizer_type);
         } else {
             // spawn `parallel` threads to compute in parallel
             let mut handlers = Vec::new();
@@ -345,7 +462,7 @@ impl ModelGraph {
                 let noise_model = Arc::clone(&noise_model);
                 handlers.push(std::thread::spawn(move || {
                     let mut instance = instance.lock().unwrap();
-                    instance.build_with_weight_function_region(&mut simulator, noise_model, weight_of, t_start, t_end, use_brief_edge);
+                    instance.build_with_weight_function_region(&mut simulator, noise_model, weight_of, t_start, t_end, use_brief_edge, stabilizer_type);
                 }));
             }
             for handler in handlers.drain(..) {
@@ -446,6 +563,76 @@ impl ModelGraph {
         &node.boundary.as_ref().unwrap().correction
     }
 
+    /// the total binary entropy `H(p) = - p log2(p) - (1-p) log2(1-p)` of the elected edges (each counted once) and boundaries, as a summary
+    /// of how informative the noise model is to the decoder: edges close to p = 1/2 carry no information; also returns the number of edges
+    pub fn edge_entropy(&self) -> (usize, f64) {
+        let binary_entropy = |p: f64| -> f64 {
+            if p <= 0. || p >= 1. { 0. } else { - p * p.log2() - (1. - p) * (1. - p).log2() }
+        };
+        let mut edge_count = 0;
+        let mut entropy = 0.;
+        for t in 0..self.nodes.len() {
+            for i in 0..self.nodes[t].len() {
+                for j in 0..self.nodes[t][i].len() {
+                    let position = &pos!(t, i, j);
+                    if !self.is_node_exist(position) {
+                        continue
+                    }
+                    let node = self.get_node_unwrap(position);
+                    for (peer_position, edge) in node.edges.iter() {
+                        if position < peer_position {
+                            edge_count += 1;
+                            entropy += binary_entropy(edge.probability);
+                        }
+                    }
+                    if let Some(boundary) = &node.boundary {
+                        edge_count += 1;
+                        entropy += binary_entropy(boundary.probability);
+                    }
+                }
+            }
+        }
+        (edge_count, entropy)
+    }
+
+    /// the elected edges whose endpoints are toggled by the error pattern, sorted; each error in the pattern is measured individually and
+    /// its nontrivial measurements are split by qubit type, so every part must be an edge or a boundary of this graph. an edge flipped an
+    /// even number of times is not reported. this is mainly used for test assertions, so it panics on errors that the graph cannot explain
+    pub fn edges_flipped_by(&self, simulator: &mut Simulator, pattern: &SparseErrorPattern) -> Vec<EdgeId> {
+        let mut flipped = BTreeMap::<EdgeId, bool>::new();
+        for (position, error) in pattern.errors.iter() {
+            let mut single_error = SparseErrorPattern::new();
+            single_error.add(position.clone(), *error);
+            let (_, sparse_measurement_real, _) = simulator.fast_measurement_given_few_errors(&single_error);
+            let mut groups: Vec<(QubitType, Vec<Position>)> = Vec::new();
+            for defect in sparse_measurement_real.to_vec().drain(..) {
+                let qubit_type = simulator.get_node_unwrap(&defect).qubit_type;
+                match groups.iter_mut().find(|(group_type, _)| *group_type == qubit_type) {
+                    Some((_, group)) => group.push(defect),
+                    None => groups.push((qubit_type, vec![defect])),
+                }
+            }
+            for (_, mut group) in groups.drain(..) {
+                group.sort();
+                let edge_id = match group.len() {
+                    1 => {
+                        assert!(self.get_node_unwrap(&group[0]).boundary.is_some(), "{:?} at {} flips {} which has no boundary edge", error, position, group[0]);
+                        EdgeId::Boundary(group[0].clone())
+                    },
+                    2 => {
+                        assert!(self.get_node_unwrap(&group[0]).edges.contains_key(&group[1]), "{:?} at {} flips {} and {} which are not connected"
+                            , error, position, group[0], group[1]);
+                        EdgeId::Edge(group[0].clone(), group[1].clone())
+                    },
+                    _ => panic!("{:?} at {} flips {:?} which is not a single edge", error, position, group),
+                };
+                let is_flipped = flipped.entry(edge_id).or_insert(false);
+                *is_flipped = !*is_flipped;
+            }
+        }
+        flipped.into_iter().filter(|(_, is_flipped)| *is_flipped).map(|(edge_id, _)| edge_id).collect()
+    }
+
     /// if there are multiple edges connecting two stabilizer measurements, elect the best one
     pub fn elect_edges<F>(&mut self, simulator: &Simulator, use_combined_probability: bool, weight_of: F) where F: Fn(f64) -> f64 + Copy {
         simulator_iter!(simulator, position, delta_t => simulator.measurement_cycles, if self.is_node_exist(position) {
@@ -486,29 +673,7 @@ impl ModelGraph {
             }
             // elect boundary edge
             if model_graph_node.all_boundaries.len() > 0 {
-                let mut elected_idx = 0;
-                let mut elected_probability = model_graph_node.all_boundaries[0].probability;
-                for i in 1..model_graph_node.all_boundaries.len() {
-                    let edge = &model_graph_node.all_boundaries[i];
-                    // update `elected_probability`
-                    if use_combined_probability {
-                        elected_probability = elected_probability * (1. - edge.probability) + edge.probability * (1. - elected_probability);  // XOR
-                    } else {
-                        elected_probability = elected_probability.max(edge.probability);
-                    }
-                    // update `elected_idx`
-                    let best_edge = &model_graph_node.all_boundaries[elected_idx];
-                    if edge.probability > best_edge.probability {
-                        elected_idx = i;  // set as best, use its 
-                    }
-                }
-                let elected = ModelGraphBoundary {
-                    probability: elected_probability,
-                    weight: weight_of(elected_probability),
-                    error_pattern: model_graph_node.all_boundaries[elected_idx].error_pattern.clone(),
-                    correction: model_graph_node.all_boundaries[elected_idx].correction.clone(),
-                    virtual_node: model_graph_node.all_boundaries[elected_idx].virtual_node.clone(),
-                };
+                let elected = Self::elect_boundary(&model_graph_node.all_boundaries, use_combined_probability, weight_of);
                 // update elected edge
                 // println!("{} to virtual boundary elected probability: {}", position, elected.probability);
                 model_graph_node.boundary = Some(Box::new(elected));
@@ -537,6 +702,254 @@ impl ModelGraph {
         });
     }
 
+    /// elect a single boundary edge out of the given ones, with the (combined) probability of all of them and the correction of the most likely one
+    fn elect_boundary<F>(boundaries: &[ModelGraphBoundary], use_combined_probability: bool, weight_of: F) -> ModelGraphBoundary where F: Fn(f64) -> f64 + Copy {
+        let mut elected_idx = 0;
+        let mut elected_probability = boundaries[0].probability;
+        for i in 1..boundaries.len() {
+            let edge = &boundaries[i];
+            // update `elected_probability`
+            if use_combined_probability {
+                elected_probability = elected_probability * (1. - edge.probability) + edge.probability * (1. - elected_probability);  // XOR
+            } else {
+                elected_probability = elected_probability.max(edge.probability);
+            }
+            // update `elected_idx`
+            let best_edge = &boundaries[elected_idx];
+            if edge.probability > best_edge.probability {
+                elected_idx = i;  // set as best, use its 
+            }
+        }
+        ModelGraphBoundary {
+            probability: elected_probability,
+            weight: weight_of(elected_probability),
+            error_pattern: boundaries[elected_idx].error_pattern.clone(),
+            correction: boundaries[elected_idx].correction.clone(),
+            virtual_node: boundaries[elected_idx].virtual_node.clone(),
+        }
+    }
+
+    /// the physical boundary segments of the code: the virtual stabilizers of the same type that are next to each other (at most 2 apart in both
+    /// `i` and `j`) form a segment, e.g. the 4 sides of a planar code; each virtual stabilizer `(i, j)` is mapped to the smallest `(i, j)` of its
+    /// segment, which represents the segment
+    pub fn boundary_segments(simulator: &Simulator) -> BTreeMap<(usize, usize), (usize, usize)> {
+        let mut virtual_stabilizers = Vec::new();
+        simulator_iter_virtual!(simulator, position, node, t => simulator.measurement_cycles, if node.gate_type.is_measurement() {
+            virtual_stabilizers.push(((position.i, position.j), node.qubit_type));
+        });
+        let mut segments = BTreeMap::new();
+        for index in 0..virtual_stabilizers.len() {  // flood fill from the smallest position of each segment
+            let (representative, qubit_type) = virtual_stabilizers[index];
+            if segments.contains_key(&representative) {
+                continue
+            }
+            segments.insert(representative, representative);
+            let mut pending = vec![representative];
+            while let Some((i, j)) = pending.pop() {
+                for &((peer_i, peer_j), peer_qubit_type) in virtual_stabilizers.iter() {
+                    if peer_qubit_type == qubit_type && !segments.contains_key(&(peer_i, peer_j)) && i.abs_diff(peer_i) <= 2 && j.abs_diff(peer_j) <= 2 {
+                        segments.insert((peer_i, peer_j), representative);
+                        pending.push((peer_i, peer_j));
+                    }
+                }
+            }
+        }
+        segments
+    }
+
+    /// split the boundary into a representative per physical boundary segment, see [`ModelGraph::boundary_segments`]: the boundary edges of a
+    /// node are elected within each segment instead of all together, and the most likely segment becomes the boundary of the node. Otherwise the
+    /// (combined) probability of reaching any boundary comes with the correction of the single most likely boundary edge, which may lead to a
+    /// less likely segment. Boundary edges without a virtual stabilizer form a segment of their own.
+    /// Note that each node still has a single boundary edge, which the MWPM decoder turns into the node's own virtual boundary vertex; the
+    /// segments only decide where this edge leads to instead of adding a vertex shared by all the nodes next to a segment
+    pub fn split_boundary(&mut self, simulator: &Simulator, weight_function: &WeightFunction, use_combined_probability: bool) {
+        match weight_function {
+            WeightFunction::Autotune => self.split_boundary_with_weight_function(simulator, weight_function::autotune, use_combined_probability),
+            WeightFunction::AutotuneImproved => self.split_boundary_with_weight_function(simulator, weight_function::autotune_improved, use_combined_probability),
+            WeightFunction::Unweighted => self.split_boundary_with_weight_function(simulator, weight_function::unweighted, use_combined_probability),
+        }
+    }
+
+    pub fn split_boundary_with_weight_function<F>(&mut self, simulator: &Simulator, weight_of: F, use_combined_probability: bool) where F: Fn(f64) -> f64 + Copy {
+        let segments = Self::boundary_segments(simulator);
+        simulator_iter!(simulator, position, delta_t => simulator.measurement_cycles, if self.is_node_exist(position) {
+            let model_graph_node = self.get_node_mut_unwrap(position);
+            let mut segment_boundaries: BTreeMap<Option<(usize, usize)>, Vec<ModelGraphBoundary>> = BTreeMap::new();
+            for boundary in model_graph_node.all_boundaries.iter() {
+                let segment = boundary.virtual_node.as_ref().map(|virtual_node| segments[&(virtual_node.i, virtual_node.j)]);
+                segment_boundaries.entry(segment).or_default().push(boundary.clone());
+            }
+            let mut elected: Option<ModelGraphBoundary> = None;
+            for boundaries in segment_boundaries.values() {
+                let segment_elected = Self::elect_boundary(boundaries, use_combined_probability, weight_of);
+                if elected.as_ref().map(|elected| segment_elected.probability > elected.probability).unwrap_or(true) {
+                    elected = Some(segment_elected);
+                }
+            }
+            model_graph_node.boundary = elected.map(Box::new);
+        });
+    }
+
+    /// scale the weight of every boundary edge, e.g. to study over- or under-matching to the boundaries; the probabilities are unchanged
+    pub fn scale_boundary_weights(&mut self, simulator: &Simulator, multiplier: f64) {
+        assert!(multiplier > 0. && multiplier.is_finite(), "boundary weight multiplier should be positive, but got {}", multiplier);
+        simulator_iter!(simulator, position, delta_t => simulator.measurement_cycles, if self.is_node_exist(position) {
+            let model_graph_node = self.get_node_mut_unwrap(position);
+            for boundary in model_graph_node.all_boundaries.iter_mut() {
+                boundary.weight *= multiplier;
+            }
+            if let Some(boundary) = model_graph_node.boundary.as_mut() {
+                boundary.weight *= multiplier;
+            }
+        });
+    }
+
+    /// remove the edges and boundaries of every node that is not a stabilizer of `qubit_type`, so that a CSS code is decoded on a single
+    /// sub-graph; the removed nodes become isolated and must never be given as defects
+    pub fn retain_stabilizer_type(&mut self, simulator: &Simulator, qubit_type: QubitType) {
+        simulator_iter!(simulator, position, node, delta_t => simulator.measurement_cycles, if self.is_node_exist(position) && node.qubit_type != qubit_type {
+            let model_graph_node = self.get_node_mut_unwrap(position);
+            model_graph_node.all_edges.clear();
+            model_graph_node.edges.clear();
+            model_graph_node.all_boundaries.clear();
+            model_graph_node.boundary = None;
+        });
+    }
+
+    /// cut the graph to the nodes inside `region`, e.g. to isolate the part of the volume responsible for a failure: an edge crossing the border
+    /// becomes a boundary edge of the inside node with its original weight and correction, which is elected if it's lighter than the existing
+    /// boundary; the nodes outside become isolated and must never be given as defects, see [`Region::clip_measurement`]
+    pub fn clip_to_region(&mut self, simulator: &Simulator, region: &Region) {
+        simulator_iter!(simulator, position, delta_t => simulator.measurement_cycles, if self.is_node_exist(position) {
+            let model_graph_node = self.get_node_mut_unwrap(position);
+            if region.contains(position) {
+                let crossing_peers: Vec<Position> = model_graph_node.edges.keys().filter(|peer_position| !region.contains(peer_position)).cloned().collect();
+                for peer_position in crossing_peers.iter() {
+                    let edge = model_graph_node.edges.remove(peer_position).unwrap();
+                    model_graph_node.all_edges.remove(peer_position);
+                    let boundary = ModelGraphBoundary {
+                        probability: edge.probability,
+                        weight: edge.weight,
+                        error_pattern: edge.error_pattern,
+                        correction: edge.correction,
+                        virtual_node: None,
+                    };
+                    if model_graph_node.boundary.as_ref().map(|existing| boundary.weight < existing.weight).unwrap_or(true) {
+                        model_graph_node.boundary = Some(Box::new(boundary.clone()));
+                    }
+                    model_graph_node.all_boundaries.push(boundary);
+                }
+            } else {
+                model_graph_node.all_edges.clear();
+                model_graph_node.edges.clear();
+                model_graph_node.all_boundaries.clear();
+                model_graph_node.boundary = None;
+            }
+        });
+    }
+
+    /// the weights of the elected edges as computed by the weight function (e.g. autotune), each pair of nodes listed once with the smaller
+    /// position first and in ascending order, e.g. to diff against a weight file; boundary edges are not included
+    pub fn weights(&self) -> Vec<(Position, Position, f64)> {
+        let mut weights = Vec::new();
+        for t in 0..self.nodes.len() {
+            for i in 0..self.nodes[t].len() {
+                for j in 0..self.nodes[t][i].len() {
+                    let position = pos!(t, i, j);
+                    if self.is_node_exist(&position) {
+                        for (peer_position, edge) in self.get_node_unwrap(&position).edges.iter() {
+                            if &position < peer_position {
+                                weights.push((position.clone(), peer_position.clone(), edge.weight));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        weights
+    }
+
+    /// the elected edges as a [`CheckMatrix`], columns ordered by their smaller detector and the boundary edge of a detector after its normal
+    /// edges; a column flips a logical operator of [`code_builder_logical_operators`] if its correction anticommutes with it
+    pub fn check_matrix(&self, simulator: &Simulator) -> CheckMatrix {
+        let detector_map = DetectorMap::from_simulator(simulator);
+        let logical_operators = code_builder_logical_operators(simulator).unwrap_or_default();
+        let mut check_matrix = CheckMatrix {
+            detectors: detector_map.len(),
+            indptr: vec![0],
+            indices: Vec::new(),
+            weights: Vec::new(),
+            error_probabilities: Vec::new(),
+            boundary_edges: Vec::new(),
+            observables: logical_operators.len(),
+            faults_indptr: vec![0],
+            faults_indices: Vec::new(),
+        };
+        for (index, position) in detector_map.positions().iter().enumerate() {
+            if !self.is_node_exist(position) {
+                continue
+            }
+            let node = self.get_node_unwrap(position);
+            for (peer_position, edge) in node.edges.iter() {
+                if position < peer_position {
+                    let peer_index = detector_map.index(peer_position).expect("model graph edge between non-detector nodes");
+                    check_matrix.push_column(&[index, peer_index], edge.weight, edge.probability, edge.correction.flipped_observables(&logical_operators));
+                }
+            }
+            if let Some(boundary) = node.boundary.as_ref() {
+                check_matrix.boundary_edges.push(check_matrix.columns());
+                check_matrix.push_column(&[index], boundary.weight, boundary.probability, boundary.correction.flipped_observables(&logical_operators));
+            }
+        }
+        check_matrix
+    }
+
+    /// identifies the configuration that a model graph is built from, i.e. the circuit, the noise model and the options of [`ModelGraph::build`],
+    /// so that a cached model graph is never used with a different configuration; stable across runs and platforms
+    pub fn config_hash(simulator: &Simulator, noise_model: &NoiseModel, weight_function: &WeightFunction, use_combined_probability: bool
+            , use_brief_edge: bool) -> String {
+        let mut hasher = StableHasher::new();
+        serde_json::to_writer(&mut hasher, &json!({
+            "code_type": simulator.code_type,
+            "code_size": simulator.code_size,
+            "weight_function": weight_function,
+            "use_combined_probability": use_combined_probability,
+            "use_brief_edge": use_brief_edge,
+        })).unwrap();
+        simulator_iter!(simulator, position, node, {
+            serde_json::to_writer(&mut hasher, &json!([position, node.qubit_type, node.gate_type, node.gate_peer, node.is_virtual, node.is_peer_virtual])).unwrap();
+        });
+        serde_json::to_writer(&mut hasher, noise_model).unwrap();
+        format!("{:016x}", hasher.hash)
+    }
+
+    /// save the model graph together with the hash of its configuration, see [`ModelGraph::config_hash`]
+    pub fn save(&self, filepath: &str, config_hash: &str) -> Result<(), String> {
+        let file = std::fs::File::create(filepath).map_err(|e| format!("[error] cannot create {}: {}", filepath, e))?;
+        serde_json::to_writer(std::io::BufWriter::new(file), &json!({
+            "config_hash": config_hash,
+            "model_graph": self,
+        })).map_err(|e| format!("[error] cannot write {}: {}", filepath, e))
+    }
+
+    /// load a model graph saved by [`ModelGraph::save`], which must be built from the configuration of `config_hash`
+    pub fn load(filepath: &str, config_hash: &str) -> Result<Self, String> {
+        #[derive(Deserialize)]
+        struct CachedModelGraph {
+            config_hash: String,
+            model_graph: ModelGraph,
+        }
+        let file = std::fs::File::open(filepath).map_err(|e| format!("[error] cannot open {}: {}", filepath, e))?;
+        let cached: CachedModelGraph = serde_json::from_reader(std::io::BufReader::new(file))
+            .map_err(|e| format!("[error] cannot read model graph from {}: {}", filepath, e))?;
+        if cached.config_hash != config_hash {
+            return Err(format!("[error] model graph {} is built from another configuration: config hash {} but expecting {}"
+                , filepath, cached.config_hash, config_hash))
+        }
+        Ok(cached.model_graph)
+    }
+
     /// create json object for debugging and viewing
     pub fn to_json(&self, simulator: &Simulator) -> serde_json::Value {
         json!({
@@ -570,6 +983,9 @@ impl ModelGraph {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::code_builder::*;
+    use super::super::noise_model_builder::*;
+    use std::collections::BTreeSet;
 
     #[test]
     fn model_graph_basics() {  // cargo test model_graph_basics -- --nocapture
@@ -582,4 +998,153 @@ mod tests {
         }
     }
 
+    #[test]
+    fn model_graph_weights() {  // cargo test model_graph_weights -- --nocapture
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), 0.01, 10., 0.);
+        let noise_model = Arc::new(noise_model);
+        let weights = |weight_function: WeightFunction| -> Vec<(Position, Position, f64)> {
+            let mut model_graph = ModelGraph::new(&simulator);
+            model_graph.build(&mut simulator.clone(), Arc::clone(&noise_model), &weight_function, 1, true, false);
+            model_graph.weights()
+        };
+        let autotuned = weights(WeightFunction::AutotuneImproved);
+        let unweighted = weights(WeightFunction::Unweighted);
+        assert!(!autotuned.is_empty());
+        assert!(autotuned.iter().all(|(position1, position2, _)| position1 < position2));
+        // the same edges, but the biased noise gives different autotuned weights to the measurement errors and the data qubit errors
+        assert_eq!(autotuned.iter().map(|(a, b, _)| (a, b)).collect::<Vec<_>>(), unweighted.iter().map(|(a, b, _)| (a, b)).collect::<Vec<_>>());
+        assert!(autotuned.iter().any(|(_, _, weight)| *weight != autotuned[0].2));
+        assert!(unweighted.iter().all(|(_, _, weight)| *weight == unweighted[0].2));
+    }
+
+    #[test]
+    fn model_graph_clip_to_region() {  // cargo test model_graph_clip_to_region -- --nocapture
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(3, 5, 5));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), 0.01, 1., 0.);
+        let mut model_graph = ModelGraph::new(&simulator);
+        model_graph.build(&mut simulator, Arc::new(noise_model), &WeightFunction::AutotuneImproved, 1, true, false);
+        let region = Region { t: Some((simulator.measurement_cycles, simulator.measurement_cycles * 2)), i: Some((0, 5)), j: None };
+        let mut clipped = model_graph.clone();
+        clipped.clip_to_region(&simulator, &region);
+        let mut crossing_count = 0;
+        simulator_iter!(simulator, position, delta_t => simulator.measurement_cycles, if model_graph.is_node_exist(position) {
+            let node = model_graph.get_node_unwrap(position);
+            let clipped_node = clipped.get_node_unwrap(position);
+            if !region.contains(position) {
+                assert!(clipped_node.edges.is_empty() && clipped_node.boundary.is_none());
+                continue
+            }
+            // the edges inside the region are kept, the crossing ones become boundaries with the same weight
+            let mut lightest_boundary = node.boundary.as_ref().map(|boundary| boundary.weight);
+            for (peer_position, edge) in node.edges.iter() {
+                if region.contains(peer_position) {
+                    assert_eq!(clipped_node.edges[peer_position].weight, edge.weight);
+                } else {
+                    crossing_count += 1;
+                    assert!(!clipped_node.edges.contains_key(peer_position));
+                    assert!(clipped_node.all_boundaries.iter().any(|boundary| boundary.weight == edge.weight));
+                    lightest_boundary = Some(lightest_boundary.map(|weight: f64| weight.min(edge.weight)).unwrap_or(edge.weight));
+                }
+            }
+            assert_eq!(clipped_node.boundary.as_ref().map(|boundary| boundary.weight), lightest_boundary);
+        });
+        assert!(crossing_count > 0);
+    }
+
+    #[test]
+    fn model_graph_build_stabilizer_type() {  // cargo test model_graph_build_stabilizer_type -- --nocapture
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(1, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::GenericBiasedWithStandardCX.apply(&mut simulator, &mut noise_model, &json!({}), 0.001, 1., 0.);
+        let noise_model = Arc::new(noise_model);
+        let mut model_graph = ModelGraph::new(&simulator);
+        model_graph.build(&mut simulator, Arc::clone(&noise_model), &WeightFunction::AutotuneImproved, 1, true, false);
+        for stabilizer_type in [QubitType::StabX, QubitType::StabZ] {
+            let mut retained = model_graph.clone();
+            retained.retain_stabilizer_type(&simulator, stabilizer_type);
+            for parallel in [1, 2] {
+                let mut built = ModelGraph::new(&simulator);
+                built.build_stabilizer_type(&mut simulator, Arc::clone(&noise_model), &WeightFunction::AutotuneImproved, parallel, true, false, Some(stabilizer_type));
+                assert_eq!(built.weights(), retained.weights());
+                assert!(!built.weights().is_empty());
+                simulator_iter!(simulator, position, delta_t => simulator.measurement_cycles, if built.is_node_exist(position) {
+                    let boundary_weight = |model_graph: &ModelGraph| model_graph.get_node_unwrap(position).boundary.as_ref().map(|boundary| boundary.weight);
+                    assert_eq!(boundary_weight(&built), boundary_weight(&retained));
+                    assert_eq!(built.get_node_unwrap(position).all_edges.len(), retained.get_node_unwrap(position).all_edges.len());
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn model_graph_edges_flipped_by() {  // cargo test model_graph_edges_flipped_by -- --nocapture
+        let d = 5;
+        let noisy_measurements = 2;
+        let p = 0.01;
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p / 3., p / 3., p / 3., 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        let mut model_graph = ModelGraph::new(&simulator);
+        model_graph.build(&mut simulator, Arc::new(noise_model), &WeightFunction::AutotuneImproved, 1, true, false);
+        let t = simulator.measurement_cycles + 1;
+        let bulk_data = pos!(t, 5, 5);
+        assert_eq!(simulator.get_node_unwrap(&bulk_data).qubit_type, QubitType::Data);
+        // a single bulk data error flips exactly one edge, connecting the two stabilizers it toggles
+        let mut pattern = SparseErrorPattern::new();
+        pattern.add(bulk_data.clone(), ErrorType::X);
+        let flipped = model_graph.edges_flipped_by(&mut simulator, &pattern);
+        println!("{:?} flips {:?}", pattern, flipped);
+        assert_eq!(flipped.len(), 1);
+        let (_, sparse_measurement, _) = simulator.fast_measurement_given_few_errors(&pattern);
+        let defects = sparse_measurement.to_vec();
+        assert_eq!(flipped[0], EdgeId::Edge(defects[0].clone(), defects[1].clone()));
+        // a Y error flips one edge in each of the X and Z graphs
+        let mut pattern = SparseErrorPattern::new();
+        pattern.add(bulk_data.clone(), ErrorType::Y);
+        assert_eq!(model_graph.edges_flipped_by(&mut simulator, &pattern).len(), 2);
+        // the same error in the next round flips the corresponding edge one round later
+        let mut pattern = SparseErrorPattern::new();
+        pattern.add(bulk_data.clone(), ErrorType::X);
+        pattern.add(pos!(t + simulator.measurement_cycles, 5, 5), ErrorType::X);
+        let flipped = model_graph.edges_flipped_by(&mut simulator, &pattern);
+        assert_eq!(flipped.len(), 2);
+        // equivalent errors on the same edge cancel out
+        let mut pattern = SparseErrorPattern::new();
+        pattern.add(bulk_data.clone(), ErrorType::X);
+        pattern.add(pos!(t + 1, 5, 5), ErrorType::X);
+        assert_eq!(model_graph.edges_flipped_by(&mut simulator, &pattern), vec![]);
+        // a data error at the boundary flips a boundary edge
+        let mut pattern = SparseErrorPattern::new();
+        pattern.add(pos!(t, 1, 1), ErrorType::Y);
+        let flipped = model_graph.edges_flipped_by(&mut simulator, &pattern);
+        println!("{:?} flips {:?}", pattern, flipped);
+        assert_eq!(flipped.len(), 2);
+        assert!(flipped.iter().all(|edge_id| matches!(edge_id, EdgeId::Boundary(_))));
+    }
+
+
+    #[test]
+    fn model_graph_boundary_segments() {  // cargo test model_graph_boundary_segments -- --nocapture
+        for code_type in [CodeType::StandardPlanarCode, CodeType::RotatedPlanarCode] {
+            let simulator = Simulator::new(code_type, CodeSize::new(2, 5, 5));
+            let segments = ModelGraph::boundary_segments(&simulator);
+            let representatives: BTreeSet<(usize, usize)> = segments.values().cloned().collect();
+            println!("{:?}: {:?}", code_type, representatives);
+            assert_eq!(representatives.len(), 4, "the 4 sides of a planar code");
+            for (&(i, j), representative) in segments.iter() {
+                let qubit_type = simulator.get_node_unwrap(&pos!(simulator.measurement_cycles, i, j)).qubit_type;
+                let representative_qubit_type = simulator.get_node_unwrap(&pos!(simulator.measurement_cycles, representative.0, representative.1)).qubit_type;
+                assert_eq!(qubit_type, representative_qubit_type);
+            }
+        }
+        // the Z virtual stabilizers on the left side of a standard planar code form a single segment
+        let simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 5, 5));
+        let segments = ModelGraph::boundary_segments(&simulator);
+        assert!((1..10).step_by(2).all(|i| segments[&(i, 0)] == (1, 0)));
+        assert_ne!(segments[&(1, 0)], segments[&(1, 10)]);
+    }
 }
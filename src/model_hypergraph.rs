@@ -40,6 +40,10 @@ impl DefectVertices {
         defect_vertices.sort();
         Self(defect_vertices)
     }
+    /// the sorted positions of the defect vertices
+    pub fn positions(&self) -> &[Position] {
+        &self.0
+    }
 }
 
 impl Serialize for DefectVertices {
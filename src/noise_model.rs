@@ -11,7 +11,11 @@ use super::types::*;
 use serde::{Serialize, Deserialize};
 use super::code_builder::*;
 use std::sync::Arc;
+use std::collections::{BTreeMap, BTreeSet};
 use crate::visualize::*;
+use crate::artifact::ArtifactKind;
+use crate::reproducible_rand::Xoroshiro128StarStar;
+use crate::rand_core::{SeedableRng, RngCore};
 
 
 /// describing an noise model, strictly corresponding to an instance of `Simulator`
@@ -22,6 +26,53 @@ pub struct NoiseModel {
     pub nodes: Vec::< Vec::< Vec::< Option<Arc <NoiseModelNode> > > > >,
     /// additional noise that are unknown to the decoder, could be anything
     pub additional_noise: Vec<AdditionalNoise>,
+    /// scale the erasure error rates of each measurement round when sampling errors, e.g. to model increasing leakage over the circuit
+    #[serde(default)]
+    pub erasure_rate_multiplier: Option<ErasureRateMultiplier>,
+    /// measurement-conditioned Pauli corrections applied by the hardware, see [`Feedforward`]; like `additional_noise`, they are unknown to the decoder
+    #[serde(default)]
+    pub feedforwards: Vec<Feedforward>,
+    /// the data qubits `(i, j)` chosen as TLS defects, see [`TlsDefects`]; only recorded for reference since their error rates are already in `nodes`
+    #[serde(default)]
+    pub tls_defects: Vec<(usize, usize)>,
+}
+
+/// a "measure then feedforward" channel: when the measurement at `trigger` reports -1, i.e. the measured stabilizer is flipped by the propagated
+/// errors, the deterministic Pauli operators in `pauli_errors` are applied to the data qubits at `trigger.t + 1`;
+/// e.g. `{"trigger": "[6][1][2]", "pe": {"[7][2][2]": "X"}}` in the `feedforwards` field of `noise_model_configuration`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "python_binding", pyclass)]
+pub struct Feedforward {
+    pub trigger: Position,
+    #[serde(rename = "pe")]
+    pub pauli_errors: SparseErrorPattern,
+}
+
+/// multiplier of the erasure error rate as a function of the measurement round `r = t / measurement_cycles`,
+/// e.g. `{"linear": 0.5}` in the `erasure_rate_multiplier` field of `noise_model_configuration`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErasureRateMultiplier {
+    /// `1 + slope * r`
+    Linear(f64),
+    /// `base ^ r`
+    Exponential(f64),
+    /// the multiplier of each round, where the rounds beyond the list use the last one
+    Rounds(Vec<f64>),
+}
+
+impl ErasureRateMultiplier {
+    /// the multiplier of the erasure error rate at measurement round `round`
+    pub fn multiplier(&self, round: usize) -> f64 {
+        match self {
+            Self::Linear(slope) => 1. + slope * round as f64,
+            Self::Exponential(base) => base.powi(round as i32),
+            Self::Rounds(multipliers) => {
+                assert!(!multipliers.is_empty(), "at least one multiplier should be provided");
+                multipliers[round.min(multipliers.len() - 1)]
+            },
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +86,88 @@ pub struct AdditionalNoise {
     pub pauli_errors: SparseErrorPattern,
 }
 
+/// two-level-system (TLS) defects that make a few data qubits much noisier for the entire run,
+/// e.g. `{"count": 2, "severity": 10.0, "seed": 1}` in the `tls` field of `noise_model_configuration`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsDefects {
+    /// the number of defective data qubits
+    pub count: usize,
+    /// the Pauli error rates of the defective data qubits are multiplied by this factor in every round
+    pub severity: f64,
+    /// the defective data qubits are chosen deterministically from this seed
+    #[serde(default)]
+    pub seed: u64,
+    /// when false, the benchmark builds the decoding graph without the defects, i.e. the decoder is ignorant of them
+    #[serde(default = "tls_defects_default_configs::informed")]
+    pub informed: bool,
+}
+
+pub mod tls_defects_default_configs {
+    pub fn informed() -> bool { true }
+}
+
+impl TlsDefects {
+    /// choose `count` data qubits `(i, j)` uniformly at random using the seed, sorted
+    pub fn choose(&self, simulator: &Simulator) -> Result<Vec<(usize, usize)>, String> {
+        let mut data_qubits = vec![];
+        for i in 0..simulator.vertical {
+            for j in 0..simulator.horizontal {
+                let position = pos!(0, i, j);
+                if simulator.is_node_exist(&position) {
+                    let node = simulator.get_node_unwrap(&position);
+                    if node.qubit_type == QubitType::Data && !node.is_virtual {
+                        data_qubits.push((i, j));
+                    }
+                }
+            }
+        }
+        if self.count > data_qubits.len() {
+            return Err(format!("cannot choose {} TLS defects out of {} data qubits", self.count, data_qubits.len()))
+        }
+        // partial Fisher-Yates shuffle
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(self.seed);
+        for index in 0..self.count {
+            let chosen = index + (rng.next_u64() % (data_qubits.len() - index) as u64) as usize;
+            data_qubits.swap(index, chosen);
+        }
+        let mut chosen = data_qubits[..self.count].to_vec();
+        chosen.sort();
+        Ok(chosen)
+    }
+
+    /// multiply the Pauli error rates of the chosen data qubits in every round and record them in the noise model;
+    /// the noise model is left unchanged if this fails
+    pub fn apply(&self, simulator: &Simulator, noise_model: &mut NoiseModel) -> Result<(), String> {
+        if self.severity.is_nan() || self.severity < 0. {
+            return Err(format!("TLS defect severity must be non-negative, but got {}", self.severity))
+        }
+        let chosen = self.choose(simulator)?;
+        let mut nodes = vec![];
+        for &(i, j) in chosen.iter() {
+            for t in 0..simulator.height {
+                let position = &pos!(t, i, j);
+                if !simulator.is_node_exist(position) {
+                    continue
+                }
+                let mut node = noise_model.get_node_unwrap(position).clone();
+                node.pauli_error_rates.error_rate_X *= self.severity;
+                node.pauli_error_rates.error_rate_Y *= self.severity;
+                node.pauli_error_rates.error_rate_Z *= self.severity;
+                if node.pauli_error_rates.error_probability() > 1. {
+                    return Err(format!("TLS defect makes the Pauli error rate exceed 1 at {}", position))
+                }
+                nodes.push((position.clone(), node));
+            }
+        }
+        for (position, node) in nodes.into_iter() {
+            noise_model.set_node(&position, Some(Arc::new(node)));
+        }
+        noise_model.tls_defects = chosen;
+        Ok(())
+    }
+}
+
 impl QecpVisualizer for NoiseModel {
     fn component_info(&self, abbrev: bool) -> (String, serde_json::Value) {
         let name = "noise_model";
@@ -59,13 +192,14 @@ impl QecpVisualizer for NoiseModel {
                 }).collect::<Vec<Vec<Option<serde_json::Value>>>>()
             }).collect::<Vec<Vec<Vec<Option<serde_json::Value>>>>>(),
             "additional_noise": self.additional_noise,
+            "tls_defects": self.tls_defects,
         });
         (name.to_string(), info)
     }
 }
 
 /// noise model node corresponds to 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[cfg_attr(feature = "python_binding", pyclass)]
 pub struct NoiseModelNode {
     /// without losing generality, errors are applied after the gate
@@ -108,6 +242,15 @@ impl NoiseModelNode {
         }
         true
     }
+
+    /// check if two nodes have the same error rates, where a missing correlated error rate is the same as all zero
+    pub fn is_equivalent(&self, other: &Self) -> bool {
+        self.pauli_error_rates == other.pauli_error_rates && self.erasure_error_rate == other.erasure_error_rate
+            && self.correlated_pauli_error_rates.clone().unwrap_or(CorrelatedPauliErrorRates::default())
+                == other.correlated_pauli_error_rates.clone().unwrap_or(CorrelatedPauliErrorRates::default())
+            && self.correlated_erasure_error_rates.clone().unwrap_or(CorrelatedErasureErrorRates::default())
+                == other.correlated_erasure_error_rates.clone().unwrap_or(CorrelatedErasureErrorRates::default())
+    }
 }
 
 #[cfg_attr(feature = "python_binding", cfg_eval)]
@@ -130,6 +273,9 @@ impl NoiseModel {
                 }).collect()
             }).collect(),
             additional_noise: vec![],
+            erasure_rate_multiplier: None,
+            feedforwards: vec![],
+            tls_defects: vec![],
         }
     }
 }
@@ -168,6 +314,282 @@ impl NoiseModel {
     pub fn set_node(&mut self, position: &Position, node: Option<Arc<NoiseModelNode>>) {
         self.nodes[position.t][position.i][position.j] = node;
     }
+
+    /// load the noise model from the json generated by [`Simulator::to_json`], e.g. the output of `--debug-print noise-model`
+    pub fn from_json(value: &serde_json::Value) -> Result<Self, String> {
+        ArtifactKind::NoiseModel.check_envelope(value)?;
+        let nodes = value.get("nodes").ok_or("missing field: nodes".to_string())?.as_array().ok_or("format error: nodes".to_string())?;
+        let mut noise_model_nodes = Vec::with_capacity(nodes.len());
+        for (t, nodes_row_0) in nodes.iter().enumerate() {
+            let nodes_row_0 = nodes_row_0.as_array().ok_or(format!("format error: nodes[{}]", t))?;
+            let mut noise_model_row_0 = Vec::with_capacity(nodes_row_0.len());
+            for (i, nodes_row_1) in nodes_row_0.iter().enumerate() {
+                let nodes_row_1 = nodes_row_1.as_array().ok_or(format!("format error: nodes[{}][{}]", t, i))?;
+                let mut noise_model_row_1 = Vec::with_capacity(nodes_row_1.len());
+                for (j, node) in nodes_row_1.iter().enumerate() {
+                    if node.is_null() {
+                        noise_model_row_1.push(None);
+                        continue
+                    }
+                    let noise_model_node = node.get("noise_model").ok_or(format!("missing field: nodes[{}][{}][{}].noise_model", t, i, j))?.clone();
+                    let noise_model_node: NoiseModelNode = serde_json::from_value(noise_model_node)
+                        .map_err(|e| format!("nodes[{}][{}][{}].noise_model: {}", t, i, j, e))?;
+                    noise_model_row_1.push(Some(Arc::new(noise_model_node)));
+                }
+                noise_model_row_0.push(noise_model_row_1);
+            }
+            noise_model_nodes.push(noise_model_row_0);
+        }
+        let tls_defects = match value.get("tls_defects") {
+            Some(tls_defects) => serde_json::from_value(tls_defects.clone()).map_err(|e| format!("tls_defects: {}", e))?,
+            None => vec![],
+        };
+        Ok(Self {
+            nodes: noise_model_nodes,
+            additional_noise: vec![],
+            erasure_rate_multiplier: None,
+            feedforwards: vec![],
+            tls_defects,
+        })
+    }
+
+    /// the erasure error rate at `position` after applying [`NoiseModel::erasure_rate_multiplier`], capped at 1;
+    /// `measurement_cycles` is that of the simulator, which defines the measurement round of `position`
+    pub fn erasure_error_rate(&self, position: &Position, measurement_cycles: usize) -> f64 {
+        let erasure_error_rate = self.get_node_unwrap(position).erasure_error_rate;
+        match &self.erasure_rate_multiplier {
+            Some(erasure_rate_multiplier) if erasure_error_rate > 0. => {
+                let multiplier = erasure_rate_multiplier.multiplier(position.t / measurement_cycles);
+                assert!(multiplier >= 0., "erasure rate multiplier must be non-negative, found {} at {}", multiplier, position);
+                (erasure_error_rate * multiplier).min(1.)
+            },
+            _ => erasure_error_rate,
+        }
+    }
+
+    /// compare with another noise model of the same shape, returning all positions with different error rates;
+    /// nodes shared by `Arc` are compared by value, so two equivalent noise models always give an empty list
+    #[allow(clippy::type_complexity)]
+    pub fn diff(&self, other: &Self) -> Result<Vec<(Position, Option<Arc<NoiseModelNode>>, Option<Arc<NoiseModelNode>>)>, String> {
+        if self.nodes.len() != other.nodes.len() {
+            return Err(format!("mismatch: height {} != {}", self.nodes.len(), other.nodes.len()))
+        }
+        let mut differences = Vec::new();
+        for t in 0..self.nodes.len() {
+            if self.nodes[t].len() != other.nodes[t].len() {
+                return Err(format!("mismatch: nodes[{}].len()", t))
+            }
+            for i in 0..self.nodes[t].len() {
+                if self.nodes[t][i].len() != other.nodes[t][i].len() {
+                    return Err(format!("mismatch: nodes[{}][{}].len()", t, i))
+                }
+                for j in 0..self.nodes[t][i].len() {
+                    let node = &self.nodes[t][i][j];
+                    let other_node = &other.nodes[t][i][j];
+                    let is_equivalent = match (node, other_node) {
+                        (Some(node), Some(other_node)) => Arc::ptr_eq(node, other_node) || node.is_equivalent(other_node),
+                        (None, None) => true,
+                        _ => false,
+                    };
+                    if !is_equivalent {
+                        differences.push((pos!(t, i, j), node.clone(), other_node.clone()));
+                    }
+                }
+            }
+        }
+        Ok(differences)
+    }
+
+    /// compose two noise models of the same shape by time: the layers `t` in `rounds` use the nodes of `override_model` and all the others
+    /// use the nodes of `base`, e.g. to simulate a calibration glitch lasting a few rounds; the nodes are shared by `Arc` rather than copied.
+    /// the other fields (additional noise, feedforwards, etc.) come from `base`
+    pub fn with_time_override(base: &NoiseModel, rounds: std::ops::Range<usize>, override_model: &NoiseModel) -> NoiseModel {
+        assert!(base.diff(override_model).is_ok(), "cannot compose noise models of different shapes");
+        let mut noise_model = base.clone();
+        for t in rounds.start..rounds.end.min(noise_model.nodes.len()) {
+            noise_model.nodes[t] = override_model.nodes[t].clone();
+        }
+        noise_model
+    }
+
+    /// check if a single Pauli error at `position` has nonzero probability; correlated errors are only weakly checked by their existence
+    pub fn is_single_error_possible(&self, simulator: &Simulator, position: &Position, error: &ErrorType) -> bool {
+        if error == &ErrorType::I {
+            return true
+        }
+        let noise_model_node = self.get_node_unwrap(position);
+        let mut possible = match error {
+            ErrorType::X => noise_model_node.pauli_error_rates.error_rate_X > 0.,
+            ErrorType::Y => noise_model_node.pauli_error_rates.error_rate_Y > 0.,
+            ErrorType::Z => noise_model_node.pauli_error_rates.error_rate_Z > 0.,
+            _ => unreachable!(),
+        };
+        possible |= noise_model_node.erasure_error_rate > 0.;
+        possible |= noise_model_node.correlated_pauli_error_rates.is_some();  // weak check
+        possible |= noise_model_node.correlated_erasure_error_rates.is_some();  // weak check
+        if !possible {  // check peer only if still not possible
+            if let Some(peer_position) = simulator.get_node_unwrap(position).gate_peer.as_ref() {
+                let peer_noise_model_node = self.get_node_unwrap(peer_position);
+                possible |= peer_noise_model_node.correlated_pauli_error_rates.is_some();  // weak check
+                possible |= peer_noise_model_node.correlated_erasure_error_rates.is_some();  // weak check
+            }
+        }
+        if !possible {  // additional noise can generate arbitrary errors
+            possible |= self.additional_noise.iter().any(|noise| noise.probability > 0. && noise.pauli_errors.get(position).is_some());
+            possible |= self.additional_noise.iter().any(|noise| noise.probability > 0. && noise.erasures.erasures.contains(position));
+        }
+        possible
+    }
+
+    /// check if every error in the pattern has nonzero probability, returning the first impossible error location otherwise;
+    /// unlike [`Simulator::load_sparse_error_pattern`], this check is also done in release mode, useful to validate externally-sourced error patterns
+    pub fn is_error_possible(&self, simulator: &Simulator, pattern: &SparseErrorPattern) -> Result<(), String> {
+        for (position, error) in pattern.iter() {
+            if !simulator.is_node_exist(position) || !self.is_node_exist(position) {
+                return Err(format!("invalid error at position {}", position))
+            }
+            if !self.is_single_error_possible(simulator, position, error) {
+                return Err(format!("error {} at position {} has 0 probability", error, position))
+            }
+        }
+        Ok(())
+    }
+
+    /// the analytic probability of each real detector to flip in a shot, i.e. to be reported by [`SimulatorGenerics::generate_sparse_measurement`];
+    /// every independent error source flips a detector with probability `q`, and the detector flips if an odd number of them fire, which
+    /// happens with probability `(1 - Π(1 - 2q)) / 2`; an erasure leaves a uniformly random Pauli error, flipping each affected detector with probability 1/2
+    pub fn detector_flip_probabilities(&self, simulator: &Simulator) -> BTreeMap<Position, f64> {
+        let mut simulator = simulator.clone();
+        simulator.clear_all_errors();
+        let mut single_flips = BTreeMap::new();
+        // `Π(1 - 2q)` of each detector
+        let mut no_flip_products: BTreeMap<Position, f64> = BTreeMap::new();
+        for t in (simulator.measurement_cycles..simulator.height).step_by(simulator.measurement_cycles) {
+            simulator_iter_real!(simulator, position, node, t => t, {
+                if node.gate_type.is_measurement() {
+                    no_flip_products.insert(position.clone(), 1.);
+                }
+            });
+        }
+        let add_source = |no_flip_products: &mut BTreeMap<Position, f64>, detectors: &BTreeSet<Position>, q: f64| {
+            if q > 0. {
+                for detector in detectors.iter() {
+                    *no_flip_products.get_mut(detector).expect("flipped detector must be a real measurement") *= 1. - 2. * q;
+                }
+            }
+        };
+        let mut positions = Vec::new();
+        simulator_iter!(simulator, position, {
+            positions.push(position.clone());
+        });
+        for position in positions.iter() {
+            let noise_model_node = self.get_node_unwrap(position);
+            // single-qubit Pauli errors are mutually exclusive, so they form a single source per detector
+            let mut pauli_flip_probabilities: BTreeMap<Position, f64> = BTreeMap::new();
+            for error in ErrorType::all_possible_errors().iter() {
+                let p = noise_model_node.pauli_error_rates.error_rate(error);
+                if p > 0. {
+                    let mut pattern = SparseErrorPattern::new();
+                    pattern.add(position.clone(), *error);
+                    for detector in pattern_flipped_detectors(&mut simulator, &mut single_flips, &pattern).into_iter() {
+                        *pauli_flip_probabilities.entry(detector).or_insert(0.) += p;
+                    }
+                }
+            }
+            for (detector, q) in pauli_flip_probabilities.into_iter() {
+                add_source(&mut no_flip_products, &BTreeSet::from([detector]), q);
+            }
+            add_source(&mut no_flip_products, &erasure_affected_detectors(&mut simulator, &mut single_flips, std::slice::from_ref(position))
+                , self.erasure_error_rate(position, simulator.measurement_cycles) / 2.);
+            if let Some(correlated_pauli_error_rates) = &noise_model_node.correlated_pauli_error_rates {
+                let gate_peer = simulator.get_node_unwrap(position).gate_peer.as_ref()
+                    .expect("correlated pauli error must corresponds to a two-qubit gate").as_ref().clone();
+                let mut correlated_flip_probabilities: BTreeMap<Position, f64> = BTreeMap::new();
+                for error_type in CorrelatedPauliErrorType::all_possible_errors().iter() {
+                    let p = correlated_pauli_error_rates.error_rate(error_type);
+                    if p > 0. {
+                        let mut pattern = SparseErrorPattern::new();
+                        pattern.add(position.clone(), error_type.my_error());
+                        pattern.add(gate_peer.clone(), error_type.peer_error());
+                        for detector in pattern_flipped_detectors(&mut simulator, &mut single_flips, &pattern).into_iter() {
+                            *correlated_flip_probabilities.entry(detector).or_insert(0.) += p;
+                        }
+                    }
+                }
+                for (detector, q) in correlated_flip_probabilities.into_iter() {
+                    add_source(&mut no_flip_products, &BTreeSet::from([detector]), q);
+                }
+            }
+            if let Some(correlated_erasure_error_rates) = &noise_model_node.correlated_erasure_error_rates {
+                let gate_peer = simulator.get_node_unwrap(position).gate_peer.as_ref()
+                    .expect("correlated erasure error must corresponds to a two-qubit gate").as_ref().clone();
+                let my_detectors = erasure_affected_detectors(&mut simulator, &mut single_flips, std::slice::from_ref(position));
+                let peer_detectors = erasure_affected_detectors(&mut simulator, &mut single_flips, std::slice::from_ref(&gate_peer));
+                let all_detectors: BTreeSet<Position> = my_detectors.union(&peer_detectors).cloned().collect();
+                for detector in all_detectors.iter() {
+                    let mut q = 0.;
+                    if my_detectors.contains(detector) { q += correlated_erasure_error_rates.error_rate_EI; }
+                    if peer_detectors.contains(detector) { q += correlated_erasure_error_rates.error_rate_IE; }
+                    q += correlated_erasure_error_rates.error_rate_EE;
+                    add_source(&mut no_flip_products, &BTreeSet::from([detector.clone()]), q / 2.);
+                }
+            }
+        }
+        for additional_noise in self.additional_noise.iter() {
+            let erasures: Vec<Position> = additional_noise.erasures.iter().cloned().collect();
+            let erased_detectors = erasure_affected_detectors(&mut simulator, &mut single_flips, &erasures);
+            add_source(&mut no_flip_products, &erased_detectors, additional_noise.probability / 2.);
+            let pauli_detectors = pattern_flipped_detectors(&mut simulator, &mut single_flips, &additional_noise.pauli_errors);
+            add_source(&mut no_flip_products, &pauli_detectors.difference(&erased_detectors).cloned().collect(), additional_noise.probability);
+        }
+        no_flip_products.into_iter().map(|(detector, no_flip_product)| (detector, (1. - no_flip_product) / 2.)).collect()
+    }
+
+    /// the analytic expectation of the number of defects per shot, which predicts the syndrome density before sampling
+    pub fn expected_defects(&self, simulator: &Simulator) -> f64 {
+        self.detector_flip_probabilities(simulator).values().sum()
+    }
+}
+
+/// the detectors flipped by a single X or Z error at `position`, cached because the errors are propagated through the circuit
+fn single_flipped_detectors<'a>(simulator: &mut Simulator, single_flips: &'a mut BTreeMap<(Position, bool), BTreeSet<Position>>
+        , position: &Position, is_x: bool) -> &'a BTreeSet<Position> {
+    single_flips.entry((position.clone(), is_x)).or_insert_with(|| {
+        let mut pattern = SparseErrorPattern::new();
+        pattern.add(position.clone(), if is_x { ErrorType::X } else { ErrorType::Z });
+        let (_correction, sparse_measurement_real, _sparse_measurement_virtual) = simulator.fast_measurement_given_few_errors(&pattern);
+        sparse_measurement_real.iter().cloned().collect()
+    })
+}
+
+/// the detectors flipped by an error pattern, using the linearity of error propagation: `Y = XZ` flips the symmetric difference
+fn pattern_flipped_detectors(simulator: &mut Simulator, single_flips: &mut BTreeMap<(Position, bool), BTreeSet<Position>>
+        , pattern: &SparseErrorPattern) -> BTreeSet<Position> {
+    let mut detectors = BTreeSet::new();
+    for (position, error) in pattern.iter() {
+        for (is_x, has_component) in [(true, matches!(error, ErrorType::X | ErrorType::Y)), (false, matches!(error, ErrorType::Z | ErrorType::Y))] {
+            if has_component {
+                for detector in single_flipped_detectors(simulator, single_flips, position, is_x).iter() {
+                    if !detectors.remove(detector) {
+                        detectors.insert(detector.clone());
+                    }
+                }
+            }
+        }
+    }
+    detectors
+}
+
+/// the detectors that flip with probability 1/2 when the given positions are erased, i.e. those flipped by any X or Z error on them
+fn erasure_affected_detectors(simulator: &mut Simulator, single_flips: &mut BTreeMap<(Position, bool), BTreeSet<Position>>
+        , positions: &[Position]) -> BTreeSet<Position> {
+    let mut detectors = BTreeSet::new();
+    for position in positions.iter() {
+        for is_x in [true, false] {
+            detectors.extend(single_flipped_detectors(simulator, single_flips, position, is_x).iter().cloned());
+        }
+    }
+    detectors
 }
 
 /// check if error rates are not zero at perfect measurement ranges or at (always) virtual nodes,
@@ -175,12 +597,13 @@ impl NoiseModel {
 pub fn noise_model_sanity_check(simulator: &Simulator, noise_model: &NoiseModel) -> Result<(), String> {
     match simulator.code_size {
         CodeSize { noisy_measurements, .. } => {
-            // check that no errors present in the final perfect measurement rounds
-            let expected_height = simulator.measurement_cycles * (noisy_measurements + 1) + 1;
+            // check that no errors present in the final perfect measurement rounds, or only at the top layer if the final round is noisy
+            let expected_height = simulator.measurement_cycles * (noisy_measurements + simulator.code_size.final_perfect_rounds) + 1;
             if simulator.height != expected_height {
                 return Err(format!("height {} is not expected {}, don't know where is perfect measurement", simulator.height, expected_height))
             }
-            for t in simulator.height - simulator.measurement_cycles .. simulator.height {
+            let noiseless_t = if simulator.noisy_final_round { simulator.height - 1 } else { simulator.perfect_measurement_start() };
+            for t in noiseless_t .. simulator.height {
                 simulator_iter!(simulator, position, _node, t => t, {
                     let noise_model_node = noise_model.get_node_unwrap(position);
                     if !noise_model_node.is_noiseless() {
@@ -230,14 +653,227 @@ pub fn noise_model_sanity_check(simulator: &Simulator, noise_model: &NoiseModel)
             }
         }
     });
+    for feedforward in noise_model.feedforwards.iter() {
+        let trigger = &feedforward.trigger;
+        if !simulator.is_node_exist(trigger) || !simulator.get_node_unwrap(trigger).gate_type.is_measurement() {
+            return Err(format!("feedforward trigger {} is not a measurement", trigger))
+        }
+        for (position, _error) in feedforward.pauli_errors.iter() {
+            if position.t != trigger.t + 1 || !simulator.is_node_exist(position) || simulator.get_node_unwrap(position).qubit_type != QubitType::Data {
+                return Err(format!("feedforward of trigger {} should be applied on data qubits at t = {}, but found {}", trigger, trigger.t + 1, position))
+            }
+        }
+    }
     Ok(())
 }
 
+/// the non-zero error rates of every position, keyed by the flattened serialized fields of [`NoiseModelNode`] (e.g. `pp.pz` or `corr_pp.pXZ`);
+/// a canonical form to compare noise models position by position, see [`rate_table_diff`]
+pub type NoiseRateTable = BTreeMap<Position, BTreeMap<String, f64>>;
+
+/// the significant digits kept in [`NoiseRateTable`], so that the rounding errors of the floating point arithmetic don't show up in the table
+pub const RATE_TABLE_SIGNIFICANT_DIGITS: usize = 12;
+
+impl NoiseModel {
+    /// the canonical per-position table of the error rates, with noiseless positions omitted
+    pub fn rate_table(&self) -> NoiseRateTable {
+        fn flatten(prefix: &str, value: &serde_json::Value, rates: &mut BTreeMap<String, f64>) {
+            match value {
+                serde_json::Value::Number(number) => {
+                    let rate = number.as_f64().unwrap();
+                    if rate != 0. {
+                        let rounded = format!("{:.*e}", RATE_TABLE_SIGNIFICANT_DIGITS - 1, rate).parse().unwrap();
+                        rates.insert(prefix.to_string(), rounded);
+                    }
+                },
+                serde_json::Value::Object(object) => {
+                    for (key, value) in object.iter() {
+                        flatten(&if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) }, value, rates);
+                    }
+                },
+                _ => { },  // absent correlated error rates
+            }
+        }
+        let mut table = NoiseRateTable::new();
+        for t in 0..self.nodes.len() {
+            for i in 0..self.nodes[t].len() {
+                for j in 0..self.nodes[t][i].len() {
+                    let position = pos!(t, i, j);
+                    if self.is_node_exist(&position) {
+                        let mut rates = BTreeMap::new();
+                        flatten("", &serde_json::to_value(self.get_node_unwrap(&position)).unwrap(), &mut rates);
+                        if !rates.is_empty() {
+                            table.insert(position, rates);
+                        }
+                    }
+                }
+            }
+        }
+        table
+    }
+}
+
+/// print the table as json with one position per line, so that a changed rate shows up as a single line in the diff of a checked-in file
+pub fn rate_table_to_string(table: &NoiseRateTable) -> String {
+    let lines: Vec<String> = table.iter().map(|(position, rates)| format!("{}: {}", json!(position), json!(rates))).collect();
+    format!("{{\n{}\n}}\n", lines.join(",\n"))
+}
+
+/// the differences between two tables, each naming the position and the field, where two rates are considered equal if their relative difference
+/// is within `relative_tolerance`; empty if the tables agree
+pub fn rate_table_diff(expected: &NoiseRateTable, actual: &NoiseRateTable, relative_tolerance: f64) -> Vec<String> {
+    let empty = BTreeMap::new();
+    let positions: BTreeSet<&Position> = expected.keys().chain(actual.keys()).collect();
+    let mut differences = Vec::new();
+    for position in positions {
+        let expected_rates = expected.get(position).unwrap_or(&empty);
+        let actual_rates = actual.get(position).unwrap_or(&empty);
+        let fields: BTreeSet<&String> = expected_rates.keys().chain(actual_rates.keys()).collect();
+        for field in fields {
+            let expected_rate = expected_rates.get(field).cloned().unwrap_or(0.);
+            let actual_rate = actual_rates.get(field).cloned().unwrap_or(0.);
+            if (expected_rate - actual_rate).abs() > relative_tolerance * expected_rate.abs().max(actual_rate.abs()) {
+                differences.push(format!("{} {}: expected {:e}, actual {:e}", position, field, expected_rate, actual_rate));
+            }
+        }
+    }
+    differences
+}
+
 #[cfg(feature="python_binding")]
 #[pyfunction]
 pub(crate) fn register(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<NoiseModel>()?;
     m.add_class::<NoiseModelNode>()?;
     m.add_class::<AdditionalNoise>()?;
+    m.add_class::<Feedforward>()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::noise_model_builder::*;
+
+    #[test]
+    fn noise_model_rate_table() {  // cargo test noise_model_rate_table -- --nocapture
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), 0.01, 10., 0.005);
+        let table = noise_model.rate_table();
+        let data_position = pos!(0, 1, 1);
+        assert_eq!(table[&data_position]["pe"], 0.005);
+        assert!(table[&data_position].contains_key("pp.pz") && !table[&data_position].contains_key("corr_pp.pXZ"));
+        let parsed: NoiseRateTable = serde_json::from_str(&rate_table_to_string(&table)).unwrap();
+        assert_eq!(parsed, table);
+        assert!(rate_table_diff(&table, &parsed, 1e-9).is_empty());
+        // a tweaked rate is reported with its position and field
+        let mut node = noise_model.get_node_unwrap(&data_position).clone();
+        node.pauli_error_rates.error_rate_Z *= 1.01;
+        noise_model.set_node(&data_position, Some(Arc::new(node)));
+        let differences = rate_table_diff(&table, &noise_model.rate_table(), 1e-9);
+        assert_eq!(differences.len(), 1);
+        assert!(differences[0].starts_with("[0][1][1] pp.pz: expected"), "{}", differences[0]);
+        // only the differences beyond the tolerance count
+        assert!(rate_table_diff(&table, &noise_model.rate_table(), 0.1).is_empty());
+        noise_model.set_node(&data_position, None);
+        assert_eq!(rate_table_diff(&table, &noise_model.rate_table(), 1e-9).len(), table[&data_position].len());
+    }
+
+    #[test]
+    fn noise_model_is_error_possible() {  // cargo test noise_model_is_error_possible -- --nocapture
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), 0.003, 1., 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        // phenomenological noise model with bias_eta = 1 only has Z errors on data qubits
+        let data_position = simulator.height - 1 - simulator.measurement_cycles;
+        let mut pattern = SparseErrorPattern::new();
+        simulator_iter_real!(simulator, position, node, t => data_position, if node.qubit_type == QubitType::Data {
+            pattern.add(position.clone(), ErrorType::Z);
+        });
+        assert_eq!(noise_model.is_error_possible(&simulator, &pattern), Ok(()));
+        let mut impossible_position = None;
+        simulator_iter!(simulator, position, node, if node.qubit_type == QubitType::Data && noise_model.get_node_unwrap(position).is_noiseless() {
+            impossible_position = Some(position.clone());
+        });
+        let impossible_position = impossible_position.expect("noiseless data qubit must exist at the bottom layer");
+        pattern.add(impossible_position.clone(), ErrorType::Z);
+        let message = noise_model.is_error_possible(&simulator, &pattern).unwrap_err();
+        assert!(message.contains(&impossible_position.to_string()), "{}", message);
+        // invalid position is also reported
+        let mut pattern = SparseErrorPattern::new();
+        pattern.add(pos!(0, 100, 100), ErrorType::X);
+        assert!(noise_model.is_error_possible(&simulator, &pattern).is_err());
+    }
+
+    #[test]
+    fn noise_model_expected_defects() {  // cargo test noise_model_expected_defects -- --nocapture
+        use crate::rand_core::SeedableRng;
+        use crate::reproducible_rand::Xoroshiro128StarStar;
+        let shots = 5000;
+        for (noise_model_builder, p, pe) in [(NoiseModelBuilder::Phenomenological, 0.02, 0.), (NoiseModelBuilder::ErasureOnlyPhenomenological, 0., 0.03)
+                , (NoiseModelBuilder::StimNoiseModel, 0.005, 0.), (NoiseModelBuilder::DepolarizingNoise, 0.005, 0.)] {
+            let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(3, 3, 3));
+            let mut noise_model = NoiseModel::new(&simulator);
+            noise_model_builder.apply(&mut simulator, &mut noise_model, &json!({}), p, 0.5, pe);
+            simulator.compress_error_rates(&mut noise_model);
+            let expected_defects = noise_model.expected_defects(&simulator);
+            assert!(expected_defects > 0.);
+            simulator.rng = Xoroshiro128StarStar::seed_from_u64(1716);
+            let (mut sum, mut square_sum) = (0., 0.);
+            for _ in 0..shots {
+                simulator.generate_random_errors(&noise_model);
+                let defects = simulator.generate_sparse_measurement().len() as f64;
+                sum += defects;
+                square_sum += defects * defects;
+            }
+            let mean = sum / shots as f64;
+            let standard_error = ((square_sum / shots as f64 - mean * mean) / shots as f64).sqrt();
+            println!("{:?}: analytic {} Monte Carlo {} ± {}", noise_model_builder, expected_defects, mean, standard_error);
+            assert!((expected_defects - mean).abs() < 5. * standard_error, "analytic expectation deviates from the Monte Carlo mean");
+        }
+    }
+
+    #[test]
+    fn noise_model_with_time_override() {  // cargo test noise_model_with_time_override -- --nocapture
+        use crate::rand_core::SeedableRng;
+        use crate::reproducible_rand::Xoroshiro128StarStar;
+        let noisy_measurements = 9;
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(noisy_measurements, 5, 5));
+        let build = |simulator: &mut Simulator, p: f64| -> NoiseModel {
+            let mut noise_model = NoiseModel::new(simulator);
+            NoiseModelBuilder::Phenomenological.apply(simulator, &mut noise_model, &json!({}), p, 0.5, 0.);
+            simulator.compress_error_rates(&mut noise_model);
+            noise_model
+        };
+        let base = build(&mut simulator, 0.002);
+        let glitch = build(&mut simulator, 0.05);
+        let measurement_cycles = simulator.measurement_cycles;
+        let (start, end) = (4, 6);
+        let layers = start * measurement_cycles .. end * measurement_cycles;
+        let noise_model = NoiseModel::with_time_override(&base, layers.clone(), &glitch);
+        // the nodes are shared rather than copied
+        simulator_iter!(simulator, position, {
+            let expected = if layers.contains(&position.t) { &glitch } else { &base };
+            assert!(Arc::ptr_eq(noise_model.get_node(position).as_ref().unwrap(), expected.get_node(position).as_ref().unwrap()), "{}", position);
+        });
+        // the defects cluster in the rounds right after the glitch
+        simulator.rng = Xoroshiro128StarStar::seed_from_u64(1734);
+        let mut defects_of_round = vec![0usize; noisy_measurements + 2];
+        for _ in 0..200 {
+            simulator.generate_random_errors(&noise_model);
+            for position in simulator.generate_sparse_measurement().to_vec() {
+                defects_of_round[position.t / measurement_cycles] += 1;
+            }
+        }
+        println!("defects of each round: {:?}", defects_of_round);
+        // a measurement error in the last round of the window also flips the detector of the next round, which is therefore excluded
+        let inside: Vec<usize> = (start + 1..=end).collect();
+        let outside: Vec<usize> = (1..defects_of_round.len()).filter(|round| *round <= start || *round > end + 1).collect();
+        let min_inside = inside.iter().map(|&round| defects_of_round[round]).min().unwrap();
+        let max_outside = outside.iter().map(|&round| defects_of_round[round]).max().unwrap();
+        assert!(min_inside > 5 * max_outside, "defects inside the window {} vs outside {}", min_inside, max_outside);
+    }
+
+}
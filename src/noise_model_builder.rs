@@ -10,6 +10,7 @@ use super::clap::ValueEnum;
 use super::code_builder::*;
 use std::sync::Arc;
 use std::collections::BTreeSet;
+use crate::artifact::ArtifactKind;
 #[cfg(feature="python_binding")]
 use pyo3::prelude::*;
 
@@ -33,8 +34,16 @@ pub enum NoiseModelBuilder {
     OnlyGateErrorCircuitLevel,
     /// mixed erasure error and Pauli errors only on the data qubits before the gates happen and on the ancilla qubits before the measurement
     MixedPhenomenological,
-    /// Fault-tolerant weighted union-find decoding on the toric code
+    /// Fault-tolerant weighted union-find decoding on the toric code; the idle error rate is `p` unless it's computed from the elapsed time
+    /// by `round_duration` and `idle_rate_per_time`, see [`idle_depolarizing_error_rate`]
     DepolarizingNoise,
+    /// code capacity noise model: data qubit errors only at a single layer `t` and no measurement errors; by default `t` is just below
+    /// the first noisy measurement, see [`code_capacity_default_layer`], and it must be valid by [`code_capacity_validate_layer`]
+    CodeCapacity,
+    /// Pauli twirl of a coherent over-rotation `exp(-i angle/2 axis.σ)` after each two-qubit gate on both qubits, i.e. the gate fails into
+    /// Pauli `σ_k` with probability `sin^2(angle/2) axis_k^2`; configured by `angle` (in radian, by default `2 asin(sqrt(p))` so that the
+    /// total error rate is `p`) and `axis` (by default `[0, 0, 1]`, normalized automatically)
+    PauliTwirledRotation,
     /// the noise model in stim: after_clifford_depolarization, before_round_data_depolarization, before_measure_flip_probability, after_reset_flip_probability;
     /// see https://github.com/quantumlib/Stim/blob/main/doc/python_api_reference_vDev.md#stim.Circuit.generated
     StimNoiseModel,
@@ -54,6 +63,22 @@ impl NoiseModelBuilder {
 
     /// apply noise model
     pub fn apply(&self, simulator: &mut Simulator, noise_model: &mut NoiseModel, noise_model_configuration: &serde_json::Value, p: f64, bias_eta: f64, pe: f64) {
+        // the erasure rate multiplier applies to all noise model builders, see [`ErasureRateMultiplier`]
+        let mut noise_model_configuration = noise_model_configuration.clone();
+        if let Some(value) = noise_model_configuration.as_object_mut().and_then(|config| config.remove("erasure_rate_multiplier")) {
+            noise_model.erasure_rate_multiplier = Some(serde_json::from_value(value).expect("erasure_rate_multiplier format error"));
+        }
+        // the feedforwards are independent of the noise model builder as well, see [`Feedforward`]
+        if let Some(value) = noise_model_configuration.as_object_mut().and_then(|config| config.remove("feedforwards")) {
+            noise_model.feedforwards = serde_json::from_value(value).expect("feedforwards format error");
+        }
+        // the TLS defects are applied on top of the noise model builder, see [`TlsDefects`]
+        let tls_defects: Option<TlsDefects> = noise_model_configuration.as_object_mut().and_then(|config| config.remove("tls"))
+            .map(|value| serde_json::from_value(value).expect("tls format error"));
+        // the final measurement round repeats the noise of the round below it instead of being perfect, see [`Simulator::noisy_final_round`]
+        let noisy_final_round = noise_model_configuration.as_object_mut().and_then(|config| config.remove("noisy_final_round"))
+            .map(|value| value.as_bool().expect("noisy_final_round must be `bool`")).unwrap_or(false);
+        let noise_model_configuration = &noise_model_configuration;
         // commonly used biased qubit error node
         let px = p / (1. + bias_eta) / 2.;
         let py = px;
@@ -64,6 +89,8 @@ impl NoiseModelBuilder {
         biased_node.pauli_error_rates.error_rate_Z = pz;
         biased_node.erasure_error_rate = pe;
         let biased_node = Arc::new(biased_node);
+        // the rate of the non-dominant errors; a zero rate stays exactly zero even if `bias_eta` is 0
+        let divide_by_bias = |rate: f64| -> f64 { if rate == 0. { 0. } else { rate / bias_eta } };
         // commonly used pure measurement error node
         let mut pm = p;
         if let Some(value) = noise_model_configuration.get("measurement_error_rate") {
@@ -79,13 +106,13 @@ impl NoiseModelBuilder {
             Self::Phenomenological => {
                 let simulator = &*simulator;  // force simulator to be immutable, to avoid unexpected changes
                 assert!(px + py + pz <= 1. && px >= 0. && py >= 0. && pz >= 0.);
-                assert!(pe == 0.);  // phenomenological noise model doesn't support erasure errors
+                assert!((0. ..=1.).contains(&pe));  // erasure errors only happen on data qubits, together with the Pauli errors
                 if simulator.measurement_cycles == 1 {
                     eprintln!("[warning] setting error rates of unknown code, no perfect measurement protection is enabled");
                 }
                 simulator_iter_real!(simulator, position, node, {
                     noise_model.set_node(position, Some(noiseless_node.clone()));  // clear existing noise model
-                    if position.t >= simulator.height - simulator.measurement_cycles {  // no error at the final perfect measurement round
+                    if position.t >= simulator.perfect_measurement_start() {  // no error at the final perfect measurement round
                         continue
                     }
                     if position.t % simulator.measurement_cycles == 0 && node.qubit_type == QubitType::Data {
@@ -115,7 +142,7 @@ impl NoiseModelBuilder {
                 });
                 let simulator = &*simulator;  // force simulator to be immutable, to avoid unexpected changes
                 assert!(px + py + pz <= 1. && px >= 0. && py >= 0. && pz >= 0.);
-                assert!(pe == 0.);  // phenomenological noise model doesn't support erasure errors
+                assert!((0. ..=1.).contains(&pe));  // erasure errors only happen on data qubits, together with the Pauli errors
                 if simulator.measurement_cycles == 1 {
                     eprintln!("[warning] setting error rates of unknown code, no perfect measurement protection is enabled");
                 }
@@ -133,7 +160,7 @@ impl NoiseModelBuilder {
                             }
                         }
                     } else if position.t >= simulator.measurement_cycles {  // no error before the first round
-                        if position.t < simulator.height - simulator.measurement_cycles {  // no error at the final perfect measurement round
+                        if position.t < simulator.perfect_measurement_start() {  // no error at the final perfect measurement round
                             if position.t % simulator.measurement_cycles == 0 && node.qubit_type == QubitType::Data {
                                 noise_model.set_node(position, Some(biased_node.clone()));
                             }
@@ -155,25 +182,25 @@ impl NoiseModelBuilder {
                 if !config.is_empty() { panic!("unknown keys: {:?}", config.keys().collect::<Vec<&String>>()); }
                 // normal biased node
                 let mut normal_biased_node = NoiseModelNode::new();
-                normal_biased_node.pauli_error_rates.error_rate_X = initialization_error_rate / bias_eta;
+                normal_biased_node.pauli_error_rates.error_rate_X = divide_by_bias(initialization_error_rate);
                 normal_biased_node.pauli_error_rates.error_rate_Z = initialization_error_rate;
-                normal_biased_node.pauli_error_rates.error_rate_Y = initialization_error_rate / bias_eta;
+                normal_biased_node.pauli_error_rates.error_rate_Y = divide_by_bias(initialization_error_rate);
                 let normal_biased_node = Arc::new(normal_biased_node);
                 // CZ gate node
                 let mut cphase_node = NoiseModelNode::new();
-                cphase_node.correlated_pauli_error_rates = Some(CorrelatedPauliErrorRates::default_with_probability(p / bias_eta));
+                cphase_node.correlated_pauli_error_rates = Some(CorrelatedPauliErrorRates::default_with_probability(divide_by_bias(p)));
                 cphase_node.correlated_pauli_error_rates.as_mut().unwrap().error_rate_ZI = p;
                 cphase_node.correlated_pauli_error_rates.as_mut().unwrap().error_rate_IZ = p;
                 let cphase_node = Arc::new(cphase_node);
                 // CZ gate with measurement error
                 let mut cphase_measurement_error_node: NoiseModelNode = (*cphase_node).clone();
-                cphase_measurement_error_node.pauli_error_rates.error_rate_X = initialization_error_rate / bias_eta;
+                cphase_measurement_error_node.pauli_error_rates.error_rate_X = divide_by_bias(initialization_error_rate);
                 cphase_measurement_error_node.pauli_error_rates.error_rate_Z = initialization_error_rate;
-                cphase_measurement_error_node.pauli_error_rates.error_rate_Y = initialization_error_rate / bias_eta;
+                cphase_measurement_error_node.pauli_error_rates.error_rate_Y = divide_by_bias(initialization_error_rate);
                 let cphase_measurement_error_node = Arc::new(cphase_measurement_error_node);
                 // CX gate node
                 let mut cx_node = NoiseModelNode::new();
-                cx_node.correlated_pauli_error_rates = Some(CorrelatedPauliErrorRates::default_with_probability(p / bias_eta));
+                cx_node.correlated_pauli_error_rates = Some(CorrelatedPauliErrorRates::default_with_probability(divide_by_bias(p)));
                 cx_node.correlated_pauli_error_rates.as_mut().unwrap().error_rate_ZI = p;
                 match self {
                     Self::GenericBiasedWithStandardCX => {
@@ -191,15 +218,15 @@ impl NoiseModelBuilder {
                 let cx_node = Arc::new(cx_node);
                 // CX gate with measurement error
                 let mut cx_measurement_error_node: NoiseModelNode = (*cx_node).clone();
-                cx_measurement_error_node.pauli_error_rates.error_rate_X = initialization_error_rate / bias_eta;
+                cx_measurement_error_node.pauli_error_rates.error_rate_X = divide_by_bias(initialization_error_rate);
                 cx_measurement_error_node.pauli_error_rates.error_rate_Z = initialization_error_rate;
-                cx_measurement_error_node.pauli_error_rates.error_rate_Y = initialization_error_rate / bias_eta;
+                cx_measurement_error_node.pauli_error_rates.error_rate_Y = divide_by_bias(initialization_error_rate);
                 let cx_measurement_error_node = Arc::new(cx_measurement_error_node);
                 // iterate over all nodes
                 simulator_iter_real!(simulator, position, node, {
                     // first clear error rate
                     noise_model.set_node(position, Some(noiseless_node.clone()));
-                    if position.t >= simulator.height - simulator.measurement_cycles {  // no error on the top, as a perfect measurement round
+                    if position.t >= simulator.perfect_measurement_start() {  // no error on the top, as a perfect measurement round
                         continue
                     }
                     // do different things for each stage
@@ -229,7 +256,7 @@ impl NoiseModelBuilder {
                 });
             },
             Self::TailoredScBellInitCircuit => {
-                let CodeSize { noisy_measurements, di: dp, dj: _dn } = match simulator.code_type {
+                let CodeSize { noisy_measurements, di: dp, dj: _dn, .. } = match simulator.code_type {
                     CodeType::RotatedTailoredCodeBellInit => { simulator.code_size.clone() }
                     _ => unimplemented!("tailored surface code with Bell state initialization is only implemented for open-boundary rotated tailored surface code")
                 };
@@ -287,14 +314,14 @@ impl NoiseModelBuilder {
                 let initialization_error_rate = p;
                 // normal bias nodes
                 let mut normal_biased_node = NoiseModelNode::new();
-                normal_biased_node.pauli_error_rates.error_rate_X = initialization_error_rate / bias_eta;
+                normal_biased_node.pauli_error_rates.error_rate_X = divide_by_bias(initialization_error_rate);
                 normal_biased_node.pauli_error_rates.error_rate_Z = initialization_error_rate;
-                normal_biased_node.pauli_error_rates.error_rate_Y = initialization_error_rate / bias_eta;
+                normal_biased_node.pauli_error_rates.error_rate_Y = divide_by_bias(initialization_error_rate);
                 let normal_biased_node = Arc::new(normal_biased_node);
 
                 // normal bias + cx node (for init)
                 let mut normal_biased_with_cx_node = (*normal_biased_node).clone();
-                normal_biased_with_cx_node.correlated_pauli_error_rates = Some(CorrelatedPauliErrorRates::default_with_probability(p / bias_eta));
+                normal_biased_with_cx_node.correlated_pauli_error_rates = Some(CorrelatedPauliErrorRates::default_with_probability(divide_by_bias(p)));
                 normal_biased_with_cx_node.correlated_pauli_error_rates.as_mut().unwrap().error_rate_ZI = p;
                 normal_biased_with_cx_node.correlated_pauli_error_rates.as_mut().unwrap().error_rate_IZ = 0.5 * p;
                 normal_biased_with_cx_node.correlated_pauli_error_rates.as_mut().unwrap().error_rate_ZZ = 0.5 * p;
@@ -302,7 +329,7 @@ impl NoiseModelBuilder {
 
                 // biased CX gate node; CX & CY have same noise model if using bias-preserving gate
                 let mut cx_node = NoiseModelNode::new();
-                cx_node.correlated_pauli_error_rates = Some(CorrelatedPauliErrorRates::default_with_probability(p / bias_eta));
+                cx_node.correlated_pauli_error_rates = Some(CorrelatedPauliErrorRates::default_with_probability(divide_by_bias(p)));
                 cx_node.correlated_pauli_error_rates.as_mut().unwrap().error_rate_ZI = p;
                 cx_node.correlated_pauli_error_rates.as_mut().unwrap().error_rate_IZ = 0.5 * p;
                 cx_node.correlated_pauli_error_rates.as_mut().unwrap().error_rate_ZZ = 0.5 * p;
@@ -310,7 +337,7 @@ impl NoiseModelBuilder {
 
                 // reversed CX gate node, for convinience
                 let mut rev_cx_node = NoiseModelNode::new();
-                rev_cx_node.correlated_pauli_error_rates = Some(CorrelatedPauliErrorRates::default_with_probability(p / bias_eta));
+                rev_cx_node.correlated_pauli_error_rates = Some(CorrelatedPauliErrorRates::default_with_probability(divide_by_bias(p)));
                 rev_cx_node.correlated_pauli_error_rates.as_mut().unwrap().error_rate_IZ = p;
                 rev_cx_node.correlated_pauli_error_rates.as_mut().unwrap().error_rate_ZI = 0.5 * p;
                 rev_cx_node.correlated_pauli_error_rates.as_mut().unwrap().error_rate_ZZ = 0.5 * p;
@@ -318,9 +345,9 @@ impl NoiseModelBuilder {
 
                 // CX gate with measurement error
                 let mut cx_measurement_error_node: NoiseModelNode = (*cx_node).clone();
-                cx_measurement_error_node.pauli_error_rates.error_rate_X = initialization_error_rate / bias_eta;
+                cx_measurement_error_node.pauli_error_rates.error_rate_X = divide_by_bias(initialization_error_rate);
                 cx_measurement_error_node.pauli_error_rates.error_rate_Z = initialization_error_rate;
-                cx_measurement_error_node.pauli_error_rates.error_rate_Y = initialization_error_rate / bias_eta;
+                cx_measurement_error_node.pauli_error_rates.error_rate_Y = divide_by_bias(initialization_error_rate);
                 let cx_measurement_error_node = Arc::new(cx_measurement_error_node);    
 
                 let simulator = &*simulator;  // force simulator to be immutable, to avoid unexpected changes
@@ -387,7 +414,7 @@ impl NoiseModelBuilder {
                                 //nothing
                             },
                         }
-                    } else if position.t < simulator.height - simulator.measurement_cycles {  // no error before the first round and at final round
+                    } else if position.t < simulator.perfect_measurement_start() {  // no error before the first round and at final round
                         // do different things for each stage
                         match position.t % simulator.measurement_cycles {
                             1 => {  // pauli error on qubits
@@ -421,7 +448,7 @@ impl NoiseModelBuilder {
                 simulator_iter_real!(simulator, position, node, {
                     // first clear error rate
                     noise_model.set_node(position, Some(noiseless_node.clone()));
-                    if position.t >= simulator.height - simulator.measurement_cycles {  // no error on the top, as a perfect measurement round
+                    if position.t >= simulator.perfect_measurement_start() {  // no error on the top, as a perfect measurement round
                         continue
                     }
                     if position.t % simulator.measurement_cycles == 0 {  // add data qubit erasure at the beginning
@@ -448,7 +475,7 @@ impl NoiseModelBuilder {
                 simulator_iter_real!(simulator, position, node, {
                     // first clear error rate
                     noise_model.set_node(position, Some(noiseless_node.clone()));
-                    if position.t >= simulator.height - simulator.measurement_cycles {  // no error on the top, as a perfect measurement round
+                    if position.t >= simulator.perfect_measurement_start() {  // no error on the top, as a perfect measurement round
                         continue
                     }
                     if position.t % simulator.measurement_cycles == 0 {  // add data qubit erasure at the beginning
@@ -499,7 +526,7 @@ impl NoiseModelBuilder {
                 simulator_iter_real!(simulator, position, node, {
                     // first clear error rate
                     noise_model.set_node(position, Some(noiseless_node.clone()));
-                    if position.t >= simulator.height - simulator.measurement_cycles {  // no error on the top, as a perfect measurement round
+                    if position.t >= simulator.perfect_measurement_start() {  // no error on the top, as a perfect measurement round
                         continue
                     }
                     noise_model.set_node(position, Some(erasure_noiseless_node.clone()));
@@ -548,7 +575,7 @@ impl NoiseModelBuilder {
                                         }
                                         // calculate what are the effected qubits in the next round
                                         let nt = t + dt + 1;
-                                        if nt >= simulator.height - simulator.measurement_cycles {
+                                        if nt >= simulator.perfect_measurement_start() {
                                             break
                                         }
                                         let mut next_erased_qubits = BTreeSet::new();
@@ -665,7 +692,7 @@ impl NoiseModelBuilder {
                 simulator_iter_real!(simulator, position, node, {
                     // first clear error rate
                     noise_model.set_node(position, Some(noiseless_node.clone()));
-                    if position.t >= simulator.height - simulator.measurement_cycles {  // no error on the top, as a perfect measurement round
+                    if position.t >= simulator.perfect_measurement_start() {  // no error on the top, as a perfect measurement round
                         continue
                     }
                     // do different things for each stage
@@ -691,7 +718,7 @@ impl NoiseModelBuilder {
                                 if node.qubit_type != QubitType::Data {
                                     error_node = measure_flip_node.clone();
                                 } else {
-                                    if position.t == simulator.height - simulator.measurement_cycles - 2 {
+                                    if position.t == simulator.perfect_measurement_start() - 2 {
                                         let mut new_error_node = error_node.as_ref().clone();
                                         new_error_node.pauli_error_rates = data_qubit_depolarize_node.pauli_error_rates.clone();
                                         error_node = Arc::new(new_error_node);
@@ -703,21 +730,87 @@ impl NoiseModelBuilder {
                     }
                 });
             },
+            Self::CodeCapacity => {
+                let simulator = &*simulator;  // force simulator to be immutable, to avoid unexpected changes
+                assert!(px + py + pz <= 1. && px >= 0. && py >= 0. && pz >= 0.);
+                let mut config_cloned = noise_model_configuration.clone();
+                let config = config_cloned.as_object_mut().expect("noise_model_configuration must be JSON object");
+                let mut error_layer = code_capacity_default_layer(simulator);
+                if let Some(value) = config.remove("t") {
+                    error_layer = value.as_u64().expect("t must be `usize`") as usize;
+                }
+                if !config.is_empty() { panic!("unknown keys: {:?}", config.keys().collect::<Vec<&String>>()); }
+                if let Err(message) = code_capacity_validate_layer(simulator, error_layer) {
+                    panic!("{}", message);
+                }
+                simulator_iter_real!(simulator, position, node, {
+                    noise_model.set_node(position, Some(noiseless_node.clone()));  // clear existing noise model
+                    if position.t == error_layer && node.qubit_type == QubitType::Data {
+                        noise_model.set_node(position, Some(biased_node.clone()));
+                    }
+                });
+            },
+            Self::PauliTwirledRotation => {
+                let simulator = &*simulator;  // force simulator to be immutable, to avoid unexpected changes
+                let mut config_cloned = noise_model_configuration.clone();
+                let config = config_cloned.as_object_mut().expect("noise_model_configuration must be JSON object");
+                let mut angle = 2. * p.sqrt().asin();
+                let mut axis = [0., 0., 1.];
+                if let Some(value) = config.remove("angle") {
+                    angle = value.as_f64().expect("angle must be `f64`");
+                }
+                if let Some(value) = config.remove("axis") {
+                    axis = serde_json::from_value(value).expect("axis must be `[f64; 3]`");
+                }
+                if !config.is_empty() { panic!("unknown keys: {:?}", config.keys().collect::<Vec<&String>>()); }
+                let (px, py, pz) = pauli_twirled_rotation_error_rates(angle, axis);
+                let mut twirled_node = NoiseModelNode::new();
+                twirled_node.pauli_error_rates.error_rate_X = px;
+                twirled_node.pauli_error_rates.error_rate_Y = py;
+                twirled_node.pauli_error_rates.error_rate_Z = pz;
+                let twirled_node = Arc::new(twirled_node);
+                simulator_iter_real!(simulator, position, node, {
+                    noise_model.set_node(position, Some(noiseless_node.clone()));  // clear existing noise model
+                    if position.t == 0 || position.t >= simulator.perfect_measurement_start() {  // no error on the top, as a perfect measurement round
+                        continue
+                    }
+                    if node.gate_type.is_two_qubit_gate() && !node.is_peer_virtual {
+                        noise_model.set_node(position, Some(twirled_node.clone()));
+                    }
+                });
+            },
             Self::DepolarizingNoise => {
                 let mut config_cloned = noise_model_configuration.clone();
                 let config = config_cloned.as_object_mut().expect("noise_model_configuration must be JSON object");
+                // the duration of a measurement round, evenly divided among its `measurement_cycles` steps, and the depolarizing rate per unit time of an idle qubit
+                let round_duration = config.remove("round_duration").map(|value| value.as_f64().expect("round_duration must be `f64`"));
+                let idle_rate_per_time = config.remove("idle_rate_per_time").map(|value| value.as_f64().expect("idle_rate_per_time must be `f64`"));
                 if !config.is_empty() { panic!("unknown keys: {:?}", config.keys().collect::<Vec<&String>>()); }
+                let idle_error_rate = match (round_duration, idle_rate_per_time) {
+                    (None, None) => p,
+                    (Some(round_duration), Some(idle_rate_per_time)) => {
+                        assert!(round_duration >= 0. && idle_rate_per_time >= 0., "round_duration and idle_rate_per_time must be non-negative");
+                        idle_depolarizing_error_rate(idle_rate_per_time, round_duration / simulator.measurement_cycles as f64)
+                    },
+                    _ => panic!("round_duration and idle_rate_per_time must be given together"),
+                };
                 // depolarizing node
                 let mut depolarizing_node = NoiseModelNode::new();
                 depolarizing_node.pauli_error_rates.error_rate_X = p / 3.;
                 depolarizing_node.pauli_error_rates.error_rate_Z = p / 3.;
                 depolarizing_node.pauli_error_rates.error_rate_Y = p / 3.;
                 let depolarizing_node = Arc::new(depolarizing_node);
-                // double depolarizing node
+                // idle depolarizing node, the same as the depolarizing node by default
+                let mut idle_node = NoiseModelNode::new();
+                idle_node.pauli_error_rates.error_rate_X = idle_error_rate / 3.;
+                idle_node.pauli_error_rates.error_rate_Z = idle_error_rate / 3.;
+                idle_node.pauli_error_rates.error_rate_Y = idle_error_rate / 3.;
+                let idle_node = Arc::new(idle_node);
+                // idle and measurement error node
                 let mut double_depolarizing_node = NoiseModelNode::new();
-                double_depolarizing_node.pauli_error_rates.error_rate_X = 2. * p / 3.;
-                double_depolarizing_node.pauli_error_rates.error_rate_Z = 2. * p / 3.;
-                double_depolarizing_node.pauli_error_rates.error_rate_Y = 2. * p / 3.;
+                double_depolarizing_node.pauli_error_rates.error_rate_X = (idle_error_rate + p) / 3.;
+                double_depolarizing_node.pauli_error_rates.error_rate_Z = (idle_error_rate + p) / 3.;
+                double_depolarizing_node.pauli_error_rates.error_rate_Y = (idle_error_rate + p) / 3.;
                 let double_depolarizing_node = Arc::new(double_depolarizing_node);
                 // two qubit depolarizing node
                 let mut correlated_depolarizing_node = NoiseModelNode::new();
@@ -728,18 +821,22 @@ impl NoiseModelBuilder {
                 simulator_iter_real!(simulator, position, node, {
                     // first clear error rate
                     noise_model.set_node(position, Some(noiseless_node.clone()));
-                    if position.t == 0 || position.t >= simulator.height - simulator.measurement_cycles {  // no error on the top, as a perfect measurement round
+                    if position.t == 0 || position.t >= simulator.perfect_measurement_start() {  // no error on the top, as a perfect measurement round
                         continue
                     }
                     // do different things for each stage
                     match position.t % simulator.measurement_cycles {
-                        1 => {  // initialization
-                            noise_model.set_node(position, Some(depolarizing_node.clone()));
+                        1 => {  // initialization: the ancilla qubits are reset while the data qubits idle
+                            if node.qubit_type == QubitType::Data {
+                                noise_model.set_node(position, Some(idle_node.clone()));
+                            } else {
+                                noise_model.set_node(position, Some(depolarizing_node.clone()));
+                            }
                         },
                         0 => {  // measurement
-                            // do nothing
+                            // the data qubits idle
                             if node.qubit_type == QubitType::Data {
-                                noise_model.set_node(position, Some(depolarizing_node.clone()));
+                                noise_model.set_node(position, Some(idle_node.clone()));
                             }
                         },
                         _ => {
@@ -747,7 +844,7 @@ impl NoiseModelBuilder {
                                 if position.t % simulator.measurement_cycles == simulator.measurement_cycles - 1 && node.qubit_type != QubitType::Data {
                                     noise_model.set_node(position, Some(double_depolarizing_node.clone()));
                                 } else {
-                                    noise_model.set_node(position, Some(depolarizing_node.clone()));
+                                    noise_model.set_node(position, Some(idle_node.clone()));
                                 }
                             } else {
                                 if node.qubit_type == QubitType::Data {
@@ -762,10 +859,26 @@ impl NoiseModelBuilder {
                 });
             },
         }
+        simulator.noisy_final_round = noisy_final_round;
+        if noisy_final_round {
+            assert!(simulator.height > 2 * simulator.measurement_cycles, "noisy final round requires at least one noisy measurement round to copy the noise from");
+            assert_eq!(simulator.code_size.final_perfect_rounds, 1, "noisy final round replaces the single final perfect measurement round");
+            let simulator = &*simulator;
+            for t in simulator.perfect_measurement_start() .. simulator.height - 1 {  // the top layer is never noisy
+                simulator_iter_real!(simulator, position, _node, t => t, {
+                    let below = pos!(t - simulator.measurement_cycles, position.i, position.j);
+                    noise_model.set_node(position, Some(noise_model.get_node_unwrap_arc(&below)));
+                });
+            }
+        }
+        if let Some(tls_defects) = tls_defects {
+            tls_defects.apply(simulator, noise_model).expect("tls defects error");
+        }
     }
 
     /// check as strictly as possible, given the user specified json noise model description
     pub fn apply_noise_model_modifier(simulator : &mut Simulator, noise_model: &mut NoiseModel, modifier: &serde_json::Value) -> Result<(), String> {
+        ArtifactKind::NoiseModel.check_envelope(modifier)?;
         if modifier.get("code_type").ok_or(format!("missing field: code_type"))? != &json!(simulator.code_type) {
             return Err(format!("mismatch: code_type"))
         }
@@ -778,6 +891,9 @@ impl NoiseModelBuilder {
         if modifier.get("horizontal").ok_or(format!("missing field: horizontal"))? != &json!(simulator.horizontal) {
             return Err(format!("mismatch: horizontal"))
         }
+        if let Some(noisy_final_round) = modifier.get("noisy_final_round") {
+            simulator.noisy_final_round = noisy_final_round.as_bool().ok_or("format error: noisy_final_round".to_string())?;
+        }
         // iterate nodes
         let nodes = modifier.get("nodes").ok_or(format!("missing field: nodes"))?.as_array().ok_or(format!("format error: nodes"))?;
         if simulator.nodes.len() != nodes.len() {
@@ -847,3 +963,318 @@ pub(crate) fn register(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<NoiseModelBuilder>()?;
     Ok(())
 }
+
+/// the Pauli error rates `(px, py, pz)` of the Pauli twirl of a single-qubit rotation `exp(-i angle/2 axis.σ)`: the rotation is
+/// `cos(angle/2) I - i sin(angle/2) axis.σ`, and the twirl removes the cross terms, leaving `σ_k` with probability `sin^2(angle/2) axis_k^2`
+pub fn pauli_twirled_rotation_error_rates(angle: f64, axis: [f64; 3]) -> (f64, f64, f64) {
+    let norm_square: f64 = axis.iter().map(|component| component * component).sum();
+    assert!(norm_square > 0., "rotation axis must be non-zero");
+    let sin_square = (angle / 2.).sin().powi(2);
+    let [nx, ny, nz] = axis;
+    (sin_square * nx * nx / norm_square, sin_square * ny * ny / norm_square, sin_square * nz * nz / norm_square)
+}
+
+/// the default error layer of [`NoiseModelBuilder::CodeCapacity`]: just below the first noisy measurement at `t = measurement_cycles`,
+/// i.e. after all the gates of the first round; without any noisy measurement round (T = 0, `height = measurement_cycles + 1`), the final
+/// perfect measurement round starts right above the bottom layer, which is then the only layer that can have errors
+pub fn code_capacity_default_layer(simulator: &Simulator) -> usize {
+    if simulator.measurement_cycles < simulator.perfect_measurement_start() {
+        simulator.measurement_cycles - 1
+    } else {
+        0
+    }
+}
+
+/// check that the data qubit errors at layer `t` are detected by a single measurement round as in the code capacity noise model:
+/// `t` must be below the final perfect measurement round, and the data qubits must not have gates both before and after `t` in
+/// the measurement round of `t`, otherwise an error is partially detected by two consecutive rounds
+pub fn code_capacity_validate_layer(simulator: &Simulator, t: usize) -> Result<(), String> {
+    if t >= simulator.perfect_measurement_start() {
+        return Err(format!("t = {} must be below the final perfect measurement round starting at t = {}", t, simulator.perfect_measurement_start()))
+    }
+    let round_start = t / simulator.measurement_cycles * simulator.measurement_cycles;  // the layer of the previous measurement
+    let has_data_gate = |layers: std::ops::Range<usize>| -> bool {
+        let mut has_data_gate = false;
+        for layer in layers {
+            simulator_iter_real!(simulator, position, node, t => layer, {
+                if node.qubit_type == QubitType::Data && node.gate_type != GateType::None {
+                    has_data_gate = true;
+                }
+            });
+        }
+        has_data_gate
+    };
+    if has_data_gate(round_start + 1 .. t + 1) && has_data_gate(t + 1 .. round_start + simulator.measurement_cycles) {
+        return Err(format!("t = {} is in the middle of the gates of a measurement round, whose errors are detected by two rounds", t))
+    }
+    Ok(())
+}
+
+/// the depolarizing error rate of a qubit idling for `duration` with a depolarizing rate `idle_rate_per_time`, i.e. the probability
+/// `1 - exp(-idle_rate_per_time * duration)` of at least one error in a Poisson process, which is `idle_rate_per_time * duration` to first order
+pub fn idle_depolarizing_error_rate(idle_rate_per_time: f64, duration: f64) -> f64 {
+    1. - (-idle_rate_per_time * duration).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// the code type, `p`, `bias_eta`, `pe` and `noise_model_configuration` of the golden rate table of each noise model builder: by default
+    /// `p = 0.01`, `bias_eta = 10` and `pe = 0.005` on the standard planar code, unless the builder requires otherwise
+    fn golden_configuration(noise_model_builder: NoiseModelBuilder) -> (CodeType, f64, f64, f64, serde_json::Value) {
+        match noise_model_builder {
+            NoiseModelBuilder::TailoredScBellInitPhenomenological => (CodeType::RotatedTailoredCode, 0.01, 10., 0.005, json!({})),
+            NoiseModelBuilder::TailoredScBellInitCircuit => (CodeType::RotatedTailoredCodeBellInit, 0.01, 10., 0., json!({})),
+            NoiseModelBuilder::ErasureOnlyPhenomenological => (CodeType::StandardPlanarCode, 0., 10., 0.005, json!({})),
+            NoiseModelBuilder::OnlyGateErrorCircuitLevel => (CodeType::StandardPlanarCode, 0.01, 0.5, 0.005
+                , json!({"use_correlated_pauli": true, "use_correlated_erasure": true})),
+            _ => (CodeType::StandardPlanarCode, 0.01, 10., 0.005, json!({})),
+        }
+    }
+
+    /// compare the rate table of every noise model builder (d = 3, 2 noisy measurements) against the checked-in golden file; run with
+    /// `QECP_BLESS=1` to regenerate the golden files after an intentional change, and review their diff
+    #[test]
+    fn noise_model_builder_golden_rate_tables() {  // cargo test noise_model_builder_golden_rate_tables -- --nocapture
+        let bless = std::env::var("QECP_BLESS").map(|value| value == "1").unwrap_or(false);
+        let golden_folder = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src").join("noise_model_golden");
+        let mut failures = Vec::new();
+        for noise_model_builder in NoiseModelBuilder::value_variants() {
+            let name = noise_model_builder.to_possible_value().unwrap().get_name().to_string();
+            let (code_type, p, bias_eta, pe, noise_model_configuration) = golden_configuration(*noise_model_builder);
+            let mut simulator = Simulator::new(code_type, CodeSize::new(2, 3, 3));
+            let mut noise_model = NoiseModel::new(&simulator);
+            noise_model_builder.apply(&mut simulator, &mut noise_model, &noise_model_configuration, p, bias_eta, pe);
+            let table = noise_model.rate_table();
+            let filepath = golden_folder.join(format!("{}.json", name));
+            if bless {
+                fs::create_dir_all(&golden_folder).unwrap();
+                fs::write(&filepath, rate_table_to_string(&table)).unwrap();
+                continue
+            }
+            let golden: NoiseRateTable = match fs::read_to_string(&filepath) {
+                Ok(content) => serde_json::from_str(&content).unwrap(),
+                Err(_) => {
+                    failures.push(format!("{}: missing golden file {}", name, filepath.display()));
+                    continue
+                },
+            };
+            for difference in rate_table_diff(&golden, &table, 1e-9).iter() {
+                failures.push(format!("{}: {}", name, difference));
+            }
+        }
+        assert!(failures.is_empty(), "rate tables differ from the golden files, run with QECP_BLESS=1 if intended:\n{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn noise_model_builder_code_capacity() {  // cargo test noise_model_builder_code_capacity -- --nocapture
+        // (noisy measurements, configuration, error layer, layer of the defects)
+        for (noisy_measurements, noise_model_configuration, error_layer, defect_layer) in [(0, json!({}), 0, 6), (2, json!({}), 5, 12)
+                , (2, json!({"t": 0}), 0, 6), (2, json!({"t": 12}), 12, 18), (2, json!({"t": 11}), 11, 18)] {
+            let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(noisy_measurements, 5, 5));
+            let mut noise_model = NoiseModel::new(&simulator);
+            NoiseModelBuilder::CodeCapacity.apply(&mut simulator, &mut noise_model, &noise_model_configuration, 0.1, 0.5, 0.);
+            noise_model_sanity_check(&simulator, &noise_model).unwrap();
+            // only data qubits at the chosen layer are noisy
+            let mut data_positions = Vec::new();
+            simulator_iter_real!(simulator, position, node, {
+                let is_noisy = !noise_model.get_node_unwrap(position).is_noiseless();
+                assert_eq!(is_noisy, position.t == error_layer && node.qubit_type == QubitType::Data, "{}", position);
+                if is_noisy {
+                    data_positions.push(position.clone());
+                }
+            });
+            assert_eq!(data_positions.len(), 5 * 5 + 4 * 4);
+            // each error is detected by a single measurement round, and never causes a measurement error
+            for position in data_positions.iter() {
+                for error in [ErrorType::X, ErrorType::Z] {
+                    simulator.clear_all_errors();
+                    simulator.set_error_check(&noise_model, position, &error);
+                    simulator.propagate_errors();
+                    let sparse_measurement = simulator.generate_sparse_measurement();
+                    assert!(sparse_measurement.len() > 0);
+                    for defect in sparse_measurement.iter() {
+                        assert_eq!(defect.t, defect_layer);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn noise_model_builder_code_capacity_layer() {  // cargo test noise_model_builder_code_capacity_layer -- --nocapture
+        // T = 0 has a single measurement round, which is the final perfect one
+        let simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(0, 3, 3));
+        assert_eq!(simulator.height, simulator.measurement_cycles + 1);
+        assert_eq!(code_capacity_default_layer(&simulator), 0);
+        assert!(code_capacity_validate_layer(&simulator, 0).is_ok());
+        assert!(code_capacity_validate_layer(&simulator, 1).is_err());
+        let simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 3, 3));
+        assert_eq!(code_capacity_default_layer(&simulator), simulator.measurement_cycles - 1);
+        // the data qubits have gates at t = 2, 3, 4, 5 of each round
+        for t in 0..simulator.height {
+            let is_valid = t < simulator.perfect_measurement_start() && !(2..5).contains(&(t % simulator.measurement_cycles));
+            assert_eq!(code_capacity_validate_layer(&simulator, t).is_ok(), is_valid, "t = {}", t);
+        }
+    }
+
+    #[test]
+    fn noise_model_builder_pauli_twirled_rotation() {  // cargo test noise_model_builder_pauli_twirled_rotation -- --nocapture
+        // small rotation: the twirled error rate is approximately (angle/2)^2, the standard small-angle result
+        let angle = 0.02;
+        let (px, py, pz) = pauli_twirled_rotation_error_rates(angle, [0., 0., 1.]);
+        assert_eq!((px, py), (0., 0.));
+        assert!((pz / ((angle / 2.) * (angle / 2.)) - 1.).abs() < 1e-4);
+        // known values: a rotation by `angle` around a Pauli axis applies that Pauli with probability sin^2(angle/2), e.g. a pi/2 rotation
+        // (the square root of a Pauli) half of the time and a pi rotation (the Pauli itself) always; the Hadamard is a pi rotation around (X+Z)/sqrt(2)
+        let half_pi = std::f64::consts::FRAC_PI_2;
+        let pi = std::f64::consts::PI;
+        for (angle, axis, expected) in [(0.3, [1., 0., 0.], ((0.15f64).sin().powi(2), 0., 0.)), (0.3, [0., 1., 0.], (0., (0.15f64).sin().powi(2), 0.))
+                , (half_pi, [0., 0., 1.], (0., 0., 0.5)), (pi, [1., 0., 0.], (1., 0., 0.)), (pi, [0., 0., 5.], (0., 0., 1.))
+                , (pi, [1., 0., 1.], (0.5, 0., 0.5)), (half_pi, [1., 1., 1.], (1. / 6., 1. / 6., 1. / 6.)), (2. * pi, [1., 0., 0.], (0., 0., 0.))] {
+            let (px, py, pz) = pauli_twirled_rotation_error_rates(angle, axis);
+            for (rate, expected_rate) in [(px, expected.0), (py, expected.1), (pz, expected.2)] {
+                assert!((rate - expected_rate).abs() < 1e-12, "angle {} axis {:?}: {:?} != {:?}", angle, axis, (px, py, pz), expected);
+            }
+        }
+        let (angle, axis) = (0.1, [1., 2., 2.]);
+        let (px, py, pz) = pauli_twirled_rotation_error_rates(angle, axis);
+        // by default the total error rate of each gate is `p`
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::PauliTwirledRotation.apply(&mut simulator, &mut noise_model, &json!({}), 0.01, 0.5, 0.);
+        noise_model_sanity_check(&simulator, &noise_model).unwrap();
+        let mut noisy_count = 0;
+        simulator_iter_real!(simulator, position, node, {
+            let noise_model_node = noise_model.get_node_unwrap(position);
+            if !noise_model_node.is_noiseless() {
+                noisy_count += 1;
+                assert!(node.gate_type.is_two_qubit_gate());
+                assert!((noise_model_node.pauli_error_rates.error_rate_Z - 0.01).abs() < 1e-12);
+                assert_eq!(noise_model_node.pauli_error_rates.error_rate_X, 0.);
+            }
+        });
+        assert!(noisy_count > 0);
+        // configured angle and axis
+        NoiseModelBuilder::PauliTwirledRotation.apply(&mut simulator, &mut noise_model, &json!({"angle": angle, "axis": axis}), 0., 0.5, 0.);
+        simulator_iter_real!(simulator, position, _node, {
+            let noise_model_node = noise_model.get_node_unwrap(position);
+            if !noise_model_node.is_noiseless() {
+                assert_eq!(noise_model_node.pauli_error_rates.error_rate_X, px);
+                assert_eq!(noise_model_node.pauli_error_rates.error_rate_Y, py);
+                assert_eq!(noise_model_node.pauli_error_rates.error_rate_Z, pz);
+            }
+        });
+    }
+
+    #[test]
+    fn noise_model_builder_idle_duration() {  // cargo test noise_model_builder_idle_duration -- --nocapture
+        let p = 0.001;
+        let idle_rate_per_time = 1e-4;
+        let build = |noise_model_configuration: serde_json::Value| -> (Simulator, NoiseModel) {
+            let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 3, 3));
+            let mut noise_model = NoiseModel::new(&simulator);
+            NoiseModelBuilder::DepolarizingNoise.apply(&mut simulator, &mut noise_model, &noise_model_configuration, p, 0.5, 0.);
+            noise_model_sanity_check(&simulator, &noise_model).unwrap();
+            (simulator, noise_model)
+        };
+        let (simulator, default_noise_model) = build(json!({}));
+        let (_, noise_model) = build(json!({"round_duration": 6., "idle_rate_per_time": idle_rate_per_time}));
+        let (_, doubled_noise_model) = build(json!({"round_duration": 12., "idle_rate_per_time": idle_rate_per_time}));
+        let step_duration = 6. / simulator.measurement_cycles as f64;
+        let mut idle_nodes = 0;
+        simulator_iter_real!(simulator, position, node, {
+            let default_rates = &default_noise_model.get_node_unwrap(position).pauli_error_rates;
+            let rates = &noise_model.get_node_unwrap(position).pauli_error_rates;
+            let doubled_rates = &doubled_noise_model.get_node_unwrap(position).pauli_error_rates;
+            let is_idle = node.qubit_type == QubitType::Data && position.t.is_multiple_of(simulator.measurement_cycles)
+                && position.t > 0 && position.t < simulator.perfect_measurement_start();
+            if is_idle {
+                idle_nodes += 1;
+                assert!((default_rates.error_probability() - p).abs() < 1e-15);
+                assert!((rates.error_probability() - idle_depolarizing_error_rate(idle_rate_per_time, step_duration)).abs() < 1e-15);
+                // doubling the round duration doubles the idle error to first order
+                assert!((doubled_rates.error_probability() / rates.error_probability() - 2.).abs() < 1e-3);
+            }
+            // the gate errors don't depend on the duration
+            let default_node = default_noise_model.get_node_unwrap(position);
+            if default_node.correlated_pauli_error_rates.is_some() {
+                assert_eq!(noise_model.get_node_unwrap(position).correlated_pauli_error_rates, default_node.correlated_pauli_error_rates);
+            }
+        });
+        assert!(idle_nodes > 0);
+        let result = std::panic::catch_unwind(|| build(json!({"round_duration": 6.})));
+        assert!(result.is_err(), "round_duration and idle_rate_per_time must be given together");
+    }
+
+    #[test]
+    fn noise_model_builder_tls_defects() {  // cargo test noise_model_builder_tls_defects -- --nocapture
+        let build = |seed: u64| -> (Simulator, NoiseModel) {
+            let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 5, 5));
+            let mut noise_model = NoiseModel::new(&simulator);
+            let noise_model_configuration = json!({"tls": {"count": 3, "severity": 10.0, "seed": seed}});
+            NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &noise_model_configuration, 0.01, 0.5, 0.);
+            noise_model_sanity_check(&simulator, &noise_model).unwrap();
+            (simulator, noise_model)
+        };
+        let (simulator, noise_model) = build(1727);
+        println!("tls_defects: {:?}", noise_model.tls_defects);
+        assert_eq!(noise_model.tls_defects.len(), 3);
+        // reproducible from the seed
+        assert_eq!(build(1727).1.tls_defects, noise_model.tls_defects);
+        assert_ne!(build(1728).1.tls_defects, noise_model.tls_defects);
+        // the Pauli error rates of the defective data qubits are multiplied in every noisy round
+        let mut reference_noise_model = NoiseModel::new(&simulator);
+        let mut reference_simulator = simulator.clone();
+        NoiseModelBuilder::Phenomenological.apply(&mut reference_simulator, &mut reference_noise_model, &json!({}), 0.01, 0.5, 0.);
+        let mut defective_nodes = 0;
+        simulator_iter_real!(simulator, position, node, {
+            let rates = &noise_model.get_node_unwrap(position).pauli_error_rates;
+            let reference_rates = &reference_noise_model.get_node_unwrap(position).pauli_error_rates;
+            if noise_model.tls_defects.contains(&(position.i, position.j)) {
+                assert_eq!(node.qubit_type, QubitType::Data);
+                assert!((rates.error_rate_X - 10. * reference_rates.error_rate_X).abs() < 1e-12);
+                assert!((rates.error_rate_Z - 10. * reference_rates.error_rate_Z).abs() < 1e-12);
+                if reference_rates.error_probability() > 0. {
+                    defective_nodes += 1;
+                }
+            } else {
+                assert_eq!(rates, reference_rates);
+            }
+        });
+        assert_eq!(defective_nodes, 3 * 3, "3 defects in each of the 3 noisy rounds");
+        // the chosen data qubits appear in the dump and survive loading it back
+        let dump = simulator.to_json(&noise_model);
+        assert_eq!(dump["tls_defects"], json!(noise_model.tls_defects));
+        assert_eq!(NoiseModel::from_json(&dump).unwrap().tls_defects, noise_model.tls_defects);
+    }
+
+    #[test]
+    fn noise_model_builder_final_perfect_rounds() {  // cargo test noise_model_builder_final_perfect_rounds -- --nocapture
+        for noise_model_builder in [NoiseModelBuilder::Phenomenological, NoiseModelBuilder::StimNoiseModel, NoiseModelBuilder::PauliTwirledRotation] {
+            let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 3, 3).with_final_perfect_rounds(2));
+            assert_eq!(simulator.height, simulator.measurement_cycles * 4 + 1);
+            assert_eq!(simulator.perfect_measurement_start(), simulator.height - 2 * simulator.measurement_cycles);
+            let mut noise_model = NoiseModel::new(&simulator);
+            noise_model_builder.apply(&mut simulator, &mut noise_model, &json!({}), 0.05, 0.5, 0.);
+            noise_model_sanity_check(&simulator, &noise_model).unwrap();
+            // the last two measurement cycles carry no noise, while the noisy rounds below do
+            let mut last_noisy_t = 0;
+            simulator_iter_real!(simulator, position, _node, if !noise_model.get_node_unwrap(position).is_noiseless() {
+                last_noisy_t = std::cmp::max(last_noisy_t, position.t);
+            });
+            assert!(last_noisy_t < simulator.perfect_measurement_start(), "{:?}", noise_model_builder);
+            assert!(last_noisy_t + simulator.measurement_cycles >= simulator.perfect_measurement_start(), "{:?}", noise_model_builder);
+            // so the two final rounds measure the same outcomes and the top round never has a defect
+            simulator.compress_error_rates(&mut noise_model);
+            for _ in 0..100 {
+                simulator.generate_random_errors(&noise_model);
+                let sparse_measurement = simulator.generate_sparse_measurement();
+                assert_eq!(sparse_measurement.round_defects(simulator.height - 1).count(), 0);
+            }
+        }
+    }
+
+}
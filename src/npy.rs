@@ -0,0 +1,96 @@
+//! NumPy `.npy` file format
+//!
+//! Writes arrays in the NumPy format version 1.0 (<https://numpy.org/doc/stable/reference/generated/numpy.lib.format.html>), so that
+//! the sampled data can be loaded by `numpy.load` without any Python round-trip. The data is stored in C order.
+//!
+
+use std::io::Write;
+
+
+/// the magic string at the beginning of every `.npy` file
+pub const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// element types that can be written to `.npy` files
+pub trait NpyElement: Copy {
+    /// the `descr` of the dtype, e.g. `|u1` or `<f8`
+    fn descr() -> &'static str;
+    /// append the little-endian bytes of this element
+    fn extend_bytes(&self, bytes: &mut Vec<u8>);
+}
+
+impl NpyElement for u8 {
+    fn descr() -> &'static str { "|u1" }
+    fn extend_bytes(&self, bytes: &mut Vec<u8>) { bytes.push(*self); }
+}
+
+impl NpyElement for f64 {
+    fn descr() -> &'static str { "<f8" }
+    fn extend_bytes(&self, bytes: &mut Vec<u8>) { bytes.extend_from_slice(&self.to_le_bytes()); }
+}
+
+/// the header dictionary, e.g. `{'descr': '|u1', 'fortran_order': False, 'shape': (3, 2), }`
+pub fn npy_header(descr: &str, shape: &[usize]) -> String {
+    let shape = match shape.len() {
+        1 => format!("({},)", shape[0]),  // a tuple of a single element needs a trailing comma
+        _ => format!("({})", shape.iter().map(|length| length.to_string()).collect::<Vec<_>>().join(", ")),
+    };
+    format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", descr, shape)
+}
+
+/// write the elements in C order as an array of the given shape
+pub fn write_npy<W: Write, T: NpyElement>(writer: &mut W, shape: &[usize], elements: impl IntoIterator<Item = T>) -> std::io::Result<()> {
+    let mut header = npy_header(T::descr(), shape);
+    // the header is padded with spaces and terminated by a newline so that the data is aligned to 64 bytes
+    let prefix_length = NPY_MAGIC.len() + 2 + 2;
+    let padding = (64 - (prefix_length + header.len() + 1) % 64) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+    let mut bytes = Vec::with_capacity(prefix_length + header.len());
+    bytes.extend_from_slice(NPY_MAGIC);
+    bytes.extend_from_slice(&[1, 0]);  // version 1.0
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    let mut count = 0;
+    for element in elements {
+        element.extend_bytes(&mut bytes);
+        count += 1;
+    }
+    assert_eq!(count, shape.iter().product::<usize>(), "the number of elements doesn't match the shape {:?}", shape);
+    writer.write_all(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// parse the header of a `.npy` file following the format specification, returning the header dictionary and the data
+    pub fn parse_npy(bytes: &[u8]) -> (String, &[u8]) {
+        assert_eq!(&bytes[..6], NPY_MAGIC);
+        assert_eq!(&bytes[6..8], &[1, 0]);
+        let header_length = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        assert_eq!((10 + header_length) % 64, 0, "data must be aligned to 64 bytes");
+        let header = std::str::from_utf8(&bytes[10..10 + header_length]).unwrap();
+        assert!(header.ends_with('\n'));
+        (header.trim_end().to_string(), &bytes[10 + header_length..])
+    }
+
+    #[test]
+    fn npy_write_header_and_data() {  // cargo test npy_write_header_and_data -- --nocapture
+        let mut bytes = Vec::new();
+        write_npy(&mut bytes, &[2, 3], [0u8, 1, 2, 3, 4, 5]).unwrap();
+        let (header, data) = parse_npy(&bytes);
+        assert_eq!(header, "{'descr': '|u1', 'fortran_order': False, 'shape': (2, 3), }");
+        assert_eq!(data, &[0, 1, 2, 3, 4, 5]);
+        let mut bytes = Vec::new();
+        write_npy(&mut bytes, &[2], [1.5f64, -2.]).unwrap();
+        let (header, data) = parse_npy(&bytes);
+        assert_eq!(header, "{'descr': '<f8', 'fortran_order': False, 'shape': (2,), }");
+        assert_eq!(data, [1.5f64.to_le_bytes(), (-2f64).to_le_bytes()].concat());
+        // empty arrays are valid as well
+        let mut bytes = Vec::new();
+        write_npy(&mut bytes, &[0, 4], Vec::<u8>::new()).unwrap();
+        let (header, data) = parse_npy(&bytes);
+        assert_eq!(header, "{'descr': '|u1', 'fortran_order': False, 'shape': (0, 4), }");
+        assert!(data.is_empty());
+    }
+}
@@ -0,0 +1,297 @@
+//! Position Indexer
+//!
+//! Decoders work on dense indices instead of [`Position`]; this module defines the single mapping between them so that the indices
+//! used by different decoders of the same simulator agree. Positions are indexed in the sorted order of [`Position`] (`t`, then `i`,
+//! then `j`) within three separate namespaces: real stabilizer measurements, virtual boundary measurements and data qubits.
+//!
+
+use super::simulator::*;
+use super::types::*;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use serde::{Serialize, Deserialize};
+
+
+/// a dense index over a sorted list of positions
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionIndex {
+    /// index to position mapping
+    positions: Vec<Position>,
+    /// position to index mapping
+    #[serde(skip)]
+    indices: HashMap<Position, usize>,
+}
+
+impl PositionIndex {
+    fn new(mut positions: Vec<Position>) -> Self {
+        positions.sort();
+        let indices = positions.iter().enumerate().map(|(index, position)| (position.clone(), index)).collect();
+        Self {
+            positions,
+            indices,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// all positions in index order
+    pub fn positions(&self) -> &[Position] {
+        &self.positions
+    }
+
+    /// get the position of an index, panic if the index is out of range
+    pub fn position(&self, index: usize) -> &Position {
+        &self.positions[index]
+    }
+
+    /// get the index of a position, or None if the position is not in this namespace
+    pub fn get_index(&self, position: &Position) -> Option<usize> {
+        self.indices.get(position).cloned()
+    }
+
+    /// get the index of a position, panic if the position is not in this namespace
+    pub fn index(&self, position: &Position) -> usize {
+        match self.indices.get(position) {
+            Some(index) => *index,
+            None => panic!("position {} is not indexed", position),
+        }
+    }
+}
+
+/// the shared position indices of a simulator, see the module documentation
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionIndexer {
+    /// real stabilizer measurements at the end of each measurement cycle, i.e. the nodes of the model graph
+    pub stabilizers: PositionIndex,
+    /// virtual stabilizer measurements at the end of each measurement cycle, i.e. the virtual boundaries of the model graph
+    pub virtual_boundaries: PositionIndex,
+    /// real data qubits at `t = 0`
+    pub data_qubits: PositionIndex,
+}
+
+impl PositionIndexer {
+    /// whether a position is a (real or virtual) stabilizer measurement at the end of a measurement cycle
+    pub fn is_measurement(simulator: &Simulator, position: &Position) -> bool {
+        position.t != 0 && position.t.is_multiple_of(simulator.measurement_cycles) && simulator.is_node_exist(position)
+            && simulator.get_node_unwrap(position).gate_type.is_measurement()
+    }
+
+    /// whether a position is a real stabilizer measurement at the end of a measurement cycle, i.e. a node in the model graph
+    pub fn is_stabilizer(simulator: &Simulator, position: &Position) -> bool {
+        Self::is_measurement(simulator, position) && simulator.is_node_real(position)
+    }
+
+    pub fn new(simulator: &Simulator) -> Self {
+        let mut stabilizers = Vec::new();
+        let mut virtual_boundaries = Vec::new();
+        simulator_iter!(simulator, position, delta_t => simulator.measurement_cycles, if Self::is_measurement(simulator, position) {
+            if simulator.is_node_real(position) {
+                stabilizers.push(position.clone());
+            } else {
+                virtual_boundaries.push(position.clone());
+            }
+        });
+        let mut data_qubits = Vec::new();
+        simulator_iter_real!(simulator, position, node, t => 0, if node.qubit_type == QubitType::Data {
+            data_qubits.push(position.clone());
+        });
+        Self {
+            stabilizers: PositionIndex::new(stabilizers),
+            virtual_boundaries: PositionIndex::new(virtual_boundaries),
+            data_qubits: PositionIndex::new(data_qubits),
+        }
+    }
+
+    /// the number of (real and virtual) stabilizer measurements
+    pub fn measurement_num(&self) -> usize {
+        self.stabilizers.len() + self.virtual_boundaries.len()
+    }
+
+    /// index of a real or virtual stabilizer measurement, where virtual boundaries are numbered after all the real stabilizers
+    pub fn measurement_index(&self, position: &Position) -> Option<usize> {
+        self.stabilizers.get_index(position).or_else(|| {
+            self.virtual_boundaries.get_index(position).map(|index| self.stabilizers.len() + index)
+        })
+    }
+
+    /// position of a measurement index given by [`PositionIndexer::measurement_index`]
+    pub fn measurement_position(&self, index: usize) -> &Position {
+        if index < self.stabilizers.len() {
+            self.stabilizers.position(index)
+        } else {
+            self.virtual_boundaries.position(index - self.stabilizers.len())
+        }
+    }
+}
+
+/// flat detector indices of the real stabilizer measurements, the same as [`PositionIndexer::stabilizers`], for exchanging syndromes with
+/// external tools like Stim or PyMatching; it serializes as the list of detector positions in index order, so that a dataset of
+/// detector indices carries its own mapping
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "Vec<Position>", into = "Vec<Position>")]
+pub struct DetectorMap {
+    detectors: PositionIndex,
+}
+
+impl DetectorMap {
+    pub fn from_simulator(simulator: &Simulator) -> Self {
+        let mut detectors = Vec::new();
+        simulator_iter!(simulator, position, delta_t => simulator.measurement_cycles, if PositionIndexer::is_stabilizer(simulator, position) {
+            detectors.push(position.clone());
+        });
+        Self {
+            detectors: PositionIndex::new(detectors),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.detectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.detectors.is_empty()
+    }
+
+    /// all detector positions in index order
+    pub fn positions(&self) -> &[Position] {
+        self.detectors.positions()
+    }
+
+    /// the detector index of a position, or None if it's not a real stabilizer measurement
+    pub fn index(&self, position: &Position) -> Option<usize> {
+        self.detectors.get_index(position)
+    }
+
+    /// the position of a detector index, or None if the index is out of range
+    pub fn position(&self, index: usize) -> Option<&Position> {
+        self.detectors.positions().get(index)
+    }
+}
+
+impl TryFrom<Vec<Position>> for DetectorMap {
+    type Error = String;
+    fn try_from(positions: Vec<Position>) -> Result<Self, Self::Error> {
+        // the indices are implied by the order, which must be the canonical one so that it's the same as [`DetectorMap::from_simulator`]
+        if let Some(window) = positions.windows(2).find(|window| window[0] >= window[1]) {
+            return Err(format!("detector positions must be strictly ascending, but {} is followed by {}", window[0], window[1]))
+        }
+        Ok(Self {
+            detectors: PositionIndex::new(positions),
+        })
+    }
+}
+
+impl From<DetectorMap> for Vec<Position> {
+    fn from(detector_map: DetectorMap) -> Self {
+        detector_map.detectors.positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::code_builder::*;
+    use super::super::noise_model::*;
+    use super::super::noise_model_builder::*;
+    use super::super::model_graph::*;
+    use super::super::decoder_union_find::*;
+    use super::super::decoder_tailored_union_find::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn position_indexer_namespaces() {  // cargo test position_indexer_namespaces -- --nocapture
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(3, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), 0.01, 0.5, 0.);
+        let position_indexer = PositionIndexer::new(&simulator);
+        // deterministic and sorted
+        let cloned = PositionIndexer::new(&simulator.clone());
+        for (namespace, cloned_namespace) in [(&position_indexer.stabilizers, &cloned.stabilizers)
+                , (&position_indexer.virtual_boundaries, &cloned.virtual_boundaries), (&position_indexer.data_qubits, &cloned.data_qubits)] {
+            assert!(!namespace.is_empty());
+            assert_eq!(namespace.positions(), cloned_namespace.positions());
+            assert!(namespace.positions().windows(2).all(|window| window[0] < window[1]));
+            for (index, position) in namespace.positions().iter().enumerate() {
+                assert_eq!(namespace.index(position), index);
+            }
+        }
+        // the stabilizers are exactly the nodes of the model graph, and the virtual boundaries are disjoint from them
+        let model_graph = ModelGraph::new(&simulator);
+        let mut model_graph_nodes = Vec::new();
+        simulator_iter!(simulator, position, if model_graph.is_node_exist(position) {
+            model_graph_nodes.push(position.clone());
+        });
+        assert_eq!(position_indexer.stabilizers.positions(), &model_graph_nodes[..]);
+        for position in position_indexer.virtual_boundaries.positions().iter() {
+            assert!(simulator.is_node_virtual(position) && position_indexer.stabilizers.get_index(position).is_none());
+            let index = position_indexer.measurement_index(position).unwrap();
+            assert!(index >= position_indexer.stabilizers.len());
+            assert_eq!(position_indexer.measurement_position(index), position);
+        }
+        for position in position_indexer.data_qubits.positions().iter() {
+            assert_eq!(position.t, 0);
+            assert_eq!(position_indexer.measurement_index(position), None);
+        }
+    }
+
+    /// the union-find and tailored union-find decoders of the same configuration use the same stabilizer indices
+    #[test]
+    fn position_indexer_shared_by_decoders() {  // cargo test position_indexer_shared_by_decoders -- --nocapture
+        let mut simulator = Simulator::new(CodeType::RotatedTailoredCode, CodeSize::new(0, 5, 5));
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, 0.001, 0.001, 0.05, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        let noise_model = Arc::new(noise_model);
+        let position_indexer = PositionIndexer::new(&simulator);
+        let union_find_decoder = UnionFindDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1, false);
+        let tailored_union_find_decoder = TailoredUnionFindDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1, false);
+        let union_find_indexer = &union_find_decoder.position_indexer;
+        let tailored_indexer = &tailored_union_find_decoder.diagonal_union_find.position_indexer;
+        assert_eq!(union_find_indexer.stabilizers.positions(), position_indexer.stabilizers.positions());
+        assert_eq!(tailored_indexer.stabilizers.positions(), position_indexer.stabilizers.positions());
+        assert_eq!(tailored_indexer.virtual_boundaries.positions(), position_indexer.virtual_boundaries.positions());
+        for (index, position) in position_indexer.stabilizers.positions().iter().enumerate() {
+            assert_eq!(union_find_decoder.nodes[index].index, index);
+            assert_eq!(union_find_indexer.stabilizers.index(position), index);
+            assert_eq!(tailored_indexer.measurement_index(position), Some(index));
+        }
+    }
+
+    #[test]
+    fn detector_map_round_trip() {  // cargo test detector_map_round_trip -- --nocapture
+        use super::super::reproducible_rand::Xoroshiro128StarStar;
+        use crate::rand_core::SeedableRng;
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(3, 5, 5));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::StimNoiseModel.apply(&mut simulator, &mut noise_model, &json!({}), 0.01, 0.5, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        let detector_map = DetectorMap::from_simulator(&simulator);
+        // the same ordering as the decoders, i.e. the nodes of the model graph
+        assert_eq!(detector_map.positions(), PositionIndexer::new(&simulator).stabilizers.positions());
+        // the map is self-describing through serialization
+        let serialized = serde_json::to_string(&detector_map).unwrap();
+        let deserialized: DetectorMap = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.positions(), detector_map.positions());
+        simulator.rng = Xoroshiro128StarStar::seed_from_u64(1731);
+        for _ in 0..100 {
+            simulator.generate_random_errors(&noise_model);
+            let sparse_measurement = simulator.generate_sparse_measurement();
+            let indices = sparse_measurement.to_detector_indices(&detector_map);
+            assert!(indices.windows(2).all(|window| window[0] < window[1]));
+            assert!(indices.iter().all(|&index| index < detector_map.len()));
+            let recovered = SparseMeasurement::from_detector_indices(&deserialized, &indices).unwrap();
+            assert_eq!(recovered.to_vec(), sparse_measurement.to_vec());
+        }
+        // invalid inputs are reported
+        assert!(SparseMeasurement::from_detector_indices(&detector_map, &[detector_map.len()]).is_err());
+        let mut positions = detector_map.positions().to_vec();
+        positions.swap(0, 1);
+        assert!(serde_json::from_value::<DetectorMap>(json!(positions)).is_err());
+    }
+}
@@ -0,0 +1,64 @@
+//! Prelude
+//!
+//! A stable facade of the simulation pipeline for downstream Rust projects: `use qecp::prelude::*;` is enough to build a code, apply a
+//! noise model, sample errors, decode and validate the correction, and visualize the result.
+//! The items re-exported here follow semantic versioning: their signatures only change with a major version bump,
+//! while the other modules may still be reorganized between minor versions.
+//!
+
+/// simulator of the circuit, use [`SimulatorGenerics`] to sample errors and validate corrections
+pub use crate::simulator::{Simulator, SimulatorGenerics, GeneralSimulator, Position, SparseErrorPattern, SparseMeasurement, SparseCorrection, SparseErasures, GateType};
+pub use crate::simulator_compact::SimulatorCompact;
+/// builtin code types and their sizes
+pub use crate::code_builder::{CodeType, CodeSize};
+/// noise model and the builder of commonly used noise models (the error model builder)
+pub use crate::noise_model::NoiseModel;
+pub use crate::noise_model_builder::NoiseModelBuilder;
+/// basic types of qubits, gates and errors
+pub use crate::types::{QubitType, ErrorType, LogicalOp, DecodingError};
+/// decoders, where [`BenchmarkDecoder`] names all the decoders and [`GeneralDecoder`] dispatches to them
+pub use crate::tool::{BenchmarkDecoder, GeneralDecoder};
+pub use crate::decoder_mwpm::MWPMDecoder;
+pub use crate::decoder_union_find::UnionFindDecoder;
+pub use crate::decoder_tailored_mwpm::TailoredMWPMDecoder;
+pub use crate::decoder_tailored_union_find::TailoredUnionFindDecoder;
+/// visualize the simulator and decoding cases in the frontend
+pub use crate::visualize::{Visualizer, QecpVisualizer};
+/// reproducible random number generator used by the simulator, seeded by [`SeedableRng::seed_from_u64`]
+pub use crate::reproducible_rand::Xoroshiro128StarStar;
+pub use crate::rand_core::SeedableRng;
+/// noise model and decoder configurations are given as JSON values
+pub use crate::serde_json::json;
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;  // only use the facade, just like a downstream project
+
+    /// the same as `examples/memory_experiment.rs` but smaller, to make sure the prelude exposes everything it needs
+    #[test]
+    fn prelude_memory_experiment() {  // cargo test prelude_memory_experiment -- --nocapture
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(3, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::DepolarizingNoise.apply(&mut simulator, &mut noise_model, &json!({}), 0.001, 0.5, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        let noise_model = std::sync::Arc::new(noise_model);
+        let mut decoder = UnionFindDecoder::new(&simulator, noise_model.clone(), &json!({}), 1, false);
+        simulator.rng = Xoroshiro128StarStar::seed_from_u64(0);
+        let mut qec_failed = 0;
+        for _ in 0..100 {
+            simulator.generate_random_errors(&noise_model);
+            let measurement: SparseMeasurement = simulator.generate_sparse_measurement();
+            let (correction, _runtime_statistics): (SparseCorrection, _) = decoder.decode(&measurement);
+            let (logical_i, logical_j) = simulator.validate_correction(&correction);
+            if logical_i || logical_j {
+                qec_failed += 1;
+            }
+        }
+        assert!(qec_failed < 10, "logical error rate should be small at p = 0.001");
+        // the decoder registry is also available
+        let mut general_decoder = GeneralDecoder::UnionFind(decoder);
+        let (correction, _) = general_decoder.decode_with_erasure(&SparseMeasurement::new(), &SparseErasures::new());
+        assert_eq!(correction.len(), 0);
+    }
+
+}
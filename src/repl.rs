@@ -0,0 +1,298 @@
+//! interactive shell for exploratory simulation
+//!
+//! `qecp repl` keeps a single [`Simulator`] with its [`NoiseModel`] and optionally a decoder; each command maps directly onto the
+//! existing APIs, so that one can inject errors, decode and validate the correction without writing Rust or Python code.
+//! When the standard input is not a terminal, the commands are read line by line and a transcript is printed, which is handy for scripting.
+//!
+
+use super::serde_json;
+use super::serde_json::json;
+use std::sync::Arc;
+use std::io::{BufRead, Write};
+use super::clap::ValueEnum;
+#[cfg(feature="readline")]
+use super::rustyline;
+use super::simulator::*;
+use super::code_builder::*;
+use super::noise_model::*;
+use super::noise_model_builder::*;
+use super::types::*;
+use super::tool::{BenchmarkDecoder, GeneralDecoder};
+use super::decoder_mwpm::*;
+use super::decoder_tailored_mwpm::*;
+use super::decoder_union_find::*;
+use super::decoder_tailored_union_find::*;
+use super::visualize::*;
+
+pub const REPL_HELP: &str = "commands:
+    build <code> <d> <T>          build a code with distance d and T noisy measurement rounds, e.g. `build standard-planar-code 3 0`
+    noise <builder> <p> [config]  set the noise model, where builder is `none` (depolarizing) or a noise model builder
+    inject <t> <i> <j> <P>        add a Pauli error P (X, Y or Z) at position (t, i, j)
+    clearerrors                   remove all errors
+    measure                       print the defect measurements
+    decode <decoder>              decode the defect measurements, e.g. `decode mwpm` or `decode union-find`
+    validate                      check whether the correction causes a logical error
+    dumpvis <file>                save the code, noise model and the current case to a visualization file
+    help                          print this message
+    exit                          quit the shell";
+
+/// the state of the shell
+pub struct Repl {
+    pub simulator: Option<Simulator>,
+    pub noise_model: Option<Arc<NoiseModel>>,
+    /// the decoder is built on the current noise model, thus cleared whenever the code or the noise model changes
+    pub decoder: Option<(BenchmarkDecoder, GeneralDecoder)>,
+    pub correction: Option<SparseCorrection>,
+}
+
+impl Repl {
+
+    pub fn new() -> Self {
+        Self {
+            simulator: None,
+            noise_model: None,
+            decoder: None,
+            correction: None,
+        }
+    }
+
+    /// execute a single line of command and return the output, which is empty for commands that only change the state
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() {
+            return Ok(String::new())
+        }
+        let arguments = &tokens[1..];
+        let expect_arguments = |minimum: usize, maximum: usize| -> Result<(), String> {
+            if arguments.len() < minimum || arguments.len() > maximum {
+                return Err(format!("wrong number of arguments for `{}`, run `help` for usage", tokens[0]))
+            }
+            Ok(())
+        };
+        match tokens[0] {
+            "help" => {
+                expect_arguments(0, 0)?;
+                Ok(REPL_HELP.to_string())
+            },
+            "build" => {
+                expect_arguments(3, 3)?;
+                let code_type = CodeType::from_str(arguments[0], true).map_err(|e| format!("unknown code: {e}"))?;
+                let d = parse_usize(arguments[1])?;
+                let noisy_measurements = parse_usize(arguments[2])?;
+                let mut simulator = Simulator::new(code_type, CodeSize::new(noisy_measurements, d, d));
+                let mut noise_model = NoiseModel::new(&simulator);
+                simulator.compress_error_rates(&mut noise_model);
+                let output = format!("built {:?} with d = {}, T = {}, height = {}", code_type, d, noisy_measurements, simulator.height);
+                self.simulator = Some(simulator);
+                self.noise_model = Some(Arc::new(noise_model));
+                self.decoder = None;
+                self.correction = None;
+                Ok(output)
+            },
+            "noise" => {
+                expect_arguments(2, usize::MAX)?;
+                let simulator = self.simulator.as_mut().ok_or(NO_CODE_MESSAGE)?;
+                let p = parse_f64(arguments[1])?;
+                if !(0. ..=1.).contains(&p) {
+                    return Err(format!("invalid error rate {p}"))
+                }
+                let noise_model_configuration: serde_json::Value = if arguments.len() > 2 {
+                    serde_json::from_str(&arguments[2..].join(" ")).map_err(|e| format!("invalid noise model configuration: {e}"))?
+                } else {
+                    json!({})
+                };
+                let mut noise_model = NoiseModel::new(simulator);
+                simulator.set_error_rates(&mut noise_model, p / 3., p / 3., p / 3., 0.);
+                if arguments[0] != "none" {
+                    let noise_model_builder = NoiseModelBuilder::from_str(arguments[0], true).map_err(|e| format!("unknown noise model builder: {e}"))?;
+                    noise_model_builder.apply(simulator, &mut noise_model, &noise_model_configuration, p, 0.5, 0.);
+                }
+                simulator.compress_error_rates(&mut noise_model);
+                self.noise_model = Some(Arc::new(noise_model));
+                self.decoder = None;
+                self.correction = None;
+                Ok(format!("noise model {} with p = {}", arguments[0], p))
+            },
+            "inject" => {
+                expect_arguments(4, 4)?;
+                let simulator = self.simulator.as_mut().ok_or(NO_CODE_MESSAGE)?;
+                let noise_model = self.noise_model.as_ref().unwrap();
+                let position = pos!(parse_usize(arguments[0])?, parse_usize(arguments[1])?, parse_usize(arguments[2])?);
+                let error = match arguments[3] {
+                    "X" | "x" => ErrorType::X,
+                    "Y" | "y" => ErrorType::Y,
+                    "Z" | "z" => ErrorType::Z,
+                    other => return Err(format!("unknown Pauli error {other}, should be X, Y or Z")),
+                };
+                if !simulator.is_node_exist(&position) {
+                    return Err(format!("no qubit at position {}", position))
+                }
+                let node = simulator.get_node_unwrap(&position);
+                let error = node.error.multiply(&error);
+                simulator.set_error_check_result(noise_model, &position, &error)?;
+                self.correction = None;
+                Ok(String::new())
+            },
+            "clearerrors" => {
+                expect_arguments(0, 0)?;
+                let simulator = self.simulator.as_mut().ok_or(NO_CODE_MESSAGE)?;
+                simulator.clear_all_errors();
+                self.correction = None;
+                Ok(String::new())
+            },
+            "measure" => {
+                expect_arguments(0, 0)?;
+                let sparse_measurement = self.measure()?;
+                Ok(format!("{} defects: {}", sparse_measurement.len(), json!(sparse_measurement)))
+            },
+            "decode" => {
+                expect_arguments(1, 1)?;
+                let decoder_type = BenchmarkDecoder::from_str(arguments[0], true).map_err(|e| format!("unknown decoder: {e}"))?;
+                let sparse_measurement = self.measure()?;
+                if !matches!(&self.decoder, Some((existing_type, _)) if existing_type == &decoder_type) {
+                    let simulator = self.simulator.as_ref().unwrap();
+                    let noise_model = self.noise_model.as_ref().unwrap();
+                    let general_decoder = match decoder_type {
+                        BenchmarkDecoder::MWPM => GeneralDecoder::MWPM(MWPMDecoder::new(simulator, noise_model.clone(), &json!({}), 1, false)),
+                        BenchmarkDecoder::TailoredMWPM => {
+                            GeneralDecoder::TailoredMWPM(TailoredMWPMDecoder::new(simulator, noise_model.clone(), &json!({}), 1, false))
+                        },
+                        BenchmarkDecoder::UnionFind => GeneralDecoder::UnionFind(UnionFindDecoder::new(simulator, noise_model.clone(), &json!({}), 1, false)),
+                        BenchmarkDecoder::TailoredUnionFind => {
+                            GeneralDecoder::TailoredUnionFind(TailoredUnionFindDecoder::new(simulator, noise_model.clone(), &json!({}), 1, false))
+                        },
+                        _ => return Err(format!("decoder `{}` is not supported in the shell", arguments[0])),
+                    };
+                    self.decoder = Some((decoder_type, general_decoder));
+                }
+                let (_, general_decoder) = self.decoder.as_mut().unwrap();
+                let sparse_detected_erasures = self.simulator.as_ref().unwrap().generate_sparse_detected_erasures();
+                let (correction, _runtime_statistics) = general_decoder.decode_with_erasure(&sparse_measurement, &sparse_detected_erasures);
+                let output = format!("correction: {}", json!(correction));
+                self.correction = Some(correction);
+                Ok(output)
+            },
+            "validate" => {
+                expect_arguments(0, 0)?;
+                let simulator = self.simulator.as_mut().ok_or(NO_CODE_MESSAGE)?;
+                let correction = self.correction.as_ref().ok_or("no correction, run `decode` first")?;
+                let (logical_i, logical_j) = simulator.validate_correction(correction);
+                Ok(format!("{}: logical_i = {}, logical_j = {}", if logical_i || logical_j { "logical error" } else { "success" }, logical_i, logical_j))
+            },
+            "dumpvis" => {
+                expect_arguments(1, 1)?;
+                let sparse_measurement = self.measure()?;
+                let simulator = self.simulator.as_ref().unwrap();
+                let noise_model = self.noise_model.as_ref().unwrap();
+                let mut visualizer = Visualizer::new(Some(arguments[0].to_string())).map_err(|e| e.to_string())?;
+                visualizer.add_component(simulator).map_err(|e| e.to_string())?;
+                visualizer.add_component(noise_model.as_ref()).map_err(|e| e.to_string())?;
+                visualizer.end_component().map_err(|e| e.to_string())?;
+                let mut case = json!({
+                    "error_pattern": simulator.generate_sparse_error_pattern(),
+                    "measurement": sparse_measurement,
+                });
+                if let Some(correction) = &self.correction {
+                    case["correction"] = json!(correction);
+                }
+                visualizer.add_case(case).map_err(|e| e.to_string())?;
+                Ok(format!("saved to {}", arguments[0]))
+            },
+            command => Err(format!("unknown command `{command}`, run `help` for usage")),
+        }
+    }
+
+    /// propagate the injected errors and generate the defect measurements
+    fn measure(&mut self) -> Result<SparseMeasurement, String> {
+        let simulator = self.simulator.as_mut().ok_or(NO_CODE_MESSAGE)?;
+        simulator.clear_propagate_errors();
+        simulator.propagate_errors();
+        Ok(simulator.generate_sparse_measurement())
+    }
+
+    /// run commands line by line until `exit` or the end of the input, printing each command followed by its output
+    pub fn run_script(&mut self, input: impl BufRead, output: &mut impl Write) -> std::io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            writeln!(output, "> {}", line)?;
+            if line == "exit" {
+                break
+            }
+            match self.execute(line) {
+                Ok(result) => if !result.is_empty() { writeln!(output, "{}", result)?; },
+                Err(message) => { writeln!(output, "[error] {}", message)?; },
+            }
+        }
+        Ok(())
+    }
+
+    /// run the interactive shell with line editing and history
+    #[cfg(feature="readline")]
+    pub fn run_interactive(&mut self) -> rustyline::Result<()> {
+        let mut editor = rustyline::DefaultEditor::new()?;
+        println!("QECP interactive shell, run `help` for the list of commands");
+        loop {
+            let line = match editor.readline("qecp> ") {
+                Ok(line) => line,
+                Err(rustyline::error::ReadlineError::Interrupted) | Err(rustyline::error::ReadlineError::Eof) => break,
+                Err(error) => return Err(error),
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue
+            }
+            editor.add_history_entry(line)?;
+            if line == "exit" {
+                break
+            }
+            match self.execute(line) {
+                Ok(result) => if !result.is_empty() { println!("{}", result); },
+                Err(message) => { println!("[error] {}", message); },
+            }
+        }
+        Ok(())
+    }
+
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+const NO_CODE_MESSAGE: &str = "no code, run `build` first";
+
+fn parse_usize(value: &str) -> Result<usize, String> {
+    value.parse().map_err(|_| format!("invalid integer {value}"))
+}
+
+fn parse_f64(value: &str) -> Result<f64, String> {
+    value.parse().map_err(|_| format!("invalid number {value}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repl_golden_transcript() {  // cargo test repl_golden_transcript -- --nocapture
+        // the golden transcript contains the commands prefixed by `> ` followed by their outputs
+        let golden = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/repl_golden_transcript.txt"));
+        let visualize_filepath = std::env::temp_dir().join("qecp_repl_golden_transcript.json").to_str().unwrap().to_string();
+        let golden = golden.replace("<visualize_filepath>", &visualize_filepath);
+        let script: String = golden.lines().filter_map(|line| line.strip_prefix("> ")).map(|line| format!("{}\n", line)).collect();
+        let mut repl = Repl::new();
+        let mut output = Vec::new();
+        repl.run_script(script.as_bytes(), &mut output).unwrap();
+        let transcript = String::from_utf8(output).unwrap();
+        println!("{}", transcript);
+        assert_eq!(transcript, golden);
+        assert!(std::fs::metadata(&visualize_filepath).unwrap().len() > 0);
+    }
+
+}
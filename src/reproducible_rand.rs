@@ -43,6 +43,53 @@ impl Xoroshiro128StarStar {
         let mut rng = thread_rng();
         Self::seed_from_u64(rng.gen::<u64>())
     }
+
+    /// equivalent to 2^64 calls to `next_u64`, which splits the sequence into 2^64 non-overlapping streams, e.g. one per shot;
+    /// the polynomial is the same as `rand_xoshiro::Xoroshiro128StarStar::jump`
+    pub fn jump(&mut self) {
+        const JUMP: [u64; 2] = [0xdf900294d8f554a5, 0x170865df4b3201fc];
+        let mut s0 = 0;
+        let mut s1 = 0;
+        for jump in JUMP.iter() {
+            for b in 0..64 {
+                if (jump & 1 << b) != 0 {
+                    s0 ^= self.s0;
+                    s1 ^= self.s1;
+                }
+                self.next_u64();
+            }
+        }
+        self.s0 = s0;
+        self.s1 = s1;
+    }
+
+    /// equivalent to calling [`Xoroshiro128StarStar::jump`] `count` times in O(log(count)) time: the jump is linear in the 128-bit state,
+    /// so its matrix over GF(2) is raised to the power of `count` by repeated squaring
+    pub fn jump_n(&mut self, count: u64) {
+        // a 128x128 matrix over GF(2) is stored as the images of the 128 basis states
+        let to_bits = |rng: &Self| -> u128 { rng.s0 as u128 | (rng.s1 as u128) << 64 };
+        let from_bits = |bits: u128| -> Self { Self { s0: bits as u64, s1: (bits >> 64) as u64 } };
+        let apply = |matrix: &[u128], bits: u128| -> u128 {
+            (0..128).filter(|k| bits >> k & 1 != 0).fold(0, |image, k| image ^ matrix[k])
+        };
+        let mut power: Vec<u128> = (0..128).map(|k| {
+            let mut basis = from_bits(1 << k);
+            basis.jump();
+            to_bits(&basis)
+        }).collect();
+        let mut bits = to_bits(self);
+        let mut count = count;
+        while count > 0 {
+            if count & 1 != 0 {
+                bits = apply(&power, bits);
+            }
+            count >>= 1;
+            if count > 0 {
+                power = power.iter().map(|&column| apply(&power, column)).collect();
+            }
+        }
+        *self = from_bits(bits);
+    }
 }
 
 impl RngCore for Xoroshiro128StarStar {
@@ -175,3 +222,25 @@ impl SplitMix64 {
     }
 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reproducible_rand_jump_n() {  // cargo test reproducible_rand_jump_n -- --nocapture
+        let base = Xoroshiro128StarStar::seed_from_u64(1752);
+        let mut jumped = base.clone();
+        for count in 0..40 {
+            let mut fast = base.clone();
+            fast.jump_n(count);
+            assert_eq!(fast, jumped, "jump_n({}) should be the same as {} jumps", count, count);
+            jumped.jump();
+        }
+        // the streams after each jump are different from each other
+        let mut first = base.clone();
+        let mut second = base.clone();
+        second.jump();
+        assert_ne!(first.next_u64(), second.next_u64());
+    }
+}
@@ -18,6 +18,8 @@ use super::serde_hashkey;
 use super::erasure_graph::*;
 use crate::visualize::*;
 use crate::simulator_compact::*;
+use crate::artifact::ArtifactKind;
+use crate::position_indexer::DetectorMap;
 
 
 #[enum_dispatch]
@@ -28,6 +30,17 @@ pub enum GeneralSimulator {
     Simulator,
 }
 
+impl GeneralSimulator {
+    /// replace the random number generator, e.g. with a seeded one to reproduce the samples
+    pub fn set_rng(&mut self, rng: Xoroshiro128StarStar) {
+        match self {
+            Self::SimulatorCompactCompressed(simulator) => simulator.extender.base.rng = rng,
+            Self::SimulatorCompact(simulator) => simulator.rng = rng,
+            Self::Simulator(simulator) => simulator.rng = rng,
+        }
+    }
+}
+
 #[enum_dispatch(GeneralSimulator)]
 /// any struct that implements this generic can be used in the simulation cli
 pub trait SimulatorGenerics: Clone {
@@ -36,6 +49,18 @@ pub trait SimulatorGenerics: Clone {
     fn generate_sparse_error_pattern(&self) -> SparseErrorPattern;
     fn generate_sparse_measurement(&self) -> SparseMeasurement;
     fn validate_correction(&mut self, correction: &SparseCorrection) -> (bool, bool);
+    /// the logical class `(logical_i, logical_j)` of the errors (the truth) and the one claimed by the correction, separately;
+    /// their XOR is the result of [`SimulatorGenerics::validate_correction`], e.g. a chained experiment can track the decoded logical Pauli frame
+    fn validate_correction_with_logical_classes(&mut self, correction: &SparseCorrection) -> ((bool, bool), (bool, bool)) {
+        let true_class = self.validate_correction(&SparseCorrection::new());
+        let (logical_i, logical_j) = self.validate_correction(correction);
+        (true_class, (logical_i ^ true_class.0, logical_j ^ true_class.1))
+    }
+    /// the result of [`SimulatorGenerics::validate_correction`] of each logical qubit separately, for codes encoding two logical qubits
+    /// like [`CodeType::StandardToricCode`]; codes encoding a single logical qubit never fail the second one
+    fn validate_correction_multi(&mut self, correction: &SparseCorrection) -> ((bool, bool), (bool, bool)) {
+        (self.validate_correction(correction), (false, false))
+    }
 }
 
 #[cfg(feature="python_binding")]
@@ -55,6 +80,14 @@ macro_rules! bind_trait_simulator_generics {
             fn trait_generate_sparse_measurement(&mut self) -> SparseMeasurement { self.generate_sparse_measurement() }
             #[pyo3(name = "validate_correction")]
             fn trait_validate_correction(&mut self, correction: &SparseCorrection) -> (bool, bool) { self.validate_correction(correction) }
+            #[pyo3(name = "validate_correction_with_logical_classes")]
+            fn trait_validate_correction_with_logical_classes(&mut self, correction: &SparseCorrection) -> ((bool, bool), (bool, bool)) {
+                self.validate_correction_with_logical_classes(correction)
+            }
+            #[pyo3(name = "validate_correction_multi")]
+            fn trait_validate_correction_multi(&mut self, correction: &SparseCorrection) -> ((bool, bool), (bool, bool)) {
+                self.validate_correction_multi(correction)
+            }
         }
     };
 }
@@ -81,11 +114,21 @@ pub struct Simulator {
     pub horizontal: usize,
     /// nodes array, because some rotated code can easily have more than half of the nodes non-existing, existing nodes are stored on heap
     pub nodes: Vec::< Vec::< Vec::< Option<Box <SimulatorNode> > > > >,
-    /// use embedded random number generator
+    /// use embedded random number generator; it advances sequentially across [`SimulatorGenerics::generate_random_errors`] and
+    /// [`Simulator::sample_batch`], but is NOT copied by [`Clone::clone`] (see the `Clone` implementation), only by [`Simulator::deep_clone_with_rng`]
     pub rng: Xoroshiro128StarStar,
     /// how many cycles is there a round of measurements; default to 1
     #[cfg_attr(feature = "python_binding", pyo3(get, set))]
     pub measurement_cycles: usize,
+    /// the expected logical flips `(logical_i, logical_j)` caused by [`Simulator::apply_transversal_logical`], cleared with the errors
+    #[cfg_attr(feature = "python_binding", pyo3(get, set))]
+    pub applied_logical: (bool, bool),
+    /// the logical operators used to validate corrections of a [`CodeType::Customized`] code, see [`CustomizedLogicalOperators`]
+    pub customized_logical_operators: Option<CustomizedLogicalOperators>,
+    /// the final measurement round is noisy and ends with a transversal readout of the data qubits, instead of being a perfect measurement round;
+    /// the stabilizer outcomes of the top layer are then reconstructed from the data readout, see [`Simulator::stabilizer_outcome`]
+    #[cfg_attr(feature = "python_binding", pyo3(get, set))]
+    pub noisy_final_round: bool,
 }
 
 impl QecpVisualizer for Simulator {
@@ -319,6 +362,9 @@ impl GateType {
 #[cfg(feature="python_binding")]
 bind_trait_simulator_generics!{Simulator}
 
+/// cloning a simulator resets its random number generator with a fresh random seed, so that the clones distributed to parallel threads
+/// sample independent errors; to reproduce a sequence of samples from a seeded simulator, sample sequentially from the same instance
+/// or use [`Simulator::deep_clone_with_rng`] instead
 impl Clone for Simulator {
     fn clone(&self) -> Self {
         Self {
@@ -330,6 +376,9 @@ impl Clone for Simulator {
             nodes: self.nodes.clone(),
             rng: Xoroshiro128StarStar::new(),  // do not copy random number generator, otherwise parallel simulation may give same result
             measurement_cycles: self.measurement_cycles,
+            applied_logical: self.applied_logical,
+            customized_logical_operators: self.customized_logical_operators.clone(),
+            noisy_final_round: self.noisy_final_round,
         }
     }
 }
@@ -349,6 +398,9 @@ impl Simulator {
             nodes: Vec::new(),
             rng: Xoroshiro128StarStar::new(),
             measurement_cycles: 1,
+            applied_logical: (false, false),
+            customized_logical_operators: None,
+            noisy_final_round: false,
         };
         build_code(&mut simulator);
         simulator
@@ -363,10 +415,58 @@ impl Simulator {
        Clone::clone(self)
     }
 
+    /// a true deep copy that also copies the state of the random number generator, unlike [`Clone::clone`] which reseeds it;
+    /// use this when the copy must reproduce exactly the same random errors as the original, e.g. to snapshot a simulation and rerun it
+    pub fn deep_clone_with_rng(&self) -> Self {
+        let mut simulator = Clone::clone(self);
+        simulator.rng = self.rng.clone();
+        simulator
+    }
+
+    /// seed the random number generator, so that the same seed, code and noise model sample exactly the same sequence of errors,
+    /// even across processes; this is the way to replay a failing shot found by a seeded benchmark
+    pub fn set_rng_seed(&mut self, seed: u64) {
+        use crate::rand_core::SeedableRng;
+        self.rng = Xoroshiro128StarStar::seed_from_u64(seed);
+    }
+
     pub fn volume(&self) -> usize {
         self.height * self.vertical * self.horizontal
     }
 
+    /// the number of logical qubits encoded by the logical operators of the code, i.e. half of the rank of their
+    /// anticommutation matrix over GF(2); return 0 if the code type doesn't define logical operators
+    pub fn logical_operator_count(&self) -> usize {
+        let logical_operators = match code_builder_logical_operators(self) {
+            Some(logical_operators) => logical_operators,
+            None => return 0,
+        };
+        let mut matrix: Vec<Vec<bool>> = logical_operators.iter().map(|operator_1| {
+            logical_operators.iter().map(|operator_2| operator_1.anticommute(operator_2)).collect()
+        }).collect();
+        // Gaussian elimination over GF(2)
+        let size = matrix.len();
+        let mut rank = 0;
+        for column in 0..size {
+            let pivot = match (rank..size).find(|&row| matrix[row][column]) {
+                Some(pivot) => pivot,
+                None => continue,
+            };
+            matrix.swap(rank, pivot);
+            let pivot_row = matrix[rank].clone();
+            for (row, entries) in matrix.iter_mut().enumerate() {
+                if row != rank && entries[column] {
+                    for (entry, &pivot_entry) in entries.iter_mut().zip(pivot_row.iter()) {
+                        *entry ^= pivot_entry;
+                    }
+                }
+            }
+            rank += 1;
+        }
+        debug_assert!(rank % 2 == 0, "the anticommutation matrix is symplectic and must have even rank");
+        rank / 2
+    }
+
     /// judge if `[t][i][j]` is valid index of `self.nodes`
     #[inline]
     pub fn is_valid_position(&self, position: &Position) -> bool {
@@ -405,7 +505,7 @@ impl Simulator {
         noise_model_node.pauli_error_rates.error_rate_Z = pz;
         noise_model_node.erasure_error_rate = pe;
         let noise_model_node = Arc::new(noise_model_node);
-        for t in 0 .. self.height - self.measurement_cycles {
+        for t in 0 .. self.perfect_measurement_start() {
             simulator_iter_mut_real!(self, position, node, t => t, {  // only add errors on real node
                 // bug fix 2022.11.12: the first layer default to no measurement errors
                 if t != 0 || node.qubit_type == QubitType::Data {
@@ -415,6 +515,11 @@ impl Simulator {
         }
     }
 
+    /// the first layer of the final perfect measurement rounds (see [`CodeSize::final_perfect_rounds`]), no error is allowed at or above it
+    pub fn perfect_measurement_start(&self) -> usize {
+        self.height - self.measurement_cycles * self.code_size.final_perfect_rounds
+    }
+
     /// set error with sanity check
     pub fn set_error_check(&mut self, noise_model: &NoiseModel, position: &Position, error: &ErrorType) {
         self.set_error_check_result(noise_model, position, error).unwrap()
@@ -476,6 +581,24 @@ impl Simulator {
             node.has_erasure = false;
             node.propagated = I;
         });
+        self.applied_logical = (false, false);
+    }
+
+    /// apply a logical Pauli operator on the data qubits right after the gates at layer `t`, on top of the existing errors;
+    /// the operator commutes with all stabilizers so the syndrome stays clean as long as `t` is not in the middle of a measurement round,
+    /// while the expected logical flip is recorded in `applied_logical`.
+    /// call [`Simulator::clear_propagate_errors`] and [`Simulator::propagate_errors`] afterwards to update the measurements
+    pub fn apply_transversal_logical(&mut self, t: usize, logical: LogicalOp) {
+        assert!(t < self.height - 1, "logical operator must be applied before the top layer");
+        let operator = code_builder_transversal_logical(self, logical).expect("transversal logical operator not supported for this code");
+        for (position, error) in operator.iter() {
+            let node = self.get_node_mut_unwrap(&pos!(t, position.i, position.j));
+            node.set_error_temp(&node.error.multiply(error));
+        }
+        match logical {
+            LogicalOp::X => { self.applied_logical.1 ^= true; },
+            LogicalOp::Z => { self.applied_logical.0 ^= true; },
+        }
     }
 
     /// must be called before `propagate_errors` to ensure correctness, note that `generate_random_errors` already does this
@@ -502,7 +625,22 @@ impl Simulator {
             }
             propagated_clean
         });
+        self.propagate_errors_with_feedforwards(&[]);
+    }
+
+    /// propagate errors while applying the measurement-conditioned Pauli operators of [`Feedforward`], requiring a clean propagate state; the outcome of the triggering measurement
+    /// is final once the layer before it has been propagated, so the feedforward is applied right before propagating the layer `trigger.t + 1`
+    pub fn propagate_errors_with_feedforwards(&mut self, feedforwards: &[Feedforward]) {
         for t in 0..self.height - 1 {
+            for feedforward in feedforwards.iter().filter(|feedforward| feedforward.trigger.t + 1 == t) {
+                let trigger_node = self.get_node_unwrap(&feedforward.trigger);
+                if trigger_node.gate_type.stabilizer_measurement(&trigger_node.propagated) {  // measurement outcome is -1
+                    for (position, error) in feedforward.pauli_errors.iter() {
+                        let node = self.get_node_mut_unwrap(position);
+                        node.propagated = node.propagated.multiply(error);
+                    }
+                }
+            }
             simulator_iter!(self, position, _node, t => t, {
                 self.propagate_error_from(position);
             });
@@ -544,6 +682,52 @@ impl Simulator {
         None
     }
 
+    /// the outcome of the stabilizer measurement at `position`; if [`Simulator::noisy_final_round`] is set, the outcomes of the top layer are
+    /// not read from the (noisy) ancilla but reconstructed from the final data readout, so the detectors of the last round compare the
+    /// ancilla outcomes of the previous round against the parity of the data qubits
+    #[inline]
+    pub fn stabilizer_outcome(&self, position: &Position) -> bool {
+        let node = self.get_node_unwrap(position);
+        if self.noisy_final_round && position.t == self.height - 1 {
+            return node.gate_type.stabilizer_measurement(&self.data_readout_error(position))
+        }
+        node.gate_type.stabilizer_measurement(&node.propagated)
+    }
+
+    /// the error that the data qubits at the top layer would propagate to the ancilla of a top-layer measurement through noiseless two-qubit
+    /// gates of the final round, i.e. the stabilizer parity computed from the data readout
+    fn data_readout_error(&self, position: &Position) -> ErrorType {
+        let top_t = self.height - 1;
+        let mut error = I;
+        for t in position.t + 1 - self.measurement_cycles .. position.t {
+            let node = self.get_node_unwrap(&pos!(t, position.i, position.j));
+            if node.gate_type.is_two_qubit_gate() {
+                let peer_position = node.gate_peer.as_ref().expect("two-qubit gate must have a peer");
+                let peer_node = self.get_node_unwrap(peer_position);
+                if peer_node.is_virtual && !peer_node.is_peer_virtual {  // propagation from virtual to real is forbidden
+                    continue
+                }
+                let data_error = &self.get_node_unwrap(&pos!(top_t, peer_position.i, peer_position.j)).propagated;
+                error = error.multiply(&peer_node.gate_type.propagate_peer(data_error));
+            }
+        }
+        error
+    }
+
+    /// the ancilla positions `(i, j)` whose top-layer outcome reconstructed from the data readout depends on the data qubit at `(i, j)`,
+    /// see [`Simulator::stabilizer_outcome`]
+    fn data_readout_ancillas(&self, i: usize, j: usize) -> Vec<(usize, usize)> {
+        let top_t = self.height - 1;
+        (top_t + 1 - self.measurement_cycles .. top_t).filter_map(|t| {
+            let node = self.get_node_unwrap(&pos!(t, i, j));
+            if node.qubit_type == QubitType::Data && node.gate_type.is_two_qubit_gate() {
+                node.gate_peer.as_ref().map(|peer| (peer.i, peer.j))
+            } else {
+                None
+            }
+        }).collect()
+    }
+
     /// including virtual measurements in the result as an extension to [`Simulator::generate_sparse_measurement`]
     #[inline(never)]
     pub fn generate_sparse_measurement_virtual(&self) -> SparseMeasurement {
@@ -552,21 +736,9 @@ impl Simulator {
             // only iterate over virtual stabilizers, excluding those real stabilizers
             simulator_iter_virtual!(self, position, node, t => t, {
                 if node.gate_type.is_measurement() {
-                    let this_result = node.gate_type.stabilizer_measurement(&node.propagated);
-                    let mut previous_position = position.clone();
-                    loop {  // usually this loop execute only once because the previous measurement is found immediately
-                        debug_assert!(previous_position.t >= self.measurement_cycles, "cannot find the previous measurement cycle");
-                        previous_position.t -= self.measurement_cycles;
-                        let previous_node = self.get_node_unwrap(&previous_position);
-                        if previous_node.gate_type.is_measurement() {  // found previous measurement
-                            let previous_result = previous_node.gate_type.stabilizer_measurement(&previous_node.propagated);
-                            if this_result != previous_result {
-                                sparse_measurement_virtual.insert_defect_measurement(position);
-                            }
-                            break
-                        }
-                        // println!("[warning] no measurement found in previous round, continue searching...")
-                        // Yue 2022.7.11 removed warning, because some code may just remove measurement in the middle
+                    let this_result = self.stabilizer_outcome(position);
+                    if this_result != self.previous_stabilizer_result(position) {
+                        sparse_measurement_virtual.insert_defect_measurement(position);
                     }
                 }
             });
@@ -574,6 +746,174 @@ impl Simulator {
         sparse_measurement_virtual
     }
 
+    /// the boundary that the data qubit at `position` (of any `t`) sits on, found from its virtual stabilizer neighbors, i.e. the gate peers
+    /// within a measurement cycle; `None` for bulk data qubits and ancilla qubits, and also for codes whose stabilizers are not CSS type
+    pub fn boundary_type_of(&self, position: &Position) -> Option<BoundaryType> {
+        if !self.is_node_exist(position) || self.get_node_unwrap(position).qubit_type != QubitType::Data {
+            return None
+        }
+        let mut next_to_x = false;
+        let mut next_to_z = false;
+        for t in 0..std::cmp::min(self.height, self.measurement_cycles + 1) {
+            let node = self.get_node_unwrap(&pos!(t, position.i, position.j));
+            if let Some(peer_position) = node.gate_peer.as_ref() {
+                let peer_node = self.get_node_unwrap(peer_position);
+                if peer_node.is_virtual {
+                    match peer_node.qubit_type {
+                        QubitType::StabX => { next_to_x = true; }
+                        QubitType::StabZ => { next_to_z = true; }
+                        _ => { return None }
+                    }
+                }
+            }
+        }
+        match (next_to_x, next_to_z) {
+            (true, true) => Some(BoundaryType::Corner),
+            (true, false) => Some(BoundaryType::Rough),
+            (false, true) => Some(BoundaryType::Smooth),
+            (false, false) => None,
+        }
+    }
+
+    /// the outcome of the previous measurement of the stabilizer at `position`, which might not be in the last round because some codes
+    /// remove measurements in the middle; if the stabilizer is never measured before, e.g. a customized code missing the first measurements,
+    /// it's compared against its deterministic initial value (no error) instead of searching below `t = 0`
+    fn previous_stabilizer_result(&self, position: &Position) -> bool {
+        let mut previous_position = position.clone();
+        while let Some(previous_t) = previous_position.t.checked_sub(self.measurement_cycles) {
+            previous_position.t = previous_t;
+            let previous_node = self.get_node_unwrap(&previous_position);
+            if previous_node.gate_type.is_measurement() {
+                return previous_node.gate_type.stabilizer_measurement(&previous_node.propagated)
+            }
+        }
+        false
+    }
+
+    /// check that every measurement is in the same basis as the previous initialization of the same qubit; this is checked by
+    /// [`code_builder_sanity_check`] when the code is built, but the gates can be modified afterwards, so call it before sampling
+    pub fn check_basis_consistency(&self) -> Result<(), String> {
+        simulator_iter!(self, base_position, _base_node, t => 0, {
+            let mut previous_initialization = GateType::None;
+            for t in 1..self.height {
+                let position = &mut base_position.clone();
+                position.t = t;
+                let node = self.get_node_unwrap(position);
+                if node.gate_type.is_initialization() {
+                    previous_initialization = node.gate_type;
+                }
+                if node.gate_type.is_measurement() && !node.gate_type.is_corresponding_initialization(&previous_initialization) {
+                    return Err(format!("measurement and initialization not in the same basis: node {} has gate type {:?} but previous initialization is {:?}"
+                        , position, node.gate_type, previous_initialization))
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// check that the real and virtual defect measurements together account for all the stabilizer flips, i.e. the parity of the defects of
+    /// every stabilizer over all the rounds equals its final outcome reconstructed from the propagated errors of the data qubits at the top
+    /// layer; the errors should be propagated, and the final measurement round should be perfect
+    pub fn check_virtual_consistency(&self, sparse_measurement: &SparseMeasurement, sparse_measurement_virtual: &SparseMeasurement) -> Result<(), String> {
+        let mut defect_parity: BTreeMap<(usize, usize), bool> = BTreeMap::new();
+        for (defects, is_virtual) in [(sparse_measurement, false), (sparse_measurement_virtual, true)] {
+            for position in defects.iter() {
+                if !self.is_node_exist(position) || !self.get_node_unwrap(position).gate_type.is_measurement() {
+                    return Err(format!("defect measurement at {} is not a stabilizer measurement", position))
+                }
+                if self.get_node_unwrap(position).is_virtual != is_virtual {
+                    return Err(format!("defect measurement at {} is reported as {} but the stabilizer is {}", position
+                        , if is_virtual { "virtual" } else { "real" }, if is_virtual { "real" } else { "virtual" }))
+                }
+                let parity = defect_parity.entry((position.i, position.j)).or_insert(false);
+                *parity = !*parity;
+            }
+        }
+        let top_t = self.height - 1;
+        simulator_iter!(self, position, node, t => top_t, if node.gate_type.is_measurement() {
+            let readout_result = node.gate_type.stabilizer_measurement(&self.data_readout_error(position));
+            let parity = defect_parity.remove(&(position.i, position.j)).unwrap_or(false);
+            if parity != readout_result {
+                return Err(format!("the {} stabilizer at {} has {} number of defects but its outcome from the data readout is {}"
+                    , if node.is_virtual { "virtual" } else { "real" }, position, if parity { "odd" } else { "even" }, readout_result))
+            }
+        });
+        if let Some(((i, j), _)) = defect_parity.iter().find(|(_, parity)| **parity) {
+            return Err(format!("the stabilizer at ({}, {}) has odd number of defects but is not measured at the top layer", i, j))
+        }
+        Ok(())
+    }
+
+    /// the fixed order of real stabilizers used by [`Simulator::round_bitstrings`], given by their positions in the first round of measurement;
+    /// note that the rows of the check matrix of `tool export_pymatching` (see [`crate::model_graph::ModelGraph::check_matrix`]) follow the
+    /// detector order of [`crate::position_indexer::DetectorMap`] instead, which covers every measurement round
+    pub fn stabilizer_order(&self) -> Vec<Position> {
+        let mut stabilizers = Vec::new();
+        simulator_iter_real!(self, position, node, t => self.measurement_cycles, {
+            if node.gate_type.is_measurement() {
+                stabilizers.push(position.clone());
+            }
+        });
+        stabilizers
+    }
+
+    /// the real detectors (measurement nodes) that can be lost in the noisy measurement rounds, excluding the final perfect measurement rounds
+    pub fn noisy_detector_positions(&self) -> Vec<Position> {
+        let mut detectors = Vec::new();
+        for t in (self.measurement_cycles..self.perfect_measurement_start()).step_by(self.measurement_cycles) {
+            simulator_iter_real!(self, position, node, t => t, {
+                if node.gate_type.is_measurement() {
+                    detectors.push(position.clone());
+                }
+            });
+        }
+        detectors
+    }
+
+    /// the physical ancilla qubit `(i, j)` of every real detector (measurement node) of all the measurement rounds, e.g. to map the defects to the
+    /// hardware; the ancilla of a detector stays at the same location in every round
+    pub fn detector_to_ancilla_map(&self) -> BTreeMap<Position, (usize, usize)> {
+        let mut map = BTreeMap::new();
+        for t in (self.measurement_cycles..self.height).step_by(self.measurement_cycles) {
+            simulator_iter_real!(self, position, node, t => t, {
+                if node.gate_type.is_measurement() {
+                    debug_assert!(node.qubit_type != QubitType::Data, "detector {} should be measured by an ancilla qubit", position);
+                    map.insert(position.clone(), (position.i, position.j));
+                }
+            });
+        }
+        map
+    }
+
+    /// the raw stabilizer measurement outcomes of each round (including the perfect round at `t = 0`) in the order of [`Simulator::stabilizer_order`];
+    /// a stabilizer not measured in some round repeats its previous outcome, so that XORing consecutive rounds gives exactly the defects
+    pub fn round_bitstrings(&self) -> Vec<Vec<bool>> {
+        let stabilizers = self.stabilizer_order();
+        let mut round_bitstrings: Vec<Vec<bool>> = Vec::new();
+        for t in (0..self.height).step_by(self.measurement_cycles) {
+            let bitstring = stabilizers.iter().enumerate().map(|(idx, stabilizer)| {
+                let position = pos!(t, stabilizer.i, stabilizer.j);
+                if self.get_node_unwrap(&position).gate_type.is_measurement() {
+                    self.stabilizer_outcome(&position)
+                } else {
+                    round_bitstrings.last().expect("the first round must be measured")[idx]
+                }
+            }).collect();
+            round_bitstrings.push(bitstring);
+        }
+        round_bitstrings
+    }
+
+    /// sample `shots` error patterns in a batch, returning the sparse error pattern, detected erasures and defect measurement of each shot;
+    /// the embedded random number generator advances sequentially, so the result is exactly the same as calling
+    /// [`SimulatorGenerics::generate_random_errors`] `shots` times on this simulator, and the simulator is left with the errors of the last shot
+    pub fn sample_batch(&mut self, noise_model: &NoiseModel, shots: usize) -> Vec<(SparseErrorPattern, SparseErasures, SparseMeasurement)> {
+        (0..shots).map(|_| {
+            self.generate_random_errors(noise_model);
+            (self.generate_sparse_error_pattern(), self.generate_sparse_detected_erasures(), self.generate_sparse_measurement())
+        }).collect()
+    }
+
     #[inline(never)]
     pub fn fast_measurement_given_few_errors(&mut self, sparse_errors: &SparseErrorPattern) -> (SparseCorrection, SparseMeasurement, SparseMeasurement) {
         if sparse_errors.len() == 0 {
@@ -625,30 +965,25 @@ impl Simulator {
                 if t > max_t {  // accumulate only after the reaching the original max_t
                     accumulated_clean_measurements += 1;
                 }
-                for &(i, j) in interested_region.iter() {
+                // the data readout also reaches the ancillas that the errors never propagated to
+                let mut readout_region: BTreeSet<(usize, usize)> = BTreeSet::new();
+                if self.noisy_final_round && t == self.height - 1 {
+                    for &(i, j) in interested_region.iter() {
+                        readout_region.extend(self.data_readout_ancillas(i, j).into_iter().filter(|ancilla| !interested_region.contains(ancilla)));
+                    }
+                }
+                for &(i, j) in interested_region.iter().chain(readout_region.iter()) {
                     let position = &pos!(t, i, j);
                     let node = self.get_node_unwrap(position);
                     if node.gate_type.is_measurement() {
-                        let this_result = node.gate_type.stabilizer_measurement(&node.propagated);
-                        let mut previous_position = position.clone();
-                        loop {  // usually this loop execute only once because the previous measurement is found immediately
-                            debug_assert!(previous_position.t >= self.measurement_cycles, "cannot find the previous measurement cycle");
-                            previous_position.t -= self.measurement_cycles;
-                            let previous_node = self.get_node_unwrap(&previous_position);
-                            if previous_node.gate_type.is_measurement() {  // found previous measurement
-                                let previous_result = previous_node.gate_type.stabilizer_measurement(&previous_node.propagated);
-                                if this_result != previous_result {
-                                    if node.is_virtual {
-                                        sparse_measurement_virtual.insert_defect_measurement(position);
-                                    } else {
-                                        sparse_measurement_real.insert_defect_measurement(position);
-                                    }
-                                    accumulated_clean_measurements = 0;
-                                }
-                                break
+                        let this_result = self.stabilizer_outcome(position);
+                        if this_result != self.previous_stabilizer_result(position) {
+                            if node.is_virtual {
+                                sparse_measurement_virtual.insert_defect_measurement(position);
+                            } else {
+                                sparse_measurement_real.insert_defect_measurement(position);
                             }
-                            // println!("[warning] no measurement found in previous round, continue searching...")
-                            // Yue 2022.7.11 removed warning, because some code may just remove measurement in the middle
+                            accumulated_clean_measurements = 0;
                         }
                     }
                 }
@@ -734,8 +1069,63 @@ impl Simulator {
 
     /// generate correction pattern using errors only at the top layer
     pub fn generate_sparse_correction(&self) -> SparseCorrection {
+        self.generate_sparse_correction_at(self.height - 1)
+    }
+
+    /// propagate a Pauli frame `(i, j) -> Pauli` at layer `t` to `t + 1` following the same rules as [`Simulator::propagate_error_from`]
+    pub fn propagate_pauli_frame(&self, frame: &BTreeMap<(usize, usize), ErrorType>, t: usize) -> BTreeMap<(usize, usize), ErrorType> {
+        let mut next_frame: BTreeMap<(usize, usize), ErrorType> = BTreeMap::new();
+        for (&(i, j), pauli) in frame.iter() {
+            if *pauli == I {
+                continue
+            }
+            let node = self.get_node_unwrap(&pos!(t, i, j));
+            if !node.gate_type.is_initialization() {
+                let next_pauli = next_frame.entry((i, j)).or_insert(I);
+                *next_pauli = next_pauli.multiply(pauli);
+            }
+            let propagate_to_peer_forbidden = node.is_virtual && !node.is_peer_virtual;
+            if !propagate_to_peer_forbidden && node.gate_type.is_two_qubit_gate() {
+                let propagate_to_peer = node.gate_type.propagate_peer(pauli);
+                if propagate_to_peer != I {
+                    let peer = node.gate_peer.as_ref().unwrap();
+                    let next_pauli = next_frame.entry((peer.i, peer.j)).or_insert(I);
+                    *next_pauli = next_pauli.multiply(&propagate_to_peer);
+                }
+            }
+        }
+        next_frame
+    }
+
+    /// the defect measurements of the truncated volume below layer `t` (a measurement layer): `sparse_measurement` up to layer `t`, followed by
+    /// a perfect measurement round of the data qubits at layer `t`, as if the experiment ended there; the errors are not re-simulated,
+    /// the perfect round is computed by propagating the already-propagated data qubit errors at layer `t` through one noiseless round
+    pub fn generate_truncated_sparse_measurement(&self, sparse_measurement: &SparseMeasurement, t: usize) -> SparseMeasurement {
+        assert!(t.is_multiple_of(self.measurement_cycles) && t + self.measurement_cycles < self.height, "layer {} is not a noisy measurement layer", t);
+        let mut truncated = SparseMeasurement::new_set(sparse_measurement.defects.range(..pos!(t + 1, 0, 0)).cloned().collect());
+        let mut frame: BTreeMap<(usize, usize), ErrorType> = BTreeMap::new();
+        simulator_iter!(self, position, node, t => t, if node.qubit_type == QubitType::Data && node.propagated != I {
+            frame.insert((position.i, position.j), node.propagated);
+        });
+        for layer in t..t + self.measurement_cycles {
+            frame = self.propagate_pauli_frame(&frame, layer);
+        }
+        let perfect_t = t + self.measurement_cycles;
+        simulator_iter_real!(self, position, node, t => perfect_t, if node.gate_type.is_measurement() {
+            let this_result = node.gate_type.stabilizer_measurement(frame.get(&(position.i, position.j)).unwrap_or(&I));
+            if this_result != self.previous_stabilizer_result(position) {
+                truncated.insert_defect_measurement(position);
+            }
+        });
+        truncated
+    }
+
+    /// generate correction pattern using the propagated errors on data qubits at layer `t`, which is the Pauli frame of the data qubits
+    /// at that layer rather than the physical errors that happened; use it with [`Simulator::validate_correction_at`]
+    pub fn generate_sparse_correction_at(&self, t: usize) -> SparseCorrection {
+        assert!(t < self.height, "layer {} out of range", t);
         let mut sparse_correction = SparseCorrection::new();
-        simulator_iter!(self, position, node, t => self.height - 1, {
+        simulator_iter!(self, position, node, t => t, {
             if node.propagated != I && node.qubit_type == QubitType::Data {
                 sparse_correction.add(position.clone(), node.propagated);
             }
@@ -743,6 +1133,18 @@ impl Simulator {
         sparse_correction
     }
 
+    /// the propagated errors of every layer, i.e. the evolution of the Pauli frame over time, with one pattern per time slice `t`;
+    /// like [`SimulatorNode::propagated`], the slice `t` doesn't include the errors happening at `t`; useful to debug the propagation layer by layer
+    pub fn frame_history(&self) -> Vec<SparseErrorPattern> {
+        let mut history: Vec<SparseErrorPattern> = (0..self.height).map(|_| SparseErrorPattern::new()).collect();
+        simulator_iter!(self, position, node, {
+            if node.propagated != I {
+                history[position.t].add(position.clone(), node.propagated);
+            }
+        });
+        history
+    }
+
 }
 
 impl SimulatorGenerics for Simulator {
@@ -755,8 +1157,10 @@ impl SimulatorGenerics for Simulator {
         // let mut pending_pauli_errors = Vec::<(Position, ErrorType)>::new();
         // let mut pending_erasure_errors = Vec::<Position>::new();
         let mut rng = self.rng.clone();  // avoid mutable borrow
+        self.applied_logical = (false, false);  // all previous errors are overwritten
         let mut error_count = 0;
         let mut erasure_count = 0;
+        let measurement_cycles = self.measurement_cycles;
         // first apply single-qubit and two-qubit correlated errors
         simulator_iter_mut!(self, position, node, {
             let noise_model_node = noise_model.get_node_unwrap(position);
@@ -779,7 +1183,7 @@ impl SimulatorGenerics for Simulator {
             let random_erasure = rng.next_f64();
             node.has_erasure = false;
             node.propagated = I;  // clear propagated errors
-            if random_erasure < noise_model_node.erasure_error_rate {
+            if random_erasure < noise_model.erasure_error_rate(position, measurement_cycles) {
                 pending_erasure_errors.push(position.clone());
             }
             match &noise_model_node.correlated_pauli_error_rates {
@@ -840,7 +1244,7 @@ impl SimulatorGenerics for Simulator {
         }
         // apply pending erasure errors, amd generate random pauli error because of those erasures
         for position in pending_erasure_errors.iter() {
-            let mut node = self.get_node_mut_unwrap(&position);
+            let node = self.get_node_mut_unwrap(position);
             if !node.has_erasure {  // only counts new erasures; there might be duplicated pending erasure
                 erasure_count += 1;
             }
@@ -867,7 +1271,7 @@ impl SimulatorGenerics for Simulator {
             sparse_detected_erasures.len() == erasure_count
         });
         self.rng = rng;  // save the random number generator
-        self.propagate_errors();
+        self.propagate_errors_with_feedforwards(&noise_model.feedforwards);
         (error_count, erasure_count)
     }
 
@@ -879,21 +1283,9 @@ impl SimulatorGenerics for Simulator {
             // only iterate over real stabilizers, excluding those non-existing virtual stabilizers
             simulator_iter_real!(self, position, node, t => t, {
                 if node.gate_type.is_measurement() {
-                    let this_result = node.gate_type.stabilizer_measurement(&node.propagated);
-                    let mut previous_position = position.clone();
-                    loop {  // usually this loop execute only once because the previous measurement is found immediately
-                        debug_assert!(previous_position.t >= self.measurement_cycles, "cannot find the previous measurement cycle");
-                        previous_position.t -= self.measurement_cycles;
-                        let previous_node = self.get_node_unwrap(&previous_position);
-                        if previous_node.gate_type.is_measurement() {  // found previous measurement
-                            let previous_result = previous_node.gate_type.stabilizer_measurement(&previous_node.propagated);
-                            if this_result != previous_result {
-                                sparse_measurement.insert_defect_measurement(position);
-                            }
-                            break
-                        }
-                        // println!("[warning] no measurement found in previous round, continue searching...")
-                        // Yue 2022.7.11 removed warning, because some code may just remove measurement in the middle
+                    let this_result = self.stabilizer_outcome(position);
+                    if this_result != self.previous_stabilizer_result(position) {
+                        sparse_measurement.insert_defect_measurement(position);
                     }
                 }
             });
@@ -927,15 +1319,75 @@ impl SimulatorGenerics for Simulator {
     /// test if correction successfully recover the logical information
     #[inline(never)]
     fn validate_correction(&mut self, correction: &SparseCorrection) -> (bool, bool) {
+        let top_t = self.height - 1;
+        if !correction.is_at_layer(top_t) {  // corrections distributed over time are first propagated to the top layer
+            let normalized = correction.normalize_to_layer(self, top_t);
+            return self.validate_correction(&normalized)
+        }
         if let Some((logical_i, logical_j)) = code_builder_validate_correction(self, correction) {
             return (logical_i, logical_j)
         }
         unimplemented!("correction validation method not found for this code");
     }
 
+    fn validate_correction_multi(&mut self, correction: &SparseCorrection) -> ((bool, bool), (bool, bool)) {
+        let top_t = self.height - 1;
+        if !correction.is_at_layer(top_t) {
+            let normalized = correction.normalize_to_layer(self, top_t);
+            return self.validate_correction_multi(&normalized)
+        }
+        code_builder_validate_correction_multi(self, correction).unwrap_or_else(|| (self.validate_correction(correction), (false, false)))
+    }
+
 }
 
 impl Simulator {
+    /// sample shot `index` of a seeded run in which every shot draws from its own stream of the random number generator, i.e. the generator
+    /// seeded by `base_seed` and then jumped `index` times (see [`Xoroshiro128StarStar::jump`]); the jump-ahead takes O(log(index)) time,
+    /// so any shot of a billion-shot run is reproduced without replaying the previous shots; a seeded benchmark samples its shots this way
+    /// and logs the `shot_index` of each shot in the runtime statistics
+    pub fn shot_from_index(&mut self, noise_model: &NoiseModel, base_seed: u64, index: usize) -> (SparseErrorPattern, SparseMeasurement) {
+        use crate::rand_core::SeedableRng;
+        let mut rng = Xoroshiro128StarStar::seed_from_u64(base_seed);
+        rng.jump_n(index as u64);
+        self.rng = rng;
+        self.generate_random_errors(noise_model);
+        (self.generate_sparse_error_pattern(), self.generate_sparse_measurement())
+    }
+
+    /// test if correction successfully recover the logical information at layer `t` instead of the top layer; a correction distributed
+    /// over time is first propagated to layer `t`, so it must not contain any operator after `t`
+    pub fn validate_correction_at(&mut self, correction: &SparseCorrection, t: usize) -> (bool, bool) {
+        if !correction.is_at_layer(t) {
+            assert!(correction.iter().all(|(position, _)| position.t <= t), "correction after layer {} cannot be validated at this layer", t);
+            let normalized = correction.normalize_to_layer(self, t);
+            return self.validate_correction_at(&normalized, t)
+        }
+        if let Some((logical_i, logical_j)) = code_builder_validate_correction_at(self, correction, t) {
+            return (logical_i, logical_j)
+        }
+        unimplemented!("correction validation method not found for this code");
+    }
+
+    /// the Pauli operators left on the data qubits at the top layer after applying the correction to the propagated errors; when the correction
+    /// matches the syndrome, this is a product of stabilizers and (for a failed shot) the logical operator that the decoder introduced
+    pub fn residual_error(&self, correction: &SparseCorrection) -> SparseErrorPattern {
+        let top_t = self.height - 1;
+        let correction = if correction.is_at_layer(top_t) { correction.clone() } else { correction.normalize_to_layer(self, top_t) };
+        let mut residual: BTreeMap<Position, ErrorType> = BTreeMap::new();
+        simulator_iter_real!(self, position, node, t => top_t, {
+            if node.qubit_type == QubitType::Data && node.propagated != I {
+                residual.insert(position.clone(), node.propagated);
+            }
+        });
+        for (position, operator) in correction.iter() {
+            let pauli = residual.entry(position.clone()).or_insert(I);
+            *pauli = pauli.multiply(operator);
+        }
+        residual.retain(|_, pauli| *pauli != I);
+        SparseErrorPattern::new_map(residual)
+    }
+
     /// get `self.nodes[t][i][j]` without position check when compiled in release mode
     #[inline]
     pub fn get_node(&'_ self, position: &Position) -> &'_ Option<Box<SimulatorNode>> {
@@ -1021,29 +1473,11 @@ impl Simulator {
             self.get_node_mut_unwrap(position).set_error_temp(error);
             return Ok(())
         }
-        let mut possible = false;
-        if cfg!(debug_assertions) {
-            let noise_model_node = noise_model.get_node_unwrap(position);
-            let node = self.get_node_unwrap(position);
-            match error {
-                ErrorType::X => if noise_model_node.pauli_error_rates.error_rate_X > 0. { possible = true; },
-                ErrorType::Y => if noise_model_node.pauli_error_rates.error_rate_Y > 0. { possible = true; },
-                ErrorType::Z => if noise_model_node.pauli_error_rates.error_rate_Z > 0. { possible = true; },
-                _ => unreachable!(),
-            }
-            possible |= noise_model_node.erasure_error_rate > 0.;
-            possible |= noise_model_node.correlated_pauli_error_rates.is_some();  // weak check
-            possible |= noise_model_node.correlated_erasure_error_rates.is_some();  // weak check
-            if !possible {  // check peer only if still not possible
-                if let Some(peer_position) = node.gate_peer.as_ref() {
-                    let peer_noise_model_node = noise_model.get_node_unwrap(peer_position);
-                    possible |= peer_noise_model_node.correlated_pauli_error_rates.is_some();  // weak check
-                    possible |= peer_noise_model_node.correlated_erasure_error_rates.is_some();  // weak check
-                }
-            }
+        let possible = if cfg!(debug_assertions) {
+            noise_model.is_single_error_possible(self, position, error)
         } else {
-            possible = true;
-        }
+            true
+        };
         if !possible {
             return Err(format!("setting error at {} with 0 probability is forbidden", position));
         }
@@ -1065,9 +1499,77 @@ impl Simulator {
         Ok(())
     }
 
-    /// create json object for debugging and viewing
+    /// reconstruct a simulator from the "simulator" component of a visualizer file, accepting both abbreviated and full field names;
+    /// nodes are loaded as is so that customized circuits also round-trip, while the stored code type is kept for correction validation
+    pub fn from_visualizer_component(component: &serde_json::Value) -> Result<Self, String> {
+        let get_usize = |value: &serde_json::Value, key: &str| -> Result<usize, String> {
+            value.get(key).and_then(|v| v.as_u64()).map(|v| v as usize).ok_or(format!("missing or invalid field: {}", key))
+        };
+        let field = |node: &'_ serde_json::Value, abbrev: &str, full: &str| -> serde_json::Value {
+            node.get(abbrev).or(node.get(full)).cloned().unwrap_or(serde_json::Value::Null)
+        };
+        let code_type: CodeType = match component.get("code_type") {
+            Some(code_type) => serde_json::from_value(code_type.clone()).map_err(|e| format!("{:?}", e))?,
+            None => CodeType::Customized,
+        };
+        let code_size = match component.get("code_size") {
+            Some(code_size) => CodeSize::new(get_usize(code_size, "noisy_measurements")?, get_usize(code_size, "di")?, get_usize(code_size, "dj")?)
+                .with_final_perfect_rounds(get_usize(code_size, "final_perfect_rounds").unwrap_or(1)),
+            None => CodeSize::new(0, 0, 0),
+        };
+        let height = get_usize(component, "height")?;
+        let vertical = get_usize(component, "vertical")?;
+        let horizontal = get_usize(component, "horizontal")?;
+        let json_nodes = component.get("nodes").and_then(|v| v.as_array()).ok_or("missing or invalid field: nodes".to_string())?;
+        if json_nodes.len() != height {
+            return Err(format!("mismatch: nodes.len() != height {}", height))
+        }
+        let mut nodes = Vec::with_capacity(height);
+        for (t, json_nodes_row_0) in json_nodes.iter().enumerate() {
+            let json_nodes_row_0 = json_nodes_row_0.as_array().filter(|row| row.len() == vertical).ok_or(format!("format error: nodes[{}]", t))?;
+            let mut row_0 = Vec::with_capacity(vertical);
+            for (i, json_nodes_row_1) in json_nodes_row_0.iter().enumerate() {
+                let json_nodes_row_1 = json_nodes_row_1.as_array().filter(|row| row.len() == horizontal).ok_or(format!("format error: nodes[{}][{}]", t, i))?;
+                let mut row_1 = Vec::with_capacity(horizontal);
+                for (j, json_node) in json_nodes_row_1.iter().enumerate() {
+                    if json_node.is_null() {
+                        row_1.push(None);
+                        continue
+                    }
+                    let parse_error = |e: serde_json::Error| format!("format error: nodes[{}][{}][{}]: {:?}", t, i, j, e);
+                    let qubit_type: QubitType = serde_json::from_value(field(json_node, "q", "qubit_type")).map_err(parse_error)?;
+                    let gate_type: GateType = serde_json::from_value(field(json_node, "gt", "gate_type")).map_err(parse_error)?;
+                    let gate_peer: Option<Position> = serde_json::from_value(field(json_node, "gp", "gate_peer")).map_err(parse_error)?;
+                    let is_virtual: bool = serde_json::from_value(field(json_node, "v", "is_virtual")).map_err(parse_error)?;
+                    let is_peer_virtual: bool = serde_json::from_value(field(json_node, "pv", "is_peer_virtual")).map_err(parse_error)?;
+                    let miscellaneous = Some(field(json_node, "m", "miscellaneous")).filter(|value| !value.is_null());
+                    row_1.push(Some(Box::new(SimulatorNode::new(qubit_type, gate_type, gate_peer).set_virtual(is_virtual, is_peer_virtual)
+                        .with_miscellaneous(miscellaneous))));
+                }
+                row_0.push(row_1);
+            }
+            nodes.push(row_0);
+        }
+        Ok(Self {
+            code_type,
+            code_size,
+            height,
+            vertical,
+            horizontal,
+            nodes,
+            rng: Xoroshiro128StarStar::new(),
+            measurement_cycles: get_usize(component, "measurement_cycles")?,
+            applied_logical: (false, false),
+            customized_logical_operators: None,
+            noisy_final_round: component.get("noisy_final_round").and_then(|value| value.as_bool()).unwrap_or(false),
+        })
+    }
+
+    /// create json object for debugging and viewing, which is also the noise model artifact loaded by [`NoiseModel::from_json`]
     pub fn to_json(&self, noise_model: &NoiseModel) -> serde_json::Value {
         json!({
+            "artifact": ArtifactKind::NoiseModel.name(),
+            "schema_version": ArtifactKind::NoiseModel.schema_version(),
             "code_type": self.code_type,
             "height": self.height,
             "vertical": self.vertical,
@@ -1093,7 +1595,9 @@ impl Simulator {
                         }
                     }).collect::<Vec<Option<serde_json::Value>>>()
                 }).collect::<Vec<Vec<Option<serde_json::Value>>>>()
-            }).collect::<Vec<Vec<Vec<Option<serde_json::Value>>>>>()
+            }).collect::<Vec<Vec<Vec<Option<serde_json::Value>>>>>(),
+            "tls_defects": noise_model.tls_defects,
+            "noisy_final_round": self.noisy_final_round,
         })
     }
 }
@@ -1293,6 +1797,19 @@ impl SparseMeasurement {
             defects
         }
     }
+    /// the flat detector indices of the defects in ascending order, panic if a defect is not a detector of the map
+    pub fn to_detector_indices(&self, detector_map: &DetectorMap) -> Vec<usize> {
+        self.iter().map(|position| detector_map.index(position).unwrap_or_else(|| panic!("defect {} is not a detector", position))).collect()
+    }
+    /// the inverse of [`SparseMeasurement::to_detector_indices`]
+    pub fn from_detector_indices(detector_map: &DetectorMap, indices: &[usize]) -> Result<Self, String> {
+        let mut sparse_measurement = Self::new();
+        for &index in indices.iter() {
+            let position = detector_map.position(index).ok_or(format!("detector index {} out of range [0, {})", index, detector_map.len()))?;
+            sparse_measurement.insert_defect_measurement(position);
+        }
+        Ok(sparse_measurement)
+    }
     /// convert vector to sparse measurement
     pub fn from_vec(defects: &Vec<Position>) -> Self {
         let mut sparse_measurement = Self::new();
@@ -1306,10 +1823,239 @@ impl SparseMeasurement {
     pub fn iter<'a>(&'a self) -> std::collections::btree_set::Iter<'a, Position> {
         self.defects.iter()
     }
+    /// the defect measurements at time `t`, in ascending order
+    pub fn round_defects<'a>(&'a self, t: usize) -> std::collections::btree_set::Range<'a, Position> {
+        self.defects.range(pos!(t, 0, 0)..pos!(t + 1, 0, 0))
+    }
+    /// a round without any defect measurement has exactly the same stabilizer outcomes as the previous round
+    pub fn is_clean_round(&self, t: usize) -> bool {
+        self.round_defects(t).next().is_none()
+    }
+    /// collapse consecutive measurement rounds without any defect into a single run, to log long idle periods compactly
+    pub fn to_run_length_encoding(&self, simulator: &Simulator) -> RunLengthEncodedMeasurement {
+        let mut runs = Vec::new();
+        for t in (simulator.measurement_cycles..simulator.height).step_by(simulator.measurement_cycles) {
+            if self.is_clean_round(t) {
+                if let Some(MeasurementRoundRun::Clean(count)) = runs.last_mut() {
+                    *count += 1;
+                } else {
+                    runs.push(MeasurementRoundRun::Clean(1));
+                }
+            } else {
+                runs.push(MeasurementRoundRun::Defects(self.round_defects(t).map(|position| (position.i, position.j)).collect()));
+            }
+        }
+        debug_assert!(self.iter().all(|position| position.t >= simulator.measurement_cycles && position.t < simulator.height
+            && position.t % simulator.measurement_cycles == 0), "defect measurement not in a measurement round");
+        RunLengthEncodedMeasurement {
+            measurement_cycles: simulator.measurement_cycles,
+            runs,
+        }
+    }
+    /// dense tensor of shape `[rounds, vertical, horizontal]`, where `[r][i][j]` is 1 if the measurement at `t = (r + 1) * measurement_cycles`
+    /// is a defect and 0 otherwise, e.g. as the input of machine-learning decoders
+    pub fn to_ndarray(&self, simulator: &Simulator) -> ndarray::Array3<u8> {
+        let rounds = (simulator.height - 1) / simulator.measurement_cycles;
+        let mut dense = ndarray::Array3::<u8>::zeros((rounds, simulator.vertical, simulator.horizontal));
+        for position in self.iter() {
+            debug_assert!(position.t >= simulator.measurement_cycles && position.t % simulator.measurement_cycles == 0
+                , "defect measurement not in a measurement round");
+            dense[[position.t / simulator.measurement_cycles - 1, position.i, position.j]] = 1;
+        }
+        dense
+    }
+    /// compress a stream of measurement rounds into bytes, for transporting syndromes from the hardware at a low bandwidth;
+    /// consecutive empty rounds are run-length encoded as `0, count`, and a non-empty round is `count` followed by its defects in ascending order,
+    /// each delta-encoded against the previous one: `dt`, then `di` if `dt = 0` (otherwise absolute `i`), then `dj` if `di = 0` (otherwise absolute `j`);
+    /// the first defect of a round encodes `t` relative to the first defect of the previous non-empty round (zigzag, since rounds need not be sorted).
+    /// all integers are LEB128 varints, so a typical defect costs 3 bytes. For example, 1000 shots of a d = 5 rotated code with T = 5 noisy rounds
+    /// (phenomenological noise, one `SparseMeasurement` per round) take 2.4 kB / 21 kB / 76 kB at p = 0.1% / 1% / 5%,
+    /// compared to 25 kB / 82 kB / 311 kB for JSON and 61 kB / 173 kB / 605 kB for bincode (a `u64` length per round and 3 `u64` per defect),
+    /// see `simulator_measurement_compress_stream`
+    pub fn compress_stream(rounds: &[SparseMeasurement]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut empty_rounds = 0;
+        let mut previous_round_t = 0;
+        for round in rounds.iter() {
+            if round.len() == 0 {
+                empty_rounds += 1;
+                continue
+            }
+            if empty_rounds > 0 {
+                write_varint(&mut bytes, 0);
+                write_varint(&mut bytes, empty_rounds);
+                empty_rounds = 0;
+            }
+            write_varint(&mut bytes, round.len());
+            let mut previous: Option<&Position> = None;
+            for position in round.iter() {
+                match previous {
+                    None => {
+                        let dt = position.t as i64 - previous_round_t as i64;
+                        write_varint(&mut bytes, ((dt << 1) ^ (dt >> 63)) as usize);  // zigzag
+                        write_varint(&mut bytes, position.i);
+                        write_varint(&mut bytes, position.j);
+                        previous_round_t = position.t;
+                    },
+                    Some(previous) => {
+                        write_varint(&mut bytes, position.t - previous.t);
+                        if position.t != previous.t {
+                            write_varint(&mut bytes, position.i);
+                            write_varint(&mut bytes, position.j);
+                        } else {
+                            write_varint(&mut bytes, position.i - previous.i);
+                            write_varint(&mut bytes, if position.i != previous.i { position.j } else { position.j - previous.j });
+                        }
+                    },
+                }
+                previous = Some(position);
+            }
+        }
+        if empty_rounds > 0 {
+            write_varint(&mut bytes, 0);
+            write_varint(&mut bytes, empty_rounds);
+        }
+        bytes
+    }
+    /// the inverse of [`SparseMeasurement::compress_stream`]; the stream may come from an untrusted source, so it is rejected if it decodes into
+    /// more than `max_rounds` rounds instead of allocating an arbitrarily long run of empty rounds
+    pub fn decompress_stream(bytes: &[u8], max_rounds: usize) -> Result<Vec<SparseMeasurement>, String> {
+        let mut rounds = Vec::new();
+        let mut cursor = 0;
+        let mut previous_round_t = 0;
+        while cursor < bytes.len() {
+            let count = read_varint(bytes, &mut cursor)?;
+            if count == 0 {
+                let empty_rounds = read_varint(bytes, &mut cursor)?;
+                if empty_rounds == 0 {
+                    return Err(format!("empty run of length 0 at byte {}", cursor))
+                }
+                if empty_rounds > max_rounds - rounds.len() {
+                    return Err(format!("more than {} rounds at byte {}", max_rounds, cursor))
+                }
+                rounds.extend((0..empty_rounds).map(|_| SparseMeasurement::new()));
+                continue
+            }
+            if rounds.len() == max_rounds {
+                return Err(format!("more than {} rounds at byte {}", max_rounds, cursor))
+            }
+            let mut round = SparseMeasurement::new();
+            let mut previous: Option<Position> = None;
+            for _ in 0..count {
+                let position = match previous {
+                    None => {
+                        let zigzag = read_varint(bytes, &mut cursor)?;
+                        let dt = ((zigzag >> 1) as i64) ^ -((zigzag & 1) as i64);
+                        let t = (previous_round_t as i64).checked_add(dt).ok_or_else(|| format!("t overflow at byte {}", cursor))?;
+                        if t < 0 {
+                            return Err(format!("negative t at byte {}", cursor))
+                        }
+                        previous_round_t = t as usize;
+                        pos!(t as usize, read_varint(bytes, &mut cursor)?, read_varint(bytes, &mut cursor)?)
+                    },
+                    Some(previous) => {
+                        let overflow = |cursor: usize| format!("position overflow at byte {}", cursor);
+                        let dt = read_varint(bytes, &mut cursor)?;
+                        if dt != 0 {
+                            let t = previous.t.checked_add(dt).ok_or_else(|| overflow(cursor))?;
+                            pos!(t, read_varint(bytes, &mut cursor)?, read_varint(bytes, &mut cursor)?)
+                        } else {
+                            let di = read_varint(bytes, &mut cursor)?;
+                            let j = read_varint(bytes, &mut cursor)?;
+                            if di != 0 {
+                                pos!(previous.t, previous.i.checked_add(di).ok_or_else(|| overflow(cursor))?, j)
+                            } else {
+                                pos!(previous.t, previous.i, previous.j.checked_add(j).ok_or_else(|| overflow(cursor))?)
+                            }
+                        }
+                    },
+                };
+                if !round.insert_defect_measurement(&position) {
+                    return Err(format!("duplicate defect measurement {} at byte {}", position, cursor))
+                }
+                previous = Some(position);
+            }
+            rounds.push(round);
+        }
+        Ok(rounds)
+    }
+}
+
+/// write an unsigned LEB128 varint
+fn write_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7F) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// read an unsigned LEB128 varint, advancing `cursor`
+fn read_varint(bytes: &[u8], cursor: &mut usize) -> Result<usize, String> {
+    let mut value = 0usize;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.get(*cursor).ok_or_else(|| format!("truncated varint at byte {}", *cursor))?;
+        *cursor += 1;
+        if shift >= usize::BITS {
+            return Err(format!("varint overflow at byte {}", *cursor))
+        }
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value)
+        }
+        shift += 7;
+    }
+}
+
+/// consecutive measurement rounds in [`RunLengthEncodedMeasurement`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MeasurementRoundRun {
+    /// the number of consecutive rounds without any defect measurement
+    Clean(usize),
+    /// a single round with the `(i, j)` of its defect measurements
+    Defects(Vec<(usize, usize)>),
+}
+
+/// run-length encoding of [`SparseMeasurement`] over the measurement rounds `t = measurement_cycles, 2 * measurement_cycles, ...`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunLengthEncodedMeasurement {
+    pub measurement_cycles: usize,
+    pub runs: Vec<MeasurementRoundRun>,
+}
+
+impl RunLengthEncodedMeasurement {
+    /// the number of measurement rounds, including the clean ones
+    pub fn rounds(&self) -> usize {
+        self.runs.iter().map(|run| match run {
+            MeasurementRoundRun::Clean(count) => *count,
+            MeasurementRoundRun::Defects(_) => 1,
+        }).sum()
+    }
+    /// expand the runs back to the defect measurements
+    pub fn to_sparse_measurement(&self) -> SparseMeasurement {
+        let mut sparse_measurement = SparseMeasurement::new();
+        let mut t = self.measurement_cycles;
+        for run in self.runs.iter() {
+            match run {
+                MeasurementRoundRun::Clean(count) => {
+                    t += count * self.measurement_cycles;
+                },
+                MeasurementRoundRun::Defects(defects) => {
+                    for &(i, j) in defects.iter() {
+                        sparse_measurement.insert_defect_measurement(&pos!(t, i, j));
+                    }
+                    t += self.measurement_cycles;
+                },
+            }
+        }
+        sparse_measurement
+    }
 }
 
 /// detected erasures along with its effected edges
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "python_binding", cfg_eval)]
 #[cfg_attr(feature = "python_binding", pyclass)]
 pub struct SparseErasures {
@@ -1398,10 +2144,98 @@ impl SparseErasures {
     }
 }
 
-/// in most cases errors are rare, this sparse structure use `BTreeMap` to store them
+/// detectors whose measurement records are lost, e.g. because of FIFO overflow in the hardware; the defect of a lost detector cannot be computed,
+/// so the difference is taken with the next available measurement of the same stabilizer instead
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "python_binding", cfg_eval)]
 #[cfg_attr(feature = "python_binding", pyclass)]
+pub struct SparseDetectorDropout {
+    /// the measurement nodes (t, i, j) whose outcomes are lost
+    #[cfg_attr(feature = "python_binding", pyo3(get, set))]
+    pub dropped: BTreeSet<Position>,
+}
+
+impl Default for SparseDetectorDropout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg_attr(feature = "python_binding", cfg_eval)]
+#[cfg_attr(feature = "python_binding", pymethods)]
+impl SparseDetectorDropout {
+    #[cfg(feature = "python_binding")]
+    fn __repr__(&self) -> String { format!("{:?}", self) }
+    /// create a new dropout without any lost detector
+    #[cfg_attr(feature = "python_binding", new)]
+    pub fn new() -> Self {
+        Self {
+            dropped: BTreeSet::new(),
+        }
+    }
+    /// the number of lost detectors
+    pub fn len(&self) -> usize {
+        self.dropped.len()
+    }
+    /// no detector is lost
+    pub fn is_empty(&self) -> bool {
+        self.dropped.is_empty()
+    }
+    /// contains element
+    pub fn contains(&self, key: &Position) -> bool {
+        self.dropped.contains(key)
+    }
+    /// return false if this detector is already lost
+    #[inline]
+    pub fn insert_dropped(&mut self, position: &Position) -> bool {
+        self.dropped.insert(position.clone())
+    }
+}
+
+impl SparseDetectorDropout {
+    /// iterator
+    pub fn iter<'a>(&'a self) -> std::collections::btree_set::Iter<'a, Position> {
+        self.dropped.iter()
+    }
+    /// each detector in `detectors` is lost independently with probability `rate`
+    pub fn generate_random(detectors: &[Position], rate: f64, rng: &mut Xoroshiro128StarStar) -> Self {
+        let mut detector_dropout = Self::new();
+        if rate > 0. {
+            for detector in detectors.iter() {
+                if rng.next_f64() < rate {
+                    detector_dropout.insert_dropped(detector);
+                }
+            }
+        }
+        detector_dropout
+    }
+    /// compute the syndrome without the lost detectors: the defect of a lost detector is moved to the next measurement of the same stabilizer,
+    /// because the next available measurement is compared with the previous available one; the final perfect measurement round is never lost
+    pub fn apply(&self, sparse_measurement: &SparseMeasurement, measurement_cycles: usize) -> SparseMeasurement {
+        let mut defects = sparse_measurement.defects.clone();
+        for position in self.dropped.iter() {  // in ascending order of `t`, so that consecutive lost detectors are handled correctly
+            if defects.remove(position) {
+                let next_position = pos!(position.t + measurement_cycles, position.i, position.j);
+                if !defects.remove(&next_position) {
+                    defects.insert(next_position);
+                }
+            }
+        }
+        SparseMeasurement::new_set(defects)
+    }
+    /// compute the time-like edges re-weighted to 0, i.e. each lost detector is merged with its next measurement so that the time-like edge
+    /// effectively spans from the previous available measurement; the decoding graph must contain these time-like edges
+    pub fn get_erasure_edges(&self, measurement_cycles: usize) -> Vec<ErasureEdge> {
+        self.dropped.iter().map(|position| {
+            ErasureEdge::Connection(position.clone(), pos!(position.t + measurement_cycles, position.i, position.j))
+        }).collect()
+    }
+}
+
+/// in most cases errors are rare, this sparse structure use `BTreeMap` to store them
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "python_binding", cfg_eval)]
+#[cfg_attr(feature = "python_binding", pyclass)]
 pub struct SparseErrorPattern {
     /// error happening at position: Position (t, i, j)
     #[cfg_attr(feature = "python_binding", pyo3(get, set))]
@@ -1464,6 +2298,16 @@ impl SparseErrorPattern {
     pub fn get(&self, key: &Position) -> Option<&ErrorType> {
         self.errors.get(key)
     }
+    /// whether two Pauli operators anticommute, i.e. the parity of the qubit-wise symplectic inner product
+    pub fn anticommute(&self, other: &Self) -> bool {
+        let mut parity = false;
+        for (position, error) in self.iter() {
+            if let Some(other_error) = other.get(position) {
+                parity ^= error.anticommute(other_error);
+            }
+        }
+        parity
+    }
 }
 
 impl Serialize for SparseErrorPattern {
@@ -1545,6 +2389,42 @@ impl SparseCorrection {
 }
 
 impl SparseCorrection {
+    /// add a correction Pauli operator at any layer `t`, which is the Pauli frame of the qubit before the gate at `t`;
+    /// such a correction must be normalized by [`SparseCorrection::normalize_to_layer`] before being compared with others
+    pub fn add_cross_layer(&mut self, position: Position, operator: ErrorType) {
+        self.0.add(position, operator);
+    }
+    /// check if all the corrections are at layer `t`
+    pub fn is_at_layer(&self, t: usize) -> bool {
+        self.0.iter().all(|(position, _)| position.t == t)
+    }
+    /// propagate each correction forward through the circuit to `target_t` and merge them, keeping only data qubits like
+    /// [`Simulator::generate_sparse_correction`]; this allows decoders to emit time-local corrections while still using the existing validation
+    pub fn normalize_to_layer(&self, simulator: &Simulator, target_t: usize) -> SparseCorrection {
+        assert!(target_t < simulator.height, "target layer {} out of range", target_t);
+        let mut pending = self.iter().peekable();  // sorted by `t` first
+        let min_t = pending.peek().map(|(position, _)| position.t).unwrap_or(target_t);
+        let mut frame: BTreeMap<(usize, usize), ErrorType> = BTreeMap::new();
+        for t in min_t..target_t+1 {
+            while let Some((position, error)) = pending.next_if(|(position, _)| position.t == t) {
+                let pauli = frame.entry((position.i, position.j)).or_insert(I);
+                *pauli = pauli.multiply(error);
+            }
+            if t == target_t {
+                break
+            }
+            frame = simulator.propagate_pauli_frame(&frame, t);
+        }
+        assert!(pending.next().is_none(), "cannot propagate correction backward to layer {}", target_t);
+        let mut normalized = SparseCorrection::new();
+        for (&(i, j), pauli) in frame.iter() {
+            let position = pos!(target_t, i, j);
+            if *pauli != I && simulator.get_node_unwrap(&position).qubit_type == QubitType::Data {
+                normalized.add(position, *pauli);
+            }
+        }
+        normalized
+    }
     /// iterator
     pub fn iter<'a>(&'a self) -> std::collections::btree_map::Iter<'a, Position, ErrorType> {
         self.0.iter()
@@ -1557,6 +2437,19 @@ impl SparseCorrection {
     pub fn get(&self, key: &Position) -> Option<&ErrorType> {
         self.0.get(key)
     }
+    /// the indices of the logical operators (see [`code_builder_logical_operators`]) that anticommute with the correction, i.e. the logical
+    /// observables flipped by the correction
+    pub fn flipped_observables(&self, logical_operators: &[SparseErrorPattern]) -> Vec<usize> {
+        logical_operators.iter().enumerate().filter(|(_, logical_operator)| logical_operator.anticommute(&self.0))
+            .map(|(index, _)| index).collect()
+    }
+    /// remove the redundant operators, i.e. the identities left where overlapping corrections cancel each other;
+    /// the correction is unchanged as a Pauli operator, returns the number of eliminated operators
+    pub fn optimize(&mut self) -> usize {
+        let len = self.len();
+        self.0.errors.retain(|_, error| *error != I);
+        len - self.len()
+    }
 }
 
 impl Serialize for SparseCorrection {
@@ -1587,6 +2480,666 @@ mod tests {
         }
     }
 
+    #[test]
+    fn simulator_apply_transversal_logical() {  // cargo test simulator_apply_transversal_logical -- --nocapture
+        for code_type in [CodeType::StandardPlanarCode, CodeType::RotatedPlanarCode, CodeType::StandardXZZXCode, CodeType::RotatedXZZXCode
+                , CodeType::StandardTailoredCode, CodeType::RotatedTailoredCode] {
+            let mut simulator = Simulator::new(code_type, CodeSize::new(3, 5, 5));
+            let t = 2 * simulator.measurement_cycles + 1;
+            for (logical, expected) in [(LogicalOp::X, (false, true)), (LogicalOp::Z, (true, false))] {
+                simulator.clear_all_errors();
+                simulator.apply_transversal_logical(t, logical);
+                simulator.propagate_errors();
+                assert_eq!(simulator.generate_sparse_measurement().len(), 0, "syndrome should stay clean, {:?} {:?}", code_type, logical);
+                assert_eq!(simulator.applied_logical, expected);
+                assert_eq!(simulator.validate_correction(&SparseCorrection::new()), expected, "logical state should be flipped");
+            }
+            // applying it twice recovers the logical state
+            simulator.clear_all_errors();
+            simulator.apply_transversal_logical(t, LogicalOp::X);
+            simulator.apply_transversal_logical(t + simulator.measurement_cycles, LogicalOp::X);
+            simulator.propagate_errors();
+            assert_eq!(simulator.generate_sparse_measurement().len(), 0);
+            assert_eq!(simulator.validate_correction(&SparseCorrection::new()), (false, false));
+        }
+    }
+
+    #[test]
+    fn simulator_round_bitstrings() {  // cargo test simulator_round_bitstrings -- --nocapture
+        use crate::noise_model_builder::*;
+        for code_type in [CodeType::StandardPlanarCode, CodeType::RotatedPlanarCode, CodeType::StandardXZZXCode, CodeType::RotatedTailoredCode] {
+            let mut simulator = Simulator::new(code_type, CodeSize::new(3, 3, 3));
+            let mut noise_model = NoiseModel::new(&simulator);
+            NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), 0.05, 0.5, 0.);
+            simulator.compress_error_rates(&mut noise_model);
+            let stabilizers = simulator.stabilizer_order();
+            for _ in 0..100 {
+                simulator.generate_random_errors(&noise_model);
+                let round_bitstrings = simulator.round_bitstrings();
+                assert_eq!(round_bitstrings.len(), (simulator.height - 1) / simulator.measurement_cycles + 1);
+                let mut defects = SparseMeasurement::new();
+                for round in 1..round_bitstrings.len() {
+                    assert_eq!(round_bitstrings[round].len(), stabilizers.len());
+                    for (idx, stabilizer) in stabilizers.iter().enumerate() {
+                        if round_bitstrings[round][idx] != round_bitstrings[round - 1][idx] {
+                            defects.insert_defect_measurement(&pos!(round * simulator.measurement_cycles, stabilizer.i, stabilizer.j));
+                        }
+                    }
+                }
+                assert_eq!(defects.to_vec(), simulator.generate_sparse_measurement().to_vec());
+            }
+        }
+    }
+
+    #[test]
+    fn simulator_sample_batch() {  // cargo test simulator_sample_batch -- --nocapture
+        use crate::noise_model_builder::*;
+        use crate::rand_core::SeedableRng;
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(3, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::ErasureOnlyPhenomenological.apply(&mut simulator, &mut noise_model, &json!({}), 0., 0.5, 0.05);
+        simulator.compress_error_rates(&mut noise_model);
+        let shots = 50;
+        simulator.rng = Xoroshiro128StarStar::seed_from_u64(1715);
+        let batch = simulator.sample_batch(&noise_model, shots);
+        assert_eq!(batch.len(), shots);
+        simulator.rng = Xoroshiro128StarStar::seed_from_u64(1715);
+        for (error_pattern, detected_erasures, measurement) in batch.iter() {
+            simulator.generate_random_errors(&noise_model);
+            assert_eq!(error_pattern.to_vec(), simulator.generate_sparse_error_pattern().to_vec());
+            assert_eq!(detected_erasures.iter().collect::<Vec<_>>(), simulator.generate_sparse_detected_erasures().iter().collect::<Vec<_>>());
+            assert_eq!(measurement.to_vec(), simulator.generate_sparse_measurement().to_vec());
+        }
+        assert!(batch.iter().any(|(error_pattern, _, _)| error_pattern.len() > 0), "the sampled errors should not be trivial");
+        // a clone does not inherit the random number generator, so parallel workers sample independently
+        simulator.rng = Xoroshiro128StarStar::seed_from_u64(1715);
+        let mut cloned = simulator.clone();
+        assert_ne!(cloned.rng, simulator.rng);
+        let cloned_batch = cloned.sample_batch(&noise_model, shots);
+        assert!(cloned_batch.iter().zip(batch.iter()).any(|(a, b)| a.0.to_vec() != b.0.to_vec()));
+    }
+
+    #[test]
+    fn simulator_erasure_rate_multiplier() {  // cargo test simulator_erasure_rate_multiplier -- --nocapture
+        use crate::noise_model_builder::*;
+        use crate::rand_core::SeedableRng;
+        let noisy_measurements = 3;
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(noisy_measurements, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::ErasureOnlyPhenomenological.apply(&mut simulator, &mut noise_model, &json!({
+            "erasure_rate_multiplier": { "linear": 1. },
+        }), 0., 0.5, 0.05);
+        simulator.compress_error_rates(&mut noise_model);
+        assert_eq!(noise_model.erasure_rate_multiplier, Some(ErasureRateMultiplier::Linear(1.)));
+        // the erasure rate of round r is (1 + r) times the base one
+        let shots = 3000;
+        simulator.rng = Xoroshiro128StarStar::seed_from_u64(1719);
+        let mut round_erasures = vec![0usize; noisy_measurements + 1];
+        for _ in 0..shots {
+            simulator.generate_random_errors(&noise_model);
+            for position in simulator.generate_sparse_detected_erasures().iter() {
+                // the ancilla erasures of the last noisy round would be in the final perfect measurement round, so only count data qubits
+                if simulator.get_node_unwrap(position).qubit_type == QubitType::Data {
+                    round_erasures[position.t / simulator.measurement_cycles] += 1;
+                }
+            }
+        }
+        println!("round_erasures: {:?}", round_erasures);
+        for (round, &erasures) in round_erasures.iter().enumerate() {
+            let ratio = erasures as f64 / round_erasures[0] as f64;
+            assert!((ratio - (1. + round as f64)).abs() < 0.1 * (1. + round as f64), "round {} has {} times erasures of round 0", round, ratio);
+        }
+        // the analytic defect density agrees with the scaled erasure rates
+        let mut unscaled_noise_model = noise_model.clone();
+        unscaled_noise_model.erasure_rate_multiplier = None;
+        assert!(noise_model.expected_defects(&simulator) > 1.5 * unscaled_noise_model.expected_defects(&simulator));
+    }
+
+    #[test]
+    fn simulator_measurement_run_length_encoding() {  // cargo test simulator_measurement_run_length_encoding -- --nocapture
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(50, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, 0.01, 0.01, 0.01, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        // a long idle period with a few errors in the middle
+        let mut data_qubits = Vec::new();
+        simulator_iter_real!(simulator, position, node, t => 0, if node.qubit_type == QubitType::Data {
+            data_qubits.push((position.i, position.j));
+        });
+        let (i, j) = data_qubits[0];
+        simulator.set_error_check(&noise_model, &pos!(simulator.measurement_cycles, i, j), &X);
+        let (i, j) = data_qubits[data_qubits.len() - 1];
+        simulator.set_error_check(&noise_model, &pos!(20 * simulator.measurement_cycles, i, j), &Z);
+        simulator.propagate_errors();
+        let sparse_measurement = simulator.generate_sparse_measurement();
+        assert!(sparse_measurement.len() > 0);
+        let encoded = sparse_measurement.to_run_length_encoding(&simulator);
+        println!("{}", serde_json::to_string(&encoded).unwrap());
+        assert_eq!(encoded.rounds(), (simulator.height - 1) / simulator.measurement_cycles);
+        let defect_rounds = encoded.runs.iter().filter(|run| matches!(run, MeasurementRoundRun::Defects(_))).count();
+        assert!(encoded.runs.len() <= 2 * defect_rounds + 1, "consecutive clean rounds should be collapsed");
+        for position in sparse_measurement.iter() {
+            assert!(!sparse_measurement.is_clean_round(position.t));
+        }
+        // decode back to the same defect set, also through serialization
+        let decoded: RunLengthEncodedMeasurement = serde_json::from_str(&serde_json::to_string(&encoded).unwrap()).unwrap();
+        assert_eq!(decoded, encoded);
+        assert_eq!(decoded.to_sparse_measurement().to_vec(), sparse_measurement.to_vec());
+        // a clean shot is a single run
+        let clean = SparseMeasurement::new().to_run_length_encoding(&simulator);
+        assert_eq!(clean.runs, vec![MeasurementRoundRun::Clean(encoded.rounds())]);
+        assert_eq!(clean.to_sparse_measurement().len(), 0);
+    }
+
+    #[test]
+    fn simulator_measurement_compress_stream() {  // cargo test simulator_measurement_compress_stream -- --nocapture
+        use crate::noise_model_builder::*;
+        use crate::rand_core::SeedableRng;
+        for p in [0.001, 0.01, 0.05] {
+            let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(5, 5, 5));
+            let mut noise_model = NoiseModel::new(&simulator);
+            NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), p, 0.5, 0.);
+            simulator.compress_error_rates(&mut noise_model);
+            simulator.rng = Xoroshiro128StarStar::seed_from_u64(1721);
+            let mut rounds = Vec::new();
+            for _ in 0..1000 {
+                simulator.generate_random_errors(&noise_model);
+                let sparse_measurement = simulator.generate_sparse_measurement();
+                for t in (simulator.measurement_cycles..simulator.height).step_by(simulator.measurement_cycles) {
+                    rounds.push(SparseMeasurement::new_set(sparse_measurement.round_defects(t).cloned().collect()));
+                }
+            }
+            let compressed = SparseMeasurement::compress_stream(&rounds);
+            let decompressed = SparseMeasurement::decompress_stream(&compressed, rounds.len()).unwrap();
+            assert_eq!(decompressed.len(), rounds.len());
+            for (decompressed_round, round) in decompressed.iter().zip(rounds.iter()) {
+                assert_eq!(decompressed_round.to_vec(), round.to_vec());
+            }
+            let json_size = serde_json::to_string(&rounds).unwrap().len();
+            let bincode_size: usize = rounds.iter().map(|round| 8 + 24 * round.len()).sum();  // `u64` length and 3 `u64` per position
+            println!("p = {}: compressed {} bytes, json {} bytes, bincode {} bytes", p, compressed.len(), json_size, bincode_size);
+            assert!(compressed.len() < json_size && compressed.len() < bincode_size);
+        }
+        // unsorted rounds and corrupted streams
+        let rounds = vec![SparseMeasurement::from_vec(&vec![pos!(300, 1, 2), pos!(300, 1, 4), pos!(306, 0, 1)]), SparseMeasurement::new()
+            , SparseMeasurement::new(), SparseMeasurement::from_vec(&vec![pos!(6, 3, 3)])];
+        let compressed = SparseMeasurement::compress_stream(&rounds);
+        let decompressed = SparseMeasurement::decompress_stream(&compressed, rounds.len()).unwrap();
+        assert_eq!(decompressed.iter().map(|round| round.to_vec()).collect::<Vec<_>>(), rounds.iter().map(|round| round.to_vec()).collect::<Vec<_>>());
+        assert!(SparseMeasurement::decompress_stream(&compressed[..compressed.len() - 1], rounds.len()).is_err());
+        assert_eq!(SparseMeasurement::decompress_stream(&[], 0).unwrap().len(), 0);
+        // the number of rounds is bounded, both for the rounds with defects and for a huge run of empty rounds
+        assert!(SparseMeasurement::decompress_stream(&compressed, rounds.len() - 1).is_err());
+        let mut bytes = vec![];
+        for value in [0, usize::MAX] {
+            write_varint(&mut bytes, value);
+        }
+        assert!(SparseMeasurement::decompress_stream(&bytes, 1000).is_err());
+        // deltas that overflow the position are rejected instead of wrapping around
+        for (dt, di, j) in [(usize::MAX, 1, 1), (0, usize::MAX, 1), (0, 0, usize::MAX)] {
+            let mut bytes = vec![];
+            for value in [2, 12, 1, 2, dt, di, j] {
+                write_varint(&mut bytes, value);
+            }
+            assert!(SparseMeasurement::decompress_stream(&bytes, 10).is_err());
+        }
+        let mut bytes = vec![];
+        for value in [1, usize::MAX - 1, 1, 1] {  // zigzag of `i64::MAX`, then `i64::MAX` again in the next round
+            write_varint(&mut bytes, value);
+        }
+        let twice = [bytes.clone(), bytes].concat();
+        assert!(SparseMeasurement::decompress_stream(&twice, 10).is_err());
+    }
+
+    #[test]
+    fn simulator_feedforward() {  // cargo test simulator_feedforward -- --nocapture
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(3, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, 0.01, 0.01, 0.01, 0.);
+        let mut data_qubits = Vec::new();
+        simulator_iter_real!(simulator, position, node, t => 0, if node.qubit_type == QubitType::Data {
+            data_qubits.push((position.i, position.j));
+        });
+        let (i, j) = data_qubits[0];
+        simulator.set_error_check(&noise_model, &pos!(simulator.measurement_cycles, i, j), &X);
+        simulator.propagate_errors();
+        let sparse_measurement = simulator.generate_sparse_measurement();
+        let trigger = sparse_measurement.iter().next().unwrap().clone();
+        let mut clean_measurement = None;
+        simulator_iter_real!(simulator, position, node, t => trigger.t, if node.gate_type.is_measurement() && !sparse_measurement.defects.contains(position) {
+            clean_measurement = Some(position.clone());
+        });
+        let clean_measurement = clean_measurement.unwrap();
+        // the feedforward undoes the X error right after it is detected
+        let mut pauli_errors = SparseErrorPattern::new();
+        pauli_errors.add(pos!(trigger.t + 1, i, j), X);
+        noise_model.feedforwards.push(Feedforward { trigger: trigger.clone(), pauli_errors: pauli_errors.clone() });
+        assert!(noise_model_sanity_check(&simulator, &noise_model).is_ok());
+        simulator.clear_propagate_errors();
+        simulator.propagate_errors_with_feedforwards(&noise_model.feedforwards);
+        let fed_measurement = simulator.generate_sparse_measurement();
+        println!("without feedforward: {:?}, with feedforward: {:?}", sparse_measurement.to_vec(), fed_measurement.to_vec());
+        assert_eq!(fed_measurement.len(), 2 * sparse_measurement.len(), "the corrected error should be detected again in the next round");
+        assert_eq!(simulator.get_node_unwrap(&pos!(simulator.height - 1, i, j)).propagated, I);
+        // the feedforward is not applied when the triggering measurement is +1
+        noise_model.feedforwards = vec![Feedforward { trigger: clean_measurement, pauli_errors }];
+        simulator.clear_propagate_errors();
+        simulator.propagate_errors_with_feedforwards(&noise_model.feedforwards);
+        assert_eq!(simulator.generate_sparse_measurement().to_vec(), sparse_measurement.to_vec());
+        assert_eq!(simulator.get_node_unwrap(&pos!(simulator.height - 1, i, j)).propagated, X);
+        // a feedforward must act on the round right after the trigger
+        let mut misplaced = SparseErrorPattern::new();
+        misplaced.add(pos!(trigger.t + 2, i, j), X);
+        noise_model.feedforwards = vec![Feedforward { trigger, pauli_errors: misplaced }];
+        assert!(noise_model_sanity_check(&simulator, &noise_model).is_err());
+    }
+
+    #[test]
+    fn simulator_deep_clone_with_rng() {  // cargo test simulator_deep_clone_with_rng -- --nocapture
+        use crate::noise_model_builder::*;
+        use crate::rand_core::SeedableRng;
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(3, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), 0.05, 0.5, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        simulator.rng = Xoroshiro128StarStar::seed_from_u64(1715);
+        simulator.generate_random_errors(&noise_model);  // the RNG state in the middle of a simulation is copied as well
+        let mut deep_cloned = simulator.deep_clone_with_rng();
+        assert_eq!(deep_cloned.rng, simulator.rng);
+        assert_eq!(deep_cloned.generate_sparse_error_pattern().to_vec(), simulator.generate_sparse_error_pattern().to_vec());
+        let mut cloned = simulator.clone();
+        let mut cloned_diverged = false;
+        for _ in 0..20 {
+            simulator.generate_random_errors(&noise_model);
+            deep_cloned.generate_random_errors(&noise_model);
+            cloned.generate_random_errors(&noise_model);
+            let error_pattern = simulator.generate_sparse_error_pattern().to_vec();
+            assert_eq!(deep_cloned.generate_sparse_error_pattern().to_vec(), error_pattern);
+            cloned_diverged |= cloned.generate_sparse_error_pattern().to_vec() != error_pattern;
+        }
+        assert!(cloned_diverged, "the regular clone should reseed the random number generator");
+    }
+
+    #[test]
+    fn simulator_set_rng_seed() {  // cargo test simulator_set_rng_seed -- --nocapture
+        use crate::noise_model_builder::*;
+        // independently constructed simulators, as in two different processes, sample the same errors given the same seed
+        let build = |seed: u64| {
+            let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(3, 5, 5));
+            let mut noise_model = NoiseModel::new(&simulator);
+            NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), 0.05, 0.5, 0.);
+            simulator.compress_error_rates(&mut noise_model);
+            simulator.set_rng_seed(seed);
+            (simulator, noise_model)
+        };
+        let (mut simulator_1, noise_model) = build(1751);
+        let (mut simulator_2, _) = build(1751);
+        let (mut simulator_3, _) = build(1752);
+        let mut diverged = false;
+        for _ in 0..20 {
+            simulator_1.generate_random_errors(&noise_model);
+            simulator_2.generate_random_errors(&noise_model);
+            simulator_3.generate_random_errors(&noise_model);
+            let error_pattern = simulator_1.generate_sparse_error_pattern().to_vec();
+            assert_eq!(simulator_2.generate_sparse_error_pattern().to_vec(), error_pattern);
+            diverged |= simulator_3.generate_sparse_error_pattern().to_vec() != error_pattern;
+        }
+        assert!(diverged, "a different seed should sample different errors");
+    }
+
+    #[test]
+    fn simulator_shot_from_index() {  // cargo test simulator_shot_from_index -- --nocapture
+        use crate::noise_model_builder::*;
+        use crate::rand_core::SeedableRng;
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(3, 5, 5));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), 0.05, 0.5, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        // sample the shots sequentially, jumping to the next stream after each shot
+        let base_seed = 1752;
+        let mut stream = Xoroshiro128StarStar::seed_from_u64(base_seed);
+        let mut sequential = Vec::new();
+        for _ in 0..20 {
+            simulator.rng = stream.clone();
+            simulator.generate_random_errors(&noise_model);
+            sequential.push((simulator.generate_sparse_error_pattern().to_vec(), simulator.generate_sparse_measurement().to_vec()));
+            stream.jump();
+        }
+        for index in [13, 0, 19, 7] {
+            let (error_pattern, measurement) = simulator.shot_from_index(&noise_model, base_seed, index);
+            assert_eq!((error_pattern.to_vec(), measurement.to_vec()), sequential[index]);
+        }
+        // a different base seed gives a different run
+        let different: Vec<_> = (0..20).map(|index| simulator.shot_from_index(&noise_model, base_seed + 1, index).0.to_vec()).collect();
+        assert!(different.iter().zip(sequential.iter()).any(|(error_pattern, (expected, _))| error_pattern != expected));
+    }
+
+    #[test]
+    fn simulator_validate_correction_at() {  // cargo test simulator_validate_correction_at -- --nocapture
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(3, 3, 3));
+        let top_t = simulator.height - 1;
+        let layer_t = 2 * simulator.measurement_cycles;
+        for (error_t, error) in [(0, Z), (1, X), (layer_t - 1, Y), (layer_t + 1, Z), (top_t - 1, X)] {
+            let position = pos!(error_t, 1, 1);  // on both logical operators of the standard planar code
+            simulator.clear_all_errors();
+            simulator.get_node_mut_unwrap(&position).set_error_temp(&error);
+            simulator.propagate_errors();
+            let no_correction = SparseCorrection::new();
+            let top_result = simulator.validate_correction(&no_correction);
+            let layer_result = simulator.validate_correction_at(&no_correction, layer_t);
+            assert!(top_result.0 || top_result.1, "a single-qubit error on the logical operator must be visible at the top");
+            if error_t < layer_t {  // an error entirely below the layer: both agree
+                assert_eq!(layer_result, top_result);
+            } else {  // an error above the layer only affects the top
+                assert_eq!(layer_result, (false, false));
+            }
+            // the Pauli frame at the layer always corrects the errors up to that layer
+            let layer_correction = simulator.generate_sparse_correction_at(layer_t);
+            assert!(layer_correction.is_at_layer(layer_t));
+            assert_eq!(simulator.validate_correction_at(&layer_correction, layer_t), (false, false));
+            assert_eq!(simulator.validate_correction(&simulator.generate_sparse_correction()), (false, false));
+            // a time-distributed correction is propagated to the layer before validation
+            let mut early_correction = SparseCorrection::new();
+            early_correction.add_cross_layer(pos!(0, position.i, position.j), error);
+            let expected = if error_t < layer_t { (false, false) } else { top_result };
+            assert_eq!(simulator.validate_correction_at(&early_correction, layer_t), expected);
+        }
+        simulator.clear_all_errors();
+    }
+
+    #[test]
+    fn simulator_frame_history() {  // cargo test simulator_frame_history -- --nocapture
+        use crate::noise_model_builder::*;
+        use crate::rand_core::SeedableRng;
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::StimNoiseModel.apply(&mut simulator, &mut noise_model, &json!({}), 0.02, 0.5, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        simulator.rng = Xoroshiro128StarStar::seed_from_u64(1734);
+        simulator.generate_random_errors(&noise_model);
+        let history = simulator.frame_history();
+        assert_eq!(history.len(), simulator.height);
+        assert!(history.iter().any(|pattern| pattern.len() > 0));
+        // the last entry restricted to the data qubits is the propagated pattern used by the validation
+        let top_data: Vec<(Position, ErrorType)> = history.last().unwrap().to_vec().into_iter()
+            .filter(|(position, _)| simulator.get_node_unwrap(position).qubit_type == QubitType::Data).collect();
+        assert_eq!(top_data, simulator.generate_sparse_correction().to_vec());
+        // each slice is the previous slice propagated through one layer of gates, followed by the errors of the gates in the previous layer
+        for t in 0..simulator.height - 1 {
+            let frame: BTreeMap<(usize, usize), ErrorType> = history[t].to_vec().into_iter().map(|(position, error)| ((position.i, position.j), error)).collect();
+            let mut frame = simulator.propagate_pauli_frame(&frame, t);
+            simulator_iter!(simulator, position, node, t => t, if node.error != I && !node.gate_type.is_initialization() {
+                let pauli = frame.entry((position.i, position.j)).or_insert(I);
+                *pauli = pauli.multiply(&node.error);
+            });
+            let expected: Vec<_> = frame.into_iter().filter(|(_, error)| *error != I).map(|((i, j), error)| (pos!(t + 1, i, j), error)).collect();
+            assert_eq!(history[t + 1].to_vec(), expected, "layer {}", t + 1);
+        }
+    }
+
+    #[test]
+    fn simulator_from_visualizer_component() {  // cargo test simulator_from_visualizer_component -- --nocapture
+        use crate::decoder_mwpm::*;
+        use crate::noise_model_builder::*;
+        let filepath = std::env::temp_dir().join("qecp_simulator_from_visualizer_component.json").to_str().unwrap().to_string();
+        let build_decoder = |simulator: &mut Simulator| -> (Arc<NoiseModel>, MWPMDecoder) {
+            let mut noise_model = NoiseModel::new(simulator);
+            NoiseModelBuilder::Phenomenological.apply(simulator, &mut noise_model, &json!({}), 0.02, 0.5, 0.);
+            simulator.compress_error_rates(&mut noise_model);
+            let noise_model = Arc::new(noise_model);
+            let mwpm_decoder = MWPMDecoder::new(simulator, Arc::clone(&noise_model), &json!({"emmd": 16}), 1, false);
+            (noise_model, mwpm_decoder)
+        };
+        // export some cases
+        let mut simulator = Simulator::new(CodeType::RotatedPlanarCode, CodeSize::new(2, 3, 3));
+        let (noise_model, mut mwpm_decoder) = build_decoder(&mut simulator);
+        let mut expected = Vec::new();
+        {
+            let mut visualizer = Visualizer::new(Some(filepath.clone())).unwrap();
+            visualizer.add_component(&simulator).unwrap();
+            while expected.len() < 5 {
+                simulator.generate_random_errors(&noise_model);
+                let sparse_measurement = simulator.generate_sparse_measurement();
+                if sparse_measurement.len() == 0 || sparse_measurement.len() > 16 {
+                    continue
+                }
+                let (correction, _) = mwpm_decoder.decode(&sparse_measurement);
+                let validation = simulator.validate_correction(&correction);
+                visualizer.add_case(json!({
+                    "error_pattern": simulator.generate_sparse_error_pattern(),
+                    "measurement": sparse_measurement,
+                    "correction": correction,
+                })).unwrap();
+                expected.push((json!(correction), validation));
+            }
+        }
+        // reload them into a fresh simulator and decode again
+        let component = Visualizer::load_component(&filepath, "simulator").unwrap();
+        let mut reloaded = Simulator::from_visualizer_component(&component).unwrap();
+        assert_eq!(json!(reloaded.component_info(false)), json!(simulator.component_info(false)));
+        let (noise_model, mut mwpm_decoder) = build_decoder(&mut reloaded);
+        for (index, (expected_correction, expected_validation)) in expected.iter().enumerate() {
+            let (error_pattern, measurement, correction) = Visualizer::load_case(&filepath, index + 1).unwrap();  // skip the empty case
+            assert_eq!(&json!(correction.unwrap()), expected_correction);
+            reloaded.load_sparse_error_pattern(&error_pattern, &noise_model).unwrap();
+            reloaded.clear_propagate_errors();
+            reloaded.propagate_errors();
+            assert_eq!(json!(reloaded.generate_sparse_measurement()), json!(measurement));
+            let (correction, _) = mwpm_decoder.decode(&measurement);
+            assert_eq!(&json!(correction), expected_correction);
+            assert_eq!(&reloaded.validate_correction(&correction), expected_validation);
+        }
+    }
+
+    #[test]
+    fn simulator_correction_normalize_to_layer() {  // cargo test simulator_correction_normalize_to_layer -- --nocapture
+        for code_type in [CodeType::StandardPlanarCode, CodeType::RotatedPlanarCode] {
+            let mut simulator = Simulator::new(code_type, CodeSize::new(2, 3, 3));
+            let top_t = simulator.height - 1;
+            let mut positions = Vec::new();
+            simulator_iter_real!(simulator, position, node, {
+                // the simulator ignores errors right after initialization
+                if position.t < top_t && !node.gate_type.is_initialization() {
+                    positions.push(position.clone());
+                }
+            });
+            for position in positions.iter() {
+                for error in [X, Z, Y] {
+                    // an error after the gate at `t` is the same as a Pauli frame before the gate at `t + 1`
+                    simulator.clear_all_errors();
+                    simulator.get_node_mut_unwrap(position).error = error;
+                    simulator.propagate_errors();
+                    let expected = simulator.generate_sparse_correction();
+                    let mut correction = SparseCorrection::new();
+                    let mut next_position = position.clone();
+                    next_position.t += 1;
+                    correction.add(next_position, error);
+                    let normalized = correction.normalize_to_layer(&simulator, top_t);
+                    assert_eq!(normalized.to_vec(), expected.to_vec(), "error {} at {}", error, position);
+                }
+            }
+            // a correction distributed over time validates the same as its normalized form
+            simulator.clear_all_errors();
+            let mut data_qubits = Vec::new();
+            simulator_iter_real!(simulator, position, node, t => top_t, if node.qubit_type == QubitType::Data {
+                data_qubits.push((position.i, position.j));
+            });
+            let mut mixed = SparseCorrection::new();
+            mixed.add_cross_layer(pos!(0, data_qubits[0].0, data_qubits[0].1), X);
+            mixed.add_cross_layer(pos!(top_t, data_qubits[0].0, data_qubits[0].1), Z);
+            mixed.add_cross_layer(pos!(top_t - 6, data_qubits[2].0, data_qubits[2].1), Y);
+            let normalized = mixed.normalize_to_layer(&simulator, top_t);
+            assert!(normalized.is_at_layer(top_t));
+            assert_eq!(simulator.validate_correction(&mixed), simulator.validate_correction(&normalized));
+        }
+    }
+
+    #[test]
+    fn simulator_correction_optimize() {  // cargo test simulator_correction_optimize -- --nocapture
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 5, 5));
+        let top_t = simulator.height - 1;
+        let mut data_qubits = Vec::new();
+        simulator_iter_real!(simulator, position, node, t => top_t, if node.qubit_type == QubitType::Data {
+            data_qubits.push(position.clone());
+        });
+        // overlapping corrections as a decoder emits when two matched paths share qubits
+        let mut correction = SparseCorrection::new();
+        for (index, error) in [(0, X), (1, Y), (0, X), (1, Z), (2, Z), (3, X), (3, X)] {
+            correction.add(data_qubits[index].clone(), error);
+        }
+        assert_eq!(correction.len(), 4);
+        let mut optimized = correction.clone();
+        assert_eq!(optimized.optimize(), 2, "the X operators of qubit 0 and qubit 3 cancel");
+        assert_eq!(optimized.to_vec(), vec![(data_qubits[1].clone(), X), (data_qubits[2].clone(), Z)]);
+        assert_eq!(optimized.optimize(), 0, "nothing left to eliminate");
+        // the optimization never changes the logical effect of the correction
+        simulator.clear_all_errors();
+        for (position, error) in [(&data_qubits[1], X), (&data_qubits[4], Z)] {
+            let mut position = position.clone();
+            position.t = top_t - 1;
+            simulator.get_node_mut_unwrap(&position).error = error;
+        }
+        simulator.propagate_errors();
+        assert_eq!(simulator.validate_correction(&correction), simulator.validate_correction(&optimized));
+    }
+
+    /// without noise in the final round, the outcomes reconstructed from the data readout are exactly the perfect measurement
+    #[test]
+    fn simulator_noisy_final_round_data_readout() {  // cargo test simulator_noisy_final_round_data_readout -- --nocapture
+        use crate::rand_core::SeedableRng;
+        for code_type in [CodeType::StandardPlanarCode, CodeType::RotatedPlanarCode, CodeType::RotatedTailoredCode] {
+            let mut simulator = Simulator::new(code_type, CodeSize::new(2, 5, 5));
+            let mut noise_model = NoiseModel::new(&simulator);
+            simulator.set_error_rates(&mut noise_model, 0.01, 0.01, 0.01, 0.);
+            simulator.rng = Xoroshiro128StarStar::seed_from_u64(1730);
+            let mut top_defects = 0;
+            for _ in 0..50 {
+                simulator.generate_random_errors(&noise_model);
+                let sparse_measurement = simulator.generate_sparse_measurement();
+                let sparse_measurement_virtual = simulator.generate_sparse_measurement_virtual();
+                let round_bitstrings = simulator.round_bitstrings();
+                simulator.noisy_final_round = true;
+                assert_eq!(simulator.generate_sparse_measurement().to_vec(), sparse_measurement.to_vec());
+                assert_eq!(simulator.generate_sparse_measurement_virtual().to_vec(), sparse_measurement_virtual.to_vec());
+                assert_eq!(simulator.round_bitstrings(), round_bitstrings);
+                simulator.noisy_final_round = false;
+                top_defects += sparse_measurement.round_defects(simulator.height - 1).count();
+            }
+            assert!(top_defects > 0);
+        }
+    }
+
+    #[test]
+    fn simulator_check_basis_consistency() {  // cargo test simulator_check_basis_consistency -- --nocapture
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(3, 3, 3));
+        assert_eq!(simulator.check_basis_consistency(), Ok(()));
+        let mut measurement = None;
+        simulator_iter_real!(simulator, position, node, t => simulator.measurement_cycles, if node.gate_type.is_measurement() {
+            measurement = Some(position.clone());
+        });
+        let measurement = measurement.unwrap();
+        let node = simulator.get_node_mut_unwrap(&measurement);
+        node.gate_type = if node.gate_type == GateType::MeasureX { GateType::MeasureZ } else { GateType::MeasureX };
+        let error = simulator.check_basis_consistency().unwrap_err();
+        assert!(error.contains(&measurement.to_string()), "error should point to the edited node: {}", error);
+        assert!(code_builder_sanity_check(&simulator).is_err());
+    }
+
+    #[test]
+    fn simulator_detector_to_ancilla_map() {  // cargo test simulator_detector_to_ancilla_map -- --nocapture
+        for code_type in [CodeType::StandardPlanarCode, CodeType::RotatedPlanarCode] {
+            let noisy_measurements = 3;
+            let simulator = Simulator::new(code_type, CodeSize::new(noisy_measurements, 5, 5));
+            let map = simulator.detector_to_ancilla_map();
+            let mut rounds = BTreeMap::<usize, Vec<(usize, usize)>>::new();
+            for (detector, ancilla) in map.iter() {
+                assert_eq!(detector.t % simulator.measurement_cycles, 0);
+                let ancilla_node = simulator.get_node_unwrap(&pos!(detector.t, ancilla.0, ancilla.1));
+                assert!(ancilla_node.gate_type.is_measurement() && ancilla_node.qubit_type != QubitType::Data);
+                rounds.entry(detector.t).or_default().push(*ancilla);
+            }
+            // the noisy rounds and the final perfect round
+            assert_eq!(rounds.len(), noisy_measurements + 1);
+            let first_round: BTreeSet<(usize, usize)> = rounds.values().next().unwrap().iter().cloned().collect();
+            assert_eq!(first_round.len(), simulator.stabilizer_order().len(), "injective within a round");
+            for ancillas in rounds.values() {
+                assert_eq!(ancillas.len(), first_round.len());
+                assert_eq!(ancillas.iter().cloned().collect::<BTreeSet<_>>(), first_round, "stable across rounds");
+            }
+        }
+    }
+
+    #[test]
+    fn simulator_boundary_type_of() {  // cargo test simulator_boundary_type_of -- --nocapture
+        let d = 5;
+        let simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(0, d, d));
+        let (first, last) = (1, 2 * d - 1);  // the real data qubits are in [1, 2d-1] of both axes
+        let mut counts = (0, 0, 0);
+        simulator_iter_real!(simulator, position, node, t => 0, if node.qubit_type == QubitType::Data {
+            let on_top_bottom = position.i == first || position.i == last;
+            let on_left_right = position.j == first || position.j == last;
+            let expected = match (on_top_bottom, on_left_right) {
+                (true, true) => Some(BoundaryType::Corner),
+                (true, false) => Some(BoundaryType::Rough),
+                (false, true) => Some(BoundaryType::Smooth),
+                (false, false) => None,
+            };
+            assert_eq!(simulator.boundary_type_of(position), expected, "data qubit at {}", position);
+            match expected {
+                Some(BoundaryType::Rough) => counts.0 += 1,
+                Some(BoundaryType::Smooth) => counts.1 += 1,
+                Some(BoundaryType::Corner) => counts.2 += 1,
+                None => { },
+            }
+        });
+        assert_eq!(counts, (2 * (d - 2), 2 * (d - 2), 4));
+        // stabilizers have no boundary type
+        assert_eq!(simulator.boundary_type_of(&pos!(0, 1, 2)), None);
+        // the same in every round
+        assert_eq!(simulator.boundary_type_of(&pos!(simulator.height - 1, 1, 3)), Some(BoundaryType::Rough));
+        // non-CSS codes are not supported
+        let simulator = Simulator::new(CodeType::StandardXZZXCode, CodeSize::new(0, d, d));
+        assert_eq!(simulator.get_node_unwrap(&pos!(0, 1, 3)).qubit_type, QubitType::Data);
+        assert_eq!(simulator.boundary_type_of(&pos!(0, 1, 3)), None);
+    }
+
+    #[test]
+    fn simulator_missing_first_measurement() {  // cargo test simulator_missing_first_measurement -- --nocapture
+        // a customized code whose stabilizer is not measured at `t = 0`: the backward search used to underflow
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 3, 3));
+        simulator.code_type = CodeType::Customized;
+        let stabilizer = pos!(0, 1, 2);
+        assert!(simulator.get_node_unwrap(&stabilizer).gate_type.is_measurement());
+        simulator.get_node_mut_unwrap(&stabilizer).gate_type = GateType::None;
+        simulator.clear_all_errors();
+        simulator.propagate_errors();
+        assert_eq!(simulator.generate_sparse_measurement().len(), 0, "compared against the initial value when no previous measurement");
+        // the full and the fast measurement agree on every single error
+        let mut positions = Vec::new();
+        simulator_iter!(simulator, position, _node, if position.t < simulator.height - 1 {
+            positions.push(position.clone());
+        });
+        let mut checked_defects = 0;
+        for position in positions.iter() {
+            for error in [X, Z] {
+                simulator.get_node_mut_unwrap(position).error = error;
+                simulator.propagate_errors();
+                let sparse_measurement = simulator.generate_sparse_measurement();
+                let sparse_measurement_virtual = simulator.generate_sparse_measurement_virtual();
+                simulator.clear_all_errors();
+                let mut pattern = SparseErrorPattern::new();
+                pattern.add(position.clone(), error);
+                let (_, fast_measurement, fast_measurement_virtual) = simulator.fast_measurement_given_few_errors(&pattern);
+                assert_eq!(fast_measurement.to_vec(), sparse_measurement.to_vec(), "{} at {}", error, position);
+                assert_eq!(fast_measurement_virtual.to_vec(), sparse_measurement_virtual.to_vec(), "{} at {}", error, position);
+                if sparse_measurement.defects.contains(&pos!(simulator.measurement_cycles, stabilizer.i, stabilizer.j)) {
+                    checked_defects += 1;
+                }
+            }
+        }
+        assert!(checked_defects > 0, "errors before the first measurement should be detected by the missing stabilizer");
+    }
+
 }
 
 #[cfg(feature="python_binding")]
@@ -1598,6 +3151,7 @@ pub(crate) fn register(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<GateType>()?;
     m.add_class::<SparseMeasurement>()?;
     m.add_class::<SparseErasures>()?;
+    m.add_class::<SparseDetectorDropout>()?;
     m.add_class::<SparseErrorPattern>()?;
     m.add_class::<SparseCorrection>()?;
     Ok(())
@@ -184,6 +184,7 @@ impl SimulatorGenerics for SimulatorCompact {
 
 impl SimulatorCompact {
     pub fn from_simulator(mut simulator: Simulator, noise_model: Arc<NoiseModel>, parallel: usize) -> Self {
+        assert!(noise_model.feedforwards.is_empty(), "feedforwards depend on measurement outcomes and cannot be expressed as independent error sources");
         let mut simulator_compact = Self {
             error_sources: vec![],
             rng: Xoroshiro128StarStar::new(),
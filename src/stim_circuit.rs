@@ -0,0 +1,574 @@
+//! Stim Circuit
+//!
+//! Exports the circuit of a [`Simulator`] with its [`NoiseModel`] in the circuit file format of Stim
+//! (<https://github.com/quantumlib/Stim/blob/main/doc/file_format_stim_circuit.md>), so that the same experiment can be sampled and decoded
+//! by Stim-based tools. Each layer `t` becomes the gates of the layer, followed by the errors after the gates and a `TICK`. Each real
+//! stabilizer measurement of a measurement round is a `DETECTOR` comparing it with the previous measurement of the same stabilizer, the
+//! same as [`SimulatorGenerics::generate_sparse_measurement`], with the coordinates `(i, j, t)` of the measurement node. Each logical operator
+//! of [`code_builder_logical_operators`] at the top layer becomes an `OBSERVABLE_INCLUDE` with Pauli targets (supported since Stim v1.15), which
+//! is flipped whenever the errors anticommute with it, the same as the logical errors reported by [`SimulatorGenerics::validate_correction`].
+//!
+//! The detector error model of the same circuit is given in the Stim format
+//! (<https://github.com/quantumlib/Stim/blob/main/doc/file_format_dem_detector_error_model.md>) by [`Simulator::build_detector_error_model`],
+//! using the same detector indices, so that it can be fed into external decoders.
+//!
+
+use super::simulator::*;
+use super::noise_model::*;
+use super::types::*;
+use super::code_builder::*;
+use super::model_graph::weight_function;
+use super::model_hypergraph::*;
+use super::position_indexer::DetectorMap;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use serde::{Serialize, Deserialize};
+use ErrorType::*;
+
+
+impl Simulator {
+    /// the circuit in the Stim format, see the module documentation. Virtual qubits are not included, because errors never propagate from them
+    /// to the real qubits. The perfect measurement round at `t = 0` is an `MPP` of the stabilizers on the data qubits, so that the first
+    /// detectors are deterministic; the top-layer outcomes of [`Simulator::noisy_final_round`] are given by an `MPP` as well. The errors on
+    /// the initialization nodes are discarded by the simulator and thus not emitted, and an erasure becomes a fully depolarizing error
+    /// since Stim cannot herald it; return error if the noise model has additional noise or feedforwards, which have no Stim instruction
+    pub fn to_stim_circuit(&self, noise_model: &NoiseModel) -> Result<String, String> {
+        if !noise_model.additional_noise.is_empty() {
+            return Err("additional noise is not supported in Stim circuit".to_string())
+        }
+        if !noise_model.feedforwards.is_empty() {
+            return Err("feedforward is not supported in Stim circuit".to_string())
+        }
+        let mut circuit = String::new();
+        let mut qubits = BTreeMap::<(usize, usize), usize>::new();
+        simulator_iter_real!(self, position, _node, t => 0, {
+            let qubit = qubits.len();
+            circuit += &format!("QUBIT_COORDS({}, {}) {}\n", position.i, position.j, qubit);
+            qubits.insert((position.i, position.j), qubit);
+        });
+        let mut records = BTreeMap::<Position, usize>::new();  // the index of each measurement in the measurement record
+        let top_t = self.height - 1;
+        for t in 0..self.height {
+            let mut resets: [Vec<usize>; 2] = Default::default();  // R, RX
+            let mut two_qubit_gates: [Vec<(usize, usize)>; 3] = Default::default();  // CX, CY, CZ
+            let mut measurements: [Vec<(Position, String)>; 3] = Default::default();  // M, MX, MPP
+            simulator_iter_real!(self, position, node, t => t, {
+                let qubit = qubits[&(position.i, position.j)];
+                match node.gate_type {
+                    GateType::None => { },
+                    GateType::InitializeZ => { resets[0].push(qubit); },
+                    GateType::InitializeX => { resets[1].push(qubit); },
+                    GateType::MeasureZ | GateType::MeasureX if t == 0 || (t == top_t && self.noisy_final_round) => {
+                        measurements[2].push((position.clone(), self.stim_stabilizer_product(position, &qubits)));
+                    },
+                    GateType::MeasureZ => { measurements[0].push((position.clone(), qubit.to_string())); },
+                    GateType::MeasureX => { measurements[1].push((position.clone(), qubit.to_string())); },
+                    gate_type => {
+                        let peer_position = node.gate_peer.as_ref().expect("two-qubit gate must have a peer");
+                        if !self.is_node_real(peer_position) {
+                            continue  // the gate with virtual peer is physically idle
+                        }
+                        let peer = qubits[&(peer_position.i, peer_position.j)];
+                        match gate_type {
+                            GateType::CXGateControl => { two_qubit_gates[0].push((qubit, peer)); },
+                            GateType::CYGateControl => { two_qubit_gates[1].push((qubit, peer)); },
+                            GateType::CZGate if *position < **peer_position => { two_qubit_gates[2].push((qubit, peer)); },
+                            _ => { },  // added together with the peer
+                        }
+                    },
+                }
+            });
+            for (name, targets) in ["R", "RX"].iter().zip(resets.iter()) {
+                if !targets.is_empty() {
+                    circuit += &format!("{} {}\n", name, targets.iter().map(|qubit| qubit.to_string()).collect::<Vec<_>>().join(" "));
+                }
+            }
+            for (name, pairs) in ["CX", "CY", "CZ"].iter().zip(two_qubit_gates.iter()) {
+                if !pairs.is_empty() {
+                    circuit += &format!("{} {}\n", name, pairs.iter().map(|(qubit, peer)| format!("{} {}", qubit, peer)).collect::<Vec<_>>().join(" "));
+                }
+            }
+            for (name, targets) in ["M", "MX", "MPP"].iter().zip(measurements.iter()) {
+                if !targets.is_empty() {
+                    for (position, _) in targets.iter() {
+                        let record = records.len();
+                        records.insert(position.clone(), record);
+                    }
+                    circuit += &format!("{} {}\n", name, targets.iter().map(|(_, target)| target.as_str()).collect::<Vec<_>>().join(" "));
+                }
+            }
+            if t >= self.measurement_cycles && t % self.measurement_cycles == 0 {
+                simulator_iter_real!(self, position, node, t => t, if node.gate_type.is_measurement() {
+                    let mut compared = vec![records[position]];
+                    let mut previous_position = position.clone();
+                    while let Some(previous_t) = previous_position.t.checked_sub(self.measurement_cycles) {
+                        previous_position.t = previous_t;
+                        if let Some(record) = records.get(&previous_position) {
+                            compared.push(*record);
+                            break
+                        }
+                    }
+                    circuit += &format!("DETECTOR({}, {}, {}) {}\n", position.i, position.j, position.t
+                        , compared.iter().map(|record| format!("rec[-{}]", records.len() - record)).collect::<Vec<_>>().join(" "));
+                });
+            }
+            if t == top_t {
+                let logical_operators = code_builder_logical_operators(self).unwrap_or_default();
+                for (index, logical_operator) in logical_operators.iter().enumerate() {
+                    circuit += &format!("OBSERVABLE_INCLUDE({}) {}\n", index, logical_operator.iter().filter(|(_, pauli)| **pauli != I)
+                        .map(|(position, pauli)| format!("{}{}", pauli, qubits[&(position.i, position.j)])).collect::<Vec<_>>().join(" "));
+                }
+                break  // errors at the top layer are never measured
+            }
+            // single-qubit Pauli channels commute with each other, so they are grouped by the instruction
+            let mut channels = Vec::<(String, Vec<usize>)>::new();
+            let mut correlated_errors = String::new();
+            simulator_iter_real!(self, position, node, t => t, if !node.gate_type.is_initialization() {
+                let qubit = qubits[&(position.i, position.j)];
+                let noise_model_node = noise_model.get_node_unwrap(position);
+                let rates = &noise_model_node.pauli_error_rates;
+                let erasure_error_rate = noise_model.erasure_error_rate(position, self.measurement_cycles);
+                let mut add_channel = |px: f64, py: f64, pz: f64, qubit: usize| {
+                    if let Some(instruction) = stim_pauli_channel_1(px, py, pz) {
+                        match channels.iter_mut().find(|(existing, _)| existing == &instruction) {
+                            Some((_, targets)) => targets.push(qubit),
+                            None => channels.push((instruction, vec![qubit])),
+                        }
+                    }
+                };
+                add_channel(rates.error_rate_X, rates.error_rate_Y, rates.error_rate_Z, qubit);
+                add_channel(erasure_error_rate / 4., erasure_error_rate / 4., erasure_error_rate / 4., qubit);
+                let peer = node.gate_peer.as_ref().filter(|peer_position| self.is_node_real(peer_position))
+                    .map(|peer_position| qubits[&(peer_position.i, peer_position.j)]);
+                let mut correlated_terms = Vec::new();
+                if let Some(correlated_pauli_error_rates) = &noise_model_node.correlated_pauli_error_rates {
+                    correlated_terms.push(CorrelatedPauliErrorType::all_possible_errors().iter().map(|error_type| {
+                        (correlated_pauli_error_rates.error_rate(error_type), error_type.my_error(), error_type.peer_error())
+                    }).collect::<Vec<_>>());
+                }
+                if let Some(correlated_erasure_error_rates) = &noise_model_node.correlated_erasure_error_rates {
+                    correlated_terms.push(stim_correlated_erasure_terms(correlated_erasure_error_rates));
+                }
+                for terms in correlated_terms.iter() {
+                    match peer {
+                        Some(peer) => { correlated_errors += &stim_correlated_error_chain(terms, qubit, peer); },
+                        None => {  // the error on the virtual peer is never measured, leaving the marginal distribution on this qubit
+                            let marginal = |pauli: ErrorType| terms.iter().filter(|(_, my_error, _)| *my_error == pauli).map(|(p, _, _)| p).sum::<f64>();
+                            add_channel(marginal(X), marginal(Y), marginal(Z), qubit);
+                        },
+                    }
+                }
+            });
+            for (instruction, targets) in channels.iter() {
+                circuit += &format!("{} {}\n", instruction, targets.iter().map(|qubit| qubit.to_string()).collect::<Vec<_>>().join(" "));
+            }
+            circuit += &correlated_errors;
+            circuit += "TICK\n";
+        }
+        Ok(circuit)
+    }
+
+    /// the detector error model of the circuit, reusing the exhaustive single-error propagation of [`ModelHypergraph`]: the errors flipping
+    /// the same detectors and the same logical observables are merged as independent errors, while those flipping the same detectors but
+    /// different observables are kept apart; the observables flipped by an error are given by its correction, see
+    /// [`SparseCorrection::flipped_observables`]. The errors that flip no detector are not included, neither are the erasure errors
+    pub fn build_detector_error_model(&self, noise_model: &NoiseModel) -> DetectorErrorModel {
+        let mut simulator = self.clone();
+        let mut model_hypergraph = ModelHypergraph::new(&simulator);
+        model_hypergraph.build_with_weight_function(&mut simulator, Arc::new(noise_model.clone()), weight_function::unweighted, 1, false, false);
+        let detector_map = DetectorMap::from_simulator(self);
+        let logical_operators = code_builder_logical_operators(self).unwrap_or_default();
+        let mut probabilities = BTreeMap::<(Vec<usize>, Vec<usize>), f64>::new();
+        for (defect_vertices, hyperedge_group) in model_hypergraph.weighted_edges.iter() {
+            let mut detectors: Vec<usize> = defect_vertices.positions().iter().map(|position| {
+                detector_map.index(position).expect("model hypergraph edge between non-detector nodes")
+            }).collect();
+            detectors.sort();
+            for hyperedge in hyperedge_group.all_hyperedges.iter() {
+                let observables = hyperedge.correction.flipped_observables(&logical_operators);
+                let probability = probabilities.entry((detectors.clone(), observables)).or_insert(0.);
+                *probability = *probability * (1. - hyperedge.probability) + hyperedge.probability * (1. - *probability);  // XOR
+            }
+        }
+        DetectorErrorModel {
+            detectors: detector_map.positions().to_vec(),
+            observables: logical_operators.len(),
+            hyperedges: probabilities.into_iter().filter(|(_, probability)| *probability > 0.).map(|((detectors, observables), probability)| {
+                DetectorErrorModelHyperedge { probability, detectors, observables }
+            }).collect(),
+        }
+    }
+
+    /// the Pauli product on the data qubits that the stabilizer measurement at `position` measures, given by the two-qubit gates of its
+    /// measurement round (the first round for `t = 0`), e.g. `Z0*Z2*Z4` in Stim; like [`Simulator::stabilizer_outcome`] of the noisy final
+    /// round, the errors on the data qubits are propagated to the ancilla through noiseless gates
+    fn stim_stabilizer_product(&self, position: &Position, qubits: &BTreeMap<(usize, usize), usize>) -> String {
+        let end_t = if position.t == 0 { self.measurement_cycles } else { position.t };
+        let measurement = self.get_node_unwrap(position).gate_type;
+        let mut flipped_by = BTreeMap::<(usize, usize), (bool, bool)>::new();  // whether the X and Z errors of the data qubit flip the outcome
+        for t in end_t + 1 - self.measurement_cycles .. end_t {
+            let node = self.get_node_unwrap(&pos!(t, position.i, position.j));
+            if node.gate_type.is_two_qubit_gate() {
+                let peer_position = node.gate_peer.as_ref().expect("two-qubit gate must have a peer");
+                let peer_node = self.get_node_unwrap(peer_position);
+                if peer_node.is_virtual {
+                    continue
+                }
+                let flips = flipped_by.entry((peer_position.i, peer_position.j)).or_insert((false, false));
+                flips.0 ^= measurement.stabilizer_measurement(&peer_node.gate_type.propagate_peer(&X));
+                flips.1 ^= measurement.stabilizer_measurement(&peer_node.gate_type.propagate_peer(&Z));
+            }
+        }
+        flipped_by.iter().filter_map(|(ij, flips)| {
+            let pauli = match flips {
+                (true, false) => Z,
+                (false, true) => X,
+                (true, true) => Y,
+                (false, false) => return None,
+            };
+            Some(format!("{}{}", pauli, qubits[ij]))
+        }).collect::<Vec<_>>().join("*")
+    }
+}
+
+/// the independent error mechanisms of a circuit as hyperedges over its detectors, see [`Simulator::build_detector_error_model`];
+/// it's printed in the Stim format by `Display`, e.g. `error(0.001) D0 D5 L0` followed by the coordinates `detector(i, j, t) D0`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DetectorErrorModel {
+    /// the position of each detector, indexed the same as [`DetectorMap`] and the `DETECTOR`s of [`Simulator::to_stim_circuit`]
+    pub detectors: Vec<Position>,
+    /// the number of logical observables, one for each logical operator of [`code_builder_logical_operators`]
+    pub observables: usize,
+    /// sorted by the detectors and then the observables
+    pub hyperedges: Vec<DetectorErrorModelHyperedge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DetectorErrorModelHyperedge {
+    /// the probability of this error mechanism to happen
+    pub probability: f64,
+    /// the sorted indices of the flipped detectors; an edge matching to a virtual node (boundary) has a single detector
+    pub detectors: Vec<usize>,
+    /// the sorted indices of the flipped logical observables
+    pub observables: Vec<usize>,
+}
+
+impl std::fmt::Display for DetectorErrorModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for hyperedge in self.hyperedges.iter() {
+            write!(f, "error({})", stim_probability(hyperedge.probability))?;
+            for detector in hyperedge.detectors.iter() {
+                write!(f, " D{}", detector)?;
+            }
+            for observable in hyperedge.observables.iter() {
+                write!(f, " L{}", observable)?;
+            }
+            writeln!(f)?;
+        }
+        for (index, position) in self.detectors.iter().enumerate() {
+            writeln!(f, "detector({}, {}, {}) D{}", position.i, position.j, position.t, index)?;
+        }
+        for observable in 0..self.observables {
+            writeln!(f, "logical_observable L{}", observable)?;
+        }
+        Ok(())
+    }
+}
+
+/// print a probability, using the scientific notation only for tiny numbers like `1e-300`
+fn stim_probability(probability: f64) -> String {
+    if probability == 0. || probability >= 1e-4 { format!("{}", probability) } else { format!("{:e}", probability) }
+}
+
+/// the instruction (without targets) of a single-qubit Pauli channel, or None if it's noiseless
+fn stim_pauli_channel_1(px: f64, py: f64, pz: f64) -> Option<String> {
+    match (px > 0., py > 0., pz > 0.) {
+        (false, false, false) => None,
+        (true, false, false) => Some(format!("X_ERROR({})", stim_probability(px))),
+        (false, true, false) => Some(format!("Y_ERROR({})", stim_probability(py))),
+        (false, false, true) => Some(format!("Z_ERROR({})", stim_probability(pz))),
+        _ if px == py && py == pz => Some(format!("DEPOLARIZE1({})", stim_probability(3. * px))),
+        _ => Some(format!("PAULI_CHANNEL_1({}, {}, {})", stim_probability(px), stim_probability(py), stim_probability(pz))),
+    }
+}
+
+/// the mutually exclusive two-qubit Pauli errors `(probability, my error, peer error)` of a correlated erasure, where an erased qubit
+/// suffers one of `I`, `X`, `Y` and `Z` uniformly at random
+fn stim_correlated_erasure_terms(rates: &CorrelatedErasureErrorRates) -> Vec<(f64, ErrorType, ErrorType)> {
+    let mut terms = Vec::new();
+    for pauli in [X, Y, Z] {
+        terms.push((rates.error_rate_EI / 4., pauli, I));
+        terms.push((rates.error_rate_IE / 4., I, pauli));
+    }
+    for my_error in [I, X, Y, Z] {
+        for peer_error in [I, X, Y, Z] {
+            if my_error != I || peer_error != I {
+                terms.push((rates.error_rate_EE / 16., my_error, peer_error));
+            }
+        }
+    }
+    terms
+}
+
+/// the mutually exclusive two-qubit Pauli errors `(probability, my error, peer error)` as a chain of `CORRELATED_ERROR` followed by
+/// `ELSE_CORRELATED_ERROR`s, each with the probability conditioned on none of the previous errors happening
+fn stim_correlated_error_chain(terms: &[(f64, ErrorType, ErrorType)], qubit: usize, peer: usize) -> String {
+    let mut chain = String::new();
+    let mut remaining = 1.;
+    for (probability, my_error, peer_error) in terms.iter().filter(|(probability, _, _)| *probability > 0.) {
+        let targets: Vec<String> = [(my_error, qubit), (peer_error, peer)].iter().filter(|(error, _)| **error != I)
+            .map(|(error, target)| format!("{}{}", error, target)).collect();
+        chain += &format!("{}({}) {}\n", if chain.is_empty() { "CORRELATED_ERROR" } else { "ELSE_CORRELATED_ERROR" }
+            , stim_probability((probability / remaining).min(1.)), targets.join(" "));
+        remaining -= probability;
+    }
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::noise_model_builder::*;
+    use super::super::reproducible_rand::Xoroshiro128StarStar;
+    use crate::rand_core::SeedableRng;
+
+    /// a minimal checker of the instructions emitted by [`Simulator::to_stim_circuit`]: it validates the syntax and the targets of every
+    /// line, then runs a Pauli frame simulation that ignores the noise channels and applies `errors` of layer `t` right before the `t`-th
+    /// `TICK` instead, returning the coordinates of the fired detectors and the indices of the flipped observables
+    fn stim_fired_detectors(circuit: &str, errors: &SparseErrorPattern) -> (Vec<Position>, Vec<usize>) {
+        let mut qubits = BTreeMap::<(usize, usize), usize>::new();
+        let mut frame: Vec<(bool, bool)> = Vec::new();  // whether each qubit has X and Z component of error
+        let mut records: Vec<bool> = Vec::new();
+        let mut fired = Vec::new();
+        let mut flipped_observables = Vec::new();
+        let mut tick = 0;
+        let mut previous_name = "";
+        let apply_errors = |tick: usize, frame: &mut Vec<(bool, bool)>, qubits: &BTreeMap<(usize, usize), usize>| {
+            for (position, error) in errors.iter().filter(|(position, _)| position.t == tick) {
+                let qubit = qubits[&(position.i, position.j)];
+                frame[qubit].0 ^= matches!(error, X | Y);
+                frame[qubit].1 ^= matches!(error, Z | Y);
+            }
+        };
+        for line in circuit.lines() {
+            let (name, arguments, targets) = match line.find('(') {
+                Some(open) if !line[..open].contains(' ') => {
+                    let close = line.find(')').unwrap_or_else(|| panic!("unclosed arguments: {}", line));
+                    let arguments: Vec<f64> = line[open+1..close].split(',').map(|argument| argument.trim().parse()
+                        .unwrap_or_else(|_| panic!("invalid argument: {}", line))).collect();
+                    (&line[..open], arguments, line[close+1..].split_whitespace().collect::<Vec<_>>())
+                },
+                _ => {
+                    let mut tokens = line.split_whitespace();
+                    (tokens.next().expect("empty line"), vec![], tokens.collect::<Vec<_>>())
+                },
+            };
+            let qubit_count = frame.len();
+            let qubit_of = |target: &str| -> usize {
+                let qubit: usize = target.parse().unwrap_or_else(|_| panic!("invalid qubit target: {}", line));
+                assert!(qubit < qubit_count, "qubit {} not defined: {}", qubit, line);
+                qubit
+            };
+            let pauli_target_of = |target: &str| -> (ErrorType, usize) {
+                let pauli = match &target[..1] { "X" => X, "Y" => Y, "Z" => Z, _ => panic!("invalid Pauli target: {}", line) };
+                (pauli, qubit_of(&target[1..]))
+            };
+            if name.ends_with("_ERROR") || name.starts_with("DEPOLARIZE") || name == "PAULI_CHANNEL_1" {
+                assert!(!arguments.is_empty() && arguments.iter().all(|p| (0. ..=1.).contains(p)), "invalid probability: {}", line);
+                assert!(!targets.is_empty(), "no target: {}", line);
+            }
+            match name {
+                "QUBIT_COORDS" => {
+                    assert_eq!(arguments.len(), 2, "{}", line);
+                    assert_eq!(targets, vec![frame.len().to_string()], "qubits must be defined in order: {}", line);
+                    qubits.insert((arguments[0] as usize, arguments[1] as usize), frame.len());
+                    frame.push((false, false));
+                },
+                "R" | "RX" => {
+                    for target in targets.iter() {
+                        frame[qubit_of(target)] = (false, false);
+                    }
+                },
+                "CX" | "CY" | "CZ" => {
+                    assert!(targets.len() % 2 == 0, "odd number of targets: {}", line);
+                    for pair in targets.chunks(2) {
+                        let (control, target) = (qubit_of(pair[0]), qubit_of(pair[1]));
+                        assert_ne!(control, target, "{}", line);
+                        let ((xc, zc), (xt, zt)) = (frame[control], frame[target]);
+                        match name {
+                            "CX" => { frame[target].0 = xt ^ xc; frame[control].1 = zc ^ zt; },
+                            "CY" => { frame[target] = (xt ^ xc, zt ^ xc); frame[control].1 = zc ^ xt ^ zt; },
+                            _ => { frame[control].1 = zc ^ xt; frame[target].1 = zt ^ xc; },
+                        }
+                    }
+                },
+                "M" => { records.extend(targets.iter().map(|target| frame[qubit_of(target)].0)); },
+                "MX" => { records.extend(targets.iter().map(|target| frame[qubit_of(target)].1)); },
+                "MPP" => {
+                    for product in targets.iter() {
+                        records.push(product.split('*').fold(false, |flipped, target| {
+                            let (pauli, qubit) = pauli_target_of(target);
+                            flipped ^ pauli.anticommute(&match frame[qubit] { (false, false) => I, (true, false) => X, (false, true) => Z, _ => Y })
+                        }));
+                    }
+                },
+                "DETECTOR" => {
+                    assert_eq!(arguments.len(), 3, "{}", line);
+                    let value = targets.iter().fold(false, |value, target| {
+                        let lookback: usize = target.strip_prefix("rec[-").and_then(|target| target.strip_suffix(']')).and_then(|k| k.parse().ok())
+                            .unwrap_or_else(|| panic!("invalid record target: {}", line));
+                        assert!(lookback >= 1 && lookback <= records.len(), "record out of range: {}", line);
+                        value ^ records[records.len() - lookback]
+                    });
+                    if value {
+                        fired.push(pos!(arguments[2] as usize, arguments[0] as usize, arguments[1] as usize));
+                    }
+                },
+                "OBSERVABLE_INCLUDE" => {
+                    assert_eq!(arguments.len(), 1, "{}", line);
+                    let flipped = targets.iter().fold(false, |flipped, target| {
+                        let (pauli, qubit) = pauli_target_of(target);
+                        flipped ^ pauli.anticommute(&match frame[qubit] { (false, false) => I, (true, false) => X, (false, true) => Z, _ => Y })
+                    });
+                    if flipped {
+                        flipped_observables.push(arguments[0] as usize);
+                    }
+                },
+                "X_ERROR" | "Y_ERROR" | "Z_ERROR" | "DEPOLARIZE1" | "PAULI_CHANNEL_1" => {
+                    assert_eq!(arguments.len(), if name == "PAULI_CHANNEL_1" { 3 } else { 1 }, "{}", line);
+                    targets.iter().for_each(|target| { qubit_of(target); });
+                },
+                "CORRELATED_ERROR" | "ELSE_CORRELATED_ERROR" => {
+                    assert_eq!(arguments.len(), 1, "{}", line);
+                    assert!(name == "CORRELATED_ERROR" || previous_name.ends_with("CORRELATED_ERROR"), "dangling {}", line);
+                    targets.iter().for_each(|target| { pauli_target_of(target); });
+                },
+                "TICK" => {
+                    apply_errors(tick, &mut frame, &qubits);
+                    tick += 1;
+                },
+                _ => panic!("unknown instruction: {}", line),
+            }
+            previous_name = name;
+        }
+        fired.sort();
+        (fired, flipped_observables)
+    }
+
+    #[test]
+    fn stim_circuit_detectors() {  // cargo test stim_circuit_detectors -- --nocapture
+        for (code_type, noise_model_builder) in [(CodeType::StandardPlanarCode, NoiseModelBuilder::StimNoiseModel)
+                , (CodeType::RotatedPlanarCode, NoiseModelBuilder::StimNoiseModel), (CodeType::StandardXZZXCode, NoiseModelBuilder::Phenomenological)] {
+            let mut simulator = Simulator::new(code_type, CodeSize::new(2, 3, 3));
+            let mut noise_model = NoiseModel::new(&simulator);
+            noise_model_builder.apply(&mut simulator, &mut noise_model, &json!({}), 0.02, 0.5, 0.);
+            let circuit = simulator.to_stim_circuit(&noise_model).unwrap();
+            assert_eq!(circuit.lines().filter(|line| line.starts_with("DETECTOR")).count(), DetectorMap::from_simulator(&simulator).len());
+            assert_eq!(circuit.lines().filter(|line| line.starts_with("OBSERVABLE_INCLUDE")).count(), 2);
+            assert_eq!(circuit.lines().filter(|line| *line == "TICK").count(), simulator.height - 1);
+            // the circuit fires the same detectors and flips the same observables as the simulator given the same errors
+            simulator.rng = Xoroshiro128StarStar::seed_from_u64(1);
+            let mut defect_count = 0;
+            let mut logical_error_count = 0;
+            for _ in 0..200 {
+                simulator.generate_random_errors(&noise_model);
+                let mut errors = SparseErrorPattern::new();
+                for (position, error) in simulator.generate_sparse_error_pattern().iter() {
+                    // the simulator discards the errors on the initialization nodes, and those on virtual nodes never reach a real qubit
+                    if simulator.is_node_real(position) && !simulator.get_node_unwrap(position).gate_type.is_initialization() {
+                        errors.add(position.clone(), *error);
+                    }
+                }
+                let expected: Vec<Position> = simulator.generate_sparse_measurement().iter().cloned().collect();
+                let (logical_i, logical_j) = simulator.validate_correction(&SparseCorrection::new());
+                let expected_observables: Vec<usize> = [logical_i, logical_j].iter().enumerate().filter(|(_, flipped)| **flipped)
+                    .map(|(index, _)| index).collect();
+                assert_eq!(stim_fired_detectors(&circuit, &errors), (expected.clone(), expected_observables.clone()), "{:?}", code_type);
+                defect_count += expected.len();
+                logical_error_count += expected_observables.len();
+            }
+            assert!(defect_count > 0 && logical_error_count > 0);
+        }
+        // a fixed data qubit error in the middle of the rounds fires the two adjacent Z stabilizers in the next round
+        let simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        let circuit = simulator.to_stim_circuit(&noise_model).unwrap();
+        let mut errors = SparseErrorPattern::new();
+        errors.add(pos!(7, 2, 2), X);
+        assert_eq!(stim_fired_detectors(&circuit, &errors), (vec![pos!(12, 1, 2), pos!(12, 3, 2)], vec![]));
+        // noise without a Stim instruction is rejected
+        noise_model.additional_noise.push(AdditionalNoise { probability: 0.1, erasures: SparseErasures::new(), pauli_errors: errors });
+        assert!(simulator.to_stim_circuit(&noise_model).is_err());
+    }
+
+    #[test]
+    fn stim_circuit_detector_error_model() {  // cargo test stim_circuit_detector_error_model -- --nocapture
+        for (code_type, noise_model_builder) in [(CodeType::StandardPlanarCode, NoiseModelBuilder::Phenomenological)
+                , (CodeType::StandardPlanarCode, NoiseModelBuilder::StimNoiseModel), (CodeType::RotatedPlanarCode, NoiseModelBuilder::StimNoiseModel)] {
+            let mut simulator = Simulator::new(code_type, CodeSize::new(2, 3, 3));
+            let mut noise_model = NoiseModel::new(&simulator);
+            noise_model_builder.apply(&mut simulator, &mut noise_model, &json!({}), 0.02, 0.5, 0.);
+            let detector_error_model = simulator.build_detector_error_model(&noise_model);
+            let detector_map = DetectorMap::from_simulator(&simulator);
+            assert_eq!(detector_error_model.detectors, detector_map.positions().to_vec());
+            assert_eq!(detector_error_model.observables, 2);
+            // every single error is one of the hyperedges, flipping the logical observables reported by the validation
+            let mut expected = BTreeMap::<(Vec<usize>, Vec<usize>), f64>::new();
+            let mut faults: Vec<(f64, Vec<(Position, ErrorType)>)> = Vec::new();
+            simulator_iter!(simulator, position, node, if !node.gate_type.is_initialization() {
+                let noise_model_node = noise_model.get_node_unwrap(position);
+                for error in [X, Y, Z] {
+                    faults.push((noise_model_node.pauli_error_rates.error_rate(&error), vec![(position.clone(), error)]));
+                }
+                if let Some(correlated_pauli_error_rates) = &noise_model_node.correlated_pauli_error_rates {
+                    let peer_position = node.gate_peer.as_ref().expect("correlated error must corresponds to a two-qubit gate");
+                    for error_type in CorrelatedPauliErrorType::all_possible_errors().iter() {
+                        faults.push((correlated_pauli_error_rates.error_rate(error_type)
+                            , vec![(position.clone(), error_type.my_error()), ((**peer_position).clone(), error_type.peer_error())]));
+                    }
+                }
+            });
+            for (p, errors) in faults.iter().filter(|(p, _)| *p > 0.) {
+                simulator.clear_all_errors();
+                for (position, error) in errors.iter() {
+                    simulator.get_node_mut_unwrap(position).error = *error;
+                }
+                simulator.propagate_errors();
+                let detectors: Vec<usize> = simulator.generate_sparse_measurement().iter().map(|position| detector_map.index(position).unwrap()).collect();
+                if detectors.is_empty() {
+                    continue
+                }
+                let (logical_i, logical_j) = simulator.validate_correction(&SparseCorrection::new());
+                let observables: Vec<usize> = [logical_i, logical_j].iter().enumerate().filter(|(_, flipped)| **flipped).map(|(index, _)| index).collect();
+                let probability = expected.entry((detectors, observables)).or_insert(0.);
+                *probability = *probability * (1. - p) + p * (1. - *probability);
+            }
+            assert_eq!(detector_error_model.hyperedges.len(), expected.len(), "{:?}", code_type);
+            for (hyperedge, ((detectors, observables), probability)) in detector_error_model.hyperedges.iter().zip(expected.iter()) {
+                assert_eq!((&hyperedge.detectors, &hyperedge.observables), (detectors, observables), "{:?}", code_type);
+                assert!((hyperedge.probability - probability).abs() < 1e-12, "{:?} at {:?}", code_type, detectors);
+            }
+            // boundary edges have a single detector, and some of them flip a logical observable
+            assert!(detector_error_model.hyperedges.iter().any(|hyperedge| hyperedge.detectors.len() == 1 && !hyperedge.observables.is_empty()));
+            // the textual format has one line per hyperedge, detector and observable
+            let text = detector_error_model.to_string();
+            assert_eq!(text.lines().count(), detector_error_model.hyperedges.len() + detector_error_model.detectors.len() + 2);
+            for (line, hyperedge) in text.lines().zip(detector_error_model.hyperedges.iter()) {
+                let (instruction, targets) = line.split_once(' ').unwrap();
+                assert_eq!(instruction, format!("error({})", stim_probability(hyperedge.probability)));
+                let targets: Vec<&str> = targets.split(' ').collect();
+                assert_eq!(targets.len(), hyperedge.detectors.len() + hyperedge.observables.len(), "{}", line);
+                assert!(targets.iter().all(|target| target.starts_with('D') || target.starts_with('L')), "{}", line);
+            }
+            assert!(text.contains(&format!("detector({}, {}, {}) D0\n", detector_map.positions()[0].i, detector_map.positions()[0].j
+                , detector_map.positions()[0].t)));
+            assert!(text.ends_with("logical_observable L0\nlogical_observable L1\n"));
+        }
+        // circuit-level errors flip more than two detectors, e.g. a Y error on a data qubit of the standard planar code
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::StimNoiseModel.apply(&mut simulator, &mut noise_model, &json!({}), 0.02, 0.5, 0.);
+        let detector_error_model = simulator.build_detector_error_model(&noise_model);
+        assert!(detector_error_model.hyperedges.iter().any(|hyperedge| hyperedge.detectors.len() > 2));
+    }
+}
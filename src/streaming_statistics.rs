@@ -0,0 +1,407 @@
+//! Streaming statistics
+//!
+//! Summaries of the shots of a benchmark whose memory doesn't grow with the number of shots, so that billion-shot runs can keep them: a
+//! log-bucketed histogram of the decoding latency with bounded relative error of the percentiles, an exact histogram of the number of defects
+//! with an overflow bin, and a uniform random sample (reservoir) of the per-shot records. Every summary is mergeable: each worker accumulates
+//! its own summary, which is merged into the shared one at the end of each mini-batch, and the summaries of different configurations can be merged
+//! in the same way.
+//!
+
+use serde::{Serialize, Deserialize};
+use super::rand::Rng;
+use super::reproducible_rand::Xoroshiro128StarStar;
+
+
+/// histogram of non-negative values (e.g. latencies in seconds) in logarithmic buckets between [`LatencyHistogram::MIN_VALUE`] and
+/// [`LatencyHistogram::MAX_VALUE`], with an underflow and an overflow bucket; the count, sum, minimum and maximum are exact
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+    /// `counts[0]` is the underflow bucket (including 0), `counts[1..=BUCKETS]` are the logarithmic buckets and the last one is the overflow bucket
+    pub counts: Vec<u64>,
+    pub count: u64,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyHistogram {
+    pub const MIN_VALUE: f64 = 1e-9;
+    pub const MAX_VALUE: f64 = 1e4;
+    pub const BUCKETS_PER_OCTAVE: usize = 16;
+    /// the number of logarithmic buckets, enough to cover `[MIN_VALUE, MAX_VALUE)`
+    pub const BUCKETS: usize = 44 * Self::BUCKETS_PER_OCTAVE;
+    /// the maximum relative error of a percentile falling in a logarithmic bucket, i.e. half of a bucket in the logarithmic scale
+    pub const RELATIVE_ERROR: f64 = 0.02189714865411668;  // 2^(1/32) - 1
+
+    pub fn new() -> Self {
+        Self {
+            counts: vec![0; Self::BUCKETS + 2],
+            count: 0,
+            sum: 0.,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    fn bucket(value: f64) -> usize {
+        if value.is_nan() || value < Self::MIN_VALUE {
+            return 0
+        }
+        let bucket = ((value / Self::MIN_VALUE).log2() * Self::BUCKETS_PER_OCTAVE as f64).floor() as usize + 1;
+        std::cmp::min(bucket, Self::BUCKETS + 1)
+    }
+
+    /// the geometric center of a logarithmic bucket
+    fn bucket_center(bucket: usize) -> f64 {
+        Self::MIN_VALUE * ((bucket as f64 - 0.5) / Self::BUCKETS_PER_OCTAVE as f64).exp2()
+    }
+
+    pub fn add(&mut self, value: f64) {
+        self.counts[Self::bucket(value)] += 1;
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    /// add the statistics of `other` and clear it
+    pub fn take_from(&mut self, other: &mut Self) {
+        for (count, other_count) in self.counts.iter_mut().zip(other.counts.iter_mut()) {
+            *count += std::mem::take(other_count);
+        }
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        *other = Self::new();
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        if self.count == 0 { None } else { Some(self.sum / self.count as f64) }
+    }
+
+    /// the value at quantile `q` in [0, 1] using the nearest rank, within [`LatencyHistogram::RELATIVE_ERROR`] of the exact one unless it's
+    /// out of the range of the logarithmic buckets; None if there is no value
+    pub fn percentile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None
+        }
+        let rank = ((self.count - 1) as f64 * q).round() as u64;
+        let mut accumulated = 0;
+        for (bucket, &count) in self.counts.iter().enumerate() {
+            accumulated += count;
+            if accumulated > rank {
+                let value = if bucket == 0 {
+                    self.min
+                } else if bucket == Self::BUCKETS + 1 {
+                    self.max
+                } else {
+                    Self::bucket_center(bucket)
+                };
+                return Some(value.max(self.min).min(self.max))
+            }
+        }
+        unreachable!("the counts add up to {}", self.count)
+    }
+}
+
+/// exact histogram of small non-negative integers (e.g. the number of defects of each shot) up to `max_value`, with a single overflow bin for
+/// the larger ones; the count, sum and maximum are exact
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CountHistogram {
+    /// `counts[v]` is the number of value `v` for `v <= max_value`
+    pub counts: Vec<u64>,
+    /// the number of values larger than `max_value`
+    pub overflow: u64,
+    pub count: u64,
+    pub sum: u64,
+    pub max: Option<usize>,
+}
+
+impl CountHistogram {
+    pub fn new(max_value: usize) -> Self {
+        Self {
+            counts: vec![0; max_value + 1],
+            overflow: 0,
+            count: 0,
+            sum: 0,
+            max: None,
+        }
+    }
+
+    pub fn max_value(&self) -> usize {
+        self.counts.len() - 1
+    }
+
+    pub fn add(&mut self, value: usize) {
+        match self.counts.get_mut(value) {
+            Some(count) => *count += 1,
+            None => self.overflow += 1,
+        }
+        self.count += 1;
+        self.sum += value as u64;
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+    }
+
+    /// add the statistics of `other` with the same `max_value` and clear it
+    pub fn take_from(&mut self, other: &mut Self) {
+        debug_assert_eq!(self.max_value(), other.max_value());
+        for (count, other_count) in self.counts.iter_mut().zip(other.counts.iter_mut()) {
+            *count += std::mem::take(other_count);
+        }
+        self.overflow += std::mem::take(&mut other.overflow);
+        self.count += std::mem::take(&mut other.count);
+        self.sum += std::mem::take(&mut other.sum);
+        self.max = self.max.max(other.max.take());
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        if self.count == 0 { None } else { Some(self.sum as f64 / self.count as f64) }
+    }
+}
+
+/// a uniform random sample of at most `capacity` items out of all the items offered to it (reservoir sampling); two reservoirs are merged into
+/// a uniform random sample of the union of their items
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Reservoir<T> {
+    pub capacity: usize,
+    /// the number of items offered so far
+    pub seen: u64,
+    pub items: Vec<T>,
+    pub rng: Xoroshiro128StarStar,
+}
+
+impl<T> Reservoir<T> {
+    pub fn new(capacity: usize, rng: Xoroshiro128StarStar) -> Self {
+        Self {
+            capacity,
+            seen: 0,
+            items: Vec::new(),
+            rng,
+        }
+    }
+
+    /// count a new item and return the slot to store it with [`Reservoir::store`] if it's sampled; this allows the caller to build the item
+    /// only when needed
+    pub fn offer(&mut self) -> Option<usize> {
+        self.seen += 1;
+        if self.items.len() < self.capacity {
+            return Some(self.items.len())
+        }
+        let slot = self.rng.gen_range(0..self.seen);
+        if slot < self.capacity as u64 { Some(slot as usize) } else { None }
+    }
+
+    pub fn store(&mut self, slot: usize, item: T) {
+        if slot == self.items.len() {
+            self.items.push(item);
+        } else {
+            self.items[slot] = item;
+        }
+    }
+
+    pub fn add(&mut self, item: T) {
+        if let Some(slot) = self.offer() {
+            self.store(slot, item);
+        }
+    }
+
+    /// merge the items of `other` with the same capacity and clear it: the number of items drawn from each reservoir follows the hypergeometric
+    /// distribution of drawing from all the items they have seen, and the items of each reservoir are drawn uniformly at random
+    pub fn take_from(&mut self, other: &mut Self) {
+        debug_assert_eq!(self.capacity, other.capacity);
+        let mut sources = [(std::mem::take(&mut self.items), self.seen), (std::mem::take(&mut other.items), other.seen)];
+        let mut merged = Vec::with_capacity(std::cmp::min(self.capacity, sources[0].0.len() + sources[1].0.len()));
+        while merged.len() < self.capacity && sources[0].1 + sources[1].1 > 0 {
+            let source = if self.rng.gen_range(0..sources[0].1 + sources[1].1) < sources[0].1 { 0 } else { 1 };
+            let (items, remaining) = &mut sources[source];
+            let index = self.rng.gen_range(0..items.len());
+            merged.push(items.swap_remove(index));
+            *remaining -= 1;
+        }
+        self.items = merged;
+        self.seen += std::mem::take(&mut other.seen);
+    }
+}
+
+/// the streaming summaries of the shots of a benchmark, see `BenchmarkParameters::statistics_reservoir`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StreamingStatistics {
+    pub decode_latency: LatencyHistogram,
+    pub defects: CountHistogram,
+    /// the per-shot records written to the statistics log
+    pub records: Reservoir<serde_json::Value>,
+}
+
+impl StreamingStatistics {
+    /// the number of defects above which the shots share the overflow bin of the histogram
+    pub const MAX_DEFECTS: usize = 1024;
+
+    pub fn new(reservoir_capacity: usize, rng: Xoroshiro128StarStar) -> Self {
+        Self {
+            decode_latency: LatencyHistogram::new(),
+            defects: CountHistogram::new(Self::MAX_DEFECTS),
+            records: Reservoir::new(reservoir_capacity, rng),
+        }
+    }
+
+    /// record the summaries of a shot and return the slot of its record if it's sampled, see [`Reservoir::offer`]
+    pub fn add_shot(&mut self, decode_elapsed: f64, defect_count: usize) -> Option<usize> {
+        self.decode_latency.add(decode_elapsed);
+        self.defects.add(defect_count);
+        self.records.offer()
+    }
+
+    /// add the statistics of `other` and clear it
+    pub fn take_from(&mut self, other: &mut Self) {
+        self.decode_latency.take_from(&mut other.decode_latency);
+        self.defects.take_from(&mut other.defects);
+        self.records.take_from(&mut other.records);
+    }
+
+    /// the summaries without the records, with the defect histogram truncated after the largest value below the overflow bin
+    pub fn summary(&self) -> serde_json::Value {
+        let percentile = |q: f64| self.decode_latency.percentile(q);
+        let is_empty = self.decode_latency.count == 0;
+        let histogram_length = self.defects.counts.iter().rposition(|&count| count > 0).map_or(0, |value| value + 1);
+        json!({
+            "shots": self.defects.count,
+            "decode_latency": {
+                "mean": self.decode_latency.mean(),
+                "min": if is_empty { None } else { Some(self.decode_latency.min) },
+                "p50": percentile(0.5),
+                "p90": percentile(0.9),
+                "p99": percentile(0.99),
+                "p999": percentile(0.999),
+                "max": if is_empty { None } else { Some(self.decode_latency.max) },
+                "relative_error": LatencyHistogram::RELATIVE_ERROR,
+            },
+            "defects": {
+                "mean": self.defects.mean(),
+                "max": self.defects.max,
+                "histogram": &self.defects.counts[..histogram_length],
+                "overflow": self.defects.overflow,
+                "overflow_above": self.defects.max_value(),
+            },
+            "records": self.records.items.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::rand_core::SeedableRng;
+
+    /// a decoder whose latency grows with the number of defects, with some jitter
+    struct MockDecoder {
+        rng: Xoroshiro128StarStar,
+    }
+
+    impl MockDecoder {
+        /// the number of defects, the decoding latency and the record of a shot
+        fn decode(&mut self) -> (usize, f64, serde_json::Value) {
+            let defect_count = (self.rng.next_f64() * 8.).powi(2) as usize;
+            let latency = 1e-6 * (1. + defect_count as f64) * (1. + self.rng.next_f64());
+            (defect_count, latency, json!({ "defects": defect_count, "elapsed": { "decode": latency } }))
+        }
+    }
+
+    #[test]
+    fn streaming_statistics_memory_flat() {  // cargo test streaming_statistics_memory_flat -- --nocapture
+        let mut decoder = MockDecoder { rng: Xoroshiro128StarStar::seed_from_u64(0) };
+        let mut statistics = StreamingStatistics::new(100, Xoroshiro128StarStar::seed_from_u64(1));
+        let footprint = |statistics: &StreamingStatistics| -> (usize, usize, usize) {
+            (statistics.decode_latency.counts.capacity(), statistics.defects.counts.capacity(), statistics.records.items.capacity())
+        };
+        let mut run = |statistics: &mut StreamingStatistics, shots: usize| {
+            for _ in 0..shots {
+                let (defect_count, latency, record) = decoder.decode();
+                if let Some(slot) = statistics.add_shot(latency, defect_count) {
+                    statistics.records.store(slot, record);
+                }
+            }
+        };
+        run(&mut statistics, 1000);
+        let initial_footprint = footprint(&statistics);
+        run(&mut statistics, 200000);
+        assert_eq!(footprint(&statistics), initial_footprint);
+        assert_eq!(statistics.records.items.len(), 100);
+        assert_eq!(statistics.defects.count, 201000);
+        assert_eq!(statistics.records.seen, 201000);
+    }
+
+    #[test]
+    fn streaming_statistics_merge() {  // cargo test streaming_statistics_merge -- --nocapture
+        let mut decoder = MockDecoder { rng: Xoroshiro128StarStar::seed_from_u64(2) };
+        let shots: Vec<(usize, f64, serde_json::Value)> = (0..3000).map(|_| decoder.decode()).collect();
+        // three workers with uneven numbers of shots, merged into a shared one
+        let mut merged = StreamingStatistics::new(shots.len(), Xoroshiro128StarStar::seed_from_u64(3));
+        for (index, chunk) in shots.chunks(1700).flat_map(|chunk| chunk.chunks(1000)).enumerate() {
+            let mut worker = StreamingStatistics::new(shots.len(), Xoroshiro128StarStar::seed_from_u64(4 + index as u64));
+            for (defect_count, latency, record) in chunk.iter() {
+                let slot = worker.add_shot(*latency, *defect_count).unwrap();
+                worker.records.store(slot, record.clone());
+            }
+            merged.take_from(&mut worker);
+            assert_eq!(worker.defects.count, 0);
+            assert_eq!(worker.records.items.len(), 0);
+        }
+        // exact histogram and counts
+        let mut exact_defects = vec![0u64; StreamingStatistics::MAX_DEFECTS + 1];
+        for (defect_count, _, _) in shots.iter() {
+            exact_defects[*defect_count] += 1;
+        }
+        assert_eq!(merged.defects.counts, exact_defects);
+        assert_eq!(merged.defects.sum, shots.iter().map(|(defect_count, _, _)| *defect_count as u64).sum::<u64>());
+        assert_eq!(merged.defects.max, shots.iter().map(|(defect_count, _, _)| *defect_count).max());
+        // percentiles within the relative error of the exact nearest-rank ones
+        let mut latencies: Vec<f64> = shots.iter().map(|(_, latency, _)| *latency).collect();
+        latencies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for q in [0., 0.1, 0.5, 0.9, 0.99, 1.] {
+            let exact = latencies[((latencies.len() - 1) as f64 * q).round() as usize];
+            let estimated = merged.decode_latency.percentile(q).unwrap();
+            assert!((estimated / exact - 1.).abs() <= LatencyHistogram::RELATIVE_ERROR + 1e-12, "q = {}: {} vs {}", q, estimated, exact);
+        }
+        assert!((merged.decode_latency.mean().unwrap() - latencies.iter().sum::<f64>() / latencies.len() as f64).abs() < 1e-15);
+        // a reservoir large enough keeps all the records
+        assert_eq!(merged.records.seen, shots.len() as u64);
+        let mut records: Vec<String> = merged.records.items.iter().map(|record| record.to_string()).collect();
+        let mut expected: Vec<String> = shots.iter().map(|(_, _, record)| record.to_string()).collect();
+        records.sort();
+        expected.sort();
+        assert_eq!(records, expected);
+        // an empty summary has no percentile
+        assert_eq!(StreamingStatistics::new(1, Xoroshiro128StarStar::seed_from_u64(0)).summary()["decode_latency"]["p50"], json!(null));
+    }
+
+    #[test]
+    fn streaming_statistics_reservoir_uniform() {  // cargo test streaming_statistics_reservoir_uniform -- --nocapture
+        // merging a reservoir of 1000 items with one of 3000 items keeps each item with the same probability
+        let capacity = 100;
+        let repeats = 2000;
+        let mut kept_from_first = 0;
+        for repeat in 0..repeats {
+            let mut first = Reservoir::new(capacity, Xoroshiro128StarStar::seed_from_u64(3 * repeat));
+            let mut second = Reservoir::new(capacity, Xoroshiro128StarStar::seed_from_u64(3 * repeat + 1));
+            (0..1000).for_each(|item| first.add(item));
+            (1000..4000).for_each(|item| second.add(item));
+            let mut merged = Reservoir::new(capacity, Xoroshiro128StarStar::seed_from_u64(3 * repeat + 2));
+            merged.take_from(&mut first);
+            merged.take_from(&mut second);
+            assert_eq!(merged.items.len(), capacity);
+            assert_eq!(merged.seen, 4000);
+            kept_from_first += merged.items.iter().filter(|&&item| item < 1000).count();
+        }
+        let fraction = kept_from_first as f64 / (capacity * repeats as usize) as f64;
+        // the standard deviation of the fraction is about sqrt(0.25 * 0.75 / 100 / 2000) ~ 0.001
+        assert!((fraction - 0.25).abs() < 0.005, "fraction of the first reservoir: {}", fraction);
+    }
+
+}
@@ -0,0 +1,172 @@
+//! Syndrome corpus
+//!
+//! A binary file of pre-sampled syndromes at a fixed configuration, generated once by `tool generate_dataset` and decoded many times by
+//! `tool bench_decoder`, so that the decoding throughput is measured without any simulation cost. The file starts with [`CORPUS_MAGIC`],
+//! followed by the length of the json header as a little-endian `u32` and the [`SyndromeCorpusHeader`] itself. Then each shot is stored as
+//! the number of defects followed by their detector indices (see [`DetectorMap`]), all as little-endian `u32`.
+//!
+
+use super::serde_json;
+use serde::{Serialize, Deserialize};
+use std::sync::Arc;
+use std::fs;
+use super::code_builder::*;
+use super::simulator::*;
+use super::noise_model::*;
+use super::noise_model_builder::*;
+use super::position_indexer::*;
+use super::reproducible_rand::Xoroshiro128StarStar;
+use crate::rand_core::SeedableRng;
+
+
+/// the magic string at the beginning of every corpus file, whose last byte is the version of the format
+pub const CORPUS_MAGIC: &[u8] = b"QECPSYN\x01";
+
+/// the configuration that the syndromes are sampled from, so that the decoders can be rebuilt from the corpus alone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyndromeCorpusHeader {
+    pub code_type: CodeType,
+    pub d: usize,
+    pub dj: usize,
+    pub noisy_measurements: usize,
+    pub p: f64,
+    pub bias_eta: f64,
+    pub noise_model_builder: Option<NoiseModelBuilder>,
+    pub noise_model_configuration: serde_json::Value,
+    pub seed: u64,
+    pub shots: usize,
+    /// the number of detectors, a sanity check that the rebuilt simulator has the same detector indices
+    pub detectors: usize,
+}
+
+impl SyndromeCorpusHeader {
+    /// the simulator and the noise model of this configuration; erasures are not supported because the corpus doesn't store them
+    pub fn build(&self) -> Result<(Simulator, Arc<NoiseModel>), String> {
+        let mut simulator = Simulator::new(self.code_type, CodeSize::new(self.noisy_measurements, self.d, self.dj));
+        let mut noise_model = NoiseModel::new(&simulator);
+        let px = self.p / (1. + self.bias_eta) / 2.;
+        simulator.set_error_rates(&mut noise_model, px, px, self.p - 2. * px, 0.);
+        if let Some(noise_model_builder) = &self.noise_model_builder {
+            noise_model_builder.apply(&mut simulator, &mut noise_model, &self.noise_model_configuration, self.p, self.bias_eta, 0.);
+        }
+        noise_model_sanity_check(&simulator, &noise_model)?;
+        simulator.compress_error_rates(&mut noise_model);
+        Ok((simulator, Arc::new(noise_model)))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyndromeCorpus {
+    pub header: SyndromeCorpusHeader,
+    pub syndromes: Vec<SparseMeasurement>,
+}
+
+impl SyndromeCorpus {
+    /// sample `header.shots` syndromes with the seed in the header; `header.detectors` is overwritten
+    pub fn generate(mut header: SyndromeCorpusHeader) -> Result<Self, String> {
+        let (mut simulator, noise_model) = header.build()?;
+        header.detectors = DetectorMap::from_simulator(&simulator).len();
+        simulator.rng = Xoroshiro128StarStar::seed_from_u64(header.seed);
+        let mut syndromes = Vec::with_capacity(header.shots);
+        for _ in 0..header.shots {
+            simulator.generate_random_errors(&noise_model);
+            syndromes.push(simulator.generate_sparse_measurement());
+        }
+        Ok(Self { header, syndromes })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (simulator, _) = self.header.build().expect("the header is checked when the corpus is created");
+        let detector_map = DetectorMap::from_simulator(&simulator);
+        let header = serde_json::to_string(&self.header).unwrap();
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(CORPUS_MAGIC);
+        bytes.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        for sparse_measurement in self.syndromes.iter() {
+            bytes.extend_from_slice(&(sparse_measurement.len() as u32).to_le_bytes());
+            for index in sparse_measurement.to_detector_indices(&detector_map) {
+                bytes.extend_from_slice(&(index as u32).to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.starts_with(CORPUS_MAGIC) {
+            return Err("not a syndrome corpus: magic string mismatch".to_string())
+        }
+        let mut cursor = CORPUS_MAGIC.len();
+        let read_u32 = |cursor: &mut usize| -> Result<usize, String> {
+            let value = bytes.get(*cursor..*cursor + 4).ok_or(format!("syndrome corpus truncated at byte {}", *cursor))?;
+            *cursor += 4;
+            Ok(u32::from_le_bytes([value[0], value[1], value[2], value[3]]) as usize)
+        };
+        let header_length = read_u32(&mut cursor)?;
+        let header = bytes.get(cursor..cursor + header_length).ok_or("syndrome corpus truncated in the header".to_string())?;
+        cursor += header_length;
+        let header: SyndromeCorpusHeader = serde_json::from_slice(header).map_err(|e| format!("syndrome corpus header error: {}", e))?;
+        let (simulator, _) = header.build()?;
+        let detector_map = DetectorMap::from_simulator(&simulator);
+        if detector_map.len() != header.detectors {
+            return Err(format!("syndrome corpus has {} detectors but the rebuilt simulator has {}", header.detectors, detector_map.len()))
+        }
+        // the counts are not trusted for preallocation: each shot and each defect takes at least 4 of the remaining bytes
+        let mut syndromes = Vec::with_capacity(header.shots.min((bytes.len() - cursor) / 4));
+        for _ in 0..header.shots {
+            let defect_count = read_u32(&mut cursor)?;
+            let mut indices = Vec::with_capacity(defect_count.min(bytes.len().saturating_sub(cursor) / 4));
+            for _ in 0..defect_count {
+                indices.push(read_u32(&mut cursor)?);
+            }
+            syndromes.push(SparseMeasurement::from_detector_indices(&detector_map, &indices)?);
+        }
+        if cursor != bytes.len() {
+            return Err(format!("syndrome corpus has {} unexpected bytes after {} shots", bytes.len() - cursor, header.shots))
+        }
+        Ok(Self { header, syndromes })
+    }
+
+    pub fn save(&self, filepath: &str) -> Result<(), String> {
+        fs::write(filepath, self.to_bytes()).map_err(|e| format!("[error] cannot write {}: {}", filepath, e))
+    }
+
+    pub fn load(filepath: &str) -> Result<Self, String> {
+        let bytes = fs::read(filepath).map_err(|e| format!("[error] cannot read {}: {}", filepath, e))?;
+        Self::from_bytes(&bytes).map_err(|e| format!("[error] {}: {}", filepath, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn syndrome_corpus_round_trip() {  // cargo test syndrome_corpus_round_trip -- --nocapture
+        let header = SyndromeCorpusHeader {
+            code_type: CodeType::RotatedPlanarCode, d: 5, dj: 5, noisy_measurements: 5, p: 0.01, bias_eta: 0.5
+            , noise_model_builder: Some(NoiseModelBuilder::Phenomenological), noise_model_configuration: json!({}), seed: 1, shots: 200, detectors: 0,
+        };
+        let corpus = SyndromeCorpus::generate(header).unwrap();
+        assert_eq!(corpus.header.detectors, 6 * 24);  // 24 stabilizers in each of the 6 measurement rounds
+        assert!(corpus.syndromes.iter().any(|sparse_measurement| sparse_measurement.len() > 0));
+        let bytes = corpus.to_bytes();
+        let loaded = SyndromeCorpus::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.header, corpus.header);
+        assert_eq!(loaded.syndromes.iter().map(|x| x.to_vec()).collect::<Vec<_>>(), corpus.syndromes.iter().map(|x| x.to_vec()).collect::<Vec<_>>());
+        // reproducible with the same seed
+        assert_eq!(SyndromeCorpus::generate(corpus.header.clone()).unwrap().to_bytes(), bytes);
+        // broken files are rejected
+        assert!(SyndromeCorpus::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(SyndromeCorpus::from_bytes(&[bytes.as_slice(), &[0]].concat()).is_err());
+        assert!(SyndromeCorpus::from_bytes(&bytes[1..]).is_err());
+        // huge counts from a broken file are not preallocated
+        let mut broken = SyndromeCorpus { header: corpus.header.clone(), syndromes: corpus.syndromes.clone() };
+        broken.header.shots = u32::MAX as usize;
+        assert!(SyndromeCorpus::from_bytes(&broken.to_bytes()).is_err());
+        broken.header.shots = corpus.header.shots + 1;
+        assert!(SyndromeCorpus::from_bytes(&[broken.to_bytes().as_slice(), &u32::MAX.to_le_bytes()].concat()).is_err());
+    }
+
+}
@@ -11,6 +11,7 @@ use std::sync::{Arc};
 use serde::{Serialize};
 use super::model_graph::*;
 use super::float_cmp;
+use super::position_indexer::*;
 
 /// edges connecting two nontrivial measurements generated by a single error
 #[derive(Debug, Clone, Serialize)]
@@ -53,20 +54,17 @@ impl TailoredModelGraph {
                     (0..simulator.horizontal).map(|j| {
                         let position = &pos!(t, i, j);
                         // tailored model graph contains both real node and virtual node at measurement round
-                        if t != 0 && t % simulator.measurement_cycles == 0 && simulator.is_node_exist(position) {
-                            let node = simulator.get_node_unwrap(position);
-                            if node.gate_type.is_measurement() {  // only define model graph node for measurements
-                                return Some(Box::new([TailoredModelGraphNode {
-                                    all_edges: BTreeMap::new(),
-                                    edges: BTreeMap::new(),
-                                }, TailoredModelGraphNode {
-                                    all_edges: BTreeMap::new(),
-                                    edges: BTreeMap::new(),
-                                }, TailoredModelGraphNode {
-                                    all_edges: BTreeMap::new(),
-                                    edges: BTreeMap::new(),
-                                }]))
-                            }
+                        if PositionIndexer::is_measurement(simulator, position) {
+                            return Some(Box::new([TailoredModelGraphNode {
+                                all_edges: BTreeMap::new(),
+                                edges: BTreeMap::new(),
+                            }, TailoredModelGraphNode {
+                                all_edges: BTreeMap::new(),
+                                edges: BTreeMap::new(),
+                            }, TailoredModelGraphNode {
+                                all_edges: BTreeMap::new(),
+                                edges: BTreeMap::new(),
+                            }]))
                         }
                         None
                     }).collect()
@@ -6,36 +6,53 @@ use super::serde_json;
 use super::pyo3::prelude::*;
 use super::num_cpus;
 use std::sync::{Arc, Mutex};
+use std::collections::{BTreeSet, BTreeMap};
+use std::path::Path;
 use super::pbr::ProgressBar;
 use super::serde_json::{json};
 use std::fs::File;
 use std::io::prelude::*;
 use std::time::Instant;
-use super::util::local_get_temporary_store;
+use super::util::{local_get_temporary_store, git_hash, host_information};
+use crate::chrono::Local;
+#[cfg(unix)]
+use super::util::ResultStream;
 use std::fs;
 use super::code_builder::*;
 use super::simulator::*;
 use super::clap::ValueEnum;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use super::noise_model::*;
 use serde::{Serialize, Deserialize};
 use super::decoder_mwpm::*;
+use super::exact_matching;
+use super::npy;
+use super::syndrome_corpus::*;
 #[cfg(feature="fusion_blossom")]
 use super::decoder_fusion::*;
 use super::model_graph::*;
 use super::complete_model_graph::*;
 use super::decoder_tailored_mwpm::*;
+use super::decoder_tailored_union_find::*;
 use super::tailored_model_graph::*;
 use super::tailored_complete_model_graph::*;
 use super::noise_model_builder::*;
 use super::decoder_union_find::*;
+use super::decoder_uf_then_mwpm::*;
 use super::erasure_graph::*;
 use super::visualize::*;
+use super::reproducible_rand::Xoroshiro128StarStar;
+use super::streaming_statistics::StreamingStatistics;
+use crate::rand_core::{SeedableRng, RngCore};
+use super::types::*;
+use super::importance_sampling::*;
+use super::util_macros::*;
 use super::model_hypergraph::*;
 #[cfg(feature="hyperion")]
 use super::decoder_hyper_union_find::*;
 use crate::cli::*;
 use crate::simulator_compact::*;
+use crate::artifact::*;
 
 
 impl ToolCommands {
@@ -44,7 +61,807 @@ impl ToolCommands {
             Self::Benchmark(benchmark_parameters) => {
                 benchmark_parameters.run()
             }
+            Self::DiffNoiseModels(diff_noise_models_parameters) => {
+                diff_noise_models_parameters.run()
+            }
+            Self::ExportTikz(export_tikz_parameters) => {
+                export_tikz_parameters.run()
+            }
+            Self::GraphEntropy(graph_entropy_parameters) => {
+                graph_entropy_parameters.run()
+            }
+            Self::WeightSensitivity(weight_sensitivity_parameters) => {
+                weight_sensitivity_parameters.run()
+            }
+            Self::SensitivityAnalysis(sensitivity_analysis_parameters) => {
+                sensitivity_analysis_parameters.run()
+            }
+            Self::CheckVirtualConsistency(check_virtual_consistency_parameters) => {
+                check_virtual_consistency_parameters.run()
+            }
+            Self::WorstCaseLatency(benchmark_parameters) => {
+                benchmark_parameters.run_worst_case_latency()
+            }
+            Self::ValidateArtifact(validate_artifact_parameters) => {
+                validate_artifact_parameters.run()
+            }
+            Self::ShowExperiment(show_experiment_parameters) => {
+                show_experiment_parameters.run()
+            }
+            Self::ExportNpy(export_npy_parameters) => {
+                export_npy_parameters.run()
+            }
+            Self::GenerateDataset(generate_dataset_parameters) => {
+                generate_dataset_parameters.run()
+            }
+            Self::BenchDecoder(bench_decoder_parameters) => {
+                bench_decoder_parameters.run()
+            }
+            Self::BuildGraph(build_graph_parameters) => {
+                build_graph_parameters.run()
+            }
+            Self::ExportPymatching(export_pymatching_parameters) => {
+                export_pymatching_parameters.run()
+            }
+            Self::Plan(benchmark_parameters) => {
+                benchmark_parameters.run_plan()
+            }
+        }
+    }
+}
+
+impl ExportTikzParameters {
+    /// TikZ style of each qubit type; the styles are defined in the `tikzpicture` options so that they can be changed in one place
+    fn tikz_style(qubit_type: QubitType) -> &'static str {
+        match qubit_type {
+            QubitType::Data => "data",
+            QubitType::StabX => "stabx",
+            QubitType::StabZ => "stabz",
+            QubitType::StabXZZXLogicalX => "stabxzzxx",
+            QubitType::StabXZZXLogicalZ => "stabxzzxz",
+            QubitType::StabY => "staby",
+        }
+    }
+
+    /// a `tikzpicture` environment with one `\fill` command for each stabilizer plaquette followed by one `\node` command for each real qubit,
+    /// placed at [`visualize_positions`] with the i axis pointing down
+    pub fn run(&self) -> Result<String, String> {
+        let simulator = Simulator::new(self.code_type, CodeSize::new(0, self.d, self.dj.unwrap_or(self.d)));
+        let positions = visualize_positions(&simulator);
+        let coordinate = |i: usize, j: usize| -> String {
+            let position = &positions[i][j];
+            format!("({:.3},{:.3})", position.y + 0., -position.x + 0.)  // `+ 0.` avoids printing negative zero
+        };
+        let mut output = "\\begin{tikzpicture}[\n".to_string();
+        output += "    data/.style={circle, draw, fill=white, inner sep=0pt, minimum size=4mm},\n";
+        output += "    ancilla/.style={rectangle, draw, inner sep=0pt, minimum size=3mm},\n";
+        for (qubit_type, color) in [(QubitType::StabX, "red"), (QubitType::StabZ, "blue"), (QubitType::StabXZZXLogicalX, "orange")
+                , (QubitType::StabXZZXLogicalZ, "violet"), (QubitType::StabY, "green")] {
+            let style = Self::tikz_style(qubit_type);
+            output += &format!("    {}/.style={{ancilla, fill={}!60}},\n", style, color);
+            output += &format!("    {}plaquette/.style={{fill={}!20}},\n", style, color);
+        }
+        output += "]\n";
+        // the plaquette of each stabilizer is spanned by the data qubits that the ancilla interacts with in a measurement round
+        let mut plaquettes = String::new();
+        let mut nodes = String::new();
+        for i in 0..simulator.vertical {
+            for j in 0..simulator.horizontal {
+                let position = pos!(0, i, j);
+                if !simulator.is_node_real(&position) {
+                    continue
+                }
+                let node = simulator.get_node_unwrap(&position);
+                let style = Self::tikz_style(node.qubit_type);
+                nodes += &format!("\\node[{}] at {} {{}};\n", style, coordinate(i, j));
+                if node.qubit_type == QubitType::Data {
+                    continue
+                }
+                let mut corners = Vec::new();
+                for t in 1..simulator.measurement_cycles {
+                    let ancilla = simulator.get_node_unwrap(&pos!(t, i, j));
+                    if ancilla.gate_type.is_two_qubit_gate() && !ancilla.is_peer_virtual {
+                        let peer = ancilla.gate_peer.as_ref().unwrap();
+                        corners.push((peer.i, peer.j));
+                    }
+                }
+                if corners.len() < 3 {  // boundary stabilizers are drawn as triangles with the ancilla at the tip
+                    corners.push((i, j));
+                }
+                let center = &positions[i][j];
+                corners.sort_by(|(i1, j1), (i2, j2)| {
+                    let angle = |i: usize, j: usize| (positions[i][j].x - center.x).atan2(positions[i][j].y - center.y);
+                    angle(*i1, *j1).partial_cmp(&angle(*i2, *j2)).unwrap()
+                });
+                let corners: Vec<String> = corners.iter().map(|(i, j)| coordinate(*i, *j)).collect();
+                plaquettes += &format!("\\fill[{}plaquette] {} -- cycle;\n", style, corners.join(" -- "));
+            }
+        }
+        output += &plaquettes;
+        output += &nodes;
+        output += "\\end{tikzpicture}\n";
+        Ok(output)
+    }
+}
+
+impl GraphEntropyParameters {
+    /// a json object of the number of edges and their total binary entropy in bits, see [`ModelGraph::edge_entropy`]
+    pub fn run(&self) -> Result<String, String> {
+        let mut simulator = Simulator::new(self.code_type, CodeSize::new(self.noisy_measurements, self.d, self.dj.unwrap_or(self.d)));
+        let mut noise_model = NoiseModel::new(&simulator);
+        let px = self.p / (1. + self.bias_eta) / 2.;
+        simulator.set_error_rates(&mut noise_model, px, px, self.p - 2. * px, self.pe);
+        if let Some(noise_model_builder) = &self.noise_model_builder {
+            let noise_model_configuration = single_noise_model_configuration(&self.noise_model_configuration)?;
+            noise_model_builder.apply(&mut simulator, &mut noise_model, &noise_model_configuration, self.p, self.bias_eta, self.pe);
+        }
+        noise_model_sanity_check(&simulator, &noise_model)?;
+        simulator.compress_error_rates(&mut noise_model);
+        let mut model_graph = ModelGraph::new(&simulator);
+        model_graph.build(&mut simulator, Arc::new(noise_model), &WeightFunction::AutotuneImproved, 1, true, false);
+        let (edge_count, entropy) = model_graph.edge_entropy();
+        Ok(format!("{}\n", json!({
+            "edges": edge_count,
+            "entropy": entropy,
+            "mean_entropy": if edge_count > 0 { entropy / edge_count as f64 } else { 0. },
+        })))
+    }
+}
+
+impl WeightSensitivityParameters {
+    /// one json line for each quantization level, with its logical error rate and the paired differences to the previous level and to the reference,
+    /// i.e. the last level; all the levels decode the same sampled syndromes, see [`SharedSyndromeHarness`]
+    pub fn run(&self) -> Result<String, String> {
+        if self.max_half_weights.is_empty() {
+            return Err("at least one `max_half_weight` is required".to_string())
+        }
+        if !self.decoder_config.is_object() {
+            return Err("decoder configuration must be a json object".to_string())
+        }
+        let mut simulator = Simulator::new(self.code_type, CodeSize::new(self.noisy_measurements, self.d, self.dj.unwrap_or(self.d)));
+        let mut noise_model = NoiseModel::new(&simulator);
+        let px = self.p / (1. + self.bias_eta) / 2.;
+        simulator.set_error_rates(&mut noise_model, px, px, self.p - 2. * px, self.pe);
+        if let Some(noise_model_builder) = &self.noise_model_builder {
+            let noise_model_configuration = single_noise_model_configuration(&self.noise_model_configuration)?;
+            noise_model_builder.apply(&mut simulator, &mut noise_model, &noise_model_configuration, self.p, self.bias_eta, self.pe);
+        }
+        noise_model_sanity_check(&simulator, &noise_model)?;
+        simulator.compress_error_rates(&mut noise_model);
+        let noise_model = Arc::new(noise_model);
+        let mut decoders = Vec::with_capacity(self.max_half_weights.len());
+        for &max_half_weight in self.max_half_weights.iter() {
+            let mut decoder_config = self.decoder_config.clone();
+            decoder_config["max_half_weight"] = json!(max_half_weight);
+            decoders.push(match self.decoder {
+                BenchmarkDecoder::UnionFind => {
+                    GeneralDecoder::UnionFind(UnionFindDecoder::new(&simulator, Arc::clone(&noise_model), &decoder_config, 1, false))
+                },
+                BenchmarkDecoder::TailoredUnionFind => {
+                    GeneralDecoder::TailoredUnionFind(TailoredUnionFindDecoder::new(&simulator, Arc::clone(&noise_model), &decoder_config, 1, false))
+                },
+                #[cfg(feature="fusion_blossom")]
+                BenchmarkDecoder::Fusion => {
+                    GeneralDecoder::Fusion(FusionDecoder::new(&simulator, Arc::clone(&noise_model), &decoder_config, 1, false))
+                },
+                _ => return Err("`max_half_weight` is only supported by the union-find, tailored union-find and fusion decoders".to_string()),
+            });
+        }
+        simulator.rng = Xoroshiro128StarStar::seed_from_u64(self.seed);
+        let mut harness = SharedSyndromeHarness::new(simulator, noise_model, decoders);
+        let statistics = harness.run(self.shots);
+        let compare = |a: usize, b: usize| -> serde_json::Value {
+            let (difference, standard_error) = statistics.difference(a, b);
+            json!({
+                "difference": difference,
+                "standard_error": standard_error,
+                "mcnemar_z": statistics.mcnemar_z(a, b),
+            })
+        };
+        let reference = self.max_half_weights.len() - 1;
+        let mut output = String::new();
+        for (level, &max_half_weight) in self.max_half_weights.iter().enumerate() {
+            output += &format!("{}\n", json!({
+                "max_half_weight": max_half_weight,
+                "shots": statistics.shots,
+                "qec_failed": statistics.failed[level][level],
+                "error_rate": statistics.error_rate(level),
+                "vs_previous": if level > 0 { compare(level, level - 1) } else { json!(null) },
+                "vs_reference": compare(level, reference),
+            }));
+        }
+        Ok(output)
+    }
+}
+
+/// the class of an error location in a measurement cycle, see `tool sensitivity_analysis`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LocationClass {
+    /// errors of the two-qubit gates at this step of the measurement cycle
+    GateStage(usize),
+    /// single-qubit errors of the ancilla qubits before the measurement
+    Measurement,
+    /// single-qubit errors of the data qubits without a two-qubit gate
+    DataIdle,
+    /// single-qubit errors of the ancilla qubits without a two-qubit gate in the middle of a measurement cycle
+    AncillaIdle,
+}
+
+impl LocationClass {
+    pub fn name(&self) -> String {
+        match self {
+            Self::GateStage(stage) => format!("gate_stage_{}", stage),
+            Self::Measurement => "measurement".to_string(),
+            Self::DataIdle => "data_idle".to_string(),
+            Self::AncillaIdle => "ancilla_idle".to_string(),
+        }
+    }
+
+    /// the class of the single-qubit Pauli and erasure errors at a real node, or None at an initialization gate, which resets them
+    /// before they can propagate (see `Simulator::propagate_error_from`) so that they never affect the logical error rate
+    pub fn of_single_qubit_errors(simulator: &Simulator, position: &Position) -> Option<Self> {
+        let node = simulator.get_node_unwrap(position);
+        if node.gate_type.is_initialization() {
+            return None
+        }
+        let stage = position.t % simulator.measurement_cycles;
+        let has_gate = node.gate_peer.is_some() && !node.is_peer_virtual;
+        Some(if node.qubit_type == QubitType::Data {
+            if has_gate { Self::GateStage(stage) } else { Self::DataIdle }
+        } else if stage == 0 || stage == simulator.measurement_cycles - 1 {
+            Self::Measurement
+        } else if has_gate {
+            Self::GateStage(stage)
+        } else {
+            Self::AncillaIdle
+        })
+    }
+
+    /// the class of the correlated errors at a real node, which always come from a two-qubit gate
+    pub fn of_correlated_errors(simulator: &Simulator, position: &Position) -> Self {
+        Self::GateStage(position.t % simulator.measurement_cycles)
+    }
+
+    /// the number of error locations of each class with a non-zero error rate, where the single-qubit and correlated errors of a node are
+    /// counted separately; the additional noises of the noise model are not classified
+    pub fn count_locations(simulator: &Simulator, noise_model: &NoiseModel) -> BTreeMap<LocationClass, usize> {
+        let mut locations = BTreeMap::new();
+        simulator_iter_real!(simulator, position, _node, {
+            let noise_model_node = noise_model.get_node_unwrap(position);
+            if noise_model_node.pauli_error_rates.error_probability() > 0. || noise_model_node.erasure_error_rate > 0. {
+                if let Some(class) = Self::of_single_qubit_errors(simulator, position) {
+                    *locations.entry(class).or_insert(0) += 1;
+                }
+            }
+            let correlated_pauli = noise_model_node.correlated_pauli_error_rates.as_ref().map_or(0., |rates| rates.error_probability());
+            let correlated_erasure = noise_model_node.correlated_erasure_error_rates.as_ref().map_or(0., |rates| rates.error_probability());
+            if correlated_pauli > 0. || correlated_erasure > 0. {
+                *locations.entry(Self::of_correlated_errors(simulator, position)).or_insert(0) += 1;
+            }
+        });
+        locations
+    }
+
+    /// a copy of the noise model with the error rates of this class multiplied by `factor`, leaving the other locations unchanged
+    pub fn perturb(&self, simulator: &Simulator, noise_model: &NoiseModel, factor: f64) -> Result<NoiseModel, String> {
+        let mut perturbed = noise_model.clone();
+        simulator_iter_real!(simulator, position, _node, {
+            let is_single_qubit = Self::of_single_qubit_errors(simulator, position) == Some(*self);
+            let is_correlated = Self::of_correlated_errors(simulator, position) == *self;
+            let noise_model_node = noise_model.get_node_unwrap(position);
+            let has_correlated = noise_model_node.correlated_pauli_error_rates.is_some() || noise_model_node.correlated_erasure_error_rates.is_some();
+            if is_single_qubit || (is_correlated && has_correlated) {
+                let mut node = noise_model_node.clone();
+                if is_single_qubit {
+                    node.pauli_error_rates = node.pauli_error_rates.scaled(factor);
+                    node.erasure_error_rate *= factor;
+                }
+                if is_correlated {
+                    node.correlated_pauli_error_rates = node.correlated_pauli_error_rates.map(|rates| rates.scaled(factor));
+                    node.correlated_erasure_error_rates = node.correlated_erasure_error_rates.map(|rates| rates.scaled(factor));
+                }
+                let exceeds_one = node.pauli_error_rates.error_probability() > 1. || node.erasure_error_rate > 1.
+                    || node.correlated_pauli_error_rates.as_ref().is_some_and(|rates| rates.error_probability() > 1.)
+                    || node.correlated_erasure_error_rates.as_ref().is_some_and(|rates| rates.error_probability() > 1.);
+                if exceeds_one {
+                    return Err(format!("the perturbed error rate at {} exceeds 1", position))
+                }
+                perturbed.set_node(position, Some(Arc::new(node)));
+            }
+        });
+        Ok(perturbed)
+    }
+}
+
+/// sample the errors of a shot from the random numbers given by `shot_seed`; since every node draws the same number of random numbers regardless
+/// of its error rates, sampling the same seed with noise models that only differ at some locations gives identical errors at all the other locations
+/// (common random numbers), except that the Pauli errors caused by erasures are drawn afterwards
+pub fn generate_random_errors_with_seed(simulator: &mut Simulator, noise_model: &NoiseModel, shot_seed: u64) -> (usize, usize) {
+    simulator.rng = Xoroshiro128StarStar::seed_from_u64(shot_seed);
+    simulator.generate_random_errors(noise_model)
+}
+
+impl SensitivityAnalysisParameters {
+    /// one json line of the unperturbed logical error rate, followed by one json line for each class of locations with the normalized sensitivity
+    /// `d ln(pL) / d ln(p_class)`, estimated by the paired difference of the logical error rates with and without the perturbation
+    pub fn run(&self) -> Result<String, String> {
+        if self.perturbation.is_nan() || self.perturbation <= 0. {
+            return Err(format!("`perturbation` should be positive, but got {}", self.perturbation))
+        }
+        let mut simulator = Simulator::new(self.code_type, CodeSize::new(self.noisy_measurements, self.d, self.dj.unwrap_or(self.d)));
+        let mut noise_model = NoiseModel::new(&simulator);
+        let px = self.p / (1. + self.bias_eta) / 2.;
+        simulator.set_error_rates(&mut noise_model, px, px, self.p - 2. * px, self.pe);
+        if let Some(noise_model_builder) = &self.noise_model_builder {
+            let noise_model_configuration = single_noise_model_configuration(&self.noise_model_configuration)?;
+            noise_model_builder.apply(&mut simulator, &mut noise_model, &noise_model_configuration, self.p, self.bias_eta, self.pe);
+        }
+        noise_model_sanity_check(&simulator, &noise_model)?;
+        simulator.compress_error_rates(&mut noise_model);
+        let locations = LocationClass::count_locations(&simulator, &noise_model);
+        let classes: Vec<LocationClass> = locations.keys().cloned().collect();
+        let mut noise_models = vec![noise_model];
+        for class in classes.iter() {
+            noise_models.push(class.perturb(&simulator, &noise_models[0], 1. + self.perturbation)?);
+        }
+        let noise_model = Arc::new(noise_models[0].clone());
+        let mut decoder = GeneralDecoder::from_decoder_config(self.decoder, &simulator, &noise_model, &self.decoder_config, 1)?;
+        // variant 0 is the unperturbed noise model and variant `k` perturbs `classes[k - 1]`
+        let mut statistics = PairedStatistics::new(noise_models.len());
+        let mut seed_generator = Xoroshiro128StarStar::seed_from_u64(self.seed);
+        let mut qec_failed = vec![false; noise_models.len()];
+        for _ in 0..self.shots {
+            let shot_seed = seed_generator.next_u64();
+            let mut unperturbed_error_pattern = None;
+            for (variant, noise_model) in noise_models.iter().enumerate() {
+                let (error_count, erasure_count) = generate_random_errors_with_seed(&mut simulator, noise_model, shot_seed);
+                if error_count == 0 && erasure_count == 0 {  // no error and no correction never causes a logical error
+                    qec_failed[variant] = false;
+                    continue
+                }
+                // most perturbations don't change the errors of the shot, which then has the same outcome as the unperturbed one
+                let error_pattern = (simulator.generate_sparse_error_pattern(), simulator.generate_sparse_detected_erasures());
+                if variant > 0 && unperturbed_error_pattern.as_ref() == Some(&error_pattern) {
+                    qec_failed[variant] = qec_failed[0];
+                    continue
+                }
+                let (sparse_error_pattern, sparse_detected_erasures) = &error_pattern;
+                debug_assert!(sparse_error_pattern.len() > 0 || sparse_detected_erasures.len() > 0);
+                let sparse_measurement = simulator.generate_sparse_measurement();
+                let (correction, _) = decoder.decode_with_erasure(&sparse_measurement, sparse_detected_erasures);
+                let (logical_i, logical_j) = simulator.validate_correction(&correction);
+                qec_failed[variant] = logical_i || logical_j;
+                if variant == 0 {
+                    unperturbed_error_pattern = Some(error_pattern);
+                }
+            }
+            statistics.add_shot(&qec_failed);
+        }
+        let error_rate = statistics.error_rate(0);
+        let mut output = format!("{}\n", json!({
+            "class": "unperturbed",
+            "shots": statistics.shots,
+            "qec_failed": statistics.failed[0][0],
+            "error_rate": error_rate,
+        }));
+        for (index, class) in classes.iter().enumerate() {
+            let variant = index + 1;
+            let (difference, standard_error) = statistics.difference(variant, 0);
+            // d pL / d ln(p_class) by the forward difference, normalized by pL
+            let normalize = |value: f64| -> Option<f64> {
+                if error_rate > 0. { Some(value / self.perturbation / error_rate) } else { None }
+            };
+            output += &format!("{}\n", json!({
+                "class": class.name(),
+                "locations": locations[class],
+                "qec_failed": statistics.failed[variant][variant],
+                "error_rate": statistics.error_rate(variant),
+                "difference": difference,
+                "standard_error": standard_error,
+                "derivative": difference / self.perturbation,
+                "sensitivity": normalize(difference),
+                "sensitivity_standard_error": normalize(standard_error),
+            }));
+        }
+        Ok(output)
+    }
+}
+
+impl CheckVirtualConsistencyParameters {
+    /// check every shot by [`Simulator::check_virtual_consistency`] and compare the fast measurement with the full propagation, which are
+    /// otherwise only checked by `debug_assert!` in [`Simulator::fast_measurement_given_few_errors`]; the first inconsistent shot is reported as an error
+    pub fn run(&self) -> Result<String, String> {
+        let mut simulator = Simulator::new(self.code_type, CodeSize::new(self.noisy_measurements, self.d, self.dj.unwrap_or(self.d)));
+        let mut noise_model = NoiseModel::new(&simulator);
+        let px = self.p / (1. + self.bias_eta) / 2.;
+        simulator.set_error_rates(&mut noise_model, px, px, self.p - 2. * px, self.pe);
+        if let Some(noise_model_builder) = &self.noise_model_builder {
+            let noise_model_configuration = single_noise_model_configuration(&self.noise_model_configuration)?;
+            noise_model_builder.apply(&mut simulator, &mut noise_model, &noise_model_configuration, self.p, self.bias_eta, self.pe);
+        }
+        noise_model_sanity_check(&simulator, &noise_model)?;
+        simulator.compress_error_rates(&mut noise_model);
+        if simulator.code_size.final_perfect_rounds == 0 {
+            return Err("the consistency of the virtual measurements requires a final perfect measurement round".to_string())
+        }
+        let mut seed_generator = Xoroshiro128StarStar::seed_from_u64(self.seed);
+        let mut checked_shots = 0;
+        let mut defects = 0;
+        let mut virtual_defects = 0;
+        for shot in 0..self.shots {
+            let shot_seed = seed_generator.next_u64();
+            let (error_count, _) = generate_random_errors_with_seed(&mut simulator, &noise_model, shot_seed);
+            if error_count == 0 {
+                continue
+            }
+            let sparse_measurement = simulator.generate_sparse_measurement();
+            let sparse_measurement_virtual = simulator.generate_sparse_measurement_virtual();
+            let sparse_error_pattern = simulator.generate_sparse_error_pattern();
+            let report = |message: String| -> String {
+                format!("shot {} (seed {}) with errors {}: {}", shot, shot_seed, json!(sparse_error_pattern), message)
+            };
+            simulator.check_virtual_consistency(&sparse_measurement, &sparse_measurement_virtual).map_err(report)?;
+            // the fast measurement only propagates the errors within the region they reach
+            simulator.clear_all_errors();
+            let (_, fast_measurement, fast_measurement_virtual) = simulator.fast_measurement_given_few_errors(&sparse_error_pattern);
+            if fast_measurement.to_vec() != sparse_measurement.to_vec() || fast_measurement_virtual.to_vec() != sparse_measurement_virtual.to_vec() {
+                return Err(report(format!("fast measurement {:?} and virtual {:?} differ from the full propagation {:?} and virtual {:?}"
+                    , fast_measurement.to_vec(), fast_measurement_virtual.to_vec(), sparse_measurement.to_vec(), sparse_measurement_virtual.to_vec())))
+            }
+            checked_shots += 1;
+            defects += sparse_measurement.len();
+            virtual_defects += sparse_measurement_virtual.len();
+        }
+        Ok(format!("{}\n", json!({
+            "shots": self.shots,
+            "checked_shots": checked_shots,
+            "defects": defects,
+            "virtual_defects": virtual_defects,
+        })))
+    }
+}
+
+/// the logical errors of several decoders on the same samples; since the samples are shared, the difference of their logical error rates
+/// only comes from the discordant shots where exactly one of them fails, which has a much smaller variance than comparing independent runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairedStatistics {
+    pub shots: usize,
+    /// `failed[a][b]` is the number of shots where decoder `a` fails but decoder `b` succeeds, and `failed[a][a]` is the number of failures of decoder `a`
+    pub failed: Vec<Vec<usize>>,
+}
+
+impl PairedStatistics {
+    pub fn new(decoder_count: usize) -> Self {
+        Self {
+            shots: 0,
+            failed: vec![vec![0; decoder_count]; decoder_count],
+        }
+    }
+
+    /// record a shot given whether each decoder fails
+    pub fn add_shot(&mut self, qec_failed: &[bool]) {
+        assert_eq!(qec_failed.len(), self.failed.len(), "one result per decoder");
+        self.shots += 1;
+        for (a, &failed_a) in qec_failed.iter().enumerate() {
+            if !failed_a {
+                continue
+            }
+            for (b, &failed_b) in qec_failed.iter().enumerate() {
+                if a == b || !failed_b {
+                    self.failed[a][b] += 1;
+                }
+            }
+        }
+    }
+
+    pub fn error_rate(&self, a: usize) -> f64 {
+        if self.shots == 0 { 0. } else { self.failed[a][a] as f64 / self.shots as f64 }
+    }
+
+    /// the difference of the logical error rates `pL(a) - pL(b)` and its standard error, estimated from the per-shot differences in {-1, 0, 1}
+    pub fn difference(&self, a: usize, b: usize) -> (f64, f64) {
+        if self.shots == 0 || a == b {
+            return (0., 0.)
+        }
+        let shots = self.shots as f64;
+        let (n_ab, n_ba) = (self.failed[a][b] as f64, self.failed[b][a] as f64);
+        let difference = (n_ab - n_ba) / shots;
+        let variance = (n_ab + n_ba) / shots - difference * difference;
+        (difference, (variance / shots).sqrt())
+    }
+
+    /// McNemar's statistic `(n_ab - n_ba) / sqrt(n_ab + n_ba)` of the discordant shots, approximately standard normal when the two decoders
+    /// are equally accurate; 0 when there is no discordant shot
+    pub fn mcnemar_z(&self, a: usize, b: usize) -> f64 {
+        if a == b {
+            return 0.
+        }
+        let (n_ab, n_ba) = (self.failed[a][b] as f64, self.failed[b][a] as f64);
+        if n_ab + n_ba == 0. { 0. } else { (n_ab - n_ba) / (n_ab + n_ba).sqrt() }
+    }
+}
+
+/// sample the errors of each shot once and decode the same syndrome with every decoder (sample once, decode many),
+/// so that the decoders are compared on identical samples, see [`PairedStatistics`]
+pub struct SharedSyndromeHarness {
+    pub simulator: Simulator,
+    pub noise_model: Arc<NoiseModel>,
+    pub decoders: Vec<GeneralDecoder>,
+    /// the total decoding time of each decoder in seconds
+    pub decode_time: Vec<f64>,
+}
+
+impl SharedSyndromeHarness {
+    pub fn new(simulator: Simulator, noise_model: Arc<NoiseModel>, decoders: Vec<GeneralDecoder>) -> Self {
+        let decode_time = vec![0.; decoders.len()];
+        Self { simulator, noise_model, decoders, decode_time }
+    }
+
+    /// sample a shot and return whether each decoder fails on it
+    pub fn run_single_shot(&mut self) -> Vec<bool> {
+        let (error_count, erasure_count) = self.simulator.generate_random_errors(&self.noise_model);
+        if error_count == 0 && erasure_count == 0 {  // no error and no correction never causes a logical error
+            return vec![false; self.decoders.len()]
+        }
+        let sparse_detected_erasures = if erasure_count != 0 { self.simulator.generate_sparse_detected_erasures() } else { SparseErasures::new() };
+        let sparse_measurement = self.simulator.generate_sparse_measurement();
+        let simulator = &mut self.simulator;
+        self.decoders.iter_mut().zip(self.decode_time.iter_mut()).map(|(decoder, decode_time)| {
+            let begin = Instant::now();
+            let (correction, _) = decoder.decode_with_erasure(&sparse_measurement, &sparse_detected_erasures);
+            *decode_time += begin.elapsed().as_secs_f64();
+            let (logical_i, logical_j) = simulator.validate_correction(&correction);
+            logical_i || logical_j
+        }).collect()
+    }
+
+    pub fn run(&mut self, shots: usize) -> PairedStatistics {
+        let mut statistics = PairedStatistics::new(self.decoders.len());
+        for _ in 0..shots {
+            statistics.add_shot(&self.run_single_shot());
+        }
+        statistics
+    }
+}
+
+impl ExportNpyParameters {
+    pub fn run(&self) -> Result<String, String> {
+        let mut simulator = Simulator::new(self.code_type, CodeSize::new(self.noisy_measurements, self.d, self.dj.unwrap_or(self.d)));
+        let mut noise_model = NoiseModel::new(&simulator);
+        let px = self.p / (1. + self.bias_eta) / 2.;
+        simulator.set_error_rates(&mut noise_model, px, px, self.p - 2. * px, self.pe);
+        if let Some(noise_model_builder) = &self.noise_model_builder {
+            let noise_model_configuration = single_noise_model_configuration(&self.noise_model_configuration)?;
+            noise_model_builder.apply(&mut simulator, &mut noise_model, &noise_model_configuration, self.p, self.bias_eta, self.pe);
+        }
+        noise_model_sanity_check(&simulator, &noise_model)?;
+        simulator.compress_error_rates(&mut noise_model);
+        simulator.rng = Xoroshiro128StarStar::seed_from_u64(self.seed);
+        let rounds = (simulator.height - 1) / simulator.measurement_cycles;
+        let syndrome_shape = [self.shots, rounds, simulator.vertical, simulator.horizontal];
+        let mut syndromes = Vec::with_capacity(syndrome_shape.iter().product());
+        let mut logicals = Vec::with_capacity(2 * self.shots);
+        for _ in 0..self.shots {
+            simulator.generate_random_errors(&noise_model);
+            let sparse_measurement = simulator.generate_sparse_measurement();
+            syndromes.extend(sparse_measurement.to_ndarray(&simulator).iter());
+            let (logical_i, logical_j) = simulator.validate_correction(&SparseCorrection::new());
+            logicals.extend([logical_i as u8, logical_j as u8]);
+        }
+        let write = |filepath: String, shape: &[usize], elements: &Vec<u8>| -> Result<String, String> {
+            let mut file = File::create(&filepath).map_err(|e| format!("[error] cannot create {}: {}", filepath, e))?;
+            npy::write_npy(&mut file, shape, elements.iter().cloned()).map_err(|e| format!("[error] cannot write {}: {}", filepath, e))?;
+            Ok(filepath)
+        };
+        let syndromes_filepath = write(format!("{}_syndromes.npy", self.output), &syndrome_shape, &syndromes)?;
+        let logicals_filepath = write(format!("{}_logicals.npy", self.output), &[self.shots, 2], &logicals)?;
+        Ok(format!("{}\n", json!({
+            "syndromes": syndromes_filepath,
+            "syndromes_shape": syndrome_shape,
+            "logicals": logicals_filepath,
+            "logicals_shape": [self.shots, 2],
+        })))
+    }
+}
+
+impl GenerateDatasetParameters {
+    /// a json object of the corpus file and its header
+    pub fn run(&self) -> Result<String, String> {
+        let corpus = SyndromeCorpus::generate(SyndromeCorpusHeader {
+            code_type: self.code_type,
+            d: self.d,
+            dj: self.dj.unwrap_or(self.d),
+            noisy_measurements: self.noisy_measurements,
+            p: self.p,
+            bias_eta: self.bias_eta,
+            noise_model_builder: self.noise_model_builder,
+            noise_model_configuration: single_noise_model_configuration(&self.noise_model_configuration)?,
+            seed: self.seed,
+            shots: self.shots,
+            detectors: 0,
+        })?;
+        corpus.save(&self.output)?;
+        Ok(format!("{}\n", json!({
+            "corpus": self.output,
+            "header": corpus.header,
+        })))
+    }
+}
+
+/// decoding time of every shot in a corpus, over a few passes
+pub struct DecoderLatencies {
+    /// the decoding time of every shot in every pass, in seconds
+    pub latencies: Vec<f64>,
+    /// the total decoding time of each pass, in seconds
+    pub pass_elapsed: Vec<f64>,
+    pub shots: usize,
+}
+
+impl DecoderLatencies {
+    /// decode the corpus once to warm up and then `passes` times, timing each shot
+    pub fn measure(decoder: &mut GeneralDecoder, syndromes: &[SparseMeasurement], passes: usize) -> Self {
+        let sparse_detected_erasures = SparseErasures::new();
+        for sparse_measurement in syndromes.iter() {
+            decoder.decode_with_erasure(sparse_measurement, &sparse_detected_erasures);
+        }
+        let mut latencies = Vec::with_capacity(passes * syndromes.len());
+        let mut pass_elapsed = Vec::with_capacity(passes);
+        for _ in 0..passes {
+            let mut elapsed = 0.;
+            for sparse_measurement in syndromes.iter() {
+                let begin = Instant::now();
+                decoder.decode_with_erasure(sparse_measurement, &sparse_detected_erasures);
+                let latency = begin.elapsed().as_secs_f64();
+                latencies.push(latency);
+                elapsed += latency;
+            }
+            pass_elapsed.push(elapsed);
+        }
+        latencies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        Self { latencies, pass_elapsed, shots: syndromes.len() }
+    }
+
+    /// the latency at quantile `q` in [0, 1], using the nearest rank
+    pub fn percentile(&self, q: f64) -> f64 {
+        self.latencies[((self.latencies.len() - 1) as f64 * q).round() as usize]
+    }
+
+    /// the throughput of each pass in shots per second
+    pub fn throughputs(&self) -> Vec<f64> {
+        self.pass_elapsed.iter().map(|elapsed| self.shots as f64 / elapsed).collect()
+    }
+
+    /// the mean throughput across the passes and its relative standard deviation
+    pub fn throughput(&self) -> (f64, f64) {
+        let throughputs = self.throughputs();
+        let mean = throughputs.iter().sum::<f64>() / throughputs.len() as f64;
+        let variance = throughputs.iter().map(|throughput| (throughput - mean).powi(2)).sum::<f64>() / throughputs.len() as f64;
+        (mean, variance.sqrt() / mean)
+    }
+}
+
+impl BenchDecoderParameters {
+    /// one line for each decoder, in the format given by the first line; the latencies are in microseconds
+    pub fn run(&self) -> Result<String, String> {
+        if self.passes == 0 {
+            return Err("at least one pass is required".to_string())
+        }
+        if !self.decoder_config.is_object() {
+            return Err("decoder configuration must be a json object".to_string())
+        }
+        let corpus = SyndromeCorpus::load(&self.corpus)?;
+        if corpus.syndromes.is_empty() {
+            return Err(format!("[error] corpus {} has no shots", self.corpus))
+        }
+        let (simulator, noise_model) = corpus.header.build()?;
+        let mut output = "format: <decoder> <shots> <throughput> <throughput_rsd> <mean_us> <p50_us> <p90_us> <p99_us> <max_us>\n".to_string();
+        for &decoder in self.decoders.iter() {
+            let mut general_decoder = GeneralDecoder::from_decoder_config(decoder, &simulator, &noise_model, &self.decoder_config, 1)?;
+            let latencies = DecoderLatencies::measure(&mut general_decoder, &corpus.syndromes, self.passes);
+            let (throughput, throughput_rsd) = latencies.throughput();
+            let mean = latencies.latencies.iter().sum::<f64>() / latencies.latencies.len() as f64;
+            output += &format!("{} {} {:.3e} {:.3} {:.2} {:.2} {:.2} {:.2} {:.2}\n", decoder.to_possible_value().unwrap().get_name(), latencies.shots
+                , throughput, throughput_rsd, mean * 1e6, latencies.percentile(0.5) * 1e6, latencies.percentile(0.9) * 1e6
+                , latencies.percentile(0.99) * 1e6, latencies.percentile(1.) * 1e6);
+        }
+        Ok(output)
+    }
+}
+
+impl BuildGraphParameters {
+    /// a json object of the model graph file and its configuration hash
+    pub fn run(&self) -> Result<String, String> {
+        let parameters = &self.benchmark;
+        let configs = parameters.fill_in_default_parameters()?;
+        parameters.assert_single_configuration(&configs)?;
+        let config = &parameters.extract_simulation_configurations(&configs)[0];
+        let (weight_function, use_combined_probability) = parameters.model_graph_options()?;
+        let mut simulator = parameters.new_simulator(config.noisy_measurements, config.di, config.dj)?;
+        let noise_model_graph = parameters.construct_noise_model(&mut simulator, &configs, config, true)?;
+        let config_hash = ModelGraph::config_hash(&simulator, &noise_model_graph, &weight_function, use_combined_probability, parameters.use_brief_edge);
+        let mut model_graph = ModelGraph::new(&simulator);
+        model_graph.build(&mut simulator, Arc::clone(&noise_model_graph), &weight_function, configs.parallel_init, use_combined_probability
+            , parameters.use_brief_edge);
+        model_graph.save(&self.out, &config_hash)?;
+        Ok(format!("{}\n", json!({
+            "model_graph": self.out,
+            "config_hash": config_hash,
+        })))
+    }
+}
+
+impl ExportPymatchingParameters {
+    /// a json object of the check matrix file and its dimensions
+    pub fn run(&self) -> Result<String, String> {
+        let parameters = &self.benchmark;
+        let configs = parameters.fill_in_default_parameters()?;
+        parameters.assert_single_configuration(&configs)?;
+        let config = &parameters.extract_simulation_configurations(&configs)[0];
+        let (weight_function, use_combined_probability) = parameters.model_graph_options()?;
+        let mut simulator = parameters.new_simulator(config.noisy_measurements, config.di, config.dj)?;
+        let noise_model_graph = parameters.construct_noise_model(&mut simulator, &configs, config, true)?;
+        let mut model_graph = ModelGraph::new(&simulator);
+        model_graph.build(&mut simulator, Arc::clone(&noise_model_graph), &weight_function, configs.parallel_init, use_combined_probability
+            , parameters.use_brief_edge);
+        let check_matrix = model_graph.check_matrix(&simulator);
+        let file = File::create(&self.out).map_err(|e| format!("[error] cannot create {}: {}", self.out, e))?;
+        serde_json::to_writer(std::io::BufWriter::new(file), &check_matrix).map_err(|e| format!("[error] cannot write {}: {}", self.out, e))?;
+        Ok(format!("{}\n", json!({
+            "check_matrix": self.out,
+            "detectors": check_matrix.detectors,
+            "edges": check_matrix.columns(),
+            "boundary_edges": check_matrix.boundary_edges.len(),
+            "observables": check_matrix.observables,
+        })))
+    }
+}
+
+impl ValidateArtifactParameters {
+    /// print a brief summary of the artifact if it's valid, otherwise the error and its location in the file
+    pub fn run(&self) -> Result<String, String> {
+        let summary = validate_artifact_file(&self.filepath).map_err(|e| format!("[error] invalid artifact {}: {}", self.filepath, e))?;
+        Ok(format!("{}\n", summary))
+    }
+}
+
+impl ShowExperimentParameters {
+    /// print the summary of the manifest as a json line, followed by the results of the run
+    pub fn run(&self) -> Result<String, String> {
+        let directory = Path::new(&self.directory);
+        let manifest_filepath = directory.join(EXPERIMENT_MANIFEST);
+        let manifest = fs::read_to_string(&manifest_filepath).map_err(|e| format!("[error] cannot read {}: {}", manifest_filepath.display(), e))?;
+        let manifest: serde_json::Value = serde_json::from_str(&manifest).map_err(|e| format!("[error] {}: cannot parse json: {}", manifest_filepath.display(), e))?;
+        let mut summary = validate_experiment_manifest(&manifest, directory)
+            .map_err(|e| format!("[error] invalid experiment manifest {}: {}", manifest_filepath.display(), e))?;
+        summary["git_hash"] = manifest["environment"]["git_hash"].clone();
+        summary["host"] = manifest["environment"]["host"].clone();
+        let mut output = format!("{}\n", summary);
+        if let Some(results) = manifest["files"]["results"].as_str() {
+            output += &fs::read_to_string(directory.join(results)).map_err(|e| format!("[error] cannot read {}: {}", results, e))?;
+        }
+        Ok(output)
+    }
+}
+
+impl DiffNoiseModelsParameters {
+    /// print one json line for each position that differs, empty output means the two noise models are equivalent
+    pub fn run(&self) -> Result<String, String> {
+        let load = |filepath: &String| -> Result<NoiseModel, String> {
+            let value = fs::read_to_string(filepath).map_err(|_| format!("[error] noise model file cannot open: {}", filepath))?;
+            let value: serde_json::Value = serde_json::from_str(&value).map_err(|_| "[error] noise model cannot recognize, please check file format".to_string())?;
+            NoiseModel::from_json(&value)
+        };
+        let noise_model_a = load(&self.a)?;
+        let noise_model_b = load(&self.b)?;
+        let mut output = String::new();
+        for (position, node_a, node_b) in noise_model_a.diff(&noise_model_b)?.iter() {
+            output += &format!("{}\n", json!({
+                "position": position,
+                "a": node_a,
+                "b": node_b,
+            }));
         }
+        Ok(output)
     }
 }
 
@@ -70,6 +887,9 @@ pub enum BenchmarkDebugPrint {
     FailedErrorPattern,
     /// erasure graph
     ErasureGraph,
+    /// the consistency report of the erasure graph and the model graph, or the first inconsistency found, see [`validate_graph_consistency`];
+    /// supporting decoder config `weight_function` or `wf`
+    GraphConsistency,
     /// syndrome file for fusion-blossom library to use, output to `output_filename`
     FusionBlossomSyndromeFile,
 }
@@ -106,8 +926,88 @@ pub enum BenchmarkDecoder {
     TailoredMWPM,
     /// union-find decoder
     UnionFind,
+    /// tailored surface code union-find decoder, growing clusters on the positive and negative diagonal sub-graphs independently
+    TailoredUnionFind,
     /// hypergraph union-find decoder
     HyperUnionFind,
+    /// two-stage decoder that corrects the easy union-find clusters directly and decodes the ambiguous ones with MWPM
+    UfThenMwpm,
+    /// pick the decoder per configuration from [`AUTO_DECODER_CANDIDATES`] by a calibration, see `BenchmarkParameters::calibrate_auto_decoder`;
+    /// the decoder configuration is a json object of the configuration of each candidate, e.g. `{"mwpm": {"emmd": 24}}`
+    Auto,
+}
+
+/// the candidates of `--decoder auto`, sorted by their asymptotic decoding cost from the cheapest one
+pub const AUTO_DECODER_CANDIDATES: [BenchmarkDecoder; 2] = [BenchmarkDecoder::UnionFind, BenchmarkDecoder::MWPM];
+
+/// the cheapest candidate, i.e. the earliest one in [`AUTO_DECODER_CANDIDATES`], whose calibrated logical error rate is within `factor`
+/// of the best one, see `--auto-decoder-factor`; the measured decoding time is only reported, because it would make the choice depend on
+/// the machine load and break the reproducibility of seeded runs
+pub fn select_auto_decoder(error_rates: &[f64], factor: f64) -> usize {
+    let best = error_rates.iter().cloned().fold(f64::INFINITY, f64::min);
+    error_rates.iter().position(|&error_rate| error_rate <= factor * best).expect("at least one candidate is needed")
+}
+
+/// the type of errors to decode in a CSS code, see `BenchmarkParameters::decoding_basis`
+#[derive(Copy, Clone, PartialEq, Eq, Debug, ValueEnum, Serialize, Deserialize)]
+#[cfg_attr(feature = "python_binding", cfg_eval)]
+#[cfg_attr(feature = "python_binding", pyclass)]
+pub enum DecodingBasis {
+    /// decode both X and Z errors on the whole decoding graph
+    Both,
+    /// decode only the X errors from the Z stabilizers, counting only the logical X error (of j axis)
+    XOnly,
+    /// decode only the Z errors from the X stabilizers, counting only the logical Z error (of i axis)
+    ZOnly,
+}
+
+impl DecodingBasis {
+    /// the type of stabilizers whose defects are decoded, or None if all of them
+    pub fn stabilizer_type(&self) -> Option<QubitType> {
+        match self {
+            Self::Both => None,
+            Self::XOnly => Some(QubitType::StabZ),
+            Self::ZOnly => Some(QubitType::StabX),
+        }
+    }
+
+    /// whether the logical errors of (i, j) axis are ignored
+    pub fn ignored_logicals(&self) -> (bool, bool) {
+        (*self == Self::XOnly, *self == Self::ZOnly)
+    }
+
+    /// the stabilizer measurements whose defects are decoded, or None if all of them; error if the code is not a CSS code
+    pub fn decoded_defects(&self, simulator: &Simulator) -> Result<Option<BTreeSet<Position>>, String> {
+        let stabilizer_type = match self.stabilizer_type() {
+            Some(stabilizer_type) => stabilizer_type,
+            None => return Ok(None),
+        };
+        let mut decoded_defects = BTreeSet::new();
+        let mut non_css_stabilizer = None;
+        simulator_iter_real!(simulator, position, node, {
+            if position.t.is_multiple_of(simulator.measurement_cycles) && node.gate_type.is_measurement() {
+                if node.qubit_type == stabilizer_type {
+                    decoded_defects.insert(position.clone());
+                } else if node.qubit_type != QubitType::StabX && node.qubit_type != QubitType::StabZ {
+                    non_css_stabilizer = Some(node.qubit_type);
+                }
+            }
+        });
+        if let Some(qubit_type) = non_css_stabilizer {
+            return Err(format!("`decoding_basis` {:?} only supports CSS codes, but {:?} has {:?} stabilizers", self, simulator.code_type, qubit_type))
+        }
+        Ok(Some(decoded_defects))
+    }
+}
+
+/// the importance sampling method of `--importance-sampling`
+#[derive(Copy, Clone, PartialEq, Eq, Debug, ValueEnum, Serialize, Deserialize)]
+#[cfg_attr(feature = "python_binding", cfg_eval)]
+#[cfg_attr(feature = "python_binding", pyclass)]
+pub enum ImportanceSampling {
+    /// sample errors conditioned on the number of faults and reweight by the exact probability of each number, see [`WeightShellSampler`]
+    #[value(alias = "weight_shell")]
+    WeightShell,
 }
 
 /// progress variable shared between threads to update information
@@ -117,7 +1017,25 @@ pub enum BenchmarkDecoder {
 pub struct BenchmarkControl {
     pub total_repeats: usize,
     pub qec_failed: usize,
+    /// shots whose decoding exceeds the `decode_timeout_us` or `shot_time_limit_us` budget, counted regardless of whether the shot fails
+    pub timed_out: usize,
+    /// shots that even the exact minimum-weight decoder fails to correct, only counted with `optimal_failure_rate`
+    pub optimal_failed: usize,
+    /// the diagnostics of the first shot whose cross check disagrees with the regular validation, see `cross_check_fraction`
+    pub cross_check_failure: Option<String>,
     pub external_termination: bool,
+    /// repeats that are claimed by workers but not yet reported
+    pub claimed_repeats: usize,
+    /// the number of shots ever claimed, i.e. the index of the next claimed shot, see `SimulationWorker::shot_stream`
+    pub claimed_shots: usize,
+    /// the latest mini-batch size reported by any worker
+    pub mini_batch_size: usize,
+    /// logical errors bucketed by the number of detected erasures, only counted with `erasure_buckets`
+    pub erasure_conditioned: Option<ConditionedStatistics>,
+    /// logical errors binned by the error weight, only counted with `weight_conditioned`
+    pub weight_conditioned: Option<ConditionedStatistics>,
+    /// the summaries and the sampled records of all the shots, only kept with `statistics_reservoir`
+    pub streaming_statistics: Option<StreamingStatistics>,
 }
 
 impl BenchmarkControl {
@@ -125,46 +1043,313 @@ impl BenchmarkControl {
         Self {
             total_repeats: 0,
             qec_failed: 0,
+            timed_out: 0,
+            optimal_failed: 0,
+            cross_check_failure: None,
             external_termination: false,
+            claimed_repeats: 0,
+            claimed_shots: 0,
+            mini_batch_size: 0,
+            erasure_conditioned: None,
+            weight_conditioned: None,
+            streaming_statistics: None,
         }
     }
-    fn update_data_should_terminate(&mut self, is_qec_failed: bool, max_repeats: usize, min_failed_cases: usize) -> bool {
-        self.total_repeats += 1;
-        if is_qec_failed {
-            self.qec_failed += 1;
+    /// claim at most `mini_batch_size` repeats to run without synchronization, return 0 if the benchmark should terminate;
+    /// the claimed repeats never exceed `max_repeats` in total, so that the statistics is the same as synchronizing every shot;
+    /// also return the index of the first claimed shot, the others following consecutively
+    fn claim_repeats(&mut self, mini_batch_size: usize, max_repeats: usize, min_failed_cases: usize) -> (usize, usize) {
+        let first_shot_index = self.claimed_shots;
+        if self.should_terminate(max_repeats, min_failed_cases) {
+            return (first_shot_index, 0)
         }
-        self.should_terminate(max_repeats, min_failed_cases)
+        let remaining = max_repeats - self.total_repeats - self.claimed_repeats;
+        let claimed = std::cmp::min(mini_batch_size, remaining);
+        self.claimed_repeats += claimed;
+        self.claimed_shots += claimed;
+        (first_shot_index, claimed)
+    }
+    /// report the results of a mini-batch, releasing the claimed repeats
+    fn report_repeats(&mut self, claimed: usize, repeats: usize, qec_failed: usize, mini_batch_size: usize) {
+        debug_assert!(repeats <= claimed && claimed <= self.claimed_repeats);
+        self.claimed_repeats -= claimed;
+        self.total_repeats += repeats;
+        self.qec_failed += qec_failed;
+        self.mini_batch_size = mini_batch_size;
     }
     fn should_terminate(&self, max_repeats: usize, min_failed_cases: usize) -> bool {
-        self.external_termination || self.total_repeats >= max_repeats || self.qec_failed >= min_failed_cases
+        self.external_termination || self.total_repeats + self.claimed_repeats >= max_repeats || self.qec_failed >= min_failed_cases
     }
     fn set_external_terminate(&mut self) {
         self.external_termination = true;
     }
 }
 
-/// decoder might suffer from rare deadlock, and this controller will record the necessary information for debugging with low runtime overhead
+/// the logical errors conditioned on a non-negative integer of each shot, e.g. the number of detected erasures bucketed by
+/// `BenchmarkParameters::erasure_buckets` or the error weight with `BenchmarkParameters::weight_conditioned`
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct BenchmarkThreadDebugger {
-    thread_counter: usize,
-    error_pattern: Option<SparseErrorPattern>,
-    measurement: Option<SparseMeasurement>,
-    detected_erasures: Option<SparseErasures>,
-    correction: Option<SparseCorrection>,
+pub struct ConditionedStatistics {
+    /// the lower boundary of each bin, strictly increasing from 0, where the last bin has no upper boundary;
+    /// None if every value has its own bin, which grows with the largest value seen so far
+    pub boundaries: Option<Vec<usize>>,
+    /// the number of shots of each bin
+    pub shots: Vec<usize>,
+    /// the number of logical errors of each bin
+    pub failed: Vec<usize>,
 }
 
-impl BenchmarkThreadDebugger {
-    fn new() -> Self {
+/// a row of the table of conditional logical error rates, written in the runtime statistics file after the shots of each configuration
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConditionedBin {
+    /// the range of the conditioned value `[lower, upper)`, where `upper` is None for the last bin with boundaries
+    pub range: (usize, Option<usize>),
+    pub shots: usize,
+    pub failed: usize,
+    /// the fraction of all shots that fall in this bin
+    pub fraction: f64,
+    /// the conditional logical error rate, None if no shot falls in this bin
+    pub error_rate: Option<f64>,
+}
+
+impl ConditionedStatistics {
+    /// bin the values by the given lower boundaries
+    pub fn with_boundaries(boundaries: Vec<usize>) -> Result<Self, String> {
+        if boundaries.first() != Some(&0) {
+            return Err(format!("[error] conditioned bins should start from 0, but got {:?}", boundaries))
+        }
+        if boundaries.windows(2).any(|window| window[0] >= window[1]) {
+            return Err(format!("[error] conditioned bins should be strictly increasing, but got {:?}", boundaries))
+        }
+        Ok(Self {
+            shots: vec![0; boundaries.len()],
+            failed: vec![0; boundaries.len()],
+            boundaries: Some(boundaries),
+        })
+    }
+    /// every value has its own bin
+    pub fn per_value() -> Self {
         Self {
-            thread_counter: 0,
-            error_pattern: None,
-            measurement: None,
-            detected_erasures: None,
-            correction: None,
+            boundaries: None,
+            shots: vec![],
+            failed: vec![],
         }
     }
-    fn update_thread_counter(&mut self, thread_counter: usize) -> &mut Self {
-        self.thread_counter = thread_counter;
+    fn bin_of(&self, value: usize) -> usize {
+        match &self.boundaries {
+            Some(boundaries) => boundaries.partition_point(|&boundary| boundary <= value) - 1,
+            None => value,
+        }
+    }
+    fn reserve_bin(&mut self, bin: usize) {
+        if bin >= self.shots.len() {
+            self.shots.resize(bin + 1, 0);
+            self.failed.resize(bin + 1, 0);
+        }
+    }
+    pub fn add_shot(&mut self, value: usize, is_qec_failed: bool) {
+        let bin = self.bin_of(value);
+        self.reserve_bin(bin);
+        self.shots[bin] += 1;
+        if is_qec_failed {
+            self.failed[bin] += 1;
+        }
+    }
+    /// add the statistics of `other` with the same boundaries and clear it
+    pub fn take_from(&mut self, other: &mut Self) {
+        debug_assert_eq!(self.boundaries, other.boundaries);
+        self.reserve_bin(other.shots.len().saturating_sub(1));
+        for bin in 0..other.shots.len() {
+            self.shots[bin] += std::mem::take(&mut other.shots[bin]);
+            self.failed[bin] += std::mem::take(&mut other.failed[bin]);
+        }
+    }
+    pub fn bins(&self) -> Vec<ConditionedBin> {
+        let total_shots: usize = self.shots.iter().sum();
+        (0..self.shots.len()).map(|bin| {
+            let shots = self.shots[bin];
+            let failed = self.failed[bin];
+            ConditionedBin {
+                range: match &self.boundaries {
+                    Some(boundaries) => (boundaries[bin], boundaries.get(bin + 1).cloned()),
+                    None => (bin, Some(bin + 1)),
+                },
+                shots,
+                failed,
+                fraction: if total_shots > 0 { shots as f64 / total_shots as f64 } else { 0. },
+                error_rate: if shots > 0 { Some(failed as f64 / shots as f64) } else { None },
+            }
+        }).collect()
+    }
+}
+
+/// adapt the number of shots between two synchronizations, so that each mini-batch takes roughly `mini_sync_time` seconds;
+/// fast configurations then rarely lock the shared [`BenchmarkControl`] while slow ones still report progress frequently
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MiniBatchController {
+    /// current mini-batch size
+    pub batch_size: usize,
+    /// target time of each mini-batch in seconds
+    pub mini_sync_time: f64,
+    pub min_batch_size: usize,
+    pub max_batch_size: usize,
+    /// rolling average of the latency of a single shot in seconds, None before the first mini-batch
+    pub average_shot_latency: Option<f64>,
+}
+
+impl MiniBatchController {
+    /// the weight of the latest mini-batch in the rolling average
+    const SMOOTHING: f64 = 0.5;
+
+    pub fn new(mini_sync_time: f64, min_batch_size: usize, max_batch_size: usize) -> Self {
+        assert!(min_batch_size >= 1, "mini-batch size must be at least 1");
+        assert!(min_batch_size <= max_batch_size, "min_batch_size should not be larger than max_batch_size");
+        Self {
+            batch_size: min_batch_size,  // start conservatively and let the controller grow it
+            mini_sync_time,
+            min_batch_size,
+            max_batch_size,
+            average_shot_latency: None,
+        }
+    }
+
+    /// update the mini-batch size given the number of shots and the time spent in the last mini-batch
+    pub fn update(&mut self, shots: usize, elapsed: f64) -> usize {
+        if shots == 0 {
+            return self.batch_size
+        }
+        let latency = elapsed / shots as f64;
+        let average = match self.average_shot_latency {
+            Some(average) => Self::SMOOTHING * latency + (1. - Self::SMOOTHING) * average,
+            None => latency,
+        };
+        self.average_shot_latency = Some(average);
+        let ideal_batch_size = if average > 0. { self.mini_sync_time / average } else { f64::INFINITY };
+        // grow at most 2x at a time to avoid a single lucky mini-batch causing a long delay in progress report
+        let ideal_batch_size = ideal_batch_size.min(2. * self.batch_size as f64);
+        self.batch_size = (ideal_batch_size as usize).clamp(self.min_batch_size, self.max_batch_size);
+        self.batch_size
+    }
+}
+
+/// the logical error rate and its relative 95% confidence interval; without any failed case (e.g. p = 0) the relative interval is undefined,
+/// so the 95% upper bound `3 / total_repeats` of the logical error rate is reported instead (rule of three), or 0 without any shot
+pub fn logical_error_rate_with_confidence_interval(total_repeats: usize, qec_failed: usize) -> (f64, f64) {
+    if total_repeats == 0 {
+        return (0., 0.)
+    }
+    if qec_failed == 0 {
+        return (0., (3. / total_repeats as f64).min(1.))
+    }
+    let error_rate = qec_failed as f64 / total_repeats as f64;
+    let confidence_interval_95_percent = 1.96 * (error_rate * (1. - error_rate) / (total_repeats as f64)).sqrt() / error_rate;
+    (error_rate, confidence_interval_95_percent)
+}
+
+/// step through the measurement rounds of a shot for the 3D visualizer, see `--visualize-failures-movie`; frame `r` is at `t = r * measurement_cycles`
+/// and shows the errors and erasures since the previous round, the raw syndrome (flipped stabilizers), the defects and how they changed from the
+/// previous round; the errors after the last round are shown in the last frame, which also carries the correction and the residual error.
+/// the simulator must still hold the errors of this shot
+pub fn syndrome_movie_frames(simulator: &Simulator, error_pattern: &SparseErrorPattern, sparse_measurement: &SparseMeasurement
+        , sparse_detected_erasures: &SparseErasures, correction: &SparseCorrection) -> serde_json::Value {
+    let stabilizers = simulator.stabilizer_order();
+    let round_bitstrings = simulator.round_bitstrings();
+    let mut frames = Vec::with_capacity(round_bitstrings.len());
+    let mut previous_defects: BTreeSet<(usize, usize)> = BTreeSet::new();
+    for (round, bitstring) in round_bitstrings.iter().enumerate() {
+        let t = round * simulator.measurement_cycles;
+        let is_last = round + 1 == round_bitstrings.len();
+        let in_window = |position: &Position| -> bool {
+            (round == 0 || position.t > t - simulator.measurement_cycles) && (is_last || position.t <= t)
+        };
+        let errors: BTreeMap<&Position, &ErrorType> = error_pattern.iter().filter(|(position, _)| in_window(position)).collect();
+        let erasures: Vec<&Position> = sparse_detected_erasures.iter().filter(|position| in_window(position)).collect();
+        let syndrome: Vec<Position> = stabilizers.iter().zip(bitstring.iter()).filter(|(_, &flipped)| flipped)
+            .map(|(stabilizer, _)| pos!(t, stabilizer.i, stabilizer.j)).collect();
+        let defects: BTreeSet<(usize, usize)> = sparse_measurement.iter().filter(|position| position.t == t)
+            .map(|position| (position.i, position.j)).collect();
+        let to_positions = |ancillas: Vec<&(usize, usize)>| -> Vec<Position> {
+            ancillas.into_iter().map(|&(i, j)| pos!(t, i, j)).collect()
+        };
+        let mut frame = json!({
+            "round": round,
+            "t": t,
+            "errors": errors,
+            "erasures": erasures,
+            "syndrome": syndrome,
+            "defects": to_positions(defects.iter().collect()),
+            "appeared": to_positions(defects.difference(&previous_defects).collect()),
+            "disappeared": to_positions(previous_defects.difference(&defects).collect()),
+        });
+        if is_last {
+            frame["correction"] = json!(correction);
+            frame["residual"] = json!(simulator.residual_error(correction));
+        }
+        frames.push(frame);
+        previous_defects = defects;
+    }
+    json!(frames)
+}
+
+/// the number of shots a benchmark runs before it stops, extrapolated from the logical error rate measured in a calibration, see `--dry-run`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShotsEstimate {
+    /// infinite if neither `max_repeats` nor `min_failed_cases` stops the benchmark
+    pub shots: f64,
+    /// no failure is observed in the calibration, so the logical error rate is only bounded from above and the shots from below
+    pub is_lower_bound: bool,
+}
+
+impl ShotsEstimate {
+    /// the benchmark stops at `min_failed_cases` failures, i.e. after `min_failed_cases / pL` shots, or at `max_repeats` shots, where `usize::MAX`
+    /// means infinity; without any failure in the calibration, pL < 3/N with 95% confidence (rule of three), which gives a lower bound of the shots
+    pub fn new(calibration_shots: usize, calibration_failed: usize, max_repeats: usize, min_failed_cases: usize) -> Self {
+        assert!(calibration_shots > 0, "calibration needs at least one shot");
+        let max_repeats = if max_repeats == usize::MAX { f64::INFINITY } else { max_repeats as f64 };
+        let min_failed_cases = if min_failed_cases == usize::MAX { f64::INFINITY } else { min_failed_cases as f64 };
+        let (error_rate, is_upper_bound) = if calibration_failed > 0 {
+            (calibration_failed as f64 / calibration_shots as f64, false)
+        } else {
+            ((3. / calibration_shots as f64).min(1.), true)
+        };
+        let shots_to_fail = (min_failed_cases / error_rate).ceil();
+        if shots_to_fail >= max_repeats {  // a smaller logical error rate also stops at `max_repeats`
+            Self { shots: max_repeats, is_lower_bound: false }
+        } else {
+            Self { shots: shots_to_fail, is_lower_bound: is_upper_bound }
+        }
+    }
+
+    /// limit the running time of the benchmark to `time_budget`, given the running time of each shot
+    pub fn with_time_budget(self, time_budget: Option<f64>, shot_time: f64) -> Self {
+        match time_budget {
+            Some(time_budget) if self.shots * shot_time > time_budget => Self { shots: (time_budget / shot_time).ceil(), is_lower_bound: false },
+            _ => self,
+        }
+    }
+}
+
+/// decoder might suffer from rare deadlock, and this controller will record the necessary information for debugging with low runtime overhead
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchmarkThreadDebugger {
+    thread_counter: usize,
+    error_pattern: Option<SparseErrorPattern>,
+    measurement: Option<SparseMeasurement>,
+    detected_erasures: Option<SparseErasures>,
+    correction: Option<SparseCorrection>,
+}
+
+impl BenchmarkThreadDebugger {
+    fn new() -> Self {
+        Self {
+            thread_counter: 0,
+            error_pattern: None,
+            measurement: None,
+            detected_erasures: None,
+            correction: None,
+        }
+    }
+    fn update_thread_counter(&mut self, thread_counter: usize) -> &mut Self {
+        self.thread_counter = thread_counter;
         self.error_pattern = None;
         self.measurement = None;
         self.detected_erasures = None;
@@ -188,37 +1373,216 @@ impl BenchmarkThreadDebugger {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SingleSimulationConfig {
     di: usize, dj: usize, noisy_measurements: usize, p: f64, pe: f64, p_graph: f64, pe_graph: f64,
+    /// the noise model configuration with all the sweeps resolved
+    noise_model_configuration: serde_json::Value,
+    /// the resolved values of the swept keys, empty if nothing is swept
+    sweep: serde_json::Map<String, serde_json::Value>,
 }
 
 impl SingleSimulationConfig {
-    pub fn new(di: usize, dj: usize, noisy_measurements: usize, p: f64, pe: f64, p_graph: f64, pe_graph: f64) -> Self {
-        Self { di, dj, noisy_measurements, p, pe, p_graph, pe_graph }
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(di: usize, dj: usize, noisy_measurements: usize, p: f64, pe: f64, p_graph: f64, pe_graph: f64
+            , noise_model_configuration: serde_json::Value, sweep: serde_json::Map<String, serde_json::Value>) -> Self {
+        Self { di, dj, noisy_measurements, p, pe, p_graph, pe_graph, noise_model_configuration, sweep }
+    }
+}
+
+/// expand the top-level `"key": {"sweep": [v1, v2, ...]}` entries of a noise model configuration into the Cartesian product of all the
+/// swept values, returning each resolved configuration together with the resolved values of the swept keys; nested objects are kept as is,
+/// so that a sweep inside an unknown key is still reported by the noise model builder
+#[allow(clippy::type_complexity)]
+pub fn expand_noise_model_configuration_sweeps(noise_model_configuration: &serde_json::Value)
+        -> Result<Vec<(serde_json::Value, serde_json::Map<String, serde_json::Value>)>, String> {
+    let mut expanded = vec![(noise_model_configuration.clone(), serde_json::Map::new())];
+    let config = match noise_model_configuration.as_object() {
+        Some(config) => config,
+        None => return Ok(expanded),  // the noise model builder will complain about it
+    };
+    for (key, value) in config.iter() {
+        let sweep = match value.as_object() {
+            Some(object) if object.len() == 1 && object.contains_key("sweep") => &object["sweep"],
+            _ => continue,
+        };
+        let values = match sweep.as_array() {
+            Some(values) if !values.is_empty() => values,
+            _ => return Err(format!("sweep of noise model configuration `{}` must be a non-empty array, found {}", key, sweep)),
+        };
+        expanded = expanded.into_iter().flat_map(|(resolved, resolved_sweep)| values.iter().map(move |value| {
+            let (mut resolved, mut resolved_sweep) = (resolved.clone(), resolved_sweep.clone());
+            resolved[key] = value.clone();
+            resolved_sweep.insert(key.clone(), value.clone());
+            (resolved, resolved_sweep)
+        })).collect();
     }
+    Ok(expanded)
+}
+
+/// the tools other than the benchmark build a single noise model: a sweep of a single value is resolved, while a sweep of multiple values
+/// is rejected with a clear error instead of being reported as an unknown key by the noise model builder
+pub fn single_noise_model_configuration(noise_model_configuration: &serde_json::Value) -> Result<serde_json::Value, String> {
+    let mut expanded = expand_noise_model_configuration_sweeps(noise_model_configuration)?;
+    if expanded.len() > 1 {
+        return Err(format!("[error] sweeping the noise model configuration is only supported by the benchmark, found sweeps of {:?}"
+            , expanded[0].1.keys().collect::<Vec<&String>>()))
+    }
+    Ok(expanded.remove(0).0)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SimulationConfigs {
     dis: Vec<usize>, djs: Vec<usize>, nms: Vec<usize>, ps: Vec<f64>, pes: Vec<f64>, ps_graph: Vec<f64>, pes_graph: Vec<f64>
     , max_repeats: usize, min_failed_cases: usize, parallel: usize, parallel_init: usize, noise_model_modifier: Option<serde_json::Value>,
+    /// the noise model configurations after expanding the sweeps, see [`expand_noise_model_configuration_sweeps`]
+    noise_model_configurations: Vec<(serde_json::Value, serde_json::Map<String, serde_json::Value>)>,
+    /// the noise model of a few rounds replaced by another one, see [`NoiseOverride`]
+    #[serde(default)]
+    noise_override: Option<NoiseOverride>,
 }
 
 impl SimulationConfigs {
     pub fn new(dis: Vec<usize>, djs: Vec<usize>, nms: Vec<usize>, ps: Vec<f64>, pes: Vec<f64>, ps_graph: Vec<f64>, pes_graph: Vec<f64>
-        , max_repeats: usize, min_failed_cases: usize, parallel: usize, parallel_init: usize, noise_model_modifier: Option<serde_json::Value>) -> Self {
-        Self { dis, djs, nms, ps, pes, ps_graph, pes_graph, max_repeats, min_failed_cases, parallel, parallel_init, noise_model_modifier }
+        , max_repeats: usize, min_failed_cases: usize, parallel: usize, parallel_init: usize, noise_model_modifier: Option<serde_json::Value>
+        , noise_model_configurations: Vec<(serde_json::Value, serde_json::Map<String, serde_json::Value>)>, noise_override: Option<NoiseOverride>) -> Self {
+        Self { dis, djs, nms, ps, pes, ps_graph, pes_graph, max_repeats, min_failed_cases, parallel, parallel_init, noise_model_modifier, noise_model_configurations
+            , noise_override }
+    }
+}
+
+/// replace the noise model of a few measurement rounds by another one, e.g. to simulate a calibration glitch in the middle of an experiment;
+/// both the sampled errors and the decoding graph use the composed noise model, see [`NoiseModel::with_time_override`].
+/// e.g. `{"rounds": [5, 8], "error_model": "phenomenological", "p": 0.02}` in `--noise-override`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NoiseOverride {
+    /// the measurement rounds `[start, end)` to override, where the layer `t` belongs to the round `t / measurement_cycles`
+    pub rounds: (usize, usize),
+    /// the noise model builder of the override, with the same names as `--noise-model`; the same as the benchmark if not provided
+    #[serde(default)]
+    pub error_model: Option<String>,
+    pub p: f64,
+    #[serde(default)]
+    pub pe: f64,
+    /// the noise model configuration of the override, see `--noise-model-configuration`
+    #[serde(default = "noise_override_default_configs::noise_model_configuration")]
+    pub noise_model_configuration: serde_json::Value,
+}
+
+pub mod noise_override_default_configs {
+    pub fn noise_model_configuration() -> serde_json::Value { serde_json::json!({}) }
+}
+
+impl NoiseOverride {
+    pub fn from_json(value: &serde_json::Value) -> Result<Self, String> {
+        let mut noise_override: Self = serde_json::from_value(value.clone()).map_err(|e| format!("[error] noise override format error: {}", e))?;
+        noise_override.noise_model_configuration = single_noise_model_configuration(&noise_override.noise_model_configuration)?;
+        if noise_override.rounds.0 >= noise_override.rounds.1 {
+            return Err(format!("[error] noise override should have at least one round, found {:?}", noise_override.rounds))
+        }
+        noise_override.noise_model_builder(None)?;
+        Ok(noise_override)
+    }
+
+    /// the noise model builder of the override, falling back to `default` which is that of the benchmark
+    pub fn noise_model_builder(&self, default: Option<NoiseModelBuilder>) -> Result<Option<NoiseModelBuilder>, String> {
+        match &self.error_model {
+            Some(error_model) => NoiseModelBuilder::from_str(error_model, true).map(Some)
+                .map_err(|_| format!("[error] noise override has unknown error model: {}", error_model)),
+            None => Ok(default),
+        }
     }
 }
+
 impl BenchmarkParameters {
 
     pub fn run(&self) -> Result<String, String> {
+        if let Some(filepath) = &self.config_file {
+            return self.load_config_file(filepath)?.run()
+        }
+        if let Some(calibration_shots) = self.dry_run {
+            return self.run_dry_run(calibration_shots)
+        }
+        if self.seed.is_none() {  // pick a random seed so that it can be recorded
+            let mut parameters = self.clone();
+            parameters.seed = Some(Xoroshiro128StarStar::new().next_u64());
+            return parameters.run()
+        }
+        if self.importance_sampling.is_some() {
+            return self.run_importance_sampling()
+        }
+        if let Some(experiment_dir) = &self.experiment_dir {
+            return self.run_experiment(experiment_dir)
+        }
+        self.run_configurations()
+    }
+
+    /// run the benchmark with all the files written to the experiment directory, followed by the manifest, see `--experiment-dir`
+    fn run_experiment(&self, experiment_dir: &str) -> Result<String, String> {
+        let directory = Path::new(experiment_dir);
+        if directory.exists() {
+            let mut entries = fs::read_dir(directory).map_err(|e| format!("[error] cannot read experiment directory {}: {}", experiment_dir, e))?;
+            if entries.next().is_some() {
+                if !self.force {
+                    return Err(format!("[error] experiment directory {} is not empty, use --force to overwrite it", experiment_dir))
+                }
+                // the files of the previous run are removed, so that neither a failed run nor a run producing fewer files leaves stale ones
+                for filename in [EXPERIMENT_MANIFEST, EXPERIMENT_RESULTS, EXPERIMENT_RUNTIME_STATISTICS, EXPERIMENT_VISUALIZER] {
+                    if directory.join(filename).exists() {
+                        fs::remove_file(directory.join(filename)).map_err(|e| format!("[error] cannot remove the previous {}: {}", filename, e))?;
+                    }
+                }
+            }
+        }
+        fs::create_dir_all(directory).map_err(|e| format!("[error] cannot create experiment directory {}: {}", experiment_dir, e))?;
+        let start_time = Local::now();
+        let begin = Instant::now();
+        let mut parameters = self.clone();
+        parameters.log_runtime_statistics = Some(directory.join(EXPERIMENT_RUNTIME_STATISTICS).to_string_lossy().to_string());
+        let configs = parameters.fill_in_default_parameters()?;
+        let output = parameters.run_configurations()?;
+        fs::write(directory.join(EXPERIMENT_RESULTS), &output).map_err(|e| format!("[error] cannot write results: {}", e))?;
+        let mut files = BTreeMap::new();
+        files.insert("results", EXPERIMENT_RESULTS);
+        files.insert("runtime_statistics", EXPERIMENT_RUNTIME_STATISTICS);
+        if self.enable_visualizer {
+            files.insert("visualizer", EXPERIMENT_VISUALIZER);
+        }
+        let mut manifest = json!({
+            "parameters": parameters,
+            "configs": configs,
+            "configurations": self.extract_simulation_configurations(&configs),
+            "seed": self.seed,
+            "environment": {
+                "version": env!("CARGO_PKG_VERSION"),
+                "git_hash": git_hash(),
+                "arguments": std::env::args().collect::<Vec<String>>(),
+                "host": host_information(),
+            },
+            "start_time": start_time.to_rfc3339(),
+            "end_time": Local::now().to_rfc3339(),
+            "elapsed": begin.elapsed().as_secs_f64(),
+            "files": files,
+        });
+        ArtifactKind::ExperimentManifest.add_envelope(&mut manifest);
+        fs::write(directory.join(EXPERIMENT_MANIFEST), serde_json::to_string_pretty(&manifest).unwrap())
+            .map_err(|e| format!("[error] cannot write the manifest: {}", e))?;
+        Ok(output)
+    }
+
+    /// run all the configurations, after the seed is picked
+    fn run_configurations(&self) -> Result<String, String> {
         let configs = self.fill_in_default_parameters()?;
         // create runtime statistics file object if given file path
         let log_runtime_statistics_file = self.log_runtime_statistics.clone().map(|filename| 
             Arc::new(Mutex::new(File::create(filename.as_str()).expect("cannot create file"))));
-        let simulation_configuration = json!({
+        let mut simulation_configuration = json!({
             "configs": configs,
             "parameters": self,
+            "environment": {
+                "version": env!("CARGO_PKG_VERSION"),
+                "arguments": std::env::args().collect::<Vec<String>>(),
+            },
         });
+        ArtifactKind::RuntimeStatistics.add_envelope(&mut simulation_configuration);
         match &log_runtime_statistics_file {  // append runtime statistics data
             Some(log_runtime_statistics_file) => {
                 let mut log_runtime_statistics_file = log_runtime_statistics_file.lock().unwrap();
@@ -230,7 +1594,10 @@ impl BenchmarkParameters {
         }
         // first list all configurations and validate them at the beginning
         let mut output = format!("");
-        let titles = format!("format: <p> <di> <nm> <shots> <failed> <pL> <dj> <pL_dev> <pe>");
+        let mut titles = "format: <p> <di> <nm> <shots> <failed> <pL> <dj> <pL_dev> <pe>".to_string();
+        if self.decoder == BenchmarkDecoder::Auto {
+            titles += " <auto_decoder>";
+        }
         eprintln!("{}", titles);  // compatible with old scripts
         if self.debug_print.is_none() {  // debug print only, outputs user specified debug info
             output = titles + "\n";
@@ -238,6 +1605,16 @@ impl BenchmarkParameters {
         if self.enable_visualizer {
             self.assert_single_configuration(&configs)?;
         }
+        // connect to the listener of the results, if any
+        #[cfg(unix)]
+        let mut result_stream = match &self.stream_socket {
+            Some(path) => Some(ResultStream::connect(path)?),
+            None => None,
+        };
+        #[cfg(not(unix))]
+        if self.stream_socket.is_some() {
+            return Err(format!("[error] --stream-socket requires Unix domain sockets, which are not supported on this platform"))
+        }
         // start running simulations
         let configurations = self.extract_simulation_configurations(&configs);
         for config in configurations.iter() {
@@ -251,7 +1628,218 @@ impl BenchmarkParameters {
                     log_runtime_statistics_file.sync_data().unwrap();
                 }, _ => { },
             }
-            output += &(self.run_single(&configs, &config, &log_runtime_statistics_file)? + "\n");
+            let result = self.run_single(&configs, config, &log_runtime_statistics_file)?;
+            #[cfg(unix)]
+            if let Some(result_stream) = result_stream.as_mut() {
+                result_stream.send(&json!({
+                    "config": config,
+                    "result": result,
+                }));
+            }
+            output += &(result + "\n");
+        }
+        #[cfg(unix)]
+        if let Some(result_stream) = result_stream.as_mut() {
+            result_stream.close(std::time::Duration::from_secs(1));  // finish the last message before counting the dropped ones
+            if result_stream.dropped > 0 {
+                eprintln!("[warning] {} results are dropped by the stream socket because the listener is slow or disconnected", result_stream.dropped);
+            }
+        }
+        Ok(output)
+    }
+
+    /// load the parameters from the first line of a runtime statistics file, see `--config-file`
+    pub fn load_config_file(&self, filepath: &str) -> Result<BenchmarkParameters, String> {
+        let content = fs::read_to_string(filepath).map_err(|_| format!("[error] config file cannot open: {}", filepath))?;
+        let header = content.lines().next().unwrap_or("");
+        let header: serde_json::Value = serde_json::from_str(header.strip_prefix("#f ").unwrap_or(header))
+            .map_err(|e| format!("[error] config file cannot parse: {}, {}", filepath, e))?;
+        ArtifactKind::RuntimeStatistics.check_envelope(&header)?;
+        let parameters = header.get("parameters").ok_or("[error] config file missing field: parameters".to_string())?;
+        let mut parameters: BenchmarkParameters = serde_json::from_value(parameters.clone())
+            .map_err(|e| format!("[error] config file cannot parse parameters: {}", e))?;
+        parameters.config_file = None;
+        parameters.log_runtime_statistics = self.log_runtime_statistics.clone();
+        parameters.stream_socket = self.stream_socket.clone();
+        parameters.experiment_dir = self.experiment_dir.clone();
+        parameters.force = self.force;
+        Ok(parameters)
+    }
+
+    /// one json line per configuration with its budgets, where `null` means unlimited, followed by a summary of the total compute: the maximum
+    /// number of shots, the decoded spacetime volume `shots * di * dj * (nm + 1)` they amount to, and the total time budget; these are
+    /// upper bounds given by the budgets alone, see `--dry-run` for an estimate from the measured logical error rates
+    pub fn run_plan(&self) -> Result<String, String> {
+        if let Some(filepath) = &self.config_file {
+            return self.load_config_file(filepath)?.run_plan()
+        }
+        let configs = self.fill_in_default_parameters()?;
+        let configurations = self.extract_simulation_configurations(&configs);
+        let limited = |value: usize| -> Option<usize> { if value == usize::MAX { None } else { Some(value) } };
+        let max_repeats = limited(configs.max_repeats);
+        let mut output = String::new();
+        let mut total_volume = Some(0usize);
+        for config in configurations.iter() {
+            output += &format!("{}\n", json!({
+                "config": config,
+                "max_repeats": max_repeats,
+                "min_failed_cases": limited(configs.min_failed_cases),
+                "time_budget": self.time_budget,
+            }));
+            let volume = config.di * config.dj * (config.noisy_measurements + 1);
+            total_volume = total_volume.zip(max_repeats).and_then(|(total, shots)| shots.checked_mul(volume).and_then(|volume| total.checked_add(volume)));
+        }
+        output += &format!("{}\n", json!({
+            "configurations": configurations.len(),
+            "total_max_repeats": max_repeats.and_then(|shots| shots.checked_mul(configurations.len())),
+            "total_volume": total_volume,
+            "total_time_budget": self.time_budget.map(|time_budget| time_budget * configurations.len() as f64),
+            "parallel": configs.parallel,
+        }));
+        Ok(output)
+    }
+
+    /// decode `max_repeats` shots of each configuration in a single thread and print one json line per configuration with the mean and the maximum
+    /// decoding time in seconds, together with the error pattern of the slowest shot, which can be re-examined by `--fixed-error-file`
+    pub fn run_worst_case_latency(&self) -> Result<String, String> {
+        let configs = self.fill_in_default_parameters()?;
+        let mut output = String::new();
+        for config in self.extract_simulation_configurations(&configs).iter() {
+            let mut simulator = self.new_simulator(config.noisy_measurements, config.di, config.dj)?;
+            let noise_model_graph = self.construct_noise_model(&mut simulator, &configs, config, true)?;
+            let mut general_decoder = GeneralDecoder::from_parameters(self, &configs, config, &simulator, &noise_model_graph)?;
+            let noise_model = self.construct_noise_model(&mut simulator, &configs, config, false)?;
+            let mut total_latency = 0.;
+            let mut worst: Option<(f64, serde_json::Value)> = None;
+            for _ in 0..configs.max_repeats {
+                let (error_count, erasure_count) = simulator.generate_random_errors(&noise_model);
+                let sparse_detected_erasures = if erasure_count != 0 { simulator.generate_sparse_detected_erasures() } else { SparseErasures::new() };
+                let sparse_measurement = if error_count != 0 { simulator.generate_sparse_measurement() } else { SparseMeasurement::new() };
+                let begin = Instant::now();
+                general_decoder.decode_with_erasure(&sparse_measurement, &sparse_detected_erasures);
+                let latency = begin.elapsed().as_secs_f64();
+                total_latency += latency;
+                if worst.as_ref().map(|(worst_latency, _)| latency > *worst_latency).unwrap_or(true) {
+                    worst = Some((latency, json!({
+                        "error_pattern": simulator.generate_sparse_error_pattern(),
+                        "measurement": sparse_measurement,
+                        "detected_erasures": sparse_detected_erasures,
+                    })));
+                }
+            }
+            let (max_latency, worst_shot) = worst.unwrap_or((0., json!(null)));
+            output += &format!("{}\n", json!({
+                "p": config.p,
+                "di": config.di,
+                "dj": config.dj,
+                "noisy_measurements": config.noisy_measurements,
+                "pe": config.pe,
+                "shots": configs.max_repeats,
+                "mean_latency": if configs.max_repeats > 0 { total_latency / configs.max_repeats as f64 } else { 0. },
+                "max_latency": max_latency,
+                "worst_shot": worst_shot,
+            }));
+        }
+        Ok(output)
+    }
+
+    /// simulate `calibration_shots` shots of each configuration in a single thread and print the estimated number of shots and running time
+    /// of the benchmark, including the time to build the decoder, without running it; the total time is a lower bound if any row is
+    /// a lower bound
+    pub fn run_dry_run(&self, calibration_shots: usize) -> Result<String, String> {
+        if calibration_shots == 0 {
+            return Err("`dry_run` needs at least one calibration shot".to_string())
+        }
+        let configs = self.fill_in_default_parameters()?;
+        let (ignore_logical_i, ignore_logical_j) = self.ignored_logicals();
+        let titles = "format: <p> <di> <nm> <shots> <failed> <pL> <dj> <pe> <init_time> <shot_time> <estimated_shots> <estimated_time>".to_string();
+        eprintln!("{}", titles);
+        let mut output = titles + "\n";
+        let mut total_time = 0.;
+        let mut is_total_lower_bound = false;
+        for config in self.extract_simulation_configurations(&configs).iter() {
+            let begin = Instant::now();
+            let mut simulator = self.new_simulator(config.noisy_measurements, config.di, config.dj)?;
+            let noise_model_graph = self.construct_noise_model(&mut simulator, &configs, config, true)?;
+            let mut general_decoder = GeneralDecoder::from_parameters(self, &configs, config, &simulator, &noise_model_graph)?;
+            let noise_model = self.construct_noise_model(&mut simulator, &configs, config, false)?;
+            let decoded_defects = self.decoded_defects(&simulator)?;
+            let init_time = begin.elapsed().as_secs_f64();
+            if let Some(seed) = self.seed {
+                simulator.rng = Xoroshiro128StarStar::seed_from_u64(seed);
+            }
+            let begin = Instant::now();
+            let mut failed = 0;
+            for _ in 0..calibration_shots {
+                let (error_count, erasure_count) = simulator.generate_random_errors(&noise_model);
+                let sparse_detected_erasures = if erasure_count != 0 { simulator.generate_sparse_detected_erasures() } else { SparseErasures::new() };
+                let mut sparse_measurement = if error_count != 0 { simulator.generate_sparse_measurement() } else { SparseMeasurement::new() };
+                if let Some(decoded_defects) = decoded_defects.as_ref() {
+                    sparse_measurement = SparseMeasurement::new_set(sparse_measurement.iter().filter(|position| decoded_defects.contains(position)).cloned().collect());
+                }
+                let (correction, _) = general_decoder.decode_with_erasure(&sparse_measurement, &sparse_detected_erasures);
+                let (logical_i, logical_j) = simulator.validate_correction(&correction);
+                if (logical_i && !ignore_logical_i) || (logical_j && !ignore_logical_j) {
+                    failed += 1;
+                }
+            }
+            let shot_time = begin.elapsed().as_secs_f64() / calibration_shots as f64;
+            let parallel_shot_time = shot_time / configs.parallel as f64;
+            let estimate = ShotsEstimate::new(calibration_shots, failed, configs.max_repeats, configs.min_failed_cases)
+                .with_time_budget(self.time_budget, parallel_shot_time);
+            let estimated_time = init_time + estimate.shots * parallel_shot_time;
+            total_time += estimated_time;
+            is_total_lower_bound |= estimate.is_lower_bound;
+            let lower_bound_mark = if estimate.is_lower_bound { ">=" } else { "" };
+            output += &format!("{} {} {} {} {} {} {} {} {:.3e} {:.3e} {}{} {}{:.3e}\n", config.p, config.di, config.noisy_measurements, calibration_shots, failed
+                , failed as f64 / calibration_shots as f64, config.dj, config.pe, init_time, shot_time, lower_bound_mark, estimate.shots, lower_bound_mark, estimated_time);
+        }
+        output += &format!("total: {}{:.3e}\n", if is_total_lower_bound { ">=" } else { "" }, total_time);
+        Ok(output)
+    }
+
+    /// estimate the logical error rate of each configuration with `--importance-sampling`, in the same output format as the benchmark
+    pub fn run_importance_sampling(&self) -> Result<String, String> {
+        let configs = self.fill_in_default_parameters()?;
+        let (ignore_logical_i, ignore_logical_j) = self.ignored_logicals();
+        let titles = "format: <p> <di> <nm> <shots> <failed> <pL> <dj> <pL_dev> <pe>".to_string();
+        eprintln!("{}", titles);
+        let mut output = titles + "\n";
+        for config in self.extract_simulation_configurations(&configs).iter() {
+            if config.pe > 0. {
+                return Err("`importance_sampling` doesn't support erasure errors".to_string())
+            }
+            let mut simulator = self.new_simulator(config.noisy_measurements, config.di, config.dj)?;
+            let noise_model_graph = self.construct_noise_model(&mut simulator, &configs, config, true)?;
+            let mut general_decoder = GeneralDecoder::from_parameters(self, &configs, config, &simulator, &noise_model_graph)?;
+            let noise_model = self.construct_noise_model(&mut simulator, &configs, config, false)?;
+            let decoded_defects = self.decoded_defects(&simulator)?;
+            if let Some(seed) = self.seed {
+                simulator.rng = Xoroshiro128StarStar::seed_from_u64(seed);
+            }
+            // sample one more shell at a time until the truncated tail is negligible compared to the estimated logical error rate
+            let mut estimate = WeightShellEstimate::new();
+            let mut sampler = WeightShellSampler::new(&simulator, &noise_model, 1)?;
+            loop {
+                for weight in estimate.extend(&sampler) {
+                    for _ in 0..self.shell_shots {
+                        sampler.generate_errors(&mut simulator, weight);
+                        let mut sparse_measurement = simulator.generate_sparse_measurement();
+                        if let Some(decoded_defects) = decoded_defects.as_ref() {
+                            sparse_measurement = SparseMeasurement::new_set(sparse_measurement.iter().filter(|position| decoded_defects.contains(position)).cloned().collect());
+                        }
+                        let (correction, _) = general_decoder.decode_with_erasure(&sparse_measurement, &SparseErasures::new());
+                        let (logical_i, logical_j) = simulator.validate_correction(&correction);
+                        estimate.record(weight, (logical_i && !ignore_logical_i) || (logical_j && !ignore_logical_j));
+                    }
+                }
+                if sampler.max_weight == sampler.mechanisms.len() || estimate.is_tail_negligible(WEIGHT_SHELL_TAIL_TOLERANCE) {
+                    break
+                }
+                sampler.increase_max_weight()?;
+            }
+            output += &format!("{} {} {} {} {} {} {} {:.1e} {}\n", config.p, config.di, config.noisy_measurements, estimate.shots(), estimate.failed()
+                , estimate.logical_error_rate(), config.dj, estimate.relative_deviation(), config.pe);
         }
         Ok(output)
     }
@@ -264,10 +1852,19 @@ impl BenchmarkParameters {
         let nms = self.nms.clone();
         assert!(nms.len() == dis.len(), "nms and dis should be paired");
         assert!(dis.len() == djs.len(), "dis and djs should be paired");
-        let ps = self.ps.clone();
-        let ps_graph = self.ps_graph.clone().unwrap_or(ps.clone());
-        let pes = self.pes.clone().unwrap_or(vec![0.; ps.len()]);  // by default no erasure errors
-        let pes_graph = self.pes_graph.clone().unwrap_or(pes.clone());
+        let mut ps = self.ps.clone();
+        let mut ps_graph = self.ps_graph.clone().unwrap_or(ps.clone());
+        let mut pes = self.pes.clone().unwrap_or(vec![0.; ps.len()]);  // by default no erasure errors
+        let mut pes_graph = self.pes_graph.clone().unwrap_or(pes.clone());
+        if self.grid_sweep {  // expand into the pairs of the cartesian product
+            assert_eq!(ps_graph.len(), ps.len(), "ps_graph and p should be matched");
+            assert_eq!(pes_graph.len(), pes.len(), "pes_graph and pe should be matched");
+            let pes_len = pes.len();
+            ps = ps.iter().flat_map(|&p| std::iter::repeat_n(p, pes_len)).collect();
+            ps_graph = ps_graph.iter().flat_map(|&p_graph| std::iter::repeat_n(p_graph, pes_len)).collect();
+            pes = pes.repeat(self.ps.len());
+            pes_graph = pes_graph.repeat(self.ps.len());
+        }
         assert_eq!(pes.len(), ps.len(), "pe and p should be matched");
         assert_eq!(ps_graph.len(), ps.len(), "ps_graph and p should be matched");
         assert_eq!(pes_graph.len(), ps.len(), "pes_graph and p should be matched");
@@ -281,7 +1878,11 @@ impl BenchmarkParameters {
         }
         // if parallel = 0, use all CPU resources
         let parallel = if self.parallel == 0 { std::cmp::max(num_cpus::get() - 1, 1) } else { self.parallel };
-        let parallel_init: usize = self.parallel_init.clone().unwrap_or(self.parallel);
+        let parallel_init: usize = match self.graph_build_threads {
+            Some(0) => std::cmp::max(num_cpus::get() - 1, 1),
+            Some(graph_build_threads) => graph_build_threads,
+            None => self.parallel_init.unwrap_or(self.parallel),
+        };
         // noise model modifier, might from `load_noise_model_from_temporary_store` or `load_noise_model_from_file`
         let mut noise_model_modifier_str: Option<String> = None;
         match self.load_noise_model_from_temporary_store {
@@ -309,11 +1910,104 @@ impl BenchmarkParameters {
             },
             None => None,
         };
-        Ok(SimulationConfigs::new(dis, djs, nms, ps, pes, ps_graph, pes_graph, max_repeats, min_failed_cases, parallel, parallel_init, noise_model_modifier))
+        let noise_model_configurations = expand_noise_model_configuration_sweeps(&self.noise_model_configuration)?;
+        let noise_override = self.noise_override.as_ref().map(NoiseOverride::from_json).transpose()?;
+        Ok(SimulationConfigs::new(dis, djs, nms, ps, pes, ps_graph, pes_graph, max_repeats, min_failed_cases, parallel, parallel_init, noise_model_modifier
+            , noise_model_configurations, noise_override))
+    }
+
+    /// the weight function and whether to use the combined probability in the model graph built by the decoder, which are part of the
+    /// configuration hash of a saved model graph, see [`ModelGraph::config_hash`]
+    pub fn model_graph_options(&self) -> Result<(WeightFunction, bool), String> {
+        match self.decoder {
+            BenchmarkDecoder::MWPM => {
+                let config: MWPMDecoderConfig = serde_json::from_value(self.decoder_config.clone()).map_err(|e| e.to_string())?;
+                Ok((config.weight_function, config.use_combined_probability))
+            },
+            BenchmarkDecoder::UnionFind => {
+                let config: UnionFindDecoderConfig = serde_json::from_value(self.decoder_config.clone()).map_err(|e| e.to_string())?;
+                Ok((config.weight_function, config.use_combined_probability))
+            },
+            _ => Err("saved model graphs are only supported by the MWPM and union-find decoders".to_string()),
+        }
+    }
+
+    /// load the model graph of `--load_graph` if provided, which must be built from the same configuration as the simulation
+    pub fn load_model_graph(&self, simulator: &Simulator, noise_model_graph: &NoiseModel) -> Result<Option<ModelGraph>, String> {
+        match &self.load_graph {
+            Some(filepath) => {
+                let (weight_function, use_combined_probability) = self.model_graph_options()?;
+                let config_hash = ModelGraph::config_hash(simulator, noise_model_graph, &weight_function, use_combined_probability, self.use_brief_edge);
+                Ok(Some(ModelGraph::load(filepath, &config_hash)?))
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// the model graph given to the MWPM or union-find decoder: the one of `--load_graph`, restricted to the stabilizers of `--decoding_basis`
+    /// and clipped to `--decode_region`; None lets the decoder build the whole model graph itself
+    pub fn prepare_model_graph(&self, simulator: &Simulator, noise_model_graph: &Arc<NoiseModel>, parallel: usize) -> Result<Option<ModelGraph>, String> {
+        let model_graph = self.load_model_graph(simulator, noise_model_graph)?;
+        let stabilizer_type = self.decoding_basis.stabilizer_type();
+        let region = self.region()?;
+        if stabilizer_type.is_none() && region.is_none() {
+            return Ok(model_graph)
+        }
+        let mut simulator = simulator.clone();
+        let mut model_graph = match model_graph {
+            Some(mut model_graph) => {
+                if let Some(stabilizer_type) = stabilizer_type {
+                    model_graph.retain_stabilizer_type(&simulator, stabilizer_type);
+                }
+                model_graph
+            },
+            None => {  // only build the sub-graph of the decoded stabilizers
+                let (weight_function, use_combined_probability) = self.model_graph_options()?;
+                let mut model_graph = ModelGraph::new(&simulator);
+                model_graph.build_stabilizer_type(&mut simulator, Arc::clone(noise_model_graph), &weight_function, parallel, use_combined_probability
+                    , self.use_brief_edge, stabilizer_type);
+                model_graph
+            },
+        };
+        if let Some(region) = region.as_ref() {
+            model_graph.clip_to_region(&simulator, region);
+        }
+        Ok(Some(model_graph))
+    }
+
+    /// the region of `--decode_region`, if given
+    pub fn region(&self) -> Result<Option<Region>, String> {
+        match &self.decode_region {
+            Some(value) => Ok(Some(serde_json::from_value(value.clone()).map_err(|e| format!("[error] decode region format error: {}", e))?)),
+            None => Ok(None),
+        }
+    }
+
+    /// the stabilizer measurements whose defects are decoded, given by `--decoding_basis` and `--decode_region`, or None if all of them
+    pub fn decoded_defects(&self, simulator: &Simulator) -> Result<Option<BTreeSet<Position>>, String> {
+        let decoded_defects = self.decoding_basis.decoded_defects(simulator)?;
+        let region = match self.region()? {
+            Some(region) => region,
+            None => return Ok(decoded_defects),
+        };
+        let mut region_defects = BTreeSet::new();
+        simulator_iter_real!(simulator, position, node, {
+            if position.t.is_multiple_of(simulator.measurement_cycles) && node.gate_type.is_measurement() && region.contains(position)
+                    && decoded_defects.as_ref().map(|decoded_defects| decoded_defects.contains(position)).unwrap_or(true) {
+                region_defects.insert(position.clone());
+            }
+        });
+        Ok(Some(region_defects))
+    }
+
+    /// whether the logical errors of (i, j) axis are ignored, either explicitly or by `--decoding_basis`
+    pub fn ignored_logicals(&self) -> (bool, bool) {
+        let (ignore_logical_i, ignore_logical_j) = self.decoding_basis.ignored_logicals();
+        (self.ignore_logical_i || ignore_logical_i, self.ignore_logical_j || ignore_logical_j)
     }
 
     pub fn assert_single_configuration(&self, configs: &SimulationConfigs) -> Result<(), String> {
-        if configs.dis.len() != 1 || configs.ps.len() != 1 {
+        if configs.dis.len() != 1 || configs.ps.len() != 1 || configs.noise_model_configurations.len() != 1 {
             return Err("only single configuration is allowed".to_string());
         }
         Ok(())
@@ -333,12 +2027,25 @@ impl BenchmarkParameters {
                 assert!(p_graph >= 0. && p_graph <= 1.0, "invalid probability value");
                 assert!(pe >= 0. && pe <= 1.0, "invalid probability value");
                 assert!(pe_graph >= 0. && pe_graph <= 1.0, "invalid probability value");
-                configurations.push(SingleSimulationConfig::new(di, dj, noisy_measurements, p, pe, p_graph, pe_graph));
+                for (noise_model_configuration, sweep) in configs.noise_model_configurations.iter() {
+                    configurations.push(SingleSimulationConfig::new(di, dj, noisy_measurements, p, pe, p_graph, pe_graph
+                        , noise_model_configuration.clone(), sweep.clone()));
+                }
             }
         }
         configurations
     }
 
+    /// build the simulator of a configuration, ending with the final perfect rounds and with the idle rounds inserted between the measurement cycles
+    pub fn new_simulator(&self, noisy_measurements: usize, di: usize, dj: usize) -> Result<Simulator, String> {
+        if self.final_perfect_rounds == 0 {
+            return Err("[error] at least one final perfect round is required to validate the correction".to_string())
+        }
+        let mut simulator = Simulator::new(self.code_type, CodeSize::new(noisy_measurements, di, dj).with_final_perfect_rounds(self.final_perfect_rounds));
+        code_builder_insert_idle_rounds(&mut simulator, self.idle_rounds_between_cycles)?;
+        Ok(simulator)
+    }
+
     pub fn construct_noise_model(&self, simulator: &mut Simulator, configs: &SimulationConfigs, config: &SingleSimulationConfig, use_p_graph: bool) -> Result<Arc<NoiseModel>, String> {
         let mut noise_model: NoiseModel = NoiseModel::new(&simulator);
         let p = if use_p_graph { config.p_graph } else { config.p };
@@ -347,9 +2054,25 @@ impl BenchmarkParameters {
         let py = px;
         let pz = p - 2. * px;
         simulator.set_error_rates(&mut noise_model, px, py, pz, pe);
+        // the TLS defects are applied here instead of by the noise model builder, so that a malformed one is reported as an error
+        let mut noise_model_configuration = config.noise_model_configuration.clone();
+        let tls_defects = match noise_model_configuration.as_object_mut().and_then(|config| config.remove("tls")) {
+            Some(tls) => {
+                if self.noise_model_builder.is_none() {
+                    return Err("the `tls` noise model configuration requires `--noise-model`".to_string())
+                }
+                let tls_defects: TlsDefects = serde_json::from_value(tls).map_err(|e| format!("tls format error: {e}"))?;
+                // a decoder ignorant of the TLS defects builds its decoding graph without them, see [`TlsDefects::informed`]
+                if use_p_graph && !tls_defects.informed { None } else { Some(tls_defects) }
+            },
+            None => None,
+        };
         // apply customized noise model
         if let Some(noise_model_builder) = &self.noise_model_builder {
-            noise_model_builder.apply(simulator, &mut noise_model, &self.noise_model_configuration, p, self.bias_eta, pe);
+            noise_model_builder.apply(simulator, &mut noise_model, &noise_model_configuration, p, self.bias_eta, pe);
+        }
+        if let Some(tls_defects) = tls_defects {
+            tls_defects.apply(simulator, &mut noise_model).map_err(|e| format!("tls defects error: {e}"))?;
         }
         // apply noise model modifier
         match &configs.noise_model_modifier {
@@ -358,6 +2081,19 @@ impl BenchmarkParameters {
             },
             None => { }
         }
+        // replace the noise model of a few rounds, for both the sampled errors and the decoding graph
+        if let Some(noise_override) = &configs.noise_override {
+            let mut override_simulator = simulator.clone();  // the noise model builder may also modify the simulator
+            let mut override_noise_model = NoiseModel::new(&override_simulator);
+            let p = noise_override.p;
+            let px = p / (1. + self.bias_eta) / 2.;
+            override_simulator.set_error_rates(&mut override_noise_model, px, px, p - 2. * px, noise_override.pe);
+            if let Some(noise_model_builder) = noise_override.noise_model_builder(self.noise_model_builder)? {
+                noise_model_builder.apply(&mut override_simulator, &mut override_noise_model, &noise_override.noise_model_configuration, p, self.bias_eta, noise_override.pe);
+            }
+            let (start, end) = noise_override.rounds;
+            noise_model = NoiseModel::with_time_override(&noise_model, start * simulator.measurement_cycles .. end * simulator.measurement_cycles, &override_noise_model);
+        }
         debug_assert!({  // check correctness only in debug mode because it's expensive
             let sanity_check_result = code_builder_sanity_check(&simulator);
             if let Err(message) = &sanity_check_result {
@@ -422,6 +2158,15 @@ impl BenchmarkParameters {
                 erasure_graph.build(simulator, noise_model.clone(), configs.parallel_init);
                 return Ok(Some(format!("{}\n", serde_json::to_string(&erasure_graph.to_json(&simulator)).unwrap())));
             },
+            Some(BenchmarkDebugPrint::GraphConsistency) => {
+                let config: BenchmarkDebugPrintDecoderConfig = serde_json::from_value(self.decoder_config.clone()).map_err(|x| x.to_string())?;
+                let mut model_graph = ModelGraph::new(simulator);
+                model_graph.build(simulator, noise_model.clone(), &config.weight_function, configs.parallel_init, config.use_combined_probability, self.use_brief_edge);
+                let mut erasure_graph = ErasureGraph::new(simulator);
+                erasure_graph.build(simulator, noise_model.clone(), configs.parallel_init);
+                let report = validate_graph_consistency(simulator, &model_graph, &erasure_graph, noise_model)?;
+                return Ok(Some(format!("{}\n", serde_json::to_string(&report).unwrap())));
+            },
             _ => { }
         }
         Ok(None)
@@ -430,8 +2175,14 @@ impl BenchmarkParameters {
     pub fn prepare_visualizer(&self, simulator: &mut Simulator, noise_model: &Arc<NoiseModel>, noise_model_graph: &Arc<NoiseModel>, configs: &SimulationConfigs) -> Result<Option<Arc<Mutex<Visualizer>>>, String> {
         let mut visualizer = None;
         if self.enable_visualizer {
-            print_visualize_link(self.visualizer_filename.clone());
-            let mut new_visualizer: Visualizer = Visualizer::new(Some(visualize_data_folder() + self.visualizer_filename.as_str())).map_err(|x| x.to_string())?;
+            let visualizer_filepath = match &self.experiment_dir {
+                Some(experiment_dir) => Path::new(experiment_dir).join(EXPERIMENT_VISUALIZER).to_string_lossy().to_string(),
+                None => {
+                    print_visualize_link(self.visualizer_filename.clone());
+                    Path::new(&visualize_data_folder()).join(&self.visualizer_filename).to_string_lossy().to_string()
+                },
+            };
+            let mut new_visualizer: Visualizer = Visualizer::new(Some(visualizer_filepath)).map_err(|x| x.to_string())?;
             new_visualizer.add_component(simulator).map_err(|x| x.to_string())?;
             new_visualizer.add_component(noise_model.as_ref()).map_err(|x| x.to_string())?;
             if self.visualizer_model_graph {
@@ -456,14 +2207,104 @@ impl BenchmarkParameters {
 
     /// run a single simulation; self and configs are general for all simulations, config is specific to a single simulation
     pub fn run_single(&self, configs: &SimulationConfigs, config: &SingleSimulationConfig, log_runtime_statistics_file: &Option<Arc<Mutex<File>>>) -> Result<String, String> {
+        self.run_single_with_report(configs, config, log_runtime_statistics_file, |partial_result| {
+            eprintln!("[partial] {}", partial_result);
+        })
+    }
+
+    /// decode the same `auto_calibration_shots` shots of this configuration with every candidate of `--decoder auto`, and return the parameters
+    /// of the selected decoder (see [`select_auto_decoder`]) together with the selection and the calibration data of each candidate
+    pub fn calibrate_auto_decoder(&self, configs: &SimulationConfigs, config: &SingleSimulationConfig) -> Result<(BenchmarkParameters, serde_json::Value), String> {
+        if !self.decoder_config.is_object() {
+            return Err("the configuration of the `auto` decoder must be a json object of the configuration of each candidate".to_string())
+        }
+        if self.auto_calibration_shots == 0 {
+            return Err("`auto_calibration_shots` must be positive".to_string())
+        }
+        let candidate_name = |decoder: BenchmarkDecoder| decoder.to_possible_value().unwrap().get_name().to_string();
+        let mut candidates = Vec::with_capacity(AUTO_DECODER_CANDIDATES.len());
+        for decoder in AUTO_DECODER_CANDIDATES.iter() {
+            let mut parameters = self.clone();
+            parameters.decoder = *decoder;
+            parameters.decoder_config = self.decoder_config.get(candidate_name(*decoder)).cloned().unwrap_or(json!({}));
+            candidates.push(parameters);
+        }
+        for name in self.decoder_config.as_object().unwrap().keys() {
+            if !AUTO_DECODER_CANDIDATES.iter().any(|decoder| &candidate_name(*decoder) == name) {
+                return Err(format!("`{}` is not a candidate of the `auto` decoder", name))
+            }
+        }
+        let mut simulator = self.new_simulator(config.noisy_measurements, config.di, config.dj)?;
+        if self.decoded_defects(&simulator)?.is_some() {
+            return Err("the `auto` decoder decodes all the defects, which is not compatible with `decoding_basis` or `decode_region`".to_string())
+        }
+        let noise_model_graph = self.construct_noise_model(&mut simulator, configs, config, true)?;
+        let decoders = candidates.iter().map(|parameters| GeneralDecoder::from_parameters(parameters, configs, config, &simulator, &noise_model_graph))
+            .collect::<Result<Vec<_>, String>>()?;
+        let noise_model = self.construct_noise_model(&mut simulator, configs, config, false)?;
+        if let Some(seed) = self.seed {
+            simulator.rng = Xoroshiro128StarStar::seed_from_u64(seed);
+        }
+        let mut harness = SharedSyndromeHarness::new(simulator, noise_model, decoders);
+        let statistics = harness.run(self.auto_calibration_shots);
+        let error_rates: Vec<f64> = (0..candidates.len()).map(|index| statistics.error_rate(index)).collect();
+        let decode_times: Vec<f64> = harness.decode_time.iter().map(|decode_time| decode_time / statistics.shots as f64).collect();
+        let selected = select_auto_decoder(&error_rates, self.auto_decoder_factor);
+        let calibration: Vec<serde_json::Value> = candidates.iter().enumerate().map(|(index, parameters)| json!({
+            "decoder": candidate_name(parameters.decoder),
+            "failed": statistics.failed[index][index],
+            "pL": error_rates[index],
+            "decode_time": decode_times[index],
+        })).collect();
+        let selection = json!({
+            "decoder": candidate_name(candidates[selected].decoder),
+            "calibration_shots": statistics.shots,
+            "calibration": calibration,
+        });
+        Ok((candidates.swap_remove(selected), selection))
+    }
+
+    /// run a single configuration, calling `report` with the partial result in the same format as the final result every `report_interval` seconds
+    pub fn run_single_with_report(&self, configs: &SimulationConfigs, config: &SingleSimulationConfig, log_runtime_statistics_file: &Option<Arc<Mutex<File>>>
+            , mut report: impl FnMut(&str)) -> Result<String, String> {
+        if self.decoder == BenchmarkDecoder::Auto {
+            let (parameters, selection) = self.calibrate_auto_decoder(configs, config)?;
+            let result = parameters.run_single_with_report(configs, config, log_runtime_statistics_file, report)?;
+            return Ok(format!("{}{}", result, selection))  // append the selection and its calibration as a compact JSON object
+        }
         // first use p_graph and pe_graph to build decoder graph, then go back to real noise model for simulation; a mismatch between decoding graph and real noise model is realistic
-        let mut simulator = Simulator::new(self.code_type, CodeSize::new(config.noisy_measurements, config.di, config.dj));
+        let mut simulator = self.new_simulator(config.noisy_measurements, config.di, config.dj)?;
+        if let Some(filepath) = &self.load_logical_operators_from_file {
+            if self.code_type != CodeType::Customized {
+                return Err(format!("logical operators can only be loaded for customized code, but the code type is {:?}", self.code_type))
+            }
+            let customized_logical_operators = CustomizedLogicalOperators::from_json_file(filepath)?;
+            customized_logical_operators.sanity_check(&simulator)?;
+            simulator.customized_logical_operators = Some(customized_logical_operators);
+        }
         let noise_model_graph = self.construct_noise_model(&mut simulator, configs, config, true)?;
         if let Some(terminate_message) = self.execute_debug_print(configs, &mut simulator, &noise_model_graph)? {
             return Ok(terminate_message);  // debug print terminates
         }
         // build decoder instances
         let general_decoder = GeneralDecoder::from_parameters(self, configs, config, &simulator, &noise_model_graph)?;
+        if let Some(cross_check_fraction) = self.cross_check_fraction {
+            if !(0. ..=1.).contains(&cross_check_fraction) {
+                return Err(format!("`cross_check_fraction` should be in [0, 1], but got {}", cross_check_fraction))
+            }
+            if self.use_compact_simulator {
+                return Err("`cross_check_fraction` is not supported by the compact simulator".to_string())
+            }
+            if code_builder_logical_operators(&simulator).map(|logical_operators| logical_operators.len()) != Some(2) {
+                return Err("`cross_check_fraction` only supports codes encoding a single logical qubit".to_string())
+            }
+        }
+        let optimal_decoder = if self.optimal_failure_rate {
+            let decoder_config = json!({ "exact_matching_max_defects": exact_matching::EXACT_MATCHING_MAX_DEFECTS });
+            Some(MWPMDecoder::new(&simulator, noise_model_graph.clone(), &decoder_config, configs.parallel_init, self.use_brief_edge))
+        } else {
+            None
+        };
         // prepare fusion blossom exporter
         cfg_if::cfg_if! { if #[cfg(feature="fusion_blossom")] {
             let mut fusion_blossom_syndrome_exporter = None;
@@ -481,7 +2322,18 @@ impl BenchmarkParameters {
         // prepare visualizer
         let visualizer = self.prepare_visualizer(&mut simulator, &noise_model, &noise_model_graph, configs)?;
         // prepare result variables for simulation
+        let erasure_conditioned = self.erasure_buckets.clone().map(ConditionedStatistics::with_boundaries).transpose()?;
+        let failure_movies = Arc::new(AtomicUsize::new(0));
         let benchmark_control = Arc::new(Mutex::new(BenchmarkControl::new()));
+        benchmark_control.lock().unwrap().erasure_conditioned = erasure_conditioned.clone();
+        let weight_conditioned = if self.weight_conditioned { Some(ConditionedStatistics::per_value()) } else { None };
+        benchmark_control.lock().unwrap().weight_conditioned = weight_conditioned.clone();
+        let mut streaming_rng = Xoroshiro128StarStar::new();
+        if let Some(seed) = self.seed {  // a different seed from the workers, only used when merging the reservoirs
+            streaming_rng = Xoroshiro128StarStar::seed_from_u64(seed ^ 0x5eed_5a3f_11e5_0000);
+        }
+        benchmark_control.lock().unwrap().streaming_statistics = self.statistics_reservoir
+            .map(|statistics_reservoir| StreamingStatistics::new(statistics_reservoir, streaming_rng));
         // setup progress bar
         let mut pb = ProgressBar::on(std::io::stderr(), configs.max_repeats as u64);
         pb.set(0);
@@ -489,6 +2341,22 @@ impl BenchmarkParameters {
         let mut handlers = Vec::new();
         let mut threads_debugger: Vec<Arc<Mutex<BenchmarkThreadDebugger>>> = Vec::new();
         let mut threads_ended = Vec::new();  // keep updating progress bar until all threads ends
+        // prepare the detectors that may be lost
+        let noisy_detectors = Arc::new(if self.detector_dropout_rate.is_some() { simulator.noisy_detector_positions() } else { vec![] });
+        let measurement_cycles = simulator.measurement_cycles;
+        let decoded_defects = self.decoded_defects(&simulator)?.map(Arc::new);
+        // load the fixed error pattern that replaces the random errors of every shot
+        let fixed_error_pattern = match &self.fixed_error_file {
+            Some(filepath) => {
+                let value = fs::read_to_string(filepath).map_err(|_| format!("[error] fixed error file cannot open: {}", filepath))?;
+                let sparse_error_pattern: SparseErrorPattern = serde_json::from_str(&value)
+                    .map_err(|e| format!("[error] fixed error file cannot parse: {}, {}", filepath, e))?;
+                simulator.load_sparse_error_pattern(&sparse_error_pattern, &noise_model)?;
+                simulator.clear_all_errors();
+                Some(Arc::new(sparse_error_pattern))
+            },
+            None => None,
+        };
         let general_simulator: GeneralSimulator = if self.use_compact_simulator {
             let first = SimulatorCompact::from_simulator(simulator, noise_model.clone(), configs.parallel_init);
             if let Some(simulator_compact_extender_noisy_measurements) = self.simulator_compact_extender_noisy_measurements {
@@ -496,7 +2364,7 @@ impl BenchmarkParameters {
                 if simulator_compact_extender_noisy_measurements < config.noisy_measurements {
                     return Err(format!("extender only works for larger noisy_measurement than nms[0], now {simulator_compact_extender_noisy_measurements} < {}", config.noisy_measurements));
                 } else {
-                    let mut second_simulator = Simulator::new(self.code_type, CodeSize::new(config.noisy_measurements + 1, config.di, config.dj));
+                    let mut second_simulator = self.new_simulator(config.noisy_measurements + 1, config.di, config.dj)?;
                     let second_noise_model = self.construct_noise_model(&mut second_simulator, configs, config, false)?;
                     let second = SimulatorCompact::from_simulator(second_simulator, second_noise_model, configs.parallel_init);
                     let extender = SimulatorCompactExtender::new(first, second, config.noisy_measurements);
@@ -513,23 +2381,57 @@ impl BenchmarkParameters {
         } else {
             GeneralSimulator::Simulator(simulator)
         };
+        // all the seeds of the workers are derived from a single seed, if provided
+        let mut seed_generator = self.seed.map(Xoroshiro128StarStar::seed_from_u64);
         for _parallel_idx in 0..configs.parallel {
+            let general_simulator = general_simulator.clone();
+            let mut detector_dropout_generator = self.detector_dropout_rate.map(|detector_dropout_rate| {
+                DetectorDropoutGenerator::new(detector_dropout_rate, noisy_detectors.clone(), measurement_cycles)
+            });
+            let mut cross_check_rng = self.cross_check_fraction.map(|_| Xoroshiro128StarStar::new());
+            let mut streaming_statistics = self.statistics_reservoir
+                .map(|statistics_reservoir| StreamingStatistics::new(statistics_reservoir, Xoroshiro128StarStar::new()));
+            if let Some(seed_generator) = seed_generator.as_mut() {
+                if let Some(detector_dropout_generator) = detector_dropout_generator.as_mut() {
+                    detector_dropout_generator.rng = Xoroshiro128StarStar::seed_from_u64(seed_generator.next_u64());
+                }
+                if let Some(cross_check_rng) = cross_check_rng.as_mut() {
+                    *cross_check_rng = Xoroshiro128StarStar::seed_from_u64(seed_generator.next_u64());
+                }
+                if let Some(streaming_statistics) = streaming_statistics.as_mut() {
+                    streaming_statistics.records.rng = Xoroshiro128StarStar::seed_from_u64(seed_generator.next_u64());
+                }
+            }
             let thread_debugger = Arc::new(Mutex::new(BenchmarkThreadDebugger::new()));
             threads_debugger.push(thread_debugger.clone());
             let thread_ended = Arc::new(AtomicBool::new(false));
             threads_ended.push(Arc::clone(&thread_ended));
             let mut worker_state = SimulationWorker {
                 benchmark_control: benchmark_control.clone(),
-                general_simulator: general_simulator.clone(),
+                // the regular clone reseeds the random number generator so that each worker samples independent errors;
+                // `Simulator::deep_clone_with_rng` would make all workers repeat the same shots
+                general_simulator,
                 noise_model: noise_model.clone(),
                 log_runtime_statistics_file: log_runtime_statistics_file.clone(),
                 visualizer: visualizer.clone(),
                 general_decoder: general_decoder.clone(),
+                optimal_decoder: optimal_decoder.clone(),
+                shot_stream: self.seed.map(Xoroshiro128StarStar::seed_from_u64),
                 #[cfg(feature="fusion_blossom")]
                 fusion_blossom_syndrome_exporter: fusion_blossom_syndrome_exporter.clone(),
                 thread_debugger,
                 thread_ended,
+                detector_dropout_generator,
+                cross_check_rng,
+                decoded_defects: decoded_defects.clone(),
+                fixed_error_pattern: fixed_error_pattern.clone(),
+                erasure_conditioned: erasure_conditioned.clone(),
+                weight_conditioned: weight_conditioned.clone(),
+                streaming_statistics,
+                failure_movies: failure_movies.clone(),
                 parameters: self.clone(),
+                max_repeats: configs.max_repeats,
+                min_failed_cases: configs.min_failed_cases,
             };
             handlers.push(std::thread::spawn(move || {
                 worker_state.run();
@@ -542,13 +2444,31 @@ impl BenchmarkParameters {
             let total_repeats = benchmark_control.total_repeats;
             let qec_failed = benchmark_control.qec_failed;
             // compute simulation results
-            let error_rate = qec_failed as f64 / total_repeats as f64;
-            let confidence_interval_95_percent = 1.96 * (error_rate * (1. - error_rate) / (total_repeats as f64)).sqrt() / error_rate;
-            format!("{} {} {} {} {} {} {} {:.1e} {} ", config.p, config.di, config.noisy_measurements, total_repeats, qec_failed, error_rate, config.dj
-                , confidence_interval_95_percent, config.pe)
+            let (error_rate, confidence_interval_95_percent) = logical_error_rate_with_confidence_interval(total_repeats, qec_failed);
+            let mut information = format!("{} {} {} {} {} {} {} {:.1e} {} ", config.p, config.di, config.noisy_measurements, total_repeats, qec_failed, error_rate, config.dj
+                , confidence_interval_95_percent, config.pe);
+            if self.decode_timeout_us.is_some() || self.shot_time_limit_us.is_some() {  // append <timed_out> <timeout_rate> only when enabled, to keep the default format unchanged
+                let timed_out = benchmark_control.timed_out;
+                information += format!("{} {} ", timed_out, timed_out as f64 / total_repeats as f64).as_str();
+            }
+            if self.optimal_failure_rate {  // append <optimal_failed> <optimal_pL> only when enabled
+                let optimal_failed = benchmark_control.optimal_failed;
+                information += format!("{} {} ", optimal_failed, optimal_failed as f64 / total_repeats as f64).as_str();
+            }
+            if !config.sweep.is_empty() {  // append the resolved values of the swept noise model configuration as a compact JSON object
+                information += format!("{} ", serde_json::Value::Object(config.sweep.clone())).as_str();
+            }
+            information
         };
+        let mut last_report = repeat_begin;
         loop {
             let time_elapsed = repeat_begin.elapsed().as_secs_f64();
+            if let Some(report_interval) = self.report_interval {
+                if last_report.elapsed().as_secs_f64() >= report_interval {
+                    report(progress_information().as_str());
+                    last_report = Instant::now();
+                }
+            }
             match self.time_budget {
                 Some(time_budget) => {
                     if time_elapsed > time_budget {
@@ -635,11 +2555,47 @@ impl BenchmarkParameters {
             std::thread::sleep(std::time::Duration::from_millis(1000));
         }
         pb.finish();
-        eprintln!("{}", progress_information());
-        Ok(format!("{}", progress_information()))
-    }
-
-}
+        if let Some(cross_check_failure) = &benchmark_control.lock().unwrap().cross_check_failure {
+            return Err(format!("[error] cross check of the validation failed: {}", cross_check_failure))
+        }
+        let mini_batch_size = benchmark_control.lock().unwrap().mini_batch_size;
+        eprintln!("[info] final mini-batch size: {}", mini_batch_size);
+        if let (Some(erasure_conditioned), Some(log_runtime_statistics_file)) = (&benchmark_control.lock().unwrap().erasure_conditioned, log_runtime_statistics_file) {
+            let mut log_runtime_statistics_file = log_runtime_statistics_file.lock().unwrap();
+            log_runtime_statistics_file.write_all(b"#e ").unwrap();
+            log_runtime_statistics_file.write_all(json!(erasure_conditioned.bins()).to_string().as_bytes()).unwrap();
+            log_runtime_statistics_file.write_all(b"\n").unwrap();
+            log_runtime_statistics_file.sync_data().unwrap();
+        }
+        if let (Some(weight_conditioned), Some(log_runtime_statistics_file)) = (&benchmark_control.lock().unwrap().weight_conditioned, log_runtime_statistics_file) {
+            let mut log_runtime_statistics_file = log_runtime_statistics_file.lock().unwrap();
+            log_runtime_statistics_file.write_all(b"#w ").unwrap();
+            log_runtime_statistics_file.write_all(json!(weight_conditioned.bins()).to_string().as_bytes()).unwrap();
+            log_runtime_statistics_file.write_all(b"\n").unwrap();
+            log_runtime_statistics_file.sync_data().unwrap();
+        }
+        if let (Some(streaming_statistics), Some(log_runtime_statistics_file)) = (&benchmark_control.lock().unwrap().streaming_statistics, log_runtime_statistics_file) {
+            let mut log_runtime_statistics_file = log_runtime_statistics_file.lock().unwrap();
+            for record in streaming_statistics.records.items.iter() {  // the sampled shots are written as if they were logged one by one
+                log_runtime_statistics_file.write_all(format!("{}\n", record).as_bytes()).unwrap();
+            }
+            log_runtime_statistics_file.write_all(b"#s ").unwrap();
+            log_runtime_statistics_file.write_all(streaming_statistics.summary().to_string().as_bytes()).unwrap();
+            log_runtime_statistics_file.write_all(b"\n").unwrap();
+            log_runtime_statistics_file.sync_data().unwrap();
+        }
+        if let Some(log_runtime_statistics_file) = log_runtime_statistics_file {
+            let mut log_runtime_statistics_file = log_runtime_statistics_file.lock().unwrap();
+            log_runtime_statistics_file.write_all(b"#b ").unwrap();
+            log_runtime_statistics_file.write_all(json!({ "mini_batch_size": mini_batch_size }).to_string().as_bytes()).unwrap();
+            log_runtime_statistics_file.write_all(b"\n").unwrap();
+            log_runtime_statistics_file.sync_data().unwrap();
+        }
+        eprintln!("{}", progress_information());
+        Ok(format!("{}", progress_information()))
+    }
+
+}
 
 /// general class of all supported decoders in QECP
 #[derive(Clone)]
@@ -650,12 +2606,108 @@ pub enum GeneralDecoder {
     Fusion(FusionDecoder),
     TailoredMWPM(TailoredMWPMDecoder),
     UnionFind(UnionFindDecoder),
+    TailoredUnionFind(TailoredUnionFindDecoder),
     #[cfg(feature="hyperion")]
     HyperUnionFind(HyperUnionFindDecoder),
+    UfThenMwpm(UfThenMwpmDecoder),
 }
 
 impl GeneralDecoder {
+    /// build a decoder from its configuration alone, without any of the benchmark options
+    pub fn from_decoder_config(decoder: BenchmarkDecoder, simulator: &Simulator, noise_model: &Arc<NoiseModel>, decoder_config: &serde_json::Value
+            , parallel: usize) -> Result<Self, String> {
+        Ok(match decoder {
+            BenchmarkDecoder::None => GeneralDecoder::None,
+            BenchmarkDecoder::MWPM => GeneralDecoder::MWPM(MWPMDecoder::new(simulator, noise_model.clone(), decoder_config, parallel, false)),
+            #[cfg(feature="fusion_blossom")]
+            BenchmarkDecoder::Fusion => GeneralDecoder::Fusion(FusionDecoder::new(simulator, noise_model.clone(), decoder_config, parallel, false)),
+            BenchmarkDecoder::TailoredMWPM => GeneralDecoder::TailoredMWPM(TailoredMWPMDecoder::new(simulator, noise_model.clone(), decoder_config, parallel, false)),
+            BenchmarkDecoder::UnionFind => GeneralDecoder::UnionFind(UnionFindDecoder::new(simulator, noise_model.clone(), decoder_config, parallel, false)),
+            BenchmarkDecoder::TailoredUnionFind => GeneralDecoder::TailoredUnionFind(TailoredUnionFindDecoder::new(simulator, noise_model.clone(), decoder_config, parallel, false)),
+            #[cfg(feature="hyperion")]
+            BenchmarkDecoder::HyperUnionFind => GeneralDecoder::HyperUnionFind(HyperUnionFindDecoder::new(simulator, noise_model.clone(), decoder_config, parallel, false)),
+            BenchmarkDecoder::UfThenMwpm => GeneralDecoder::UfThenMwpm(UfThenMwpmDecoder::new(simulator, noise_model.clone(), decoder_config, parallel, false)),
+            #[cfg(not(feature="fusion_blossom"))]
+            BenchmarkDecoder::Fusion => return Err("decoder is not available; try enable feature `fusion_blossom`".to_string()),
+            #[cfg(not(feature="hyperion"))]
+            BenchmarkDecoder::HyperUnionFind => return Err("decoder is not available; try enable feature `hyperion`".to_string()),
+            BenchmarkDecoder::Auto => return Err("the `auto` decoder is only resolved per configuration by the benchmark".to_string()),
+        })
+    }
+
     pub fn from_parameters(parameters: &BenchmarkParameters, configs: &SimulationConfigs, config: &SingleSimulationConfig, simulator: &Simulator, noise_model_graph: &Arc<NoiseModel>) -> Result<Self, String> {
+        if parameters.decode_timeout_us.is_some() && parameters.decoder != BenchmarkDecoder::UnionFind {
+            return Err("`decode_timeout_us` is only supported by the union-find decoder".to_string())
+        }
+        if parameters.decode_timeout_us.is_some() && parameters.shot_time_limit_us.is_some() {
+            return Err("`decode_timeout_us` and `shot_time_limit_us` cannot be used together".to_string())
+        }
+        if parameters.detector_dropout_rate.is_some() && !matches!(parameters.decoder, BenchmarkDecoder::MWPM | BenchmarkDecoder::UnionFind) {
+            return Err("`detector_dropout_rate` is only supported by the MWPM and union-find decoders".to_string())
+        }
+        if parameters.detector_dropout_rate.is_some() && parameters.decode_timeout_us.is_some() {
+            return Err("`decode_timeout_us` and `detector_dropout_rate` cannot be used together".to_string())
+        }
+        if parameters.detector_dropout_rate.is_some() && parameters.decoder == BenchmarkDecoder::MWPM {
+            let config: MWPMDecoderConfig = serde_json::from_value(parameters.decoder_config.clone()).map_err(|e| e.to_string())?;
+            if config.precompute_complete_model_graph {
+                return Err("`detector_dropout_rate` invalidates the precomputed complete model graph; please disable `precompute_complete_model_graph` or `pcmg` in the decoder configuration".to_string())
+            }
+        }
+        if parameters.detector_dropout_rate.is_some() {  // a lost detector is merged with its next measurement through their time-like edge
+            let (weight_function, use_combined_probability) = parameters.model_graph_options()?;
+            let mut simulator = simulator.clone();
+            let mut model_graph = ModelGraph::new(&simulator);
+            model_graph.build(&mut simulator, Arc::clone(noise_model_graph), &weight_function, configs.parallel_init, use_combined_probability, parameters.use_brief_edge);
+            let has_time_like_edge = simulator.noisy_detector_positions().iter().any(|position| {
+                let next_position = pos!(position.t + simulator.measurement_cycles, position.i, position.j);
+                model_graph.get_node(position).as_ref().is_some_and(|node| node.edges.contains_key(&next_position))
+            });
+            if !has_time_like_edge {
+                return Err("`detector_dropout_rate` needs the time-like edges of the decoding graph to merge a lost detector with its next measurement, \
+                    but there is none, e.g. without noisy measurements or measurement errors".to_string())
+            }
+        }
+        if let Some(logical_prior) = parameters.logical_prior {
+            if parameters.decoder != BenchmarkDecoder::MWPM {
+                return Err("`logical_prior` is only supported by the MWPM decoder".to_string())
+            }
+            if !(logical_prior > 0. && logical_prior < 1.) {
+                return Err(format!("`logical_prior` should be in (0, 1), but got {}", logical_prior))
+            }
+        }
+        if parameters.load_graph.is_some() && !matches!(parameters.decoder, BenchmarkDecoder::MWPM | BenchmarkDecoder::UnionFind) {
+            return Err("`load_graph` is only supported by the MWPM and union-find decoders".to_string())
+        }
+        if parameters.decoding_basis != DecodingBasis::Both {
+            if !matches!(parameters.decoder, BenchmarkDecoder::MWPM | BenchmarkDecoder::UnionFind) {
+                return Err("`decoding_basis` is only supported by the MWPM and union-find decoders".to_string())
+            }
+            if config.pe > 0. || config.pe_graph > 0. || parameters.detector_dropout_rate.is_some() {
+                return Err("`decoding_basis` doesn't support erasure errors or detector dropout".to_string())
+            }
+            if parameters.ignored_logicals() == (true, true) {
+                return Err(format!("`decoding_basis` {:?} ignores the only logical error that is counted", parameters.decoding_basis))
+            }
+            parameters.decoding_basis.decoded_defects(simulator)?;  // check CSS code
+        }
+        if parameters.decode_region.is_some() {
+            if !matches!(parameters.decoder, BenchmarkDecoder::MWPM | BenchmarkDecoder::UnionFind) {
+                return Err("`decode_region` is only supported by the MWPM and union-find decoders".to_string())
+            }
+            if config.pe > 0. || config.pe_graph > 0. || parameters.detector_dropout_rate.is_some() {
+                return Err("`decode_region` doesn't support erasure errors or detector dropout".to_string())
+            }
+            parameters.region()?;
+        }
+        if let Some(boundary_weight_multiplier) = parameters.boundary_weight_multiplier {
+            if parameters.decoder != BenchmarkDecoder::MWPM {
+                return Err("`boundary_weight_multiplier` is only supported by the MWPM decoder".to_string())
+            }
+            if !(boundary_weight_multiplier > 0. && boundary_weight_multiplier.is_finite()) {
+                return Err(format!("`boundary_weight_multiplier` should be positive, but got {}", boundary_weight_multiplier))
+            }
+        }
         Ok(match parameters.decoder {
             BenchmarkDecoder::None => {
                 // if parameters.decoder_config.is_object() && parameters.decoder_config.as_object().ok_or("decoder config is not json object")?.len() != 0 {
@@ -664,7 +2716,16 @@ impl GeneralDecoder {
                 GeneralDecoder::None
             },
             BenchmarkDecoder::MWPM => {
-                GeneralDecoder::MWPM(MWPMDecoder::new(&simulator, noise_model_graph.clone(), &parameters.decoder_config, configs.parallel_init, parameters.use_brief_edge))
+                let mut decoder_config = parameters.decoder_config.clone();
+                if let Some(logical_prior) = parameters.logical_prior {
+                    decoder_config.as_object_mut().ok_or("decoder config is not json object")?.insert("logical_prior".to_string(), json!(logical_prior));
+                }
+                if let Some(boundary_weight_multiplier) = parameters.boundary_weight_multiplier {
+                    decoder_config.as_object_mut().ok_or("decoder config is not json object")?.insert("boundary_weight_multiplier".to_string(), json!(boundary_weight_multiplier));
+                }
+                let model_graph = parameters.prepare_model_graph(simulator, noise_model_graph, configs.parallel_init)?;
+                GeneralDecoder::MWPM(MWPMDecoder::new_with_model_graph(simulator, noise_model_graph.clone(), &decoder_config, configs.parallel_init
+                    , parameters.use_brief_edge, model_graph))
             },
             #[cfg(feature="fusion_blossom")]
             BenchmarkDecoder::Fusion => {
@@ -675,7 +2736,7 @@ impl GeneralDecoder {
                         return Err(format!("extender only works for larger noisy_measurement than nms[0], now {simulator_compact_extender_noisy_measurements} < {}", config.noisy_measurements));
                     } else {
                         // use extender to build decoder
-                        let mut second_simulator = Simulator::new(parameters.code_type, CodeSize::new(config.noisy_measurements + 1, config.di, config.dj));
+                        let mut second_simulator = parameters.new_simulator(config.noisy_measurements + 1, config.di, config.dj)?;
                         let mut second_config = config.clone();
                         second_config.noisy_measurements += 1;
                         let second_noise_model_graph = parameters.construct_noise_model(&mut second_simulator, configs, &second_config, true)?;
@@ -703,7 +2764,14 @@ impl GeneralDecoder {
                 GeneralDecoder::TailoredMWPM(TailoredMWPMDecoder::new(&simulator, noise_model_graph.clone(), &parameters.decoder_config, configs.parallel_init, parameters.use_brief_edge))
             },
             BenchmarkDecoder::UnionFind => {
-                GeneralDecoder::UnionFind(UnionFindDecoder::new(&simulator, noise_model_graph.clone(), &parameters.decoder_config, configs.parallel_init, parameters.use_brief_edge))
+                let model_graph = parameters.prepare_model_graph(simulator, noise_model_graph, configs.parallel_init)?;
+                let mut union_find_decoder = UnionFindDecoder::new_with_model_graph(simulator, noise_model_graph.clone(), &parameters.decoder_config
+                    , configs.parallel_init, parameters.use_brief_edge, model_graph);
+                union_find_decoder.decode_timeout_us = parameters.decode_timeout_us;
+                GeneralDecoder::UnionFind(union_find_decoder)
+            },
+            BenchmarkDecoder::TailoredUnionFind => {
+                GeneralDecoder::TailoredUnionFind(TailoredUnionFindDecoder::new(simulator, noise_model_graph.clone(), &parameters.decoder_config, configs.parallel_init, parameters.use_brief_edge))
             },
             #[cfg(feature="hyperion")]
             BenchmarkDecoder::HyperUnionFind => {
@@ -713,6 +2781,12 @@ impl GeneralDecoder {
             BenchmarkDecoder::HyperUnionFind => {
                 return Err("decoder is not available; try enable feature `hyperion`".to_string())
             },
+            BenchmarkDecoder::UfThenMwpm => {
+                GeneralDecoder::UfThenMwpm(UfThenMwpmDecoder::new(simulator, noise_model_graph.clone(), &parameters.decoder_config, configs.parallel_init, parameters.use_brief_edge))
+            },
+            BenchmarkDecoder::Auto => {
+                return Err("the `auto` decoder is only resolved per configuration by the benchmark".to_string())
+            },
         })
     }
 
@@ -735,15 +2809,115 @@ impl GeneralDecoder {
             Self::UnionFind(union_find_decoder) => {
                 union_find_decoder.decode_with_erasure(sparse_measurement, sparse_detected_erasures)
             }
+            Self::TailoredUnionFind(tailored_union_find_decoder) => {
+                assert!(sparse_detected_erasures.len() == 0, "tailored union-find decoder doesn't support erasures");
+                tailored_union_find_decoder.decode(sparse_measurement)
+            }
             #[cfg(feature="hyperion")]
             Self::HyperUnionFind(hyper_union_find_decoder) => {
                 hyper_union_find_decoder.decode_with_erasure(sparse_measurement, sparse_detected_erasures)
             }
+            Self::UfThenMwpm(uf_then_mwpm_decoder) => {
+                uf_then_mwpm_decoder.decode_with_erasure(sparse_measurement, sparse_detected_erasures)
+            }
+        }
+    }
+
+    /// the earliest measurement round `k` (counting from 1) such that decoding the truncated volume of the first `k'` noisy measurement rounds
+    /// followed by a perfect measurement round fails for every `k' >= k`, where `k = noisy rounds + 1` means that only the full volume fails,
+    /// see [`Simulator::generate_truncated_sparse_measurement`]; the truncated volumes are decoded from the top down until the first success,
+    /// so a failing chain that crossed in the last few rounds only takes a few more decodings; the noisy rounds are those of
+    /// [`CodeSize::noisy_measurements`], regardless of the number of final perfect rounds
+    pub fn logical_failure_round(&mut self, simulator: &mut Simulator, sparse_measurement: &SparseMeasurement, sparse_detected_erasures: &SparseErasures
+            , ignore_logical_i: bool, ignore_logical_j: bool) -> usize {
+        let noisy_rounds = simulator.code_size.noisy_measurements;
+        let top_t = simulator.height - 1;
+        let mut failure_round = noisy_rounds + 1;
+        for round in (1..=noisy_rounds).rev() {
+            let t = round * simulator.measurement_cycles;
+            let truncated_measurement = simulator.generate_truncated_sparse_measurement(sparse_measurement, t);
+            let mut truncated_erasures = SparseErasures::new();
+            for position in sparse_detected_erasures.iter().filter(|position| position.t < t) {
+                truncated_erasures.insert_erasure(position);
+            }
+            let (correction, _) = self.decode_with_erasure(&truncated_measurement, &truncated_erasures);
+            // the data qubit corrections at the top layer are the same at layer `t`, because the truncated volume has no error above `t`
+            let correction = if correction.is_at_layer(top_t) { correction } else { correction.normalize_to_layer(simulator, top_t) };
+            let mut correction_at_t = SparseCorrection::new();
+            for (position, operator) in correction.iter() {
+                correction_at_t.add(pos!(t, position.i, position.j), *operator);
+            }
+            let (logical_i, logical_j) = simulator.validate_correction_at(&correction_at_t, t);
+            if (!logical_i || ignore_logical_i) && (!logical_j || ignore_logical_j) {
+                break
+            }
+            failure_round = round;
         }
+        failure_round
+    }
+
+    /// decode with a deadline, which is checked cooperatively by the MWPM and union-find decoders to abort early;
+    /// the other decoders run to the end, but any decoding that finishes after the deadline is considered timed-out as well
+    pub fn decode_with_erasure_deadline(&mut self, sparse_measurement: &SparseMeasurement, sparse_detected_erasures: &SparseErasures
+            , deadline: Instant) -> Result<(SparseCorrection, serde_json::Value), DecodingError> {
+        self.decode_with_erasure_edges(sparse_measurement, sparse_detected_erasures, &[], Some(deadline))
+    }
+
+    /// decode with additional edges whose weights are 0 in this shot besides those caused by erasures, e.g. the time-like edges of lost detectors,
+    /// which are only supported by the MWPM and union-find decoders; see [`GeneralDecoder::decode_with_erasure_deadline`] for the deadline
+    pub fn decode_with_erasure_edges(&mut self, sparse_measurement: &SparseMeasurement, sparse_detected_erasures: &SparseErasures
+            , extra_erasure_edges: &[ErasureEdge], deadline: Option<Instant>) -> Result<(SparseCorrection, serde_json::Value), DecodingError> {
+        let result = match self {
+            Self::MWPM(mwpm_decoder) => {
+                let mut erasure_edges = sparse_detected_erasures.get_erasure_edges(&mwpm_decoder.erasure_graph);
+                erasure_edges.extend_from_slice(extra_erasure_edges);
+                mwpm_decoder.decode_with_erasure_edges(sparse_measurement, &erasure_edges, deadline)?
+            },
+            Self::UnionFind(union_find_decoder) => {
+                let mut erasure_edges = sparse_detected_erasures.get_erasure_edges(&union_find_decoder.erasure_graph);
+                erasure_edges.extend_from_slice(extra_erasure_edges);
+                union_find_decoder.decode_with_erasure_edges(sparse_measurement, &erasure_edges, deadline)?
+            },
+            _ => {
+                assert!(extra_erasure_edges.is_empty(), "only MWPM and union-find decoders support additional erasure edges");
+                self.decode_with_erasure(sparse_measurement, sparse_detected_erasures)
+            },
+        };
+        if let Some(deadline) = deadline {
+            if Instant::now() > deadline {
+                return Err(DecodingError::Timeout)
+            }
+        }
+        Ok(result)
     }
 
 }
 
+/// randomly lose the measurement records of the detectors in each shot, see `--detector-dropout-rate`
+pub struct DetectorDropoutGenerator {
+    pub detector_dropout_rate: f64,
+    /// the detectors that may be lost, shared among threads
+    pub noisy_detectors: Arc<Vec<Position>>,
+    pub measurement_cycles: usize,
+    /// each worker has its own random number generator
+    pub rng: Xoroshiro128StarStar,
+}
+
+impl DetectorDropoutGenerator {
+    pub fn new(detector_dropout_rate: f64, noisy_detectors: Arc<Vec<Position>>, measurement_cycles: usize) -> Self {
+        assert!((0. ..=1.).contains(&detector_dropout_rate), "detector dropout rate must be in [0, 1]");
+        Self {
+            detector_dropout_rate,
+            noisy_detectors,
+            measurement_cycles,
+            rng: Xoroshiro128StarStar::new(),
+        }
+    }
+    pub fn generate(&mut self) -> SparseDetectorDropout {
+        SparseDetectorDropout::generate_random(&self.noisy_detectors, self.detector_dropout_rate, &mut self.rng)
+    }
+}
+
 pub struct SimulationWorker {
     pub benchmark_control: Arc<Mutex<BenchmarkControl>>,
     pub general_simulator: GeneralSimulator,
@@ -751,110 +2925,1979 @@ pub struct SimulationWorker {
     pub log_runtime_statistics_file: Option<Arc<Mutex<File>>>,
     pub visualizer: Option<Arc<Mutex<Visualizer>>>,
     pub general_decoder: GeneralDecoder,
+    /// the exact minimum-weight decoder, see `BenchmarkParameters::optimal_failure_rate`
+    pub optimal_decoder: Option<MWPMDecoder>,
+    /// in a seeded run, shot `index` samples the errors from this generator jumped `index` times regardless of the worker running it,
+    /// so that [`Simulator::shot_from_index`] reproduces any shot of the run
+    pub shot_stream: Option<Xoroshiro128StarStar>,
     #[cfg(feature="fusion_blossom")]
     pub fusion_blossom_syndrome_exporter: Arc<Option<FusionBlossomSyndromeExporter>>,
     pub thread_debugger: Arc<Mutex<BenchmarkThreadDebugger>>,
     pub thread_ended: Arc<AtomicBool>,
+    pub detector_dropout_generator: Option<DetectorDropoutGenerator>,
+    /// samples the shots to cross check, see `BenchmarkParameters::cross_check_fraction`
+    pub cross_check_rng: Option<Xoroshiro128StarStar>,
+    /// the stabilizer measurements whose defects are decoded, see `BenchmarkParameters::decoding_basis`
+    pub decoded_defects: Option<Arc<BTreeSet<Position>>>,
+    /// the error pattern applied in every shot instead of random errors, see `BenchmarkParameters::fixed_error_file`
+    pub fixed_error_pattern: Option<Arc<SparseErrorPattern>>,
+    /// the statistics of the current mini-batch bucketed by the number of detected erasures, see `BenchmarkParameters::erasure_buckets`
+    pub erasure_conditioned: Option<ConditionedStatistics>,
+    /// the statistics of the current mini-batch binned by the error weight, see `BenchmarkParameters::weight_conditioned`
+    pub weight_conditioned: Option<ConditionedStatistics>,
+    /// the summaries and the sampled records of the current mini-batch, see `BenchmarkParameters::statistics_reservoir`
+    pub streaming_statistics: Option<StreamingStatistics>,
+    /// the number of failed cases claimed by all the workers, see `BenchmarkParameters::visualize_failures_movie`
+    pub failure_movies: Arc<AtomicUsize>,
     pub parameters: BenchmarkParameters,
+    /// `parameters.max_repeats` with 0 interpreted as infinity
+    pub max_repeats: usize,
+    /// `parameters.min_failed_cases` with 0 interpreted as infinity
+    pub min_failed_cases: usize,
 }
 
 impl SimulationWorker {
 
     pub fn run(&mut self) {
-        for thread_counter in 0..usize::MAX {
-            let parameters = &self.parameters;
-            if parameters.thread_timeout >= 0. { self.thread_debugger.lock().unwrap().update_thread_counter(thread_counter); }
-            // generate random errors and the corresponding measurement
+        let parameters = &self.parameters;
+        let mut mini_batch_controller = MiniBatchController::new(parameters.mini_sync_time, parameters.mini_batch_min, parameters.mini_batch_max);
+        let mut thread_counter = 0;
+        loop {
+            let (first_shot_index, claimed, global_qec_failed) = {
+                let mut benchmark_control = self.benchmark_control.lock().unwrap();
+                let (first_shot_index, claimed) = benchmark_control.claim_repeats(mini_batch_controller.batch_size, self.max_repeats, self.min_failed_cases);
+                (first_shot_index, claimed, benchmark_control.qec_failed)
+            };
+            if claimed == 0 {
+                break
+            }
             let begin = Instant::now();
-            let (error_count, erasure_count) = self.general_simulator.generate_random_errors(&self.noise_model);
-            let sparse_detected_erasures = if erasure_count != 0 { self.general_simulator.generate_sparse_detected_erasures() } else { SparseErasures::new() };
-            if parameters.thread_timeout >= 0. {
-                let mut thread_debugger = self.thread_debugger.lock().unwrap();
-                thread_debugger.error_pattern = Some(self.general_simulator.generate_sparse_error_pattern());
-                thread_debugger.detected_erasures = Some(sparse_detected_erasures.clone());
-            }  // runtime debug: find deadlock cases
-            if matches!(parameters.debug_print, Some(BenchmarkDebugPrint::AllErrorPattern)) {
-                let sparse_error_pattern = self.general_simulator.generate_sparse_error_pattern();
-                eprint!("{}", serde_json::to_string(&sparse_error_pattern).expect("serialize should success"));
-                if sparse_detected_erasures.len() > 0 {  // has detected erasures, report as well
-                    eprintln!(", {}", serde_json::to_string(&sparse_detected_erasures).expect("serialize should success"));
-                } else {
-                    eprintln!("");
+            let mut shot_stream = self.shot_stream.clone().map(|mut shot_stream| {
+                shot_stream.jump_n(first_shot_index as u64);
+                shot_stream
+            });
+            let mut repeats = 0;
+            let mut qec_failed = 0;
+            let mut timed_out = 0;
+            let mut optimal_failed = 0;
+            while repeats < claimed {
+                let shot_index = shot_stream.as_mut().map(|shot_stream| {
+                    self.general_simulator.set_rng(shot_stream.clone());
+                    shot_stream.jump();
+                    first_shot_index + repeats
+                });
+                let (is_qec_failed, is_timed_out, is_optimal_failed) = self.run_single_shot(thread_counter, shot_index);
+                thread_counter += 1;
+                repeats += 1;
+                if is_timed_out {
+                    timed_out += 1;
                 }
+                if is_optimal_failed {
+                    optimal_failed += 1;
+                }
+                if is_qec_failed {
+                    qec_failed += 1;
+                    if global_qec_failed + qec_failed >= self.min_failed_cases {
+                        break  // stop early to keep the same statistics as synchronizing every shot
+                    }
+                }
+            }
+            mini_batch_controller.update(repeats, begin.elapsed().as_secs_f64());
+            let mut benchmark_control = self.benchmark_control.lock().unwrap();
+            benchmark_control.report_repeats(claimed, repeats, qec_failed, mini_batch_controller.batch_size);
+            benchmark_control.timed_out += timed_out;
+            benchmark_control.optimal_failed += optimal_failed;
+            if let Some(erasure_conditioned) = self.erasure_conditioned.as_mut() {
+                benchmark_control.erasure_conditioned.as_mut().unwrap().take_from(erasure_conditioned);
+            }
+            if let Some(weight_conditioned) = self.weight_conditioned.as_mut() {
+                benchmark_control.weight_conditioned.as_mut().unwrap().take_from(weight_conditioned);
+            }
+            if let Some(streaming_statistics) = self.streaming_statistics.as_mut() {
+                benchmark_control.streaming_statistics.as_mut().unwrap().take_from(streaming_statistics);
+            }
+        }
+        self.thread_ended.store(true, Ordering::SeqCst);
+    }
+
+    /// run a single shot and return whether a logical error happens, whether the decoder exceeds `decode_timeout_us` or `shot_time_limit_us`,
+    /// and whether the exact minimum-weight decoder fails as well (always false without `optimal_failure_rate`);
+    /// `shot_index` is the index of the shot in a seeded run, logged to reproduce it with [`Simulator::shot_from_index`]
+    pub fn run_single_shot(&mut self, thread_counter: usize, shot_index: Option<usize>) -> (bool, bool, bool) {
+        let parameters = &self.parameters;
+        let (ignore_logical_i, ignore_logical_j) = parameters.ignored_logicals();
+        if parameters.thread_timeout >= 0. { self.thread_debugger.lock().unwrap().update_thread_counter(thread_counter); }
+        // generate random errors and the corresponding measurement
+        let begin = Instant::now();
+        let (error_count, erasure_count) = match (&self.fixed_error_pattern, &mut self.general_simulator) {
+            (Some(fixed_error_pattern), GeneralSimulator::Simulator(simulator)) => {
+                simulator.clear_all_errors();
+                simulator.load_sparse_error_pattern(fixed_error_pattern, &self.noise_model).expect("validated when loading the file");
+                simulator.propagate_errors_with_feedforwards(&self.noise_model.feedforwards);
+                (fixed_error_pattern.len(), 0)
+            },
+            _ => self.general_simulator.generate_random_errors(&self.noise_model),
+        };
+        let sparse_detected_erasures = if erasure_count != 0 { self.general_simulator.generate_sparse_detected_erasures() } else { SparseErasures::new() };
+        if parameters.thread_timeout >= 0. {
+            let mut thread_debugger = self.thread_debugger.lock().unwrap();
+            thread_debugger.error_pattern = Some(self.general_simulator.generate_sparse_error_pattern());
+            thread_debugger.detected_erasures = Some(sparse_detected_erasures.clone());
+        }  // runtime debug: find deadlock cases
+        if matches!(parameters.debug_print, Some(BenchmarkDebugPrint::AllErrorPattern)) {
+            let sparse_error_pattern = self.general_simulator.generate_sparse_error_pattern();
+            eprint!("{}", serde_json::to_string(&sparse_error_pattern).expect("serialize should success"));
+            if sparse_detected_erasures.len() > 0 {  // has detected erasures, report as well
+                eprintln!(", {}", serde_json::to_string(&sparse_detected_erasures).expect("serialize should success"));
+            } else {
+                eprintln!();
+            }
+        }
+        let mut sparse_measurement = if error_count != 0 { self.general_simulator.generate_sparse_measurement() } else { SparseMeasurement::new() };
+        if let Some(decoded_defects) = self.decoded_defects.as_ref() {
+            sparse_measurement = SparseMeasurement::new_set(sparse_measurement.iter().filter(|position| decoded_defects.contains(position)).cloned().collect());
+        }
+        let detector_dropout = match &mut self.detector_dropout_generator {
+            Some(detector_dropout_generator) => detector_dropout_generator.generate(),
+            None => SparseDetectorDropout::new(),
+        };
+        if !detector_dropout.is_empty() {
+            sparse_measurement = detector_dropout.apply(&sparse_measurement, self.detector_dropout_generator.as_ref().unwrap().measurement_cycles);
+        }
+        if parameters.thread_timeout >= 0. { self.thread_debugger.lock().unwrap().measurement = Some(sparse_measurement.clone()); }  // runtime debug: find deadlock cases
+        let simulate_elapsed = begin.elapsed().as_secs_f64();
+        cfg_if::cfg_if! { if #[cfg(feature="fusion_blossom")] {
+            if let Some(fusion_blossom_syndrome_exporter) = self.fusion_blossom_syndrome_exporter.as_ref() {
+                fusion_blossom_syndrome_exporter.add_syndrome(&sparse_measurement, &sparse_detected_erasures);
             }
-            let sparse_measurement = if error_count != 0 { self.general_simulator.generate_sparse_measurement() } else { SparseMeasurement::new() };
-            if parameters.thread_timeout >= 0. { self.thread_debugger.lock().unwrap().measurement = Some(sparse_measurement.clone()); }  // runtime debug: find deadlock cases
-            let simulate_elapsed = begin.elapsed().as_secs_f64();
-            cfg_if::cfg_if! { if #[cfg(feature="fusion_blossom")] {
-                if let Some(fusion_blossom_syndrome_exporter) = self.fusion_blossom_syndrome_exporter.as_ref() {
-                    fusion_blossom_syndrome_exporter.add_syndrome(&sparse_measurement, &sparse_detected_erasures);
+        } }
+        // decode; a shot without any error, erasure or detector dropout has nothing to decode, which is common for p = 0 or very small p,
+        // so skip the decoder unless the runtime statistics of every shot is requested or every shot is subject to a time limit
+        let is_defect_free = error_count == 0 && erasure_count == 0 && detector_dropout.is_empty() && self.log_runtime_statistics_file.is_none()
+            && parameters.shot_time_limit_us.is_none();
+        let begin = Instant::now();
+        let (mut correction, mut runtime_statistics, is_shot_timed_out) = if is_defect_free {
+            (SparseCorrection::new(), json!({}), false)
+        } else if parameters.shot_time_limit_us.is_some() || !detector_dropout.is_empty() {
+            // a shot without any defect is trivially decoded, which never times out even with `--shot-time-limit-us 0`
+            let deadline = parameters.shot_time_limit_us.filter(|_| sparse_measurement.len() > 0)
+                .map(|shot_time_limit_us| begin + std::time::Duration::from_micros(shot_time_limit_us));
+            let extra_erasure_edges = if !detector_dropout.is_empty() {
+                detector_dropout.get_erasure_edges(self.detector_dropout_generator.as_ref().unwrap().measurement_cycles)
+            } else { vec![] };
+            match self.general_decoder.decode_with_erasure_edges(&sparse_measurement, &sparse_detected_erasures, &extra_erasure_edges, deadline) {
+                Ok((correction, runtime_statistics)) => (correction, runtime_statistics, false),
+                Err(DecodingError::Timeout) => (SparseCorrection::new(), json!({ "timed_out": true }), true),
+            }
+        } else {
+            let (correction, runtime_statistics) = self.general_decoder.decode_with_erasure(&sparse_measurement, &sparse_detected_erasures);
+            (correction, runtime_statistics, false)
+        };
+        if parameters.thread_timeout >= 0. { self.thread_debugger.lock().unwrap().correction = Some(correction.clone()); }  // runtime debug: find deadlock cases
+        let decode_elapsed = begin.elapsed().as_secs_f64();
+        // remove the redundant operators of the correction, reporting how many of them the optimization eliminates
+        if !parameters.disable_optimize_correction_pattern && !is_shot_timed_out {
+            let optimized_correction_operators = correction.optimize();
+            runtime_statistics["optimized_correction_operators"] = json!(optimized_correction_operators);
+        }
+        let is_timed_out = runtime_statistics.get("timed_out").and_then(|timed_out| timed_out.as_bool()).unwrap_or(false);
+        // validate correction
+        let begin = Instant::now();
+        let mut is_qec_failed = false;
+        let mut logical_classes = None;
+        if is_shot_timed_out {  // no correction to validate
+            is_qec_failed = parameters.shot_time_limit_as_failure;
+        } else if !is_defect_free {  // no error and no correction never causes a logical error
+            let (logical_i, logical_j) = if parameters.log_logical_class {
+                let (true_class, claimed_class) = self.general_simulator.validate_correction_with_logical_classes(&correction);
+                logical_classes = Some((true_class, claimed_class));
+                (true_class.0 ^ claimed_class.0, true_class.1 ^ claimed_class.1)
+            } else {
+                self.general_simulator.validate_correction(&correction)
+            };
+            #[cfg(test)]
+            let (logical_i, logical_j) = if parameters.inject_validation_error { (false, false) } else { (logical_i, logical_j) };
+            let is_cross_checked = match self.cross_check_rng.as_mut() {
+                Some(cross_check_rng) => cross_check_rng.next_f64() < parameters.cross_check_fraction.unwrap(),
+                None => false,
+            };
+            if is_cross_checked {
+                let simulator = match &mut self.general_simulator {
+                    GeneralSimulator::Simulator(simulator) => simulator,
+                    _ => unreachable!("cross check is not supported by the compact simulator"),
+                };
+                let cross_checked = code_builder_cross_check_correction(simulator, &correction);
+                if cross_checked != Ok((logical_i, logical_j)) {
+                    let mut benchmark_control = self.benchmark_control.lock().unwrap();
+                    if benchmark_control.cross_check_failure.is_none() {
+                        benchmark_control.cross_check_failure = Some(format!("validation gives {:?} but cross check gives {:?}, error pattern: {}, correction: {}"
+                            , (logical_i, logical_j), cross_checked, json!(simulator.generate_sparse_error_pattern()), json!(correction)));
+                    }
+                    benchmark_control.set_external_terminate();
                 }
-            } }
-            // decode
-            let begin = Instant::now();
-            let (correction, mut runtime_statistics) = self.general_decoder.decode_with_erasure(&sparse_measurement, &sparse_detected_erasures);
-            if parameters.thread_timeout >= 0. { self.thread_debugger.lock().unwrap().correction = Some(correction.clone()); }  // runtime debug: find deadlock cases
-            let decode_elapsed = begin.elapsed().as_secs_f64();
-            // validate correction
-            let begin = Instant::now();
-            let mut is_qec_failed = false;
-            let (logical_i, logical_j) = self.general_simulator.validate_correction(&correction);
-            if logical_i && !parameters.ignore_logical_i {
+            }
+            if logical_i && !ignore_logical_i {
                 is_qec_failed = true;
             }
-            if logical_j && !parameters.ignore_logical_j {
+            if logical_j && !ignore_logical_j {
                 is_qec_failed = true;
             }
-            let validate_elapsed = begin.elapsed().as_secs_f64();
-            if is_qec_failed && matches!(parameters.debug_print, Some(BenchmarkDebugPrint::FailedErrorPattern)) {
-                let sparse_error_pattern = self.general_simulator.generate_sparse_error_pattern();
-                eprint!("{}", serde_json::to_string(&sparse_error_pattern).expect("serialize should success"));
-                if sparse_detected_erasures.len() > 0 {  // has detected erasures, report as well
-                    eprintln!(", {}", serde_json::to_string(&sparse_detected_erasures).expect("serialize should success"));
-                } else {
-                    eprintln!("");
+        }
+        let validate_elapsed = begin.elapsed().as_secs_f64();
+        // decode the same syndrome with the exact minimum-weight decoder, which fails only if the minimum-weight error is in a different logical class
+        let mut is_optimal_failed = false;
+        if let Some(optimal_decoder) = self.optimal_decoder.as_mut() {
+            if !is_defect_free {
+                let mut erasure_edges = sparse_detected_erasures.get_erasure_edges(&optimal_decoder.erasure_graph);
+                if !detector_dropout.is_empty() {
+                    erasure_edges.extend(detector_dropout.get_erasure_edges(self.detector_dropout_generator.as_ref().unwrap().measurement_cycles));
                 }
+                let (optimal_correction, _) = optimal_decoder.decode_with_erasure_edges(&sparse_measurement, &erasure_edges, None)
+                    .expect("never times out without deadline");
+                let (logical_i, logical_j) = self.general_simulator.validate_correction(&optimal_correction);
+                is_optimal_failed = (logical_i && !ignore_logical_i) || (logical_j && !ignore_logical_j);
+            }
+        }
+        if is_qec_failed && matches!(parameters.debug_print, Some(BenchmarkDebugPrint::FailedErrorPattern)) {
+            let sparse_error_pattern = self.general_simulator.generate_sparse_error_pattern();
+            eprint!("{}", serde_json::to_string(&sparse_error_pattern).expect("serialize should success"));
+            if sparse_detected_erasures.len() > 0 {  // has detected erasures, report as well
+                eprintln!(", {}", serde_json::to_string(&sparse_detected_erasures).expect("serialize should success"));
+            } else {
+                eprintln!();
+            }
+        }
+        // update statistic information
+        if let Some(erasure_conditioned) = self.erasure_conditioned.as_mut() {
+            erasure_conditioned.add_shot(sparse_detected_erasures.len(), is_qec_failed);
+        }
+        if let Some(weight_conditioned) = self.weight_conditioned.as_mut() {
+            weight_conditioned.add_shot(error_count, is_qec_failed);
+        }
+        let record_slot = self.streaming_statistics.as_mut().map(|streaming_statistics| {
+            streaming_statistics.add_shot(decode_elapsed, sparse_measurement.len())
+        });
+        // with streaming statistics, only the records of the sampled shots are built
+        if let (Some(log_runtime_statistics_file), None | Some(Some(_))) = (&self.log_runtime_statistics_file, record_slot) {
+            runtime_statistics["qec_failed"] = json!(is_qec_failed);
+            if let Some(shot_index) = shot_index {
+                runtime_statistics["shot_index"] = json!(shot_index);
             }
-            // update statistic information
-            if let Some(log_runtime_statistics_file) = &self.log_runtime_statistics_file {
-                runtime_statistics["qec_failed"] = json!(is_qec_failed);
-                if parameters.log_error_pattern_when_logical_error && is_qec_failed {
-                    runtime_statistics["error_pattern"] = json!(self.general_simulator.generate_sparse_error_pattern());
+            if self.optimal_decoder.is_some() {
+                runtime_statistics["optimal_qec_failed"] = json!(is_optimal_failed);
+            }
+            if parameters.log_error_pattern_when_logical_error && is_qec_failed {
+                runtime_statistics["error_pattern"] = json!(self.general_simulator.generate_sparse_error_pattern());
+            }
+            if let Some((true_class, claimed_class)) = logical_classes {
+                runtime_statistics["true_logical_class"] = json!(true_class);
+                runtime_statistics["claimed_logical_class"] = json!(claimed_class);
+            }
+            if parameters.log_failure_round && is_qec_failed {
+                if let GeneralSimulator::Simulator(simulator) = &mut self.general_simulator {
+                    runtime_statistics["failure_round"] = json!(self.general_decoder.logical_failure_round(simulator, &sparse_measurement
+                        , &sparse_detected_erasures, ignore_logical_i, ignore_logical_j));
                 }
-                runtime_statistics["elapsed"] = json!({
-                    "simulate": simulate_elapsed,
-                    "decode": decode_elapsed,
-                    "validate": validate_elapsed,
-                });
-                let to_be_written = format!("{}\n", runtime_statistics.to_string());
+            }
+            runtime_statistics["elapsed"] = json!({
+                "simulate": simulate_elapsed,
+                "decode": decode_elapsed,
+                "validate": validate_elapsed,
+            });
+            if let Some(Some(record_slot)) = record_slot {
+                self.streaming_statistics.as_mut().unwrap().records.store(record_slot, runtime_statistics);
+            } else {
+                let to_be_written = format!("{}\n", runtime_statistics);
                 let mut log_runtime_statistics_file = log_runtime_statistics_file.lock().unwrap();
                 log_runtime_statistics_file.write_all(to_be_written.as_bytes()).unwrap();
             }
-            // update visualizer
-            if let Some(visualizer) = &self.visualizer {
-                if !parameters.visualizer_skip_success_cases || is_qec_failed {
-                    let case = json!({
-                        "error_pattern": self.general_simulator.generate_sparse_error_pattern(),
-                        "measurement": sparse_measurement,
-                        "detected_erasures": sparse_detected_erasures,
-                        "correction": correction,
-                        "qec_failed": is_qec_failed,
-                        "elapsed": {
-                            "simulate": simulate_elapsed,
-                            "decode": decode_elapsed,
-                            "validate": validate_elapsed,
-                        },
-                    });
-                    let mut visualizer = visualizer.lock().unwrap();
-                    visualizer.add_case(case).unwrap();
-                }
-            }
-            // update simulation counters, then break the loop if benchmark should terminate
-            if self.benchmark_control.lock().unwrap().update_data_should_terminate(is_qec_failed, parameters.max_repeats, parameters.min_failed_cases) {
-                break
+        }
+        // update visualizer
+        if let Some(visualizer) = &self.visualizer {
+            let is_movie = match parameters.visualize_failures_movie {
+                Some(max_movies) => is_qec_failed && self.failure_movies.fetch_add(1, Ordering::SeqCst) < max_movies,
+                None => false,
+            };
+            let is_recorded = if parameters.visualize_failures_movie.is_some() { is_movie } else { !parameters.visualizer_skip_success_cases || is_qec_failed };
+            if is_recorded {
+                let mut case = json!({
+                    "error_pattern": self.general_simulator.generate_sparse_error_pattern(),
+                    "measurement": sparse_measurement,
+                    "detected_erasures": sparse_detected_erasures,
+                    "correction": correction,
+                    "qec_failed": is_qec_failed,
+                    "elapsed": {
+                        "simulate": simulate_elapsed,
+                        "decode": decode_elapsed,
+                        "validate": validate_elapsed,
+                    },
+                });
+                if is_movie {
+                    if let GeneralSimulator::Simulator(simulator) = &self.general_simulator {
+                        let error_pattern = simulator.generate_sparse_error_pattern();
+                        case["frames"] = syndrome_movie_frames(simulator, &error_pattern, &sparse_measurement, &sparse_detected_erasures, &correction);
+                        case["residual"] = json!(simulator.residual_error(&correction));
+                    }
+                }
+                let mut visualizer = visualizer.lock().unwrap();
+                visualizer.add_case(case).unwrap();
             }
         }
-        self.thread_ended.store(true, Ordering::SeqCst);
+        (is_qec_failed, is_timed_out, is_optimal_failed)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use crate::types::ErrorType;
+    use crate::rand_core::SeedableRng;
+
+    #[test]
+    fn tool_mini_batch_controller() {  // cargo test tool_mini_batch_controller -- --nocapture
+        // fast configuration, e.g. d=3: 1us per shot converges to the maximum batch size
+        let mut fast = MiniBatchController::new(0.5, 1, 100000);
+        for _ in 0..100 {
+            let shots = fast.batch_size;
+            fast.update(shots, shots as f64 * 1e-6);
+        }
+        assert_eq!(fast.batch_size, 100000);
+        // slow configuration, e.g. d=13 MWPM: 0.1s per shot converges to 5 shots per mini-batch
+        let mut slow = MiniBatchController::new(0.5, 1, 100000);
+        for _ in 0..100 {
+            let shots = slow.batch_size;
+            slow.update(shots, shots as f64 * 0.1);
+        }
+        assert_eq!(slow.batch_size, 5);
+        // very slow configuration is bounded by the minimum batch size
+        let mut very_slow = MiniBatchController::new(0.5, 1, 100000);
+        very_slow.update(1, 10.);
+        assert_eq!(very_slow.batch_size, 1);
+        // claimed repeats never exceed `max_repeats`, so the statistics is not affected by the mini-batch size
+        let mut benchmark_control = BenchmarkControl::new();
+        assert_eq!(benchmark_control.claim_repeats(60, 100, 10), (0, 60));
+        assert_eq!(benchmark_control.claim_repeats(60, 100, 10), (60, 40));
+        assert_eq!(benchmark_control.claim_repeats(60, 100, 10), (100, 0));
+        benchmark_control.report_repeats(60, 60, 0, 60);
+        benchmark_control.report_repeats(40, 30, 10, 60);
+        assert_eq!(benchmark_control.total_repeats, 90);
+        assert!(benchmark_control.should_terminate(100, 10));
+        assert_eq!(benchmark_control.claim_repeats(60, 100, 10).1, 0);
+    }
+
+    #[test]
+    fn tool_final_mini_batch_size() {  // cargo test tool_final_mini_batch_size -- --nocapture
+        let filepath = std::env::temp_dir().join("qecp_tool_final_mini_batch_size.txt").to_str().unwrap().to_string();
+        let final_mini_batch_size = |d: usize, noisy_measurements: usize, max_repeats: &str| -> usize {
+            parse_benchmark_parameters(&[&format!("[{}]", d), &format!("[{}]", noisy_measurements), "[0.001]", "-p", "1", "--decoder", "union-find", "-m", max_repeats
+                , "-e", "100000000000", "--mini-sync-time", "0.05", "--seed", "1706", "--log-runtime-statistics", &filepath]).run().unwrap();
+            let content = fs::read_to_string(&filepath).unwrap();
+            assert!(validate_runtime_statistics(&content).is_ok());
+            let mini_batch: serde_json::Value = serde_json::from_str(content.lines().last().unwrap().strip_prefix("#b ").unwrap()).unwrap();
+            mini_batch["mini_batch_size"].as_u64().unwrap() as usize
+        };
+        // the fast d=3 configuration grows the mini-batch, while the slow d=13 configuration keeps it small
+        let fast = final_mini_batch_size(3, 0, "20000");
+        let slow = final_mini_batch_size(13, 0, "100");
+        println!("final mini-batch size: d=3 {}, d=13 {}", fast, slow);
+        assert!(fast >= 4 * slow, "d=3 should synchronize less frequently than d=13, but the mini-batch sizes are {} and {}", fast, slow);
+        assert!(slow <= 50, "d=13 should synchronize frequently, but the mini-batch size is {}", slow);
+    }
+
+    #[test]
+    fn tool_report_interval() {  // cargo test tool_report_interval -- --nocapture
+        let cli = Cli::try_parse_from(["qecp", "tool", "benchmark", "[3]", "[0]", "[0.01]", "-p", "1", "--decoder", "union-find"
+            , "--time-budget", "1.6", "--report-interval", "0.3", "-m", "100000000000", "-e", "100000000000"]).unwrap();
+        let parameters = match cli.command {
+            Commands::Tool { command: ToolCommands::Benchmark(parameters) } => parameters,
+            _ => unreachable!(),
+        };
+        let configs = parameters.fill_in_default_parameters().unwrap();
+        let configurations = parameters.extract_simulation_configurations(&configs);
+        assert_eq!(configurations.len(), 1);
+        let mut partial_results = Vec::new();
+        let result = parameters.run_single_with_report(&configs, &configurations[0], &None, |partial_result| {
+            partial_results.push(partial_result.to_string());
+        }).unwrap();
+        println!("partial results: {:?}", partial_results);
+        assert!(partial_results.len() >= 2, "multiple partial results should be reported");
+        // <shots> <failed> are non-decreasing and never exceed the final result
+        let shots_failed = |line: &str| -> (usize, usize) {
+            let values: Vec<&str> = line.split_whitespace().collect();
+            (values[3].parse().unwrap(), values[4].parse().unwrap())
+        };
+        let mut previous = (0, 0);
+        for partial_result in partial_results.iter().map(String::as_str).chain(std::iter::once(result.as_str())) {
+            let current = shots_failed(partial_result);
+            assert!(current.0 >= previous.0 && current.1 >= previous.1);
+            previous = current;
+        }
+    }
+
+    #[test]
+    fn tool_decode_timeout() {  // cargo test tool_decode_timeout -- --nocapture
+        let run = |extra_arguments: &[&str]| -> Vec<String> {
+            let mut arguments = vec!["qecp", "tool", "benchmark", "[3]", "[3]", "[0.002]", "-p", "1", "--decoder", "union-find", "-m", "2000", "-e", "100000000000"];
+            arguments.extend_from_slice(extra_arguments);
+            let cli = Cli::try_parse_from(arguments).unwrap();
+            let parameters = match cli.command {
+                Commands::Tool { command: ToolCommands::Benchmark(parameters) } => parameters,
+                _ => unreachable!(),
+            };
+            let configs = parameters.fill_in_default_parameters().unwrap();
+            let configurations = parameters.extract_simulation_configurations(&configs);
+            let result = parameters.run_single(&configs, &configurations[0], &None).unwrap();
+            result.split_whitespace().map(|value| value.to_string()).collect()
+        };
+        // by default the output format is unchanged
+        let values = run(&[]);
+        assert_eq!(values.len(), 9);
+        // a tiny budget that times out whenever the clusters need to grow
+        let values = run(&["--decode-timeout-us", "0"]);
+        println!("{:?}", values);
+        assert_eq!(values.len(), 11);
+        let total_repeats: usize = values[3].parse().unwrap();
+        let timed_out: usize = values[9].parse().unwrap();
+        let timeout_rate: f64 = values[10].parse().unwrap();
+        assert_eq!(total_repeats, 2000);
+        assert!(timed_out > 0 && timed_out < total_repeats, "shots without defects never time out");
+        assert_eq!(timeout_rate, timed_out as f64 / total_repeats as f64);
+        // a generous budget never times out
+        let values = run(&["--decode-timeout-us", "10000000"]);
+        assert_eq!(values[9], "0");
+    }
+
+    #[test]
+    fn tool_logical_failure_round() {  // cargo test tool_logical_failure_round -- --nocapture
+        let d = 5;
+        let noisy_measurements = 8;
+        // a burst of Z errors on 3 of the 5 data qubits of a logical Z operator, which the decoder completes into a logical error
+        for final_perfect_rounds in [1, 2] {
+            let code_size = CodeSize::new(noisy_measurements, d, d).with_final_perfect_rounds(final_perfect_rounds);
+            let mut simulator = Simulator::new(CodeType::StandardPlanarCode, code_size);
+            let mut noise_model = NoiseModel::new(&simulator);
+            NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), 0.01, 0.5, 0.);
+            simulator.compress_error_rates(&mut noise_model);
+            let noise_model = Arc::new(noise_model);
+            let mut decoder = GeneralDecoder::UnionFind(UnionFindDecoder::new(&simulator, Arc::clone(&noise_model), &json!({}), 1, false));
+            let measurement_cycles = simulator.measurement_cycles;
+            for (burst_round, burst_rows) in [(2, [5, 7, 9]), (5, [1, 3, 5])] {
+                let burst_t = (burst_round * measurement_cycles..(burst_round + 1) * measurement_cycles).find(|&t| {
+                    noise_model.get_node_unwrap(&pos!(t, 1, 1)).pauli_error_rates.error_rate_Z > 0.
+                }).unwrap();
+                simulator.clear_all_errors();
+                for i in burst_rows {
+                    simulator.set_error_check(&noise_model, &pos!(burst_t, i, 1), &ErrorType::Z);
+                }
+                simulator.propagate_errors();
+                let sparse_measurement = simulator.generate_sparse_measurement();
+                let (correction, _) = decoder.decode_with_erasure(&sparse_measurement, &SparseErasures::new());
+                assert_eq!(simulator.validate_correction(&correction), (true, false));
+                let failure_round = decoder.logical_failure_round(&mut simulator, &sparse_measurement, &SparseErasures::new(), false, false);
+                println!("burst at t = {}, failure round: {}", burst_t, failure_round);
+                assert_eq!(failure_round, burst_t / measurement_cycles + 1, "the first measurement round after the burst");
+                // ignoring the failed logical axis attributes nothing to the noisy rounds
+                assert_eq!(decoder.logical_failure_round(&mut simulator, &sparse_measurement, &SparseErasures::new(), true, false), noisy_measurements + 1);
+            }
+        }
+        // the failure round of every failing shot is logged
+        let filepath = std::env::temp_dir().join("qecp_tool_logical_failure_round.txt").to_str().unwrap().to_string();
+        let _ = fs::remove_file(&filepath);
+        let parameters = parse_benchmark_parameters(&["[3]", "[4]", "[0.05]", "-p", "1", "--decoder", "union-find", "--noise-model", "phenomenological"
+            , "-m", "300", "-e", "100000000000", "--log-runtime-statistics", &filepath, "--log-failure-round"]);
+        parameters.run().unwrap();
+        let mut failed_count = 0;
+        for line in fs::read_to_string(&filepath).unwrap().lines().filter(|line| !line.starts_with('#')) {
+            let statistics: serde_json::Value = serde_json::from_str(line).unwrap();
+            if statistics["qec_failed"].as_bool().unwrap() {
+                failed_count += 1;
+                let failure_round = statistics["failure_round"].as_u64().unwrap();
+                assert!((1..=5).contains(&failure_round));
+            } else {
+                assert!(statistics.get("failure_round").is_none());
+            }
+        }
+        assert!(failed_count > 0);
+    }
+
+    #[test]
+    fn tool_log_logical_class() {  // cargo test tool_log_logical_class -- --nocapture
+        for extra_arguments in [vec![], vec!["--use-compact-simulator"]] {
+            let filepath = std::env::temp_dir().join("qecp_tool_log_logical_class.txt").to_str().unwrap().to_string();
+            let _ = fs::remove_file(&filepath);
+            let mut arguments = vec!["[3]", "[3]", "[0.03]", "-p", "1", "--decoder", "union-find", "--noise-model", "phenomenological"
+                , "-m", "500", "-e", "100000000000", "--log-runtime-statistics", &filepath, "--log-logical-class"];
+            arguments.extend_from_slice(&extra_arguments);
+            let result = parse_benchmark_parameters(&arguments).run().unwrap();
+            let values: Vec<&str> = result.lines().last().unwrap().split_whitespace().collect();
+            let total_repeats: usize = values[3].parse().unwrap();
+            let qec_failed: usize = values[4].parse().unwrap();
+            let error_rate: f64 = values[5].parse().unwrap();
+            let mut shots = 0;
+            let mut logical_errors = 0;
+            for line in fs::read_to_string(&filepath).unwrap().lines().filter(|line| !line.starts_with('#')) {
+                let statistics: serde_json::Value = serde_json::from_str(line).unwrap();
+                let true_class: (bool, bool) = serde_json::from_value(statistics["true_logical_class"].clone()).unwrap();
+                let claimed_class: (bool, bool) = serde_json::from_value(statistics["claimed_logical_class"].clone()).unwrap();
+                let is_logical_error = (true_class.0 ^ claimed_class.0) || (true_class.1 ^ claimed_class.1);
+                assert_eq!(is_logical_error, statistics["qec_failed"].as_bool().unwrap());
+                shots += 1;
+                if is_logical_error {
+                    logical_errors += 1;
+                }
+            }
+            println!("{} shots, {} logical errors", shots, logical_errors);
+            assert_eq!(shots, total_repeats);
+            assert_eq!(logical_errors, qec_failed);
+            assert!(logical_errors > 0);
+            assert_eq!(error_rate, logical_errors as f64 / shots as f64);
+        }
+    }
+
+    #[test]
+    fn tool_fixed_error_file() {  // cargo test tool_fixed_error_file -- --nocapture
+        let noisy_measurements = 2;
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(noisy_measurements, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), 0.01, 0.5, 0.);
+        let burst_t = (0..simulator.height).find(|&t| noise_model.get_node_unwrap(&pos!(t, 1, 1)).pauli_error_rates.error_rate_Z > 0.).unwrap();
+        let filepath = std::env::temp_dir().join("qecp_tool_fixed_error_file.json").to_str().unwrap().to_string();
+        let visualizer_filepath = std::env::temp_dir().join("qecp_tool_fixed_error_file_visualizer.json").to_str().unwrap().to_string();
+        // 2 of the 3 Z errors of a logical Z operator always cause a logical error, while a single Z error is always corrected
+        for (error_rows, expected_qec_failed) in [(vec![1, 3], true), (vec![3], false)] {
+            let mut sparse_error_pattern = SparseErrorPattern::new();
+            for i in error_rows {
+                sparse_error_pattern.add(pos!(burst_t, i, 1), ErrorType::Z);
+            }
+            fs::write(&filepath, serde_json::to_string(&sparse_error_pattern).unwrap()).unwrap();
+            let result = parse_benchmark_parameters(&["[3]", &format!("[{}]", noisy_measurements), "[0.01]", "-p", "1", "--decoder", "union-find"
+                , "--noise-model", "phenomenological", "-m", "50", "-e", "100000000000", "--fixed-error-file", &filepath
+                , "--enable-visualizer", "--visualizer-filename", &visualizer_filepath]).run().unwrap();
+            let values: Vec<&str> = result.lines().last().unwrap().split_whitespace().collect();
+            let total_repeats: usize = values[3].parse().unwrap();
+            let error_rate: f64 = values[5].parse().unwrap();
+            assert_eq!(total_repeats, 50);
+            assert_eq!(error_rate, if expected_qec_failed { 1. } else { 0. });
+            let data: serde_json::Value = serde_json::from_str(&fs::read_to_string(&visualizer_filepath).unwrap()).unwrap();
+            let cases = &data["cases"].as_array().unwrap()[1..];  // the first case is an empty placeholder
+            assert_eq!(cases.len(), 50);
+            for case in cases.iter() {
+                assert_eq!(case["measurement"], cases[0]["measurement"], "all shots should produce identical syndromes");
+                assert_eq!(case["error_pattern"], json!(sparse_error_pattern));
+                assert_eq!(case["qec_failed"].as_bool().unwrap(), expected_qec_failed);
+            }
+            assert!(!cases[0]["measurement"].as_array().unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn tool_worst_case_latency() {  // cargo test tool_worst_case_latency -- --nocapture
+        let arguments = ["qecp", "tool", "worst-case-latency", "[3,5]", "[2,2]", "[0.01]", "--decoder", "union-find", "--noise-model", "phenomenological", "-m", "300"];
+        let parameters = match Cli::try_parse_from(arguments).unwrap().command {
+            Commands::Tool { command: ToolCommands::WorstCaseLatency(parameters) } => parameters,
+            _ => unreachable!(),
+        };
+        let output = parameters.run_worst_case_latency().unwrap();
+        println!("{}", output);
+        let lines: Vec<serde_json::Value> = output.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        for (line, di) in lines.iter().zip([3, 5]) {
+            assert_eq!(line["di"], json!(di));
+            assert_eq!(line["shots"], json!(300));
+            let mean_latency = line["mean_latency"].as_f64().unwrap();
+            let max_latency = line["max_latency"].as_f64().unwrap();
+            assert!(mean_latency > 0.);
+            assert!(max_latency >= mean_latency, "the maximum {} should not be smaller than the mean {}", max_latency, mean_latency);
+            // the reported syndrome is the one generated by the reported error pattern, so that it can be re-examined
+            let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, di, di));
+            let mut noise_model = NoiseModel::new(&simulator);
+            NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), 0.01, 0.5, 0.);
+            let error_pattern: SparseErrorPattern = serde_json::from_value(line["worst_shot"]["error_pattern"].clone()).unwrap();
+            simulator.load_sparse_error_pattern(&error_pattern, &noise_model).unwrap();
+            simulator.propagate_errors();
+            assert_eq!(json!(simulator.generate_sparse_measurement()), line["worst_shot"]["measurement"]);
+        }
+    }
+
+    #[test]
+    fn tool_idle_rounds_between_cycles() {  // cargo test tool_idle_rounds_between_cycles -- --nocapture
+        let error_rate = |idle_rounds: &str| -> f64 {
+            let result = parse_benchmark_parameters(&["[3]", "[3]", "[0.005]", "-p", "1", "--decoder", "union-find", "--noise-model", "depolarizing-noise"
+                , "-m", "100000000000", "-e", "200", "--idle-rounds-between-cycles", idle_rounds, "--seed", "1725"]).run().unwrap();
+            println!("{}", result);
+            let values: Vec<&str> = result.lines().last().unwrap().split_whitespace().collect();
+            assert_eq!(values[4], "200");
+            values[5].parse().unwrap()
+        };
+        // every idle qubit suffers the same depolarizing noise as in the other gate-free layers, so a long delay increases the logical error rate
+        assert!(error_rate("3") > 1.5 * error_rate("0"));
+    }
+
+    #[test]
+    fn tool_shots_estimate() {  // cargo test tool_shots_estimate -- --nocapture
+        // stops at `min_failed_cases` failures
+        assert_eq!(ShotsEstimate::new(1000, 10, usize::MAX, 100), ShotsEstimate { shots: 10000., is_lower_bound: false });
+        assert_eq!(ShotsEstimate::new(1000, 3, usize::MAX, 10), ShotsEstimate { shots: 3334., is_lower_bound: false });
+        // or at `max_repeats`
+        assert_eq!(ShotsEstimate::new(1000, 10, 5000, 100), ShotsEstimate { shots: 5000., is_lower_bound: false });
+        assert_eq!(ShotsEstimate::new(1000, 10, 5000, usize::MAX), ShotsEstimate { shots: 5000., is_lower_bound: false });
+        assert_eq!(ShotsEstimate::new(1000, 10, usize::MAX, usize::MAX).shots, f64::INFINITY);
+        // without any failure, the rule of three gives a lower bound
+        assert_eq!(ShotsEstimate::new(1000, 0, usize::MAX, 100), ShotsEstimate { shots: 33334., is_lower_bound: true });
+        assert_eq!(ShotsEstimate::new(1, 0, usize::MAX, 100), ShotsEstimate { shots: 100., is_lower_bound: true });
+        // unless `max_repeats` is reached anyway
+        assert_eq!(ShotsEstimate::new(1000, 0, 20000, 100), ShotsEstimate { shots: 20000., is_lower_bound: false });
+        // the time budget stops the benchmark early
+        let estimate = ShotsEstimate::new(1000, 0, usize::MAX, 100).with_time_budget(Some(1.), 1e-3);
+        assert_eq!(estimate, ShotsEstimate { shots: 1000., is_lower_bound: false });
+        assert_eq!(ShotsEstimate::new(1000, 10, usize::MAX, 100).with_time_budget(Some(100.), 1e-3).shots, 10000.);
+    }
+
+    #[test]
+    fn tool_dry_run() {  // cargo test tool_dry_run -- --nocapture
+        let begin = Instant::now();
+        let result = parse_benchmark_parameters(&["[3,5]", "[3,5]", "[0.01,0.02,0.04]", "--decoder", "union-find", "--noise-model", "phenomenological"
+            , "-e", "1000", "-p", "4", "--dry-run", "--seed", "1"]).run().unwrap();
+        println!("{}", result);
+        println!("elapsed: {:?}", begin.elapsed());
+        let lines: Vec<&str> = result.lines().collect();
+        assert_eq!(lines.len(), 1 + 6 + 1);
+        let mut total_time = 0.;
+        for line in lines[1..7].iter() {
+            let values: Vec<&str> = line.split_whitespace().collect();
+            assert_eq!(values[3], "1000");
+            let failed: usize = values[4].parse().unwrap();
+            let shot_time: f64 = values[9].parse().unwrap();
+            let estimated_shots: f64 = values[10].trim_start_matches(">=").parse().unwrap();
+            assert_eq!(values[10].starts_with(">="), failed == 0);
+            if failed > 0 {
+                assert_eq!(estimated_shots, (1000. * 1000. / failed as f64).ceil());
+            }
+            let init_time: f64 = values[8].parse().unwrap();
+            let estimated_time: f64 = values[11].trim_start_matches(">=").parse().unwrap();
+            assert!((estimated_time - init_time - estimated_shots * shot_time / 4.).abs() <= 1e-3 * estimated_time);
+            total_time += estimated_time;
+        }
+        let total: f64 = lines[7].strip_prefix("total: ").unwrap().trim_start_matches(">=").parse().unwrap();
+        assert!((total - total_time).abs() <= 1e-2 * total);
+        assert!(parse_benchmark_parameters(&["[3]", "[3]", "[0.01]", "--dry-run", "0"]).run().is_err());
+    }
+
+    #[test]
+    fn tool_grid_sweep() {  // cargo test tool_grid_sweep -- --nocapture
+        let result = parse_benchmark_parameters(&["[3]", "[0]", "[0.01,0.02]", "--pes", "[0,0.05,0.1]", "--grid-sweep", "--decoder", "union-find"
+            , "-m", "10"]).run().unwrap();
+        println!("{}", result);
+        let rows: Vec<(f64, f64)> = result.lines().skip(1).map(|line| {
+            let values: Vec<&str> = line.split_whitespace().collect();
+            (values[0].parse().unwrap(), values[8].parse().unwrap())
+        }).collect();
+        assert_eq!(rows, vec![(0.01, 0.), (0.01, 0.05), (0.01, 0.1), (0.02, 0.), (0.02, 0.05), (0.02, 0.1)]);
+        // without the flag, ps and pes must be paired
+        assert!(std::panic::catch_unwind(|| {
+            parse_benchmark_parameters(&["[3]", "[0]", "[0.01,0.02]", "--pes", "[0,0.05,0.1]"]).fill_in_default_parameters()
+        }).is_err());
+    }
+
+    fn parse_benchmark_parameters(arguments: &[&str]) -> BenchmarkParameters {
+        let mut full_arguments = vec!["qecp", "tool", "benchmark"];
+        full_arguments.extend_from_slice(arguments);
+        match Cli::try_parse_from(full_arguments).unwrap().command {
+            Commands::Tool { command: ToolCommands::Benchmark(parameters) } => parameters,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn tool_shot_time_limit() {  // cargo test tool_shot_time_limit -- --nocapture
+        // a tiny limit makes every shot with defects time out, so the effective logical error rate approaches 100% when most shots have defects,
+        // while the shots without any defect never time out
+        for decoder in ["mwpm", "union-find"] {
+            for (p, minimum_timeout_rate, maximum_timeout_rate) in [("[0.05]", 0.9, 1.), ("[0]", 0., 0.)] {
+                let parameters = parse_benchmark_parameters(&["[3]", "[3]", p, "-p", "1", "--decoder", decoder, "-m", "1000", "-e", "100000000000"
+                    , "--shot-time-limit-us", "0", "--shot-time-limit-as-failure", "--seed", "1712"]);
+                let configs = parameters.fill_in_default_parameters().unwrap();
+                let configurations = parameters.extract_simulation_configurations(&configs);
+                let result = parameters.run_single(&configs, &configurations[0], &None).unwrap();
+                let values: Vec<&str> = result.split_whitespace().collect();
+                let error_rate: f64 = values[5].parse().unwrap();
+                let timeout_rate: f64 = values[10].parse().unwrap();
+                assert!(timeout_rate >= minimum_timeout_rate && timeout_rate <= maximum_timeout_rate, "{} p = {}: timeout rate is {}", decoder, p, timeout_rate);
+                assert!(error_rate >= timeout_rate, "{} p = {}: every timed-out shot counts as a failure", decoder, p);
+            }
+        }
+        // a generous limit gives exactly the same results as the unlimited run, given the same random errors
+        for (decoder, decoder_config) in [("mwpm", r#"{"emmd":16}"#), ("union-find", "{}")] {
+            let parameters = parse_benchmark_parameters(&["[3]", "[3]", "[0.005]", "--decoder", decoder, "--decoder-config", decoder_config]);
+            let configs = parameters.fill_in_default_parameters().unwrap();
+            let config = &parameters.extract_simulation_configurations(&configs)[0];
+            let mut simulator = Simulator::new(parameters.code_type, CodeSize::new(config.noisy_measurements, config.di, config.dj));
+            let noise_model = parameters.construct_noise_model(&mut simulator, &configs, config, false).unwrap();
+            let mut general_decoder = GeneralDecoder::from_parameters(&parameters, &configs, config, &simulator, &noise_model).unwrap();
+            simulator.rng = Xoroshiro128StarStar::seed_from_u64(1712);
+            for _ in 0..300 {
+                simulator.generate_random_errors(&noise_model);
+                let sparse_measurement = simulator.generate_sparse_measurement();
+                if sparse_measurement.len() > 16 {
+                    continue  // blossom V may not be available
+                }
+                let (correction, _) = general_decoder.decode_with_erasure(&sparse_measurement, &SparseErasures::new());
+                let deadline = Instant::now() + std::time::Duration::from_secs(10);
+                let (limited_correction, _) = general_decoder.decode_with_erasure_deadline(&sparse_measurement, &SparseErasures::new(), deadline).unwrap();
+                assert_eq!(json!(correction), json!(limited_correction));
+                assert_eq!(simulator.validate_correction(&correction), simulator.validate_correction(&limited_correction));
+            }
+        }
+    }
+
+    #[test]
+    fn tool_detector_dropout() {  // cargo test tool_detector_dropout -- --nocapture
+        for (decoder, decoder_config) in [("mwpm", r#"{"emmd":16}"#), ("union-find", "{}")] {
+            let parameters = parse_benchmark_parameters(&["[3]", "[3]", "[0.02]", "--decoder", decoder, "--decoder-config", decoder_config
+                , "--noise-model-builder", "phenomenological", "--detector-dropout-rate", "0.05"]);
+            let configs = parameters.fill_in_default_parameters().unwrap();
+            let config = &parameters.extract_simulation_configurations(&configs)[0];
+            let mut simulator = Simulator::new(parameters.code_type, CodeSize::new(config.noisy_measurements, config.di, config.dj));
+            let noise_model = parameters.construct_noise_model(&mut simulator, &configs, config, false).unwrap();
+            let mut general_decoder = GeneralDecoder::from_parameters(&parameters, &configs, config, &simulator, &noise_model).unwrap();
+            let noisy_detectors = Arc::new(simulator.noisy_detector_positions());
+            let measurement_cycles = simulator.measurement_cycles;
+            // the lost detectors change the weights of the complete model graph, which therefore cannot be precomputed
+            if decoder == "mwpm" {
+                let parameters = parse_benchmark_parameters(&["[3]", "[3]", "[0.02]", "--decoder", decoder, "--decoder-config", r#"{"pcmg":true}"#
+                    , "--noise-model-builder", "phenomenological", "--detector-dropout-rate", "0.05"]);
+                let error = GeneralDecoder::from_parameters(&parameters, &configs, config, &simulator, &noise_model).err().unwrap();
+                assert!(error.contains("precompute_complete_model_graph"), "{}", error);
+            }
+            // at r = 0 nothing is lost and the results are unchanged
+            let mut detector_dropout_generator = DetectorDropoutGenerator::new(0., noisy_detectors.clone(), measurement_cycles);
+            simulator.rng = Xoroshiro128StarStar::seed_from_u64(1714);
+            for _ in 0..100 {
+                simulator.generate_random_errors(&noise_model);
+                let sparse_measurement = simulator.generate_sparse_measurement();
+                if sparse_measurement.len() > 16 {
+                    continue  // blossom V may not be available
+                }
+                let detector_dropout = detector_dropout_generator.generate();
+                assert_eq!(detector_dropout.len(), 0);
+                let dropped_measurement = detector_dropout.apply(&sparse_measurement, measurement_cycles);
+                assert_eq!(dropped_measurement.to_vec(), sparse_measurement.to_vec());
+                let (correction, _) = general_decoder.decode_with_erasure(&sparse_measurement, &SparseErasures::new());
+                let (dropped_correction, _) = general_decoder.decode_with_erasure_edges(&dropped_measurement, &SparseErasures::new()
+                    , &detector_dropout.get_erasure_edges(measurement_cycles), None).unwrap();
+                assert_eq!(json!(correction), json!(dropped_correction));
+            }
+            // a whole round is lost: any single error is still corrected, and no defect appears in the lost round
+            let lost_t = 2 * measurement_cycles;
+            let mut detector_dropout = SparseDetectorDropout::new();
+            for detector in noisy_detectors.iter().filter(|detector| detector.t == lost_t) {
+                detector_dropout.insert_dropped(detector);
+            }
+            assert!(!detector_dropout.is_empty());
+            let erasure_edges = detector_dropout.get_erasure_edges(measurement_cycles);
+            let mut positions = Vec::new();
+            simulator_iter!(simulator, position, _node, {
+                positions.push(position.clone());
+            });
+            for position in positions.iter() {
+                for error in [ErrorType::X, ErrorType::Z] {
+                    if !noise_model.is_single_error_possible(&simulator, position, &error) {
+                        continue
+                    }
+                    simulator.clear_all_errors();
+                    simulator.set_error_check(&noise_model, position, &error);
+                    simulator.clear_propagate_errors();
+                    simulator.propagate_errors();
+                    let dropped_measurement = detector_dropout.apply(&simulator.generate_sparse_measurement(), measurement_cycles);
+                    assert!(dropped_measurement.iter().all(|defect| defect.t != lost_t));
+                    let (correction, _) = general_decoder.decode_with_erasure_edges(&dropped_measurement, &SparseErasures::new()
+                        , &erasure_edges, None).unwrap();
+                    assert_eq!(simulator.validate_correction(&correction), (false, false), "{}: {} at {}", decoder, error, position);
+                }
+            }
+            // at small r the logical error rate degrades gracefully on the same random errors
+            let mut detector_dropout_generator = DetectorDropoutGenerator::new(0.05, noisy_detectors.clone(), measurement_cycles);
+            detector_dropout_generator.rng = Xoroshiro128StarStar::seed_from_u64(1714);
+            simulator.rng = Xoroshiro128StarStar::seed_from_u64(1714);
+            let (mut qec_failed, mut dropout_qec_failed, mut total_dropped) = (0, 0, 0);
+            for _ in 0..500 {
+                simulator.generate_random_errors(&noise_model);
+                let sparse_measurement = simulator.generate_sparse_measurement();
+                if sparse_measurement.len() > 16 {
+                    continue  // blossom V may not be available
+                }
+                let (correction, _) = general_decoder.decode_with_erasure(&sparse_measurement, &SparseErasures::new());
+                let (logical_i, logical_j) = simulator.validate_correction(&correction);
+                if logical_i || logical_j { qec_failed += 1; }
+                let detector_dropout = detector_dropout_generator.generate();
+                total_dropped += detector_dropout.len();
+                let dropped_measurement = detector_dropout.apply(&sparse_measurement, measurement_cycles);
+                let (correction, _) = general_decoder.decode_with_erasure_edges(&dropped_measurement, &SparseErasures::new()
+                    , &detector_dropout.get_erasure_edges(measurement_cycles), None).unwrap();
+                let (logical_i, logical_j) = simulator.validate_correction(&correction);
+                if logical_i || logical_j { dropout_qec_failed += 1; }
+            }
+            println!("{}: qec_failed = {}, with dropout = {}, total dropped = {}", decoder, qec_failed, dropout_qec_failed, total_dropped);
+            assert!(total_dropped > 0);
+            assert!(dropout_qec_failed <= 2 * qec_failed + 10, "logical error rate should degrade gracefully");
+        }
+        // without any time-like edge, a lost detector cannot be merged with its next measurement
+        for (decoder, decoder_config) in [("mwpm", r#"{"emmd":16}"#), ("union-find", "{}")] {
+            for (noisy_measurements, noise_model_builder) in [("[0]", "phenomenological"), ("[3]", "code-capacity")] {
+                let parameters = parse_benchmark_parameters(&["[3]", noisy_measurements, "[0.02]", "--decoder", decoder, "--decoder-config", decoder_config
+                    , "--noise-model-builder", noise_model_builder, "--detector-dropout-rate", "0.05"]);
+                let configs = parameters.fill_in_default_parameters().unwrap();
+                let config = &parameters.extract_simulation_configurations(&configs)[0];
+                let error = parameters.run_single(&configs, config, &None).unwrap_err();
+                assert!(error.contains("time-like edges"), "{}", error);
+            }
+            // the decoders skip the lost detectors without a time-like edge instead of panicking
+            let parameters = parse_benchmark_parameters(&["[3]", "[3]", "[0.02]", "--decoder", decoder, "--decoder-config", decoder_config
+                , "--noise-model-builder", "code-capacity"]);
+            let configs = parameters.fill_in_default_parameters().unwrap();
+            let config = &parameters.extract_simulation_configurations(&configs)[0];
+            let mut simulator = Simulator::new(parameters.code_type, CodeSize::new(config.noisy_measurements, config.di, config.dj));
+            let noise_model = parameters.construct_noise_model(&mut simulator, &configs, config, false).unwrap();
+            let mut general_decoder = GeneralDecoder::from_parameters(&parameters, &configs, config, &simulator, &noise_model).unwrap();
+            let mut detector_dropout = SparseDetectorDropout::new();
+            for detector in simulator.noisy_detector_positions().iter() {
+                detector_dropout.insert_dropped(detector);
+            }
+            simulator.rng = Xoroshiro128StarStar::seed_from_u64(1714);
+            simulator.generate_random_errors(&noise_model);
+            let dropped_measurement = detector_dropout.apply(&simulator.generate_sparse_measurement(), simulator.measurement_cycles);
+            general_decoder.decode_with_erasure_edges(&dropped_measurement, &SparseErasures::new()
+                , &detector_dropout.get_erasure_edges(simulator.measurement_cycles), None).unwrap();
+        }
+        // the benchmark runs with lost detectors
+        let parameters = parse_benchmark_parameters(&["[3]", "[3]", "[0.01]", "-p", "1", "--decoder", "union-find", "-m", "1000", "-e", "100000000000"
+            , "--detector-dropout-rate", "0.05"]);
+        let configs = parameters.fill_in_default_parameters().unwrap();
+        let configurations = parameters.extract_simulation_configurations(&configs);
+        let result = parameters.run_single(&configs, &configurations[0], &None).unwrap();
+        let values: Vec<&str> = result.split_whitespace().collect();
+        assert_eq!(values[3], "1000");
+    }
+
+    #[test]
+    fn tool_noise_model_configuration_sweep() {  // cargo test tool_noise_model_configuration_sweep -- --nocapture
+        let expanded = expand_noise_model_configuration_sweeps(&json!({"a": {"sweep": [1, 2, 3]}, "b": {"sweep": [true, false]}, "c": 0.1})).unwrap();
+        assert_eq!(expanded.len(), 6);
+        assert_eq!(expanded[1].0, json!({"a": 1, "b": false, "c": 0.1}));
+        assert_eq!(serde_json::Value::Object(expanded[5].1.clone()), json!({"a": 3, "b": false}));
+        assert!(expand_noise_model_configuration_sweeps(&json!({"a": {"sweep": []}})).is_err());
+        assert!(expand_noise_model_configuration_sweeps(&json!({"a": {"sweep": 0.1}})).is_err());
+        // nested sweeps are not expanded
+        assert_eq!(expand_noise_model_configuration_sweeps(&json!({"a": {"b": {"sweep": [1, 2]}}})).unwrap().len(), 1);
+        // the benchmark runs every combination and records the resolved values
+        let before_measure_flip_probabilities = [0.001, 0.003, 0.01];
+        let after_reset_flip_probabilities = [0.002, 0.004];
+        let parameters = parse_benchmark_parameters(&["[3]", "[1]", "[0.001]", "-p", "1", "--code-type", "rotated-planar-code", "--decoder", "union-find"
+            , "-m", "100", "-e", "100000000000", "--noise-model-builder", "stim-noise-model", "--noise-model-configuration"
+            , &json!({"before_measure_flip_probability": {"sweep": before_measure_flip_probabilities}
+                , "after_reset_flip_probability": {"sweep": after_reset_flip_probabilities}}).to_string()]);
+        let output = parameters.run().unwrap();
+        let records: Vec<&str> = output.lines().skip(1).collect();  // skip the format line
+        assert_eq!(records.len(), 6);
+        let mut resolved_values = Vec::new();
+        for record in records.iter() {
+            let values: Vec<&str> = record.split_whitespace().collect();
+            assert_eq!(values[3], "100");
+            let sweep: serde_json::Value = serde_json::from_str(values.last().unwrap()).unwrap();
+            resolved_values.push((sweep["after_reset_flip_probability"].as_f64().unwrap(), sweep["before_measure_flip_probability"].as_f64().unwrap()));
+        }
+        let mut expected_values = Vec::new();
+        for after_reset_flip_probability in after_reset_flip_probabilities {
+            for before_measure_flip_probability in before_measure_flip_probabilities {
+                expected_values.push((after_reset_flip_probability, before_measure_flip_probability));
+            }
+        }
+        assert_eq!(resolved_values, expected_values);
+        // the resolved value is used to build the noise model
+        let configs = parameters.fill_in_default_parameters().unwrap();
+        let configurations = parameters.extract_simulation_configurations(&configs);
+        assert_eq!(configurations[0].noise_model_configuration, json!({"before_measure_flip_probability": 0.001, "after_reset_flip_probability": 0.002}));
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown keys")]
+    fn tool_noise_model_configuration_sweep_unknown_key() {  // cargo test tool_noise_model_configuration_sweep_unknown_key -- --nocapture
+        let parameters = parse_benchmark_parameters(&["[3]", "[1]", "[0.001]", "-p", "1", "--code-type", "rotated-planar-code", "--decoder", "union-find"
+            , "-m", "100", "-e", "100000000000", "--noise-model-builder", "stim-noise-model", "--noise-model-configuration"
+            , r#"{"unknown_key": {"nested": {"sweep": [0.001, 0.002]}}}"#]);
+        let configs = parameters.fill_in_default_parameters().unwrap();
+        let configurations = parameters.extract_simulation_configurations(&configs);
+        parameters.run_single(&configs, &configurations[0], &None).unwrap();
+    }
+
+    #[test]
+    fn tool_zero_error_rate() {  // cargo test tool_zero_error_rate -- --nocapture
+        // p = 0 with erasures: zero-probability edges are kept in the decoding graph only to apply the erasures
+        for (decoder, decoder_config) in [("mwpm", r#"{"emmd":16}"#), ("union-find", "{}")] {
+            let parameters = parse_benchmark_parameters(&["[3]", "[3]", "[0]", "--pes", "[0.01]", "--decoder", decoder, "--decoder-config", decoder_config]);
+            let configs = parameters.fill_in_default_parameters().unwrap();
+            let config = &parameters.extract_simulation_configurations(&configs)[0];
+            let mut simulator = Simulator::new(parameters.code_type, CodeSize::new(config.noisy_measurements, config.di, config.dj));
+            let noise_model = parameters.construct_noise_model(&mut simulator, &configs, config, false).unwrap();
+            let mut general_decoder = GeneralDecoder::from_parameters(&parameters, &configs, config, &simulator, &noise_model).unwrap();
+            simulator.rng = Xoroshiro128StarStar::seed_from_u64(1719);
+            let mut erasure_shots = 0;
+            for _ in 0..300 {
+                let (error_count, erasure_count) = simulator.generate_random_errors(&noise_model);
+                let sparse_measurement = simulator.generate_sparse_measurement();
+                if sparse_measurement.len() > 16 {
+                    continue  // blossom V may not be available
+                }
+                assert!(error_count == 0 || erasure_count > 0, "errors only happen on erased qubits");
+                erasure_shots += if erasure_count > 0 { 1 } else { 0 };
+                let sparse_detected_erasures = simulator.generate_sparse_detected_erasures();
+                let (correction, _) = general_decoder.decode_with_erasure(&sparse_measurement, &sparse_detected_erasures);
+                code_builder_sanity_check_correction(&mut simulator, &correction).unwrap();
+            }
+            assert!(erasure_shots > 0);
+        }
+        // the phenomenological noise model puts the erasures on data qubits
+        let parameters = parse_benchmark_parameters(&["[3]", "[3]", "[0]", "--pes", "[0.01]", "-p", "1", "--decoder", "union-find"
+            , "--noise-model-builder", "phenomenological", "-m", "500", "-e", "100000000000"]);
+        let configs = parameters.fill_in_default_parameters().unwrap();
+        let configurations = parameters.extract_simulation_configurations(&configs);
+        let result = parameters.run_single(&configs, &configurations[0], &None).unwrap();
+        let values: Vec<&str> = result.split_whitespace().collect();
+        assert_eq!(values[3], "500");
+        // p = 0 without erasures never fails, and the defect-free shots skip the decoder
+        for decoder in ["mwpm", "union-find"] {
+            let parameters = parse_benchmark_parameters(&["[3]", "[3]", "[0]", "-p", "1", "--decoder", decoder, "-m", "10000", "-e", "100000000000"]);
+            let configs = parameters.fill_in_default_parameters().unwrap();
+            let configurations = parameters.extract_simulation_configurations(&configs);
+            let result = parameters.run_single(&configs, &configurations[0], &None).unwrap();
+            println!("{}", result);
+            let values: Vec<&str> = result.split_whitespace().collect();
+            assert_eq!(values[3], "10000");
+            assert_eq!(values[4], "0");
+            let error_rate: f64 = values[5].parse().unwrap();
+            let confidence_interval: f64 = values[7].parse().unwrap();
+            assert_eq!(error_rate, 0.);
+            assert_eq!(confidence_interval, 3. / 10000., "the upper bound of the rule of three");
+        }
+        assert_eq!(logical_error_rate_with_confidence_interval(0, 0), (0., 0.));
+        assert_eq!(logical_error_rate_with_confidence_interval(2, 0), (0., 1.));
+    }
+
+    #[test]
+    fn tool_export_tikz() {  // cargo test tool_export_tikz -- --nocapture
+        let d = 3;
+        // (code type, number of data qubits, number of stabilizers)
+        for (code_type, data_count, stabilizer_count) in [(CodeType::StandardPlanarCode, d * d + (d - 1) * (d - 1), 2 * d * (d - 1))
+                , (CodeType::RotatedPlanarCode, d * d, d * d - 1)] {
+            let parameters = ExportTikzParameters { d, dj: None, code_type };
+            let output = parameters.run().unwrap();
+            println!("{}", output);
+            assert!(output.starts_with("\\begin{tikzpicture}"));
+            assert!(output.ends_with("\\end{tikzpicture}\n"));
+            assert_eq!(output.matches("\\node[data]").count(), data_count);
+            assert_eq!(output.matches("\\node[").count(), data_count + stabilizer_count);
+            assert_eq!(output.matches("\\fill[").count(), stabilizer_count);
+        }
+    }
+
+    #[test]
+    fn tool_graph_entropy() {  // cargo test tool_graph_entropy -- --nocapture
+        let d = 3;
+        let p = 0.1;
+        // code capacity with pure Z errors: every data qubit is a distinct edge (or boundary) of the X stabilizers, all with probability p
+        let parameters = GraphEntropyParameters { d, dj: None, noisy_measurements: 0, p, pe: 0., bias_eta: f64::INFINITY
+            , code_type: CodeType::StandardPlanarCode, noise_model_builder: Some(NoiseModelBuilder::CodeCapacity), noise_model_configuration: json!({}) };
+        let output: serde_json::Value = serde_json::from_str(&parameters.run().unwrap()).unwrap();
+        println!("{}", output);
+        let edge_count = d * d + (d - 1) * (d - 1);
+        let expected_entropy = edge_count as f64 * (- p * p.log2() - (1. - p) * (1. - p).log2());
+        assert_eq!(output["edges"], json!(edge_count));
+        assert!((output["entropy"].as_f64().unwrap() - expected_entropy).abs() < 1e-9);
+        assert!((output["mean_entropy"].as_f64().unwrap() - 0.4689955935892812).abs() < 1e-9);
+        // an edge with p = 1/2 carries one bit of entropy
+        let parameters = GraphEntropyParameters { p: 0.5, ..parameters };
+        let output: serde_json::Value = serde_json::from_str(&parameters.run().unwrap()).unwrap();
+        assert!((output["entropy"].as_f64().unwrap() - edge_count as f64).abs() < 1e-9);
+        // a sweep of a single value is resolved, while sweeping multiple values is only supported by the benchmark
+        let parameters = GraphEntropyParameters { noise_model_builder: Some(NoiseModelBuilder::DepolarizingNoise)
+            , noise_model_configuration: json!({"round_duration": {"sweep": [1.]}, "idle_rate_per_time": 0.01}), ..parameters };
+        assert_eq!(parameters.run(), GraphEntropyParameters { noise_model_configuration: json!({"round_duration": 1., "idle_rate_per_time": 0.01}), ..parameters.clone() }.run());
+        let parameters = GraphEntropyParameters { noise_model_configuration: json!({"round_duration": {"sweep": [1., 2.]}, "idle_rate_per_time": 0.01}), ..parameters };
+        assert!(parameters.run().unwrap_err().contains("only supported by the benchmark"));
+    }
+
+    #[test]
+    fn tool_paired_statistics() {  // cargo test tool_paired_statistics -- --nocapture
+        let mut statistics = PairedStatistics::new(3);
+        assert_eq!(statistics.error_rate(0), 0.);
+        assert_eq!(statistics.difference(0, 1), (0., 0.));
+        // 10 shots: decoder 0 fails in 4 shots, decoder 1 fails in 2 shots (1 of them shared with decoder 0), decoder 2 never fails
+        for qec_failed in [[true, true, false], [true, false, false], [true, false, false], [true, false, false], [false, true, false]] {
+            statistics.add_shot(&qec_failed);
+        }
+        for _ in 0..5 {
+            statistics.add_shot(&[false, false, false]);
+        }
+        assert_eq!(statistics.shots, 10);
+        assert_eq!(statistics.failed, vec![vec![4, 3, 4], vec![1, 2, 2], vec![0, 0, 0]]);
+        assert_eq!(statistics.error_rate(0), 0.4);
+        assert_eq!(statistics.error_rate(1), 0.2);
+        // per-shot differences of decoder 0 and 1 are three 1s, one -1 and six 0s: mean 0.2, population variance 0.4 - 0.04 = 0.36
+        let (difference, standard_error) = statistics.difference(0, 1);
+        assert!((difference - 0.2).abs() < 1e-12);
+        assert!((standard_error - (0.36f64 / 10.).sqrt()).abs() < 1e-12);
+        let (difference, standard_error) = statistics.difference(1, 0);
+        assert!((difference + 0.2).abs() < 1e-12);
+        assert!((standard_error - (0.36f64 / 10.).sqrt()).abs() < 1e-12);
+        assert!((statistics.mcnemar_z(0, 1) - 1.).abs() < 1e-12);  // (3 - 1) / sqrt(4)
+        assert!((statistics.mcnemar_z(1, 0) + 1.).abs() < 1e-12);
+        assert!((statistics.mcnemar_z(1, 2) - 2f64.sqrt()).abs() < 1e-12);  // (2 - 0) / sqrt(2)
+        // a decoder compared with itself, or two decoders always failing together, has no difference
+        assert_eq!(statistics.difference(0, 0), (0., 0.));
+        assert_eq!(statistics.mcnemar_z(2, 2), 0.);
+        let mut statistics = PairedStatistics::new(2);
+        statistics.add_shot(&[true, true]);
+        statistics.add_shot(&[false, false]);
+        assert_eq!(statistics.difference(0, 1), (0., 0.));
+        assert_eq!(statistics.mcnemar_z(0, 1), 0.);
+    }
+
+    #[test]
+    fn tool_weight_sensitivity() {  // cargo test tool_weight_sensitivity -- --nocapture
+        let run = |max_half_weights: &str| -> Vec<serde_json::Value> {
+            let output = match Cli::try_parse_from(["qecp", "tool", "weight-sensitivity", "--d", "5", "--noisy-measurements", "2", "--p", "0.01"
+                    , "--noise-model", "only-gate-error-circuit-level", "--max-half-weights", max_half_weights, "-m", "500", "--seed", "1724"]).unwrap().command {
+                Commands::Tool { command } => command.run().unwrap(),
+                _ => unreachable!(),
+            };
+            println!("{}", output);
+            output.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+        };
+        // the same level decodes the same samples, so there is no discordant shot at all
+        let levels = run("[4,4]");
+        assert!(levels[0]["qec_failed"].as_u64().unwrap() > 0);
+        assert_eq!(levels[0]["qec_failed"], levels[1]["qec_failed"]);
+        assert_eq!(levels[1]["vs_previous"], json!({ "difference": 0., "standard_error": 0., "mcnemar_z": 0. }));
+        // the samples only depend on the seed, not on the levels
+        let levels_more = run("[1,4]");
+        assert_eq!(levels_more[1]["qec_failed"], levels[0]["qec_failed"]);
+        let difference = levels_more[0]["vs_reference"]["difference"].as_f64().unwrap();
+        let error_rates: Vec<f64> = levels_more.iter().map(|level| level["error_rate"].as_f64().unwrap()).collect();
+        assert!((difference - (error_rates[0] - error_rates[1])).abs() < 1e-12);
+        assert_eq!(levels_more[1]["vs_reference"], json!({ "difference": 0., "standard_error": 0., "mcnemar_z": 0. }));
+        match Cli::try_parse_from(["qecp", "tool", "weight-sensitivity", "--d", "3", "--p", "0.01", "--decoder", "mwpm"]).unwrap().command {
+            Commands::Tool { command } => assert!(command.run().is_err(), "mwpm decoder doesn't support `max_half_weight`"),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn tool_common_random_numbers() {  // cargo test tool_common_random_numbers -- --nocapture
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::DepolarizingNoise.apply(&mut simulator, &mut noise_model, &json!({}), 0.002, 0.5, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        let locations = LocationClass::count_locations(&simulator, &noise_model);
+        println!("{:?}", locations);
+        assert!(locations.contains_key(&LocationClass::DataIdle) && locations.contains_key(&LocationClass::Measurement));
+        assert!(locations.contains_key(&LocationClass::GateStage(2)) && locations.contains_key(&LocationClass::AncillaIdle));
+        let perturbed = LocationClass::DataIdle.perturb(&simulator, &noise_model, 3.).unwrap();
+        let diff = noise_model.diff(&perturbed).unwrap();
+        assert_eq!(diff.len(), locations[&LocationClass::DataIdle]);
+        let mut identical_shots = 0;
+        let mut seed_generator = Xoroshiro128StarStar::seed_from_u64(0);
+        for _ in 0..200 {
+            let shot_seed = seed_generator.next_u64();
+            generate_random_errors_with_seed(&mut simulator, &noise_model, shot_seed);
+            let unperturbed = simulator.generate_sparse_error_pattern();
+            generate_random_errors_with_seed(&mut simulator, &perturbed, shot_seed);
+            let errors = simulator.generate_sparse_error_pattern();
+            if errors == unperturbed {
+                identical_shots += 1;
+            }
+            // the errors only differ at the perturbed locations
+            let positions: BTreeSet<&Position> = unperturbed.errors.keys().chain(errors.errors.keys()).collect();
+            for position in positions {
+                if unperturbed.errors.get(position) != errors.errors.get(position) {
+                    assert_eq!(LocationClass::of_single_qubit_errors(&simulator, position), Some(LocationClass::DataIdle));
+                }
+            }
+            // the same seed reproduces the same errors
+            generate_random_errors_with_seed(&mut simulator, &noise_model, shot_seed);
+            assert_eq!(simulator.generate_sparse_error_pattern(), unperturbed);
+        }
+        println!("identical shots: {}", identical_shots);
+        assert!(identical_shots > 0 && identical_shots < 200);
+        // the error rates cannot exceed 1
+        assert!(LocationClass::DataIdle.perturb(&simulator, &noise_model, 1000.).is_err());
+    }
+
+    #[test]
+    fn tool_sensitivity_analysis() {  // cargo test tool_sensitivity_analysis -- --nocapture
+        let output = match Cli::try_parse_from(["qecp", "tool", "sensitivity-analysis", "--d", "3", "--noisy-measurements", "3", "--p", "0.005"
+                , "--noise-model", "depolarizing-noise", "--perturbation", "1", "-m", "10000", "--seed", "1"]).unwrap().command {
+            Commands::Tool { command } => command.run().unwrap(),
+            _ => unreachable!(),
+        };
+        println!("{}", output);
+        let lines: Vec<serde_json::Value> = output.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines[0]["class"], json!("unperturbed"));
+        assert!(lines[0]["qec_failed"].as_u64().unwrap() > 0);
+        let sensitivity = |class: &str| -> f64 {
+            lines.iter().find(|line| line["class"] == json!(class)).unwrap()["sensitivity"].as_f64().unwrap()
+        };
+        let gate_sensitivities: Vec<f64> = lines.iter().filter(|line| line["class"].as_str().unwrap().starts_with("gate_stage_"))
+            .map(|line| line["sensitivity"].as_f64().unwrap()).collect();
+        assert_eq!(gate_sensitivities.len(), 4);
+        // the ancilla errors at the initialization are reset, so they don't form a class of their own
+        assert!(lines.iter().all(|line| line["class"] != json!("initialization")));
+        // every CX stage is more sensitive than the idle ancilla qubits; in this model, however, the data qubits idle at the same rate p
+        // during both the initialization and the measurement of every round, and these many weight-1 faults dominate all the other
+        // classes, while a measurement error only flips a time-like edge and needs a space-like fault to cause a logical error
+        for &gate_sensitivity in gate_sensitivities.iter() {
+            assert!(gate_sensitivity > sensitivity("ancilla_idle") && gate_sensitivity > sensitivity("measurement"));
+            assert!(gate_sensitivity < sensitivity("data_idle"));
+        }
+        // a logical error of the distance-3 code needs two faults, so the sensitivities roughly add up to 2
+        let total: f64 = lines[1..].iter().map(|line| line["sensitivity"].as_f64().unwrap()).sum();
+        assert!(total > 1. && total < 4., "total sensitivity {}", total);
+    }
+
+    #[test]
+    fn tool_check_virtual_consistency() {  // cargo test tool_check_virtual_consistency -- --nocapture
+        for code_type in ["standard-planar-code", "rotated-planar-code"] {
+            let output = match Cli::try_parse_from(["qecp", "tool", "check-virtual-consistency", "--d", "3", "--noisy-measurements", "3", "--p", "0.05"
+                    , "-c", code_type, "--noise-model", "depolarizing-noise", "-m", "2000", "--seed", "1"]).unwrap().command {
+                Commands::Tool { command } => command.run().unwrap(),
+                _ => unreachable!(),
+            };
+            println!("{}", output);
+            let summary: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
+            assert!(summary["checked_shots"].as_u64().unwrap() > 1000);
+            assert!(summary["virtual_defects"].as_u64().unwrap() > 0);
+        }
+        // a missing virtual defect breaks the consistency
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 3, 3));
+        let mut boundary_data_qubit = None;
+        simulator_iter_real!(simulator, position, node, t => 1, if node.qubit_type == QubitType::Data && simulator.boundary_type_of(position).is_some() {
+            boundary_data_qubit = Some(position.clone());
+        });
+        simulator.get_node_mut_unwrap(&boundary_data_qubit.unwrap()).error = ErrorType::X;
+        simulator.propagate_errors();
+        let sparse_measurement = simulator.generate_sparse_measurement();
+        let sparse_measurement_virtual = simulator.generate_sparse_measurement_virtual();
+        assert_eq!(simulator.check_virtual_consistency(&sparse_measurement, &sparse_measurement_virtual), Ok(()));
+        assert_eq!(sparse_measurement.len(), 1);
+        assert_eq!(sparse_measurement_virtual.len(), 1);
+        assert!(simulator.check_virtual_consistency(&sparse_measurement, &SparseMeasurement::new()).is_err());
+        assert!(simulator.check_virtual_consistency(&sparse_measurement_virtual, &sparse_measurement).is_err());
+    }
+
+    #[test]
+    fn tool_diff_noise_models() {  // cargo test tool_diff_noise_models -- --nocapture
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, 0.001, 0.001, 0.001, 0.);
+        NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), 0.003, 0.5, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        let folder = std::env::temp_dir();
+        let filepath_a = folder.join("qecp_tool_diff_noise_models_a.json").to_str().unwrap().to_string();
+        let filepath_b = folder.join("qecp_tool_diff_noise_models_b.json").to_str().unwrap().to_string();
+        fs::write(&filepath_a, simulator.to_json(&noise_model).to_string()).unwrap();
+        // expanding the error rates breaks the `Arc` sharing but doesn't change the noise model
+        let mut expanded_noise_model = noise_model.clone();
+        simulator.expand_error_rates(&mut expanded_noise_model);
+        fs::write(&filepath_b, simulator.to_json(&expanded_noise_model).to_string()).unwrap();
+        let parameters = DiffNoiseModelsParameters { a: filepath_a.clone(), b: filepath_b.clone() };
+        assert_eq!(parameters.run().unwrap(), "");
+        // change a single error rate
+        let position = Position::new(6, 1, 1);
+        let mut node = noise_model.get_node_unwrap(&position).clone();
+        node.pauli_error_rates.error_rate_X += 0.01;
+        expanded_noise_model.set_node(&position, Some(Arc::new(node)));
+        fs::write(&filepath_b, simulator.to_json(&expanded_noise_model).to_string()).unwrap();
+        let output = parameters.run().unwrap();
+        assert_eq!(output.lines().count(), 1);
+        let difference: serde_json::Value = serde_json::from_str(output.lines().next().unwrap()).unwrap();
+        assert_eq!(difference["position"], json!(position));
+    }
+
+    #[test]
+    fn tool_shot_index() {  // cargo test tool_shot_index -- --nocapture
+        let filepath = std::env::temp_dir().join("qecp_tool_shot_index.txt").to_str().unwrap().to_string();
+        let (p, seed) = (0.05, 1752);
+        parse_benchmark_parameters(&["[3]", "[2]", &format!("[{}]", p), "-p", "2", "--decoder", "union-find", "--noise-model", "phenomenological"
+            , "-m", "300", "-e", "100000000000", "--seed", &seed.to_string(), "--log-runtime-statistics", &filepath
+            , "--log-error-pattern-when-logical-error"]).run().unwrap();
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        simulator.set_error_rates(&mut noise_model, p / 3., p / 3., p / 3., 0.);
+        NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), p, 0.5, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        // every shot of the two workers is logged with its index, and the failed ones are reproduced from the index alone
+        let mut shot_indices = BTreeSet::new();
+        let mut failed_count = 0;
+        for line in fs::read_to_string(&filepath).unwrap().lines().filter(|line| !line.starts_with('#')) {
+            let statistics: serde_json::Value = serde_json::from_str(line).unwrap();
+            let shot_index = statistics["shot_index"].as_u64().unwrap() as usize;
+            assert!(shot_indices.insert(shot_index), "each shot index is sampled once");
+            if statistics["qec_failed"].as_bool().unwrap() {
+                failed_count += 1;
+                let (error_pattern, _) = simulator.shot_from_index(&noise_model, seed, shot_index);
+                assert_eq!(json!(error_pattern), statistics["error_pattern"]);
+            }
+        }
+        assert_eq!(shot_indices, (0..300).collect());
+        assert!(failed_count > 0);
+    }
+
+    #[test]
+    fn tool_config_file_round_trip() {  // cargo test tool_config_file_round_trip -- --nocapture
+        let folder = std::env::temp_dir();
+        let original_filepath = folder.join("qecp_tool_config_file_original.txt").to_str().unwrap().to_string();
+        let reproduced_filepath = folder.join("qecp_tool_config_file_reproduced.txt").to_str().unwrap().to_string();
+        let original = parse_benchmark_parameters(&["[3]", "[2]", "[0.02]", "-p", "1", "--decoder", "union-find", "--noise-model", "phenomenological"
+            , "--noise-model-configuration", r#"{"erasure_rate_multiplier":{"linear":0.5}}"#, "-m", "300", "--log-runtime-statistics", &original_filepath
+            , "--log-error-pattern-when-logical-error"]).run().unwrap();
+        let read_header = |filepath: &String| -> serde_json::Value {
+            let content = fs::read_to_string(filepath).unwrap();
+            serde_json::from_str(content.lines().next().unwrap().strip_prefix("#f ").unwrap()).unwrap()
+        };
+        let header = read_header(&original_filepath);
+        assert_eq!(header["environment"]["version"], json!(env!("CARGO_PKG_VERSION")));
+        assert!(!header["environment"]["arguments"].as_array().unwrap().is_empty());
+        assert!(header["parameters"]["seed"].is_u64(), "a random seed should be picked and recorded");
+        assert_eq!(header["parameters"]["noise_model_configuration"], json!({"erasure_rate_multiplier":{"linear":0.5}}));
+        // the header is usable by `--config-file`, reproducing exactly the same shots
+        let reproduced = match Cli::try_parse_from(["qecp", "tool", "benchmark", "--config-file", &original_filepath
+                , "--log-runtime-statistics", &reproduced_filepath]).unwrap().command {
+            Commands::Tool { command } => command.run().unwrap(),
+            _ => unreachable!(),
+        };
+        assert_eq!(reproduced, original);
+        let mut reproduced_header = read_header(&reproduced_filepath);
+        reproduced_header["parameters"]["log_runtime_statistics"] = json!(original_filepath);
+        assert_eq!(reproduced_header["parameters"], header["parameters"]);
+        let shots = |filepath: &String| -> Vec<serde_json::Value> {
+            fs::read_to_string(filepath).unwrap().lines().filter(|line| !line.starts_with('#')).map(|line| {
+                let shot: serde_json::Value = serde_json::from_str(line).unwrap();
+                json!([shot["qec_failed"], shot["error_pattern"]])
+            }).collect()
+        };
+        let original_shots = shots(&original_filepath);
+        assert_eq!(original_shots.len(), 300);
+        assert!(original_shots.iter().any(|shot| shot[0] == json!(true)));
+        assert_eq!(shots(&reproduced_filepath), original_shots);
+    }
+
+    #[test]
+    fn tool_tls_defects_informed_decoding() {  // cargo test tool_tls_defects_informed_decoding -- --nocapture
+        // with the same seed, the informed and the ignorant decoders decode exactly the same shots
+        let failed = |informed: bool| -> usize {
+            let noise_model_configuration = json!({"tls": {"count": 3, "severity": 10.0, "seed": 1, "informed": informed}}).to_string();
+            let result = parse_benchmark_parameters(&["[5]", "[0]", "[0.03]", "-p", "1", "--decoder", "union-find", "--decoder-config", r#"{"max_half_weight":10}"#
+                , "--noise-model", "code-capacity", "--noise-model-configuration", &noise_model_configuration, "-m", "5000", "-e", "100000000000"
+                , "--seed", "1727"]).run().unwrap();
+            println!("informed = {}: {}", informed, result.lines().last().unwrap());
+            let values: Vec<&str> = result.lines().last().unwrap().split_whitespace().collect();
+            values[4].parse().unwrap()
+        };
+        let informed_failed = failed(true);
+        let ignorant_failed = failed(false);
+        assert!(informed_failed < ignorant_failed, "informed {} vs ignorant {}", informed_failed, ignorant_failed);
+        // a malformed TLS configuration is an error instead of a panic, and it needs a noise model builder to apply to
+        for (noise_model, tls, expected) in [("code-capacity", r#"{"count":3,"severity":10.0,"unknown":1}"#, "tls format error")
+                , ("code-capacity", r#"{"count":1000,"severity":10.0}"#, "cannot choose"), ("code-capacity", r#"{"count":3,"severity":1e6}"#, "exceed 1")
+                , ("code-capacity", r#"{"count":3,"severity":-1.0}"#, "non-negative"), ("", r#"{"count":3,"severity":10.0}"#, "requires `--noise-model`")] {
+            let noise_model_configuration = format!(r#"{{"tls":{}}}"#, tls);
+            let mut arguments = vec!["[5]", "[0]", "[0.03]", "--decoder", "union-find", "--noise-model-configuration", &noise_model_configuration];
+            if !noise_model.is_empty() {
+                arguments.extend_from_slice(&["--noise-model", noise_model]);
+            }
+            let error = parse_benchmark_parameters(&arguments).run().unwrap_err();
+            assert!(error.contains(expected), "{}", error);
+        }
+    }
+
+    #[test]
+    fn tool_optimal_failure_rate() {  // cargo test tool_optimal_failure_rate -- --nocapture
+        // with only Z errors, the exact minimum-weight matching finds the true minimum-weight error
+        let run = |decoder: &str, decoder_config: &str| -> Vec<String> {
+            let result = parse_benchmark_parameters(&["[5]", "[0]", "[0.06]", "-p", "1", "--decoder", decoder, "--decoder-config", decoder_config
+                , "--noise-model", "code-capacity", "--bias-eta", "1e200", "-m", "3000", "-e", "100000000000", "--seed", "1729", "--optimal-failure-rate"]).run().unwrap();
+            println!("{}: {}", decoder, result.lines().last().unwrap());
+            result.lines().last().unwrap().split_whitespace().map(|value| value.to_string()).collect()
+        };
+        // the union-find decoder is a heuristic: it fails at least as often as the exact minimum-weight decoder on the same shots
+        let values = run("union-find", "{}");
+        assert_eq!(values.len(), 11);
+        let failed: usize = values[4].parse().unwrap();
+        let optimal_failed: usize = values[9].parse().unwrap();
+        let optimal_error_rate: f64 = values[10].parse().unwrap();
+        assert!(optimal_failed > 0 && failed >= optimal_failed, "failed {} vs optimal {}", failed, optimal_failed);
+        assert_eq!(optimal_error_rate, optimal_failed as f64 / 3000.);
+        // the MWPM decoder with the exact matcher is the optimal decoder itself
+        let values = run("mwpm", r#"{"emmd":24}"#);
+        assert_eq!(values[4], values[9]);
+    }
+
+    #[test]
+    fn tool_cross_check_fraction() {  // cargo test tool_cross_check_fraction -- --nocapture
+        // the regular validation agrees with the independent path on every shot, including noisy measurement rounds
+        let result = parse_benchmark_parameters(&["[3]", "[2]", "[0.02]", "-p", "1", "--decoder", "union-find", "-m", "2000", "-e", "100000000000"
+            , "--seed", "1", "--cross-check-fraction", "1"]).run();
+        assert!(result.is_ok(), "{:?}", result);
+        // a validation that marks every shot as successful is detected with a small fraction
+        let mut parameters = parse_benchmark_parameters(&["[3]", "[0]", "[0.1]", "-p", "1", "--decoder", "union-find", "--noise-model", "code-capacity"
+            , "-m", "20000", "-e", "100000000000", "--seed", "1", "--cross-check-fraction", "0.01"]);
+        assert!(parameters.clone().run().is_ok());
+        parameters.inject_validation_error = true;
+        let error = parameters.run().unwrap_err();
+        println!("{}", error);
+        assert!(error.contains("cross check of the validation failed"));
+    }
+
+    #[test]
+    fn tool_export_npy() {  // cargo test tool_export_npy -- --nocapture
+        let output = format!("{}/tool_export_npy", std::env::temp_dir().display());
+        let parameters = ExportNpyParameters::parse_from(["", "--d", "3", "--noisy-measurements", "2", "--p", "0.05"
+            , "--noise-model", "phenomenological", "-m", "100", "--seed", "1", "-o", &output]);
+        let result: serde_json::Value = serde_json::from_str(&parameters.run().unwrap()).unwrap();
+        println!("{}", result);
+        let read_npy = |filepath: &serde_json::Value, shape: &[usize]| -> Vec<u8> {
+            let bytes = fs::read(filepath.as_str().unwrap()).unwrap();
+            assert_eq!(&bytes[..6], npy::NPY_MAGIC);
+            let header_length = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+            let header = std::str::from_utf8(&bytes[10..10 + header_length]).unwrap();
+            assert_eq!(header.trim_end(), npy::npy_header("|u1", shape));
+            bytes[10 + header_length..].to_vec()
+        };
+        // 3 measurement rounds of the 7x7 lattice of the d = 3 standard planar code, including the virtual boundaries
+        let syndromes = read_npy(&result["syndromes"], &[100, 3, 7, 7]);
+        let logicals = read_npy(&result["logicals"], &[100, 2]);
+        assert_eq!(syndromes.len(), 100 * 3 * 7 * 7);
+        assert!(syndromes.iter().all(|&value| value <= 1) && syndromes.contains(&1));
+        assert!(logicals.iter().all(|&value| value <= 1));
+        // reproducible with the same seed
+        let reproduced: serde_json::Value = serde_json::from_str(&parameters.run().unwrap()).unwrap();
+        assert_eq!(read_npy(&reproduced["syndromes"], &[100, 3, 7, 7]), syndromes);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tool_stream_socket() {  // cargo test tool_stream_socket -- --nocapture
+        use std::os::unix::net::UnixListener;
+        use std::io::BufReader;
+        let path = format!("{}/tool_stream_socket_{}.sock", std::env::temp_dir().display(), std::process::id());
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let listener_thread = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            BufReader::new(stream).lines().map(|line| line.unwrap()).collect::<Vec<String>>()
+        });
+        let parameters = parse_benchmark_parameters(&["[3]", "[0]", "[0.01,0.02]", "-p", "1", "--decoder", "union-find", "-m", "100"
+            , "--stream-socket", &path]);
+        let output = parameters.run().unwrap();
+        let messages = listener_thread.join().unwrap();  // the connection is closed when the benchmark finishes
+        fs::remove_file(&path).unwrap();
+        println!("{:?}", messages);
+        // one message per completed configuration, with the same result as the output
+        assert_eq!(messages.len(), 2);
+        for (message, (p, result)) in messages.iter().zip([0.01, 0.02].iter().zip(output.lines().skip(1))) {
+            let message: serde_json::Value = serde_json::from_str(message).unwrap();
+            assert_eq!(message["config"]["p"], json!(p));
+            assert_eq!(message["result"], json!(result));
+        }
+        // failing to connect is reported before running the simulations
+        let parameters = parse_benchmark_parameters(&["[3]", "[0]", "[0.01]", "--stream-socket", &path]);
+        assert!(parameters.run().unwrap_err().contains("cannot connect to stream socket"));
+    }
+
+    #[test]
+    fn tool_erasure_buckets() {  // cargo test tool_erasure_buckets -- --nocapture
+        let d = 5;
+        let filepath = std::env::temp_dir().join("qecp_tool_erasure_buckets.txt").to_str().unwrap().to_string();
+        let output = parse_benchmark_parameters(&[&format!("[{}]", d), "[0]", "[0]", "--pes", "[0.35]", "-p", "1", "--decoder", "union-find"
+            , "--noise-model", "erasure-only-phenomenological", "-m", "3000", "-e", "0", "--seed", "1", "--erasure-buckets", "[0,5,10,15,20,25,30]"
+            , "--log-runtime-statistics", &filepath]).run().unwrap();
+        let content = fs::read_to_string(&filepath).unwrap();
+        let buckets: Vec<ConditionedBin> = serde_json::from_str(content.lines().find_map(|line| line.strip_prefix("#e ")).unwrap()).unwrap();
+        println!("{}", output);
+        for bucket in buckets.iter() {
+            println!("{:?}", bucket);
+        }
+        assert!(validate_runtime_statistics(&content).is_ok());
+        // the marginal reproduces the unconditional logical error rate exactly
+        let values: Vec<&str> = output.lines().last().unwrap().split_whitespace().collect();
+        let (shots, failed): (usize, usize) = (values[3].parse().unwrap(), values[4].parse().unwrap());
+        assert_eq!(buckets.iter().map(|bucket| bucket.shots).sum::<usize>(), shots);
+        assert_eq!(buckets.iter().map(|bucket| bucket.failed).sum::<usize>(), failed);
+        assert!((buckets.iter().map(|bucket| bucket.fraction).sum::<f64>() - 1.).abs() < 1e-9);
+        // a logical error needs at least d erasures, and the conditional error rate rises sharply with more erasures
+        assert_eq!(buckets[0].failed, 0);
+        let error_rate = |bucket: usize| buckets[bucket].error_rate.unwrap();
+        assert!(error_rate(1) < error_rate(2) && error_rate(2) < error_rate(3) && error_rate(3) < error_rate(4));
+        assert!(error_rate(2) < 0.1 && error_rate(4) > 0.3);
+        // invalid buckets are rejected
+        assert!(ConditionedStatistics::with_boundaries(vec![1, 2]).is_err());
+        assert!(ConditionedStatistics::with_boundaries(vec![0, 2, 2]).is_err());
+    }
+
+    #[test]
+    fn tool_weight_conditioned() {  // cargo test tool_weight_conditioned -- --nocapture
+        let d = 3;
+        let filepath = std::env::temp_dir().join("qecp_tool_weight_conditioned.txt").to_str().unwrap().to_string();
+        let output = parse_benchmark_parameters(&[&format!("[{}]", d), "[0]", "[0.05]", "-p", "2", "--decoder", "union-find"
+            , "-m", "3000", "-e", "0", "--seed", "1", "--weight-conditioned", "--log-runtime-statistics", &filepath]).run().unwrap();
+        let content = fs::read_to_string(&filepath).unwrap();
+        let bins: Vec<ConditionedBin> = serde_json::from_str(content.lines().find_map(|line| line.strip_prefix("#w ")).unwrap()).unwrap();
+        println!("{}", output);
+        for bin in bins.iter() {
+            println!("{:?}", bin);
+        }
+        assert!(validate_runtime_statistics(&content).is_ok());
+        // the marginal reproduces the unconditional logical error rate exactly
+        let values: Vec<&str> = output.lines().last().unwrap().split_whitespace().collect();
+        let (shots, failed): (usize, usize) = (values[3].parse().unwrap(), values[4].parse().unwrap());
+        assert_eq!(bins.iter().map(|bin| bin.shots).sum::<usize>(), shots);
+        assert_eq!(bins.iter().map(|bin| bin.failed).sum::<usize>(), failed);
+        assert!((bins.iter().map(|bin| bin.fraction).sum::<f64>() - 1.).abs() < 1e-9);
+        for (weight, bin) in bins.iter().enumerate() {
+            assert_eq!(bin.range, (weight, Some(weight + 1)));
+        }
+        // no error never causes a logical error, while heavier errors do
+        assert!(bins[0].shots > 0);
+        assert_eq!(bins[0].failed, 0);
+        assert_eq!(bins[0].error_rate, Some(0.));
+        assert!(failed > 0);
+        // merging the statistics of the workers adds the bins of different lengths
+        let mut weight_conditioned = ConditionedStatistics::per_value();
+        weight_conditioned.add_shot(1, false);
+        let mut other = ConditionedStatistics::per_value();
+        other.add_shot(3, true);
+        weight_conditioned.take_from(&mut other);
+        assert_eq!((weight_conditioned.shots, weight_conditioned.failed), (vec![0, 1, 0, 1], vec![0, 0, 0, 1]));
+        assert_eq!(other.shots, vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn tool_statistics_reservoir() {  // cargo test tool_statistics_reservoir -- --nocapture
+        let filepath = std::env::temp_dir().join("qecp_tool_statistics_reservoir.txt").to_str().unwrap().to_string();
+        let run = |extra_arguments: &[&str]| -> String {
+            let mut arguments = vec!["[3]", "[3]", "[0.02,0.05]", "-p", "2", "--decoder", "union-find", "--noise-model", "phenomenological"
+                , "-m", "2000", "-e", "0", "--seed", "1", "--log-runtime-statistics", &filepath];
+            arguments.extend_from_slice(extra_arguments);
+            parse_benchmark_parameters(&arguments).run().unwrap();
+            fs::read_to_string(&filepath).unwrap()
+        };
+        let content = run(&["--statistics-reservoir", "50"]);
+        assert!(validate_runtime_statistics(&content).is_ok());
+        // the log is bounded by the reservoir size for each configuration
+        let sections: Vec<&str> = content.split("\n# ").skip(1).collect();
+        assert_eq!(sections.len(), 2);
+        for section in sections.iter() {
+            let lines: Vec<&str> = section.lines().skip(1).filter(|line| !line.starts_with("#b ")).collect();
+            let summary: serde_json::Value = serde_json::from_str(lines.last().unwrap().strip_prefix("#s ").unwrap()).unwrap();
+            println!("{}", summary);
+            assert_eq!(lines.len() - 1, 50);
+            assert!(lines[..lines.len() - 1].iter().all(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["elapsed"].is_object()));
+            // the summaries count every shot
+            assert_eq!(summary["shots"], json!(2000));
+            assert_eq!(summary["records"], json!(50));
+            let histogram: Vec<u64> = serde_json::from_value(summary["defects"]["histogram"].clone()).unwrap();
+            assert_eq!(histogram.iter().sum::<u64>() + summary["defects"]["overflow"].as_u64().unwrap(), 2000);
+            assert!(summary["decode_latency"]["p50"].as_f64().unwrap() <= summary["decode_latency"]["p99"].as_f64().unwrap());
+        }
+        // without a reservoir every shot is logged
+        let content = run(&[]);
+        assert_eq!(validate_runtime_statistics(&content).unwrap()["shots"], json!(4000));
+    }
+
+    #[test]
+    fn tool_noise_override() {  // cargo test tool_noise_override -- --nocapture
+        let filepath = std::env::temp_dir().join("qecp_tool_noise_override.txt").to_str().unwrap().to_string();
+        let noise_override = r#"{"rounds": [2, 4], "error_model": "phenomenological", "p": 0.05}"#;
+        let run = |extra_arguments: &[&str]| -> (String, SimulationConfigs) {
+            let mut arguments = vec!["[3]", "[5]", "[0.005]", "-p", "1", "--decoder", "union-find", "--noise-model", "phenomenological"
+                , "-m", "1000", "--seed", "1"];
+            arguments.extend_from_slice(extra_arguments);
+            let parameters = parse_benchmark_parameters(&arguments);
+            (parameters.run().unwrap(), parameters.fill_in_default_parameters().unwrap())
+        };
+        let failed = |output: &str| -> usize { output.lines().last().unwrap().split_whitespace().nth(4).unwrap().parse().unwrap() };
+        let (base_output, _) = run(&[]);
+        let (output, configs) = run(&["--noise-override", noise_override, "--log-runtime-statistics", &filepath]);
+        println!("{}{}", base_output, output);
+        assert!(failed(&output) > failed(&base_output));
+        // the override is recorded in the runtime statistics
+        let header: serde_json::Value = serde_json::from_str(fs::read_to_string(&filepath).unwrap().lines().next().unwrap().strip_prefix("#f ").unwrap()).unwrap();
+        assert_eq!(header["configs"]["noise_override"]["rounds"], json!([2, 4]));
+        assert_eq!(header["parameters"]["noise_override"], serde_json::from_str::<serde_json::Value>(noise_override).unwrap());
+        // both the sampled errors and the decoding graph use the composed noise model
+        let parameters = parse_benchmark_parameters(&["[3]", "[5]", "[0.005]", "--noise-model", "phenomenological", "--noise-override", noise_override]);
+        let config = &parameters.extract_simulation_configurations(&configs)[0];
+        let mut simulator = parameters.new_simulator(5, 3, 3).unwrap();
+        let noise_model = parameters.construct_noise_model(&mut simulator, &configs, config, false).unwrap();
+        let noise_model_graph = parameters.construct_noise_model(&mut simulator, &configs, config, true).unwrap();
+        let error_rate = |noise_model: &NoiseModel, round: usize| -> f64 {
+            let mut error_rate: f64 = 0.;
+            simulator_iter!(simulator, position, _node, {
+                if position.t / simulator.measurement_cycles == round {
+                    error_rate = error_rate.max(noise_model.get_node_unwrap(position).pauli_error_rates.error_probability());
+                }
+            });
+            error_rate
+        };
+        for noise_model in [&noise_model, &noise_model_graph] {
+            assert!(error_rate(noise_model, 2) > 5. * error_rate(noise_model, 1));
+            assert!(error_rate(noise_model, 3) > 5. * error_rate(noise_model, 4));
+        }
+        // invalid overrides are rejected at the beginning
+        for invalid in [r#"{"rounds": [2, 2], "p": 0.05}"#, r#"{"rounds": [2, 4], "error_model": "unknown", "p": 0.05}"#, r#"{"rounds": [2, 4]}"#] {
+            let parameters = parse_benchmark_parameters(&["[3]", "[5]", "[0.005]", "--noise-override", invalid]);
+            assert!(parameters.run().unwrap_err().contains("noise override"));
+        }
+    }
+
+    fn tool_bench_decoder_corpus(name: &str, d: usize, shots: usize) -> String {
+        let corpus = format!("{}/{}.bin", std::env::temp_dir().display(), name);
+        let d = d.to_string();
+        let shots = shots.to_string();
+        GenerateDatasetParameters::parse_from(["", "--d", &d, "--noisy-measurements", &d, "--p", "0.005", "-c", "rotated-planar-code"
+            , "--noise-model", "phenomenological", "-m", &shots, "--seed", "1", "-o", &corpus]).run().unwrap();
+        corpus
+    }
+
+    /// the rows of the `bench_decoder` table, as `(decoder, shots, throughput, throughput_rsd)`
+    fn tool_bench_decoder_rows(output: &str) -> Vec<(String, usize, f64, f64)> {
+        output.lines().skip(1).map(|line| {
+            let values: Vec<&str> = line.split_whitespace().collect();
+            assert_eq!(values.len(), 9, "{}", line);
+            (values[0].to_string(), values[1].parse().unwrap(), values[2].parse().unwrap(), values[3].parse().unwrap())
+        }).collect()
+    }
+
+    #[test]
+    fn tool_bench_decoder() {  // cargo test tool_bench_decoder -- --nocapture
+        let corpus = tool_bench_decoder_corpus("tool_bench_decoder", 3, 200);
+        let output = BenchDecoderParameters::parse_from(["", "--corpus", &corpus, "--decoder", "mwpm,union-find", "--passes", "2"]).run().unwrap();
+        println!("{}", output);
+        let rows = tool_bench_decoder_rows(&output);
+        assert_eq!(rows.iter().map(|row| row.0.as_str()).collect::<Vec<_>>(), ["mwpm", "union-find"]);
+        for (_, shots, throughput, throughput_rsd) in rows.iter() {
+            assert_eq!(*shots, 200);
+            assert!(*throughput > 0. && *throughput_rsd >= 0.);
+        }
+        // a broken corpus is rejected
+        fs::write(&corpus, b"QECPSYN").unwrap();
+        assert!(BenchDecoderParameters::parse_from(["", "--corpus", &corpus, "--decoder", "mwpm"]).run().is_err());
+    }
+
+    /// timing-sensitive, run it on an idle machine in release mode; large syndromes need blossom V
+    #[cfg(feature = "blossom_v")]
+    #[test]
+    #[ignore]
+    fn tool_bench_decoder_throughput_ordering() {  // cargo test --release tool_bench_decoder_throughput_ordering -- --ignored --nocapture
+        let corpus = tool_bench_decoder_corpus("tool_bench_decoder_throughput_ordering", 5, 20000);
+        let parameters = BenchDecoderParameters::parse_from(["", "--corpus", &corpus, "--decoder", "mwpm,union-find", "--passes", "3"]);
+        let runs: Vec<_> = (0..2).map(|_| {
+            let output = parameters.run().unwrap();
+            println!("{}", output);
+            tool_bench_decoder_rows(&output)
+        }).collect();
+        for rows in runs.iter() {
+            let (mwpm, union_find) = (&rows[0], &rows[1]);
+            assert!(union_find.2 > mwpm.2, "union-find should be faster than MWPM");
+            assert!(mwpm.3 < 0.15 && union_find.3 < 0.15, "the throughput is not stable across the passes");
+        }
+        // the two runs agree with each other
+        for decoder in 0..2 {
+            let (a, b) = (runs[0][decoder].2, runs[1][decoder].2);
+            assert!((a - b).abs() / a.max(b) < 0.2, "throughput {} and {} differ a lot between runs", a, b);
+        }
+    }
+
+    #[test]
+    fn tool_build_graph() {  // cargo test tool_build_graph -- --nocapture
+        let filepath = format!("{}/tool_build_graph.json", std::env::temp_dir().display());
+        for (decoder, decoder_config) in [("mwpm", r#"{"emmd":24}"#), ("union-find", "{}")] {
+            let arguments = ["[3]", "[3]", "[0.01]", "--noise-model", "phenomenological", "--decoder", decoder, "--decoder-config", decoder_config];
+            let output = BuildGraphParameters::parse_from([&[""], &arguments[..], &["--out", &filepath]].concat()).run().unwrap();
+            println!("{}", output);
+            // loading the saved model graph yields identical decoding results to rebuilding it
+            let parameters = parse_benchmark_parameters(&arguments);
+            let loading_parameters = parse_benchmark_parameters(&[&arguments[..], &["--load-graph", &filepath]].concat());
+            let configs = parameters.fill_in_default_parameters().unwrap();
+            let config = &parameters.extract_simulation_configurations(&configs)[0];
+            let mut simulator = parameters.new_simulator(config.noisy_measurements, config.di, config.dj).unwrap();
+            let noise_model = parameters.construct_noise_model(&mut simulator, &configs, config, false).unwrap();
+            let noise_model_graph = parameters.construct_noise_model(&mut simulator, &configs, config, true).unwrap();
+            let mut rebuilt_decoder = GeneralDecoder::from_parameters(&parameters, &configs, config, &simulator, &noise_model_graph).unwrap();
+            let mut loaded_decoder = GeneralDecoder::from_parameters(&loading_parameters, &configs, config, &simulator, &noise_model_graph).unwrap();
+            simulator.rng = Xoroshiro128StarStar::seed_from_u64(1);
+            for _ in 0..300 {
+                simulator.generate_random_errors(&noise_model);
+                let sparse_measurement = simulator.generate_sparse_measurement();
+                let (rebuilt_correction, _) = rebuilt_decoder.decode_with_erasure(&sparse_measurement, &SparseErasures::new());
+                let (loaded_correction, _) = loaded_decoder.decode_with_erasure(&sparse_measurement, &SparseErasures::new());
+                assert_eq!(loaded_correction.to_vec(), rebuilt_correction.to_vec());
+            }
+            // the same benchmark result
+            let benchmark = |extra_arguments: &[&str]| -> String {
+                parse_benchmark_parameters(&[&arguments[..], &["-p", "1", "-m", "500", "--seed", "1"], extra_arguments].concat()).run().unwrap()
+            };
+            assert_eq!(benchmark(&["--load-graph", &filepath]), benchmark(&[]));
+            // the saved model graph is rejected by a different configuration
+            for other_arguments in [&["[3]", "[3]", "[0.02]"], &["[3]", "[4]", "[0.01]"]] {
+                let error = parse_benchmark_parameters(&[&other_arguments[..], &arguments[3..], &["--load-graph", &filepath]].concat()).run().unwrap_err();
+                assert!(error.contains("config hash"), "{}", error);
+            }
+            let mut other_decoder_config: serde_json::Value = serde_json::from_str(decoder_config).unwrap();
+            other_decoder_config["wf"] = json!("Unweighted");
+            let other_decoder_config = other_decoder_config.to_string();
+            let error = parse_benchmark_parameters(&[&arguments[..7], &["--decoder-config", &other_decoder_config, "--load-graph", &filepath]].concat())
+                .run().unwrap_err();
+            assert!(error.contains("config hash"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn tool_export_pymatching() {  // cargo test tool_export_pymatching -- --nocapture
+        let filepath = format!("{}/tool_export_pymatching.json", std::env::temp_dir().display());
+        let arguments = ["[3]", "[3]", "[0.01]", "--noise-model", "phenomenological", "--decoder", "mwpm", "--decoder-config", r#"{"emmd":24}"#];
+        let output = ExportPymatchingParameters::parse_from([&[""], &arguments[..], &["--out", &filepath]].concat()).run().unwrap();
+        println!("{}", output);
+        let check_matrix: CheckMatrix = serde_json::from_str(&fs::read_to_string(&filepath).unwrap()).unwrap();
+        // rebuild the model graph of the same configuration
+        let parameters = parse_benchmark_parameters(&arguments);
+        let configs = parameters.fill_in_default_parameters().unwrap();
+        let config = &parameters.extract_simulation_configurations(&configs)[0];
+        let (weight_function, use_combined_probability) = parameters.model_graph_options().unwrap();
+        let mut simulator = parameters.new_simulator(config.noisy_measurements, config.di, config.dj).unwrap();
+        let noise_model_graph = parameters.construct_noise_model(&mut simulator, &configs, config, true).unwrap();
+        let mut model_graph = ModelGraph::new(&simulator);
+        model_graph.build(&mut simulator, noise_model_graph, &weight_function, 1, use_combined_probability, false);
+        // one row per detector and one column per elected edge, including the boundary edges
+        let detector_map = crate::position_indexer::DetectorMap::from_simulator(&simulator);
+        let boundaries = detector_map.positions().iter().filter(|position| model_graph.get_node_unwrap(position).boundary.is_some()).count();
+        assert!(boundaries > 0);
+        assert_eq!(check_matrix.detectors, detector_map.len());
+        assert_eq!(check_matrix.columns(), model_graph.weights().len() + boundaries);
+        assert_eq!(check_matrix.boundary_edges.len(), boundaries);
+        assert_eq!(check_matrix.indptr.len(), check_matrix.columns() + 1);
+        assert_eq!(check_matrix.faults_indptr.len(), check_matrix.columns() + 1);
+        assert_eq!(check_matrix.error_probabilities.len(), check_matrix.columns());
+        assert_eq!(*check_matrix.indptr.last().unwrap(), check_matrix.indices.len());
+        assert!(check_matrix.indices.iter().all(|&row| row < check_matrix.detectors));
+        for column in 0..check_matrix.columns() {
+            let nonzeros = check_matrix.indptr[column + 1] - check_matrix.indptr[column];
+            assert_eq!(nonzeros, if check_matrix.boundary_edges.contains(&column) { 1 } else { 2 });
+        }
+        // the X and Z logical operators of the single logical qubit, each flipped by some of the boundary edges
+        assert_eq!(check_matrix.observables, 2);
+        assert!(check_matrix.faults_indices.iter().all(|&observable| observable < check_matrix.observables));
+        assert!(!check_matrix.faults_indices.is_empty());
+    }
+
+    #[test]
+    fn tool_decoding_basis() {  // cargo test tool_decoding_basis -- --nocapture
+        for (decoder, decoder_config) in [("mwpm", r#"{"emmd":24}"#), ("union-find", "{}")] {
+            let arguments = ["[5]", "[5]", "[0.01]", "-c", "rotated-planar-code", "--noise-model", "phenomenological", "--decoder", decoder
+                , "--decoder-config", decoder_config, "-p", "1", "-m", "2000", "--seed", "1"];
+            let failed = |extra_arguments: &[&str]| -> usize {
+                let result = parse_benchmark_parameters(&[&arguments[..], extra_arguments].concat()).run().unwrap();
+                println!("{}", result);
+                let values: Vec<&str> = result.lines().last().unwrap().split_whitespace().collect();
+                assert_eq!(values[3], "2000");
+                values[4].parse().unwrap()
+            };
+            // the two types of errors are decoded independently on disconnected sub-graphs, so the single-type pipeline counts the same failures
+            let z_failed = failed(&["--decoding-basis", "z-only"]);
+            let x_failed = failed(&["--decoding-basis", "x-only"]);
+            assert!(z_failed > 0 && x_failed > 0);
+            assert_eq!(z_failed, failed(&["--ignore-logical-j"]));
+            assert_eq!(x_failed, failed(&["--ignore-logical-i"]));
+            // nothing left to count
+            let error = parse_benchmark_parameters(&[&arguments[..], &["--decoding-basis", "z-only", "--ignore-logical-i"]].concat()).run().unwrap_err();
+            assert!(error.contains("decoding_basis"), "{}", error);
+        }
+        // only CSS codes and the decoders built on the model graph are supported
+        let error = parse_benchmark_parameters(&["[5]", "[0]", "[0.01]", "-c", "rotated-xzzx-code", "--decoder", "union-find", "--decoding-basis", "z-only"])
+            .run().unwrap_err();
+        assert!(error.contains("CSS"), "{}", error);
+        let error = parse_benchmark_parameters(&["[5]", "[0]", "[0.01]", "--decoder", "tailored-mwpm", "--decoding-basis", "z-only"]).run().unwrap_err();
+        assert!(error.contains("MWPM and union-find"), "{}", error);
+    }
+
+    #[test]
+    fn tool_importance_sampling() {  // cargo test tool_importance_sampling -- --nocapture
+        let arguments = ["[3]", "[0]", "[0.01]", "-p", "1", "--noise-model", "code-capacity", "--decoder", "union-find", "--seed", "1"];
+        let parse = |result: &str| -> (usize, f64, f64) {
+            let values: Vec<&str> = result.lines().last().unwrap().split_whitespace().collect();
+            let error_rate: f64 = values[5].parse().unwrap();
+            let deviation: f64 = values[7].parse().unwrap();
+            (values[3].parse().unwrap(), error_rate, error_rate * deviation / 1.96)
+        };
+        let monte_carlo = parse_benchmark_parameters(&[&arguments[..], &["-m", "50000", "-e", "100000000000"]].concat()).run().unwrap();
+        let importance = parse_benchmark_parameters(&[&arguments[..], &["--importance-sampling", "weight_shell", "--shell-shots", "2000"]].concat())
+            .run().unwrap();
+        println!("{}{}", monte_carlo, importance);
+        let (_, monte_carlo_error_rate, monte_carlo_sigma) = parse(&monte_carlo);
+        let (shots, estimate, estimate_sigma) = parse(&importance);
+        assert!(monte_carlo_error_rate > 0. && estimate > 0.);
+        assert!(shots < 50000 && shots % 2000 == 0);
+        assert!((estimate - monte_carlo_error_rate).abs() <= 3. * (monte_carlo_sigma.powi(2) + estimate_sigma.powi(2)).sqrt()
+            , "estimate {} disagrees with Monte Carlo {}", estimate, monte_carlo_error_rate);
+        // the estimate is much more precise than the direct Monte Carlo using more shots
+        assert!(estimate_sigma < monte_carlo_sigma);
+        let error = parse_benchmark_parameters(&[&arguments[..], &["--importance-sampling", "weight-shell", "--pes", "[0.01]"]].concat()).run().unwrap_err();
+        assert!(error.contains("erasure"), "{}", error);
+    }
+
+    #[test]
+    fn tool_decode_region() {  // cargo test tool_decode_region -- --nocapture
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 3, 3));
+        let mut noise_model = NoiseModel::new(&simulator);
+        NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), 0.01, 0.5, 0.);
+        let burst_t = (0..simulator.height).find(|&t| noise_model.get_node_unwrap(&pos!(t, 1, 1)).pauli_error_rates.error_rate_Z > 0.).unwrap();
+        let filepath = std::env::temp_dir().join("qecp_tool_decode_region.json").to_str().unwrap().to_string();
+        let mut sparse_error_pattern = SparseErrorPattern::new();
+        sparse_error_pattern.add(pos!(burst_t, 1, 1), ErrorType::Z);
+        sparse_error_pattern.add(pos!(burst_t, 3, 1), ErrorType::Z);
+        fs::write(&filepath, serde_json::to_string(&sparse_error_pattern).unwrap()).unwrap();
+        let arguments = ["[3]", "[2]", "[0.01]", "-p", "1", "--noise-model", "phenomenological", "-m", "10", "--fixed-error-file", &filepath];
+        // a region covering the whole volume replays the failure of the full decoding
+        for decoder in ["mwpm", "union-find"] {
+            let result = parse_benchmark_parameters(&[&arguments[..], &["--decoder", decoder, "--decoder-config", r#"{"pcmg":false}"#
+                , "--decode-region", r#"{"t":[0,100]}"#]].concat()).run().unwrap();
+            let values: Vec<&str> = result.lines().last().unwrap().split_whitespace().collect();
+            assert_eq!(values[5], "1", "{}", result);
+        }
+        let error = parse_benchmark_parameters(&[&arguments[..], &["--decoder", "mwpm", "--decode-region", r#"{"k":[0,1]}"#]].concat()).run().unwrap_err();
+        assert!(error.contains("decode region"), "{}", error);
+        let error = parse_benchmark_parameters(&[&arguments[..], &["--decoder", "tailored-mwpm", "--decode-region", "{}"]].concat()).run().unwrap_err();
+        assert!(error.contains("MWPM and union-find"), "{}", error);
+    }
+
+    #[test]
+    #[ignore]  // timing test, run in release mode
+    fn tool_decoding_basis_speedup() {  // cargo test --release tool_decoding_basis_speedup -- --ignored --nocapture
+        let arguments = ["[11]", "[11]", "[0.03]", "-c", "rotated-planar-code", "--noise-model", "phenomenological", "--decoder", "union-find"];
+        let parameters = parse_benchmark_parameters(&arguments);
+        let z_only_parameters = parse_benchmark_parameters(&[&arguments[..], &["--decoding-basis", "z-only"]].concat());
+        let configs = parameters.fill_in_default_parameters().unwrap();
+        let config = &parameters.extract_simulation_configurations(&configs)[0];
+        let mut simulator = parameters.new_simulator(config.noisy_measurements, config.di, config.dj).unwrap();
+        let noise_model = parameters.construct_noise_model(&mut simulator, &configs, config, false).unwrap();
+        let noise_model_graph = parameters.construct_noise_model(&mut simulator, &configs, config, true).unwrap();
+        let decoded_defects = z_only_parameters.decoding_basis.decoded_defects(&simulator).unwrap().unwrap();
+        simulator.rng = Xoroshiro128StarStar::seed_from_u64(1);
+        let mut shots = Vec::new();
+        for _ in 0..2000 {
+            simulator.generate_random_errors(&noise_model);
+            shots.push((simulator.generate_sparse_error_pattern(), simulator.generate_sparse_measurement()));
+        }
+        let mut decode = |parameters: &BenchmarkParameters| -> (f64, usize) {
+            let mut decoder = GeneralDecoder::from_parameters(parameters, &configs, config, &simulator, &noise_model_graph).unwrap();
+            let mut elapsed = 0.;
+            let mut z_failed = 0;
+            for (error_pattern, sparse_measurement) in shots.iter() {
+                let sparse_measurement = if parameters.decoding_basis == DecodingBasis::ZOnly {
+                    SparseMeasurement::new_set(sparse_measurement.iter().filter(|position| decoded_defects.contains(position)).cloned().collect())
+                } else { sparse_measurement.clone() };
+                let begin = Instant::now();
+                let (correction, _) = decoder.decode_with_erasure(&sparse_measurement, &SparseErasures::new());
+                elapsed += begin.elapsed().as_secs_f64();
+                simulator.clear_all_errors();
+                simulator.load_sparse_error_pattern(error_pattern, &noise_model).unwrap();
+                simulator.propagate_errors();
+                let (logical_i, _) = simulator.validate_correction(&correction);
+                z_failed += logical_i as usize;
+            }
+            (elapsed, z_failed)
+        };
+        let (both_elapsed, both_z_failed) = decode(&parameters);
+        let (z_only_elapsed, z_only_z_failed) = decode(&z_only_parameters);
+        println!("both: {both_elapsed}s, z-only: {z_only_elapsed}s, logical Z failures: {both_z_failed}");
+        assert_eq!(z_only_z_failed, both_z_failed);
+        assert!(z_only_elapsed < 0.75 * both_elapsed, "z-only decoding should take about half the time");
+    }
+
+    #[test]
+    fn tool_experiment_dir() {  // cargo test tool_experiment_dir -- --nocapture
+        let directory = std::env::temp_dir().join("qecp_tool_experiment_dir");
+        let _ = fs::remove_dir_all(&directory);
+        let experiment_dir = directory.to_str().unwrap().to_string();
+        let arguments = ["[3]", "[2]", "[0.02]", "-p", "1", "--decoder", "union-find", "--noise-model", "phenomenological", "-m", "20"
+            , "--seed", "1", "--enable-visualizer", "--experiment-dir", &experiment_dir];
+        let output = parse_benchmark_parameters(&arguments).run().unwrap();
+        let manifest_filepath = directory.join(EXPERIMENT_MANIFEST);
+        let manifest: serde_json::Value = serde_json::from_str(&fs::read_to_string(&manifest_filepath).unwrap()).unwrap();
+        println!("{}", serde_json::to_string_pretty(&manifest).unwrap());
+        assert_eq!(manifest["seed"], json!(1));
+        assert_eq!(manifest["environment"]["version"], json!(env!("CARGO_PKG_VERSION")));
+        assert_eq!(manifest["configurations"].as_array().unwrap().len(), 1);
+        // every produced file is referenced by the manifest and every referenced file exists
+        let files = manifest["files"].as_object().unwrap();
+        let mut produced: Vec<String> = fs::read_dir(&directory).unwrap().map(|entry| entry.unwrap().file_name().to_str().unwrap().to_string())
+            .filter(|filename| filename != EXPERIMENT_MANIFEST).collect();
+        produced.sort();
+        let mut referenced: Vec<String> = files.values().map(|filename| filename.as_str().unwrap().to_string()).collect();
+        referenced.sort();
+        assert_eq!(produced, referenced);
+        assert_eq!(fs::read_to_string(directory.join(EXPERIMENT_RESULTS)).unwrap(), output);
+        for filename in [EXPERIMENT_RUNTIME_STATISTICS, EXPERIMENT_VISUALIZER, EXPERIMENT_MANIFEST] {
+            validate_artifact_file(directory.join(filename).to_str().unwrap()).unwrap();
+        }
+        // summarized by `show_experiment`
+        let shown = match Cli::try_parse_from(["qecp", "tool", "show-experiment", &experiment_dir]).unwrap().command {
+            Commands::Tool { command } => command.run().unwrap(),
+            _ => unreachable!(),
+        };
+        println!("{}", shown);
+        let (summary, results) = shown.split_once('\n').unwrap();
+        let summary: serde_json::Value = serde_json::from_str(summary).unwrap();
+        assert_eq!(summary["artifact"], json!(ArtifactKind::ExperimentManifest));
+        assert_eq!(summary["configurations"], json!(1));
+        assert_eq!(results, output);
+        // refuse to overwrite the previous run unless forced
+        let error = parse_benchmark_parameters(&arguments).run().unwrap_err();
+        assert!(error.contains("--force"), "{}", error);
+        let forced = parse_benchmark_parameters(&[&arguments[..], &["--force"]].concat()).run().unwrap();
+        assert_eq!(forced, output, "the same seed reproduces the same results");
+        // a missing file invalidates the manifest
+        fs::remove_file(directory.join(EXPERIMENT_VISUALIZER)).unwrap();
+        let error = validate_artifact_file(manifest_filepath.to_str().unwrap()).unwrap_err();
+        assert!(error.starts_with("files.visualizer: "), "{}", error);
+        // the files of the previous run are cleared when forced, e.g. the visualizer of a run that had it enabled
+        parse_benchmark_parameters(&[&arguments[..], &["--force"]].concat()).run().unwrap();
+        assert!(directory.join(EXPERIMENT_VISUALIZER).exists());
+        let without_visualizer: Vec<&str> = arguments.iter().cloned().filter(|argument| *argument != "--enable-visualizer").collect();
+        parse_benchmark_parameters(&[&without_visualizer[..], &["--force"]].concat()).run().unwrap();
+        assert!(!directory.join(EXPERIMENT_VISUALIZER).exists());
+        validate_artifact_file(manifest_filepath.to_str().unwrap()).unwrap();
+    }
+
+
+    #[test]
+    fn tool_visualize_failures_movie() {  // cargo test tool_visualize_failures_movie -- --nocapture
+        let visualizer_filepath = std::env::temp_dir().join("qecp_tool_visualize_failures_movie.json").to_str().unwrap().to_string();
+        let max_movies = 3;
+        // near the threshold of phenomenological noise, so that enough shots fail
+        let result = parse_benchmark_parameters(&["[5]", "[5]", "[0.03]", "-p", "1", "--decoder", "union-find", "--noise-model", "phenomenological"
+            , "-m", "200", "-e", "100000000000", "--seed", "1", "--enable-visualizer", "--visualizer-filename", &visualizer_filepath
+            , "--visualize-failures-movie", &max_movies.to_string()]).run().unwrap();
+        let values: Vec<&str> = result.lines().last().unwrap().split_whitespace().collect();
+        let qec_failed: usize = values[4].parse().unwrap();
+        assert!(qec_failed > max_movies, "{}", result);
+        let data: serde_json::Value = serde_json::from_str(&fs::read_to_string(&visualizer_filepath).unwrap()).unwrap();
+        let cases = &data["cases"].as_array().unwrap()[1..];  // the first case is an empty placeholder
+        assert_eq!(cases.len(), max_movies);
+        for case in cases.iter() {
+            assert!(case["qec_failed"].as_bool().unwrap());
+            let frames = case["frames"].as_array().unwrap();
+            assert_eq!(frames.len(), 5 + 2);  // the perfect round at t = 0, the noisy rounds and the final perfect round
+            let mut errors = serde_json::Map::new();
+            let mut defects = Vec::new();
+            let mut previous_defects: BTreeSet<(usize, usize)> = BTreeSet::new();
+            for (round, frame) in frames.iter().enumerate() {
+                assert_eq!(frame["round"], json!(round));
+                errors.extend(frame["errors"].as_object().unwrap().clone());
+                let frame_defects: Vec<Position> = serde_json::from_value(frame["defects"].clone()).unwrap();
+                let appeared: Vec<Position> = serde_json::from_value(frame["appeared"].clone()).unwrap();
+                let disappeared: Vec<Position> = serde_json::from_value(frame["disappeared"].clone()).unwrap();
+                let ancillas: BTreeSet<(usize, usize)> = frame_defects.iter().map(|position| (position.i, position.j)).collect();
+                assert_eq!(appeared.len(), ancillas.difference(&previous_defects).count());
+                assert_eq!(disappeared.len(), previous_defects.difference(&ancillas).count());
+                defects.extend(frame_defects.into_iter().map(|position| json!(position)));
+                previous_defects = ancillas;
+            }
+            assert_eq!(json!(errors), case["error_pattern"]);
+            assert_eq!(json!(defects), case["measurement"]);
+            let last_frame = frames.last().unwrap();
+            assert_eq!(last_frame["correction"], case["correction"]);
+            assert_eq!(last_frame["residual"], case["residual"]);
+            assert!(case["residual"].as_object().unwrap().len() >= 5, "a logical error spans at least d qubits");
+        }
+        // the flag only makes sense together with the visualizer
+        assert!(Cli::try_parse_from(["qecp", "tool", "benchmark", "[5]", "[5]", "[0.03]", "--visualize-failures-movie", "3"]).is_err());
+    }
+
+
+    #[test]
+    fn tool_select_auto_decoder() {  // cargo test tool_select_auto_decoder -- --nocapture
+        // the cheaper decoder is kept when it is within the factor of the more accurate one
+        assert_eq!(select_auto_decoder(&[0.0105, 0.01], 1.1), 0);
+        assert_eq!(select_auto_decoder(&[0.01, 0.0105], 1.1), 0);
+        assert_eq!(select_auto_decoder(&[0.012, 0.01], 1.1), 1);
+        assert_eq!(select_auto_decoder(&[0.012, 0.01], 1.5), 0);
+        assert_eq!(select_auto_decoder(&[0.3, 0.2, 0.21], 1.1), 1);
+        assert_eq!(select_auto_decoder(&[0.3, 0.22, 0.21], 1.1), 1);
+        // without any failure the cheapest decoder is picked, but a single failure is already infinitely worse than none
+        assert_eq!(select_auto_decoder(&[0., 0.], 1.1), 0);
+        assert_eq!(select_auto_decoder(&[0.001, 0.], 1.1), 1);
+    }
+
+    #[test]
+    fn tool_auto_decoder() {  // cargo test tool_auto_decoder -- --nocapture
+        // union-find is as good as MWPM far below the threshold of circuit-level noise but clearly worse near the threshold
+        let result = parse_benchmark_parameters(&["[3]", "[3]", "[0.0002,0.01]", "-p", "1", "--noise-model", "depolarizing-noise", "--decoder", "auto"
+            , "--decoder-config", r#"{"mwpm":{"emmd":24}}"#, "-m", "100", "--auto-calibration-shots", "2000", "--seed", "1"]).run().unwrap();
+        assert!(result.lines().next().unwrap().ends_with(" <pe> <auto_decoder>"), "{}", result);
+        let selected: Vec<String> = result.lines().skip(1).map(|line| {
+            assert!(!line.ends_with(' '), "{:?}", line);
+            let selection: serde_json::Value = serde_json::from_str(line.split_whitespace().last().unwrap()).unwrap();
+            assert_eq!(selection["calibration_shots"], json!(2000));
+            assert_eq!(selection["calibration"].as_array().unwrap().len(), AUTO_DECODER_CANDIDATES.len());
+            selection["decoder"].as_str().unwrap().to_string()
+        }).collect();
+        assert_eq!(selected, vec!["union-find", "mwpm"], "{}", result);
+        // the configuration is given per candidate
+        let error = parse_benchmark_parameters(&["[3]", "[3]", "[0.01]", "--decoder", "auto", "--decoder-config", r#"{"tailored-mwpm":{}}"#]).run().unwrap_err();
+        assert!(error.contains("not a candidate"), "{}", error);
+    }
+
+
+    #[test]
+    fn tool_plan() {  // cargo test tool_plan -- --nocapture
+        let filepath = std::env::temp_dir().join("qecp_tool_plan.txt").to_str().unwrap().to_string();
+        parse_benchmark_parameters(&["[3,5]", "[0,1]", "[0.01,0.02]", "-p", "1", "--decoder", "union-find", "-m", "20", "-e", "7"
+            , "--time-budget", "30", "--noise-model", "phenomenological", "--noise-model-configuration", r#"{"measurement_error_rate":{"sweep":[0.01,0.02,0.03]}}"#
+            , "--log-runtime-statistics", &filepath]).run().unwrap();
+        let plan = |arguments: &[&str]| -> Vec<serde_json::Value> {
+            let output = match Cli::try_parse_from([&["qecp", "tool", "plan"], arguments].concat()).unwrap().command {
+                Commands::Tool { command } => command.run().unwrap(),
+                _ => unreachable!(),
+            };
+            output.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+        };
+        let lines = plan(&["--config-file", &filepath]);
+        // 2 code sizes, 2 physical error rates and 3 swept measurement error rates
+        let (configurations, summary) = lines.split_at(lines.len() - 1);
+        assert_eq!(configurations.len(), 2 * 2 * 3);
+        let mut swept = BTreeSet::new();
+        for configuration in configurations.iter() {
+            assert_eq!(configuration["max_repeats"], json!(20));
+            assert_eq!(configuration["min_failed_cases"], json!(7));
+            assert_eq!(configuration["time_budget"], json!(30.));
+            let config = &configuration["config"];
+            swept.insert(format!("{} {} {} {}", config["di"], config["noisy_measurements"], config["p"], config["sweep"]["measurement_error_rate"]));
+        }
+        assert_eq!(swept.len(), configurations.len(), "each configuration appears once");
+        assert_eq!(summary[0]["configurations"], json!(12));
+        assert_eq!(summary[0]["total_max_repeats"], json!(20 * 12));
+        assert_eq!(summary[0]["total_volume"], json!(20 * 2 * 3 * (3 * 3 + 5 * 5 * 2)), "the spacetime volume of d = 3 with 1 round and d = 5 with 2 rounds");
+        assert_eq!(summary[0]["total_time_budget"], json!(30. * 12.));
+        // unlimited budgets
+        let lines = plan(&["[3]", "[0]", "[0.01]", "-m", "0", "-e", "0"]);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["max_repeats"], json!(null));
+        assert_eq!(lines[0]["min_failed_cases"], json!(null));
+        assert_eq!(lines[1]["total_max_repeats"], json!(null));
+        assert_eq!(lines[1]["total_volume"], json!(null));
+        assert_eq!(lines[1]["total_time_budget"], json!(null));
     }
 
 }
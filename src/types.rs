@@ -28,6 +28,19 @@ impl QubitType {
     }
 }
 
+/// the type of the code boundary a data qubit sits on, following the convention that the X stabilizers are the vertex operators:
+/// chains of Z errors terminate at a rough boundary and chains of X errors terminate at a smooth boundary
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "python_binding", pyclass)]
+pub enum BoundaryType {
+    /// next to a virtual X stabilizer
+    Rough,
+    /// next to a virtual Z stabilizer
+    Smooth,
+    /// next to both virtual X and Z stabilizers, where a rough and a smooth boundary meet
+    Corner,
+}
+
 /// Error type, corresponds to `ETYPE` in `FaultTolerantView.vue`
 #[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -80,6 +93,11 @@ impl ErrorType {
             (Self::Y, Self::Y) => Self::I,
         }
     }
+    /// symplectic inner product of two single-qubit Pauli operators: whether they anticommute
+    #[inline]
+    pub fn anticommute(&self, other: &Self) -> bool {
+        *self != Self::I && *other != Self::I && self != other
+    }
     //#[staticmethod]
     pub fn all_possible_errors() -> Vec::<Self> {
         vec![Self::X, Self::Z, Self::Y]
@@ -97,6 +115,23 @@ impl ErrorType {
     }
 }
 
+/// logical Pauli operator of a code encoding a single logical qubit
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "python_binding", pyclass)]
+pub enum LogicalOp {
+    /// flips the logical state, reported as `logical_j` in correction validation
+    X,
+    /// flips the logical phase, reported as `logical_i` in correction validation
+    Z,
+}
+
+/// error of a decoder that fails to return a correction
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum DecodingError {
+    /// the decoder is preempted because the deadline of this shot has passed
+    Timeout,
+}
+
 /// Correlated error type for two qubit errors
 #[allow(dead_code)]
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -181,6 +216,14 @@ impl PauliErrorRates {
     pub fn no_error_probability(&self) -> f64 {
         1. - self.error_probability()
     }
+    /// all the error rates multiplied by `factor`
+    pub fn scaled(&self, factor: f64) -> Self {
+        Self {
+            error_rate_X: self.error_rate_X * factor,
+            error_rate_Z: self.error_rate_Z * factor,
+            error_rate_Y: self.error_rate_Y * factor,
+        }
+    }
     pub fn error_rate(&self, error_type: &ErrorType) -> f64 {
         match error_type {
             ErrorType::I => self.no_error_probability(),
@@ -258,6 +301,26 @@ impl CorrelatedPauliErrorRates {
     pub fn no_error_probability(&self) -> f64 {
         1. - self.error_probability()
     }
+    /// all the error rates multiplied by `factor`
+    pub fn scaled(&self, factor: f64) -> Self {
+        Self {
+            error_rate_IX: self.error_rate_IX * factor,
+            error_rate_IZ: self.error_rate_IZ * factor,
+            error_rate_IY: self.error_rate_IY * factor,
+            error_rate_XI: self.error_rate_XI * factor,
+            error_rate_XX: self.error_rate_XX * factor,
+            error_rate_XZ: self.error_rate_XZ * factor,
+            error_rate_XY: self.error_rate_XY * factor,
+            error_rate_ZI: self.error_rate_ZI * factor,
+            error_rate_ZX: self.error_rate_ZX * factor,
+            error_rate_ZZ: self.error_rate_ZZ * factor,
+            error_rate_ZY: self.error_rate_ZY * factor,
+            error_rate_YI: self.error_rate_YI * factor,
+            error_rate_YX: self.error_rate_YX * factor,
+            error_rate_YZ: self.error_rate_YZ * factor,
+            error_rate_YY: self.error_rate_YY * factor,
+        }
+    }
     pub fn error_rate(&self, error_type: &CorrelatedPauliErrorType) -> f64 {
         match error_type {
             CorrelatedPauliErrorType::II => self.no_error_probability(),
@@ -372,6 +435,14 @@ impl CorrelatedErasureErrorRates {
     pub fn no_error_probability(&self) -> f64 {
         1. - self.error_probability()
     }
+    /// all the error rates multiplied by `factor`
+    pub fn scaled(&self, factor: f64) -> Self {
+        Self {
+            error_rate_IE: self.error_rate_IE * factor,
+            error_rate_EI: self.error_rate_EI * factor,
+            error_rate_EE: self.error_rate_EE * factor,
+        }
+    }
     // pub fn error_rate(&self, error_type: &CorrelatedErasureErrorType) -> f64 {
     //     match error_type {
     //         CorrelatedErasureErrorType::II => self.no_error_probability(),
@@ -473,5 +544,7 @@ impl std::fmt::Display for NoiseModelName {
 pub(crate) fn register(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<ErrorType>()?;
     m.add_class::<QubitType>()?;
+    m.add_class::<BoundaryType>()?;
+    m.add_class::<LogicalOp>()?;
     Ok(())
 }
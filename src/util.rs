@@ -6,6 +6,10 @@ use super::lazy_static::lazy_static;
 use std::sync::{RwLock};
 use std::collections::{BTreeMap};
 use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
 #[cfg(feature="python_binding")]
 use pyo3::prelude::*;
 
@@ -168,6 +172,22 @@ pub fn local_put_temporary_store(value: String) -> Option<usize> {
     Some(insert_key)
 }
 
+/// the git commit of the source tree this binary was built from, captured by the build script; None if it was not built from a git repository
+pub fn git_hash() -> Option<String> {
+    option_env!("QECP_GIT_HASH").map(|hash| hash.to_string())
+}
+
+/// brief information of the machine running the simulation, recorded with the results so that timing can be interpreted later
+pub fn host_information() -> serde_json::Value {
+    let hostname = std::env::var("HOSTNAME").ok().or_else(|| fs::read_to_string("/etc/hostname").ok().map(|hostname| hostname.trim().to_string()));
+    json!({
+        "hostname": hostname,
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "cpus": num_cpus::get(),
+    })
+}
+
 /**
  * If you want to modify a field of a Rust struct, it will return a copy of it to avoid memory unsafety.
  * Thus, typical way of modifying a python field doesn't work, e.g. `obj.a.b.c = 1` won't actually modify `obj`.
@@ -218,6 +238,82 @@ impl PyMut {
     }
 }
 
+/// push JSON lines to a consumer listening on a Unix domain socket without ever blocking the caller, e.g. a live dashboard of a long benchmark;
+/// a message is dropped if the consumer is too slow to receive the previous one, or if it has disconnected
+#[cfg(unix)]
+pub struct ResultStream {
+    stream: UnixStream,
+    /// the unsent remainder of the last message
+    pending: Vec<u8>,
+    /// the consumer has closed the connection, all the following messages are dropped
+    disconnected: bool,
+    /// the number of dropped messages
+    pub dropped: usize,
+}
+
+#[cfg(unix)]
+impl ResultStream {
+    pub fn connect(path: &str) -> Result<Self, String> {
+        let stream = UnixStream::connect(path).map_err(|e| format!("[error] cannot connect to stream socket {}: {}", path, e))?;
+        stream.set_nonblocking(true).map_err(|e| format!("[error] cannot set stream socket {} to non-blocking: {}", path, e))?;
+        Ok(Self {
+            stream,
+            pending: Vec::new(),
+            disconnected: false,
+            dropped: 0,
+        })
+    }
+
+    /// try to send the remainder of the last message, return whether it has been fully sent
+    fn flush_pending(&mut self) -> bool {
+        while !self.pending.is_empty() {
+            match self.stream.write(&self.pending) {
+                Ok(0) => return false,
+                Ok(written) => { self.pending.drain(..written); },
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return false,
+                Err(_) => {
+                    self.pending.clear();
+                    self.disconnected = true;
+                    return false
+                },
+            }
+        }
+        true
+    }
+
+    /// send a message as a single line, or drop it if the socket is not ready
+    pub fn send(&mut self, message: &serde_json::Value) {
+        if self.disconnected || !self.flush_pending() {
+            self.dropped += 1;
+            return
+        }
+        self.pending = format!("{}\n", message).into_bytes();
+        if !self.flush_pending() && self.disconnected {
+            self.dropped += 1;
+        }
+    }
+
+    /// finish sending the partially written last message, waiting for the consumer up to `timeout`; it's dropped if that fails
+    pub fn close(&mut self, timeout: std::time::Duration) {
+        if self.disconnected || self.pending.is_empty() {
+            return
+        }
+        let finished = self.stream.set_nonblocking(false).is_ok() && self.stream.set_write_timeout(Some(timeout)).is_ok()
+            && self.stream.write_all(&self.pending).is_ok();
+        if !finished {
+            self.dropped += 1;
+        }
+        self.pending.clear();
+    }
+}
+
+#[cfg(unix)]
+impl Drop for ResultStream {
+    fn drop(&mut self) {
+        self.close(std::time::Duration::from_secs(1));
+    }
+}
+
 #[cfg(feature="python_binding")]
 pub fn json_to_pyobject_locked<'py>(value: serde_json::Value, py: Python<'py>) -> PyObject {
     match value {
@@ -312,4 +408,28 @@ mod tests {
         assert_eq!(read_1, Some(format!("hello")));
         assert_eq!(read_2, Some(format!("world")));
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn util_result_stream_close() {  // cargo test util_result_stream_close -- --nocapture
+        use std::os::unix::net::UnixListener;
+        use std::io::Read;
+        let path = std::env::temp_dir().join(format!("qecp_util_result_stream_close_{}.sock", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let mut result_stream = ResultStream::connect(path.to_str().unwrap()).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        // a message much larger than the socket buffer is only partially written without blocking
+        let message = json!("x".repeat(1 << 22));
+        result_stream.send(&message);
+        assert!(!result_stream.pending.is_empty());
+        let reader = std::thread::spawn(move || {
+            let mut received = String::new();
+            stream.read_to_string(&mut received).unwrap();
+            received
+        });
+        drop(result_stream);  // the rest of the message is flushed when the stream is dropped
+        assert_eq!(reader.join().unwrap(), format!("{}\n", message));
+        fs::remove_file(&path).unwrap();
+    }
 }
@@ -12,6 +12,8 @@ use crate::urlencoding;
 #[cfg(feature="python_binding")]
 use pyo3::prelude::*;
 use std::collections::BTreeSet;
+use crate::simulator::{SparseErrorPattern, SparseMeasurement, SparseCorrection};
+use crate::artifact::ArtifactKind;
 
 
 pub trait QecpVisualizer {
@@ -75,7 +77,8 @@ impl Visualizer {
         if let Some(file) = file.as_mut() {
             file.set_len(0)?;  // truncate the file
             file.seek(SeekFrom::Start(0))?;  // move the cursor to the front
-            file.write_all(format!("{{\"format\":\"qecp\",\"version\":\"{}\"}}", env!("CARGO_PKG_VERSION")).as_bytes())?;
+            file.write_all(format!("{{\"format\":\"qecp\",\"version\":\"{}\",\"artifact\":\"{}\",\"schema_version\":{}}}", env!("CARGO_PKG_VERSION")
+                , ArtifactKind::Visualizer.name(), ArtifactKind::Visualizer.schema_version()).as_bytes())?;
             file.sync_all()?;
         }
         Ok(Self {
@@ -165,6 +168,34 @@ impl Visualizer {
         Ok(())
     }
 
+    /// read a component (e.g. "simulator") from a visualizer file, which can be used to rebuild the object in a new session
+    pub fn load_component(filepath: &str, name: &str) -> Result<serde_json::Value, String> {
+        let data = Self::load_file(filepath)?;
+        data.get(name).cloned().ok_or(format!("component {} not found in {}", name, filepath))
+    }
+
+    /// read the error pattern, the measurement and (if recorded) the correction of a case from a visualizer file;
+    /// note that `index` counts the empty case inserted when the components end, consistent with the case index in the frontend
+    pub fn load_case(filepath: &str, index: usize) -> Result<(SparseErrorPattern, SparseMeasurement, Option<SparseCorrection>), String> {
+        let data = Self::load_file(filepath)?;
+        let cases = data.get("cases").and_then(|cases| cases.as_array()).ok_or("missing field: cases".to_string())?;
+        let case = cases.get(index).ok_or(format!("case {} out of range, there are {} cases", index, cases.len()))?;
+        let error_pattern = serde_json::from_value(case.get("error_pattern").cloned().unwrap_or(json!({}))).map_err(|e| format!("{:?}", e))?;
+        let measurement = serde_json::from_value(case.get("measurement").cloned().unwrap_or(json!([]))).map_err(|e| format!("{:?}", e))?;
+        let correction = match case.get("correction") {
+            Some(correction) if !correction.is_null() => Some(serde_json::from_value(correction.clone()).map_err(|e| format!("{:?}", e))?),
+            _ => None,
+        };
+        Ok((error_pattern, measurement, correction))
+    }
+
+    fn load_file(filepath: &str) -> Result<serde_json::Value, String> {
+        let content = std::fs::read_to_string(filepath).map_err(|e| format!("cannot read {}: {:?}", filepath, e))?;
+        let data = serde_json::from_str(&content).map_err(|e| format!("cannot parse {}: {:?}", filepath, e))?;
+        ArtifactKind::Visualizer.check_envelope(&data).map_err(|e| format!("cannot load {}: {}", filepath, e))?;
+        Ok(data)
+    }
+
 }
 
 impl Drop for Visualizer {
@@ -4,6 +4,12 @@ use clap::FromArgMatches;
 use crate::serde::Deserialize;
 use crate::actix_web::{web, App, HttpServer, HttpRequest, HttpResponse, Error};
 use super::util::{local_get_temporary_store, local_put_temporary_store, TEMPORARY_STORE};
+use super::simulator::*;
+use super::noise_model::*;
+use super::code_builder::*;
+use super::types::*;
+use super::util_macros::*;
+use super::decoder_mwpm::*;
 
 
 pub const TEMPORARY_STORE_SIZE_LIMIT: usize = 10_000_000;  // 10MB, only applicable to web service
@@ -18,6 +24,8 @@ pub async fn run_server(port: i32, addr: String, root_url: String) -> std::io::R
                     .service(web::resource("hello").route(web::get().to(get_hello)))
                     .service(web::resource("version").route(web::get().to(get_version)))
                     .service(web::resource("view_noise_model").route(web::get().to(view_noise_model)))
+                    .service(web::resource("error_model_2d").route(web::get().to(error_model_2d)))
+                    .service(web::resource("decode_windowed").route(web::post().to(decode_windowed)))
                     .service(web::resource("new_temporary_store").route(web::post().to(new_temporary_store)))
                     .service(web::resource("get_temporary_store/{resource_id}").route(web::get().to(get_temporary_store)))
             )
@@ -102,6 +110,236 @@ async fn view_noise_model(info: web::Query<ViewNoiseModelQuery>) -> Result<HttpR
     Ok(HttpResponse::Ok().body(output))
 }
 
+fn default_code_distance() -> usize {
+    5
+}
+
+fn default_bias_eta() -> f64 {
+    0.5
+}
+
+#[derive(Deserialize)]
+struct ErrorModel2DQuery {
+    #[serde(default = "default_code_distance")]
+    di: usize,
+    /// default to `di`
+    dj: Option<usize>,
+    /// default to `di`
+    noisy_measurements: Option<usize>,
+    /// code type, e.g. `rotated-planar-code`
+    code_type: Option<String>,
+    /// noise model builder, e.g. `phenomenological`
+    error_model: Option<String>,
+    #[serde(default = "default_probability")]
+    p: f64,
+    #[serde(default = "default_probability")]
+    pe: f64,
+    #[serde(default = "default_bias_eta")]
+    bias_eta: f64,
+    #[serde(default = "default_parameters")]
+    parameters: String,
+    #[serde(default = "default_resource_id")]
+    noise_model_temporary_id: usize,
+}
+
+/// build the simulator and noise model the same way as `tool benchmark`, and then aggregate the error rates along the time axis;
+/// this gives the 2D noise model viewer a compact grid instead of the full 3D noise model
+async fn error_model_2d(info: web::Query<ErrorModel2DQuery>) -> Result<HttpResponse, Error> {
+    let di = info.di;
+    let dj = info.dj.unwrap_or(di);
+    let noisy_measurements = info.noisy_measurements.unwrap_or(di);
+    let mut tokens = vec![format!("qecp"), format!("tool"), format!("benchmark")
+        , format!("[{}]", di), format!("--djs"), format!("[{}]", dj), format!("[{}]", noisy_measurements)
+        , format!("[{}]", info.p), format!("--pes"), format!("[{}]", info.pe), format!("--bias-eta"), format!("{}", info.bias_eta)];
+    if let Some(code_type) = &info.code_type {
+        tokens.push(format!("--code-type"));
+        tokens.push(code_type.clone());
+    }
+    if let Some(error_model) = &info.error_model {
+        tokens.push(format!("--noise-model-builder"));
+        tokens.push(error_model.clone());
+    }
+    let temporary_store = TEMPORARY_STORE.read().unwrap();  // hold the reader lock until the noise model is constructed
+    if info.noise_model_temporary_id > 0 {
+        if local_get_temporary_store(info.noise_model_temporary_id).is_none() {
+            return Ok(HttpResponse::NotFound().body(format!("noise_model_temporary_id={} not found, might be expired", info.noise_model_temporary_id)))
+        }
+        tokens.push(format!("--load-noise-model-from-temporary-store"));
+        tokens.push(format!("{}", info.noise_model_temporary_id));
+    }
+    tokens.append(&mut match crate::shlex::split(&info.parameters) {
+        Some(t) => t,
+        None => {
+            return Ok(HttpResponse::BadRequest().body(format!("building tokens from parameters failed")))
+        }
+    });
+    let parameters = match parse_benchmark_parameters(tokens) {
+        Ok(parameters) => parameters,
+        Err(error) => { return Ok(HttpResponse::BadRequest().body(error)) }
+    };
+    let build_result = (|| -> Result<serde_json::Value, String> {
+        let configs = parameters.fill_in_default_parameters()?;
+        let configurations = parameters.extract_simulation_configurations(&configs);
+        parameters.assert_single_configuration(&configs)?;
+        let mut simulator = Simulator::new(parameters.code_type, CodeSize::new(noisy_measurements, di, dj));
+        let noise_model = parameters.construct_noise_model(&mut simulator, &configs, &configurations[0], false)?;
+        Ok(noise_model_2d_summary(&simulator, &noise_model))
+    })();
+    drop(temporary_store);
+    match build_result {
+        Ok(summary) => Ok(HttpResponse::Ok().json(summary)),
+        Err(error) => Ok(HttpResponse::BadRequest().body(error)),
+    }
+}
+
+/// parse the command line tokens of `tool benchmark`
+fn parse_benchmark_parameters(tokens: Vec<String>) -> Result<crate::cli::BenchmarkParameters, String> {
+    use crate::clap::CommandFactory;
+    use crate::cli::*;
+    let matches = Cli::command().color(clap::ColorChoice::Never).try_get_matches_from(tokens).map_err(|error| format!("{:?}", error))?;
+    let cli = Cli::from_arg_matches(&matches).map_err(|error| format!("{:?}", error))?;
+    match cli.command {
+        Commands::Tool { command: ToolCommands::Benchmark(parameters) } => Ok(parameters),
+        _ => unreachable!()  // forbid the web to access other commands
+    }
+}
+
+fn default_decoder_config() -> serde_json::Value {
+    json!({})
+}
+
+#[derive(Deserialize)]
+struct DecodeWindowedBody {
+    #[serde(default = "default_code_distance")]
+    di: usize,
+    /// default to `di`
+    dj: Option<usize>,
+    /// default to `di`
+    noisy_measurements: Option<usize>,
+    /// code type, e.g. `rotated-planar-code`
+    code_type: Option<String>,
+    /// noise model builder, e.g. `phenomenological`
+    error_model: Option<String>,
+    #[serde(default = "default_probability")]
+    p: f64,
+    #[serde(default = "default_bias_eta")]
+    bias_eta: f64,
+    #[serde(default = "default_parameters")]
+    parameters: String,
+    /// MWPM decoder configuration
+    #[serde(default = "default_decoder_config")]
+    decoder_config: serde_json::Value,
+    /// the defect measurements of the whole volume
+    measurement: SparseMeasurement,
+    /// number of measurement rounds from the beginning of each committed region to the end of the buffer above it; the same `window - commit`
+    /// buffer rounds are also kept below the committed region, so each window spans up to `2 * window - commit` measurement rounds
+    window: usize,
+    /// number of measurement rounds committed in each window, at least 1 and at most `window`
+    commit: usize,
+    /// number of threads decoding the independent windows
+    #[serde(default = "default_parallel")]
+    parallel: usize,
+}
+
+fn default_parallel() -> usize {
+    1
+}
+
+/// decode a long syndrome with overlapping windows using the MWPM decoder, where the decoding graph is built the same way as `tool benchmark`
+async fn decode_windowed(body: web::Json<DecodeWindowedBody>) -> Result<HttpResponse, Error> {
+    let di = body.di;
+    let dj = body.dj.unwrap_or(di);
+    let noisy_measurements = body.noisy_measurements.unwrap_or(di);
+    if body.commit == 0 || body.commit > body.window {
+        return Ok(HttpResponse::BadRequest().body(format!("require 1 <= commit <= window")))
+    }
+    if body.parallel == 0 {
+        return Ok(HttpResponse::BadRequest().body(format!("require parallel >= 1")))
+    }
+    let mut tokens = vec![format!("qecp"), format!("tool"), format!("benchmark")
+        , format!("[{}]", di), format!("--djs"), format!("[{}]", dj), format!("[{}]", noisy_measurements)
+        , format!("[{}]", body.p), format!("--bias-eta"), format!("{}", body.bias_eta)];
+    if let Some(code_type) = &body.code_type {
+        tokens.push(format!("--code-type"));
+        tokens.push(code_type.clone());
+    }
+    if let Some(error_model) = &body.error_model {
+        tokens.push(format!("--noise-model-builder"));
+        tokens.push(error_model.clone());
+    }
+    tokens.append(&mut match crate::shlex::split(&body.parameters) {
+        Some(t) => t,
+        None => {
+            return Ok(HttpResponse::BadRequest().body(format!("building tokens from parameters failed")))
+        }
+    });
+    let parameters = match parse_benchmark_parameters(tokens) {
+        Ok(parameters) => parameters,
+        Err(error) => { return Ok(HttpResponse::BadRequest().body(error)) }
+    };
+    let decode_result = (|| -> Result<serde_json::Value, String> {
+        let configs = parameters.fill_in_default_parameters()?;
+        let configurations = parameters.extract_simulation_configurations(&configs);
+        parameters.assert_single_configuration(&configs)?;
+        let mut simulator = Simulator::new(parameters.code_type, CodeSize::new(noisy_measurements, di, dj));
+        let noise_model = parameters.construct_noise_model(&mut simulator, &configs, &configurations[0], false)?;
+        for position in body.measurement.iter() {
+            if !simulator.is_node_exist(position) || simulator.get_node_unwrap(position).gate_type.is_measurement() == false {
+                return Err(format!("invalid defect measurement at position {}", position))
+            }
+        }
+        let decoder_config: MWPMDecoderConfig = serde_json::from_value(body.decoder_config.clone()).map_err(|error| format!("{:?}", error))?;
+        let mut mwpm_decoder = MWPMDecoder::new(&simulator, noise_model, &json!(decoder_config), 1, false);
+        let (correction, runtime_statistics) = mwpm_decoder.decode_windowed(&body.measurement, body.window, body.commit, body.parallel);
+        Ok(json!({
+            "correction": correction,
+            "runtime_statistics": runtime_statistics,
+        }))
+    })();
+    match decode_result {
+        Ok(result) => Ok(HttpResponse::Ok().json(result)),
+        Err(error) => Ok(HttpResponse::BadRequest().body(error)),
+    }
+}
+
+/// aggregate the noise model along the time axis: each (i, j) reports the probability that at least one error happens at any t;
+/// data qubits and ancilla qubits are reported in separate grids, and the correlated two-qubit errors are listed as gate pairs
+pub fn noise_model_2d_summary(simulator: &Simulator, noise_model: &NoiseModel) -> serde_json::Value {
+    let mut data: Vec<Vec<Option<f64>>> = vec![vec![None; simulator.horizontal]; simulator.vertical];
+    let mut ancilla: Vec<Vec<Option<f64>>> = vec![vec![None; simulator.horizontal]; simulator.vertical];
+    let mut correlated_pairs = Vec::new();
+    simulator_iter_real!(simulator, position, node, {
+        let noise_model_node = noise_model.get_node_unwrap(position);
+        let mut no_error_probability = (1. - noise_model_node.pauli_error_rates.error_probability()) * (1. - noise_model_node.erasure_error_rate);
+        let mut correlated_probability = 0.;
+        if let Some(correlated_pauli_error_rates) = &noise_model_node.correlated_pauli_error_rates {
+            correlated_probability += correlated_pauli_error_rates.error_probability();
+        }
+        if let Some(correlated_erasure_error_rates) = &noise_model_node.correlated_erasure_error_rates {
+            correlated_probability += correlated_erasure_error_rates.error_probability();
+        }
+        if correlated_probability > 0. {
+            no_error_probability *= 1. - correlated_probability;
+            correlated_pairs.push(json!({
+                "position": position,
+                "peer": node.gate_peer,
+                "p": correlated_probability,
+            }));
+        }
+        let grid = if node.qubit_type == QubitType::Data { &mut data } else { &mut ancilla };
+        let aggregated = grid[position.i][position.j].get_or_insert(0.);
+        *aggregated = 1. - (1. - *aggregated) * no_error_probability;
+    });
+    json!({
+        "code_type": simulator.code_type,
+        "vertical": simulator.vertical,
+        "horizontal": simulator.horizontal,
+        "data": data,
+        "ancilla": ancilla,
+        "correlated_pairs": correlated_pairs,
+    })
+}
+
 #[derive(Deserialize)]
 struct NewTemporaryStore {
     value: String,
@@ -152,4 +390,71 @@ mod tests {
         assert_eq!(read_2, Some(format!("world")));
     }
 
+    #[actix_web::test]
+    async fn web_error_model_2d() {  // cargo test web_error_model_2d -- --nocapture
+        use actix_web::test;
+        let app = test::init_service(App::new()
+            .service(web::resource("error_model_2d").route(web::get().to(error_model_2d)))).await;
+        let request = test::TestRequest::get().uri("/error_model_2d?di=3&noisy_measurements=2&p=0.01&error_model=phenomenological").to_request();
+        let response: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        let simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(2, 3, 3));
+        for grid_name in ["data", "ancilla"] {
+            let grid = response[grid_name].as_array().unwrap();
+            assert_eq!(grid.len(), simulator.vertical);
+            for row in grid.iter() {
+                let row = row.as_array().unwrap();
+                assert_eq!(row.len(), simulator.horizontal);
+                for value in row.iter().filter(|value| !value.is_null()) {
+                    let value = value.as_f64().unwrap();
+                    assert!(value >= 0. && value <= 1.);
+                }
+            }
+        }
+        // phenomenological noise model has both data errors and measurement errors, but no correlated errors
+        assert!(response["data"][1][1].as_f64().unwrap() > 0.);
+        assert!(response["ancilla"][1][2].as_f64().unwrap() > 0.);
+        assert_eq!(response["correlated_pairs"].as_array().unwrap().len(), 0);
+    }
+
+    #[actix_web::test]
+    async fn web_decode_windowed() {  // cargo test web_decode_windowed -- --nocapture
+        use actix_web::test;
+        use crate::reproducible_rand::Xoroshiro128StarStar;
+        use crate::rand_core::SeedableRng;
+        let app = test::init_service(App::new()
+            .service(web::resource("decode_windowed").route(web::post().to(decode_windowed)))).await;
+        let (d, noisy_measurements, p) = (3, 6, 0.005);
+        let decoder_config = json!({"emmd": 16});  // avoid requiring blossom V library
+        let mut simulator = Simulator::new(CodeType::StandardPlanarCode, CodeSize::new(noisy_measurements, d, d));
+        let mut noise_model = NoiseModel::new(&simulator);
+        crate::noise_model_builder::NoiseModelBuilder::Phenomenological.apply(&mut simulator, &mut noise_model, &json!({}), p, 0.5, 0.);
+        simulator.compress_error_rates(&mut noise_model);
+        let noise_model = std::sync::Arc::new(noise_model);
+        let mut mwpm_decoder = MWPMDecoder::new(&simulator, noise_model.clone(), &decoder_config, 1, false);
+        simulator.rng = Xoroshiro128StarStar::seed_from_u64(1711);
+        let mut agreed = 0;
+        let repeats = 20;
+        for _ in 0..repeats {
+            simulator.generate_random_errors(&noise_model);
+            let sparse_measurement = simulator.generate_sparse_measurement();
+            if sparse_measurement.len() > 16 {
+                continue
+            }
+            let request = test::TestRequest::post().uri("/decode_windowed").set_json(json!({
+                "di": d, "noisy_measurements": noisy_measurements, "p": p, "error_model": "phenomenological",
+                "decoder_config": decoder_config, "measurement": sparse_measurement, "window": 3, "commit": 1, "parallel": 2,
+            })).to_request();
+            let response: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+            let windowed_correction: SparseCorrection = serde_json::from_value(response["correction"].clone()).unwrap();
+            // the windowed correction must clear the syndrome
+            code_builder_sanity_check_correction(&mut simulator, &windowed_correction).unwrap();
+            let (correction, _) = mwpm_decoder.decode(&sparse_measurement);
+            if simulator.validate_correction(&windowed_correction) == simulator.validate_correction(&correction) {
+                agreed += 1;
+            }
+        }
+        // at low physical error rate, the windowed decoder almost always agrees with the whole-volume decoder
+        assert!(agreed >= repeats * 9 / 10, "agreed {} out of {}", agreed, repeats);
+    }
+
 }